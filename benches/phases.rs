@@ -0,0 +1,129 @@
+//! Benchmarks of the phases of loading markup and building widgets, matching the phases of [`snowcap::PhaseTimings`]
+//!
+//! Run with `cargo bench --bench phases`
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use snowcap::{module::data::EmbeddedData, testing::DecodeQueue, Message, Snowcap, SnowcapParser};
+
+/// Message broadcast to run an update without routing to any endpoint
+#[derive(Debug, Clone)]
+struct Tick;
+
+/// Generate markup of a column with `count` text items, with `changed` item text changed
+fn markup(count: usize, changed: Option<usize>) -> String {
+    let items: Vec<String> = (0..count)
+        .map(|i| {
+            if Some(i) == changed {
+                format!(r#"text("Changed {i}")"#)
+            } else {
+                format!(r#"text("Item {i}")"#)
+            }
+        })
+        .collect();
+
+    format!("{{|[{}]}}", items.join(", "))
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    for count in [10, 100, 1000] {
+        let data = markup(count, None);
+        group.bench_function(format!("{count} items"), |b| {
+            b.iter(|| SnowcapParser::<Message>::parse_memory(black_box(&data)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+/// Reload markup with one changed item into a loaded tree, which diffs and patches the tree
+fn reload(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reload");
+
+    for count in [10, 100, 1000] {
+        let original = markup(count, None);
+        let changed = markup(count, Some(count / 2));
+
+        group.bench_function(format!("{count} items"), |b| {
+            b.iter_batched(
+                || {
+                    let mut snow = Snowcap::new().unwrap();
+                    snow.load_memory(&original).unwrap();
+                    snow
+                },
+                |mut snow| {
+                    snow.load_memory(black_box(&changed)).unwrap();
+                    snow
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+/// Build the widgets of a newly loaded tree, and the root element
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+
+    for count in [10, 100, 1000] {
+        let data = markup(count, None);
+
+        group.bench_function(format!("{count} items"), |b| {
+            b.iter_batched(
+                || {
+                    let mut snow = Snowcap::new().unwrap();
+                    snow.load_memory(&data).unwrap();
+                    snow
+                },
+                |mut snow| {
+                    let _ = snow.update(Message::broadcast(Tick));
+                    drop(black_box(snow.view()));
+                    snow
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+/// Encode a GIF with a number of frames of a size
+fn gif(width: u32, height: u32, frames: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = image::codecs::gif::GifEncoder::new(&mut bytes);
+        encoder
+            .encode_frames(
+                (0..frames).map(|_| image::Frame::new(image::RgbaImage::new(width, height))),
+            )
+            .unwrap();
+    }
+    bytes
+}
+
+/// Decode the frames of animated images serially, as widgets are built, and on worker threads, as updates with
+/// at least `PARALLEL_DECODE_THRESHOLD` module data items do. Widgets are always built serially.
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    let data = gif(64, 64, 4);
+
+    for count in [2, 4, 8, 32] {
+        let queue = DecodeQueue::new(count, || Box::new(EmbeddedData::new(data.clone()))).unwrap();
+
+        group.bench_function(format!("serial {count} images"), |b| {
+            b.iter(|| black_box(queue.decode_serially()))
+        });
+        group.bench_function(format!("parallel {count} images"), |b| {
+            b.iter(|| black_box(queue.decode_concurrently()))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(phases, parse, reload, build, decode);
+criterion_main!(phases);
@@ -0,0 +1,222 @@
+//! Theme selection following the light/dark appearance of the operating system
+//!
+//! With `theme:"system"` on the markup root, the engine switches between the light and dark themes configured
+//! with [`crate::Snowcap::set_system_themes()`] as the appearance of the operating system changes. While the system
+//! theme is active, the appearance is polled on a blocking thread by the subscription of
+//! [`crate::Snowcap::subscription()`], which sends a [`Command::SetAppearance`] message when it changes.
+
+use std::time::Duration;
+
+use iced::{
+    futures::{SinkExt as _, Stream},
+    Subscription, Theme,
+};
+use salish::Message;
+use tracing::debug;
+
+use crate::{
+    conversion::theme::{root_system_theme, root_theme},
+    message::Command,
+    IndexedTree,
+};
+
+/// Interval between checks of the system appearance
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Light or dark appearance of the operating system
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Appearance {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Appearance {
+    /// Detect the current appearance of the operating system. Systems without a dark mode are light.
+    pub fn detect() -> Self {
+        match dark_light::detect() {
+            dark_light::Mode::Dark => Appearance::Dark,
+            dark_light::Mode::Light | dark_light::Mode::Default => Appearance::Light,
+        }
+    }
+}
+
+/// Create a [`Subscription`] polling the system appearance while the theme follows it, sending
+/// [`Command::SetAppearance`] each time it changes
+pub(crate) fn subscription(state: &ThemeState) -> Subscription<Message> {
+    if !state.follows_system() {
+        return Subscription::none();
+    }
+
+    Subscription::run(watch)
+}
+
+/// Poll the system appearance, which can block on platform calls, so it's detected on a blocking thread
+fn watch() -> impl Stream<Item = Message> {
+    iced::stream::channel(1, |mut output| async move {
+        let mut last = None;
+        loop {
+            let Ok(appearance) = tokio::task::spawn_blocking(Appearance::detect).await else {
+                break;
+            };
+            if last != Some(appearance) {
+                debug!("System appearance {appearance:?}");
+                last = Some(appearance);
+                if output
+                    .send(Message::broadcast(Command::SetAppearance(appearance)))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    })
+}
+
+/// Active theme of the engine, shared with the command endpoint
+#[derive(Debug)]
+pub(crate) struct ThemeState {
+    /// Theme applied to every widget
+    active: Option<Theme>,
+
+    /// Follow the system appearance, set by `theme:"system"`
+    system: bool,
+
+    /// The active theme was declared on the root of the markup, rather than set by the application
+    from_markup: bool,
+
+    /// Last detected system appearance
+    appearance: Appearance,
+
+    /// Themes used for the light and dark appearances
+    light: Theme,
+    dark: Theme,
+}
+
+impl Default for ThemeState {
+    fn default() -> Self {
+        Self {
+            active: None,
+            system: false,
+            from_markup: false,
+            appearance: Appearance::default(),
+            light: Theme::Light,
+            dark: Theme::Dark,
+        }
+    }
+}
+
+impl ThemeState {
+    /// Get the active theme
+    pub fn active(&self) -> Option<Theme> {
+        self.active.clone()
+    }
+
+    /// Returns true if the theme follows the system appearance
+    pub fn follows_system(&self) -> bool {
+        self.system
+    }
+
+    /// Set a fixed theme, no longer following the system appearance
+    pub fn set(&mut self, theme: Theme) {
+        self.system = false;
+        self.from_markup = false;
+        self.active = Some(theme);
+    }
+
+    /// Set the themes used for the light and dark system appearances
+    pub fn set_system_themes(&mut self, light: Theme, dark: Theme) {
+        self.light = light;
+        self.dark = dark;
+        self.update_system();
+    }
+
+    /// Record a change of the system appearance
+    pub fn set_appearance(&mut self, appearance: Appearance) {
+        self.appearance = appearance;
+        self.update_system();
+    }
+
+    /// Apply the theme declared on the root of the markup. The default theme is restored if the markup no longer
+    /// declares the active theme.
+    pub fn apply_markup(&mut self, tree: &IndexedTree) {
+        if root_system_theme(tree) {
+            self.system = true;
+            self.from_markup = true;
+            self.update_system();
+        } else if let Some(theme) = root_theme(tree) {
+            self.set(theme);
+            self.from_markup = true;
+        } else if self.from_markup {
+            self.system = false;
+            self.from_markup = false;
+            self.active = None;
+        }
+    }
+
+    fn update_system(&mut self) {
+        if self.system {
+            self.active = Some(match self.appearance {
+                Appearance::Light => self.light.clone(),
+                Appearance::Dark => self.dark.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Theme;
+    use tracing_test::traced_test;
+
+    use super::{Appearance, ThemeState};
+    use crate::{Message, SnowcapParser};
+
+    #[traced_test]
+    #[test]
+    fn follow_system() {
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{<theme:"system"> text("system")}"#)
+            .unwrap()
+            .index();
+
+        let mut state = ThemeState::default();
+        state.set_system_themes(Theme::Nord, Theme::Dracula);
+        state.apply_markup(&tree);
+        assert!(state.follows_system());
+        assert_eq!(state.active(), Some(Theme::Nord));
+
+        state.set_appearance(Appearance::Dark);
+        assert_eq!(state.active(), Some(Theme::Dracula));
+
+        // A fixed theme stops following the system appearance
+        state.set(Theme::Ferra);
+        assert!(!state.follows_system());
+        state.set_appearance(Appearance::Light);
+        assert_eq!(state.active(), Some(Theme::Ferra));
+    }
+
+    #[traced_test]
+    #[test]
+    fn remove_markup_theme() {
+        let parse = |markup| {
+            SnowcapParser::<Message>::parse_memory(markup)
+                .unwrap()
+                .index()
+        };
+
+        let mut state = ThemeState::default();
+        state.apply_markup(&parse(r#"{<theme:"dark"> text("dark")}"#));
+        assert_eq!(state.active(), Some(Theme::Dark));
+
+        // Removing the theme attribute restores the default theme
+        state.apply_markup(&parse(r#"{text("default")}"#));
+        assert_eq!(state.active(), None);
+
+        // Themes set by the application are kept
+        state.set(Theme::Nord);
+        state.apply_markup(&parse(r#"{text("default")}"#));
+        assert_eq!(state.active(), Some(Theme::Nord));
+    }
+}
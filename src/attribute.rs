@@ -5,16 +5,29 @@ use std::{
     collections::HashMap,
     hash::{Hash, Hasher},
     sync::Arc,
-    time::Duration,
 };
 
-use parking_lot::{ArcRwLockReadGuard, RawRwLock, RwLock};
+use arc_swap::ArcSwap;
 use strum::{EnumDiscriminants, EnumIter};
 use xxhash_rust::xxh64::Xxh64;
 
 use crate::{parser::module::Module, SyncError};
 
+pub mod breakpoint;
+pub mod custom;
+pub mod expression;
+pub mod handler;
 mod hash;
+pub mod palette;
+pub mod relative;
+pub mod transition;
+
+use breakpoint::Breakpoint;
+use custom::CustomAttributes;
+use expression::ValueExpression;
+use handler::Handler;
+use palette::PaletteColor;
+use transition::Transition;
 
 /// All possible [`Attribute`] inner values
 #[derive(Default, Debug, Clone, EnumDiscriminants, PartialEq)]
@@ -25,6 +38,8 @@ pub enum AttributeValue {
     None,
     /// Text Color sRGB color space
     TextColor(iced::Color),
+    /// Text color from the palette of the active theme
+    TextPalette(PaletteColor),
     /// Border which can be applied to styles
     Border(iced::Border),
     /// Shadow which can be applied to styles
@@ -39,16 +54,26 @@ pub enum AttributeValue {
     WidthLength(iced::Length),
     /// Width in units of [`iced::Pixels`]
     WidthPixels(iced::Pixels),
+    /// Width relative to the parent or the window, such as `50%` or `10vw`
+    WidthRelative(relative::RelativeLength),
     /// Maximum width in [`iced::Pixels`]
     MaxWidth(iced::Pixels),
     /// Maximum height in [`iced::Pixels`]
     MaxHeight(iced::Pixels),
+    /// Minimum width in [`iced::Pixels`]
+    MinWidth(iced::Pixels),
+    /// Minimum height in [`iced::Pixels`]
+    MinHeight(iced::Pixels),
     /// Height in units of [`iced::Length`]
     HeightLength(iced::Length),
     /// Height in units of [`iced::Pixels`]
     HeightPixels(iced::Pixels),
+    /// Height relative to the parent or the window, such as `50%` or `20vh`
+    HeightRelative(relative::RelativeLength),
     /// Background of an element. Color or Gradient.
     Background(iced::Background),
+    /// Background color from the palette of the active theme
+    BackgroundPalette(PaletteColor),
     /// Spacing between elements
     Spacing(iced::Pixels),
     /// Size in [`iced::Pixels`]
@@ -61,18 +86,126 @@ pub enum AttributeValue {
     Toggled(bool),
     /// Selected value for pick list widget
     Selected(String),
+    /// Keyboard focus of a selection widget, set by keyboard navigation
+    Focused(bool),
+    /// Selected values for multi-select widget
+    SelectedList(Vec<String>),
     /// A label
     Label(String),
     /// Built in [`iced::Theme`]
     Theme(iced::Theme),
+    /// Follow the light/dark appearance of the operating system
+    SystemTheme,
+    /// Stylesheet declared in the markup, stored in the attributes of the root node
+    Stylesheet(Arc<crate::parser::style::Stylesheet>),
+    /// Path of an external stylesheet referenced with `styles!()`, stored in the attributes of the root node
+    StylesheetFile(std::path::PathBuf),
+    /// Classes of an element, selecting the rules of the stylesheet applied to it
+    Class(Vec<String>),
     /// Text wrapping
     Wrapping(iced::widget::text::Wrapping),
     /// Text shaping
     Shaping(iced::widget::text::Shaping),
+    /// Font of text
+    Font(iced::Font),
     /// Slider Value
     SliderValue(i32),
     /// Scroll Direction
     ScrollDirection(iced::widget::scrollable::Direction),
+    /// Adjust the value of a slider with the mouse wheel
+    Wheel(bool),
+    /// Show increment and decrement buttons beside a slider
+    Spin(bool),
+    /// Amount a slider or number input value is adjusted by each wheel or button step
+    Step(f64),
+    /// Play the frames of an animated image
+    Animated(bool),
+    /// Values for each [`Breakpoint`], resolved against the nearest `responsive` widget
+    Responsive(Vec<(Breakpoint, AttributeValue)>),
+    /// Value computed from an expression when the widget is built
+    Expression(ValueExpression),
+    /// Breakpoint of the width available to a `responsive` widget
+    Breakpoint(Breakpoint),
+    /// Opacity of the style colors of a container, from 0 (transparent) to 1 (opaque)
+    Opacity(f32),
+    /// Attributes tweened when their values change
+    Transition(Vec<Transition>),
+    /// Handler of button presses
+    OnPress(Handler),
+    /// Handler of toggles
+    OnToggle(Handler),
+    /// Handler of selections
+    OnSelect(Handler),
+    /// Handler of value changes
+    OnChange(Handler),
+    /// Value of a widget bound to application data with `bind(path)`
+    Bind(String),
+    /// Handler of markdown link clicks
+    OnLink(Handler),
+    /// Size of h1 markdown headings, which smaller headings are scaled from
+    HeadingSize(iced::Pixels),
+    /// Size of markdown code
+    CodeSize(iced::Pixels),
+    /// Color of inline markdown code
+    CodeColor(iced::Color),
+    /// Background color of inline markdown code
+    CodeBackground(iced::Color),
+    /// Color of markdown links
+    LinkColor(iced::Color),
+    /// Language of a code widget, as a name or file extension
+    Language(String),
+    /// Wrap long lines of a code widget
+    Wrap(bool),
+    /// Show line numbers in a code widget
+    LineNumbers(bool),
+    /// Height of each item of a `lazy-col`
+    ItemHeight(iced::Pixels),
+    /// Range of the items of a `lazy-col` which are visible in its viewport
+    VisibleRange(usize, usize),
+    /// Text being edited in a text, number or password input
+    InputValue(String),
+    /// Text shown in an empty input
+    Placeholder(String),
+    /// Minimum value of a number input
+    Min(f64),
+    /// Maximum value of a number input
+    Max(f64),
+    /// Mask the text of an input
+    Secure(bool),
+    /// Handler of input submissions
+    OnSubmit(Handler),
+    /// Start drags from an element
+    Draggable(bool),
+    /// Accept drops onto an element
+    DropTarget(bool),
+    /// Files from the operating system are being dragged over the window of a `dropzone`
+    FileHovered(bool),
+    /// Handler of drops
+    OnDrop(Handler),
+    /// Color of the track of a toggler which is toggled on
+    ActiveColor(iced::Color),
+    /// Color of the handle of a toggler
+    HandleColor(iced::Color),
+    /// Size of the label text of a toggler
+    TextSize(iced::Pixels),
+    /// Width of the line of a rule
+    Thickness(iced::Pixels),
+    /// Color of the line of a rule
+    RuleColor(iced::Color),
+    /// Color of the line of a rule from the palette of the theme
+    RulePalette(PaletteColor),
+    /// Fill mode of the line of a rule
+    FillMode(iced::widget::rule::FillMode),
+    /// Background image of a container, decoded from the data of its `bg:image()` module
+    BackgroundImage(iced::widget::image::Handle),
+    /// How the background image of a container fits its bounds
+    ContentFit(iced::ContentFit),
+    /// Opacity of a black overlay dimming the background image of a container, from 0 to 1
+    Dim(f32),
+    /// Layer of a child of a stack. Children in higher layers are drawn above lower layers.
+    ZIndex(i32),
+    /// Attributes which aren't built in, such as the attributes of custom widgets, by name
+    Custom(CustomAttributes),
 }
 
 impl AttributeValue {
@@ -88,17 +221,22 @@ impl std::fmt::Display for AttributeValue {
     }
 }
 
-/// A set of [`Attribute`] items. This is represented as a [`HashMap`] wrapped in an [`Arc`] and [`parking_lot::RwLock`]
-/// allowing the attributes to be cloned, and sent between threads
+/// Map of attribute kinds to attributes, shared by each clone of [`Attributes`]
+type AttributeMap = HashMap<AttributeKind, Attribute>;
+
+/// A set of [`Attribute`] items. This is represented as an immutable [`HashMap`] in an [`ArcSwap`], shared by
+/// clones of the set, and sent between threads.
+///
+/// Reads load a snapshot of the map and never block. Writes copy the map, and atomically swap in the modified copy,
+/// retrying if another write was swapped in concurrently. Attribute sets are small, and rarely written after parsing.
 #[derive(Default, Clone)]
-pub struct Attributes(Arc<RwLock<HashMap<AttributeKind, Attribute>>>);
+pub struct Attributes(Arc<ArcSwap<AttributeMap>>);
 
 impl std::hash::Hash for Attributes {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.each_with(state, |state, attr| {
             attr.hash(state);
-        })
-        .unwrap();
+        });
     }
 }
 
@@ -108,82 +246,142 @@ impl Attributes {
         Self::default()
     }
 
+    /// Create a set of attributes from a map, not shared with any other set
+    fn from_map(attrs: AttributeMap) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(attrs)))
+    }
+
     /// Get the number of attributes in the set
     pub fn len(&self) -> usize {
-        self.0.read().len()
+        self.0.load().len()
+    }
+
+    /// Modify a copy of the map, and swap it in
+    fn update(&self, f: impl Fn(&mut AttributeMap)) {
+        self.0.rcu(|current| {
+            let mut attrs = AttributeMap::clone(current);
+            f(&mut attrs);
+            attrs
+        });
     }
 
-    /// Try to acquire a write lock, and push the [`Attribute`] to the set of [`Attributes`]
+    /// Push the [`Attribute`] to the set of [`Attributes`], replacing an attribute of the same kind
     pub fn push(&mut self, attr: Attribute) -> Result<&Self, SyncError> {
-        if let Some(mut guard) = self.0.try_write() {
-            guard.insert(attr.kind(), attr);
-            Ok(self)
-        } else {
-            Err(SyncError::Deadlock(format!(
-                "RwLock try_write() failed in Attributes::push() {:#?}",
-                attr
-            )))
-        }
+        self.update(|attrs| {
+            attrs.insert(attr.kind(), attr.clone());
+        });
+        Ok(self)
     }
 
     /// Get an [`AttributeValue`] from the set of [`Attributes'] for the specified [`AttributeKind`]
     pub fn get(&self, kind: AttributeKind) -> Result<Option<AttributeValue>, SyncError> {
-        match self.0.try_read_for(Duration::from_secs(1)) {
-            Some(guard) => Ok(guard.get(&kind).and_then(|attr| attr.value().cloned())),
-            None => Err(SyncError::Deadlock(format!(
-                "RwLock read lock failed in Attributes::get() {:?}",
-                kind
-            ))),
-        }
+        Ok(self
+            .0
+            .load()
+            .get(&kind)
+            .and_then(|attr| attr.value().cloned()))
     }
 
+    /// Set an [`AttributeValue`], replacing the value of the same kind. Every clone of the set sees the new value.
     pub fn set(&self, value: AttributeValue) -> Result<(), SyncError> {
-        match self.0.try_write_for(Duration::from_secs(1)) {
-            Some(mut guard) => {
-                guard.insert(value.kind(), Attribute::from(value));
-                Ok(())
-            }
-            None => Err(SyncError::Deadlock(format!(
-                "RwLock write lock failed in Attributes::set() {:?}",
-                value
-            ))),
+        self.update(|attrs| {
+            attrs.insert(value.kind(), Attribute::from(value.clone()));
+        });
+        Ok(())
+    }
+
+    /// Get the value of a custom attribute, converted by the parser registered for its name with
+    /// [`crate::Snowcap::attributes()`]. Attributes without a registered parser have a [`crate::Value`].
+    pub fn custom<T: std::any::Any + Clone>(&self, name: &str) -> Option<T> {
+        match self.get(AttributeKind::Custom).ok()?? {
+            AttributeValue::Custom(attrs) => attrs
+                .get(&name.to_ascii_lowercase())
+                .and_then(|value| value.get::<T>().cloned()),
+            _ => None,
+        }
+    }
+
+    /// Get the text of the value of a custom attribute in the markup
+    pub fn custom_source(&self, name: &str) -> Option<String> {
+        match self.get(AttributeKind::Custom).ok()?? {
+            AttributeValue::Custom(attrs) => attrs
+                .get(&name.to_ascii_lowercase())
+                .map(|value| value.source().to_string()),
+            _ => None,
         }
     }
 
-    pub fn each_with<T, F>(&self, mut with: T, f: F) -> Result<T, SyncError>
+    /// Call `f` with each attribute in a deterministic order, passing `with` through each call
+    pub fn each_with<T, F>(&self, mut with: T, f: F) -> T
     where
         F: Fn(&mut T, &Attribute),
     {
-        match self.0.try_read_for(Duration::from_secs(1)) {
-            Some(guard) => {
-                // Collect and sort keys from the HashMap to yield attributes
-                // in a deterministic order
-                let mut keys: Vec<&AttributeKind> = guard.keys().collect();
-                keys.sort();
-
-                for key in keys {
-                    if let Some(attr) = guard.get(key) {
-                        f(&mut with, attr);
+        let attrs = self.0.load();
+
+        // Collect and sort keys from the HashMap to yield attributes
+        // in a deterministic order
+        let mut keys: Vec<&AttributeKind> = attrs.keys().collect();
+        keys.sort();
+
+        for key in keys {
+            if let Some(attr) = attrs.get(key) {
+                f(&mut with, attr);
+            }
+        }
+
+        with
+    }
+
+    /// Returns true if any attribute has values for each [`Breakpoint`]
+    pub fn is_responsive(&self) -> bool {
+        self.into_iter()
+            .any(|attr| matches!(attr.value(), Some(AttributeValue::Responsive(_))))
+    }
+
+    /// Returns true if any attribute has a value computed from an expression
+    pub fn is_computed(&self) -> bool {
+        self.into_iter()
+            .any(|attr| matches!(attr.value(), Some(AttributeValue::Expression(_))))
+    }
+
+    /// Resolve attributes with values for each [`Breakpoint`] to the value of the supplied breakpoint.
+    /// Returns the same set if no attributes are responsive.
+    pub fn resolve(&self, breakpoint: Breakpoint) -> Attributes {
+        if !self.is_responsive() {
+            return self.clone();
+        }
+
+        let mut attrs = HashMap::new();
+        let mut resolved = Vec::new();
+
+        for attr in self {
+            match attr.value() {
+                Some(AttributeValue::Responsive(values)) => {
+                    if let Some(value) = breakpoint.select(values) {
+                        resolved.push(Attribute::from(value.clone()));
                     }
                 }
-
-                Ok(with)
+                _ => {
+                    attrs.insert(attr.kind(), attr);
+                }
             }
-            None => Err(SyncError::Deadlock(format!(
-                "RwLock read lock failed in Attributes::each()",
-            ))),
         }
+
+        // Responsive values take precedence over fixed values of the same kind
+        for attr in resolved {
+            attrs.insert(attr.kind(), attr);
+        }
+
+        Attributes::from_map(attrs)
     }
 
     /// Get the Xxh64 hash of the set of attributes
     pub fn xxhash(&self) -> u64 {
         let mut hasher = Xxh64::new(0);
 
-        hasher = self
-            .each_with(hasher, |hasher, attr| {
-                attr.hash(hasher);
-            })
-            .unwrap();
+        hasher = self.each_with(hasher, |hasher, attr| {
+            attr.hash(hasher);
+        });
 
         hasher.finish()
     }
@@ -216,9 +414,8 @@ impl std::fmt::Display for Attributes {
 }
 
 /// Convert a reference to [`Attributes`] into an [`AttributeIter`]
-/// This attempts to obtain an Arc read lock guard, which has a `static lifetime
-/// so the guard can be included in the iterator state. This holds the read lock
-/// while the iterator is in scope.
+/// The iterator holds a snapshot of the set, so attributes set while iterating
+/// are not yielded, and iterating never blocks writers.
 ///
 /// Each attribute discriminant present in the set of attributes is collected
 /// into a vector, and the iterator then steps through the discriminants to
@@ -231,26 +428,19 @@ impl<'a> IntoIterator for &'a Attributes {
     type IntoIter = AttributeIter;
 
     fn into_iter(self) -> Self::IntoIter {
-        // Acquire an ArcRwLockReadGuard. This has a static lifetime that we can use with Iterator
-        match self.0.try_read_arc_for(Duration::from_secs(1)) {
-            Some(guard) => {
-                // Collect a vec of AttributeKinds for each key in the HashMap. The iterator will iterate through
-                // each kind from the set to yield a reference to the [`Attribute`] while the RwLock Arc read guard is held
-                let mut attr_kinds: Vec<AttributeKind> =
-                    guard.iter().map(|(kind, _attr)| *kind).collect();
-
-                // Ensure the vec of attributes is sorted, so the attributes are yielded in a deterministic order.
-                // This is required for producing deterministic hashes of attribute sets.
-                attr_kinds.sort();
-
-                AttributeIter {
-                    guard,
-                    iter: attr_kinds.into_iter(),
-                }
-            }
-            None => {
-                panic!("Deadlock in Attributes iterator");
-            }
+        let attrs = self.0.load_full();
+
+        // Collect a vec of AttributeKinds for each key in the HashMap. The iterator will iterate through
+        // each kind from the set to yield a clone of each [`Attribute`] in the snapshot
+        let mut attr_kinds: Vec<AttributeKind> = attrs.keys().copied().collect();
+
+        // Ensure the vec of attributes is sorted, so the attributes are yielded in a deterministic order.
+        // This is required for producing deterministic hashes of attribute sets.
+        attr_kinds.sort();
+
+        AttributeIter {
+            attrs,
+            iter: attr_kinds.into_iter(),
         }
     }
 }
@@ -267,7 +457,7 @@ impl IntoIterator for Attributes {
 
 /// An [`Iterator`] over [`Attribute`] items
 pub struct AttributeIter {
-    guard: ArcRwLockReadGuard<RawRwLock, HashMap<AttributeKind, Attribute>>,
+    attrs: Arc<AttributeMap>,
     iter: std::vec::IntoIter<AttributeKind>,
 }
 
@@ -276,7 +466,7 @@ impl<'a> Iterator for AttributeIter {
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(kind) = &self.iter.next() {
-            self.guard.get(kind).map(|item| item.clone())
+            self.attrs.get(kind).map(|item| item.clone())
         } else {
             None
         }
@@ -411,6 +601,39 @@ mod attribute_tests {
         }
     }
 
+    #[traced_test]
+    #[test]
+    fn test_attributes_concurrent() {
+        let attrs = Attributes::new();
+        attrs.set(AttributeValue::Clip(true)).unwrap();
+
+        // Iterating holds a snapshot, so setting attributes while iterating doesn't block or panic
+        let mut iter = (&attrs).into_iter();
+        attrs.set(AttributeValue::Spacing(4.0.into())).unwrap();
+        assert_eq!(
+            iter.next().map(|attr| attr.kind()),
+            Some(AttributeKind::Clip)
+        );
+        assert!(iter.next().is_none());
+        assert_eq!(attrs.len(), 2);
+
+        // Concurrent writers to clones of the set don't lose updates
+        std::thread::scope(|scope| {
+            for width in 0..8 {
+                let attrs = attrs.clone();
+                scope.spawn(move || {
+                    for _ in 0..100 {
+                        attrs
+                            .set(AttributeValue::WidthPixels((width as f32).into()))
+                            .unwrap();
+                        assert!(attrs.get(AttributeKind::Clip).unwrap().is_some());
+                    }
+                });
+            }
+        });
+        assert_eq!(attrs.len(), 3);
+    }
+
     #[traced_test]
     #[test]
     fn test_attribute_hash() {
@@ -0,0 +1,101 @@
+//! Layout breakpoints for responsive attributes
+//!
+//! An attribute value can vary with the width of the nearest enclosing `responsive` widget, by mapping
+//! breakpoints to values such as `width:{sm:fill, lg:400}`. Breakpoints apply from their minimum width upwards,
+//! so the value of the largest breakpoint not wider than the available width is used. Below the smallest
+//! breakpoint of the map the attribute is unset.
+//!
+//! | Breakpoint | Minimum width |
+//! |------------|---------------|
+//! | `xs`       | 0             |
+//! | `sm`       | 640           |
+//! | `md`       | 768           |
+//! | `lg`       | 1024          |
+//! | `xl`       | 1280          |
+
+use crate::parser::error::ParseError;
+
+/// A range of layout widths
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Breakpoint {
+    #[default]
+    Xs,
+    Sm,
+    Md,
+    Lg,
+    Xl,
+}
+
+impl Breakpoint {
+    /// Get the breakpoint of a layout width
+    pub fn from_width(width: f32) -> Self {
+        [Self::Xl, Self::Lg, Self::Md, Self::Sm]
+            .into_iter()
+            .find(|breakpoint| width >= breakpoint.min_width())
+            .unwrap_or(Self::Xs)
+    }
+
+    /// Minimum layout width of the breakpoint
+    pub fn min_width(&self) -> f32 {
+        match self {
+            Self::Xs => 0.0,
+            Self::Sm => 640.0,
+            Self::Md => 768.0,
+            Self::Lg => 1024.0,
+            Self::Xl => 1280.0,
+        }
+    }
+
+    /// Parse a breakpoint name
+    pub fn parse(name: &str) -> Result<Self, ParseError> {
+        match name.trim().to_lowercase().as_str() {
+            "xs" => Ok(Self::Xs),
+            "sm" => Ok(Self::Sm),
+            "md" => Ok(Self::Md),
+            "lg" => Ok(Self::Lg),
+            "xl" => Ok(Self::Xl),
+            _ => Err(ParseError::Unhandled(format!("breakpoint '{name}'"))),
+        }
+    }
+
+    /// Select the value for this breakpoint from a breakpoint map
+    pub fn select<'a, T>(&self, values: &'a [(Breakpoint, T)]) -> Option<&'a T> {
+        values
+            .iter()
+            .filter(|(breakpoint, _)| breakpoint <= self)
+            .max_by_key(|(breakpoint, _)| *breakpoint)
+            .map(|(_, value)| value)
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Xs => "xs",
+            Self::Sm => "sm",
+            Self::Md => "md",
+            Self::Lg => "lg",
+            Self::Xl => "xl",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::Breakpoint;
+
+    #[traced_test]
+    #[test]
+    fn select_breakpoint() {
+        assert_eq!(Breakpoint::from_width(320.0), Breakpoint::Xs);
+        assert_eq!(Breakpoint::from_width(800.0), Breakpoint::Md);
+        assert_eq!(Breakpoint::from_width(2000.0), Breakpoint::Xl);
+
+        let values = [(Breakpoint::Sm, "sm"), (Breakpoint::Lg, "lg")];
+        assert_eq!(Breakpoint::Xs.select(&values), None);
+        assert_eq!(Breakpoint::Md.select(&values), Some(&"sm"));
+        assert_eq!(Breakpoint::Xl.select(&values), Some(&"lg"));
+    }
+}
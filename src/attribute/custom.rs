@@ -0,0 +1,221 @@
+//! Attributes of custom widgets
+//!
+//! Widgets registered with [`crate::Snowcap::widgets()`] can take attributes which aren't built in, such as
+//! `gauge<needle-color:#f00, sweep:270>`. Hosts register a parser for each attribute name with
+//! [`crate::Snowcap::attributes()`], converting the text of the value to a typed value, which the factory of the
+//! widget gets with [`Attributes::custom()`](super::Attributes::custom).
+//!
+//! ```ignore
+//! snow.attributes().register("needle-color", |source| {
+//!     iced::Color::parse(source).ok_or_else(|| ConversionError::InvalidType(format!("color {source}")))
+//! });
+//!
+//! snow.widgets().register("gauge", |_node_id, attrs, _content| {
+//!     let color: Option<iced::Color> = attrs.custom("needle-color");
+//!     let sweep = attrs.custom::<Value>("sweep").and_then(|sweep| sweep.float().ok());
+//!     ...
+//! });
+//! ```
+//!
+//! Attributes without a registered parser are kept as generic key/value attributes, with the value parsed as a
+//! [`Value`], or kept as a string if it isn't a string, number, boolean or array. The name of a built in attribute
+//! with a value it doesn't accept is an error rather than a custom attribute. Values of custom attributes are
+//! literal, and can't be provided by modules.
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    sync::{Arc, LazyLock},
+};
+
+use parking_lot::RwLock;
+use tracing::debug;
+
+use crate::{
+    parser::{error::ParseError, value::ValueParser, ParserContext},
+    ConversionError, Value,
+};
+
+/// Parser of the value of a custom attribute, type erased
+type DynAttributeParser =
+    Arc<dyn Fn(&str) -> Result<Arc<dyn Any + Send + Sync>, ConversionError> + Send + Sync>;
+
+/// Global registry of parsers of custom attributes, by attribute name
+static ATTRIBUTE_REGISTRY: LazyLock<RwLock<HashMap<String, DynAttributeParser>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Names of the built in attributes, taken from the keywords of the `attr_*` rules of the attribute grammar
+static BUILTIN_NAMES: LazyLock<HashSet<String>> = LazyLock::new(|| {
+    include_str!("../parser/attribute.pest")
+        .lines()
+        .filter(|line| line.starts_with("attr_"))
+        .filter_map(|line| line.split_once('=')?.1.split("delimiter").next())
+        .flat_map(|rule| rule.split("^\"").skip(1))
+        .filter_map(|keyword| keyword.split('"').next())
+        .map(str::to_ascii_lowercase)
+        .collect()
+});
+
+/// Returns true if the name is the name of a built in attribute
+pub(crate) fn is_builtin(name: &str) -> bool {
+    BUILTIN_NAMES.contains(&name.to_ascii_lowercase())
+}
+
+/// Value of a custom attribute, with the text it was parsed from
+#[derive(Clone)]
+pub struct CustomValue {
+    source: String,
+    value: Arc<dyn Any + Send + Sync>,
+}
+
+impl CustomValue {
+    /// Get the text of the value in the markup
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the value, if it has the type returned by the parser registered for the attribute
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref::<T>()
+    }
+}
+
+impl std::fmt::Debug for CustomValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.source)
+    }
+}
+
+// Values are parsed from their source, so values with the same source are equal
+impl PartialEq for CustomValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Hash for CustomValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.source.hash(state);
+    }
+}
+
+/// Custom attributes of an element, by name
+pub type CustomAttributes = BTreeMap<String, CustomValue>;
+
+/// Handle to the registry of parsers of custom attributes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AttributeRegistry;
+
+impl AttributeRegistry {
+    /// Register the parser of the values of a custom attribute, replacing any parser already registered for it.
+    /// The names of built in attributes can't be registered.
+    pub fn register<T, F>(&self, name: &str, parser: F) -> Result<(), ConversionError>
+    where
+        T: Any + Send + Sync,
+        F: Fn(&str) -> Result<T, ConversionError> + Send + Sync + 'static,
+    {
+        if is_builtin(name) {
+            return Err(ConversionError::InvalidType(format!(
+                "{name} is a built in attribute"
+            )));
+        }
+
+        debug!("Registering custom attribute '{name}'");
+        let parser: DynAttributeParser = Arc::new(move |source| {
+            parser(source).map(|value| Arc::new(value) as Arc<dyn Any + Send + Sync>)
+        });
+        ATTRIBUTE_REGISTRY
+            .write()
+            .insert(name.to_ascii_lowercase(), parser);
+
+        Ok(())
+    }
+
+    /// Remove the parser of a custom attribute. Returns true if a parser was registered.
+    pub fn unregister(&self, name: &str) -> bool {
+        ATTRIBUTE_REGISTRY
+            .write()
+            .remove(&name.to_ascii_lowercase())
+            .is_some()
+    }
+
+    /// Returns true if a parser is registered for a custom attribute
+    pub fn contains(&self, name: &str) -> bool {
+        ATTRIBUTE_REGISTRY
+            .read()
+            .contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Parse the value of a custom attribute, with the parser registered for its name, or as a generic [`Value`]
+    pub(crate) fn parse(name: &str, source: &str) -> Result<CustomValue, ParseError> {
+        let parser = ATTRIBUTE_REGISTRY
+            .read()
+            .get(&name.to_ascii_lowercase())
+            .cloned();
+
+        let value: Arc<dyn Any + Send + Sync> = match parser {
+            Some(parser) => parser(source)
+                .map_err(|e| ParseError::CustomAttribute(name.to_string(), e.to_string()))?,
+            None => {
+                debug!("No parser registered for attribute {name}, keeping it as a generic value");
+                let value = ValueParser::parse_str(source, &ParserContext::default())
+                    .unwrap_or_else(|_| Value::new_string(source.to_string()));
+                Arc::new(value)
+            }
+        };
+
+        Ok(CustomValue {
+            source: source.to_string(),
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{is_builtin, AttributeRegistry};
+    use crate::{parser::attribute::AttributeParser, ConversionError, Value};
+
+    #[traced_test]
+    #[test]
+    fn custom_attributes() {
+        assert!(is_builtin("width"));
+        assert!(is_builtin("bg"));
+        assert!(is_builtin("Text-Colour"));
+        assert!(!is_builtin("sweep"));
+
+        AttributeRegistry
+            .register("test-needle", |source| match source {
+                "#f00" => Ok(iced::Color::from_rgb(1.0, 0.0, 0.0)),
+                _ => Err(ConversionError::InvalidType(source.into())),
+            })
+            .unwrap();
+        assert!(AttributeRegistry
+            .register("width", |source| Ok(source.to_string()))
+            .is_err());
+
+        let attrs = AttributeParser::parse_attributes(
+            r#"test-needle:#f00, sweep:270, width:fill, note:"a, b""#,
+        )
+        .unwrap();
+        assert_eq!(
+            attrs.custom::<iced::Color>("test-needle"),
+            Some(iced::Color::from_rgb(1.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            attrs.custom::<Value>("sweep").unwrap().integer().unwrap(),
+            270
+        );
+        assert_eq!(attrs.custom::<Value>("note").unwrap().to_string(), "a, b");
+        assert!(attrs.custom::<Value>("missing").is_none());
+
+        // Built in attributes with values they don't accept, and values rejected by a parser, are errors
+        assert!(AttributeParser::parse_attributes("width:sideways").is_err());
+        assert!(AttributeParser::parse_attributes("test-needle:blue").is_err());
+
+        assert!(AttributeRegistry.unregister("test-needle"));
+    }
+}
@@ -0,0 +1,68 @@
+//! Attribute values computed from expressions
+//!
+//! Numeric and boolean attributes accept an expression in place of a value, which is evaluated each time the
+//! widget is built:
+//!
+//! ```text
+//! {<padding: 2 * pad, width: 200 + 2 * pad> text<size: parent.width / 30>("Title")}
+//! toggler<toggled: (window.width >= 800)>("Wide layout")
+//! ```
+//!
+//! An expression has at least one operator, or is enclosed in parentheses. Comparisons with `<` and `>` must be
+//! in parentheses, as `<` and `>` delimit the attributes. The expression syntax and its functions, such as
+//! `if(cond, a, b)`, `min()` and `round()`, are those of module output expressions.
+//!
+//! Variables available to expressions are:
+//!
+//! | Variable                        | Value                                                                   |
+//! |---------------------------------|-------------------------------------------------------------------------|
+//! | `window.width`, `window.height` | Last known size of the window                                           |
+//! | `parent.width`, `parent.height` | Fixed size of the nearest ancestor with one, or the size of the window  |
+//! | Any other name                  | Value of the key in the state store of the `state!` module              |
+//!
+//! Widgets with computed attributes are rebuilt when the window is resized.
+
+use std::sync::Arc;
+
+use crate::parser::expr::Expr;
+
+/// Expression computing the value of an attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueExpression {
+    /// Name of the attribute, used to parse the result as an attribute of the same name
+    name: String,
+    /// Source text of the expression
+    source: String,
+    expr: Arc<Expr>,
+}
+
+impl ValueExpression {
+    pub fn new(name: impl Into<String>, source: impl Into<String>, expr: Expr) -> Self {
+        Self {
+            name: name.into(),
+            source: source.into(),
+            expr: Arc::new(expr),
+        }
+    }
+
+    /// Get the name of the attribute computed by the expression
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the source text of the expression
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the parsed expression
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+}
+
+impl std::fmt::Display for ValueExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
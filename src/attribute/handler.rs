@@ -0,0 +1,171 @@
+//! Declarative event handlers publishing to topics
+//!
+//! Widgets can publish their events directly to a pub/sub topic, which modules and application subscriptions
+//! consume, without handling the [`WidgetMessage`](crate::message::widget::WidgetMessage) in Rust:
+//!
+//! ```text
+//! button<on-press:publish("nav", "settings")>(text("Settings"))
+//! toggler<on-toggle:publish("dark-mode")>("Dark mode")
+//! pick-list<on-select:publish("language")>(["en", "fr"])
+//! slider<on-change:publish("volume")>
+//! markdown<on-link:publish("links")>(file!{path:"README.md"})
+//! ```
+//!
+//! The second argument of `publish` is published as a string. Without it, the value of the event is published:
+//! nothing for a press, a boolean for a toggle, the selected string for a selection, the number for a change,
+//! and the URL for a link.
+//!
+//! Handlers can also update the state store read by the [`state`](crate::module::state) module:
+//!
+//! ```text
+//! button<on-press:state.increment("counter")>(text("+"))
+//! ```
+
+use iced::Task;
+use salish::Message;
+
+use crate::{
+    message::{
+        module::{Topic, TopicMessage},
+        widget::WidgetEvent,
+    },
+    module::{
+        pubsub::publish,
+        state::{StateAction, StateStore},
+    },
+    Value,
+};
+
+use super::{AttributeKind, AttributeValue, Attributes};
+
+/// Handler of a widget event
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Handler {
+    /// Publish the event to a topic, with an optional payload replacing the value of the event
+    Publish {
+        topic: String,
+        payload: Option<String>,
+    },
+    /// Update the state store
+    State(StateAction),
+}
+
+impl Handler {
+    pub fn publish(topic: impl Into<String>, payload: Option<String>) -> Self {
+        Self::Publish {
+            topic: topic.into(),
+            payload,
+        }
+    }
+
+    /// Get the message published for an event. This is the payload of the handler if given, otherwise the
+    /// value of the event.
+    pub fn message(payload: Option<&str>, event: &WidgetEvent) -> TopicMessage {
+        if let Some(payload) = payload {
+            return TopicMessage::from(payload);
+        }
+
+        match event {
+            WidgetEvent::Toggler(toggled) => TopicMessage::Value(Value::new_bool(*toggled)),
+            WidgetEvent::PickListSelected(selected) => TopicMessage::Value(selected.clone()),
+            WidgetEvent::SelectionAdded(selected) => TopicMessage::from(selected.as_str()),
+            WidgetEvent::SliderChanged(value) => TopicMessage::from(f64::from(*value)),
+            WidgetEvent::NumberChanged(value) => TopicMessage::from(*value),
+            WidgetEvent::InputChanged(text) | WidgetEvent::InputSubmitted(text) => {
+                TopicMessage::from(text.as_str())
+            }
+            WidgetEvent::Markdown(url) => TopicMessage::from(url.as_str()),
+            WidgetEvent::FileDropped(path) => TopicMessage::String(path.display().to_string()),
+            WidgetEvent::DroppedOn { source, .. } => match &source.element_id {
+                Some(element_id) => TopicMessage::from(element_id.as_str()),
+                None => TopicMessage::Trigger,
+            },
+            _ => TopicMessage::Trigger,
+        }
+    }
+
+    /// Get a [`Task`] handling an event, updating `state` for [`Handler::State`]
+    pub fn task(&self, event: &WidgetEvent, state: &StateStore) -> Task<Message> {
+        match self {
+            Self::Publish { topic, payload } => publish(
+                Topic::new(topic.clone()),
+                Self::message(payload.as_deref(), event),
+            ),
+            Self::State(action) => action.apply(state),
+        }
+    }
+}
+
+/// Kind of the handler attribute of a widget event
+fn handler_kind(event: &WidgetEvent) -> Option<AttributeKind> {
+    match event {
+        WidgetEvent::ButtonPress => Some(AttributeKind::OnPress),
+        WidgetEvent::Toggler(_) => Some(AttributeKind::OnToggle),
+        WidgetEvent::PickListSelected(_) | WidgetEvent::SelectionAdded(_) => {
+            Some(AttributeKind::OnSelect)
+        }
+        WidgetEvent::SliderChanged(_)
+        | WidgetEvent::InputChanged(_)
+        | WidgetEvent::NumberChanged(_) => Some(AttributeKind::OnChange),
+        WidgetEvent::InputSubmitted(_) => Some(AttributeKind::OnSubmit),
+        WidgetEvent::FileDropped(_) | WidgetEvent::DroppedOn { .. } => Some(AttributeKind::OnDrop),
+        WidgetEvent::Markdown(_) => Some(AttributeKind::OnLink),
+        _ => None,
+    }
+}
+
+/// Get a [`Task`] publishing a widget event with the handler declared in the attributes of the widget, if any
+pub(crate) fn dispatch(
+    attrs: &Attributes,
+    event: &WidgetEvent,
+    state: &StateStore,
+) -> Task<Message> {
+    let Some(kind) = handler_kind(event) else {
+        return Task::none();
+    };
+
+    match attrs.get(kind) {
+        Ok(Some(
+            AttributeValue::OnPress(handler)
+            | AttributeValue::OnToggle(handler)
+            | AttributeValue::OnSelect(handler)
+            | AttributeValue::OnChange(handler)
+            | AttributeValue::OnSubmit(handler)
+            | AttributeValue::OnDrop(handler)
+            | AttributeValue::OnLink(handler),
+        )) => handler.task(event, state),
+        _ => Task::none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::Handler;
+    use crate::{
+        message::{module::TopicMessage, widget::WidgetEvent},
+        Value,
+    };
+
+    #[traced_test]
+    #[test]
+    fn handler_message() {
+        assert_eq!(
+            Handler::message(Some("settings"), &WidgetEvent::ButtonPress).as_str(),
+            Some("settings")
+        );
+        assert_eq!(
+            Handler::message(
+                None,
+                &WidgetEvent::PickListSelected(Value::new_string("fr".into()))
+            )
+            .as_str(),
+            Some("fr")
+        );
+        assert!(matches!(
+            Handler::message(None, &WidgetEvent::ButtonPress),
+            TopicMessage::Trigger
+        ));
+    }
+}
@@ -61,6 +61,20 @@ fn hash_length<H: Hasher>(length: &iced::Length, state: &mut H) {
     }
 }
 
+fn hash_fill_mode<H: Hasher>(mode: &iced::widget::rule::FillMode, state: &mut H) {
+    std::mem::discriminant(mode).hash(state);
+
+    match mode {
+        iced::widget::rule::FillMode::Percent(percent) => state.write(&percent.to_le_bytes()),
+        iced::widget::rule::FillMode::Padded(padding) => state.write_u16(*padding),
+        iced::widget::rule::FillMode::AsymmetricPadding(start, end) => {
+            state.write_u16(*start);
+            state.write_u16(*end);
+        }
+        iced::widget::rule::FillMode::Full => {}
+    }
+}
+
 fn hash_gradient<H: Hasher>(gradient: &iced::Gradient, state: &mut H) {
     std::mem::discriminant(gradient).hash(state);
     match gradient {
@@ -84,19 +98,42 @@ fn hash_background<H: Hasher>(background: &iced::Background, state: &mut H) {
     }
 }
 
+fn hash_scrollbar<H: Hasher>(scrollbar: &iced::widget::scrollable::Scrollbar, state: &mut H) {
+    // The fields of Scrollbar are private, so hash its debug representation
+    format!("{scrollbar:?}").hash(state);
+}
+
 fn hash_direction<H: Hasher>(direction: &iced::widget::scrollable::Direction, state: &mut H) {
     std::mem::discriminant(direction).hash(state);
 
-    // TODO: Hash the scrollbars
+    match direction {
+        iced::widget::scrollable::Direction::Vertical(scrollbar)
+        | iced::widget::scrollable::Direction::Horizontal(scrollbar) => {
+            hash_scrollbar(scrollbar, state)
+        }
+        iced::widget::scrollable::Direction::Both {
+            vertical,
+            horizontal,
+        } => {
+            hash_scrollbar(vertical, state);
+            hash_scrollbar(horizontal, state);
+        }
+    }
 }
 
 fn hash_theme<H: Hasher>(theme: &iced::Theme, state: &mut H) {
     std::mem::discriminant(theme).hash(state);
 
     match theme {
-        iced::Theme::Custom(_arc) => {
-            tracing::error!("Hashing of custom theme not implemented");
-            todo!()
+        iced::Theme::Custom(_) => {
+            // Custom themes are identified by name, and their palette
+            theme.to_string().hash(state);
+            let palette = theme.palette();
+            hash_color(&palette.background, state);
+            hash_color(&palette.text, state);
+            hash_color(&palette.primary, state);
+            hash_color(&palette.success, state);
+            hash_color(&palette.danger, state);
         }
         _ => {}
     }
@@ -112,6 +149,7 @@ impl std::hash::Hash for AttributeValue {
         match self {
             AttributeValue::None => {}
             AttributeValue::TextColor(color) => hash_color(color, state),
+            AttributeValue::TextPalette(color) => color.hash(state),
             AttributeValue::Border(border) => hash_border(border, state),
             AttributeValue::Shadow(shadow) => hash_shadow(shadow, state),
             AttributeValue::HorizontalAlignment(horizontal) => horizontal.hash(state),
@@ -119,23 +157,90 @@ impl std::hash::Hash for AttributeValue {
             AttributeValue::Padding(padding) => hash_padding(padding, state),
             AttributeValue::WidthLength(length) => hash_length(length, state),
             AttributeValue::WidthPixels(pixels) => hash_pixels(pixels, state),
+            AttributeValue::WidthRelative(length) => length.hash(state),
             AttributeValue::MaxWidth(pixels) => hash_pixels(pixels, state),
             AttributeValue::MaxHeight(pixels) => hash_pixels(pixels, state),
+            AttributeValue::MinWidth(pixels) => hash_pixels(pixels, state),
+            AttributeValue::MinHeight(pixels) => hash_pixels(pixels, state),
             AttributeValue::HeightLength(length) => hash_length(length, state),
             AttributeValue::HeightPixels(pixels) => hash_pixels(pixels, state),
+            AttributeValue::HeightRelative(length) => length.hash(state),
             AttributeValue::Background(background) => hash_background(background, state),
+            AttributeValue::BackgroundPalette(color) => color.hash(state),
             AttributeValue::Spacing(pixels) => hash_pixels(pixels, state),
             AttributeValue::Size(pixels) => hash_pixels(pixels, state),
             AttributeValue::CellSize(pixels) => hash_pixels(pixels, state),
             AttributeValue::Clip(clip) => clip.hash(state),
             AttributeValue::Toggled(toggled) => toggled.hash(state),
             AttributeValue::Selected(selected) => selected.hash(state),
+            AttributeValue::Focused(focused) => focused.hash(state),
+            AttributeValue::SelectedList(selected) => selected.hash(state),
             AttributeValue::Label(label) => label.hash(state),
             AttributeValue::Theme(theme) => hash_theme(theme, state),
+            AttributeValue::Responsive(values) => {
+                for (breakpoint, value) in values {
+                    breakpoint.hash(state);
+                    value.hash(state);
+                }
+            }
+            AttributeValue::Expression(expression) => expression.source().hash(state),
+            AttributeValue::Breakpoint(breakpoint) => breakpoint.hash(state),
+            AttributeValue::Opacity(opacity) => state.write(&opacity.to_le_bytes()),
+            AttributeValue::Transition(transitions) => transitions.hash(state),
+            AttributeValue::OnPress(handler)
+            | AttributeValue::OnToggle(handler)
+            | AttributeValue::OnSelect(handler)
+            | AttributeValue::OnChange(handler)
+            | AttributeValue::OnLink(handler)
+            | AttributeValue::OnSubmit(handler)
+            | AttributeValue::OnDrop(handler) => handler.hash(state),
+            AttributeValue::Bind(path) => path.hash(state),
+            AttributeValue::HeadingSize(pixels) => hash_pixels(pixels, state),
+            AttributeValue::CodeSize(pixels) => hash_pixels(pixels, state),
+            AttributeValue::CodeColor(color) => hash_color(color, state),
+            AttributeValue::CodeBackground(color) => hash_color(color, state),
+            AttributeValue::LinkColor(color) => hash_color(color, state),
+            AttributeValue::ActiveColor(color) => hash_color(color, state),
+            AttributeValue::HandleColor(color) => hash_color(color, state),
+            AttributeValue::TextSize(pixels) => hash_pixels(pixels, state),
+            AttributeValue::Thickness(pixels) => hash_pixels(pixels, state),
+            AttributeValue::RuleColor(color) => hash_color(color, state),
+            AttributeValue::RulePalette(color) => color.hash(state),
+            AttributeValue::FillMode(mode) => hash_fill_mode(mode, state),
+            AttributeValue::BackgroundImage(handle) => handle.id().hash(state),
+            AttributeValue::ContentFit(fit) => std::mem::discriminant(fit).hash(state),
+            AttributeValue::Dim(dim) => state.write(&dim.to_le_bytes()),
+            AttributeValue::ZIndex(z) => state.write_i32(*z),
+            AttributeValue::Language(language) => language.hash(state),
+            AttributeValue::Wrap(wrap) => wrap.hash(state),
+            AttributeValue::LineNumbers(line_numbers) => line_numbers.hash(state),
+            AttributeValue::ItemHeight(pixels) => hash_pixels(pixels, state),
+            AttributeValue::VisibleRange(start, end) => {
+                start.hash(state);
+                end.hash(state);
+            }
+            AttributeValue::SystemTheme => {}
+            AttributeValue::Stylesheet(stylesheet) => stylesheet.hash(state),
+            AttributeValue::StylesheetFile(path) => path.hash(state),
+            AttributeValue::Class(classes) => classes.hash(state),
             AttributeValue::Wrapping(wrapping) => wrapping.hash(state),
             AttributeValue::Shaping(shaping) => shaping.hash(state),
+            AttributeValue::Font(font) => font.hash(state),
             AttributeValue::SliderValue(value) => value.hash(state),
             AttributeValue::ScrollDirection(direction) => hash_direction(direction, state),
+            AttributeValue::Wheel(wheel) => wheel.hash(state),
+            AttributeValue::Spin(spin) => spin.hash(state),
+            AttributeValue::Step(step) => state.write(&step.to_le_bytes()),
+            AttributeValue::Animated(animated) => animated.hash(state),
+            AttributeValue::InputValue(text) => text.hash(state),
+            AttributeValue::Placeholder(text) => text.hash(state),
+            AttributeValue::Min(min) => state.write(&min.to_le_bytes()),
+            AttributeValue::Max(max) => state.write(&max.to_le_bytes()),
+            AttributeValue::Secure(secure) => secure.hash(state),
+            AttributeValue::Draggable(draggable) => draggable.hash(state),
+            AttributeValue::DropTarget(drop_target) => drop_target.hash(state),
+            AttributeValue::FileHovered(hovered) => hovered.hash(state),
+            AttributeValue::Custom(attrs) => attrs.hash(state),
         }
     }
 }
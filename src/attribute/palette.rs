@@ -0,0 +1,156 @@
+//! Colors referencing the palette of the active theme
+//!
+//! Attributes such as `text-color:palette(primary)` or `bg:palette(background.weak)` hold a [`PaletteColor`],
+//! which is resolved against the [`iced::Theme`] passed to the style closure of the widget. Widgets follow
+//! theme switches without being rebuilt. Text colors are honored by containers, buttons and text, and inherited by
+//! the text inside other elements. Backgrounds are drawn by containers and buttons, and rejected by other widgets
+//! with no background.
+//!
+//! A reference is a role, an optional shade, and an optional `text` suffix selecting the text color
+//! paired with the shade, such as `primary.strong.text`.
+
+use iced::{theme::palette::Pair, Color, Theme};
+
+use crate::parser::error::ParseError;
+
+/// Role of a color in the extended palette of a theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteRole {
+    Background,
+    Text,
+    Primary,
+    Secondary,
+    Success,
+    Danger,
+}
+
+/// Shade of a [`PaletteRole`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteShade {
+    #[default]
+    Base,
+    Weak,
+    Strong,
+}
+
+/// A color of the palette of the active theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PaletteColor {
+    role: PaletteRole,
+    shade: PaletteShade,
+    text: bool,
+}
+
+impl PaletteColor {
+    /// Parse a palette reference such as `primary`, `background.weak` or `danger.strong.text`
+    pub fn parse(reference: &str) -> Result<Self, ParseError> {
+        let invalid = || ParseError::InvalidColor(format!("palette({reference})"));
+
+        let mut parts = reference.trim().split('.');
+
+        let role = match parts.next().unwrap_or_default().to_lowercase().as_str() {
+            "background" | "bg" => PaletteRole::Background,
+            "text" => PaletteRole::Text,
+            "primary" => PaletteRole::Primary,
+            "secondary" => PaletteRole::Secondary,
+            "success" => PaletteRole::Success,
+            "danger" => PaletteRole::Danger,
+            _ => return Err(invalid()),
+        };
+
+        let mut color = Self {
+            role,
+            shade: PaletteShade::Base,
+            text: false,
+        };
+
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "base" => color.shade = PaletteShade::Base,
+                "weak" => color.shade = PaletteShade::Weak,
+                "strong" => color.shade = PaletteShade::Strong,
+                "text" => color.text = true,
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(color)
+    }
+
+    /// Resolve the color against a theme
+    pub fn resolve(&self, theme: &Theme) -> Color {
+        let palette = theme.extended_palette();
+
+        let (base, weak, strong): (Pair, Pair, Pair) = match self.role {
+            PaletteRole::Text => return theme.palette().text,
+            PaletteRole::Background => (
+                palette.background.base,
+                palette.background.weak,
+                palette.background.strong,
+            ),
+            PaletteRole::Primary => (
+                palette.primary.base,
+                palette.primary.weak,
+                palette.primary.strong,
+            ),
+            PaletteRole::Secondary => (
+                palette.secondary.base,
+                palette.secondary.weak,
+                palette.secondary.strong,
+            ),
+            PaletteRole::Success => (
+                palette.success.base,
+                palette.success.weak,
+                palette.success.strong,
+            ),
+            PaletteRole::Danger => (
+                palette.danger.base,
+                palette.danger.weak,
+                palette.danger.strong,
+            ),
+        };
+
+        let pair = match self.shade {
+            PaletteShade::Base => base,
+            PaletteShade::Weak => weak,
+            PaletteShade::Strong => strong,
+        };
+
+        if self.text {
+            pair.text
+        } else {
+            pair.color
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Theme;
+    use tracing_test::traced_test;
+
+    use super::PaletteColor;
+
+    #[traced_test]
+    #[test]
+    fn resolve_palette() {
+        let primary = PaletteColor::parse("primary").unwrap();
+        assert_eq!(primary.resolve(&Theme::Dark), Theme::Dark.palette().primary);
+        assert_eq!(primary.resolve(&Theme::Nord), Theme::Nord.palette().primary);
+
+        let weak = PaletteColor::parse("background.weak").unwrap();
+        assert_eq!(
+            weak.resolve(&Theme::Light),
+            Theme::Light.extended_palette().background.weak.color
+        );
+
+        let text = PaletteColor::parse("danger.strong.text").unwrap();
+        assert_eq!(
+            text.resolve(&Theme::Light),
+            Theme::Light.extended_palette().danger.strong.text
+        );
+
+        assert!(PaletteColor::parse("accent").is_err());
+        assert!(PaletteColor::parse("primary.dim").is_err());
+    }
+}
@@ -0,0 +1,69 @@
+//! Lengths relative to the parent or the window
+//!
+//! The `width` and `height` attributes accept a percentage of the space available to the element in its parent,
+//! such as `width:50%`, or a percentage of the size of the window with `vw` and `vh` units, such as `height:20vh`.
+//! Relative lengths are resolved during layout, so they follow the parent and the window as they are resized.
+
+use iced::Size;
+
+/// A length resolved against the space available in the parent, or the size of the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelativeLength {
+    /// Percentage of the length available in the parent along the same axis
+    Percent(f32),
+    /// Percentage of the width of the window
+    ViewportWidth(f32),
+    /// Percentage of the height of the window
+    ViewportHeight(f32),
+}
+
+impl RelativeLength {
+    /// Resolve the length in pixels, from the length available in the parent along the same axis,
+    /// and the size of the window
+    pub fn resolve(&self, available: f32, window: Size) -> f32 {
+        let length = match self {
+            Self::Percent(percent) => available * percent / 100.0,
+            Self::ViewportWidth(percent) => window.width * percent / 100.0,
+            Self::ViewportHeight(percent) => window.height * percent / 100.0,
+        };
+
+        length.max(0.0)
+    }
+}
+
+impl std::hash::Hash for RelativeLength {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Self::Percent(percent)
+            | Self::ViewportWidth(percent)
+            | Self::ViewportHeight(percent) => state.write(&percent.to_le_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Size;
+    use tracing_test::traced_test;
+
+    use super::RelativeLength;
+
+    #[traced_test]
+    #[test]
+    fn resolve() {
+        let window = Size::new(1000.0, 800.0);
+
+        assert_eq!(RelativeLength::Percent(50.0).resolve(300.0, window), 150.0);
+        assert_eq!(
+            RelativeLength::ViewportWidth(10.0).resolve(300.0, window),
+            100.0
+        );
+        assert_eq!(
+            RelativeLength::ViewportHeight(25.0).resolve(300.0, window),
+            200.0
+        );
+        assert_eq!(RelativeLength::Percent(-5.0).resolve(300.0, window), 0.0);
+    }
+}
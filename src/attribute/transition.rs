@@ -0,0 +1,254 @@
+//! Transitions of attribute values
+//!
+//! A `transition` attribute lists the attributes of a node which are tweened when their values change,
+//! rather than snapping to the new value on rebuild:
+//!
+//! ```text
+//! {<opacity:0.5, transition:opacity(300ms, ease-out), padding(200ms)> text("Hello")}
+//! ```
+//!
+//! | Property  | Attributes                                                              |
+//! |-----------|-------------------------------------------------------------------------|
+//! | `opacity` | `opacity`                                                               |
+//! | `padding` | `padding`                                                               |
+//! | `size`    | `width`, `height`, their `min-` and `max-` bounds, and `size` in pixels |
+//! | `color`   | `text-color`, and `bg` with a solid color                               |
+//!
+//! The easing is one of `linear` (the default), `ease-in`, `ease-out` or `ease-in-out`.
+
+use std::time::Duration;
+
+use iced::{Background, Color, Padding, Pixels};
+
+use crate::parser::error::ParseError;
+
+use super::{AttributeKind, AttributeValue};
+
+/// Group of attributes animated by a [`Transition`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransitionProperty {
+    Opacity,
+    Padding,
+    Size,
+    Color,
+}
+
+impl TransitionProperty {
+    /// Parse a property name
+    pub fn parse(name: &str) -> Result<Self, ParseError> {
+        match name.to_lowercase().as_str() {
+            "opacity" => Ok(Self::Opacity),
+            "padding" => Ok(Self::Padding),
+            "size" => Ok(Self::Size),
+            "color" | "colour" => Ok(Self::Color),
+            _ => Err(ParseError::Unhandled(format!(
+                "transition property '{name}'"
+            ))),
+        }
+    }
+
+    /// Kinds of the attributes animated by this property
+    pub fn kinds(&self) -> &'static [AttributeKind] {
+        match self {
+            Self::Opacity => &[AttributeKind::Opacity],
+            Self::Padding => &[AttributeKind::Padding],
+            Self::Size => &[
+                AttributeKind::WidthPixels,
+                AttributeKind::HeightPixels,
+                AttributeKind::MaxWidth,
+                AttributeKind::MaxHeight,
+                AttributeKind::MinWidth,
+                AttributeKind::MinHeight,
+                AttributeKind::Size,
+            ],
+            Self::Color => &[AttributeKind::TextColor, AttributeKind::Background],
+        }
+    }
+}
+
+/// Timing function of a [`Transition`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Parse an easing name
+    pub fn parse(name: &str) -> Result<Self, ParseError> {
+        match name.to_lowercase().as_str() {
+            "linear" => Ok(Self::Linear),
+            "ease-in" => Ok(Self::EaseIn),
+            "ease-out" => Ok(Self::EaseOut),
+            "ease-in-out" => Ok(Self::EaseInOut),
+            _ => Err(ParseError::Unhandled(format!("easing '{name}'"))),
+        }
+    }
+
+    /// Map linear progress in the range 0..=1 to eased progress
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t * t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// Transition of a [`TransitionProperty`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Transition {
+    pub property: TransitionProperty,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl Transition {
+    /// Returns true if the transition animates attributes of a kind
+    pub fn animates(&self, kind: AttributeKind) -> bool {
+        self.property.kinds().contains(&kind)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    Color::from_rgba(
+        lerp(a.r, b.r, t),
+        lerp(a.g, b.g, t),
+        lerp(a.b, b.b, t),
+        lerp(a.a, b.a, t),
+    )
+}
+
+/// Interpolate between two values of an attribute, with `t` in the range 0..=1.
+/// Returns None if the values can't be interpolated, such as gradient backgrounds.
+pub fn interpolate(from: &AttributeValue, to: &AttributeValue, t: f32) -> Option<AttributeValue> {
+    let pixels = |a: &Pixels, b: &Pixels| Pixels(lerp(a.0, b.0, t));
+
+    match (from, to) {
+        (AttributeValue::Opacity(a), AttributeValue::Opacity(b)) => {
+            Some(AttributeValue::Opacity(lerp(*a, *b, t)))
+        }
+        (AttributeValue::Padding(a), AttributeValue::Padding(b)) => {
+            Some(AttributeValue::Padding(Padding {
+                top: lerp(a.top, b.top, t),
+                right: lerp(a.right, b.right, t),
+                bottom: lerp(a.bottom, b.bottom, t),
+                left: lerp(a.left, b.left, t),
+            }))
+        }
+        (AttributeValue::WidthPixels(a), AttributeValue::WidthPixels(b)) => {
+            Some(AttributeValue::WidthPixels(pixels(a, b)))
+        }
+        (AttributeValue::HeightPixels(a), AttributeValue::HeightPixels(b)) => {
+            Some(AttributeValue::HeightPixels(pixels(a, b)))
+        }
+        (AttributeValue::MaxWidth(a), AttributeValue::MaxWidth(b)) => {
+            Some(AttributeValue::MaxWidth(pixels(a, b)))
+        }
+        (AttributeValue::MaxHeight(a), AttributeValue::MaxHeight(b)) => {
+            Some(AttributeValue::MaxHeight(pixels(a, b)))
+        }
+        (AttributeValue::MinWidth(a), AttributeValue::MinWidth(b)) => {
+            Some(AttributeValue::MinWidth(pixels(a, b)))
+        }
+        (AttributeValue::MinHeight(a), AttributeValue::MinHeight(b)) => {
+            Some(AttributeValue::MinHeight(pixels(a, b)))
+        }
+        (AttributeValue::Size(a), AttributeValue::Size(b)) => {
+            Some(AttributeValue::Size(pixels(a, b)))
+        }
+        (AttributeValue::TextColor(a), AttributeValue::TextColor(b)) => {
+            Some(AttributeValue::TextColor(mix(*a, *b, t)))
+        }
+        (
+            AttributeValue::Background(Background::Color(a)),
+            AttributeValue::Background(Background::Color(b)),
+        ) => Some(AttributeValue::Background(Background::Color(mix(
+            *a, *b, t,
+        )))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::{Color, Padding};
+    use tracing_test::traced_test;
+
+    use super::{interpolate, Easing};
+    use crate::attribute::AttributeValue;
+
+    #[traced_test]
+    #[test]
+    fn interpolate_values() {
+        assert_eq!(
+            interpolate(
+                &AttributeValue::Opacity(0.0),
+                &AttributeValue::Opacity(1.0),
+                0.25
+            ),
+            Some(AttributeValue::Opacity(0.25))
+        );
+
+        assert_eq!(
+            interpolate(
+                &AttributeValue::Padding(Padding::new(0.0)),
+                &AttributeValue::Padding(Padding::new(10.0)),
+                0.5
+            ),
+            Some(AttributeValue::Padding(Padding::new(5.0)))
+        );
+
+        assert_eq!(
+            interpolate(
+                &AttributeValue::TextColor(Color::BLACK),
+                &AttributeValue::TextColor(Color::WHITE),
+                0.5
+            ),
+            Some(AttributeValue::TextColor(Color::from_rgb(0.5, 0.5, 0.5)))
+        );
+
+        // Values of different kinds can't be interpolated
+        assert_eq!(
+            interpolate(
+                &AttributeValue::Opacity(0.0),
+                &AttributeValue::Size(1.0.into()),
+                0.5
+            ),
+            None
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn easing() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+        }
+
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+        assert_eq!(Easing::Linear.apply(2.0), 1.0);
+    }
+}
@@ -0,0 +1,123 @@
+//! Batching of widget rebuilds
+//!
+//! Modules can send data faster than the display refreshes, such as a websocket streaming samples at 100Hz.
+//! Data messages update the node data in the tree as they arrive, but rebuilding the widgets of dirty nodes
+//! is limited to once per update interval, set with [`crate::Snowcap::set_update_interval()`].
+//!
+//! An update within the interval of the previous rebuild leaves the dirty nodes for later, and schedules a
+//! [`Command::Flush`] message at the end of the interval, so the last changes of a burst are always shown.
+
+use std::time::{Duration, Instant};
+
+use iced::Task;
+use salish::Message;
+
+use crate::message::Command;
+
+/// Default interval between widget rebuilds, one frame at 60Hz
+pub(crate) const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Decision of [`UpdateBatch::poll()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Poll {
+    /// Rebuild widgets now
+    Build,
+    /// Defer the rebuild, and schedule a flush after the delay
+    Schedule(Duration),
+    /// Defer the rebuild to a flush which is already scheduled
+    Pending,
+}
+
+/// Tracks the time of the last widget rebuild, and any scheduled flush
+#[derive(Debug)]
+pub(crate) struct UpdateBatch {
+    interval: Duration,
+    last_build: Option<Instant>,
+    flush_pending: bool,
+}
+
+impl Default for UpdateBatch {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_UPDATE_INTERVAL,
+            last_build: None,
+            flush_pending: false,
+        }
+    }
+}
+
+impl UpdateBatch {
+    /// Set the minimum interval between rebuilds. A zero interval rebuilds on every update.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Decide whether widgets of dirty nodes should be rebuilt at `now`
+    pub fn poll(&mut self, now: Instant) -> Poll {
+        let elapsed = match self.last_build {
+            Some(last_build) => now.saturating_duration_since(last_build),
+            None => return Poll::Build,
+        };
+
+        if elapsed >= self.interval {
+            Poll::Build
+        } else if self.flush_pending {
+            Poll::Pending
+        } else {
+            self.flush_pending = true;
+            Poll::Schedule(self.interval - elapsed)
+        }
+    }
+
+    /// Record a rebuild at `now`
+    pub fn built(&mut self, now: Instant) {
+        self.last_build = Some(now);
+        self.flush_pending = false;
+    }
+}
+
+/// Get a [`Task`] sending [`Command::Flush`] after a delay
+pub(crate) fn flush(delay: Duration) -> Task<Message> {
+    Task::future(async move {
+        tokio::time::sleep(delay).await;
+        Message::broadcast(Command::Flush)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tracing_test::traced_test;
+
+    use super::{Poll, UpdateBatch};
+
+    #[traced_test]
+    #[test]
+    fn batch_rebuilds() {
+        let mut batch = UpdateBatch::default();
+        batch.set_interval(Duration::from_millis(10));
+
+        let start = Instant::now();
+
+        // The first update always builds
+        assert_eq!(batch.poll(start), Poll::Build);
+        batch.built(start);
+
+        // Updates within the interval schedule a single flush at the end of the interval
+        assert_eq!(
+            batch.poll(start + Duration::from_millis(4)),
+            Poll::Schedule(Duration::from_millis(6))
+        );
+        assert_eq!(batch.poll(start + Duration::from_millis(5)), Poll::Pending);
+
+        // The flush builds
+        let flush = start + Duration::from_millis(10);
+        assert_eq!(batch.poll(flush), Poll::Build);
+        batch.built(flush);
+
+        // A zero interval builds on every update
+        batch.set_interval(Duration::ZERO);
+        assert_eq!(batch.poll(flush), Poll::Build);
+    }
+}
@@ -0,0 +1,302 @@
+//! Two-way binding of widget values to application data
+//!
+//! The application registers a [`Bound`] cell under a path with [`crate::Snowcap::bind()`], and the markup
+//! binds the value of a widget to it with `value:bind(path)`:
+//!
+//! ```text
+//! toggler<value:bind(settings.dark-mode)>("Dark mode")
+//! slider<value:bind(player.volume)>
+//! pick-list<value:bind(user.language)>(["en", "fr"])
+//! number-input<value:bind(order.quantity), min:1>()
+//! ```
+//!
+//! Setting the cell with [`Bound::set()`] rebuilds only the widgets bound to its path, and changes made with
+//! a bound widget are written back to the cell. Both directions are routed as
+//! [`Command::BindingChanged`] messages, which also publish the new value to the `bind/{path}` topic.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
+
+use iced::Task;
+use parking_lot::Mutex;
+use salish::Message;
+use tracing::{debug, warn};
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    message::{module::Topic, widget::WidgetEvent, Command},
+    NodeId, SyncError, Value,
+};
+
+/// Get the topic changes of a bound value are published to
+pub fn topic(path: &str) -> Topic {
+    Topic::new(format!("bind/{path}"))
+}
+
+/// Types which can be bound to widget values
+pub trait Bindable: Clone + Debug + Send + 'static {
+    /// Convert into a [`Value`] for a widget
+    fn to_value(&self) -> Value;
+
+    /// Convert from the [`Value`] of a widget, returning None if the value can't be converted
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl Bindable for String {
+    fn to_value(&self) -> Value {
+        Value::new_string(self.clone())
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let text: std::borrow::Cow<'_, str> = value.inner().into();
+        Some(text.into_owned())
+    }
+}
+
+impl Bindable for bool {
+    fn to_value(&self) -> Value {
+        Value::new_bool(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.boolean().ok()
+    }
+}
+
+impl Bindable for f64 {
+    fn to_value(&self) -> Value {
+        Value::new_float(*self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.float().ok()
+    }
+}
+
+impl Bindable for i32 {
+    fn to_value(&self) -> Value {
+        Value::new_float(f64::from(*self))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.float().ok().map(|value| value.round() as i32)
+    }
+}
+
+/// Application data bound to widgets, shared between the application and the engine
+#[derive(Debug, Default, Clone)]
+pub struct Bound<T> {
+    value: Arc<Mutex<T>>,
+
+    /// Paths this cell is bound to
+    paths: Arc<Mutex<Vec<String>>>,
+}
+
+impl<T: Bindable> Bound<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value: Arc::new(Mutex::new(value)),
+            paths: Arc::default(),
+        }
+    }
+
+    /// Get the current value
+    pub fn get(&self) -> T {
+        self.value.lock().clone()
+    }
+
+    /// Set the value, and get a [`Task`] rebuilding the widgets bound to it
+    pub fn set(&self, value: T) -> Task<Message> {
+        *self.value.lock() = value;
+
+        Task::batch(
+            self.paths
+                .lock()
+                .iter()
+                .map(|path| Task::done(Message::broadcast(Command::BindingChanged(path.clone())))),
+        )
+    }
+}
+
+/// Type erased [`Bound`] cell held by the engine
+pub(crate) trait BindingSource: Debug + Send + Sync {
+    fn value(&self) -> Value;
+
+    /// Set the value from a widget. Returns false if the value can't be converted.
+    fn set_value(&self, value: &Value) -> bool;
+
+    fn attach(&self, path: &str);
+}
+
+impl<T: Bindable> BindingSource for Bound<T> {
+    fn value(&self) -> Value {
+        self.value.lock().to_value()
+    }
+
+    fn set_value(&self, value: &Value) -> bool {
+        match T::from_value(value) {
+            Some(value) => {
+                *self.value.lock() = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn attach(&self, path: &str) {
+        let mut paths = self.paths.lock();
+        if !paths.iter().any(|bound| bound == path) {
+            paths.push(path.to_string());
+        }
+    }
+}
+
+/// Get the value attribute of a widget from a bound value
+fn widget_attribute(widget: &str, value: &Value) -> Option<AttributeValue> {
+    match widget {
+        "slider" | "vertical-slider" => i32::from_value(value).map(AttributeValue::SliderValue),
+        "toggler" => bool::from_value(value).map(AttributeValue::Toggled),
+        "pick-list" => String::from_value(value).map(AttributeValue::Selected),
+        "text-input" | "password-input" => {
+            String::from_value(value).map(AttributeValue::InputValue)
+        }
+        "number-input" => {
+            f64::from_value(value).map(|value| AttributeValue::InputValue(value.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Get the value of a widget event which is written back to a binding
+pub(crate) fn event_value(event: &WidgetEvent) -> Option<Value> {
+    match event {
+        WidgetEvent::Toggler(toggled) => Some(toggled.to_value()),
+        WidgetEvent::SliderChanged(value) => Some(value.to_value()),
+        WidgetEvent::PickListSelected(selected) => Some(selected.clone()),
+        WidgetEvent::InputChanged(text) => Some(text.to_value()),
+        WidgetEvent::NumberChanged(value) => Some(value.to_value()),
+        _ => None,
+    }
+}
+
+/// Data sources registered by the application, and the nodes bound to them
+#[derive(Debug, Default)]
+pub(crate) struct Bindings {
+    sources: HashMap<String, Arc<dyn BindingSource>>,
+
+    /// Nodes built with each path, which are rebuilt when its value changes
+    nodes: HashMap<String, HashSet<NodeId>>,
+}
+
+impl Bindings {
+    /// Register a data source under a path
+    pub fn insert(&mut self, path: &str, source: Arc<dyn BindingSource>) {
+        source.attach(path);
+        if self.sources.insert(path.to_string(), source).is_some() {
+            warn!("Binding {path} replaced");
+        }
+    }
+
+    /// Get the current value of a path
+    pub fn value(&self, path: &str) -> Option<Value> {
+        self.sources.get(path).map(|source| source.value())
+    }
+
+    /// Write a value from a widget back to the data source of a path. Returns false if the path isn't
+    /// bound, or the value can't be converted.
+    pub fn set(&self, path: &str, value: &Value) -> bool {
+        self.sources
+            .get(path)
+            .is_some_and(|source| source.set_value(value))
+    }
+
+    /// Nodes bound to a path
+    pub fn nodes(&self, path: &str) -> Vec<NodeId> {
+        self.nodes
+            .get(path)
+            .map(|nodes| nodes.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the attributes to build a widget with, replacing a `value:bind(path)` attribute with the
+    /// value attribute of the widget.
+    pub fn resolve(
+        &mut self,
+        node_id: NodeId,
+        widget: Option<&str>,
+        attrs: &Attributes,
+    ) -> Result<Attributes, SyncError> {
+        let Some(AttributeValue::Bind(path)) = attrs.get(AttributeKind::Bind)? else {
+            return Ok(attrs.clone());
+        };
+
+        self.nodes.entry(path.clone()).or_default().insert(node_id);
+
+        let mut resolved = Attributes::new();
+        for attr in attrs {
+            if attr.kind() != AttributeKind::Bind {
+                resolved.push(attr)?;
+            }
+        }
+
+        match (widget, self.value(&path)) {
+            (Some(widget), Some(value)) => match widget_attribute(widget, &value) {
+                Some(value) => {
+                    debug!("Node {node_id} bound to {path}");
+                    resolved.set(value)?;
+                }
+                None => warn!("Can't bind {value} of {path} to {widget}"),
+            },
+            (_, None) => warn!("Node {node_id} bound to unknown path {path}"),
+            _ => {}
+        }
+
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use tracing_test::traced_test;
+
+    use super::{Bindings, Bound};
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        parser::attribute::AttributeParser,
+        Message, SnowcapParser, Value,
+    };
+
+    #[traced_test]
+    #[test]
+    fn bind_toggler() {
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{text("Hello")}"#)
+            .unwrap()
+            .index();
+        let node_id = tree.root().node().id();
+
+        let dark_mode = Bound::new(false);
+        let mut bindings = Bindings::default();
+        bindings.insert("settings.dark-mode", Arc::new(dark_mode.clone()));
+
+        let attrs = AttributeParser::parse_attributes("value:bind(settings.dark-mode)").unwrap();
+        let _ = dark_mode.set(true);
+
+        let resolved = bindings.resolve(node_id, Some("toggler"), &attrs).unwrap();
+        assert!(resolved.get(AttributeKind::Bind).unwrap().is_none());
+        assert_eq!(
+            resolved.get(AttributeKind::Toggled).unwrap(),
+            Some(AttributeValue::Toggled(true))
+        );
+        assert_eq!(bindings.nodes("settings.dark-mode"), vec![node_id]);
+
+        // Changes from the widget are written back to the cell
+        assert!(bindings.set("settings.dark-mode", &Value::new_bool(false)));
+        assert!(!dark_mode.get());
+    }
+}
@@ -1,23 +1,56 @@
 //! In-tree widget cache and Tree widget updates
 
-use std::{collections::HashMap, time::Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
 
 use arbutus::{TreeNode, TreeNodeRef as _};
-use colored::Colorize as _;
-use iced::{Element, Task};
+use file_format::FileFormat;
+use iced::{Element, Task, Theme};
+use parking_lot::Mutex;
 use salish::Message;
-use tracing::{debug, debug_span, instrument};
+use tracing::{debug, debug_span, instrument, warn};
 
 use crate::{
-    attribute::{Attribute, AttributeValue, Attributes},
+    attribute::{Attribute, AttributeKind, AttributeValue, Attributes},
+    binding::Bindings,
     conversion::{
-        column::SnowcapColumn, container::SnowcapContainer, row::SnowcapRow, stack::SnowcapStack,
-        widget::SnowcapWidget,
+        animation::AnimationFrames,
+        cascade::{self, Cascades},
+        column::SnowcapColumn,
+        constraint,
+        container::{self, SnowcapContainer},
+        drag, expression,
+        lazy_column::{self, SnowcapLazyColumn},
+        registry::WidgetRegistry,
+        responsive,
+        row::SnowcapRow,
+        stack::{self, SnowcapStack},
+        style, theme,
+        video::VideoDecoder,
+        widget::{SnowcapWidget, WidgetProviders},
     },
     dynamic_widget::DynamicWidget,
-    module::{data::ModuleData, manager::ModuleManager},
-    node::{Content, SnowcapNode, State},
-    parser::module::Module,
+    metrics,
+    module::{
+        data::{EmbeddedData, ModuleData, ModuleDataKind},
+        manager::ModuleManager,
+        selector::{DataSelector, DERIVE_MODULE},
+        window::WindowSize,
+    },
+    node::{self, Content, SnowcapNode, State},
+    parser::{
+        location::{SourceLocation, SourceMap},
+        module::Module,
+        style::Stylesheet,
+        value::ValueData,
+    },
+    plugin::{Plugins, WidgetPlugin},
+    telemetry::{self, TelemetryEvent},
+    tween::Tweens,
+    util::ThreadBound,
     ConversionError, IndexedTree, NodeId, NodeRef, Value,
 };
 
@@ -31,7 +64,13 @@ pub enum WidgetContent<M> {
     Module(Module),
     Text(String),
     Image(iced::widget::image::Handle),
+    /// Decoded frames of an animated image
+    Animation(Arc<AnimationFrames>),
+    /// Encoded video data
+    Video(Arc<Vec<u8>>),
     Svg(iced::widget::svg::Handle),
+    /// A module failed to provide content
+    Error(String),
 }
 
 impl<M> std::fmt::Display for WidgetContent<M>
@@ -52,31 +91,121 @@ where
             }
             WidgetContent::Module(module) => write!(f, "{module}"),
             WidgetContent::Image(_) => write!(f, "Image Handle"),
+            WidgetContent::Animation(frames) => {
+                write!(f, "Animation {} frames", frames.frame_count())
+            }
             WidgetContent::Svg(_) => write!(f, "SVG Handle"),
+            WidgetContent::Video(data) => write!(f, "Video {} bytes", data.len()),
             WidgetContent::Text(_) => write!(f, "Text Content"),
+            WidgetContent::Error(error) => write!(f, "Error {error}"),
             //WidgetContent::Markdown(_) => write!(f, "Markdown Items"),
         }
     }
 }
 
-/// Conversion of a reference to a boxed dyn [`ModuleData`] into [`WidgetContent`]
-impl<M> From<&Box<dyn ModuleData>> for WidgetContent<M> {
-    fn from(data: &Box<dyn ModuleData>) -> Self {
-        match data.kind() {
-            crate::module::data::ModuleDataKind::Unknown => todo!(),
-            crate::module::data::ModuleDataKind::Image => WidgetContent::Image(
-                iced::widget::image::Handle::from_bytes(data.bytes().unwrap().clone()),
-            ),
-            crate::module::data::ModuleDataKind::Svg => WidgetContent::Svg(
-                iced::widget::svg::Handle::from_memory(data.bytes().unwrap().clone()),
+/// Content decoded from [`ModuleData`]. Unlike [`WidgetContent`] this is [`Send`], so module data
+/// can be decoded on worker threads.
+#[derive(Debug)]
+pub(crate) enum DataContent {
+    Image(iced::widget::image::Handle),
+    Animation(Arc<AnimationFrames>),
+    Video(Arc<Vec<u8>>),
+    Svg(iced::widget::svg::Handle),
+    Text(String),
+    Error(String),
+}
+
+impl DataContent {
+    /// Decode module data into content for a widget. Data which can't be decoded is
+    /// returned as [`DataContent::Error`], which is rendered as an error widget.
+    /// GIF images are only decoded to [`AnimationFrames`] for widgets which are `animated`.
+    pub fn decode(data: &dyn ModuleData, animated: bool) -> Self {
+        let bytes = match data.bytes() {
+            Ok(bytes) => bytes,
+            Err(e) => return DataContent::Error(format!("Failed to read module data: {e}")),
+        };
+
+        // Binary data is rendered as the format identified from its contents
+        let kind = match data.kind() {
+            ModuleDataKind::Bytes => FileFormat::from_bytes(bytes).into(),
+            kind => kind,
+        };
+
+        match kind {
+            ModuleDataKind::Unknown | ModuleDataKind::Bytes => {
+                DataContent::Error("Unknown module data kind".into())
+            }
+            ModuleDataKind::Image => {
+                // Decode the frames of animated GIFs, falling back to a static image
+                let frames = (animated && AnimationFrames::is_gif(bytes))
+                    .then(|| AnimationFrames::decode_gif(bytes))
+                    .and_then(|frames| {
+                        frames
+                            .inspect_err(|e| warn!("Failed to decode GIF frames: {e}"))
+                            .ok()
+                    })
+                    .filter(|frames| frames.frame_count() > 1);
+
+                match frames {
+                    Some(frames) => DataContent::Animation(Arc::new(frames)),
+                    None => {
+                        DataContent::Image(iced::widget::image::Handle::from_bytes(bytes.clone()))
+                    }
+                }
+            }
+            // Video is kept by the widget while it plays, so the bytes are shared rather than copied
+            ModuleDataKind::Video => DataContent::Video(
+                data.shared_bytes()
+                    .unwrap_or_else(|| Arc::new(bytes.clone())),
             ),
-            crate::module::data::ModuleDataKind::Text => {
-                WidgetContent::Text(String::from_utf8(data.bytes().unwrap().clone()).unwrap())
+            ModuleDataKind::Svg => {
+                DataContent::Svg(iced::widget::svg::Handle::from_memory(bytes.clone()))
             }
+            ModuleDataKind::Text => match String::from_utf8(bytes.clone()) {
+                Ok(text) => DataContent::Text(text),
+                Err(e) => DataContent::Error(format!("Invalid text data: {e}")),
+            },
+            ModuleDataKind::Error => DataContent::Error(String::from_utf8_lossy(bytes).to_string()),
         }
     }
 }
 
+impl<M> From<DataContent> for WidgetContent<M> {
+    fn from(content: DataContent) -> Self {
+        match content {
+            DataContent::Image(handle) => WidgetContent::Image(handle),
+            DataContent::Animation(frames) => WidgetContent::Animation(frames),
+            DataContent::Video(data) => WidgetContent::Video(data),
+            DataContent::Svg(handle) => WidgetContent::Svg(handle),
+            DataContent::Text(text) => WidgetContent::Text(text),
+            DataContent::Error(error) => WidgetContent::Error(error),
+        }
+    }
+}
+
+/// Conversion of a reference to a boxed dyn [`ModuleData`] into [`WidgetContent`]. GIF images are decoded as a
+/// static image.
+impl<M> From<&Box<dyn ModuleData>> for WidgetContent<M> {
+    fn from(data: &Box<dyn ModuleData>) -> Self {
+        DataContent::decode(data.as_ref(), false).into()
+    }
+}
+
+/// Returns true if a node plays animated images, with the `animated:true` attribute
+pub(crate) fn is_animated(noderef: &NodeRef) -> bool {
+    matches!(
+        noderef.node().data().attrs.get(AttributeKind::Animated),
+        Ok(Some(AttributeValue::Animated(true)))
+    )
+}
+
+/// Text element describing a conversion error, rendered in place of a widget which couldn't be built
+fn error_element<M: 'static>(error: String) -> Element<'static, M> {
+    iced::widget::Text::new(error)
+        .style(iced::widget::text::danger)
+        .into()
+}
+
 /// Convert WidgetContent into iced::Element
 impl<M> Into<Element<'static, M>> for WidgetContent<M>
 where
@@ -84,8 +213,11 @@ where
 {
     fn into(self) -> Element<'static, M> {
         match self {
-            WidgetContent::Widget(dynamic_widget) => dynamic_widget.into_element().unwrap(),
-            _ => unimplemented!("Cannot convert non-Widget type content into an Element"),
+            WidgetContent::Widget(dynamic_widget) => dynamic_widget
+                .into_element()
+                .unwrap_or_else(|e| error_element(e.to_string())),
+            WidgetContent::Error(error) => error_element(error),
+            _ => error_element("Cannot convert non-Widget type content into an Element".into()),
         }
     }
 }
@@ -101,12 +233,12 @@ where
 
     fn into_iter(self) -> Self::IntoIter {
         match self {
-            WidgetContent::Widget(widget) => vec![widget.into_element().unwrap()].into_iter(),
+            WidgetContent::Widget(_) => vec![self.into()].into_iter(),
             WidgetContent::List(vec) => vec
                 .into_iter()
                 .filter_map(|item| {
-                    if let WidgetContent::Widget(widget) = item {
-                        Some(widget.into_element().unwrap())
+                    if let WidgetContent::Widget(_) = item {
+                        Some(item.into())
                     } else {
                         None
                     }
@@ -121,10 +253,59 @@ where
     }
 }
 
+/// Minimum number of module data items in an update which are decoded on worker threads rather than serially
+const PARALLEL_DECODE_THRESHOLD: usize = 4;
+
 /// Cache of Widgets and tree updates
 #[derive(Default, Debug)]
 pub struct WidgetCache {
-    widgets: HashMap<NodeId, DynamicWidget<Message>>,
+    /// Widgets of each node, bound to the thread which built them as iced widgets aren't [`Send`]
+    widgets: HashMap<NodeId, ThreadBound<DynamicWidget<Message>>>,
+
+    /// Nodes which failed to convert into a widget, and their errors
+    failed: HashMap<NodeId, String>,
+
+    /// Tweens of attributes with transitions, shared with the animation frame handler
+    tweens: Arc<Mutex<Tweens>>,
+
+    /// Data sources bound to widget values, shared with the message endpoints
+    bindings: Arc<Mutex<Bindings>>,
+
+    /// Active theme of the engine, which styles of themed widgets are derived from
+    theme: Option<Theme>,
+
+    /// Nodes with styles derived from the active theme, rebuilt when it changes
+    themed: HashSet<NodeId>,
+
+    /// Stylesheet applied to elements by id and class
+    stylesheet: Option<Arc<Stylesheet>>,
+
+    /// Lines of the markup the tree was parsed from, to locate the elements of widgets and errors
+    source_map: Option<Arc<SourceMap>>,
+
+    /// Nodes with an id or class, rebuilt when the stylesheet changes
+    styled: HashSet<NodeId>,
+
+    /// Nodes with attributes computed from expressions, rebuilt when the window is resized
+    computed: HashSet<NodeId>,
+
+    /// Nodes with expressions reading each state key, rebuilt when the value of the key changes
+    state_readers: HashMap<String, HashSet<NodeId>>,
+
+    /// Cascading attributes in effect at each node, inherited by descendant text widgets
+    cascades: Cascades,
+
+    /// Plugins intercepting the widgets of the nodes they match
+    plugins: Plugins,
+
+    /// Custom widgets and the video decoder provided by the application
+    providers: WidgetProviders,
+
+    /// Dirty epoch of nodes at the end of the last update, see [`node::dirty_epoch()`]
+    epoch: Option<u64>,
+
+    /// Set when the widgets are cleared, so the next update rebuilds them without deferring to a flush
+    cleared: bool,
 }
 
 impl WidgetCache {
@@ -132,12 +313,147 @@ impl WidgetCache {
     pub fn drop_widget(&mut self, node_id: NodeId) {
         debug!("Dropping widget {node_id}");
         self.widgets.remove(&node_id);
+        self.cascades.invalidate(node_id);
+    }
+
+    /// Drop all cached widgets. The nodes must be marked dirty to be rebuilt.
+    pub(crate) fn clear(&mut self) {
+        debug!("Clearing {} cached widgets", self.widgets.len());
+        self.widgets.clear();
+        self.cascades.clear();
+        self.failed.clear();
+        self.themed.clear();
+        self.styled.clear();
+        self.computed.clear();
+        self.state_readers.clear();
+        self.epoch = None;
+        self.cleared = true;
+    }
+
+    /// Returns true if the widgets were cleared since the last call. The next update must rebuild them
+    /// immediately, as there are no widgets to view until it does.
+    pub(crate) fn take_cleared(&mut self) -> bool {
+        std::mem::take(&mut self.cleared)
+    }
+
+    /// Register a plugin intercepting the widgets of the nodes it matches. Widgets already built aren't passed to
+    /// the plugin until they are rebuilt.
+    pub(crate) fn register_plugin(&mut self, plugin: Arc<dyn WidgetPlugin>) {
+        self.plugins.register(plugin);
+    }
+
+    /// Get the [`Tweens`] of attributes with transitions
+    pub(crate) fn tweens(&self) -> Arc<Mutex<Tweens>> {
+        self.tweens.clone()
+    }
+
+    /// Set the active theme of the engine. Returns the nodes with styles derived from the theme,
+    /// which must be marked dirty if it changed.
+    pub(crate) fn set_theme(&mut self, theme: Option<Theme>) -> Vec<NodeId> {
+        if self.theme == theme {
+            return Vec::new();
+        }
+
+        debug!("Active theme changed to {theme:?}");
+        self.theme = theme;
+        self.themed.drain().collect()
+    }
+
+    /// Set the stylesheet applied to elements. Returns the nodes with an id or class, which must be marked dirty
+    /// if it changed.
+    pub(crate) fn set_stylesheet(&mut self, stylesheet: Option<Arc<Stylesheet>>) -> Vec<NodeId> {
+        if self.stylesheet == stylesheet {
+            return Vec::new();
+        }
+
+        debug!("Stylesheet changed");
+        self.stylesheet = stylesheet;
+        self.styled.drain().collect()
+    }
+
+    /// Set the map of the markup the tree was parsed from
+    pub(crate) fn set_source_map(&mut self, source_map: Option<SourceMap>) {
+        self.source_map = source_map.map(Arc::new);
+    }
+
+    /// Get the location in the markup of the element of a node
+    pub(crate) fn source_location(&self, data: &SnowcapNode) -> Option<SourceLocation> {
+        let span = data.span()?;
+        Some(self.source_map.as_ref()?.locate_span(span))
+    }
+
+    /// Get the nodes with attributes computed from expressions, which must be marked dirty when the variables of
+    /// the expressions change, such as the size of the window
+    pub(crate) fn take_computed(&mut self) -> Vec<NodeId> {
+        self.computed.drain().collect()
+    }
+
+    /// Get the nodes with expressions reading a state key, which must be marked dirty when the value of the key
+    /// changes
+    pub(crate) fn take_state_readers(&mut self, key: &str) -> Vec<NodeId> {
+        self.state_readers
+            .remove(key)
+            .map(|nodes| nodes.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Mark themed widgets dirty if an ancestor with a `theme` attribute is dirty, such as a `themer` with its
+    /// theme set by a module, so they are rebuilt with the theme of the ancestor
+    fn invalidate_themed(&mut self, tree: &IndexedTree) {
+        if self.themed.is_empty() {
+            return;
+        }
+
+        tree.leaf_iter().for_each(|noderef| {
+            let node_id = noderef.node().id();
+            if self.themed.contains(&node_id) && theme::ancestor_theme_dirty(noderef) {
+                debug!("Theme of node {node_id} changed with its ancestor");
+                self.themed.remove(&node_id);
+                noderef.node_mut().data_mut().set_dirty(true);
+            }
+        });
+    }
+
+    /// Mark text widgets dirty if a dirty ancestor changed the cascading attributes they inherit, such as the
+    /// `text-color` of a container, so they are rebuilt with the new attributes
+    fn invalidate_cascaded(&mut self, tree: &IndexedTree) {
+        if self.cascades.is_empty() {
+            return;
+        }
+
+        tree.leaf_iter().for_each(|noderef| {
+            if self.cascades.changed(noderef) {
+                debug!(
+                    "Cascading attributes of node {} changed with its ancestor",
+                    noderef.node().id()
+                );
+                noderef.node_mut().data_mut().set_dirty(true);
+            }
+        });
+    }
+
+    /// Get the [`Bindings`] of widget values to application data
+    pub(crate) fn bindings(&self) -> Arc<Mutex<Bindings>> {
+        self.bindings.clone()
+    }
+
+    /// Get the [`WidgetRegistry`] of custom widgets built by this cache
+    pub(crate) fn registry(&self) -> WidgetRegistry {
+        self.providers.registry.clone()
+    }
+
+    /// Set the [`VideoDecoder`] of video widgets. Video widgets which were already built use the decoder once they
+    /// are rebuilt.
+    pub(crate) fn set_video_decoder(&mut self, decoder: Arc<dyn VideoDecoder>) {
+        self.providers.video_decoder = Some(decoder);
     }
 
     /// Get the cached widget for the specified NodeId, or None
     /// if it doesn't exist in the cache
     pub fn get(&self, node_id: NodeId) -> Option<DynamicWidget<Message>> {
-        self.widgets.get(&node_id).cloned()
+        self.widgets
+            .get(&node_id)
+            .map(|widget| widget.get().clone())
     }
 
     /// Find dirty paths, mark nodes as dirty along the path and drop widgets.
@@ -174,6 +490,7 @@ where
 
             match node.data().get_state() {
                 State::New => {
+                    let node_id = node.id();
                     let data = node.data_mut();
 
                     for attr in &data.attrs {
@@ -181,10 +498,30 @@ where
                             let (handle_id, task) =
                                 modules.instantiate(module.name(), module.args().clone())?;
 
+                            if attr.kind() == AttributeKind::BackgroundImage {
+                                // The image data is set on the node, and decoded when its container is built
+                                let selector = DataSelector::from_args(module.args())?;
+                                tasks.push(modules.connect_node(
+                                    handle_id,
+                                    noderef.clone(),
+                                    selector,
+                                ));
+                            } else {
+                                // Keep the instance alive while this node is in the tree
+                                modules.attach_node(handle_id, node_id);
+                            }
+
                             debug!(
-                                "Started attribute module '{}' HandleId: {handle_id}",
+                                handle_id,
+                                node_id = %node_id,
+                                "Started attribute module '{}'",
                                 module.name()
                             );
+                            telemetry::emit(TelemetryEvent::ModuleStarted {
+                                handle_id,
+                                name: module.name().clone(),
+                                node_id,
+                            });
                             tasks.push(task);
                         }
                     }
@@ -193,25 +530,40 @@ where
                     if let Content::Module(module) = data.content_mut() {
                         let args = module.args().clone();
 
-                        // Instantate the module, and get its handle_id and init task
-                        let (handle_id, task) =
-                            modules.instantiate(module.name(), module.args().clone())?;
+                        // Part of the module data this node consumes
+                        let selector = DataSelector::from_args(&args)?;
 
-                        // Set the Handle ID of the instantiated module into the tree node
-                        module.set_handle_id(handle_id);
+                        if module.name() == DERIVE_MODULE {
+                            // Derived nodes consume data from a named instance rather than instantiating a module
+                            let source = args.get("from")?.to_string();
+                            tasks.push(modules.connect_derived(&source, noderef.clone(), selector));
+                        } else {
+                            // Instantate the module, and get its handle_id and init task
+                            let (handle_id, task) =
+                                modules.instantiate(module.name(), args.clone())?;
 
-                        // Connect a NodeRef to the module
-                        modules.connect_node(handle_id, noderef.clone());
+                            // Set the Handle ID of the instantiated module into the tree node
+                            module.set_handle_id(handle_id);
 
-                        // Push the update task from the module to the set of tasks to run
-                        // after this update pass has completed.
-                        tasks.push(task);
+                            // Connect a NodeRef to the module
+                            tasks.push(modules.connect_node(handle_id, noderef.clone(), selector));
 
-                        println!(
-                            "Instantiated module handle {handle_id} for node {} args {}",
-                            node.id().clone(),
-                            args
-                        );
+                            // Push the update task from the module to the set of tasks to run
+                            // after this update pass has completed.
+                            tasks.push(task);
+
+                            debug!(
+                                handle_id,
+                                node_id = %node_id,
+                                "Instantiated module '{}' args {args}",
+                                module.name()
+                            );
+                            telemetry::emit(TelemetryEvent::ModuleStarted {
+                                handle_id,
+                                name: module.name().clone(),
+                                node_id,
+                            });
+                        }
                     }
 
                     drop(node);
@@ -249,6 +601,68 @@ where
         Ok((update_queue, tasks))
     }
 
+    /// Returns true if the node is inside the guarded element of an error boundary
+    fn in_boundary(noderef: &NodeRef) -> bool {
+        let mut current = noderef.clone();
+
+        loop {
+            let parent = match current.node().parent() {
+                Some(parent) => parent.clone(),
+                None => return false,
+            };
+
+            if let Content::Boundary = **parent.node().data() {
+                // Only the first child of a boundary is guarded
+                let guarded = parent
+                    .node()
+                    .children()
+                    .and_then(|children| children.first().map(|child| child.node().id()));
+
+                return guarded == Some(current.node().id());
+            }
+
+            current = parent;
+        }
+    }
+
+    /// Returns true if a node or any of its descendants failed to convert, or has error data from a module
+    fn subtree_failed(&self, noderef: &NodeRef) -> bool {
+        let node = noderef.node();
+
+        if self.failed.contains_key(&node.id()) {
+            return true;
+        }
+
+        if let Some(data) = node.data().module_data() {
+            if let ModuleDataKind::Error = data.kind() {
+                return true;
+            }
+        }
+
+        node.children()
+            .is_some_and(|children| children.iter().any(|child| self.subtree_failed(child)))
+    }
+
+    /// Get the widget of an error boundary, which is the widget of the guarded element,
+    /// or the fallback element if anything inside the guarded element has failed
+    fn boundary_widget(&self, noderef: &NodeRef) -> Option<DynamicWidget<Message>> {
+        let node = noderef.node();
+        let children = node.children()?;
+        let (guarded, fallback) = (children.first()?, children.get(1)?);
+
+        let child = if self.subtree_failed(guarded) {
+            debug!("Error boundary {} rendering fallback", node.id());
+            fallback
+        } else {
+            guarded
+        };
+
+        let child_id = child.node().id();
+        self.widgets
+            .get(&child_id)
+            .map(|widget| widget.get().clone())
+    }
+
     /// Collect cached [`DynamicWidget`] objects for all children of this node, if there are any.
     /// Returns None if no cached widgets are available.
     fn child_widgets(&self, node: &NodeRef) -> Option<Vec<DynamicWidget<Message>>> {
@@ -259,7 +673,11 @@ where
                 let widgets: Vec<DynamicWidget<Message>> = children
                     .iter()
                     //.filter_map(|child| child.node().data().widget.clone())
-                    .filter_map(|child| self.widgets.get(&child.node().id()).cloned())
+                    .filter_map(|child| {
+                        self.widgets
+                            .get(&child.node().id())
+                            .map(|widget| widget.get().clone())
+                    })
                     .collect();
 
                 (!widgets.is_empty()).then_some(widgets)
@@ -267,11 +685,96 @@ where
         child_widgets
     }
 
-    /// Get [`WidgetContent`] for a node from a Vec of [`DynamicWidget`] of the children
+    /// Get the layers of the children of a stack from their `z` attributes, for each child with a cached widget
+    /// in the same order as [`Self::child_widgets()`]
+    fn child_layers(&self, node: &NodeRef) -> Vec<i32> {
+        node.node()
+            .children()
+            .map(|children| {
+                children
+                    .iter()
+                    .filter(|child| self.widgets.contains_key(&child.node().id()))
+                    .map(|child| stack::layer(&child.node().data().attrs))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Decode the module data of nodes in the update queue, keyed by the id of the module node.
+    ///
+    /// Widgets aren't [`Send`], so they are built serially from the leaves to the root. Decoding module data
+    /// (images, animation frames, text) is the expensive part of rebuilding large subtrees, and is done
+    /// concurrently before the widgets are built if there is enough of it to outweigh spawning threads.
+    #[profiling::function]
+    fn decode_data(queue: &[NodeRef]) -> HashMap<NodeId, DataContent> {
+        let nodes: Vec<&NodeRef> = queue
+            .iter()
+            .filter(|noderef| noderef.node().data().module_data().is_some())
+            .filter(|noderef| !lazy_column::is_offscreen(noderef))
+            .collect();
+
+        // Threads can't be spawned on wasm
+        if nodes.len() < PARALLEL_DECODE_THRESHOLD || cfg!(target_arch = "wasm32") {
+            return HashMap::new();
+        }
+
+        Self::decode_concurrently(&nodes)
+    }
+
+    /// Decode the module data of nodes on worker threads, keyed by the id of the module node. The threshold of
+    /// [`Self::decode_data()`] is measured by the `decode` group of the `phases` benchmark.
+    pub(crate) fn decode_concurrently(nodes: &[&NodeRef]) -> HashMap<NodeId, DataContent> {
+        let start = Instant::now();
+
+        let threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1);
+        let chunk_size = nodes.len().div_ceil(threads);
+
+        let decoded: HashMap<NodeId, DataContent> = std::thread::scope(|scope| {
+            let handles: Vec<_> = nodes
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .filter_map(|noderef| {
+                                let node = noderef.node();
+                                let data = node.data().module_data()?;
+                                // The animated attribute is on the widget the module provides data for
+                                let animated = node.parent().is_some_and(is_animated);
+                                Some((node.id(), DataContent::decode(data.as_ref(), animated)))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            // Data which failed to decode on a worker is decoded again serially when the widget is built
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().ok())
+                .flatten()
+                .collect()
+        });
+
+        debug!(
+            "Decoded {} module data on {} threads in {:?}",
+            decoded.len(),
+            threads.min(nodes.len()),
+            Instant::now() - start
+        );
+
+        decoded
+    }
+
+    /// Get [`WidgetContent`] for a node from a Vec of [`DynamicWidget`] of the children.
+    /// Module data decoded by [`Self::decode_data()`] is taken from `decoded`.
     fn widget_content(
         noderef: &NodeRef,
         child_widgets: Option<Vec<DynamicWidget<Message>>>,
-    ) -> WidgetContent<Message> {
+        decoded: &mut HashMap<NodeId, DataContent>,
+    ) -> Result<WidgetContent<Message>, ConversionError> {
         let node = noderef.node();
 
         let content = if let Some(mut children) = child_widgets {
@@ -288,14 +791,27 @@ where
             }
         } else {
             // This node does not have childlren with widgets. Could be a Module or a Value
-            if node.num_children() == 1 {
-                let child = node.children().unwrap().iter().last().unwrap();
+            let children = node.children().map(|children| &children[..]);
 
+            if let Some([child]) = children {
                 match child.node().data().content() {
-                    Content::Value(value) => WidgetContent::Value(value.clone()),
+                    // Assets inlined in the markup are decoded like the data of a module
+                    Content::Value(value) => match value.inner() {
+                        ValueData::Bytes(bytes) => DataContent::decode(
+                            &EmbeddedData::new(bytes.to_vec()),
+                            is_animated(noderef),
+                        )
+                        .into(),
+                        _ => WidgetContent::Value(value.clone()),
+                    },
                     Content::Module(module) => {
                         if let Some(data) = child.node().data().module_data() {
-                            WidgetContent::from(data)
+                            match decoded.remove(&child.node().id()) {
+                                Some(content) => content.into(),
+                                None => {
+                                    DataContent::decode(data.as_ref(), is_animated(noderef)).into()
+                                }
+                            }
                         } else {
                             WidgetContent::Module(module.clone())
                         }
@@ -303,23 +819,35 @@ where
                     _ => WidgetContent::None,
                 }
             } else if node.num_children() > 1 {
-                println!("{}\n{noderef}", "At Node".red());
-                panic!("More than one child node for a node with data content");
+                return Err(ConversionError::Children(node.id(), node.num_children()));
             } else {
                 WidgetContent::None
             }
         };
 
-        content
+        Ok(content)
     }
 
-    /// Build the widget for a Node
+    /// Build the widget for a Node. The layers of the children are only used by stacks. Lengths relative to the
+    /// window are resolved against the size recorded in `window`.
     fn build_widget(
+        &self,
         node_id: NodeId,
         attrs: Attributes,
         data: &SnowcapNode,
         content: WidgetContent<Message>,
+        layers: &[i32],
+        window: &WindowSize,
     ) -> Result<Option<DynamicWidget<Message>>, ConversionError> {
+        // The layer of a child of a stack is applied by the parent
+        let attrs = stack::without_layer(attrs)?;
+
+        // Drag and drop attributes apply to any widget, and wrap the converted widget
+        let drag_attrs = attrs.clone();
+
+        // Relative lengths and size bounds are applied during layout by a wrapper around the widget
+        let (attrs, constraints) = constraint::split(attrs)?;
+
         let widget = match &**data {
             Content::Widget(widget) => {
                 debug!("Building widget {widget} node {node_id} contents {content}");
@@ -328,8 +856,10 @@ where
                     node_id,
                     widget.clone(),
                     data.element_id.clone(),
+                    data.stable_id().cloned(),
                     attrs,
                     content,
+                    &self.providers,
                 )?
                 .with_node_id(node_id);
 
@@ -337,6 +867,7 @@ where
             }
             Content::Container => {
                 debug!("Building Container node {node_id} contents {content}");
+                let attrs = container::with_background_image(attrs, data)?;
                 let widget = SnowcapContainer::new(attrs, content)?.with_node_id(node_id);
                 Some(widget)
             }
@@ -352,7 +883,7 @@ where
             }
             Content::Stack => {
                 debug!("Building Stack node {node_id} contents {content}");
-                let widget = SnowcapStack::convert(attrs, content)?.with_node_id(node_id);
+                let widget = SnowcapStack::convert(attrs, content, layers)?.with_node_id(node_id);
                 Some(widget)
             }
             Content::Root => {
@@ -360,15 +891,143 @@ where
                 if let WidgetContent::Widget(widget) = content {
                     Some(widget)
                 } else {
-                    panic!("No widget in root");
+                    return Err(ConversionError::Missing(format!(
+                        "widget in root, found {content}"
+                    )));
+                }
+            }
+            Content::Region => {
+                debug!("Building Region node {node_id} contents {content}");
+                if let WidgetContent::Widget(widget) = content {
+                    Some(widget)
+                } else {
+                    return Err(ConversionError::Missing(format!(
+                        "widget in region, found {content}"
+                    )));
                 }
             }
+            // Boundary widgets are selected from the children, and lazy columns are built from
+            // their visible children in update_tree()
+            Content::Boundary | Content::LazyColumn => None,
             Content::Module(_module) => None,
             Content::Value(_value) => None,
             Content::None => None,
         };
 
-        Ok(widget)
+        widget
+            .map(|widget| {
+                drag::wrap(node_id, data, &drag_attrs, widget)
+                    .and_then(|widget| constraint::wrap(constraints, widget, window))
+                    .map(|widget| widget.with_node_id(node_id))
+            })
+            .transpose()
+    }
+
+    /// Resolve the attributes a node is built with, from its responsive values, transitions,
+    /// bindings and the theme it is rendered with. Expressions read variables from the state store and window size
+    /// of the modules.
+    fn node_attrs(
+        &mut self,
+        noderef: &NodeRef,
+        node_id: NodeId,
+        data: &SnowcapNode,
+        modules: &ModuleManager,
+    ) -> Result<Attributes, ConversionError> {
+        // Apply the rules of the stylesheet selecting the element by id or class
+        let element_id = data.element_id.as_deref();
+        if style::is_styled(&data.attrs, element_id) {
+            self.styled.insert(node_id);
+        }
+        let attrs = style::apply(self.stylesheet.as_deref(), element_id, &data.attrs)?;
+
+        // Resolve attributes with values for each breakpoint against the nearest responsive widget
+        let attrs = if attrs.is_responsive() {
+            attrs.resolve(responsive::breakpoint(noderef))
+        } else {
+            attrs
+        };
+
+        // Evaluate attributes computed from expressions
+        if attrs.is_computed() {
+            self.computed.insert(node_id);
+            for key in expression::state_keys(&attrs) {
+                self.state_readers.entry(key).or_default().insert(node_id);
+            }
+        }
+        let attrs = expression::evaluate(noderef, &attrs, modules.state(), modules.window_size())?;
+
+        // Tween attributes with transitions towards their new values
+        let attrs = self
+            .tweens
+            .lock()
+            .apply(node_id, data.stable_id(), &attrs, Instant::now())?;
+
+        // Replace bound values with the current value of their data source
+        let widget_name = match &**data {
+            Content::Widget(name) => Some(name.as_str()),
+            _ => None,
+        };
+        let attrs = self.bindings.lock().resolve(node_id, widget_name, &attrs)?;
+
+        // Text widgets inherit cascading attributes from their ancestors
+        let attrs = if widget_name.is_some_and(cascade::inherits) {
+            self.cascades.apply(noderef, &attrs)?
+        } else {
+            attrs
+        };
+
+        // Some widget styles are derived from the theme the widget is rendered with
+        if widget_name.is_some_and(theme::is_themed) {
+            self.themed.insert(node_id);
+            let rendered = theme::ancestor_theme(noderef)
+                .or_else(|| self.theme.clone())
+                .unwrap_or(Theme::Light);
+            theme::with_theme(&attrs, rendered)
+        } else {
+            Ok(attrs)
+        }
+    }
+
+    /// Get the errors of nodes which failed to build in the last update which built them
+    pub(crate) fn errors(&self) -> Vec<(NodeId, String)> {
+        let mut errors: Vec<(NodeId, String)> = self
+            .failed
+            .iter()
+            .map(|(node_id, error)| (*node_id, error.clone()))
+            .collect();
+        errors.sort_by_key(|(node_id, _)| *node_id);
+        errors
+    }
+
+    /// Get the error of a node if it failed to build
+    pub(crate) fn error(&self, node_id: NodeId) -> Option<String> {
+        self.failed.get(&node_id).cloned()
+    }
+
+    /// Get the message of a node which failed to build, such as `button at sidebar.iced:42:7: ...`
+    fn located_error(
+        data: &SnowcapNode,
+        location: Option<&SourceLocation>,
+        error: &ConversionError,
+    ) -> String {
+        match location {
+            Some(location) => format!("{} at {location}: {error}", data.content()),
+            None => format!("{}: {error}", data.content()),
+        }
+    }
+
+    /// Emit the telemetry of a node whose widget failed to build
+    fn emit_failed(node_id: NodeId, data: &SnowcapNode, error: &ConversionError) {
+        telemetry::emit(TelemetryEvent::WidgetFailed {
+            node_id,
+            element_id: data.element_id.clone(),
+            error: error.to_string(),
+        });
+    }
+
+    /// Returns true if nodes have been created or marked dirty since the last update
+    pub(crate) fn needs_update(&self) -> bool {
+        self.epoch != Some(node::dirty_epoch())
     }
 
     /// Perform updates to widgets in the tree
@@ -387,37 +1046,139 @@ where
             + MaybeSend
             + 'static,
         */ {
+        // Skip walking the tree if no nodes have been created or marked dirty since the last update
+        if !self.needs_update() {
+            return Ok(Task::none());
+        }
+
         let start = Instant::now();
 
-        debug_span!("tree-update").in_scope(|| {
+        let result = debug_span!("tree-update").in_scope(|| {
+            // Themed widgets inside a themer with a changed theme are rebuilt with the new theme
+            self.invalidate_themed(tree);
+
+            // Text widgets are rebuilt if the attributes they inherit from a changed ancestor differ
+            self.invalidate_cascaded(tree);
+
             // First pass - Find dirty paths, mark nodes along the paths as dirty, and drop cached widgets
             let (queue, tasks) = self.mark_dirty_paths(tree, module_manager)?;
 
+            // Decode module data up front, concurrently for large updates
+            let mut decoded = Self::decode_data(&queue);
+
+            // Number of widgets built in this update, recorded in the metrics
+            let mut rebuilt = 0;
+
             for noderef in queue {
                 let node = noderef.try_node()?;
-                let data = node.data();
                 let node_id = node.id();
-                let attrs = data.attrs.clone();
 
-                if self.widgets.contains_key(&node_id) {
-                    // Already have a widget for this node, continue down the tree
-                    return Ok(Task::none());
+                // Items of a lazy column outside its viewport keep their data, but not their widgets
+                if lazy_column::is_offscreen(&noderef) {
+                    drop(node);
+                    self.drop_widget(node_id);
+                    noderef.try_node_mut()?.data_mut().set_state(State::Clean);
+                    continue;
                 }
 
-                // Get a Vec of the children's DynamicWidgets
-                let child_widgets = self.child_widgets(&noderef);
+                let data = node.data();
+                let location = self.source_location(data);
 
-                // Get the WidgetContent for this node
-                let content = Self::widget_content(&noderef, child_widgets);
+                let attrs = self.node_attrs(&noderef, node_id, data, module_manager);
 
-                let widget = Self::build_widget(node_id, attrs, data, content)?;
+                if attrs.is_ok() && self.widgets.contains_key(&node_id) {
+                    // Already have a widget for this node, continue down the tree
+                    drop(node);
+                    noderef.try_node_mut()?.data_mut().set_state(State::Clean);
+                    continue;
+                }
+
+                let widget = attrs.and_then(|attrs| {
+                    if let Content::Boundary = **data {
+                        Ok(self
+                            .boundary_widget(&noderef)
+                            .map(|widget| widget.with_node_id(node_id)))
+                    } else if let Content::LazyColumn = **data {
+                        let items = self.child_widgets(&noderef).unwrap_or_default();
+                        let widget = SnowcapLazyColumn::convert(
+                            node_id,
+                            data,
+                            attrs,
+                            items,
+                            noderef.num_children(),
+                        )?;
+                        Ok(Some(widget.with_node_id(node_id)))
+                    } else {
+                        // Get a Vec of the children's DynamicWidgets
+                        let child_widgets = self.child_widgets(&noderef);
+                        let layers = match **data {
+                            Content::Stack => self.child_layers(&noderef),
+                            _ => Vec::new(),
+                        };
+
+                        // Get the WidgetContent for this node, and build its widget
+                        Self::widget_content(&noderef, child_widgets, &mut decoded).and_then(
+                            |content| {
+                                self.build_widget(
+                                    node_id,
+                                    attrs,
+                                    data,
+                                    content,
+                                    &layers,
+                                    module_manager.window_size(),
+                                )
+                            },
+                        )
+                    }
+                });
+
+                // Plugins intercept the widgets of the nodes they match
+                let widget = widget.and_then(|widget| {
+                    widget
+                        .map(|widget| self.plugins.apply(node_id, data, location.as_ref(), widget))
+                        .transpose()
+                });
+
+                // A failing node doesn't stop the update, the rest of the queue is still built
+                let widget = match widget {
+                    Ok(widget) => {
+                        self.failed.remove(&node_id);
+                        widget
+                    }
+                    // Failures inside an error boundary are rendered by the boundary fallback
+                    Err(e) if Self::in_boundary(&noderef) => {
+                        warn!(
+                            %node_id,
+                            element_id = ?data.element_id,
+                            "Node failed inside error boundary: {e}"
+                        );
+                        Self::emit_failed(node_id, data, &e);
+                        self.failed
+                            .insert(node_id, Self::located_error(data, location.as_ref(), &e));
+                        None
+                    }
+                    // Other failures are rendered as an error placeholder in place of the widget
+                    Err(e) => {
+                        warn!(
+                            %node_id,
+                            element_id = ?data.element_id,
+                            "Node failed to build: {e}"
+                        );
+                        Self::emit_failed(node_id, data, &e);
+                        let error = Self::located_error(data, location.as_ref(), &e);
+                        self.failed.insert(node_id, error.clone());
+                        Some(SnowcapWidget::error(error).with_node_id(node_id))
+                    }
+                };
 
                 // Drop node so we can reborrow as mutable
                 drop(node);
 
                 if let Some(widget) = widget {
                     // Replace the widget
-                    self.widgets.insert(node_id, widget);
+                    let widget = widget.with_location(location);
+                    self.widgets.insert(node_id, ThreadBound::new(widget));
+                    rebuilt += 1;
                     //noderef.try_node_mut()?.data_mut().widget.replace(widget);
                 }
 
@@ -427,18 +1188,31 @@ where
 
             let duration = Instant::now() - start;
             debug!("Finished updating tree. Took {duration:?}");
+            metrics::observe_count(metrics::WIDGETS_REBUILT, rebuilt);
 
             Ok(Task::batch(tasks))
-        })
+        });
+
+        if result.is_ok() {
+            self.epoch = Some(node::dirty_epoch());
+        }
+
+        result
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::Source;
+    use arbutus::TreeNodeRef as _;
     use tracing_test::traced_test;
 
-    use crate::{cache::WidgetCache, module::manager::ModuleManager, Message, SnowcapParser};
+    use crate::{
+        cache::{DataContent, WidgetCache},
+        conversion::lazy_column,
+        module::{data::TextData, manager::ModuleManager},
+        Message, SnowcapParser,
+    };
 
     #[traced_test]
     #[test]
@@ -459,4 +1233,301 @@ mod tests {
 
         let _task = cache.update_tree(&tree, &mut modules).unwrap();
     }
+
+    #[traced_test]
+    #[test]
+    pub fn error_boundary_fallback() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        // An image widget can't be built from a string value, and is rendered as an error placeholder
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{-[image("missing"), text("A")]}"#)
+            .unwrap()
+            .index();
+        let mut cache = WidgetCache::default();
+        assert!(cache.update_tree(&tree, &mut modules).is_ok());
+        assert_eq!(cache.failed.len(), 1);
+        let (failed_id, _) = &cache.errors()[0];
+        assert!(cache.get(*failed_id).is_some());
+
+        // The rest of the tree still builds
+        let root = tree.root().node().id();
+        assert!(cache.get(root).is_some());
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let row = container.node().children().unwrap()[0].clone();
+        for child in row.node().children().unwrap() {
+            assert!(cache.get(child.node().id()).is_some());
+        }
+
+        // The same failure inside a boundary renders the fallback
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{error-boundary { image("missing") } fallback { text("failed") }}"#,
+        )
+        .unwrap()
+        .index();
+        let mut cache = WidgetCache::default();
+        assert!(cache.update_tree(&tree, &mut modules).is_ok());
+        assert_eq!(cache.failed.len(), 1);
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn release_removed_modules() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{-[text(timing!{periodic:"1s"}), text(timing!{periodic:"5s"})]}"#,
+        )
+        .unwrap()
+        .index();
+        let mut cache = WidgetCache::default();
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+        assert_eq!(modules.instance_count(), 2);
+
+        // Nodes still in the tree keep their instances
+        let _task = modules.release_nodes(|_| true);
+        assert_eq!(modules.instance_count(), 2);
+
+        let _task = modules.release_nodes(|_| false);
+        assert_eq!(modules.instance_count(), 0);
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn share_identical_modules() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{-[text(timing!{periodic:"1s"}), text(timing!{periodic:"1s"}), text(timing!{periodic:"5s"})]}"#,
+        )
+        .unwrap()
+        .index();
+        let mut cache = WidgetCache::default();
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+
+        // Identical invocations share an instance
+        assert_eq!(modules.instance_count(), 2);
+
+        // The shared instance is kept while any of its nodes remain
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let row = container.node().children().unwrap()[0].clone();
+        let text = row.node().children().unwrap()[0].clone();
+        let first = text.node().children().unwrap()[0].node().id();
+        let _task = modules.release_nodes(|id| id != first);
+        assert_eq!(modules.instance_count(), 2);
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn lazy_column_visible_items() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        let items: Vec<String> = (0..100).map(|i| format!(r#"text("Item {i}")"#)).collect();
+        let tree = SnowcapParser::<Message>::parse_memory(&format!(
+            "{{lazy-col<item-height:20, height:100>[{}]}}",
+            items.join(",")
+        ))
+        .unwrap()
+        .index();
+        let mut cache = WidgetCache::default();
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let lazy = container.node().children().unwrap()[0].clone();
+        let items = lazy.node().children().unwrap().to_vec();
+        let has_widget = |index: usize| cache.widgets.contains_key(&items[index].node().id());
+
+        // Only the items in the viewport and the overscan below it are built
+        assert!(has_widget(0) && has_widget(8));
+        assert!(!has_widget(9) && !has_widget(50));
+
+        // Scrolling builds the items entering the viewport, and drops the items leaving it
+        assert!(lazy_column::set_range(&lazy, (40, 50)));
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+        let has_widget = |index: usize| cache.widgets.contains_key(&items[index].node().id());
+        assert!(has_widget(40) && has_widget(49));
+        assert!(!has_widget(0) && !has_widget(50));
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn skip_clean_tree() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{text("A")}"#)
+            .unwrap()
+            .index();
+        let mut cache = WidgetCache::default();
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let text = container.node().children().unwrap()[0].clone();
+        let text_id = text.node().id();
+        assert!(cache.widgets.contains_key(&text_id));
+
+        // Marking a node dirty advances the epoch, so the next update rebuilds it
+        let epoch = crate::node::dirty_epoch();
+        text.node_mut().data_mut().set_dirty(true);
+        assert!(crate::node::dirty_epoch() > epoch);
+
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+        assert!(cache.widgets.contains_key(&text_id));
+        assert!(!text.node().data().is_dirty());
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn palette_colors() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        // Buttons are styled with palette colors, and widgets without a background reject one
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{-[button<bg:palette(primary), text-color:palette(primary.strong.text)>(text("A")), text<bg:palette(primary)>("B"), slider<text-color:palette(primary)>()]}"#,
+        )
+        .unwrap()
+        .index();
+        let mut cache = WidgetCache::default();
+        assert!(cache.update_tree(&tree, &mut modules).is_ok());
+        assert_eq!(cache.failed.len(), 2);
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let row = container.node().children().unwrap()[0].clone();
+        let button = row.node().children().unwrap()[0].node().id();
+        assert!(!cache.failed.contains_key(&button));
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn media_error_fallback() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        // The on-error text of a failed module is shown in place of an image
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{image(file!{path:"missing.png"})}"#)
+            .unwrap()
+            .index();
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let image = container.node().children().unwrap()[0].clone();
+        let module = image.node().children().unwrap()[0].clone();
+        module
+            .node_mut()
+            .data_mut()
+            .set_module_data(Box::new(TextData::new("N/A")));
+
+        let mut cache = WidgetCache::default();
+        assert!(cache.update_tree(&tree, &mut modules).is_ok());
+        assert!(cache.failed.is_empty());
+        assert!(cache.get(image.node().id()).is_some());
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn clear_widgets() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{text("A")}"#)
+            .unwrap()
+            .index();
+        let mut cache = WidgetCache::default();
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+        assert!(!cache.take_cleared());
+
+        // The next update must rebuild the cleared widgets, rather than deferring to a flush
+        cache.clear();
+        assert!(cache.widgets.is_empty());
+        assert!(cache.take_cleared());
+        assert!(!cache.take_cleared());
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn rebuild_inheriting_text() {
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{<text-color:#cccccc> |[text("A"), text<size:12>("B")]}"#,
+        )
+        .unwrap()
+        .index();
+        let mut cache = WidgetCache::default();
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let column = container.node().children().unwrap()[0].clone();
+        let text = column.node().children().unwrap()[0].clone();
+
+        // Rebuilding an ancestor without changing its cascading attributes leaves the text alone
+        column.node_mut().data_mut().set_dirty(true);
+        cache.invalidate_cascaded(&tree);
+        assert!(!text.node().data().is_dirty());
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+
+        // Changing the text color of the container rebuilds the text inheriting it
+        let changed = SnowcapParser::<Message>::parse_memory(r#"{<text-color:#ff0000> text("A")}"#)
+            .unwrap()
+            .index();
+        let attrs = changed.root().node().children().unwrap()[0]
+            .node()
+            .data()
+            .attrs
+            .clone();
+        container.node_mut().data_mut().attrs = attrs;
+        container.node_mut().data_mut().set_dirty(true);
+        cache.invalidate_cascaded(&tree);
+        assert!(text.node().data().is_dirty());
+
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+        assert!(!text.node().data().is_dirty());
+    }
+
+    #[traced_test]
+    #[test]
+    pub fn decode_data_concurrently() {
+        let items: Vec<String> = (0..8)
+            .map(|i| format!(r#"text(file!{{path:"{i}.txt"}})"#))
+            .collect();
+        let tree = SnowcapParser::<Message>::parse_memory(&format!("{{-[{}]}}", items.join(",")))
+            .unwrap()
+            .index();
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let row = container.node().children().unwrap()[0].clone();
+        let modules: Vec<_> = row
+            .node()
+            .children()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let module = text.node().children().unwrap()[0].clone();
+                module
+                    .node_mut()
+                    .data_mut()
+                    .set_module_data(Box::new(TextData::new(format!("Item {i}"))));
+                module
+            })
+            .collect();
+
+        let decoded = WidgetCache::decode_data(&modules);
+        assert_eq!(decoded.len(), 8);
+
+        let content = decoded.get(&modules[3].node().id()).unwrap();
+        assert!(matches!(content, DataContent::Text(text) if text == "Item 3"));
+    }
 }
@@ -0,0 +1,127 @@
+//! Engine behaviors triggered by [`Command`](crate::message::Command) messages, which need more than a few lines
+//! in the command endpoint of the engine
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::Task;
+use salish::Message;
+use tracing::{debug, info};
+
+use crate::{
+    graph::{self, GraphFormat},
+    message::{SCREENSHOT_TOPIC, TREE_TOPIC},
+    module::pubsub::publish,
+    IndexedTree, NodeRef, TopicMessage,
+};
+
+/// Get a [`Task`] publishing the tree exported as graph text to [`TREE_TOPIC`]
+pub(crate) fn dump_tree(root: &NodeRef, format: GraphFormat) -> Task<Message> {
+    let graph = graph::dump(root, format);
+    debug!("Dumped tree as {format:?}, {} bytes", graph.len());
+    publish(TREE_TOPIC, TopicMessage::String(graph))
+}
+
+/// Mark every node of the tree dirty and drop cached widgets, so the whole tree is rebuilt on the next update
+pub(crate) fn mark_all_dirty(tree: &IndexedTree) {
+    let mut count = 0;
+    tree.leaf_iter().for_each(|noderef| {
+        noderef.node_mut().data_mut().set_dirty(true);
+        count += 1;
+    });
+    info!("Cleared widget cache, rebuilding {count} nodes");
+}
+
+/// Get a [`Task`] capturing the oldest window of the application into a PNG file. The path is published
+/// to [`SCREENSHOT_TOPIC`] once the file is written.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn screenshot(path: std::path::PathBuf) -> Task<Message> {
+    iced::window::get_oldest()
+        .and_then(iced::window::screenshot)
+        .then(move |screenshot| {
+            let written = image::save_buffer(
+                &path,
+                &screenshot.bytes,
+                screenshot.size.width,
+                screenshot.size.height,
+                image::ColorType::Rgba8,
+            );
+
+            match written {
+                Ok(()) => {
+                    info!("Screenshot saved to {}", path.display());
+                    publish(
+                        SCREENSHOT_TOPIC,
+                        TopicMessage::String(path.display().to_string()),
+                    )
+                }
+                Err(e) => {
+                    tracing::error!("Failed to save screenshot to {}: {e}", path.display());
+                    Task::none()
+                }
+            }
+        })
+}
+
+#[cfg(all(test, not(target_arch = "wasm32"), feature = "headless"))]
+mod tests {
+    use std::sync::Arc;
+
+    use iced::Task;
+    use parking_lot::Mutex;
+    use tracing_test::traced_test;
+
+    use crate::{
+        headless::Headless,
+        message::{Command, OPEN_SOURCE_TOPIC, TREE_TOPIC},
+        GraphFormat, Message,
+    };
+
+    #[traced_test]
+    #[test]
+    fn engine_commands() {
+        let mut headless = Headless::new().unwrap();
+        headless
+            .load(r#"{|[text#greeting("Hello"), text#rows("World")]}"#)
+            .unwrap();
+
+        let dumped = Arc::new(Mutex::new(None));
+        let topic_dumped = dumped.clone();
+        let _tree = headless.engine().on_topic(TREE_TOPIC, move |message| {
+            *topic_dumped.lock() = message.as_str().map(str::to_string);
+            Task::none()
+        });
+
+        headless.send(Message::broadcast(Command::DumpTree(GraphFormat::Mermaid)));
+        let graph = dumped.lock().take().unwrap();
+        assert!(graph.starts_with("flowchart TD"));
+        assert!(graph.contains("#greeting"));
+
+        // Every widget is rebuilt after clearing the cache
+        headless.send(Message::broadcast(Command::ClearCache));
+        let tree = headless.describe().unwrap();
+        assert!(tree.find("greeting").unwrap().built);
+        assert!(tree.find("rows").unwrap().built);
+    }
+
+    #[traced_test]
+    #[test]
+    fn open_source() {
+        let mut headless = Headless::new().unwrap();
+        headless.load(r#"{text("Hello")}"#).unwrap();
+
+        let opened = Arc::new(Mutex::new(None));
+        let topic_opened = opened.clone();
+        let _open = headless
+            .engine()
+            .on_topic(OPEN_SOURCE_TOPIC, move |message| {
+                *topic_opened.lock() = message.as_str().map(str::to_string);
+                Task::none()
+            });
+
+        headless.send(Message::broadcast(Command::OpenSource {
+            path: "sidebar.iced".into(),
+            line: 42,
+            column: 7,
+        }));
+        assert_eq!(opened.lock().as_deref(), Some("sidebar.iced:42:7"));
+    }
+}
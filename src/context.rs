@@ -0,0 +1,244 @@
+//! Values of the application referenced in markup
+//!
+//! The host application sets values with [`crate::Snowcap::set_context()`], which markup references with
+//! `${ctx.key}`, in a string or as the value of an attribute:
+//!
+//! ```text
+//! {|[text("Signed in as ${ctx.username}"), text<size:${ctx.title-size}>("Dashboard")]}
+//! ```
+//!
+//! References inside strings are replaced when the string is parsed, so the value is only ever text, and can
+//! contain any characters. References outside strings are replaced before the markup is parsed, and only with values
+//! which are a single literal such as `24`, `1.5`, `50%` or `fill`. Other values are rejected with a warning, so a
+//! value can't inject elements, handlers or modules into the markup.
+//!
+//! A reference to a key without a value is replaced with an empty string. Setting a value reparses the markup, and
+//! patches the changes into the tree, so only the elements referencing the changed key are rebuilt.
+
+use std::{borrow::Cow, cell::RefCell, collections::BTreeMap};
+
+use tracing::{debug, warn};
+
+use crate::{
+    parser::{error::ParseErrorContext, ParserOptions},
+    Message, SnowcapParser, Tree,
+};
+
+/// Start of a reference to a value
+const OPEN: &str = "${ctx.";
+
+thread_local! {
+    /// Context whose values replace the references in the strings of the markup being parsed
+    static STRING_CONTEXT: RefCell<Option<AppContext>> = const { RefCell::new(None) };
+}
+
+/// Values of the application, by key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct AppContext {
+    values: BTreeMap<String, String>,
+}
+
+impl AppContext {
+    /// Set the value of a key. Returns true if the value changed.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) -> bool {
+        let value = value.into();
+        let key = key.into();
+
+        if self.values.get(&key) == Some(&value) {
+            return false;
+        }
+
+        debug!("Context {key} set to {value}");
+        self.values.insert(key, value);
+        true
+    }
+
+    /// Remove the value of a key. Returns true if the key had a value.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.values.remove(key).is_some()
+    }
+
+    /// Get the value of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Replace the references to values outside the strings of markup. References inside strings are left for
+    /// [`expand_string()`]. Returns the markup unchanged if it has no references.
+    pub fn expand<'a>(&self, markup: &'a str) -> Cow<'a, str> {
+        if !markup.contains(OPEN) {
+            return Cow::Borrowed(markup);
+        }
+
+        let mut expanded = String::with_capacity(markup.len());
+        let mut rest = markup;
+        let mut in_string = false;
+
+        while let Some(c) = rest.chars().next() {
+            if !in_string {
+                // Comments are copied as they are, as they may contain quotes. Unquoted URLs aren't comments.
+                if rest.starts_with("//") && !expanded.ends_with(':') {
+                    let end = rest.find('\n').unwrap_or(rest.len());
+                    expanded.push_str(&rest[..end]);
+                    rest = &rest[end..];
+                    continue;
+                }
+
+                if let Some((key, after)) = reference(rest) {
+                    match self.values.get(key) {
+                        Some(value) if is_literal(value) => expanded.push_str(value),
+                        Some(_) => {
+                            warn!("Context {key} isn't a literal, so it's only replaced in strings")
+                        }
+                        None => debug!("Context {key} has no value"),
+                    }
+                    rest = after;
+                    continue;
+                }
+            }
+
+            let len = match c {
+                // Escapes in strings are copied as they are
+                '\\' if in_string => rest[1..]
+                    .chars()
+                    .next()
+                    .map_or(1, |next| 1 + next.len_utf8()),
+                '"' => {
+                    in_string = !in_string;
+                    1
+                }
+                _ => c.len_utf8(),
+            };
+            expanded.push_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+
+        Cow::Owned(expanded)
+    }
+
+    /// Replace the references in the text of a string with the values as they are
+    fn expand_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !text.contains(OPEN) {
+            return Cow::Borrowed(text);
+        }
+
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find(OPEN) {
+            expanded.push_str(&rest[..start]);
+            match reference(&rest[start..]) {
+                Some((key, after)) => {
+                    match self.values.get(key) {
+                        Some(value) => expanded.push_str(value),
+                        None => debug!("Context {key} has no value"),
+                    }
+                    rest = after;
+                }
+                None => {
+                    expanded.push_str(OPEN);
+                    rest = &rest[start + OPEN.len()..];
+                }
+            }
+        }
+
+        expanded.push_str(rest);
+        Cow::Owned(expanded)
+    }
+
+    /// Run a parser with the references inside strings replaced with the values of this context
+    pub fn scope<R>(&self, parse: impl FnOnce() -> R) -> R {
+        let previous = STRING_CONTEXT.replace(Some(self.clone()));
+        let result = parse();
+        STRING_CONTEXT.set(previous);
+        result
+    }
+
+    /// Parse markup with the references to values replaced
+    pub fn parse(&self, markup: &str, options: &ParserOptions) -> Result<Tree, ParseErrorContext> {
+        self.scope(|| SnowcapParser::<Message>::parse_memory_with(&self.expand(markup), options))
+    }
+}
+
+/// Replace the references in the text of a string being parsed, if the parser runs in [`AppContext::scope()`]
+pub(crate) fn expand_string(text: &str) -> String {
+    STRING_CONTEXT.with_borrow(|context| match context {
+        Some(context) => context.expand_text(text).into_owned(),
+        None => text.to_string(),
+    })
+}
+
+/// Split a reference at the start of text into its key and the text after it
+fn reference(text: &str) -> Option<(&str, &str)> {
+    let after = text.strip_prefix(OPEN)?;
+    let end = after.find('}')?;
+    Some((after[..end].trim(), &after[end + 1..]))
+}
+
+/// Returns true if a value is a single literal, which can't change the structure of the markup it's inserted in
+fn is_literal(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '%' | '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use tracing_test::traced_test;
+
+    use super::{expand_string, AppContext};
+
+    #[traced_test]
+    #[test]
+    fn expand() {
+        let mut context = AppContext::default();
+        assert!(context.set("username", "alice"));
+        assert!(!context.set("username", "alice"));
+        context.set("size", "24");
+
+        // References in strings are left for the parser
+        assert_eq!(
+            context.expand(r#"text<size:${ctx.size}>("Hi ${ctx.username}, ${ctx.missing}!")"#),
+            r#"text<size:24>("Hi ${ctx.username}, ${ctx.missing}!")"#
+        );
+        assert_eq!(
+            context.scope(|| expand_string("Hi ${ctx.username}, ${ctx.missing}!")),
+            "Hi alice, !"
+        );
+        assert_eq!(expand_string("Hi ${ctx.username}"), "Hi ${ctx.username}");
+
+        // Markup without references, or with an unterminated reference, is unchanged
+        assert_eq!(context.expand(r#"text("Hi")"#), r#"text("Hi")"#);
+        assert_eq!(context.expand("${ctx.username"), "${ctx.username");
+
+        assert!(context.remove("username"));
+        assert_eq!(context.get("username"), None);
+    }
+
+    #[traced_test]
+    #[test]
+    fn reject_injection() {
+        let mut context = AppContext::default();
+        context.set("size", r#"24>(text("injected")), text<size:24"#);
+        context.set("name", r#"")), button<on-press:publish("admin")>(text(""#);
+
+        // Values which aren't a literal aren't inserted outside strings
+        assert_eq!(
+            context.expand(r#"text<size:${ctx.size}>("Hi")"#),
+            r#"text<size:>("Hi")"#
+        );
+
+        // Values inside strings are only ever text
+        let tree = context
+            .parse(
+                r#"{|[text#greeting("Hi ${ctx.name}")]}"#,
+                &Default::default(),
+            )
+            .unwrap();
+        let root = tree.root().node();
+        let column = root.children().unwrap()[0].node();
+        assert_eq!(column.children().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,331 @@
+//! Animated GIF images
+//!
+//! GIF data from a module is decoded into [`AnimationFrames`] when the image widget has the `animated:true`
+//! attribute, which plays the frames. Other images only decode the first frame. Animations are limited to
+//! [`MAX_DIMENSION`] pixels on each side, and at most [`MAX_FRAMES`] frames within [`MAX_DECODED_BYTES`] are
+//! played.
+//!
+//! ```text
+//! image<animated:true>(file!{path:"spinner.gif"})
+//! ```
+//!
+//! Frames are advanced by a [`FrameScheduler`] kept in the widget state. On each redraw the scheduler moves to the
+//! frame due at the current time, and requests the next redraw at the deadline of that frame, so no tasks or
+//! subscriptions are running between frames. Animations outside of the viewport don't request redraws, and resume
+//! on the next redraw once they are scrolled back into view.
+
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use iced::{
+    advanced::{
+        layout, mouse, renderer,
+        widget::{tree, Tree},
+        Layout, Shell, Widget,
+    },
+    event,
+    time::Instant,
+    widget::{image::Handle, Image},
+    window, Event, Length, Rectangle, Size,
+};
+
+use crate::error::ConversionError;
+
+/// Frames with a delay shorter than this are shown for [`DEFAULT_DELAY`], as browsers do
+const MIN_DELAY: Duration = Duration::from_millis(20);
+
+/// Delay of frames which don't specify a usable delay
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// Largest width or height of an animation
+pub const MAX_DIMENSION: u32 = 4096;
+
+/// Largest number of frames of an animation. Later frames are dropped.
+pub const MAX_FRAMES: usize = 1000;
+
+/// Largest size of the decoded frames of an animation. Frames past the limit are dropped.
+pub const MAX_DECODED_BYTES: usize = 256 * 1024 * 1024;
+
+/// A single decoded frame of an animation
+#[derive(Debug, Clone)]
+struct Frame {
+    handle: Handle,
+    delay: Duration,
+}
+
+/// Decoded frames of an animated image
+#[derive(Debug)]
+pub struct AnimationFrames {
+    frames: Vec<Frame>,
+}
+
+impl AnimationFrames {
+    /// Returns true if the bytes start with a GIF header
+    pub fn is_gif(bytes: &[u8]) -> bool {
+        bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a")
+    }
+
+    /// Decode the frames of a GIF into image [`Handle`]s, up to [`MAX_FRAMES`] frames and [`MAX_DECODED_BYTES`]
+    pub fn decode_gif(bytes: &[u8]) -> Result<Self, ConversionError> {
+        use ::image::{AnimationDecoder as _, ImageDecoder as _};
+
+        let decoder = ::image::codecs::gif::GifDecoder::new(Cursor::new(bytes))
+            .map_err(|e| ConversionError::InvalidType(format!("GIF: {e}")))?;
+
+        let (width, height) = decoder.dimensions();
+        if width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(ConversionError::InvalidType(format!(
+                "GIF of {width}x{height} is larger than {MAX_DIMENSION}x{MAX_DIMENSION}"
+            )));
+        }
+
+        // Each frame is decoded to an RGBA buffer of the full size of the image
+        let frame_bytes = (width as usize * height as usize * 4).max(1);
+        let max_frames = (MAX_DECODED_BYTES / frame_bytes).clamp(1, MAX_FRAMES);
+
+        let frames = decoder
+            .into_frames()
+            .take(max_frames)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ConversionError::InvalidType(format!("GIF frame: {e}")))?
+            .into_iter()
+            .map(|frame| {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                let delay = Duration::from_micros(1000 * numer as u64 / denom.max(1) as u64);
+                let delay = if delay < MIN_DELAY {
+                    DEFAULT_DELAY
+                } else {
+                    delay
+                };
+
+                let buffer = frame.into_buffer();
+                let (width, height) = buffer.dimensions();
+
+                Frame {
+                    handle: Handle::from_rgba(width, height, buffer.into_raw()),
+                    delay,
+                }
+            })
+            .collect::<Vec<Frame>>();
+
+        if frames.is_empty() {
+            return Err(ConversionError::Missing("GIF frames".into()));
+        }
+
+        Ok(Self { frames })
+    }
+
+    /// Get the number of frames
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Get the image [`Handle`] of the first frame
+    pub fn first(&self) -> Handle {
+        self.frames[0].handle.clone()
+    }
+
+    fn handle(&self, index: usize) -> Handle {
+        self.frames[index % self.frames.len()].handle.clone()
+    }
+
+    fn delay(&self, index: usize) -> Duration {
+        self.frames[index % self.frames.len()].delay
+    }
+}
+
+/// Tracks the current frame of an animation, and when the next frame is due
+#[derive(Debug, Default)]
+pub(crate) struct FrameScheduler {
+    index: usize,
+    deadline: Option<Instant>,
+}
+
+impl FrameScheduler {
+    /// Advance to the frame due at `now`, and return the deadline of the next frame.
+    /// The first call starts the animation at `now`.
+    pub fn advance(&mut self, frames: &AnimationFrames, now: Instant) -> Instant {
+        let mut deadline = *self
+            .deadline
+            .get_or_insert_with(|| now + frames.delay(self.index));
+
+        // Skip frames that were missed since the last redraw, such as while the window was hidden
+        while deadline <= now {
+            self.index = (self.index + 1) % frames.frame_count();
+            deadline += frames.delay(self.index);
+        }
+
+        self.deadline = Some(deadline);
+        deadline
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// Image widget playing [`AnimationFrames`]. Layout and drawing of the current frame are delegated to an [`Image`].
+pub(crate) struct AnimatedImage {
+    frames: Arc<AnimationFrames>,
+}
+
+impl AnimatedImage {
+    pub fn new(frames: Arc<AnimationFrames>) -> Self {
+        Self { frames }
+    }
+
+    fn image(&self, tree: &Tree) -> Image<Handle> {
+        let index = tree.state.downcast_ref::<FrameScheduler>().index();
+        Image::new(self.frames.handle(index))
+    }
+}
+
+impl<M> Widget<M, iced::Theme, iced::Renderer> for AnimatedImage {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<FrameScheduler>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(FrameScheduler::default())
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Shrink, Length::Shrink)
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let image = self.image(tree);
+        Widget::<M, iced::Theme, iced::Renderer>::layout(&image, tree, renderer, limits)
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _renderer: &iced::Renderer,
+        _clipboard: &mut dyn iced::advanced::Clipboard,
+        shell: &mut Shell<'_, M>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        if let Event::Window(window::Event::RedrawRequested(now)) = event {
+            // Pause while outside of the viewport
+            if self.frames.frame_count() > 1 && layout.bounds().intersects(viewport) {
+                let scheduler = tree.state.downcast_mut::<FrameScheduler>();
+                let deadline = scheduler.advance(&self.frames, now);
+                shell.request_redraw(window::RedrawRequest::At(deadline));
+            }
+        }
+
+        event::Status::Ignored
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let image = self.image(tree);
+        Widget::<M, iced::Theme, iced::Renderer>::draw(
+            &image, tree, renderer, theme, style, layout, cursor, viewport,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use iced::{time::Instant, widget::image::Handle};
+    use tracing_test::traced_test;
+
+    use super::{AnimationFrames, Frame, FrameScheduler, MAX_DIMENSION};
+    use crate::{cache::DataContent, module::data::EmbeddedData};
+
+    /// Encode a GIF with a number of frames of a size
+    fn gif(width: u32, height: u32, frames: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = ::image::codecs::gif::GifEncoder::new(&mut bytes);
+            encoder
+                .encode_frames(
+                    (0..frames)
+                        .map(|_| ::image::Frame::new(::image::RgbaImage::new(width, height))),
+                )
+                .unwrap();
+        }
+        bytes
+    }
+
+    #[traced_test]
+    #[test]
+    fn schedule_frames() {
+        let frame = |ms| Frame {
+            handle: Handle::from_rgba(1, 1, vec![0; 4]),
+            delay: Duration::from_millis(ms),
+        };
+        let frames = AnimationFrames {
+            frames: vec![frame(100), frame(50), frame(100)],
+        };
+
+        let start = Instant::now();
+        let mut scheduler = FrameScheduler::default();
+
+        assert_eq!(
+            scheduler.advance(&frames, start),
+            start + Duration::from_millis(100)
+        );
+        assert_eq!(scheduler.index(), 0);
+
+        scheduler.advance(&frames, start + Duration::from_millis(120));
+        assert_eq!(scheduler.index(), 1);
+
+        // Missed frames are skipped, wrapping to the first frame
+        let deadline = scheduler.advance(&frames, start + Duration::from_millis(260));
+        assert_eq!(scheduler.index(), 0);
+        assert_eq!(deadline, start + Duration::from_millis(350));
+    }
+
+    #[traced_test]
+    #[test]
+    fn detect_gif() {
+        assert!(AnimationFrames::is_gif(b"GIF89a\x01\x00"));
+        assert!(!AnimationFrames::is_gif(b"\x89PNG"));
+    }
+
+    #[traced_test]
+    #[test]
+    fn decode_limits() {
+        let frames = AnimationFrames::decode_gif(&gif(2, 2, 3)).unwrap();
+        assert_eq!(frames.frame_count(), 3);
+
+        // Images larger than the limit aren't decoded to frames
+        assert!(AnimationFrames::decode_gif(&gif(MAX_DIMENSION + 1, 1, 2)).is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn decode_animated_only() {
+        let data = EmbeddedData::new(gif(2, 2, 3));
+
+        // Widgets which aren't animated only decode the first frame
+        assert!(matches!(
+            DataContent::decode(&data, false),
+            DataContent::Image(_)
+        ));
+        assert!(matches!(
+            DataContent::decode(&data, true),
+            DataContent::Animation(frames) if frames.frame_count() == 3
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+//! Cascading of text attributes from containers to descendant text widgets
+//!
+//! The `text-color`, `size`, `font` and `shaping` attributes set on any element apply to the text widgets inside it,
+//! unless a text widget or a nearer ancestor sets its own value:
+//!
+//! ```text
+//! {<text-color:#cccccc, font:monospace> |[text("Inherited"), text<text-color:#ff0000>("Overridden")]}
+//! ```
+//!
+//! The cascading attributes in effect at each ancestor are cached by the widget cache, so text
+//! widgets rebuilt together share the resolved attributes of their ancestors rather than each walking the tree.
+//! The cache is invalidated when an ancestor is rebuilt, and text widgets are rebuilt when the attributes they
+//! inherit from a changed ancestor differ from those they were built with.
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+};
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+
+use crate::{
+    attribute::{Attribute, AttributeKind, Attributes},
+    conversion::responsive,
+    NodeId, NodeRef, SyncError,
+};
+
+/// Kinds of attributes inherited by text widgets from their ancestors
+pub(crate) const CASCADING: &[AttributeKind] = &[
+    AttributeKind::TextColor,
+    AttributeKind::TextPalette,
+    AttributeKind::Size,
+    AttributeKind::Font,
+    AttributeKind::Shaping,
+];
+
+/// Text colors are set by either kind, so a value of one kind overrides an inherited value of the other
+const COLORS: &[AttributeKind] = &[AttributeKind::TextColor, AttributeKind::TextPalette];
+
+/// Returns true if attributes of a kind are inherited by descendant text widgets
+pub(crate) fn is_cascading(kind: AttributeKind) -> bool {
+    CASCADING.contains(&kind)
+}
+
+/// Returns true if a widget inherits cascading attributes
+pub(crate) fn inherits(widget_name: &str) -> bool {
+    widget_name == "text"
+}
+
+/// Hash of the cascading attributes a text widget was built with
+fn hash_attrs(attrs: &[Attribute]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    attrs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns true if an ancestor of a node is dirty, and may have changed the attributes the node inherits
+fn ancestor_dirty(noderef: &NodeRef) -> bool {
+    let mut current = noderef.node().parent().cloned();
+
+    while let Some(parent) = current {
+        if parent.node().data().is_dirty() {
+            return true;
+        }
+        current = parent.node().parent().cloned();
+    }
+
+    false
+}
+
+/// Cascading attributes in effect at each node, inherited from its ancestors or set by the node itself
+#[derive(Debug, Default)]
+pub(crate) struct Cascades {
+    effective: HashMap<NodeId, Vec<Attribute>>,
+
+    /// Hash of the attributes in effect at the parent of each text widget when it was built
+    inherited: HashMap<NodeId, u64>,
+}
+
+impl Cascades {
+    /// Forget the cascading attributes of a node when it is rebuilt. The attributes of its descendants
+    /// include those of the node, so all resolved attributes are forgotten if the node had any.
+    pub fn invalidate(&mut self, node_id: NodeId) {
+        self.inherited.remove(&node_id);
+        if self.effective.remove(&node_id).is_some() {
+            self.effective.clear();
+        }
+    }
+
+    /// Forget the cascading attributes of all nodes
+    pub fn clear(&mut self) {
+        self.effective.clear();
+        self.inherited.clear();
+    }
+
+    /// Returns true if a text widget inherits different attributes than it was built with, because a dirty
+    /// ancestor changed its cascading attributes. The attributes are resolved again without the cache, as the
+    /// ancestor hasn't been rebuilt yet.
+    pub fn changed(&self, noderef: &NodeRef) -> bool {
+        let Some(built) = self.inherited.get(&noderef.node().id()) else {
+            return false;
+        };
+        let Some(parent) = noderef.node().parent().cloned() else {
+            return false;
+        };
+
+        ancestor_dirty(noderef) && hash_attrs(&Cascades::default().effective(&parent)) != *built
+    }
+
+    /// Returns true if any text widgets inherit cascading attributes
+    pub fn is_empty(&self) -> bool {
+        self.inherited.is_empty()
+    }
+
+    /// Add the attributes inherited from the ancestors of a node to its own attributes.
+    /// Returns the same set if nothing is inherited.
+    pub fn apply(
+        &mut self,
+        noderef: &NodeRef,
+        attrs: &Attributes,
+    ) -> Result<Attributes, SyncError> {
+        let Some(parent) = noderef.node().parent().cloned() else {
+            return Ok(attrs.clone());
+        };
+
+        let effective = self.effective(&parent);
+        self.inherited
+            .insert(noderef.node().id(), hash_attrs(&effective));
+
+        let inherited: Vec<Attribute> = effective
+            .into_iter()
+            .filter(|attr| !Self::overridden(attrs, attr.kind()))
+            .collect();
+
+        if inherited.is_empty() {
+            return Ok(attrs.clone());
+        }
+
+        let mut cascaded = Attributes::new();
+        for attr in attrs {
+            cascaded.push(attr)?;
+        }
+        for attr in inherited {
+            cascaded.push(attr)?;
+        }
+
+        Ok(cascaded)
+    }
+
+    /// Returns true if a set of attributes has its own value of a cascading kind
+    fn overridden(attrs: &Attributes, kind: AttributeKind) -> bool {
+        let kinds = if COLORS.contains(&kind) {
+            COLORS
+        } else {
+            std::slice::from_ref(&kind)
+        };
+
+        kinds
+            .iter()
+            .any(|kind| attrs.get(*kind).is_ok_and(|value| value.is_some()))
+    }
+
+    /// Get the cascading attributes in effect at a node, from the cache if they have been resolved
+    fn effective(&mut self, noderef: &NodeRef) -> Vec<Attribute> {
+        let node_id = noderef.node().id();
+        if let Some(attrs) = self.effective.get(&node_id) {
+            return attrs.clone();
+        }
+
+        let attrs = {
+            let node = noderef.node();
+            let attrs = &node.data().attrs;
+            if attrs.is_responsive() {
+                attrs.resolve(responsive::breakpoint(noderef))
+            } else {
+                attrs.clone()
+            }
+        };
+
+        let parent = noderef.node().parent().cloned();
+        let mut effective: Vec<Attribute> = match parent {
+            Some(parent) => self
+                .effective(&parent)
+                .into_iter()
+                .filter(|attr| !Self::overridden(&attrs, attr.kind()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        effective.extend(
+            attrs
+                .into_iter()
+                .filter(|attr| is_cascading(attr.kind()) && attr.value().is_some()),
+        );
+
+        self.effective.insert(node_id, effective.clone());
+        effective
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use iced::Font;
+    use tracing_test::traced_test;
+
+    use super::Cascades;
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        Message, SnowcapParser,
+    };
+
+    #[traced_test]
+    #[test]
+    fn cascade_text_attributes() {
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{<text-color:#cccccc, font:monospace> |[text<size:20>("Inherited"), text<text-color:palette(primary)>("Overridden")]}"#,
+        )
+        .unwrap()
+        .index();
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let column = container.node().children().unwrap()[0].clone();
+        let texts = column.node().children().unwrap().to_vec();
+
+        let mut cascades = Cascades::default();
+
+        let attrs = texts[0].node().data().attrs.clone();
+        let inherited = cascades.apply(&texts[0], &attrs).unwrap();
+        assert!(matches!(
+            inherited.get(AttributeKind::TextColor).unwrap(),
+            Some(AttributeValue::TextColor(_))
+        ));
+        assert_eq!(
+            inherited.get(AttributeKind::Font).unwrap(),
+            Some(AttributeValue::Font(Font::MONOSPACE))
+        );
+        assert!(inherited.get(AttributeKind::Size).unwrap().is_some());
+
+        // A palette color overrides an inherited color
+        let attrs = texts[1].node().data().attrs.clone();
+        let overridden = cascades.apply(&texts[1], &attrs).unwrap();
+        assert!(overridden.get(AttributeKind::TextColor).unwrap().is_none());
+        assert!(overridden.get(AttributeKind::Font).unwrap().is_some());
+    }
+}
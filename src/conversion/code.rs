@@ -0,0 +1,159 @@
+//! Syntax highlighted code widget
+//!
+//! ```text
+//! code<language:"rust">(file!{path:"main.rs"})
+//! code<language:"toml", line-numbers:true, wrap:false, size:13>(file!{path:"Cargo.toml"})
+//! ```
+//!
+//! The `language` is a language name or file extension, and plain text is rendered if unspecified.
+//! Colors are taken from a light or dark highlighting theme matching the theme the widget is rendered
+//! with, see [`super::theme::with_theme()`].
+//!
+//! Long lines are wrapped by default. With `wrap:false`, the code is scrollable horizontally instead.
+
+use iced::{
+    advanced::text::Highlighter as _,
+    highlighter::{self, Highlighter},
+    widget::{
+        container,
+        scrollable::{Direction, Scrollbar},
+        text::{Rich, Span},
+        Column, Container, Row, Scrollable, Text,
+    },
+    Element, Font, Length, Pixels, Theme,
+};
+use salish::Message;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    ConversionError,
+};
+
+/// Name of the code widget in the grammar
+pub(crate) const CODE_WIDGET: &str = "code";
+
+/// Language of code without a `language` attribute
+const PLAIN_TEXT: &str = "txt";
+
+/// Get the highlighting theme matching the theme a code widget is rendered with
+fn highlighter_theme(theme: &Theme) -> highlighter::Theme {
+    if theme.extended_palette().is_dark {
+        highlighter::Theme::Base16Mocha
+    } else {
+        highlighter::Theme::InspiredGitHub
+    }
+}
+
+/// Highlight each line of code into spans
+fn highlight(code: &str, language: &str, theme: &Theme) -> Vec<Vec<Span<'static, Message>>> {
+    let mut highlighter = Highlighter::new(&highlighter::Settings {
+        theme: highlighter_theme(theme),
+        token: language.to_string(),
+    });
+
+    code.lines()
+        .map(|line| {
+            highlighter
+                .highlight_line(line)
+                .map(|(range, highlight)| {
+                    let format = highlight.to_format();
+                    Span::new(line[range].to_string())
+                        .color_maybe(format.color)
+                        .font_maybe(format.font)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Build a code widget from the text of the code and its attributes
+pub(crate) fn code(
+    text: &str,
+    attrs: &Attributes,
+) -> Result<Element<'static, Message>, ConversionError> {
+    let theme = match attrs.get(AttributeKind::Theme)? {
+        Some(AttributeValue::Theme(theme)) => theme,
+        _ => Theme::Light,
+    };
+
+    let language = match attrs.get(AttributeKind::Language)? {
+        Some(AttributeValue::Language(language)) => language,
+        _ => PLAIN_TEXT.to_string(),
+    };
+
+    let size = match attrs.get(AttributeKind::Size)? {
+        Some(AttributeValue::Size(size)) => size,
+        _ => Pixels(14.0),
+    };
+
+    let line_numbers = matches!(
+        attrs.get(AttributeKind::LineNumbers)?,
+        Some(AttributeValue::LineNumbers(true))
+    );
+
+    let wrap = !matches!(
+        attrs.get(AttributeKind::Wrap)?,
+        Some(AttributeValue::Wrap(false))
+    );
+
+    let lines = highlight(text, &language, &theme);
+    let gutter = Pixels(size.0 * 0.6 * (lines.len().to_string().len() as f32 + 1.0));
+    let gutter_color = theme.extended_palette().background.strong.color;
+
+    let column = Column::with_children(lines.into_iter().enumerate().map(|(index, spans)| {
+        let line = Rich::with_spans(spans).size(size).font(Font::MONOSPACE);
+
+        if line_numbers {
+            Row::new()
+                .push(
+                    Text::new((index + 1).to_string())
+                        .size(size)
+                        .font(Font::MONOSPACE)
+                        .color(gutter_color)
+                        .width(gutter),
+                )
+                .push(line)
+                .into()
+        } else {
+            line.into()
+        }
+    }));
+
+    let content: Element<'static, Message> = if wrap {
+        column.width(Length::Fill).into()
+    } else {
+        Scrollable::new(column)
+            .direction(Direction::Horizontal(Scrollbar::new()))
+            .width(Length::Fill)
+            .into()
+    };
+
+    Ok(Container::new(content)
+        .padding(8)
+        .width(Length::Fill)
+        .style(container::rounded_box)
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Theme;
+    use tracing_test::traced_test;
+
+    use super::highlight;
+
+    #[traced_test]
+    #[test]
+    fn highlight_lines() {
+        let lines = highlight(
+            "fn main() {\n    println!(\"hi\");\n}",
+            "rust",
+            &Theme::Dark,
+        );
+        assert_eq!(lines.len(), 3);
+
+        // Spans of each line cover the whole line
+        let first: String = lines[0].iter().map(|span| span.text.as_ref()).collect();
+        assert_eq!(first, "fn main() {");
+    }
+}
@@ -3,6 +3,7 @@ use iced::widget::Column;
 use crate::{
     attribute::{AttributeValue, Attributes},
     cache::WidgetContent,
+    conversion::{cascade, drag},
     dynamic_widget::DynamicWidget,
     error::ConversionError,
 };
@@ -28,8 +29,11 @@ impl SnowcapColumn {
                 Some(AttributeValue::WidthPixels(length)) => col.width(length),
                 Some(AttributeValue::HeightPixels(length)) => col.height(length),
                 Some(AttributeValue::Spacing(pixels)) => col.spacing(pixels),
-                Some(AttributeValue::MaxWidth(length)) => col.max_width(length),
                 Some(AttributeValue::Clip(clip)) => col.clip(clip),
+                // Inherited by descendant text widgets
+                _ if cascade::is_cascading(attr.kind()) => col,
+                // Applied by the widget cache
+                _ if drag::is_drag(attr.kind()) => col,
                 _ => return Err(ConversionError::UnsupportedAttribute(attr, "Column".into())),
             };
         }
@@ -0,0 +1,329 @@
+//! Size constraints applied to any widget
+//!
+//! ```text
+//! -[col<width:30%, min-width:160>[text("Sidebar")], col<width:70%, height:50vh, max-width:960>[text("Content")]]
+//! ```
+//!
+//! A widget with a [`RelativeLength`] width or height is built to fill its bounds, and wrapped in a [`Constrained`]
+//! widget which resolves the length when it is laid out. Percentages are of the space available to the widget
+//! in its parent, and `vw` and `vh` units are of the last known size of the window, or of the available space
+//! before the window has reported its size.
+//!
+//! The `min-width`, `min-height`, `max-width` and `max-height` attributes limit the size the widget is laid out
+//! with, including the size resolved from relative lengths, so they apply the same way to every widget.
+
+use iced::{
+    advanced::{
+        layout, mouse, overlay, renderer,
+        widget::{tree, Operation, Tree},
+        Clipboard, Layout, Shell, Widget,
+    },
+    event, Element, Event, Length, Rectangle, Size, Vector,
+};
+use salish::Message;
+
+use crate::{
+    attribute::{relative::RelativeLength, Attribute, AttributeValue, Attributes},
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+    module::window::WindowSize,
+};
+
+/// Relative lengths and bounds of the size of a widget
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct SizeConstraints {
+    pub width: Option<RelativeLength>,
+    pub height: Option<RelativeLength>,
+    pub min_width: Option<f32>,
+    pub min_height: Option<f32>,
+    pub max_width: Option<f32>,
+    pub max_height: Option<f32>,
+}
+
+impl SizeConstraints {
+    /// Returns true if the size of the widget isn't constrained
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Take the size constraints out of a set of attributes. The widget is built with the returned attributes,
+/// which fill the axes with a relative length so the widget takes the size resolved by [`wrap()`].
+pub(crate) fn split(attrs: Attributes) -> Result<(Attributes, SizeConstraints), ConversionError> {
+    let mut constraints = SizeConstraints::default();
+    let mut split = Attributes::new();
+
+    for attr in &attrs {
+        match attr.value() {
+            Some(AttributeValue::WidthRelative(width)) => {
+                constraints.width = Some(*width);
+                split.push(Attribute::from(AttributeValue::WidthLength(Length::Fill)))?;
+            }
+            Some(AttributeValue::HeightRelative(height)) => {
+                constraints.height = Some(*height);
+                split.push(Attribute::from(AttributeValue::HeightLength(Length::Fill)))?;
+            }
+            Some(AttributeValue::MinWidth(pixels)) => constraints.min_width = Some(pixels.0),
+            Some(AttributeValue::MinHeight(pixels)) => constraints.min_height = Some(pixels.0),
+            Some(AttributeValue::MaxWidth(pixels)) => constraints.max_width = Some(pixels.0),
+            Some(AttributeValue::MaxHeight(pixels)) => constraints.max_height = Some(pixels.0),
+            _ => {
+                split.push(attr)?;
+            }
+        }
+    }
+
+    if constraints.is_empty() {
+        return Ok((attrs, constraints));
+    }
+
+    Ok((split, constraints))
+}
+
+/// Wrap a widget in a [`Constrained`] widget laying it out within its constraints, if it has any. Lengths relative
+/// to the window are resolved against the size recorded in `window`.
+pub(crate) fn wrap(
+    constraints: SizeConstraints,
+    widget: DynamicWidget<Message>,
+    window: &WindowSize,
+) -> Result<DynamicWidget<Message>, ConversionError> {
+    if constraints.is_empty() {
+        return Ok(widget);
+    }
+
+    Ok(DynamicWidget::default().with_widget(Constrained::new(
+        constraints,
+        widget.into_element()?,
+        window.clone(),
+    )))
+}
+
+/// Widget laying out its content within [`SizeConstraints`]
+pub(crate) struct Constrained {
+    constraints: SizeConstraints,
+    content: Element<'static, Message>,
+    window: WindowSize,
+}
+
+impl Constrained {
+    pub fn new(
+        constraints: SizeConstraints,
+        content: Element<'static, Message>,
+        window: WindowSize,
+    ) -> Self {
+        Self {
+            constraints,
+            content,
+            window,
+        }
+    }
+
+    /// Get the limits of the content, with the relative lengths resolved against the available space
+    /// and the bounds applied
+    fn limits(&self, limits: &layout::Limits) -> layout::Limits {
+        let available = limits.max();
+        let window = self.window.get().unwrap_or(available);
+        let constraints = &self.constraints;
+
+        let mut limits = *limits;
+        if let Some(width) = constraints.width {
+            limits = limits.width(width.resolve(available.width, window));
+        }
+        if let Some(height) = constraints.height {
+            limits = limits.height(height.resolve(available.height, window));
+        }
+
+        // Relative lengths are resolved first, so the bounds apply to the resolved size
+        if let Some(max_width) = constraints.max_width {
+            limits = limits.max_width(max_width);
+        }
+        if let Some(max_height) = constraints.max_height {
+            limits = limits.max_height(max_height);
+        }
+        if let Some(min_width) = constraints.min_width {
+            limits = limits.min_width(min_width);
+        }
+        if let Some(min_height) = constraints.min_height {
+            limits = limits.min_height(min_height);
+        }
+        limits
+    }
+}
+
+impl Widget<Message, iced::Theme, iced::Renderer> for Constrained {
+    fn tag(&self) -> tree::Tag {
+        self.content.as_widget().tag()
+    }
+
+    fn state(&self) -> tree::State {
+        self.content.as_widget().state()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        self.content.as_widget().children()
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        self.content.as_widget().diff(tree);
+    }
+
+    fn size(&self) -> Size<Length> {
+        // Resolved lengths are fixed once laid out, so relative axes don't take a share of the fill space
+        let size = self.content.as_widget().size();
+        Size::new(
+            if self.constraints.width.is_some() {
+                Length::Shrink
+            } else {
+                size.width
+            },
+            if self.constraints.height.is_some() {
+                Length::Shrink
+            } else {
+                size.height
+            },
+        )
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content
+            .as_widget()
+            .layout(tree, renderer, &self.limits(limits))
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        operation: &mut dyn Operation,
+    ) {
+        self.content
+            .as_widget()
+            .operate(tree, layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        self.content.as_widget_mut().on_event(
+            tree, event, layout, cursor, renderer, clipboard, shell, viewport,
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content
+            .as_widget()
+            .draw(tree, renderer, theme, style, layout, cursor, viewport);
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        self.content
+            .as_widget()
+            .mouse_interaction(tree, layout, cursor, viewport, renderer)
+    }
+
+    fn overlay<'a>(
+        &'a mut self,
+        tree: &'a mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'a, Message, iced::Theme, iced::Renderer>> {
+        self.content
+            .as_widget_mut()
+            .overlay(tree, layout, renderer, translation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::{advanced::layout::Limits, Length, Size};
+    use tracing_test::traced_test;
+
+    use super::{split, Constrained, SizeConstraints};
+    use crate::{
+        attribute::{relative::RelativeLength, AttributeKind, AttributeValue},
+        module::window::WindowSize,
+        parser::attribute::AttributeParser,
+    };
+
+    #[traced_test]
+    #[test]
+    fn split_constraints() {
+        let attrs =
+            AttributeParser::parse_attributes("width:50%, height:40, min-width:100, padding:4")
+                .unwrap();
+        let (attrs, constraints) = split(attrs).unwrap();
+
+        assert_eq!(constraints.width, Some(RelativeLength::Percent(50.0)));
+        assert_eq!(constraints.height, None);
+        assert_eq!(constraints.min_width, Some(100.0));
+        assert_eq!(
+            attrs.get(AttributeKind::WidthLength).unwrap(),
+            Some(AttributeValue::WidthLength(Length::Fill))
+        );
+        assert!(attrs.get(AttributeKind::WidthRelative).unwrap().is_none());
+        assert!(attrs.get(AttributeKind::MinWidth).unwrap().is_none());
+        assert!(attrs.get(AttributeKind::HeightPixels).unwrap().is_some());
+        assert!(attrs.get(AttributeKind::Padding).unwrap().is_some());
+    }
+
+    #[traced_test]
+    #[test]
+    fn resolve_limits() {
+        let limits = Limits::new(Size::ZERO, Size::new(800.0, 600.0));
+        let constrained = |constraints| {
+            Constrained::new(
+                constraints,
+                iced::widget::Space::new(Length::Fill, Length::Fill).into(),
+                WindowSize::default(),
+            )
+        };
+
+        let relative = constrained(SizeConstraints {
+            width: Some(RelativeLength::Percent(25.0)),
+            ..Default::default()
+        });
+        assert_eq!(relative.limits(&limits).max(), Size::new(200.0, 600.0));
+
+        // Bounds apply to the resolved length
+        let bounded = constrained(SizeConstraints {
+            width: Some(RelativeLength::Percent(25.0)),
+            min_width: Some(300.0),
+            max_height: Some(400.0),
+            ..Default::default()
+        });
+        let resolved = bounded.limits(&limits);
+        assert_eq!(resolved.min().width, 300.0);
+        assert_eq!(resolved.max().height, 400.0);
+    }
+}
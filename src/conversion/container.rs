@@ -1,7 +1,64 @@
-use crate::{attribute::AttributeValue, cache::WidgetContent};
-use iced::widget::Container;
+//! Containers, styled with colors, gradients or palette colors of the theme, or an image background
+//!
+//! ```text
+//! {<bg:image(file!{path:"hero.png"}), fit:cover, dim:0.4, width:fill, height:320, align-x:center, align-y:center>
+//!     text<size:48, text-color:#ffffff>("Snowcap")}
+//! ```
+//!
+//! The data of the `bg:image()` module is set on the container node, and decoded into the background image when
+//! the container is built. The image fills the container, drawn below its content and any background color, and is
+//! dimmed by a black overlay with the opacity of the `dim` attribute. The `fit` attribute is `contain`, `cover`,
+//! `fill`, `none` or `scale-down`, and defaults to `cover`.
 
-use crate::{attribute::Attributes, dynamic_widget::DynamicWidget, error::ConversionError};
+use crate::{
+    attribute::{Attribute, AttributeKind, AttributeValue},
+    cache::{DataContent, WidgetContent},
+    node::SnowcapNode,
+    util::ElementWrapper,
+};
+use iced::{
+    widget::{container, Container, Image, Stack},
+    Color, ContentFit, Element, Length,
+};
+use tracing::warn;
+
+use crate::{
+    attribute::Attributes, conversion::drag, dynamic_widget::DynamicWidget, error::ConversionError,
+};
+
+/// Get the attributes to build a container with, adding the [`AttributeValue::BackgroundImage`] decoded from the
+/// data of its `bg:image()` module once it has been received
+pub(crate) fn with_background_image(
+    attrs: Attributes,
+    data: &SnowcapNode,
+) -> Result<Attributes, ConversionError> {
+    let Some(module_data) = data.module_data() else {
+        return Ok(attrs);
+    };
+
+    let handle = match DataContent::decode(module_data.as_ref(), false) {
+        DataContent::Image(handle) => handle,
+        DataContent::Animation(frames) => frames.first(),
+        DataContent::Error(error) => {
+            return Err(ConversionError::InvalidType(format!(
+                "background image: {error}"
+            )))
+        }
+        _ => {
+            return Err(ConversionError::InvalidType(
+                "background image expecting image data".into(),
+            ))
+        }
+    };
+
+    let mut with_image = Attributes::new();
+    for attr in &attrs {
+        with_image.push(attr)?;
+    }
+    with_image.push(Attribute::from(AttributeValue::BackgroundImage(handle)))?;
+
+    Ok(with_image)
+}
 
 pub struct SnowcapContainer;
 
@@ -16,9 +73,28 @@ impl SnowcapContainer {
         let mut container = Container::new(content);
         let mut style = iced::widget::container::Style::default();
 
+        // Palette colors are resolved against the theme when the container is styled
+        let mut text_palette = None;
+        let mut background_palette = None;
+        let mut opacity = None;
+
+        // An image background is drawn below the container, and takes its size
+        let mut image = None;
+        let mut fit = ContentFit::Cover;
+        let mut dim = None;
+        let (mut width, mut height) = (Length::Shrink, Length::Shrink);
+
         for attr in attrs {
             (container, style) = match attr.value().cloned() {
                 Some(AttributeValue::TextColor(color)) => (container, style.color(color)),
+                Some(AttributeValue::TextPalette(color)) => {
+                    text_palette = Some(color);
+                    (container, style)
+                }
+                Some(AttributeValue::BackgroundPalette(color)) => {
+                    background_palette = Some(color);
+                    (container, style)
+                }
                 Some(AttributeValue::Border(border)) => (container, style.border(border)),
                 Some(AttributeValue::Shadow(shadow)) => (container, style.shadow(shadow)),
                 Some(AttributeValue::Background(background)) => {
@@ -31,12 +107,51 @@ impl SnowcapContainer {
                     (container.align_y(vertical), style)
                 }
                 Some(AttributeValue::Padding(padding)) => (container.padding(padding), style),
-                Some(AttributeValue::MaxWidth(pixels)) => (container.max_width(pixels), style),
-                Some(AttributeValue::WidthLength(length)) => (container.width(length), style),
-                Some(AttributeValue::HeightLength(length)) => (container.height(length), style),
-                Some(AttributeValue::WidthPixels(pixels)) => (container.width(pixels), style),
-                Some(AttributeValue::HeightPixels(pixels)) => (container.height(pixels), style),
+                Some(AttributeValue::WidthLength(length)) => {
+                    width = length;
+                    (container.width(length), style)
+                }
+                Some(AttributeValue::HeightLength(length)) => {
+                    height = length;
+                    (container.height(length), style)
+                }
+                Some(AttributeValue::WidthPixels(pixels)) => {
+                    width = Length::from(pixels);
+                    (container.width(pixels), style)
+                }
+                Some(AttributeValue::HeightPixels(pixels)) => {
+                    height = Length::from(pixels);
+                    (container.height(pixels), style)
+                }
                 Some(AttributeValue::Clip(clip)) => (container.clip(clip), style),
+                Some(AttributeValue::Opacity(value)) => {
+                    opacity = Some(value.clamp(0.0, 1.0));
+                    (container, style)
+                }
+                Some(AttributeValue::BackgroundImage(handle)) => {
+                    image = Some(handle);
+                    (container, style)
+                }
+                // The module of the background image hasn't sent its data yet
+                None if attr.kind() == AttributeKind::BackgroundImage => (container, style),
+                Some(AttributeValue::ContentFit(content_fit)) => {
+                    fit = content_fit;
+                    (container, style)
+                }
+                Some(AttributeValue::Dim(value)) => {
+                    dim = Some(value.clamp(0.0, 1.0));
+                    (container, style)
+                }
+                // Inherited by descendant text widgets
+                Some(AttributeValue::Size(_))
+                | Some(AttributeValue::Font(_))
+                | Some(AttributeValue::Shaping(_)) => (container, style),
+                // Themes of the root container are applied by the engine
+                Some(AttributeValue::Theme(_)) | Some(AttributeValue::SystemTheme) => {
+                    (container, style)
+                }
+                // Applied by the widget cache
+                _ if drag::is_drag(attr.kind()) => (container, style),
                 _ => {
                     return Err(ConversionError::UnsupportedAttribute(
                         attr,
@@ -46,8 +161,126 @@ impl SnowcapContainer {
             };
         }
 
-        container = container.style(move |_theme| style);
+        container = container.style(move |theme| {
+            let mut style = style;
+            if let Some(color) = text_palette {
+                style.text_color = Some(color.resolve(theme));
+            }
+            if let Some(color) = background_palette {
+                style.background = Some(color.resolve(theme).into());
+            }
+            if let Some(opacity) = opacity {
+                style.text_color = style.text_color.map(|color| color.scale_alpha(opacity));
+                style.background = style
+                    .background
+                    .map(|background| background.scale_alpha(opacity));
+                style.border.color = style.border.color.scale_alpha(opacity);
+                style.shadow.color = style.shadow.color.scale_alpha(opacity);
+            }
+            style
+        });
+
+        let Some(handle) = image else {
+            if dim.is_some() {
+                warn!("Container dim has no effect without a background image");
+            }
+            return Ok(DynamicWidget::default().with_widget(container));
+        };
+
+        // The stack takes the size of the container, with the image filling it below the content
+        let mut background = Image::new(handle)
+            .content_fit(fit)
+            .width(Length::Fill)
+            .height(Length::Fill);
+        if let Some(opacity) = opacity {
+            background = background.opacity(opacity);
+        }
+
+        let mut stack = Stack::new().width(width).height(height).push(background);
+
+        if let Some(dim) = dim {
+            stack = stack.push(
+                Container::new(iced::widget::Space::new(Length::Fill, Length::Fill))
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .style(move |_theme| {
+                        container::Style::default().background(Color::BLACK.scale_alpha(dim))
+                    }),
+            );
+        }
+
+        let element: Element<'static, M> = stack
+            .push(container.width(Length::Fill).height(Length::Fill))
+            .into();
+
+        Ok(DynamicWidget::default().with_widget(ElementWrapper::new(element)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use tracing_test::traced_test;
+
+    use super::with_background_image;
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        module::{
+            data::{ModuleData, ModuleDataKind, TextData},
+            error::ModuleError,
+        },
+        Message, SnowcapParser,
+    };
+
+    #[derive(Debug)]
+    struct ImageData(Vec<u8>);
+
+    impl ModuleData for ImageData {
+        fn kind(&self) -> ModuleDataKind {
+            ModuleDataKind::Image
+        }
+
+        fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
+            Ok(&self.0)
+        }
+    }
+
+    #[traced_test]
+    #[test]
+    fn background_image() {
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{<bg:image(file!{path:"hero.png"}), fit:contain, dim:0.5> text("Hero")}"#,
+        )
+        .unwrap()
+        .index();
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let attrs = container.node().data().attrs.clone();
+        assert_eq!(
+            attrs.get(AttributeKind::ContentFit).unwrap(),
+            Some(AttributeValue::ContentFit(iced::ContentFit::Contain))
+        );
+
+        // Without data from the module, the container is built without the image
+        let built = with_background_image(attrs.clone(), &container.node().data()).unwrap();
+        assert!(built.get(AttributeKind::BackgroundImage).unwrap().is_none());
+
+        container
+            .node_mut()
+            .data_mut()
+            .set_module_data(Box::new(ImageData(b"\x89PNG".to_vec())));
+        let built = with_background_image(attrs.clone(), &container.node().data()).unwrap();
+        assert!(matches!(
+            built.get(AttributeKind::BackgroundImage).unwrap(),
+            Some(AttributeValue::BackgroundImage(_))
+        ));
+        assert!(built.get(AttributeKind::Dim).unwrap().is_some());
 
-        Ok(DynamicWidget::default().with_widget(container))
+        // Data other than an image fails to build
+        container
+            .node_mut()
+            .data_mut()
+            .set_module_data(Box::new(TextData::new("not an image")));
+        assert!(with_background_image(attrs, &container.node().data()).is_err());
     }
 }
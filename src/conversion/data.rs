@@ -19,7 +19,7 @@ impl DataType {
         M: std::fmt::Debug + From<(NodeId, WidgetMessage)> + 'static,
     {
         match self {
-            DataType::Null => panic!("Null DataType"),
+            DataType::Null => Err(ConversionError::Missing("data for Null DataType".into())),
             DataType::Text(string) => {
                 let text = Text::new(string.clone());
 
@@ -0,0 +1,222 @@
+//! Drag and drop between widgets
+//!
+//! Any element can be dragged with `draggable:true`, and dropped onto an element with `drop-target:true`:
+//!
+//! ```text
+//! -[col#todo<drop-target:true>[text#card<draggable:true>("Write docs")], col#done<drop-target:true>[text("Done")]]
+//! ```
+//!
+//! Pressing the mouse on a draggable element sends [`WidgetEvent::DragStarted`] from it, and the engine tracks it as
+//! the source of the drag. Releasing the mouse over a drop target sends [`WidgetEvent::DroppedOn`] from the target,
+//! with the source and target of the drop. Releasing it anywhere else cancels the drag. Interactive widgets such as
+//! buttons handle their own presses, so the draggable element should contain only text, images or layout.
+
+use std::sync::Arc;
+
+use iced::{widget::MouseArea, Task};
+use parking_lot::Mutex;
+use salish::Message;
+use tracing::debug;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+    message::{
+        widget::{DragNode, WidgetEvent, WidgetMessage},
+        Command,
+    },
+    node::SnowcapNode,
+    util::ElementWrapper,
+    NodeId,
+};
+
+/// Kinds of attributes applied by [`wrap()`] to the widget of any element
+const DRAG_KINDS: &[AttributeKind] = &[AttributeKind::Draggable, AttributeKind::DropTarget];
+
+/// Returns true if attributes of a kind are applied by [`wrap()`], rather than by the conversion of a widget
+pub(crate) fn is_drag(kind: AttributeKind) -> bool {
+    DRAG_KINDS.contains(&kind)
+}
+
+/// Wrap the widget of a node in a [`MouseArea`] starting drags from it, or accepting drops onto it,
+/// if enabled by its attributes
+pub(crate) fn wrap(
+    node_id: NodeId,
+    data: &SnowcapNode,
+    attrs: &Attributes,
+    widget: DynamicWidget<Message>,
+) -> Result<DynamicWidget<Message>, ConversionError> {
+    let draggable = matches!(
+        attrs.get(AttributeKind::Draggable)?,
+        Some(AttributeValue::Draggable(true))
+    );
+    let drop_target = matches!(
+        attrs.get(AttributeKind::DropTarget)?,
+        Some(AttributeValue::DropTarget(true))
+    );
+
+    if !draggable && !drop_target {
+        return Ok(widget);
+    }
+
+    let node = DragNode {
+        node_id,
+        element_id: data.element_id.clone(),
+    };
+
+    let mut area = MouseArea::new(widget.into_element()?);
+
+    if draggable {
+        area = area.on_press(Message::broadcast(
+            WidgetMessage::new(
+                node_id,
+                node.element_id.clone(),
+                WidgetEvent::DragStarted {
+                    source: node.clone(),
+                },
+            )
+            .with_stable_id(data.stable_id().cloned()),
+        ));
+    }
+
+    if drop_target {
+        area = area.on_release(Message::broadcast(Command::Drop(node)));
+    }
+
+    Ok(DynamicWidget::default().with_widget(ElementWrapper::new(area.into())))
+}
+
+/// Source of the drag in progress, shared by the endpoints of the engine
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DragState(Arc<Mutex<Option<DragNode>>>);
+
+impl DragState {
+    /// Start a drag from a source, replacing any drag in progress
+    pub fn start(&self, source: DragNode) {
+        debug!("Drag started from node {}", source.node_id);
+        *self.0.lock() = Some(source);
+    }
+
+    /// Returns true if a drag is in progress
+    pub fn is_active(&self) -> bool {
+        self.0.lock().is_some()
+    }
+
+    /// End the drag in progress without dropping it
+    pub fn cancel(&self) {
+        if let Some(source) = self.0.lock().take() {
+            debug!("Drag from node {} cancelled", source.node_id);
+        }
+    }
+
+    /// End the drag in progress by dropping it onto a target, and get a [`Task`] sending
+    /// [`WidgetEvent::DroppedOn`] from the target. Nothing is sent if no drag is in progress, or the source
+    /// is dropped onto itself.
+    pub fn drop_on(&self, target: DragNode) -> Task<Message> {
+        let Some(source) = self.0.lock().take() else {
+            return Task::none();
+        };
+
+        if source.node_id == target.node_id {
+            return Task::none();
+        }
+
+        debug!(
+            "Dropped node {} onto node {}",
+            source.node_id, target.node_id
+        );
+
+        Task::done(Message::broadcast(WidgetMessage::new(
+            target.node_id,
+            target.element_id.clone(),
+            WidgetEvent::DroppedOn { source, target },
+        )))
+    }
+}
+
+/// Get a [`iced::Subscription`] cancelling the drag in progress when the mouse is released anywhere
+/// other than a drop target, which captures the release
+pub(crate) fn subscription(state: &DragState) -> iced::Subscription<Message> {
+    if !state.is_active() {
+        return iced::Subscription::none();
+    }
+
+    iced::event::listen_with(|event, status, _window| match (event, status) {
+        (
+            iced::Event::Mouse(iced::mouse::Event::ButtonReleased(iced::mouse::Button::Left)),
+            iced::event::Status::Ignored,
+        ) => Some(Message::broadcast(Command::DragCancel)),
+        _ => None,
+    })
+}
+
+#[cfg(all(test, not(target_arch = "wasm32"), feature = "headless"))]
+mod tests {
+    use std::sync::Arc;
+
+    use iced::Task;
+    use parking_lot::Mutex;
+    use tracing_test::traced_test;
+
+    use crate::{
+        headless::Headless,
+        message::{
+            widget::{DragNode, WidgetEvent},
+            Command,
+        },
+        testing::Harness,
+    };
+
+    #[traced_test]
+    #[test]
+    fn drag_and_drop() {
+        let mut harness = Harness::new(
+            r#"-[col#todo<drop-target:true>[text#card<draggable:true>("Card")], col#done<drop-target:true>[text("Done")]]"#,
+        )
+        .unwrap();
+
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let handler_dropped = dropped.clone();
+        let _done = harness.engine().on_widget_event("#done", move |event| {
+            if let WidgetEvent::DroppedOn { source, target } = event {
+                handler_dropped.lock().push((source, target));
+            }
+            Task::none()
+        });
+
+        let card = harness.describe().unwrap().find("card").unwrap().node_id;
+        let done = harness.describe().unwrap().find("done").unwrap().node_id;
+
+        // Releasing over a target without a drag in progress doesn't drop
+        drop_on(harness.headless(), done);
+        assert!(dropped.lock().is_empty());
+
+        let source = DragNode {
+            node_id: card,
+            element_id: Some("card".into()),
+        };
+        harness
+            .event(
+                "#card",
+                "text",
+                WidgetEvent::DragStarted {
+                    source: source.clone(),
+                },
+            )
+            .unwrap();
+        drop_on(harness.headless(), done);
+
+        let dropped = dropped.lock();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].0, source);
+        assert_eq!(dropped[0].1.element_id.as_deref(), Some("done"));
+    }
+
+    fn drop_on(headless: &mut Headless, node_id: crate::NodeId) {
+        headless.send(salish::Message::broadcast(Command::Drop(DragNode {
+            node_id,
+            element_id: Some("done".into()),
+        })));
+    }
+}
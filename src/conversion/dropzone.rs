@@ -0,0 +1,213 @@
+//! Dropping files from the operating system onto the application
+//!
+//! A `dropzone` wraps an element, and is highlighted while files are dragged over the window. Each file dropped
+//! onto the window is sent as a [`WidgetEvent::FileDropped`] event from every `dropzone` in the tree, which can
+//! be published to a topic with an `on-drop` handler:
+//!
+//! ```text
+//! dropzone#open<padding:20, on-drop:publish("open")>(text("Drop files here"))
+//! ```
+//!
+//! The window doesn't report the position of dragged files, so the whole window accepts drops. The engine also
+//! publishes the path of each dropped file to the `window/file-dropped` topic, see [`crate::module::window`].
+
+use std::path::PathBuf;
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{
+    widget::{container, Container},
+    window, Border, Task,
+};
+use salish::Message;
+use tracing::debug;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    cache::WidgetContent,
+    conversion::drag,
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+    message::widget::{WidgetEvent, WidgetMessage},
+    node::Content,
+    IndexedTree, NodeRef,
+};
+
+/// Name of the dropzone widget in markup
+pub(crate) const DROPZONE_WIDGET: &str = "dropzone";
+
+fn is_dropzone(noderef: &NodeRef) -> bool {
+    matches!(noderef.node().data().content(), Content::Widget(name) if name == DROPZONE_WIDGET)
+}
+
+/// Update the dropzones of the tree from a file drag or drop event of the window. Hovering files highlight
+/// every dropzone, and dropped files are sent from each of them. Returns a [`Task`] sending the drop events.
+pub(crate) fn handle_file_event(tree: &IndexedTree, event: &window::Event) -> Task<Message> {
+    let (hovered, dropped): (bool, Option<&PathBuf>) = match event {
+        window::Event::FileHovered(_) => (true, None),
+        window::Event::FilesHoveredLeft => (false, None),
+        window::Event::FileDropped(path) => (false, Some(path)),
+        _ => return Task::none(),
+    };
+
+    let mut tasks = Vec::new();
+
+    tree.leaf_iter().filter(is_dropzone).for_each(|noderef| {
+        let changed = {
+            let node = noderef.node();
+            let attrs = &node.data().attrs;
+            let current = matches!(
+                attrs.get(AttributeKind::FileHovered),
+                Ok(Some(AttributeValue::FileHovered(true)))
+            );
+            current != hovered && attrs.set(AttributeValue::FileHovered(hovered)).is_ok()
+        };
+
+        if changed {
+            noderef.node_mut().data_mut().set_dirty(true);
+        }
+
+        if let Some(path) = dropped {
+            let node = noderef.node();
+            let data = node.data();
+            debug!("File {} dropped on node {}", path.display(), node.id());
+
+            tasks.push(Task::done(Message::broadcast(
+                WidgetMessage::new(
+                    node.id(),
+                    data.element_id.clone(),
+                    WidgetEvent::FileDropped(path.clone()),
+                )
+                .with_stable_id(data.stable_id().cloned()),
+            )));
+        }
+    });
+
+    Task::batch(tasks)
+}
+
+/// Build the widget of a `dropzone`, a container around its content highlighted while files are hovering
+pub(crate) fn build(
+    attrs: Attributes,
+    content: WidgetContent<Message>,
+) -> Result<DynamicWidget<Message>, ConversionError> {
+    let hovered = matches!(
+        attrs.get(AttributeKind::FileHovered)?,
+        Some(AttributeValue::FileHovered(true))
+    );
+
+    let mut zone = Container::new(content);
+
+    for attr in attrs {
+        zone = match attr.value().cloned() {
+            Some(AttributeValue::Padding(padding)) => zone.padding(padding),
+            Some(AttributeValue::WidthLength(width)) => zone.width(width),
+            Some(AttributeValue::WidthPixels(width)) => zone.width(width),
+            Some(AttributeValue::HeightLength(height)) => zone.height(height),
+            Some(AttributeValue::HeightPixels(height)) => zone.height(height),
+            Some(AttributeValue::HorizontalAlignment(horizontal)) => zone.align_x(horizontal),
+            Some(AttributeValue::VerticalAlignment(vertical)) => zone.align_y(vertical),
+            // Set by the engine while files are hovering
+            Some(AttributeValue::FileHovered(_)) => zone,
+            // Published by the engine from the drop message
+            Some(AttributeValue::OnDrop(_)) => zone,
+            // Applied by the widget cache
+            _ if drag::is_drag(attr.kind()) => zone,
+            _ => {
+                return Err(ConversionError::UnsupportedAttribute(
+                    attr,
+                    "Dropzone".into(),
+                ))
+            }
+        };
+    }
+
+    let zone = zone.style(move |theme: &iced::Theme| {
+        let palette = theme.extended_palette();
+        if hovered {
+            container::Style {
+                background: Some(palette.primary.weak.color.scale_alpha(0.3).into()),
+                border: Border::default()
+                    .color(palette.primary.strong.color)
+                    .width(2)
+                    .rounded(4),
+                ..Default::default()
+            }
+        } else {
+            container::Style {
+                border: Border::default()
+                    .color(palette.background.strong.color)
+                    .width(1)
+                    .rounded(4),
+                ..Default::default()
+            }
+        }
+    });
+
+    Ok(DynamicWidget::default().with_widget(zone))
+}
+
+#[cfg(all(test, not(target_arch = "wasm32"), feature = "headless"))]
+mod tests {
+    use std::{path::PathBuf, sync::Arc};
+
+    use iced::{window, Task};
+    use parking_lot::Mutex;
+    use tracing_test::traced_test;
+
+    use crate::{
+        headless::Headless,
+        message::{widget::WidgetEvent, Command},
+    };
+
+    #[traced_test]
+    #[test]
+    fn drop_files() {
+        let mut headless = Headless::new().unwrap();
+        headless
+            .load(r#"{dropzone#open<on-drop:publish("open")>(text("Drop files here"))}"#)
+            .unwrap();
+
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+        let handler_dropped = dropped.clone();
+        let _open = headless.engine().on_widget_event("#open", move |event| {
+            if let WidgetEvent::FileDropped(path) = event {
+                handler_dropped.lock().push(path);
+            }
+            Task::none()
+        });
+
+        let published = Arc::new(Mutex::new(Vec::new()));
+        let handler_published = published.clone();
+        let _topic = headless.engine().on_topic("open", move |message| {
+            handler_published
+                .lock()
+                .push(message.as_str().map(String::from));
+            Task::none()
+        });
+
+        let path = PathBuf::from("samples/ferris.png");
+        headless.send(salish::Message::broadcast(Command::Window(
+            window::Event::FileHovered(path.clone()),
+        )));
+        let tree = headless.describe().unwrap();
+        assert!(tree
+            .find("open")
+            .unwrap()
+            .attrs
+            .contains("FileHovered(true)"));
+
+        headless.send(salish::Message::broadcast(Command::Window(
+            window::Event::FileDropped(path.clone()),
+        )));
+        let tree = headless.describe().unwrap();
+        assert!(tree
+            .find("open")
+            .unwrap()
+            .attrs
+            .contains("FileHovered(false)"));
+        assert_eq!(*dropped.lock(), vec![path.clone()]);
+
+        // The on-drop handler publishes the path of the dropped file
+        assert_eq!(*published.lock(), vec![Some(path.display().to_string())]);
+    }
+}
@@ -0,0 +1,220 @@
+//! Evaluation of attributes computed from expressions, see [`crate::attribute::expression`]
+
+use std::borrow::Cow;
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{Length, Size};
+use tracing::debug;
+
+use crate::{
+    attribute::{Attribute, AttributeKind, AttributeValue, Attributes},
+    error::ConversionError,
+    module::{state::StateStore, window::WindowSize},
+    parser::{attribute::AttributeParser, expr::ExprValue},
+    NodeRef,
+};
+
+/// Evaluate the attributes of a node computed from expressions, and parse each result as an attribute of the
+/// same name. Variables other than the sizes of the window and parent are read from `state`. Returns the same
+/// set if no attributes are computed.
+pub(crate) fn evaluate(
+    noderef: &NodeRef,
+    attrs: &Attributes,
+    state: &StateStore,
+    window: &WindowSize,
+) -> Result<Attributes, ConversionError> {
+    if !attrs.is_computed() {
+        return Ok(attrs.clone());
+    }
+
+    let mut evaluated = Attributes::new();
+
+    for attr in attrs {
+        let Some(AttributeValue::Expression(expression)) = attr.value() else {
+            evaluated.push(attr)?;
+            continue;
+        };
+
+        let result = expression
+            .expr()
+            .eval(&|name| variable(noderef, state, window, name))?;
+
+        let text = format!("{}:{result}", expression.name());
+        debug!("Expression {expression} evaluated to {text}");
+
+        let value = AttributeParser::parse_attributes(&text)
+            .map_err(|e| ConversionError::InvalidType(format!("{text}: {e}")))?
+            .into_iter()
+            .find_map(|attr| attr.value().cloned());
+
+        match value {
+            // The result is a literal value, rather than another expression
+            Some(value) if !matches!(value, AttributeValue::Expression(_)) => {
+                evaluated.push(Attribute::from(value))?;
+            }
+            _ => {
+                return Err(ConversionError::InvalidType(format!(
+                    "{text} is not a value of {}",
+                    expression.name()
+                )))
+            }
+        }
+    }
+
+    Ok(evaluated)
+}
+
+/// Variables resolved from the sizes of the window and parent, rather than the state store
+const SIZE_VARIABLES: [&str; 4] = [
+    "window.width",
+    "window.height",
+    "parent.width",
+    "parent.height",
+];
+
+/// Get the state keys read by the expressions of the attributes
+pub(crate) fn state_keys(attrs: &Attributes) -> Vec<String> {
+    attrs
+        .into_iter()
+        .filter_map(|attr| match attr.value() {
+            Some(AttributeValue::Expression(expression)) => Some(
+                expression
+                    .expr()
+                    .variables()
+                    .into_iter()
+                    .filter(|name| !SIZE_VARIABLES.contains(name))
+                    .map(String::from)
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// Get the value of a variable of an expression
+fn variable(
+    noderef: &NodeRef,
+    state: &StateStore,
+    window: &WindowSize,
+    name: &str,
+) -> Option<ExprValue> {
+    let window = window.get().unwrap_or(Size::ZERO);
+
+    let number = match name {
+        "window.width" => window.width,
+        "window.height" => window.height,
+        "parent.width" => {
+            parent_length(noderef, AttributeKind::WidthPixels).unwrap_or(window.width)
+        }
+        "parent.height" => {
+            parent_length(noderef, AttributeKind::HeightPixels).unwrap_or(window.height)
+        }
+        name => {
+            let value = state.get(name)?;
+            let text: Cow<'_, str> = value.inner().into();
+            return Some(ExprValue::from_text(&text));
+        }
+    };
+
+    Some(ExprValue::Number(number as f64))
+}
+
+/// Get the fixed width or height of the nearest ancestor with one
+fn parent_length(noderef: &NodeRef, kind: AttributeKind) -> Option<f32> {
+    let mut current = noderef.node().parent().cloned();
+
+    while let Some(parent) = current {
+        let length = match parent.node().data().attrs.get(kind) {
+            Ok(Some(
+                AttributeValue::WidthPixels(pixels) | AttributeValue::HeightPixels(pixels),
+            )) => Some(pixels.0),
+            _ => None,
+        };
+
+        // Lengths declared as fixed(..)
+        let length = length.or_else(|| {
+            let kind = match kind {
+                AttributeKind::WidthPixels => AttributeKind::WidthLength,
+                _ => AttributeKind::HeightLength,
+            };
+            match parent.node().data().attrs.get(kind) {
+                Ok(Some(
+                    AttributeValue::WidthLength(Length::Fixed(length))
+                    | AttributeValue::HeightLength(Length::Fixed(length)),
+                )) => Some(length),
+                _ => None,
+            }
+        });
+
+        if length.is_some() {
+            return length;
+        }
+        current = parent.node().parent().cloned();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::TreeNodeRef as _;
+    use iced::Pixels;
+    use tracing_test::traced_test;
+
+    use super::{evaluate, state_keys};
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        module::{state::StateStore, window::WindowSize},
+        Message, SnowcapParser, Value,
+    };
+
+    #[traced_test]
+    #[test]
+    fn evaluate_expressions() {
+        let state = StateStore::default();
+        let _ = state.set("expr_pad", Value::new_integer(6));
+
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{<width:600>{<width: 200 + 2 * expr_pad, size: parent.width / 3, clip:(expr_pad > 4)> text("x")}}"#,
+        )
+        .unwrap()
+        .index();
+
+        let outer = tree.root().node().children().unwrap()[0].clone();
+        let inner = outer.node().children().unwrap()[0].clone();
+        let attrs = inner.node().data().attrs.clone();
+        assert!(attrs.is_computed());
+
+        let evaluated = evaluate(&inner, &attrs, &state, &WindowSize::default()).unwrap();
+        assert!(!evaluated.is_computed());
+        assert_eq!(
+            evaluated.get(AttributeKind::WidthPixels).unwrap(),
+            Some(AttributeValue::WidthPixels(Pixels(212.0)))
+        );
+        assert_eq!(
+            evaluated.get(AttributeKind::Size).unwrap(),
+            Some(AttributeValue::Size(Pixels(200.0)))
+        );
+        assert_eq!(
+            evaluated.get(AttributeKind::Clip).unwrap(),
+            Some(AttributeValue::Clip(true))
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn expression_state_keys() {
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{<width:600>{<width: 200 + 2 * expr_pad, size: max(parent.width / 3, min_size)> text("x")}}"#,
+        )
+        .unwrap()
+        .index();
+
+        let outer = tree.root().node().children().unwrap()[0].clone();
+        let inner = outer.node().children().unwrap()[0].clone();
+        let mut keys = state_keys(&inner.node().data().attrs);
+        keys.sort();
+        assert_eq!(keys, vec!["expr_pad".to_string(), "min_size".to_string()]);
+    }
+}
@@ -0,0 +1,279 @@
+//! Virtualized columns for large collections
+//!
+//! A `lazy-col` scrolls its items like a `scrollable` column, but only builds widgets for the items visible in
+//! its viewport. Every item has the same height, which is set with `item-height`.
+//!
+//! ```text
+//! lazy-col<item-height:24, height:400, spacing:4>[
+//!     text("Item 1"),
+//!     text("Item 2"),
+//!     ...
+//! ]
+//! ```
+//!
+//! The range of visible items is computed from the [`Viewport`] reported as the column is scrolled, and stored in the
+//! attributes of the `lazy-col` node. Items outside the range keep their nodes and module data in the tree, but the
+//! [`crate::cache::WidgetCache`] drops their widgets, and the space they would take is left empty. Only the items
+//! entering or leaving the range are marked dirty when it changes.
+//!
+//! Until the viewport is reported, the visible range is taken from a `height` in pixels.
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{
+    widget::{scrollable::Viewport, Column, Container, Scrollable, Space},
+    Length,
+};
+use salish::Message;
+use tracing::{debug, warn};
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    conversion::drag,
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+    message::widget::{WidgetEvent, WidgetMessage},
+    node::{Content, SnowcapNode},
+    scroll::scrollable_id,
+    NodeId, NodeRef,
+};
+
+/// Height of items of a `lazy-col` without an `item-height`
+const DEFAULT_ITEM_HEIGHT: f32 = 32.0;
+
+/// Number of items built before the viewport is known, if the column has no height in pixels
+const INITIAL_ITEMS: usize = 32;
+
+/// Number of items built above and below the viewport, so items are ready as they scroll into view
+const OVERSCAN: usize = 4;
+
+/// Returns true if the node is a `lazy-col`
+fn is_lazy_column(noderef: &NodeRef) -> bool {
+    matches!(noderef.node().data().content(), Content::LazyColumn)
+}
+
+/// Distance between the tops of consecutive items
+fn stride(attrs: &Attributes) -> f32 {
+    let item_height = match attrs.get(AttributeKind::ItemHeight) {
+        Ok(Some(AttributeValue::ItemHeight(height))) => height.0,
+        _ => DEFAULT_ITEM_HEIGHT,
+    };
+
+    let spacing = match attrs.get(AttributeKind::Spacing) {
+        Ok(Some(AttributeValue::Spacing(spacing))) => spacing.0,
+        _ => 0.0,
+    };
+
+    item_height + spacing
+}
+
+/// Get the range of items visible in a viewport at a vertical offset, including the overscan
+pub(crate) fn visible_range(offset: f32, height: f32, stride: f32, count: usize) -> (usize, usize) {
+    let stride = stride.max(1.0);
+
+    let first = (offset.max(0.0) / stride).floor() as usize;
+    let last = ((offset.max(0.0) + height.max(0.0)) / stride).ceil() as usize;
+
+    (
+        first.saturating_sub(OVERSCAN).min(count),
+        (last + OVERSCAN).min(count),
+    )
+}
+
+/// Get the range of visible items of a `lazy-col` with `count` items
+pub(crate) fn range(attrs: &Attributes, count: usize) -> (usize, usize) {
+    match attrs.get(AttributeKind::VisibleRange) {
+        Ok(Some(AttributeValue::VisibleRange(start, end))) => (start.min(count), end.min(count)),
+        _ => match attrs.get(AttributeKind::HeightPixels) {
+            Ok(Some(AttributeValue::HeightPixels(height))) => {
+                visible_range(0.0, height.0, stride(attrs), count)
+            }
+            _ => (0, INITIAL_ITEMS.min(count)),
+        },
+    }
+}
+
+/// Returns true if the node is an item of a `lazy-col` outside its visible range, or a descendant of one
+pub(crate) fn is_offscreen(noderef: &NodeRef) -> bool {
+    let mut current = noderef.clone();
+
+    loop {
+        let parent = match current.node().parent() {
+            Some(parent) => parent.clone(),
+            None => return false,
+        };
+
+        if is_lazy_column(&parent) {
+            let node_id = current.node().id();
+            let parent_node = parent.node();
+
+            if let Some(children) = parent_node.children() {
+                let (start, end) = range(&parent_node.data().attrs, children.len());
+                let index = children
+                    .iter()
+                    .position(|child| child.node().id() == node_id);
+
+                if index.is_some_and(|index| index < start || index >= end) {
+                    return true;
+                }
+            }
+        }
+
+        current = parent;
+    }
+}
+
+/// Mark a node and all of its descendants as dirty
+fn mark_subtree(noderef: &NodeRef) {
+    noderef.node_mut().data_mut().set_dirty(true);
+
+    let children = noderef
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+
+    for child in children {
+        mark_subtree(&child);
+    }
+}
+
+/// Store the visible range of a `lazy-col`, and mark the items entering or leaving the range as dirty.
+/// Returns false if the range didn't change.
+pub(crate) fn set_range(noderef: &NodeRef, range: (usize, usize)) -> bool {
+    let children = noderef
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+
+    let attrs = noderef.node().data().attrs.clone();
+    let previous = self::range(&attrs, children.len());
+
+    if previous == range {
+        return false;
+    }
+
+    debug!(
+        "Node {} visible items {}..{}",
+        noderef.node().id(),
+        range.0,
+        range.1
+    );
+
+    if let Err(e) = attrs.set(AttributeValue::VisibleRange(range.0, range.1)) {
+        warn!("Failed to set visible range: {e}");
+        return false;
+    }
+
+    let visible = |(start, end): (usize, usize), index: usize| index >= start && index < end;
+
+    for (index, child) in children.iter().enumerate() {
+        if visible(previous, index) != visible(range, index) {
+            mark_subtree(child);
+        }
+    }
+
+    noderef.node_mut().data_mut().set_dirty(true);
+    true
+}
+
+/// Update the visible range of a `lazy-col` from the viewport reported when it is scrolled
+pub(crate) fn set_viewport(noderef: &NodeRef, viewport: &Viewport) -> bool {
+    if !is_lazy_column(noderef) {
+        return false;
+    }
+
+    let attrs = noderef.node().data().attrs.clone();
+    let range = visible_range(
+        viewport.absolute_offset().y,
+        viewport.bounds().height,
+        stride(&attrs),
+        noderef.node().num_children(),
+    );
+
+    set_range(noderef, range)
+}
+
+pub struct SnowcapLazyColumn;
+
+impl SnowcapLazyColumn {
+    /// Build a `lazy-col` from the widgets of its visible items, with space in place of the items outside the range
+    pub fn convert(
+        node_id: NodeId,
+        data: &SnowcapNode,
+        attrs: Attributes,
+        items: Vec<DynamicWidget<Message>>,
+        count: usize,
+    ) -> Result<DynamicWidget<Message>, ConversionError> {
+        let stride = stride(&attrs);
+        let (start, end) = range(&attrs, count);
+
+        let element_id = data.element_id.clone();
+        let stable_id = data.stable_id().cloned();
+        let id = stable_id.as_ref().map(scrollable_id);
+
+        let mut col = Column::new().push(Space::with_height(start as f32 * stride));
+        for item in items {
+            // Items are given the full stride, so the spacing is below each item
+            col = col.push(Container::new(item.into_element()?).height(stride));
+        }
+        col = col.push(Space::with_height(
+            count.saturating_sub(end) as f32 * stride,
+        ));
+
+        let mut scroll = Scrollable::new(col.width(Length::Fill)).on_scroll(move |viewport| {
+            Message::broadcast(
+                WidgetMessage::new(node_id, element_id.clone(), WidgetEvent::Scrolled(viewport))
+                    .with_stable_id(stable_id.clone()),
+            )
+        });
+
+        if let Some(id) = id {
+            scroll = scroll.id(id);
+        }
+
+        for attr in attrs {
+            scroll = match attr.value().cloned() {
+                Some(AttributeValue::HeightLength(height)) => scroll.height(height),
+                Some(AttributeValue::HeightPixels(height)) => scroll.height(height),
+                Some(AttributeValue::WidthLength(width)) => scroll.width(width),
+                Some(AttributeValue::WidthPixels(width)) => scroll.width(width),
+                // Layout of the items, used above
+                Some(AttributeValue::ItemHeight(_))
+                | Some(AttributeValue::Spacing(_))
+                | Some(AttributeValue::VisibleRange(_, _)) => scroll,
+                // Applied by the widget cache
+                _ if drag::is_drag(attr.kind()) => scroll,
+                _ => {
+                    return Err(ConversionError::UnsupportedAttribute(
+                        attr,
+                        "LazyColumn".into(),
+                    ))
+                }
+            };
+        }
+
+        Ok(DynamicWidget::default().with_widget(scroll))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::visible_range;
+
+    #[traced_test]
+    #[test]
+    fn lazy_column_range() {
+        // 10 items of 20px are visible in a 200px viewport, with overscan below
+        assert_eq!(visible_range(0.0, 200.0, 20.0, 1000), (0, 14));
+
+        // Scrolled to item 100
+        assert_eq!(visible_range(2000.0, 200.0, 20.0, 1000), (96, 114));
+
+        // Clamped to the number of items
+        assert_eq!(visible_range(19_900.0, 200.0, 20.0, 1000), (991, 1000));
+        assert_eq!(visible_range(0.0, 200.0, 20.0, 5), (0, 5));
+    }
+}
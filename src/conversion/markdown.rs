@@ -0,0 +1,113 @@
+//! Markdown widget styling
+//!
+//! The style of a markdown widget is derived from the palette of the theme it is rendered with, see
+//! [`super::theme::with_theme()`].
+//!
+//! ```text
+//! markdown<size:16, heading-size:36, code-size:14, code-color:#c0caf5, code-bg:#1a1b26, link-color:#7aa2f7>(file!{path:"README.md"})
+//! ```
+//!
+//! Link clicks are sent as [`WidgetEvent::Markdown`](crate::message::widget::WidgetEvent::Markdown) messages,
+//! and can be published to a topic with `on-link:publish("links")`.
+
+use iced::{
+    widget::markdown::{Settings, Style},
+    Background, Pixels, Theme,
+};
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    SyncError,
+};
+
+/// Name of the markdown widget in the grammar
+pub(crate) const MARKDOWN_WIDGET: &str = "markdown";
+
+/// Sizes of the h1 to h6 headings relative to `heading-size`
+const HEADING_SCALE: [f32; 6] = [1.0, 0.875, 0.75, 0.625, 0.5625, 0.5];
+
+/// Get the [`Settings`] of a markdown widget from its attributes
+pub(crate) fn settings(attrs: &Attributes) -> Result<Settings, SyncError> {
+    let mut settings = match attrs.get(AttributeKind::Size)? {
+        Some(AttributeValue::Size(size)) => Settings::with_text_size(size),
+        _ => Settings::default(),
+    };
+
+    if let Some(AttributeValue::HeadingSize(size)) = attrs.get(AttributeKind::HeadingSize)? {
+        let [h1, h2, h3, h4, h5, h6] = HEADING_SCALE.map(|scale| Pixels(size.0 * scale));
+        settings.h1_size = h1;
+        settings.h2_size = h2;
+        settings.h3_size = h3;
+        settings.h4_size = h4;
+        settings.h5_size = h5;
+        settings.h6_size = h6;
+    }
+
+    if let Some(AttributeValue::CodeSize(size)) = attrs.get(AttributeKind::CodeSize)? {
+        settings.code_size = size;
+    }
+
+    Ok(settings)
+}
+
+/// Get the [`Style`] of a markdown widget from the palette of its theme, and its attributes
+pub(crate) fn style(attrs: &Attributes) -> Result<Style, SyncError> {
+    let theme = match attrs.get(AttributeKind::Theme)? {
+        Some(AttributeValue::Theme(theme)) => theme,
+        _ => Theme::Light,
+    };
+
+    let mut style = Style::from_palette(theme.palette());
+
+    for attr in attrs {
+        match attr.value() {
+            Some(AttributeValue::CodeColor(color)) => style.inline_code_color = *color,
+            Some(AttributeValue::CodeBackground(color)) => {
+                style.inline_code_highlight.background = Background::Color(*color)
+            }
+            Some(AttributeValue::LinkColor(color)) => style.link_color = *color,
+            _ => {}
+        }
+    }
+
+    Ok(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::{Color, Pixels, Theme};
+    use tracing_test::traced_test;
+
+    use super::{settings, style};
+    use crate::{conversion::theme::with_theme, parser::attribute::AttributeParser};
+
+    #[traced_test]
+    #[test]
+    fn markdown_style() {
+        let attrs =
+            AttributeParser::parse_attributes("heading-size:40, code-size:12, link-color:#ff0000")
+                .unwrap();
+
+        let settings = settings(&attrs).unwrap();
+        assert_eq!(settings.h1_size, Pixels(40.0));
+        assert_eq!(settings.h3_size, Pixels(30.0));
+        assert_eq!(settings.code_size, Pixels(12.0));
+
+        let attrs = with_theme(&attrs, Theme::Dark).unwrap();
+        assert_eq!(
+            style(&attrs).unwrap().link_color,
+            Color::from_rgb(1.0, 0.0, 0.0)
+        );
+
+        // Without a link color, links use the primary color of the theme
+        let attrs = with_theme(
+            &AttributeParser::parse_attributes("size:14").unwrap(),
+            Theme::Dark,
+        )
+        .unwrap();
+        assert_eq!(
+            style(&attrs).unwrap().link_color,
+            Theme::Dark.palette().primary
+        );
+    }
+}
@@ -1,10 +1,28 @@
 pub(crate) mod alignment;
+pub(crate) mod animation;
+pub(crate) mod cascade;
+pub(crate) mod code;
 pub(crate) mod column;
+pub(crate) mod constraint;
 pub(crate) mod container;
+pub(crate) mod drag;
+pub(crate) mod dropzone;
 pub(crate) mod dynamic_widget;
+pub(crate) mod expression;
+pub(crate) mod lazy_column;
+pub(crate) mod markdown;
+pub(crate) mod multi_select;
+pub(crate) mod pick_list;
+pub(crate) mod registry;
+pub(crate) mod responsive;
 pub(crate) mod row;
+pub(crate) mod rule;
+pub(crate) mod slider;
 pub(crate) mod stack;
+pub(crate) mod style;
+pub(crate) mod text_input;
 pub(crate) mod theme;
+pub(crate) mod video;
 pub(crate) mod widget;
 
 /*
@@ -0,0 +1,150 @@
+//! Multi-select widget, rendering the selected options as removable chips above a pick list of the remaining options
+//!
+//! ```text
+//! multi-select#tags<selected:["rust", "iced"]>(["rust", "iced", "pest", "tokio"])
+//! ```
+
+use iced::{
+    widget::{button, Button, Column, PickList, Row, Text},
+    Element,
+};
+use salish::Message;
+use tracing::warn;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+    identity::StableId,
+    message::widget::{WidgetEvent, WidgetMessage},
+    util::ElementWrapper,
+    NodeId,
+};
+
+/// Builds a multi-select widget. The selection is stored in the [`AttributeValue::SelectedList`] attribute
+/// of the node, and each change emits a [`WidgetEvent::SelectionAdded`] or [`WidgetEvent::SelectionRemoved`] event.
+#[derive(Debug, Clone)]
+pub(crate) struct MultiSelect {
+    node_id: NodeId,
+    element_id: Option<String>,
+    stable_id: Option<StableId>,
+    attrs: Attributes,
+}
+
+impl MultiSelect {
+    pub fn new(
+        node_id: NodeId,
+        element_id: Option<String>,
+        stable_id: Option<StableId>,
+        attrs: Attributes,
+    ) -> Self {
+        Self {
+            node_id,
+            element_id,
+            stable_id,
+            attrs,
+        }
+    }
+
+    /// Get the currently selected options
+    pub fn selected(&self) -> Result<Vec<String>, ConversionError> {
+        Ok(match self.attrs.get(AttributeKind::SelectedList)? {
+            Some(AttributeValue::SelectedList(selected)) => selected,
+            _ => Vec::new(),
+        })
+    }
+
+    /// Update the selection with `f`, and return the message for `event`
+    fn update(&self, f: impl FnOnce(&mut Vec<String>), event: WidgetEvent) -> Message {
+        let mut selected = self.selected().unwrap_or_default();
+        f(&mut selected);
+
+        if let Err(e) = self.attrs.set(AttributeValue::SelectedList(selected)) {
+            warn!("Failed to set multi-select selection: {e}");
+        }
+
+        Message::broadcast(
+            WidgetMessage::new(self.node_id, self.element_id.clone(), event)
+                .with_stable_id(self.stable_id.clone()),
+        )
+    }
+
+    /// Add an option to the selection
+    pub fn add(&self, option: String) -> Message {
+        let added = option.clone();
+        self.update(
+            move |selected| {
+                if !selected.contains(&added) {
+                    selected.push(added)
+                }
+            },
+            WidgetEvent::SelectionAdded(option),
+        )
+    }
+
+    /// Remove an option from the selection
+    pub fn remove(&self, option: String) -> Message {
+        let removed = option.clone();
+        self.update(
+            move |selected| selected.retain(|s| *s != removed),
+            WidgetEvent::SelectionRemoved(option),
+        )
+    }
+
+    /// Build the widget for the supplied options
+    pub fn build(self, options: Vec<String>) -> Result<DynamicWidget<Message>, ConversionError> {
+        let selected = self.selected()?;
+
+        // Render each selected option as a chip which removes it when pressed
+        let chips = selected
+            .iter()
+            .fold(Row::new().spacing(4), |row, option| {
+                let chip = self.clone();
+                let value = option.clone();
+                row.push(
+                    Button::new(Text::new(format!("{option} ×")).size(12))
+                        .padding([2, 8])
+                        .style(button::secondary)
+                        .on_press_with(move || chip.remove(value.clone())),
+                )
+            })
+            .wrap();
+
+        let remaining: Vec<String> = options
+            .into_iter()
+            .filter(|option| !selected.contains(option))
+            .collect();
+
+        let add = self.clone();
+        let picklist = PickList::new(remaining, None::<String>, move |option| add.add(option))
+            .placeholder("Add...");
+
+        let element: Element<'static, Message> =
+            Column::new().push(chips).push(picklist).spacing(4).into();
+
+        Ok(DynamicWidget::default().with_widget(ElementWrapper::new(element)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::MultiSelect;
+    use crate::parser::attribute::AttributeParser;
+
+    #[traced_test]
+    #[test]
+    fn selection_changes() {
+        let attrs = AttributeParser::parse_attributes(r#"selected:["rust", "iced"]"#).unwrap();
+        let multi = MultiSelect::new(0, None, None, attrs);
+        assert_eq!(multi.selected().unwrap(), vec!["rust", "iced"]);
+
+        multi.add("pest".into());
+        multi.add("rust".into());
+        assert_eq!(multi.selected().unwrap(), vec!["rust", "iced", "pest"]);
+
+        multi.remove("iced".into());
+        assert_eq!(multi.selected().unwrap(), vec!["rust", "pest"]);
+    }
+}
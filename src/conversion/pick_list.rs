@@ -0,0 +1,212 @@
+//! Pick list of strings, numbers or labelled values
+//!
+//! ```text
+//! pick-list#language<selected:"en">([{label:"English", value:"en"}, {label:"Français", value:"fr"}])
+//! pick-list#quantity([1, 2, 5, 10])
+//! ```
+//!
+//! A labelled option shows its label and selects its value, and other options show their value as text. Selecting
+//! an option sends [`WidgetEvent::PickListSelected`] with its value, and stores the value as text in the
+//! [`AttributeValue::Selected`] attribute. The `selected` attribute matches either the value or the label
+//! of an option.
+//!
+//! Pick lists can also be focused and navigated with the keyboard, from the engine.
+
+use std::borrow::Cow;
+
+use iced::{
+    widget::{pick_list, PickList},
+    Theme,
+};
+use salish::Message;
+use tracing::warn;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+    focus,
+    identity::StableId,
+    message::widget::{WidgetEvent, WidgetMessage},
+    parser::value::ValueData,
+    NodeId, Value,
+};
+
+/// Get the text of an option value, as stored in the `selected` attribute
+pub(crate) fn value_text(value: &Value) -> String {
+    match value.inner() {
+        ValueData::Array(_) | ValueData::AttributeKind(_) => value.to_string(),
+        data => {
+            let text: Cow<'_, str> = data.into();
+            text.into_owned()
+        }
+    }
+}
+
+/// An option of a pick list, showing its label and selecting its value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PickListOption {
+    label: String,
+    value: Value,
+}
+
+impl PickListOption {
+    /// Get the option of a value from the content of a pick list
+    pub fn from_value(value: &Value) -> Self {
+        match value.labelled() {
+            Ok((label, value)) => Self {
+                label: label.to_string(),
+                value: value.clone(),
+            },
+            Err(_) => Self {
+                label: value_text(value),
+                value: value.clone(),
+            },
+        }
+    }
+
+    /// Returns true if the value or the label of this option is the selected text
+    fn is_selected(&self, selected: &str) -> bool {
+        value_text(&self.value) == selected || self.label == selected
+    }
+}
+
+impl std::fmt::Display for PickListOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// Builds a pick list widget from its options
+#[derive(Debug, Clone)]
+pub(crate) struct PickListWidget {
+    node_id: NodeId,
+    element_id: Option<String>,
+    stable_id: Option<StableId>,
+    attrs: Attributes,
+}
+
+impl PickListWidget {
+    pub fn new(
+        node_id: NodeId,
+        element_id: Option<String>,
+        stable_id: Option<StableId>,
+        attrs: Attributes,
+    ) -> Self {
+        Self {
+            node_id,
+            element_id,
+            stable_id,
+            attrs,
+        }
+    }
+
+    /// Get the selected option
+    pub fn current(
+        &self,
+        options: &[PickListOption],
+    ) -> Result<Option<PickListOption>, ConversionError> {
+        let Some(AttributeValue::Selected(selected)) = self.attrs.get(AttributeKind::Selected)?
+        else {
+            return Ok(None);
+        };
+
+        Ok(options
+            .iter()
+            .find(|option| option.is_selected(&selected))
+            .cloned())
+    }
+
+    /// Store the selected option in the attributes, and get the message of the selection
+    pub fn select(&self, option: PickListOption) -> Message {
+        if let Err(e) = self
+            .attrs
+            .set(AttributeValue::Selected(value_text(&option.value)))
+        {
+            warn!("Failed to set selected option: {e}");
+        }
+
+        Message::broadcast(
+            WidgetMessage::new(
+                self.node_id,
+                self.element_id.clone(),
+                WidgetEvent::PickListSelected(option.value),
+            )
+            .with_stable_id(self.stable_id.clone()),
+        )
+    }
+
+    /// Build the pick list from the values of its content
+    pub fn build(self, values: &[Value]) -> Result<DynamicWidget<Message>, ConversionError> {
+        let options: Vec<PickListOption> = values.iter().map(PickListOption::from_value).collect();
+        let current = self.current(&options)?;
+        let focused = focus::is_focused(&self.attrs);
+
+        let picklist = PickList::new(options, current, move |option| self.select(option));
+
+        // Keyboard focus is shown with a border in the primary color
+        let picklist = if focused {
+            picklist.style(|theme: &Theme, status| {
+                let mut style = pick_list::default(theme, status);
+                style.border.color = theme.extended_palette().primary.strong.color;
+                style.border.width = 2.0;
+                style
+            })
+        } else {
+            picklist
+        };
+
+        Ok(DynamicWidget::default().with_widget(picklist))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{PickListOption, PickListWidget};
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        parser::{attribute::AttributeParser, value::ValueParser, ParserContext},
+        Value,
+    };
+
+    #[traced_test]
+    #[test]
+    fn labelled_options() {
+        let values = ValueParser::parse_str(
+            r#"[{label:"One", value:1}, {label:"Two", value:2}, 3]"#,
+            &ParserContext::default(),
+        )
+        .unwrap();
+        let options: Vec<PickListOption> = values
+            .array()
+            .unwrap()
+            .iter()
+            .map(PickListOption::from_value)
+            .collect();
+
+        assert_eq!(options[0].to_string(), "One");
+        assert_eq!(options[0].value, Value::new_integer(1));
+        assert_eq!(options[2].to_string(), "3");
+
+        // The selected attribute matches either the value or the label
+        let attrs = AttributeParser::parse_attributes(r#"selected:"Two""#).unwrap();
+        let picklist = PickListWidget::new(0, None, None, attrs.clone());
+        assert_eq!(
+            picklist.current(&options).unwrap(),
+            Some(options[1].clone())
+        );
+
+        // Selecting stores the value of the option
+        picklist.select(options[0].clone());
+        assert_eq!(
+            attrs.get(AttributeKind::Selected).unwrap(),
+            Some(AttributeValue::Selected("1".into()))
+        );
+        assert_eq!(
+            picklist.current(&options).unwrap(),
+            Some(options[0].clone())
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Registry of custom widgets provided by the application
+//!
+//! Applications register a factory for a widget name with [`crate::Snowcap::widgets()`], and markup can then use
+//! the name like any built-in widget. Names of built-in widgets always build the built-in widget. Each engine has
+//! its own registry.
+//!
+//! ```ignore
+//! snow.widgets().register("gauge", |node_id, attrs, content| {
+//!     let value = match content {
+//!         WidgetContent::Value(value) => value.float()? as f32,
+//!         _ => 0.0,
+//!     };
+//!     Ok(DynamicWidget::from(Gauge::new(value)))
+//! });
+//! ```
+
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::RwLock;
+use salish::Message;
+use tracing::debug;
+
+use crate::{
+    attribute::Attributes, cache::WidgetContent, dynamic_widget::DynamicWidget, ConversionError,
+    NodeId,
+};
+
+/// Factory building the widget of a node from its attributes and content
+pub type WidgetFactory = Arc<
+    dyn Fn(
+            NodeId,
+            Attributes,
+            WidgetContent<Message>,
+        ) -> Result<DynamicWidget<Message>, ConversionError>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+/// Handle to the registry of custom widgets of an engine, with the widget factories by widget name. Clones share
+/// the same factories.
+#[derive(Clone, Default)]
+pub struct WidgetRegistry(Arc<RwLock<HashMap<String, WidgetFactory>>>);
+
+impl std::fmt::Debug for WidgetRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.read().keys()).finish()
+    }
+}
+
+/// Handles are equal if they share the same factories
+impl PartialEq for WidgetRegistry {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for WidgetRegistry {}
+
+impl WidgetRegistry {
+    /// Register a factory for a widget name, replacing any factory already registered for it.
+    ///
+    /// Nodes which were already built with the name are built with the factory once they are rebuilt, such as by a
+    /// reload of the markup or [`crate::message::Command::ClearCache`].
+    pub fn register<F>(&self, name: &str, factory: F)
+    where
+        F: Fn(
+                NodeId,
+                Attributes,
+                WidgetContent<Message>,
+            ) -> Result<DynamicWidget<Message>, ConversionError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        debug!("Registering widget factory '{name}'");
+        self.0.write().insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// Remove the factory of a widget name. Returns true if a factory was registered.
+    pub fn unregister(&self, name: &str) -> bool {
+        self.0.write().remove(name).is_some()
+    }
+
+    /// Returns true if a factory is registered for a widget name
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.read().contains_key(name)
+    }
+
+    /// Get the factory registered for a widget name
+    pub(crate) fn get(&self, name: &str) -> Option<WidgetFactory> {
+        self.0.read().get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::widget::Text;
+    use tracing_test::traced_test;
+
+    use super::WidgetRegistry;
+    use crate::{
+        attribute::Attributes,
+        cache::WidgetContent,
+        conversion::widget::{SnowcapWidget, WidgetProviders},
+        dynamic_widget::DynamicWidget,
+        ConversionError,
+    };
+
+    #[traced_test]
+    #[test]
+    fn custom_widget() {
+        let registry = WidgetRegistry::default();
+        let build = |registry: &WidgetRegistry| {
+            SnowcapWidget::new(
+                1,
+                "test-gauge".into(),
+                None,
+                None,
+                Attributes::default(),
+                WidgetContent::None,
+                &WidgetProviders {
+                    registry: registry.clone(),
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert!(matches!(
+            build(&registry),
+            Err(ConversionError::UnsupportedWidget(_))
+        ));
+
+        registry.register("test-gauge", |_node_id, _attrs, _content| {
+            Ok(DynamicWidget::from(Text::new("gauge")))
+        });
+        assert!(registry.contains("test-gauge"));
+        assert!(build(&registry).is_ok());
+
+        // Registries of other engines don't share factories
+        assert!(build(&WidgetRegistry::default()).is_err());
+
+        assert!(registry.unregister("test-gauge"));
+        assert!(build(&registry).is_err());
+    }
+}
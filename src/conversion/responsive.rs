@@ -0,0 +1,316 @@
+//! Responsive layouts
+//!
+//! A `responsive` widget reports the [`Breakpoint`] of the width available to it, and attributes of its
+//! descendants mapping breakpoints to values resolve against it.
+//!
+//! ```text
+//! responsive(row<spacing:{xs:4, lg:16}>[
+//!     text<width:{xs:fill, md:200}>("Sidebar"),
+//!     text("Content")
+//! ])
+//! ```
+//!
+//! The width is measured during layout, and a [`WidgetEvent::Breakpoint`] message is published when the breakpoint
+//! changes. The engine stores the breakpoint in the attributes of the `responsive` node, and marks only the
+//! descendants with responsive attributes as dirty, so just those subtrees are rebuilt.
+//!
+//! The content is laid out directly rather than through [`iced::widget::responsive`], as cached widgets can't be
+//! rebuilt from inside a layout closure.
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{
+    advanced::{
+        layout, mouse, overlay, renderer,
+        widget::{tree, Operation, Tree},
+        Clipboard, Layout, Shell, Widget,
+    },
+    event, Element, Event, Length, Rectangle, Size, Vector,
+};
+use salish::Message;
+use tracing::{debug, warn};
+
+use crate::{
+    attribute::{breakpoint::Breakpoint, AttributeKind, AttributeValue},
+    identity::StableId,
+    message::widget::{WidgetEvent, WidgetMessage},
+    node::Content,
+    NodeId, NodeRef,
+};
+
+/// Name of the responsive widget in markup
+pub(crate) const RESPONSIVE_WIDGET: &str = "responsive";
+
+/// Returns true if the node is a `responsive` widget
+fn is_responsive(noderef: &NodeRef) -> bool {
+    matches!(noderef.node().data().content(), Content::Widget(name) if name == RESPONSIVE_WIDGET)
+}
+
+/// Get the breakpoint of the nearest `responsive` ancestor of a node, or [`Breakpoint::Xs`] if there is none
+pub(crate) fn breakpoint(noderef: &NodeRef) -> Breakpoint {
+    let mut current = noderef.node().parent().cloned();
+
+    while let Some(parent) = current {
+        if is_responsive(&parent) {
+            return match parent.node().data().attrs.get(AttributeKind::Breakpoint) {
+                Ok(Some(AttributeValue::Breakpoint(breakpoint))) => breakpoint,
+                _ => Breakpoint::default(),
+            };
+        }
+        current = parent.node().parent().cloned();
+    }
+
+    Breakpoint::default()
+}
+
+/// Store the breakpoint reported by a `responsive` node, and mark descendants with responsive attributes as dirty
+pub(crate) fn set_breakpoint(noderef: &NodeRef, breakpoint: Breakpoint) {
+    debug!("Node {} breakpoint {breakpoint}", noderef.node().id());
+
+    if let Err(e) = noderef
+        .node()
+        .data()
+        .attrs
+        .set(AttributeValue::Breakpoint(breakpoint))
+    {
+        warn!("Failed to set breakpoint: {e}");
+    }
+
+    mark_responsive(noderef);
+}
+
+/// Mark descendants with responsive attributes as dirty. Nested `responsive` widgets have their own breakpoint.
+fn mark_responsive(noderef: &NodeRef) {
+    let children = noderef
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+
+    for child in children {
+        if child.node().data().attrs.is_responsive() {
+            child.node_mut().data_mut().set_dirty(true);
+        }
+
+        if !is_responsive(&child) {
+            mark_responsive(&child);
+        }
+    }
+}
+
+/// State of a [`Responsive`] widget
+#[derive(Debug, Default)]
+struct State {
+    /// Breakpoint of the width measured in the last layout
+    current: Breakpoint,
+
+    /// Node and breakpoint last published, so the breakpoint is published again if the node is replaced
+    reported: Option<(NodeId, Breakpoint)>,
+}
+
+/// Widget reporting the [`Breakpoint`] of its available width
+pub(crate) struct Responsive {
+    node_id: NodeId,
+    element_id: Option<String>,
+    stable_id: Option<StableId>,
+    content: Element<'static, Message>,
+}
+
+impl Responsive {
+    pub fn new(
+        node_id: NodeId,
+        element_id: Option<String>,
+        stable_id: Option<StableId>,
+        content: Element<'static, Message>,
+    ) -> Self {
+        Self {
+            node_id,
+            element_id,
+            stable_id,
+            content,
+        }
+    }
+}
+
+impl Widget<Message, iced::Theme, iced::Renderer> for Responsive {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.content));
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        tree.state.downcast_mut::<State>().current = Breakpoint::from_width(limits.max().width);
+
+        let node = self
+            .content
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits);
+        layout::Node::with_children(node.size(), vec![node])
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        operation: &mut dyn Operation,
+    ) {
+        self.content.as_widget().operate(
+            &mut tree.children[0],
+            layout.children().next().unwrap(),
+            renderer,
+            operation,
+        );
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+        let current = (self.node_id, state.current);
+
+        if state.reported != Some(current) {
+            state.reported = Some(current);
+            shell.publish(Message::broadcast(
+                WidgetMessage::new(
+                    self.node_id,
+                    self.element_id.clone(),
+                    WidgetEvent::Breakpoint(current.1),
+                )
+                .with_stable_id(self.stable_id.clone()),
+            ));
+        }
+
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout.children().next().unwrap(),
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout.children().next().unwrap(),
+            cursor,
+            viewport,
+        );
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout.children().next().unwrap(),
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'a>(
+        &'a mut self,
+        tree: &'a mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'a, Message, iced::Theme, iced::Renderer>> {
+        self.content.as_widget_mut().overlay(
+            &mut tree.children[0],
+            layout.children().next().unwrap(),
+            renderer,
+            translation,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use tracing_test::traced_test;
+
+    use super::{breakpoint, set_breakpoint};
+    use crate::{attribute::breakpoint::Breakpoint, Message, SnowcapParser};
+
+    #[traced_test]
+    #[test]
+    fn resolve_breakpoint() {
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{responsive(col[text<width:{xs:shrink, md:200}>("a"), text("b")])}"#,
+        )
+        .unwrap()
+        .index();
+
+        // root -> container -> responsive -> column
+        let responsive = tree.root().node().children().unwrap()[0]
+            .node()
+            .children()
+            .unwrap()[0]
+            .clone();
+        let column = responsive.node().children().unwrap()[0].clone();
+        let children = column.node().children().unwrap().to_vec();
+        let (a, b) = (&children[0], &children[1]);
+
+        assert_eq!(breakpoint(a), Breakpoint::Xs);
+
+        a.node_mut().data_mut().set_state(crate::node::State::Clean);
+        b.node_mut().data_mut().set_state(crate::node::State::Clean);
+
+        set_breakpoint(&responsive, Breakpoint::Lg);
+        assert_eq!(breakpoint(a), Breakpoint::Lg);
+
+        // Only the node with responsive attributes is rebuilt
+        assert!(a.node().data().is_dirty());
+        assert!(!b.node().data().is_dirty());
+    }
+}
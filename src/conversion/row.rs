@@ -1,6 +1,7 @@
 use crate::{
     attribute::{AttributeValue, Attributes},
     cache::WidgetContent,
+    conversion::{cascade, drag},
     dynamic_widget::DynamicWidget,
     error::ConversionError,
 };
@@ -34,6 +35,15 @@ impl SnowcapRow {
                 Some(AttributeValue::HeightPixels(pixels)) => row.height(pixels),
                 Some(AttributeValue::Spacing(pixels)) => row.spacing(pixels),
                 Some(AttributeValue::Clip(clip)) => row.clip(clip),
+                // Backgrounds are drawn by a container around the row
+                Some(AttributeValue::Background(_))
+                | Some(AttributeValue::BackgroundPalette(_)) => {
+                    return Err(ConversionError::UnsupportedAttribute(attr, "Row".into()))
+                }
+                // Inherited by descendant text widgets
+                _ if cascade::is_cascading(attr.kind()) => row,
+                // Applied by the widget cache
+                _ if drag::is_drag(attr.kind()) => row,
                 _ => {
                     warn!("Unsupported Row attribute {:#?}", attr);
                     row
@@ -0,0 +1,76 @@
+//! Horizontal and vertical rules
+//!
+//! ```text
+//! rule-horizontal<thickness:2, color:palette(primary.weak), fill-mode:80%>()
+//! rule-vertical<width:12, fill-mode:padded(4, 8)>()
+//! ```
+//!
+//! The `thickness` attribute sets the width of the line, which is 1 pixel by default. The space reserved for the
+//! rule is its `height` for a horizontal rule or its `width` for a vertical rule, and defaults to the thickness.
+//! The `fill-mode` attribute is `full`, a percentage of the available length, or `padded()` with the padding at
+//! both ends or each end.
+
+use iced::widget::{rule, Rule};
+use salish::Message;
+
+use crate::{
+    attribute::{palette::PaletteColor, AttributeValue, Attributes},
+    conversion::drag,
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+};
+
+/// Width of the line of a rule if the `thickness` attribute isn't set
+const DEFAULT_THICKNESS: f32 = 1.0;
+
+/// Build a `rule-horizontal`, or a `rule-vertical` if vertical is set
+pub(crate) fn build(
+    attrs: Attributes,
+    vertical: bool,
+) -> Result<DynamicWidget<Message>, ConversionError> {
+    let mut thickness = None;
+    let mut space = None;
+    let mut color = None;
+    let mut palette: Option<PaletteColor> = None;
+    let mut fill_mode = None;
+
+    for attr in attrs {
+        match attr.value().cloned() {
+            Some(AttributeValue::Thickness(pixels)) => thickness = Some(pixels.0),
+            Some(AttributeValue::HeightPixels(pixels)) if !vertical => space = Some(pixels),
+            Some(AttributeValue::WidthPixels(pixels)) if vertical => space = Some(pixels),
+            Some(AttributeValue::RuleColor(rule_color)) => color = Some(rule_color),
+            Some(AttributeValue::RulePalette(rule_palette)) => palette = Some(rule_palette),
+            Some(AttributeValue::FillMode(mode)) => fill_mode = Some(mode),
+            // Applied by the widget cache
+            _ if drag::is_drag(attr.kind()) => {}
+            _ => return Err(ConversionError::UnsupportedAttribute(attr, "Rule".into())),
+        }
+    }
+
+    let thickness = thickness.unwrap_or(DEFAULT_THICKNESS);
+    let space = space.unwrap_or(iced::Pixels(thickness));
+
+    let mut widget = if vertical {
+        Rule::vertical(space)
+    } else {
+        Rule::horizontal(space)
+    };
+
+    widget = widget.style(move |theme: &iced::Theme| {
+        let mut style = rule::default(theme);
+        style.width = thickness.round().max(1.0) as u16;
+        if let Some(color) = color {
+            style.color = color;
+        }
+        if let Some(palette) = palette {
+            style.color = palette.resolve(theme);
+        }
+        if let Some(fill_mode) = fill_mode {
+            style.fill_mode = fill_mode;
+        }
+        style
+    });
+
+    Ok(DynamicWidget::default().with_widget(widget))
+}
@@ -0,0 +1,225 @@
+//! Adjustment of slider values with the mouse wheel and spin buttons
+//!
+//! Steps which don't change the value, such as scrolling past the end of the range, don't send
+//! [`WidgetEvent::SliderChanged`].
+//!
+//! ```text
+//! slider<wheel:true, spin:true, step:100>()
+//! ```
+
+use std::ops::RangeInclusive;
+
+use iced::{
+    mouse::ScrollDelta,
+    widget::{Button, Column, MouseArea, Row, Text},
+    Alignment, Element,
+};
+use salish::Message;
+use tracing::warn;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+    identity::StableId,
+    message::widget::{WidgetEvent, WidgetMessage},
+    util::ElementWrapper,
+    NodeId,
+};
+
+/// Range of values of slider widgets
+pub(crate) const SLIDER_RANGE: RangeInclusive<i32> = 0..=32768;
+
+/// Adjustment of each wheel or button step if the `step` attribute isn't set
+const DEFAULT_STEP: i32 = 1;
+
+/// Message of a wheel or button step which doesn't change the value. Mouse areas send a message for every
+/// scroll, so steps past the bounds send this message, which isn't routed to any endpoint.
+#[derive(Debug, Clone)]
+struct Unchanged;
+
+/// Get the message of a wheel or button step, or a message routed to no endpoint if the step didn't change the
+/// value
+pub(crate) fn step_message(message: Option<Message>) -> Message {
+    message.unwrap_or_else(|| Message::broadcast(Unchanged))
+}
+
+/// Get the number of steps of a scroll of the mouse wheel. Scrolling up increases the value.
+pub(crate) fn wheel_steps(delta: ScrollDelta) -> i32 {
+    let y = match delta {
+        ScrollDelta::Lines { y, .. } | ScrollDelta::Pixels { y, .. } => y,
+    };
+
+    if y > 0.0 {
+        1
+    } else if y < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Adjusts the value of a slider from the mouse wheel and increment/decrement buttons,
+/// enabled with the `wheel:true` and `spin:true` attributes. Each adjustment emits
+/// the same [`WidgetEvent::SliderChanged`] event as dragging the slider.
+#[derive(Debug, Clone)]
+pub(crate) struct SliderAdjust {
+    node_id: NodeId,
+    element_id: Option<String>,
+    stable_id: Option<StableId>,
+    attrs: Attributes,
+    step: i32,
+    wheel: bool,
+    spin: bool,
+}
+
+impl SliderAdjust {
+    pub fn new(
+        node_id: NodeId,
+        element_id: Option<String>,
+        stable_id: Option<StableId>,
+        attrs: Attributes,
+    ) -> Result<Self, ConversionError> {
+        let wheel = matches!(
+            attrs.get(AttributeKind::Wheel)?,
+            Some(AttributeValue::Wheel(true))
+        );
+        let spin = matches!(
+            attrs.get(AttributeKind::Spin)?,
+            Some(AttributeValue::Spin(true))
+        );
+        let step = match attrs.get(AttributeKind::Step)? {
+            Some(AttributeValue::Step(step)) => (step.round() as i32).max(1),
+            _ => DEFAULT_STEP,
+        };
+
+        Ok(Self {
+            node_id,
+            element_id,
+            stable_id,
+            attrs,
+            step,
+            wheel,
+            spin,
+        })
+    }
+
+    /// Returns true if wheel or button adjustment is enabled
+    pub fn enabled(&self) -> bool {
+        self.wheel || self.spin
+    }
+
+    /// Adjust the slider value by a number of steps, clamped to [`SLIDER_RANGE`].
+    /// The new value is stored in the slider attributes, and a [`WidgetEvent::SliderChanged`] message is returned,
+    /// or None if the value didn't change.
+    pub fn adjust(&self, steps: i32) -> Option<Message> {
+        let current = match self.attrs.get(AttributeKind::SliderValue) {
+            Ok(Some(AttributeValue::SliderValue(value))) => value,
+            _ => 0,
+        };
+
+        let value = current
+            .saturating_add(steps.saturating_mul(self.step))
+            .clamp(*SLIDER_RANGE.start(), *SLIDER_RANGE.end());
+
+        if value == current {
+            return None;
+        }
+
+        if let Err(e) = self.attrs.set(AttributeValue::SliderValue(value)) {
+            warn!("Failed to set slider value: {e}");
+        }
+
+        Some(Message::broadcast(
+            WidgetMessage::new(
+                self.node_id,
+                self.element_id.clone(),
+                WidgetEvent::SliderChanged(value),
+            )
+            .with_stable_id(self.stable_id.clone()),
+        ))
+    }
+
+    /// Wrap a slider in a [`MouseArea`] handling wheel events, and place the spin buttons around it.
+    /// Buttons are placed in a row beside a horizontal slider, or a column above and below a vertical slider.
+    pub fn wrap(
+        self,
+        slider: impl Into<Element<'static, Message>>,
+        vertical: bool,
+    ) -> DynamicWidget<Message> {
+        let mut element: Element<'static, Message> = slider.into();
+
+        if self.wheel {
+            let wheel = self.clone();
+            element = MouseArea::new(element)
+                .on_scroll(move |delta| step_message(wheel.adjust(wheel_steps(delta))))
+                .into();
+        }
+
+        if self.spin {
+            let increment = self.clone();
+            let decrement = self.clone();
+
+            let plus = Button::new(Text::new("+"))
+                .on_press_with(move || step_message(increment.adjust(1)));
+            let minus = Button::new(Text::new("-"))
+                .on_press_with(move || step_message(decrement.adjust(-1)));
+
+            element = if vertical {
+                Column::new()
+                    .push(plus)
+                    .push(element)
+                    .push(minus)
+                    .spacing(4)
+                    .align_x(Alignment::Center)
+                    .into()
+            } else {
+                Row::new()
+                    .push(minus)
+                    .push(element)
+                    .push(plus)
+                    .spacing(4)
+                    .align_y(Alignment::Center)
+                    .into()
+            };
+        }
+
+        DynamicWidget::default().with_widget(ElementWrapper::new(element))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{SliderAdjust, SLIDER_RANGE};
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        parser::attribute::AttributeParser,
+    };
+
+    #[traced_test]
+    #[test]
+    fn adjust_steps() {
+        let attrs = AttributeParser::parse_attributes("wheel:true, step:10").unwrap();
+        let adjust = SliderAdjust::new(0, None, None, attrs.clone()).unwrap();
+        assert!(adjust.enabled());
+
+        assert!(adjust.adjust(3).is_some());
+        assert_eq!(
+            attrs.get(AttributeKind::SliderValue).unwrap(),
+            Some(AttributeValue::SliderValue(30))
+        );
+
+        // Values are clamped to the slider range
+        assert!(adjust.adjust(-10).is_some());
+        assert_eq!(
+            attrs.get(AttributeKind::SliderValue).unwrap(),
+            Some(AttributeValue::SliderValue(*SLIDER_RANGE.start()))
+        );
+
+        // Steps which don't change the value are skipped
+        assert!(adjust.adjust(-1).is_none());
+        assert!(adjust.adjust(0).is_none());
+    }
+}
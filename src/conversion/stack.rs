@@ -1,28 +1,79 @@
-use iced::widget::Stack;
+//! Stacks of overlapping layers
+//!
+//! ```text
+//! stack[image(file!{path:"map.png"}), text<z:2>("Popup"), text<z:1>("Marker")]
+//! ```
+//!
+//! Children are drawn in the order they are declared, unless they set a layer with the `z` attribute. Children in
+//! higher layers are drawn above, and receive events before, children in lower layers. Children without a `z`
+//! attribute are in layer 0, and children in the same layer keep their declaration order.
+
+use iced::{widget::Stack, Element};
 
 use crate::{
-    attribute::{AttributeValue, Attributes},
+    attribute::{AttributeKind, AttributeValue, Attributes},
     cache::WidgetContent,
+    conversion::{cascade, drag},
     dynamic_widget::DynamicWidget,
     error::ConversionError,
 };
 
+/// Get the layer of a child of a stack from its `z` attribute, which is 0 if unset
+pub(crate) fn layer(attrs: &Attributes) -> i32 {
+    match attrs.get(AttributeKind::ZIndex) {
+        Ok(Some(AttributeValue::ZIndex(z))) => z,
+        _ => 0,
+    }
+}
+
+/// Remove the `z` attribute, which is applied by the parent stack rather than by the conversion of a widget
+pub(crate) fn without_layer(attrs: Attributes) -> Result<Attributes, ConversionError> {
+    if attrs.get(AttributeKind::ZIndex)?.is_none() {
+        return Ok(attrs);
+    }
+
+    let mut without = Attributes::new();
+    for attr in &attrs {
+        if attr.kind() != AttributeKind::ZIndex {
+            without.push(attr)?;
+        }
+    }
+    Ok(without)
+}
+
 pub struct SnowcapStack;
 
 impl SnowcapStack {
+    /// Convert a stack, with the layer of each of its children. Children are drawn in declaration order
+    /// if the layers don't match the children.
     pub fn convert<M>(
         attrs: Attributes,
         contents: WidgetContent<M>,
+        layers: &[i32],
     ) -> Result<DynamicWidget<M>, ConversionError>
     where
         M: std::fmt::Debug + 'static,
     {
-        let mut stack = Stack::with_children(contents);
+        let mut children: Vec<Element<'static, M>> = contents.into_iter().collect();
+
+        if children.len() == layers.len() {
+            // Sorting is stable, so children in the same layer keep their order
+            let mut layered: Vec<(i32, Element<'static, M>)> =
+                layers.iter().copied().zip(children).collect();
+            layered.sort_by_key(|(layer, _)| *layer);
+            children = layered.into_iter().map(|(_, child)| child).collect();
+        }
+
+        let mut stack = Stack::with_children(children);
 
         for attr in attrs {
             stack = match attr.value().cloned() {
                 Some(AttributeValue::WidthLength(length)) => stack.width(length),
                 Some(AttributeValue::HeightLength(length)) => stack.height(length),
+                // Inherited by descendant text widgets
+                _ if cascade::is_cascading(attr.kind()) => stack,
+                // Applied by the widget cache
+                _ if drag::is_drag(attr.kind()) => stack,
                 _ => return Err(ConversionError::UnsupportedAttribute(attr, "Stack".into())),
             };
         }
@@ -30,3 +81,29 @@ impl SnowcapStack {
         Ok(DynamicWidget::default().with_widget(stack))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{layer, without_layer};
+    use crate::{attribute::AttributeKind, parser::attribute::AttributeParser};
+
+    #[traced_test]
+    #[test]
+    fn layers() {
+        let attrs = AttributeParser::parse_attributes("z:3").unwrap();
+        assert_eq!(layer(&attrs), 3);
+
+        let attrs = AttributeParser::parse_attributes("z-index:-1, padding:4").unwrap();
+        assert_eq!(layer(&attrs), -1);
+
+        // The layer is removed before the widget is converted
+        let attrs = without_layer(attrs).unwrap();
+        assert!(attrs.get(AttributeKind::ZIndex).unwrap().is_none());
+        assert!(attrs.get(AttributeKind::Padding).unwrap().is_some());
+
+        let attrs = AttributeParser::parse_attributes("padding:4").unwrap();
+        assert_eq!(layer(&attrs), 0);
+    }
+}
@@ -0,0 +1,179 @@
+//! Styling elements from a stylesheet
+//!
+//! A `styles` block before the root container declares rules applying attributes to elements by id or class,
+//! so presentation can be kept apart from the structure of the markup:
+//!
+//! ```text
+//! styles {
+//!     #title { size:40, text-color:#ffffff }
+//!     .card { padding:12, border:radius(8), bg:palette(background.weak) }
+//! }
+//! {|[text#title("Snowcap"), {<class:"card"> text("First")}, {<class:["card", "raised"]> text("Second")}]}
+//! ```
+//!
+//! Rules for the classes of an element are applied first, in the order they are declared, then rules for its id.
+//! Attributes of the element override the attributes of any rule. Modules can't be used in stylesheets.
+//!
+//! The stylesheet can also live in a separate file, referenced with `styles!("theme.iss")` in place of the block.
+//! A relative path is resolved from the directory of the markup file. The engine watches the file, and an edit
+//! only rebuilds the widgets of the elements with an id or class, rather than reparsing the markup.
+//!
+//! The stylesheet can be replaced at runtime with [`crate::Snowcap::set_stylesheet()`], which rebuilds the widgets
+//! of the elements with an id or class.
+
+use std::{path::PathBuf, sync::Arc};
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    error::ConversionError,
+    parser::style::{StyleSelector, Stylesheet},
+    IndexedTree,
+};
+
+/// Get the stylesheet declared in the markup, stored in the attributes of the root node
+pub(crate) fn root_stylesheet(tree: &IndexedTree) -> Option<Arc<Stylesheet>> {
+    match tree
+        .root()
+        .node()
+        .data()
+        .attrs
+        .get(AttributeKind::Stylesheet)
+    {
+        Ok(Some(AttributeValue::Stylesheet(stylesheet))) => Some(stylesheet),
+        _ => None,
+    }
+}
+
+/// External stylesheet file referenced by the markup, loaded by the engine
+#[derive(Debug)]
+pub(crate) struct StyleFile {
+    /// Path of the file referenced by the markup, resolved from the directory of the markup file
+    pub reference: PathBuf,
+    /// Canonical path of the file, matching the paths reported by the watcher
+    pub path: PathBuf,
+    /// Rules of the file, or None if it failed to load
+    pub stylesheet: Option<Arc<Stylesheet>>,
+}
+
+/// Get the path of the external stylesheet referenced with `styles!()`, stored in the attributes of the root node
+pub(crate) fn root_stylesheet_file(tree: &IndexedTree) -> Option<PathBuf> {
+    match tree
+        .root()
+        .node()
+        .data()
+        .attrs
+        .get(AttributeKind::StylesheetFile)
+    {
+        Ok(Some(AttributeValue::StylesheetFile(path))) => Some(path),
+        _ => None,
+    }
+}
+
+/// Returns true if an element can be selected by the rules of a stylesheet
+pub(crate) fn is_styled(attrs: &Attributes, element_id: Option<&str>) -> bool {
+    element_id.is_some() || matches!(attrs.get(AttributeKind::Class), Ok(Some(_)))
+}
+
+/// Get the attributes of an element with the matching rules of the stylesheet applied. The `class` attribute
+/// is removed, as it only selects rules.
+pub(crate) fn apply(
+    stylesheet: Option<&Stylesheet>,
+    element_id: Option<&str>,
+    attrs: &Attributes,
+) -> Result<Attributes, ConversionError> {
+    let classes = match attrs.get(AttributeKind::Class)? {
+        Some(AttributeValue::Class(classes)) => Some(classes),
+        _ => None,
+    };
+
+    if classes.is_none() && (stylesheet.is_none() || element_id.is_none()) {
+        return Ok(attrs.clone());
+    }
+
+    let classes = classes.unwrap_or_default();
+    let mut styled = Attributes::new();
+
+    if let Some(stylesheet) = stylesheet {
+        let matches = |selector: &StyleSelector, id_rules: bool| match selector {
+            StyleSelector::Class(class) => !id_rules && classes.contains(class),
+            StyleSelector::Id(id) => id_rules && element_id == Some(id.as_str()),
+        };
+
+        // Class rules are applied before id rules, which are more specific
+        for id_rules in [false, true] {
+            for rule in stylesheet.rules() {
+                if !rule
+                    .selectors()
+                    .iter()
+                    .any(|selector| matches(selector, id_rules))
+                {
+                    continue;
+                }
+
+                for attr in rule.attrs() {
+                    if attr.value().is_some() {
+                        styled.push(attr)?;
+                    }
+                }
+            }
+        }
+    }
+
+    for attr in attrs {
+        if attr.kind() != AttributeKind::Class {
+            styled.push(attr)?;
+        }
+    }
+
+    Ok(styled)
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::apply;
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        parser::{attribute::AttributeParser, style::StyleParser},
+    };
+
+    #[traced_test]
+    #[test]
+    fn apply_rules() {
+        let stylesheet = StyleParser::parse_str(
+            "styles { #title { size:40, padding:8 } .card { size:20, spacing:4 } .raised { padding:2 } }",
+        )
+        .unwrap();
+
+        // Id rules override class rules, and the attributes of the element override both
+        let attrs =
+            AttributeParser::parse_attributes(r#"class:"card raised", spacing:10"#).unwrap();
+        let styled = apply(Some(&stylesheet), Some("title"), &attrs).unwrap();
+        assert_eq!(
+            styled.get(AttributeKind::Size).unwrap(),
+            Some(AttributeValue::Size(40.into()))
+        );
+        assert_eq!(
+            styled.get(AttributeKind::Spacing).unwrap(),
+            Some(AttributeValue::Spacing(10.into()))
+        );
+        assert!(styled.get(AttributeKind::Padding).unwrap().is_some());
+        assert!(styled.get(AttributeKind::Class).unwrap().is_none());
+
+        // Only the rules of the classes of the element are applied
+        let attrs = AttributeParser::parse_attributes(r#"class:["card"]"#).unwrap();
+        let styled = apply(Some(&stylesheet), None, &attrs).unwrap();
+        assert_eq!(
+            styled.get(AttributeKind::Size).unwrap(),
+            Some(AttributeValue::Size(20.into()))
+        );
+        assert!(styled.get(AttributeKind::Padding).unwrap().is_none());
+
+        // The class is removed without a stylesheet
+        let styled = apply(None, None, &attrs).unwrap();
+        assert_eq!(styled.len(), 0);
+    }
+}
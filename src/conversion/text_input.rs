@@ -0,0 +1,357 @@
+//! Text, number and password inputs
+//!
+//! ```text
+//! text-input#name<placeholder:"Name">()
+//! password-input#secret<placeholder:"Password">()
+//! number-input#quantity<min:1, max:10, step:0.5, spin:true, wheel:true>(2)
+//! ```
+//!
+//! The three widgets share the same text input, and store the text being edited in their attributes.
+//! A `text-input` or `password-input` sends [`WidgetEvent::InputChanged`] as the text is edited, and
+//! [`WidgetEvent::InputSubmitted`] when enter is pressed. A `password-input` masks its text, which can be
+//! shown with `secure:false`, and a `text-input` can be masked with `secure:true`.
+//!
+//! A `number-input` sends [`WidgetEvent::NumberChanged`] with the value clamped to its `min` and `max` attributes
+//! whenever the text is a number, and [`WidgetEvent::InputChanged`] while it isn't, such as when a
+//! sign has been typed. Pressing enter sends the clamped value again. With `spin:true`, increment and decrement
+//! buttons adjust the value by `step`, and replace the text with the adjusted value. With `wheel:true`, the mouse
+//! wheel adjusts the value the same way while the pointer is over the input. Steps which don't change the value,
+//! such as scrolling past a bound, don't send [`WidgetEvent::NumberChanged`].
+
+use iced::{
+    widget::{Button, MouseArea, Row, Text, TextInput},
+    Alignment, Element,
+};
+use salish::Message;
+use tracing::warn;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    cache::WidgetContent,
+    conversion::{
+        drag,
+        slider::{step_message, wheel_steps},
+    },
+    dynamic_widget::DynamicWidget,
+    error::ConversionError,
+    identity::StableId,
+    message::widget::{WidgetEvent, WidgetMessage},
+    util::ElementWrapper,
+    NodeId,
+};
+
+/// Adjustment of each button step of a `number-input` if the `step` attribute isn't set
+const DEFAULT_STEP: f64 = 1.0;
+
+/// Kind of an input widget
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum InputKind {
+    Text,
+    Number,
+    Password,
+}
+
+impl InputKind {
+    /// Get the kind of input built for a widget name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text-input" => Some(Self::Text),
+            "number-input" => Some(Self::Number),
+            "password-input" => Some(Self::Password),
+            _ => None,
+        }
+    }
+}
+
+/// Builds the widget of a `text-input`, `number-input` or `password-input`, and the messages of its events
+#[derive(Debug, Clone)]
+pub(crate) struct Input {
+    kind: InputKind,
+    node_id: NodeId,
+    element_id: Option<String>,
+    stable_id: Option<StableId>,
+    attrs: Attributes,
+}
+
+impl Input {
+    pub fn new(
+        kind: InputKind,
+        node_id: NodeId,
+        element_id: Option<String>,
+        stable_id: Option<StableId>,
+        attrs: Attributes,
+    ) -> Self {
+        Self {
+            kind,
+            node_id,
+            element_id,
+            stable_id,
+            attrs,
+        }
+    }
+
+    /// Get the text being edited, or the initial text from the widget content if it hasn't been edited
+    fn text(&self, content: &WidgetContent<Message>) -> Result<String, ConversionError> {
+        if let Some(AttributeValue::InputValue(text)) = self.attrs.get(AttributeKind::InputValue)? {
+            return Ok(text);
+        }
+
+        Ok(match content {
+            WidgetContent::Value(value) => value.to_string(),
+            WidgetContent::Text(text) => text.clone(),
+            _ => String::new(),
+        })
+    }
+
+    /// Get the `min` and `max` bounds of a `number-input`
+    fn bounds(&self) -> Result<(f64, f64), ConversionError> {
+        let min = match self.attrs.get(AttributeKind::Min)? {
+            Some(AttributeValue::Min(min)) => min,
+            _ => f64::MIN,
+        };
+        let max = match self.attrs.get(AttributeKind::Max)? {
+            Some(AttributeValue::Max(max)) => max,
+            _ => f64::MAX,
+        };
+
+        if min > max {
+            return Err(ConversionError::InvalidType(format!(
+                "number-input min {min} is greater than max {max}"
+            )));
+        }
+
+        Ok((min, max))
+    }
+
+    /// Parse the text of a `number-input`, clamping the number to the bounds
+    fn number(&self, text: &str) -> Option<f64> {
+        let (min, max) = self.bounds().ok()?;
+        text.trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .map(|value| value.clamp(min, max))
+    }
+
+    /// Store the edited text in the attributes, and get the message of the edit
+    pub fn edit(&self, text: String) -> Message {
+        let event = match self.kind {
+            InputKind::Number => match self.number(&text) {
+                Some(value) => WidgetEvent::NumberChanged(value),
+                None => WidgetEvent::InputChanged(text.clone()),
+            },
+            InputKind::Text | InputKind::Password => WidgetEvent::InputChanged(text.clone()),
+        };
+
+        self.store(text);
+        self.message(event)
+    }
+
+    /// Get the message sent when enter is pressed with the current text. A `number-input` sends its clamped value
+    /// if the text is a number.
+    pub fn submit(&self, text: &str) -> Message {
+        match self.kind {
+            InputKind::Number => match self.number(text) {
+                Some(value) => self.message(WidgetEvent::NumberChanged(value)),
+                None => self.message(WidgetEvent::InputSubmitted(text.to_string())),
+            },
+            InputKind::Text | InputKind::Password => {
+                self.message(WidgetEvent::InputSubmitted(text.to_string()))
+            }
+        }
+    }
+
+    /// Adjust the value of a `number-input` from its current text by a number of steps,
+    /// from the `min` bound if the text isn't a number. Returns None if the value didn't change.
+    pub fn adjust(&self, text: &str, steps: f64) -> Option<Message> {
+        let step = match self.attrs.get(AttributeKind::Step) {
+            Ok(Some(AttributeValue::Step(step))) => step,
+            _ => DEFAULT_STEP,
+        };
+        let (min, max) = self.bounds().unwrap_or((f64::MIN, f64::MAX));
+
+        let current = self.number(text);
+        let value = match current {
+            Some(current) => (current + steps * step).clamp(min, max),
+            None if min > f64::MIN => min,
+            None => 0.0,
+        };
+
+        if current == Some(value) {
+            return None;
+        }
+
+        self.store(value.to_string());
+        Some(self.message(WidgetEvent::NumberChanged(value)))
+    }
+
+    fn store(&self, text: String) {
+        if let Err(e) = self.attrs.set(AttributeValue::InputValue(text)) {
+            warn!("Failed to set input value: {e}");
+        }
+    }
+
+    fn message(&self, event: WidgetEvent) -> Message {
+        Message::broadcast(
+            WidgetMessage::new(self.node_id, self.element_id.clone(), event)
+                .with_stable_id(self.stable_id.clone()),
+        )
+    }
+
+    /// Build the input widget
+    pub fn build(
+        self,
+        content: WidgetContent<Message>,
+    ) -> Result<DynamicWidget<Message>, ConversionError> {
+        let text = self.text(&content)?;
+        if self.kind == InputKind::Number {
+            self.bounds()?;
+        }
+
+        let placeholder = match self.attrs.get(AttributeKind::Placeholder)? {
+            Some(AttributeValue::Placeholder(placeholder)) => placeholder,
+            _ => String::new(),
+        };
+        let secure = match self.attrs.get(AttributeKind::Secure)? {
+            Some(AttributeValue::Secure(secure)) => secure,
+            _ => self.kind == InputKind::Password,
+        };
+        let spin = self.kind == InputKind::Number
+            && matches!(
+                self.attrs.get(AttributeKind::Spin)?,
+                Some(AttributeValue::Spin(true))
+            );
+        let wheel = self.kind == InputKind::Number
+            && matches!(
+                self.attrs.get(AttributeKind::Wheel)?,
+                Some(AttributeValue::Wheel(true))
+            );
+
+        // The widget is rebuilt after each edit, so the submit message is made with the current text
+        let edit = self.clone();
+        let mut input = TextInput::<Message>::new(&placeholder, &text)
+            .on_input(move |text| edit.edit(text))
+            .on_submit(self.submit(&text))
+            .secure(secure);
+
+        for attr in self.attrs.clone() {
+            input = match attr.value().cloned() {
+                Some(AttributeValue::Placeholder(_)) | Some(AttributeValue::Secure(_)) => input,
+                Some(AttributeValue::Spin(_)) | Some(AttributeValue::Wheel(_))
+                    if self.kind == InputKind::Number =>
+                {
+                    input
+                }
+                Some(AttributeValue::WidthLength(width)) => input.width(width),
+                Some(AttributeValue::WidthPixels(width)) => input.width(width),
+                Some(AttributeValue::Size(size)) => input.size(size),
+                Some(AttributeValue::Padding(padding)) => input.padding(padding),
+                Some(AttributeValue::Font(font)) => input.font(font),
+                Some(AttributeValue::HorizontalAlignment(horizontal)) => input.align_x(horizontal),
+                // Stored by the input as it is edited
+                Some(AttributeValue::InputValue(_)) => input,
+                Some(AttributeValue::Min(_))
+                | Some(AttributeValue::Max(_))
+                | Some(AttributeValue::Step(_))
+                    if self.kind == InputKind::Number =>
+                {
+                    input
+                }
+                // Published by the engine from the input message
+                Some(AttributeValue::OnChange(_)) | Some(AttributeValue::OnSubmit(_)) => input,
+                Some(AttributeValue::Bind(_)) => input,
+                // Applied by the widget cache
+                _ if drag::is_drag(attr.kind()) => input,
+                _ => {
+                    return Err(ConversionError::UnsupportedAttribute(
+                        attr,
+                        "TextInput".into(),
+                    ))
+                }
+            };
+        }
+
+        if !spin && !wheel {
+            return Ok(DynamicWidget::default().with_widget(input));
+        }
+
+        let mut element: Element<'static, Message> =
+            if spin {
+                let increment = self.clone();
+                let decrement = self.clone();
+                let (increment_text, decrement_text) = (text.clone(), text.clone());
+                Row::new()
+                    .push(Button::new(Text::new("-")).on_press_with(move || {
+                        step_message(decrement.adjust(&decrement_text, -1.0))
+                    }))
+                    .push(input)
+                    .push(Button::new(Text::new("+")).on_press_with(move || {
+                        step_message(increment.adjust(&increment_text, 1.0))
+                    }))
+                    .spacing(4)
+                    .align_y(Alignment::Center)
+                    .into()
+            } else {
+                input.into()
+            };
+
+        if wheel {
+            element = MouseArea::new(element)
+                .on_scroll(move |delta| step_message(self.adjust(&text, wheel_steps(delta) as f64)))
+                .into();
+        }
+
+        Ok(DynamicWidget::default().with_widget(ElementWrapper::new(element)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{Input, InputKind};
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        parser::attribute::AttributeParser,
+    };
+
+    #[traced_test]
+    #[test]
+    fn number_input() {
+        let attrs = AttributeParser::parse_attributes("min:0, max:10, step:0.5").unwrap();
+        let input = Input::new(InputKind::Number, 0, None, None, attrs.clone());
+
+        // Numbers are clamped to the bounds, and the typed text is kept while editing
+        assert_eq!(input.number("12"), Some(10.0));
+        assert_eq!(input.number("-"), None);
+        input.edit("12".into());
+        assert_eq!(
+            attrs.get(AttributeKind::InputValue).unwrap(),
+            Some(AttributeValue::InputValue("12".into()))
+        );
+
+        // Adjusting steps from the clamped value
+        assert!(input.adjust("12", -3.0).is_some());
+        assert_eq!(
+            attrs.get(AttributeKind::InputValue).unwrap(),
+            Some(AttributeValue::InputValue("8.5".into()))
+        );
+
+        // Steps past a bound don't change the value
+        assert!(input.adjust("10", 1.0).is_none());
+        assert!(input.adjust("8.5", 0.0).is_none());
+
+        // Text inputs don't parse numbers
+        let attrs = AttributeParser::parse_attributes("secure:true").unwrap();
+        let input = Input::new(InputKind::Text, 0, None, None, attrs.clone());
+        input.edit("hunter2".into());
+        assert_eq!(
+            attrs.get(AttributeKind::Secure).unwrap(),
+            Some(AttributeValue::Secure(true))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::InputValue).unwrap(),
+            Some(AttributeValue::InputValue("hunter2".into()))
+        );
+    }
+}
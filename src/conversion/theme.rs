@@ -1,5 +1,12 @@
+use crate::attribute::{Attribute, AttributeKind, AttributeValue, Attributes};
+use crate::conversion::{code::CODE_WIDGET, markdown::MARKDOWN_WIDGET};
 use crate::error::ConversionError;
-use iced::Theme;
+use crate::{IndexedTree, NodeRef, SyncError};
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{Pixels, Theme};
+
+/// Name of the theme attribute value following the system appearance
+pub(crate) const SYSTEM_THEME: &str = "system";
 
 /// A wrapper around the `Theme` enum that provides additional functionality,
 /// such as converting a string representation of a theme into its corresponding
@@ -63,7 +70,16 @@ impl TryFrom<&str> for SnowcapTheme {
     /// ```
 
     fn try_from(theme_name: &str) -> Result<Self, ConversionError> {
-        let theme = match theme_name.to_lowercase().as_str() {
+        // Names are matched ignoring case and separators, so the display names of the themes such as
+        // "Tokyo Night" or "Catppuccin Frappé" are accepted along with "tokyo-night" and "catppuccinfrappe"
+        let name: String = theme_name
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .flat_map(char::to_lowercase)
+            .map(|c| if c == 'é' { 'e' } else { c })
+            .collect();
+
+        let theme = match name.as_str() {
             "light" => SnowcapTheme(Theme::Light),
             "dark" => SnowcapTheme(Theme::Dark),
             "dracula" => SnowcapTheme(Theme::Dracula),
@@ -97,6 +113,101 @@ impl TryFrom<&str> for SnowcapTheme {
     }
 }
 
+/// Returns true if the styles of a widget are derived from the theme it is rendered with. These widgets
+/// are built with the theme of the nearest `themer` ancestor or the active theme of the engine, and are
+/// rebuilt when the active theme changes.
+pub(crate) fn is_themed(widget: &str) -> bool {
+    matches!(widget, MARKDOWN_WIDGET | CODE_WIDGET)
+}
+
+/// Get the theme of the nearest ancestor of a node with a `theme` attribute, such as a `themer`
+pub(crate) fn ancestor_theme(noderef: &NodeRef) -> Option<Theme> {
+    let mut current = noderef.node().parent().cloned();
+
+    while let Some(parent) = current {
+        if let Ok(Some(AttributeValue::Theme(theme))) =
+            parent.node().data().attrs.get(AttributeKind::Theme)
+        {
+            return Some(theme);
+        }
+        current = parent.node().parent().cloned();
+    }
+
+    None
+}
+
+/// Returns true if the nearest ancestor of a node with a `theme` attribute is dirty, and the theme
+/// it renders the node with may have changed
+pub(crate) fn ancestor_theme_dirty(noderef: &NodeRef) -> bool {
+    let mut current = noderef.node().parent().cloned();
+
+    while let Some(parent) = current {
+        {
+            let node = parent.node();
+            if let Ok(Some(_)) = node.data().attrs.get(AttributeKind::Theme) {
+                return node.data().is_dirty();
+            }
+        }
+        current = parent.node().parent().cloned();
+    }
+
+    false
+}
+
+/// Get the attributes to build a themed widget with, adding the theme it is rendered with
+/// if the widget has no `theme` attribute
+pub(crate) fn with_theme(attrs: &Attributes, theme: Theme) -> Result<Attributes, SyncError> {
+    if attrs.get(AttributeKind::Theme)?.is_some() {
+        return Ok(attrs.clone());
+    }
+
+    let mut themed = Attributes::new();
+    for attr in attrs {
+        themed.push(attr)?;
+    }
+    themed.push(Attribute::new(AttributeKind::Theme).with_value(AttributeValue::Theme(theme)))?;
+
+    Ok(themed)
+}
+
+/// Get the theme declared with a `theme` attribute on the root container of the markup,
+/// such as `{<theme:"dark"> ...}`. A custom theme definition before the root container
+/// takes precedence.
+pub(crate) fn root_theme(tree: &IndexedTree) -> Option<Theme> {
+    match root_attribute(tree, AttributeKind::Theme) {
+        Some(AttributeValue::Theme(theme)) => Some(theme),
+        _ => None,
+    }
+}
+
+/// Returns true if the root container of the markup follows the system appearance with `theme:"system"`
+pub(crate) fn root_system_theme(tree: &IndexedTree) -> bool {
+    matches!(
+        root_attribute(tree, AttributeKind::SystemTheme),
+        Some(AttributeValue::SystemTheme)
+    )
+}
+
+/// Get the default text size declared in the `text` section of a custom theme definition
+pub(crate) fn root_text_size(tree: &IndexedTree) -> Option<Pixels> {
+    match root_attribute(tree, AttributeKind::Size) {
+        Some(AttributeValue::Size(size)) => Some(size),
+        _ => None,
+    }
+}
+
+/// Get an attribute of the root node, set by a custom theme definition, or the root container
+fn root_attribute(tree: &IndexedTree, kind: AttributeKind) -> Option<AttributeValue> {
+    let root = tree.root().node();
+    if let Ok(Some(value)) = root.data().attrs.get(kind) {
+        return Some(value);
+    }
+
+    let container = root.children()?.first()?.clone();
+    let node = container.node();
+    node.data().attrs.get(kind).ok().flatten()
+}
+
 /*
 impl TryInto<Theme> for &Value {
     type Error = ConversionError;
@@ -165,10 +276,75 @@ impl TryInto<Theme> for Attribute {
 #[cfg(test)]
 mod test {
 
-    use super::SnowcapTheme;
+    use iced::{Color, Pixels, Theme};
+
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+
+    use super::{ancestor_theme_dirty, root_text_size, root_theme, SnowcapTheme};
+    use crate::{node::State, Message, SnowcapParser};
 
     #[test]
     pub fn from_string() {
         let _theme = SnowcapTheme::try_from("Light").unwrap().theme();
+
+        // Display names of the built-in themes are accepted
+        for theme in Theme::ALL {
+            let name = theme.to_string();
+            assert_eq!(
+                SnowcapTheme::try_from(name.as_str()).unwrap().theme(),
+                theme
+            );
+        }
+        assert_eq!(
+            SnowcapTheme::try_from("tokyo-night-storm").unwrap().theme(),
+            &Theme::TokyoNightStorm
+        );
+    }
+
+    #[test]
+    pub fn markup_root_theme() {
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{<theme:"dracula"> text("themed")}"#)
+            .unwrap()
+            .index();
+        assert_eq!(root_theme(&tree), Some(Theme::Dracula));
+
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{text("default")}"#)
+            .unwrap()
+            .index();
+        assert_eq!(root_theme(&tree), None);
+    }
+
+    #[test]
+    pub fn markup_custom_theme() {
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"theme "ocean" { palette { primary:#336699 }, text { size:14 } } {text("custom")}"#,
+        )
+        .unwrap()
+        .index();
+
+        let theme = root_theme(&tree).unwrap();
+        assert_eq!(theme.to_string(), "ocean");
+        assert_eq!(theme.palette().primary, Color::from_rgb8(0x33, 0x66, 0x99));
+        assert_eq!(root_text_size(&tree), Some(Pixels(14.0)));
+    }
+
+    #[test]
+    pub fn themer_dirty() {
+        let tree =
+            SnowcapParser::<Message>::parse_memory(r#"{themer<theme:"nord">(markdown("Themed"))}"#)
+                .unwrap()
+                .index();
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let themer = container.node().children().unwrap()[0].clone();
+        let markdown = themer.node().children().unwrap()[0].clone();
+
+        themer.node_mut().data_mut().set_state(State::Clean);
+        assert!(!ancestor_theme_dirty(&markdown));
+
+        // A changed theme of the themer is propagated to the themed widgets inside it
+        themer.node_mut().data_mut().set_dirty(true);
+        assert!(ancestor_theme_dirty(&markdown));
+        assert!(!ancestor_theme_dirty(&themer));
     }
 }
@@ -0,0 +1,366 @@
+//! Video playback
+//!
+//! Video data from a module (such as a file with a video format) is played by the `video` widget. Snowcap doesn't
+//! include a video decoder, instead the application provides a [`VideoDecoder`] with [`crate::Snowcap::set_video_decoder()`],
+//! which opens a [`VideoStream`] producing the frames of the video.
+//!
+//! ```text
+//! video#intro(file!{path:"clip.mp4"})
+//! ```
+//!
+//! The first frame is shown until the video is clicked, which toggles playback. Starting and pausing playback emit
+//! [`WidgetEvent::VideoPlay`] and [`WidgetEvent::VideoPause`] widget messages, and [`WidgetEvent::VideoEnded`] is
+//! emitted when the stream has no more frames.
+
+use std::{sync::Arc, time::Duration};
+
+use iced::{
+    advanced::{
+        layout, mouse, renderer,
+        widget::{tree, Tree},
+        Layout, Renderer as _, Shell, Widget,
+    },
+    event,
+    time::Instant,
+    widget::{image::Handle, Image},
+    window, Color, Event, Length, Rectangle, Size,
+};
+use salish::Message;
+use tracing::warn;
+
+use crate::{
+    error::ConversionError,
+    identity::StableId,
+    message::widget::{WidgetEvent, WidgetMessage},
+    NodeId,
+};
+
+/// Height of the placeholder shown before the first frame is decoded
+const PLACEHOLDER_HEIGHT: f32 = 180.0;
+
+/// Opens video data for playback
+pub trait VideoDecoder: Send + Sync {
+    /// Open a [`VideoStream`] of the encoded video data
+    fn open(&self, data: Arc<Vec<u8>>) -> Result<Box<dyn VideoStream>, ConversionError>;
+}
+
+impl std::fmt::Debug for dyn VideoDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("VideoDecoder")
+    }
+}
+
+/// Frames of an opened video. Streams are polled from the UI thread on each redraw while playing,
+/// so decoding should happen in the background with this returning the most recent decoded frame.
+pub trait VideoStream: Send {
+    /// Get the frame to show at `position` from the start of the video, or `None` once the video has ended
+    fn frame_at(&mut self, position: Duration) -> Option<Handle>;
+
+    /// Time between frames, used to schedule redraws while playing
+    fn frame_interval(&self) -> Duration;
+}
+
+/// Playback state of a video widget, kept in the widget tree so it survives rebuilds of the node
+#[derive(Default)]
+pub(crate) struct Playback {
+    /// Video data the stream was opened from, to reopen the stream if the data changes. The data is kept, so it
+    /// can't be freed and replaced by other data at the same address.
+    source: Option<Arc<Vec<u8>>>,
+    stream: Option<Box<dyn VideoStream>>,
+    playing: bool,
+    position: Duration,
+    last: Option<Instant>,
+    frame: Option<Handle>,
+}
+
+impl Playback {
+    /// Open a stream of the video data if it isn't already open, and show the first frame
+    fn open(&mut self, decoder: &dyn VideoDecoder, data: &Arc<Vec<u8>>) {
+        let opened = self
+            .source
+            .as_ref()
+            .is_some_and(|source| Arc::ptr_eq(source, data));
+        if self.stream.is_some() && opened {
+            return;
+        }
+
+        *self = Self {
+            source: Some(data.clone()),
+            ..Default::default()
+        };
+
+        match decoder.open(data.clone()) {
+            Ok(mut stream) => {
+                self.frame = stream.frame_at(Duration::ZERO);
+                self.stream = Some(stream);
+            }
+            Err(e) => warn!("Failed to open video: {e}"),
+        }
+    }
+
+    /// Toggle playback, returning the [`WidgetEvent`] of the new state
+    fn toggle(&mut self) -> WidgetEvent {
+        self.playing = !self.playing;
+        self.last = None;
+
+        if self.playing {
+            WidgetEvent::VideoPlay
+        } else {
+            WidgetEvent::VideoPause
+        }
+    }
+
+    /// Advance the playback position to `now` and update the frame. Returns the time of the next frame
+    /// while playing, or [`WidgetEvent::VideoEnded`] once the stream has ended.
+    fn tick(&mut self, now: Instant) -> Result<Option<Instant>, WidgetEvent> {
+        let Some(stream) = self.stream.as_mut().filter(|_| self.playing) else {
+            return Ok(None);
+        };
+
+        if let Some(last) = self.last {
+            self.position += now.saturating_duration_since(last);
+        }
+        self.last = Some(now);
+
+        match stream.frame_at(self.position) {
+            Some(frame) => {
+                self.frame = Some(frame);
+                Ok(Some(now + stream.frame_interval()))
+            }
+            None => {
+                // Rewind, so playing again starts from the beginning
+                self.playing = false;
+                self.position = Duration::ZERO;
+                self.last = None;
+                Err(WidgetEvent::VideoEnded)
+            }
+        }
+    }
+}
+
+/// Widget playing video data with the [`VideoDecoder`] of the application
+pub(crate) struct VideoPlayer {
+    node_id: NodeId,
+    element_id: Option<String>,
+    stable_id: Option<StableId>,
+    data: Arc<Vec<u8>>,
+    decoder: Arc<dyn VideoDecoder>,
+}
+
+impl VideoPlayer {
+    pub fn new(
+        node_id: NodeId,
+        element_id: Option<String>,
+        stable_id: Option<StableId>,
+        data: Arc<Vec<u8>>,
+        decoder: Arc<dyn VideoDecoder>,
+    ) -> Self {
+        Self {
+            node_id,
+            element_id,
+            stable_id,
+            data,
+            decoder,
+        }
+    }
+
+    fn message(&self, event: WidgetEvent) -> Message {
+        Message::broadcast(
+            WidgetMessage::new(self.node_id, self.element_id.clone(), event)
+                .with_stable_id(self.stable_id.clone()),
+        )
+    }
+}
+
+impl Widget<Message, iced::Theme, iced::Renderer> for VideoPlayer {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<Playback>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(Playback::default())
+    }
+
+    fn size(&self) -> Size<Length> {
+        Size::new(Length::Shrink, Length::Shrink)
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        match tree.state.downcast_ref::<Playback>().frame.clone() {
+            Some(frame) => Widget::<Message, iced::Theme, iced::Renderer>::layout(
+                &Image::new(frame),
+                tree,
+                renderer,
+                limits,
+            ),
+            None => layout::Node::new(limits.resolve(
+                Length::Fill,
+                Length::Fixed(PLACEHOLDER_HEIGHT),
+                Size::ZERO,
+            )),
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &iced::Renderer,
+        _clipboard: &mut dyn iced::advanced::Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        let playback = tree.state.downcast_mut::<Playback>();
+
+        match event {
+            Event::Window(window::Event::RedrawRequested(now)) => {
+                let had_frame = playback.frame.is_some();
+                playback.open(self.decoder.as_ref(), &self.data);
+
+                match playback.tick(now) {
+                    Ok(Some(next)) => shell.request_redraw(window::RedrawRequest::At(next)),
+                    Ok(None) => {}
+                    Err(event) => shell.publish(self.message(event)),
+                }
+
+                // The size of the widget changes once the first frame is available
+                if !had_frame && playback.frame.is_some() {
+                    shell.invalidate_layout();
+                }
+
+                event::Status::Ignored
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if cursor.is_over(layout.bounds()) =>
+            {
+                let event = playback.toggle();
+                shell.publish(self.message(event));
+                shell.request_redraw(window::RedrawRequest::NextFrame);
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        match tree.state.downcast_ref::<Playback>().frame.clone() {
+            Some(frame) => Widget::<Message, iced::Theme, iced::Renderer>::draw(
+                &Image::new(frame),
+                tree,
+                renderer,
+                theme,
+                style,
+                layout,
+                cursor,
+                viewport,
+            ),
+            None => renderer.fill_quad(
+                renderer::Quad {
+                    bounds: layout.bounds(),
+                    ..Default::default()
+                },
+                Color::BLACK,
+            ),
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        _tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        if cursor.is_over(layout.bounds()) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use iced::{time::Instant, widget::image::Handle};
+    use tracing_test::traced_test;
+
+    use super::{Playback, VideoDecoder, VideoStream};
+    use crate::{error::ConversionError, message::widget::WidgetEvent};
+
+    /// A one second video with a frame every 100ms
+    struct TestStream;
+
+    impl VideoStream for TestStream {
+        fn frame_at(&mut self, position: Duration) -> Option<Handle> {
+            (position < Duration::from_secs(1)).then(|| Handle::from_rgba(1, 1, vec![0; 4]))
+        }
+
+        fn frame_interval(&self) -> Duration {
+            Duration::from_millis(100)
+        }
+    }
+
+    struct TestDecoder;
+
+    impl VideoDecoder for TestDecoder {
+        fn open(&self, _data: Arc<Vec<u8>>) -> Result<Box<dyn VideoStream>, ConversionError> {
+            Ok(Box::new(TestStream))
+        }
+    }
+
+    #[traced_test]
+    #[test]
+    fn playback() {
+        let mut playback = Playback::default();
+        playback.open(&TestDecoder, &Arc::new(Vec::new()));
+        assert!(playback.frame.is_some());
+
+        // Paused until toggled
+        let start = Instant::now();
+        assert!(matches!(playback.tick(start), Ok(None)));
+        assert!(matches!(playback.toggle(), WidgetEvent::VideoPlay));
+
+        assert_eq!(
+            playback.tick(start).unwrap(),
+            Some(start + Duration::from_millis(100))
+        );
+        assert!(playback.tick(start + Duration::from_millis(500)).is_ok());
+        assert_eq!(playback.position, Duration::from_millis(500));
+
+        // The stream ends after one second, and playback rewinds
+        assert!(matches!(
+            playback.tick(start + Duration::from_millis(1200)),
+            Err(WidgetEvent::VideoEnded)
+        ));
+        assert!(!playback.playing);
+        assert_eq!(playback.position, Duration::ZERO);
+
+        // The stream is only reopened for other data, even if it's equal
+        let data = Arc::new(vec![1]);
+        playback.open(&TestDecoder, &data);
+        playback.position = Duration::from_millis(300);
+        playback.open(&TestDecoder, &data);
+        assert_eq!(playback.position, Duration::from_millis(300));
+        playback.open(&TestDecoder, &Arc::new(vec![1]));
+        assert_eq!(playback.position, Duration::ZERO);
+    }
+}
@@ -1,19 +1,71 @@
+use std::sync::Arc;
+
 use crate::attribute::{AttributeKind, AttributeValue};
 use crate::cache::WidgetContent;
 use crate::util::ElementWrapper;
 //use crate::util::ElementWrapper;
 use crate::NodeId;
-use iced::widget::{
-    Button, PickList, Rule, Scrollable, Slider, Space, Themer, Toggler, VerticalSlider,
-};
+use iced::widget::{Button, Scrollable, Slider, Space, Themer, Toggler, VerticalSlider};
 use iced::widget::{Image, Svg, Text};
 use salish::Message;
-use tracing::warn;
+use tracing::{debug, warn};
 
 use crate::attribute::Attributes;
+use crate::conversion::animation::AnimatedImage;
+use crate::conversion::code::{self, CODE_WIDGET};
+use crate::conversion::drag;
+use crate::conversion::dropzone::{self, DROPZONE_WIDGET};
+use crate::conversion::markdown::{self, MARKDOWN_WIDGET};
+use crate::conversion::multi_select::MultiSelect;
+use crate::conversion::pick_list::PickListWidget;
+use crate::conversion::registry::WidgetRegistry;
+use crate::conversion::responsive::{Responsive, RESPONSIVE_WIDGET};
+use crate::conversion::rule;
+use crate::conversion::slider::{SliderAdjust, SLIDER_RANGE};
+use crate::conversion::text_input::{Input, InputKind};
+use crate::conversion::video::{VideoDecoder, VideoPlayer};
 use crate::dynamic_widget::DynamicWidget;
 use crate::error::ConversionError;
+use crate::identity::StableId;
 use crate::message::widget::{WidgetEvent, WidgetMessage};
+use crate::scroll::scrollable_id;
+
+/// Names of the widgets built by [`SnowcapWidget::new()`], without a registered factory
+pub(crate) const BUILTIN_WIDGETS: &[&str] = &[
+    "image",
+    "svg",
+    "video",
+    CODE_WIDGET,
+    MARKDOWN_WIDGET,
+    "qr-code",
+    "text",
+    "space",
+    "button",
+    "rule-horizontal",
+    "rule-vertical",
+    "slider",
+    "vertical-slider",
+    "scrollable",
+    "toggler",
+    DROPZONE_WIDGET,
+    RESPONSIVE_WIDGET,
+    "themer",
+    "pick-list",
+    "multi-select",
+    "text-input",
+    "number-input",
+    "password-input",
+];
+
+/// Custom widgets and decoders provided by the application, which widgets are built with
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WidgetProviders {
+    /// Factories of custom widgets
+    pub registry: WidgetRegistry,
+
+    /// Decoder of the data of video widgets
+    pub video_decoder: Option<Arc<dyn VideoDecoder>>,
+}
 
 pub struct SnowcapWidget;
 
@@ -24,22 +76,47 @@ impl SnowcapWidget {
             .with_node_id(8989898)
     }
 
+    /// Error widget rendered in place of content from a failed module
+    pub fn error<'a, M>(error: String) -> DynamicWidget<M> {
+        DynamicWidget::default().with_widget(Text::new(error).style(iced::widget::text::danger))
+    }
+
     pub fn new<'a>(
         node_id: NodeId,
         name: String,
         element_id: Option<String>,
+        stable_id: Option<StableId>,
         attrs: Attributes,
         content: WidgetContent<Message>,
+        providers: &WidgetProviders,
     ) -> Result<DynamicWidget<Message>, ConversionError> {
         match name.as_str() {
             "image" => match content {
                 WidgetContent::Module(module) => {
-                    println!("Image has Module content {module}");
+                    debug!(
+                        %node_id,
+                        element_id = ?element_id,
+                        "Image waiting for module content {module}"
+                    );
                     Ok(DynamicWidget::default().with_widget(Text::new("loading")))
                 }
                 WidgetContent::Image(handle) => {
                     Ok(DynamicWidget::default().with_widget(Image::new(handle)))
                 }
+                WidgetContent::Animation(frames) => {
+                    if let Some(AttributeValue::Animated(true)) =
+                        attrs.get(AttributeKind::Animated)?
+                    {
+                        Ok(DynamicWidget::default().with_widget(AnimatedImage::new(frames)))
+                    } else {
+                        Ok(DynamicWidget::default().with_widget(Image::new(frames.first())))
+                    }
+                }
+                WidgetContent::Error(error) => Ok(Self::error(error)),
+                // The on-error fallback of a failed module is shown in place of the image
+                WidgetContent::Text(text) => {
+                    Ok(DynamicWidget::default().with_widget(Text::new(text)))
+                }
                 _ => Err(ConversionError::InvalidType(format!(
                     "Image expecting WidgetContent::Image {}:{}",
                     file!(),
@@ -54,32 +131,77 @@ impl SnowcapWidget {
                     let svg = Svg::new(handle);
                     Ok(DynamicWidget::default().with_widget(svg))
                 }
+                WidgetContent::Error(error) => Ok(Self::error(error)),
+                // The on-error fallback of a failed module is shown in place of the image
+                WidgetContent::Text(text) => {
+                    Ok(DynamicWidget::default().with_widget(Text::new(text)))
+                }
                 _ => Err(ConversionError::InvalidType(format!(
                     "Image expecting WidgetContent::Image {}:{}",
                     file!(),
                     line!()
                 ))),
             },
-            "markdown" => match content {
+            "video" => match content {
+                WidgetContent::Module(_module) => {
+                    Ok(DynamicWidget::default().with_widget(Text::new("loading")))
+                }
+                WidgetContent::Video(data) => match providers.video_decoder.clone() {
+                    Some(decoder) => Ok(DynamicWidget::default().with_widget(VideoPlayer::new(
+                        node_id, element_id, stable_id, data, decoder,
+                    ))),
+                    None => Ok(Self::error("no video decoder is set".into())),
+                },
+                WidgetContent::Error(error) => Ok(Self::error(error)),
+                // The on-error fallback of a failed module is shown in place of the video
+                WidgetContent::Text(text) => {
+                    Ok(DynamicWidget::default().with_widget(Text::new(text)))
+                }
+                _ => Err(ConversionError::InvalidType(format!(
+                    "Video expecting WidgetContent::Video {}:{}",
+                    file!(),
+                    line!()
+                ))),
+            },
+            CODE_WIDGET => match content {
+                WidgetContent::Module(_module) => {
+                    Ok(DynamicWidget::default().with_widget(Text::new("loading")))
+                }
+                WidgetContent::Error(error) => Ok(Self::error(error)),
+                WidgetContent::Text(text) => Ok(DynamicWidget::default()
+                    .with_widget(ElementWrapper::<Message>::new(code::code(&text, &attrs)?))),
+                WidgetContent::Value(value) => Ok(DynamicWidget::default().with_widget(
+                    ElementWrapper::<Message>::new(code::code(&value.to_string(), &attrs)?),
+                )),
+                _ => Err(ConversionError::InvalidType(format!(
+                    "Code expecting text content. Got {:?} {}:{}",
+                    content,
+                    file!(),
+                    line!()
+                ))),
+            },
+            MARKDOWN_WIDGET => match content {
                 WidgetContent::Module(_module) => {
                     Ok(DynamicWidget::default().with_widget(Text::new("loading")))
                 }
+                WidgetContent::Error(error) => Ok(Self::error(error)),
                 //WidgetContent::Markdown(items) => {
                 WidgetContent::Text(text) => {
                     let items: Vec<iced::widget::markdown::Item> =
                         iced::widget::markdown::parse(&text).collect();
-                    let style =
-                        iced::widget::markdown::Style::from_palette(iced::Theme::Light.palette());
-
-                    let settings = iced::widget::markdown::Settings::default();
+                    let style = markdown::style(&attrs)?;
+                    let settings = markdown::settings(&attrs)?;
 
                     let markdown =
                         iced::widget::markdown(&items, settings, style).map(move |url| {
-                            Message::broadcast(WidgetMessage::new(
-                                node_id,
-                                element_id.clone(),
-                                WidgetEvent::Markdown(url),
-                            ))
+                            Message::broadcast(
+                                WidgetMessage::new(
+                                    node_id,
+                                    element_id.clone(),
+                                    WidgetEvent::Markdown(url),
+                                )
+                                .with_stable_id(stable_id.clone()),
+                            )
                         });
 
                     let wrapped = ElementWrapper::<Message>::new(markdown);
@@ -133,20 +255,26 @@ impl SnowcapWidget {
                     Text::new(value.inner())
                 } else if let WidgetContent::Text(value) = content {
                     Text::new(value)
+                } else if let WidgetContent::Error(error) = content {
+                    return Ok(Self::error(error));
                 } else {
                     Text::new("X")
                 };
 
                 let mut style = iced::widget::text::Style::default();
 
-                //TODO add shaping, font
-
                 for attr in attrs {
                     (text, style) = match attr.value().cloned() {
                         Some(AttributeValue::TextColor(color)) => {
                             style.color = Some(color);
                             (text.color(color), style)
                         }
+                        Some(AttributeValue::TextPalette(color)) => (
+                            text.style(move |theme| iced::widget::text::Style {
+                                color: Some(color.resolve(theme)),
+                            }),
+                            style,
+                        ),
                         Some(AttributeValue::HorizontalAlignment(horizontal)) => {
                             (text.align_x(horizontal), style)
                         }
@@ -162,6 +290,14 @@ impl SnowcapWidget {
                             (text.wrapping(wrapping), style)
                         }
                         Some(AttributeValue::Shaping(shaping)) => (text.shaping(shaping), style),
+                        Some(AttributeValue::Font(font)) => (text.font(font), style),
+                        // Backgrounds are drawn by a container around the text
+                        Some(AttributeValue::Background(_))
+                        | Some(AttributeValue::BackgroundPalette(_)) => {
+                            return Err(ConversionError::UnsupportedAttribute(attr, "Text".into()))
+                        }
+                        // Applied by the widget cache
+                        _ if drag::is_drag(attr.kind()) => (text, style),
                         _ => {
                             warn!("Unsupported Text attribute {:?}", attr);
                             (text, style)
@@ -181,13 +317,18 @@ impl SnowcapWidget {
 
             "button" => {
                 let mut button = Button::new(content).on_press_with(move || {
-                    Message::broadcast(WidgetMessage::new(
-                        node_id,
-                        element_id.clone(),
-                        WidgetEvent::ButtonPress,
-                    ))
+                    Message::broadcast(
+                        WidgetMessage::new(node_id, element_id.clone(), WidgetEvent::ButtonPress)
+                            .with_stable_id(stable_id.clone()),
+                    )
                 });
 
+                // Colors override the style of the theme, with palette colors resolved against it
+                let mut text_color = None;
+                let mut text_palette = None;
+                let mut background = None;
+                let mut background_palette = None;
+
                 for attr in attrs {
                     button = match attr.value().cloned() {
                         Some(AttributeValue::HeightLength(height)) => button.height(height),
@@ -195,14 +336,51 @@ impl SnowcapWidget {
                         Some(AttributeValue::WidthLength(width)) => button.width(width),
                         Some(AttributeValue::WidthPixels(width)) => button.width(width),
                         Some(AttributeValue::Padding(padding)) => button.padding(padding),
+                        Some(AttributeValue::TextColor(color)) => {
+                            text_color = Some(color);
+                            button
+                        }
+                        Some(AttributeValue::TextPalette(color)) => {
+                            text_palette = Some(color);
+                            button
+                        }
+                        Some(AttributeValue::Background(color)) => {
+                            background = Some(color);
+                            button
+                        }
+                        Some(AttributeValue::BackgroundPalette(color)) => {
+                            background_palette = Some(color);
+                            button
+                        }
                         _ => button,
                     }
                 }
 
+                if text_color.is_some()
+                    || text_palette.is_some()
+                    || background.is_some()
+                    || background_palette.is_some()
+                {
+                    button = button.style(move |theme, status| {
+                        let mut style = iced::widget::button::primary(theme, status);
+                        if let Some(color) =
+                            text_color.or_else(|| text_palette.map(|color| color.resolve(theme)))
+                        {
+                            style.text_color = color;
+                        }
+                        if let Some(background) = background
+                            .or_else(|| background_palette.map(|color| color.resolve(theme).into()))
+                        {
+                            style.background = Some(background);
+                        }
+                        style
+                    });
+                }
+
                 Ok(DynamicWidget::default().with_widget(button))
             }
-            "rule-horizontal" => Ok(DynamicWidget::default().with_widget(Rule::horizontal(1))),
-            "rule-vertical" => Ok(DynamicWidget::default().with_widget(Rule::vertical(1))),
+            "rule-horizontal" => rule::build(attrs, false),
+            "rule-vertical" => rule::build(attrs, true),
 
             "slider" => {
                 let value = if let Some(AttributeValue::SliderValue(value)) =
@@ -213,33 +391,60 @@ impl SnowcapWidget {
                     0
                 };
 
+                let adjust = SliderAdjust::new(
+                    node_id,
+                    element_id.clone(),
+                    stable_id.clone(),
+                    attrs.clone(),
+                )?;
+
                 let _element_id = element_id.clone();
+                let _stable_id = stable_id.clone();
                 let _attrs = attrs.clone();
-                let mut slider = Slider::<i32, Message>::new(0..=32768, value, move |val| {
+                let mut slider = Slider::<i32, Message>::new(SLIDER_RANGE, value, move |val| {
                     _attrs.set(AttributeValue::SliderValue(val)).unwrap();
 
-                    Message::broadcast(WidgetMessage::new(
-                        node_id,
-                        _element_id.clone(),
-                        WidgetEvent::SliderChanged(val),
-                    ))
+                    Message::broadcast(
+                        WidgetMessage::new(
+                            node_id,
+                            _element_id.clone(),
+                            WidgetEvent::SliderChanged(val),
+                        )
+                        .with_stable_id(_stable_id.clone()),
+                    )
                 })
-                .on_release(Message::broadcast(WidgetMessage::new(
-                    node_id,
-                    element_id.clone(),
-                    WidgetEvent::SliderReleased(value),
-                )));
+                .on_release(Message::broadcast(
+                    WidgetMessage::new(
+                        node_id,
+                        element_id.clone(),
+                        WidgetEvent::SliderReleased(value),
+                    )
+                    .with_stable_id(stable_id.clone()),
+                ));
 
                 for attr in attrs {
                     slider = match attr.value().cloned() {
                         Some(AttributeValue::HeightPixels(height)) => slider.height(height),
                         Some(AttributeValue::WidthLength(width)) => slider.width(width),
                         Some(AttributeValue::WidthPixels(width)) => slider.width(width),
+                        Some(AttributeValue::TextColor(_))
+                        | Some(AttributeValue::TextPalette(_))
+                        | Some(AttributeValue::Background(_))
+                        | Some(AttributeValue::BackgroundPalette(_)) => {
+                            return Err(ConversionError::UnsupportedAttribute(
+                                attr,
+                                "Slider".into(),
+                            ))
+                        }
                         _ => slider,
                     }
                 }
 
-                Ok(DynamicWidget::default().with_widget(slider))
+                if adjust.enabled() {
+                    Ok(adjust.wrap(slider, false))
+                } else {
+                    Ok(DynamicWidget::default().with_widget(slider))
+                }
             }
 
             "vertical-slider" => {
@@ -251,48 +456,81 @@ impl SnowcapWidget {
                     0
                 };
 
+                let adjust = SliderAdjust::new(
+                    node_id,
+                    element_id.clone(),
+                    stable_id.clone(),
+                    attrs.clone(),
+                )?;
+
                 let _element_id = element_id.clone();
+                let _stable_id = stable_id.clone();
                 let _attrs = attrs.clone();
                 let mut slider =
-                    VerticalSlider::<i32, Message>::new(0..=32768, value, move |val| {
+                    VerticalSlider::<i32, Message>::new(SLIDER_RANGE, value, move |val| {
                         _attrs.set(AttributeValue::SliderValue(val)).unwrap();
 
-                        Message::broadcast(WidgetMessage::new(
-                            node_id,
-                            _element_id.clone(),
-                            WidgetEvent::SliderChanged(val),
-                        ))
+                        Message::broadcast(
+                            WidgetMessage::new(
+                                node_id,
+                                _element_id.clone(),
+                                WidgetEvent::SliderChanged(val),
+                            )
+                            .with_stable_id(_stable_id.clone()),
+                        )
                     })
-                    .on_release(Message::broadcast(WidgetMessage::new(
-                        node_id,
-                        element_id,
-                        WidgetEvent::SliderReleased(value),
-                    )));
+                    .on_release(Message::broadcast(
+                        WidgetMessage::new(node_id, element_id, WidgetEvent::SliderReleased(value))
+                            .with_stable_id(stable_id.clone()),
+                    ));
 
                 for attr in attrs {
                     slider = match attr.value().cloned() {
                         Some(AttributeValue::HeightLength(height)) => slider.height(height),
                         Some(AttributeValue::HeightPixels(height)) => slider.height(height),
                         Some(AttributeValue::WidthPixels(width)) => slider.width(width),
+                        Some(AttributeValue::TextColor(_))
+                        | Some(AttributeValue::TextPalette(_))
+                        | Some(AttributeValue::Background(_))
+                        | Some(AttributeValue::BackgroundPalette(_)) => {
+                            return Err(ConversionError::UnsupportedAttribute(
+                                attr,
+                                "VerticalSlider".into(),
+                            ))
+                        }
                         _ => slider,
                     }
                 }
 
-                Ok(DynamicWidget::default().with_widget(slider))
+                if adjust.enabled() {
+                    Ok(adjust.wrap(slider, true))
+                } else {
+                    Ok(DynamicWidget::default().with_widget(slider))
+                }
             }
 
             "scrollable" => {
                 if let WidgetContent::Widget(widget) = content {
+                    // Scrollables are identified by their stable id, to scroll them with Command::ScrollTo
+                    let id = stable_id.as_ref().map(scrollable_id);
+
                     let mut scroll = Scrollable::new(widget.into_element().unwrap()).on_scroll(
                         move |viewport| {
-                            Message::broadcast(WidgetMessage::new(
-                                node_id,
-                                element_id.clone(),
-                                WidgetEvent::Scrolled(viewport),
-                            ))
+                            Message::broadcast(
+                                WidgetMessage::new(
+                                    node_id,
+                                    element_id.clone(),
+                                    WidgetEvent::Scrolled(viewport),
+                                )
+                                .with_stable_id(stable_id.clone()),
+                            )
                         },
                     );
 
+                    if let Some(id) = id {
+                        scroll = scroll.id(id);
+                    }
+
                     for attr in attrs {
                         scroll = match attr.value().cloned() {
                             Some(AttributeValue::HeightLength(height)) => scroll.height(height),
@@ -303,7 +541,14 @@ impl SnowcapWidget {
                             Some(AttributeValue::ScrollDirection(direction)) => {
                                 scroll.direction(direction)
                             }
-                            _ => todo!(),
+                            // Applied by the widget cache
+                            _ if drag::is_drag(attr.kind()) => scroll,
+                            _ => {
+                                return Err(ConversionError::UnsupportedAttribute(
+                                    attr,
+                                    "Scrollable".into(),
+                                ))
+                            }
                         };
                     }
 
@@ -327,25 +572,78 @@ impl SnowcapWidget {
                 let _attrs = attrs.clone();
                 let mut toggler = Toggler::new(is_toggled).on_toggle(move |toggled| {
                     _attrs.set(AttributeValue::Toggled(toggled)).unwrap();
-                    Message::broadcast(WidgetMessage::new(
-                        node_id,
-                        element_id.clone(),
-                        WidgetEvent::Toggler(toggled),
-                    ))
+                    Message::broadcast(
+                        WidgetMessage::new(
+                            node_id,
+                            element_id.clone(),
+                            WidgetEvent::Toggler(toggled),
+                        )
+                        .with_stable_id(stable_id.clone()),
+                    )
                 });
 
+                let mut active_color = None;
+                let mut handle_color = None;
+
                 for attr in attrs {
                     toggler = match attr.value().cloned() {
                         Some(AttributeValue::Size(pixels)) => toggler.size(pixels),
                         Some(AttributeValue::Label(label)) => toggler.label(label),
+                        Some(AttributeValue::TextSize(pixels)) => toggler.text_size(pixels),
+                        Some(AttributeValue::HorizontalAlignment(horizontal)) => {
+                            toggler.text_alignment(horizontal)
+                        }
+                        Some(AttributeValue::Font(font)) => toggler.font(font),
+                        Some(AttributeValue::ActiveColor(color)) => {
+                            active_color = Some(color);
+                            toggler
+                        }
+                        Some(AttributeValue::HandleColor(color)) => {
+                            handle_color = Some(color);
+                            toggler
+                        }
                         Some(AttributeValue::Toggled(_)) => toggler,
-                        _ => todo!(),
+                        // Published by the engine from the toggle message
+                        Some(AttributeValue::OnToggle(_)) => toggler,
+                        // Applied by the widget cache
+                        _ if drag::is_drag(attr.kind()) => toggler,
+                        _ => {
+                            return Err(ConversionError::UnsupportedAttribute(
+                                attr,
+                                "Toggler".into(),
+                            ))
+                        }
                     };
                 }
 
+                if active_color.is_some() || handle_color.is_some() {
+                    toggler = toggler.style(move |theme: &iced::Theme, status| {
+                        use iced::widget::toggler::{default, Status};
+
+                        let mut style = default(theme, status);
+                        let toggled = matches!(
+                            status,
+                            Status::Active { is_toggled: true }
+                                | Status::Hovered { is_toggled: true }
+                        );
+                        if let (Some(color), true) = (active_color, toggled) {
+                            style.background = color;
+                        }
+                        if let Some(color) = handle_color {
+                            style.foreground = color;
+                        }
+                        style
+                    });
+                }
+
                 Ok(DynamicWidget::default().with_widget(toggler))
             }
 
+            DROPZONE_WIDGET => dropzone::build(attrs, content),
+            RESPONSIVE_WIDGET => {
+                let responsive = Responsive::new(node_id, element_id, stable_id, content.into());
+                Ok(DynamicWidget::default().with_widget(responsive))
+            }
             "themer" => {
                 let theme =
                     if let Some(AttributeValue::Theme(theme)) = attrs.get(AttributeKind::Theme)? {
@@ -354,10 +652,10 @@ impl SnowcapWidget {
                         None
                     };
 
+                // Without a theme, the content is rendered with the theme of the parent
                 let themer = Themer::new(
-                    move |old_theme| {
-                        tracing::info!("Themer from {:?} to {:?}", old_theme, theme);
-                        theme.as_ref().unwrap().clone()
+                    move |old_theme: &iced::Theme| {
+                        theme.clone().unwrap_or_else(|| old_theme.clone())
                     },
                     content,
                 );
@@ -365,40 +663,36 @@ impl SnowcapWidget {
             }
             "pick-list" => {
                 if let WidgetContent::Value(value) = content {
-                    let current = if let Some(AttributeValue::Selected(selected)) =
-                        attrs.get(AttributeKind::Selected)?
-                    {
-                        Some(selected)
-                    } else {
-                        None
-                    };
-
-                    let values: Vec<String> =
+                    PickListWidget::new(node_id, element_id, stable_id, attrs).build(value.array()?)
+                } else {
+                    Err(ConversionError::InvalidType("expecting value array".into()))
+                }
+            }
+            "multi-select" => {
+                if let WidgetContent::Value(value) = content {
+                    let options: Vec<String> =
                         value.array()?.into_iter().map(|x| x.to_string()).collect();
 
-                    let _attrs = attrs.clone();
-                    let picklist = PickList::new(values, current, move |selected| {
-                        _attrs
-                            .set(AttributeValue::Selected(selected.clone()))
-                            .unwrap();
-
-                        Message::broadcast(WidgetMessage::new(
-                            node_id,
-                            element_id.clone(),
-                            WidgetEvent::PickListSelected(selected),
-                        ))
-                    });
-
-                    Ok(DynamicWidget::default().with_widget(picklist))
+                    MultiSelect::new(node_id, element_id, stable_id, attrs).build(options)
                 } else {
                     Err(ConversionError::InvalidType("expecting value array".into()))
                 }
             }
-            _ => {
-                return Err(ConversionError::UnsupportedWidget(format!(
-                    "Unhandled element type {name}"
-                )))
+            "text-input" | "number-input" | "password-input" => {
+                // Matched by name above, so the kind is always known
+                let kind = InputKind::from_name(&name).unwrap();
+                Input::new(kind, node_id, element_id, stable_id, attrs).build(content)
             }
+            // Names which aren't built in are built by factories registered by the application
+            _ => match providers.registry.get(&name) {
+                Some(factory) => {
+                    debug!(%node_id, element_id = ?element_id, "Building custom widget {name}");
+                    factory(node_id, attrs, content)
+                }
+                None => Err(ConversionError::UnsupportedWidget(format!(
+                    "Unhandled element type {name}"
+                ))),
+            },
         }
     }
 }
@@ -6,7 +6,6 @@ use super::{
 };
 use crate::{connector::Inlet, message::Event, parser::error::ParseError};
 use arbutus::NodeId;
-use colored::Colorize;
 use iced::{task::Handle, Task};
 use parking_lot::Mutex;
 use reqwest::header::CONTENT_TYPE;
@@ -24,9 +23,13 @@ pub struct UrlProvider {
 
 impl Drop for UrlProvider {
     fn drop(&mut self) {
-        println!("{} {}", "Provider dropped".bright_magenta(), self);
+        debug!(
+            node_id = ?self.node_id,
+            url = %self.url,
+            "Provider dropped, aborting {} tasks",
+            self.task_handles.len()
+        );
         for handle in &self.task_handles {
-            println!("Aborting task");
             handle.abort();
         }
     }
@@ -0,0 +1,268 @@
+//! Human readable reports of the changes a reload applied to the live tree
+//!
+//! A [`DiffRecorder`] listens to tree events while a diff patch is applied, and produces a [`DiffReport`]
+//! listing the added, removed and modified nodes with a markup snippet of each. When the diff viewer is
+//! enabled with [`crate::Snowcap::set_diff_viewer()`], the most recent report is shown in a panel below the root widget.
+
+use std::sync::Arc;
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{
+    widget::{Column, Scrollable, Text},
+    Color, Element, Font,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    identity::StableId,
+    node::{Content, SnowcapNode},
+    NodeRef,
+};
+
+/// Kind of change made to a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffChange {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl DiffChange {
+    fn symbol(&self) -> char {
+        match self {
+            DiffChange::Added => '+',
+            DiffChange::Removed => '-',
+            DiffChange::Modified => '~',
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            DiffChange::Added => Color::from_rgb8(0x3f, 0xb9, 0x50),
+            DiffChange::Removed => Color::from_rgb8(0xf8, 0x51, 0x49),
+            DiffChange::Modified => Color::from_rgb8(0xd2, 0x99, 0x22),
+        }
+    }
+}
+
+/// A single node change in a [`DiffReport`]
+#[derive(Debug, Clone)]
+pub struct DiffEntry {
+    pub change: DiffChange,
+    pub stable_id: Option<StableId>,
+    pub snippet: String,
+}
+
+impl std::fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ", self.change.symbol())?;
+        if let Some(stable_id) = &self.stable_id {
+            write!(f, "{stable_id} ")?;
+        }
+        f.write_str(&self.snippet)
+    }
+}
+
+/// Changes applied to the live tree by a reload
+#[derive(Debug, Clone, Default)]
+pub struct DiffReport {
+    /// Where the new markup was loaded from
+    pub source: String,
+    pub entries: Vec<DiffEntry>,
+}
+
+impl DiffReport {
+    /// Get the number of entries with the supplied [`DiffChange`]
+    pub fn count(&self, change: DiffChange) -> usize {
+        self.entries.iter().filter(|e| e.change == change).count()
+    }
+
+    /// Create a panel [`Element`] listing the changes of this report
+    pub fn view<'a, M: 'a>(&self) -> Element<'a, M> {
+        let summary = Text::new(format!(
+            "Reload of {}: {} added, {} removed, {} modified",
+            self.source,
+            self.count(DiffChange::Added),
+            self.count(DiffChange::Removed),
+            self.count(DiffChange::Modified),
+        ))
+        .size(14);
+
+        let entries = self.entries.iter().fold(Column::new(), |column, entry| {
+            column.push(
+                Text::new(entry.to_string())
+                    .font(Font::MONOSPACE)
+                    .size(12)
+                    .color(entry.change.color()),
+            )
+        });
+
+        Column::new()
+            .push(summary)
+            .push(Scrollable::new(entries).height(160))
+            .spacing(4)
+            .padding(8)
+            .into()
+    }
+}
+
+impl std::fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Reload of {}", self.source)?;
+        for entry in &self.entries {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Records tree events while a patch is applied. Cloned into the tree event listener.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DiffRecorder {
+    changes: Arc<Mutex<Vec<(DiffChange, NodeRef)>>>,
+}
+
+impl DiffRecorder {
+    /// Record a tree event emitted while patching the tree
+    pub fn record(&self, event: &arbutus::TreeEvent<NodeRef>) {
+        let mut changes = self.changes.lock();
+
+        match event {
+            arbutus::TreeEvent::NodeRemoved { node } => {
+                changes.push((DiffChange::Removed, node.clone()))
+            }
+            arbutus::TreeEvent::NodeReplaced { node } => {
+                changes.push((DiffChange::Modified, node.clone()))
+            }
+            arbutus::TreeEvent::SubtreeInserted { node } => {
+                changes.push((DiffChange::Added, node.clone()))
+            }
+            arbutus::TreeEvent::ChildRemoved { parent, .. }
+            | arbutus::TreeEvent::ChildrenRemoved { parent, .. } => {
+                changes.push((DiffChange::Modified, parent.clone()))
+            }
+            arbutus::TreeEvent::ChildrenAdded { children, .. } => changes.extend(
+                children
+                    .iter()
+                    .map(|child| (DiffChange::Added, child.clone())),
+            ),
+            arbutus::TreeEvent::ChildReplaced { parent, index }
+            | arbutus::TreeEvent::ChildInserted { parent, index } => {
+                let change = match event {
+                    arbutus::TreeEvent::ChildInserted { .. } => DiffChange::Added,
+                    _ => DiffChange::Modified,
+                };
+
+                if let Some(child) = parent
+                    .node()
+                    .children()
+                    .and_then(|children| children.get(*index).cloned())
+                {
+                    changes.push((change, child));
+                }
+            }
+        }
+    }
+
+    /// Build the [`DiffReport`] of the recorded changes. This should be called after the identities
+    /// of the patched tree are rebuilt, so entries include the [`StableId`] of each node.
+    pub fn finish(self, source: impl Into<String>) -> DiffReport {
+        let entries = self
+            .changes
+            .lock()
+            .drain(..)
+            .map(|(change, noderef)| DiffEntry {
+                change,
+                stable_id: noderef.node().data().stable_id().cloned(),
+                snippet: snippet(&noderef),
+            })
+            .collect();
+
+        DiffReport {
+            source: source.into(),
+            entries,
+        }
+    }
+}
+
+/// Render a node as a short markup snippet
+pub(crate) fn snippet(noderef: &NodeRef) -> String {
+    let node = noderef.node();
+    let data: &SnowcapNode = node.data();
+
+    let id = data
+        .element_id
+        .as_ref()
+        .map(|id| format!("#{id}"))
+        .unwrap_or_default();
+
+    let attrs = if data.attrs.len() > 0 {
+        data.attrs.to_string()
+    } else {
+        String::new()
+    };
+
+    match &**data {
+        Content::Widget(name) => {
+            // Inline a single value or module child, such as text("hello")
+            let inner = node
+                .children()
+                .filter(|children| children.len() == 1)
+                .and_then(|children| children.first())
+                .map(|child| {
+                    let inline = matches!(
+                        &**child.node().data(),
+                        Content::Value(_) | Content::Module(_)
+                    );
+
+                    if inline {
+                        snippet(child)
+                    } else {
+                        "..".into()
+                    }
+                })
+                .unwrap_or_default();
+
+            format!("{name}{id}{attrs}({inner})")
+        }
+        Content::Row => format!("row{id}{attrs}[..]"),
+        Content::Column => format!("col{id}{attrs}[..]"),
+        Content::Stack => format!("stack{id}{attrs}[..]"),
+        Content::LazyColumn => format!("lazy-col{id}{attrs}[..]"),
+        Content::Container => format!("{{{attrs} ..}}"),
+        Content::Boundary => format!("error-boundary{id}{attrs} {{..}} fallback {{..}}"),
+        Content::Region => format!("region{id} {{..}}"),
+        Content::Value(value) => value.to_string(),
+        Content::Module(module) => format!(
+            "{}!{{{}}}",
+            module.name(),
+            module.args().to_string().trim_start_matches("args=")
+        ),
+        Content::Root => "root".into(),
+        Content::None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::TreeNodeRef as _;
+    use tracing_test::traced_test;
+
+    use super::snippet;
+    use crate::{Message, SnowcapParser};
+
+    #[traced_test]
+    #[test]
+    fn markup_snippets() {
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{col#main[text#title("Hello")]}"#)
+            .unwrap()
+            .index();
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let column = container.node().children().unwrap()[0].clone();
+        let text = column.node().children().unwrap()[0].clone();
+
+        assert_eq!(snippet(&column), "col#main[..]");
+        assert_eq!(snippet(&text), "text#title(Hello)");
+    }
+}
@@ -7,7 +7,7 @@ use iced::{advanced::Widget, Element};
 use parking_lot::{ArcRwLockWriteGuard, RawRwLock, RwLock};
 use tracing::{debug, warn};
 
-use crate::{NodeId, SyncError};
+use crate::{parser::location::SourceLocation, picker, NodeId, SyncError};
 
 /// Widget reference is an Arc RwLockWriteGuard that can be acquired from a clone of [`DynamicWidget`]
 /// and wrapped into an `iced::Element<'static>`. This enables reuse of the same underlying widget.
@@ -15,6 +15,7 @@ use crate::{NodeId, SyncError};
 /// Only one `WidgetRef` may be acquired at a time from a [`DynamicWidget`].
 pub struct WidgetRef<M> {
     node_id: NodeId,
+    location: Option<SourceLocation>,
     widget: ArcRwLockWriteGuard<RawRwLock, Box<dyn Widget<M, iced::Theme, iced::Renderer>>>,
 }
 
@@ -33,6 +34,8 @@ impl<M> Drop for WidgetRef<M> {
 /// but the DynamicWidget itself and the underlying iced Widget will remain and can be re-acquired on subsequent view() calls.
 pub struct DynamicWidget<M> {
     node_id: Option<NodeId>,
+    /// Location in the markup of the element the widget was built from
+    location: Option<SourceLocation>,
     widget: Option<Arc<RwLock<Box<dyn Widget<M, iced::Theme, iced::Renderer>>>>>,
 }
 
@@ -41,6 +44,7 @@ impl<M> Clone for DynamicWidget<M> {
         //tracing::debug!("Cloning DynamicWidget {:?}", self.node_id);
         DynamicWidget {
             node_id: self.node_id,
+            location: self.location.clone(),
             widget: self.widget.clone(),
         }
     }
@@ -50,6 +54,7 @@ impl<'a, M> std::default::Default for DynamicWidget<M> {
     fn default() -> Self {
         Self {
             node_id: None,
+            location: None,
             widget: None,
         }
     }
@@ -72,6 +77,7 @@ where
             let widget_ref = WidgetRef {
                 widget: guard,
                 node_id: self.node_id.unwrap(),
+                location: self.location,
             };
             //debug!("New WidgetRef node {:?}", self.node_id);
             Ok(Element::new(widget_ref))
@@ -87,7 +93,11 @@ impl<M> std::fmt::Debug for DynamicWidget<M> {
             f,
             "DynamicWidget node_id={:?}",
             self.node_id.unwrap_or(9999999)
-        )
+        )?;
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        Ok(())
     }
 }
 
@@ -95,6 +105,7 @@ impl<M> DynamicWidget<M> {
     pub fn from(widget: impl Widget<M, iced::Theme, iced::Renderer> + 'static) -> Self {
         Self {
             node_id: None,
+            location: None,
             widget: Some(Arc::new(RwLock::new(Box::new(widget)))),
         }
     }
@@ -125,6 +136,17 @@ impl<M> DynamicWidget<M> {
         self
     }
 
+    /// Set the location in the markup of the element the widget was built from
+    pub fn with_location(mut self, location: Option<SourceLocation>) -> Self {
+        self.location = location;
+        self
+    }
+
+    /// Get the location in the markup of the element the widget was built from
+    pub fn location(&self) -> Option<&SourceLocation> {
+        self.location.as_ref()
+    }
+
     /// Replace the inner Boxed dyn Widget. This requires there are no [`WidgetRef`] alive, as they hold a write lock
     pub fn replace(
         &self,
@@ -208,6 +230,9 @@ impl<M> Widget<M, iced::Theme, iced::Renderer> for WidgetRef<M> {
         shell: &mut iced::advanced::Shell<'_, M>,
         viewport: &iced::Rectangle,
     ) -> iced::event::Status {
+        // Offer the location of the element to a Ctrl+click picked by the inspector
+        picker::offer(layout.bounds(), self.location.as_ref());
+
         self.widget.on_event(
             tree, event, layout, cursor, renderer, clipboard, shell, viewport,
         )
@@ -33,6 +33,9 @@ pub enum ConversionError {
     #[error("missing {0}")]
     Missing(String),
 
+    #[error("node {0} has {1} children, expected a single content node")]
+    Children(arbutus::NodeId, usize),
+
     #[error("unknown {0}")]
     Unknown(String),
 
@@ -53,6 +56,9 @@ pub enum ConversionError {
 
     #[error(transparent)]
     Module(#[from] ModuleError),
+
+    #[error("expression {0}")]
+    Expression(#[from] crate::parser::expr::ExprError),
 }
 
 #[derive(Error, Debug)]
@@ -84,6 +90,12 @@ pub enum Error {
     #[error("Node {0} Not Found")]
     NodeNotFound(arbutus::NodeId),
 
+    #[error("Element #{0} Not Found")]
+    ElementNotFound(String),
+
+    #[error("Element #{0} is not a {1}")]
+    ElementKind(String, String),
+
     #[cfg(not(target_arch = "wasm32"))]
     #[error(transparent)]
     Tokio(tokio::task::JoinError),
@@ -100,4 +112,19 @@ pub enum Error {
 
     #[error(transparent)]
     Module(#[from] ModuleError),
+
+    #[error(transparent)]
+    Catalog(#[from] crate::module::i18n::catalog::CatalogError),
+
+    #[error(transparent)]
+    Session(#[from] SessionError),
+}
+
+#[derive(Error, Debug)]
+pub enum SessionError {
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    #[error("io error {0}")]
+    Io(#[from] std::io::Error),
 }
@@ -0,0 +1,261 @@
+//! Keyboard navigation of selection widgets
+//!
+//! Pick lists can be used without a mouse. Tab and Shift+Tab move the keyboard focus between the pick lists of the
+//! markup in the order they are declared, and the focused pick list is drawn with a border in the primary color of
+//! the theme.
+//!
+//! | Key                      | Navigation                                          |
+//! |--------------------------|-----------------------------------------------------|
+//! | Tab, Shift+Tab           | Focus the next or previous pick list                |
+//! | Up or Left, Down or Right| Select the previous or next option                  |
+//! | Home, End                | Select the first or last option                     |
+//! | Enter                    | Select the current option again                     |
+//! | Escape                   | Release the focus                                   |
+//!
+//! Selections send the same [`WidgetEvent::PickListSelected`](crate::message::widget::WidgetEvent) message as a
+//! selection with the mouse, so bindings and event handlers respond the same way. Keys captured by a widget, such
+//! as a text input, are not used for navigation.
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{
+    keyboard::{key::Named, Key, Modifiers},
+    Task,
+};
+use salish::Message;
+use tracing::debug;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue, Attributes},
+    conversion::pick_list::{PickListOption, PickListWidget},
+    message::{Command, Navigation},
+    node::Content,
+    IndexedTree, NodeRef, Value,
+};
+
+/// Name of the widgets which can be focused
+const PICK_LIST_WIDGET: &str = "pick-list";
+
+/// Map a key press to a [`Command::Navigate`] message, for [`iced::keyboard::on_key_press()`]
+pub(crate) fn key_press(key: Key, modifiers: Modifiers) -> Option<Message> {
+    let navigation = match key {
+        Key::Named(Named::Tab) if modifiers.shift() => Navigation::Previous,
+        Key::Named(Named::Tab) => Navigation::Next,
+        Key::Named(Named::ArrowUp | Named::ArrowLeft) => Navigation::Up,
+        Key::Named(Named::ArrowDown | Named::ArrowRight) => Navigation::Down,
+        Key::Named(Named::Home) => Navigation::First,
+        Key::Named(Named::End) => Navigation::Last,
+        Key::Named(Named::Enter) => Navigation::Activate,
+        Key::Named(Named::Escape) => Navigation::Cancel,
+        _ => return None,
+    };
+
+    Some(Message::broadcast(Command::Navigate(navigation)))
+}
+
+/// Returns true if the element of the attributes has the keyboard focus
+pub(crate) fn is_focused(attrs: &Attributes) -> bool {
+    matches!(
+        attrs.get(AttributeKind::Focused),
+        Ok(Some(AttributeValue::Focused(true)))
+    )
+}
+
+/// Move the focus between the pick lists of the tree, or change the selection of the focused pick list.
+/// Returns a [`Task`] sending the selection.
+pub(crate) fn navigate(tree: &IndexedTree, navigation: Navigation) -> Task<Message> {
+    let targets = focusable(tree);
+    let focused = targets
+        .iter()
+        .position(|(noderef, _)| is_focused(&noderef.node().data().attrs));
+
+    match navigation {
+        Navigation::Next | Navigation::Previous => {
+            let len = targets.len();
+            if len == 0 {
+                return Task::none();
+            }
+
+            let next = match (focused, navigation) {
+                (None, Navigation::Next) => 0,
+                (None, _) => len - 1,
+                (Some(index), Navigation::Next) => (index + 1) % len,
+                (Some(index), _) => (index + len - 1) % len,
+            };
+
+            if let Some(index) = focused {
+                set_focused(&targets[index].0, false);
+            }
+            set_focused(&targets[next].0, true);
+            Task::none()
+        }
+        Navigation::Cancel => {
+            if let Some(index) = focused {
+                set_focused(&targets[index].0, false);
+            }
+            Task::none()
+        }
+        _ => match focused {
+            Some(index) => select(&targets[index].0, &targets[index].1, navigation),
+            None => Task::none(),
+        },
+    }
+}
+
+/// Get the pick lists of the tree with their options, in the order they are declared
+fn focusable(tree: &IndexedTree) -> Vec<(NodeRef, Vec<Value>)> {
+    let mut found = Vec::new();
+    let mut pending = vec![tree.root().clone()];
+
+    while let Some(noderef) = pending.pop() {
+        let children: Vec<NodeRef> = noderef
+            .node()
+            .children()
+            .map(|children| children.iter().cloned().collect())
+            .unwrap_or_default();
+
+        let is_pick_list = matches!(
+            noderef.node().data().content(),
+            Content::Widget(name) if name == PICK_LIST_WIDGET
+        );
+
+        if is_pick_list {
+            // Only options declared in the markup can be navigated
+            let options = children
+                .iter()
+                .find_map(|child| match child.node().data().content() {
+                    Content::Value(value) => value.array().ok().cloned(),
+                    _ => None,
+                });
+
+            if let Some(options) = options {
+                found.push((noderef.clone(), options));
+            }
+        }
+
+        pending.extend(children.into_iter().rev());
+    }
+
+    found
+}
+
+/// Set the focus of a pick list, and mark it dirty to redraw its border
+fn set_focused(noderef: &NodeRef, focused: bool) {
+    let attrs = noderef.node().data().attrs.clone();
+    if is_focused(&attrs) == focused {
+        return;
+    }
+
+    if attrs.set(AttributeValue::Focused(focused)).is_ok() {
+        debug!(node_id = %noderef.node().id(), focused, "Keyboard focus changed");
+        noderef.clone().node_mut().data_mut().set_dirty(true);
+    }
+}
+
+/// Select an option of the focused pick list, and get a [`Task`] sending the selection
+fn select(noderef: &NodeRef, values: &[Value], navigation: Navigation) -> Task<Message> {
+    let node = noderef.node();
+    let data = node.data();
+
+    let options: Vec<PickListOption> = values.iter().map(PickListOption::from_value).collect();
+    let Some(last) = options.len().checked_sub(1) else {
+        return Task::none();
+    };
+
+    let picklist = PickListWidget::new(
+        node.id(),
+        data.element_id.clone(),
+        data.stable_id().cloned(),
+        data.attrs.clone(),
+    );
+
+    let current = picklist
+        .current(&options)
+        .ok()
+        .flatten()
+        .and_then(|current| options.iter().position(|option| *option == current));
+
+    let index = match (navigation, current) {
+        (Navigation::Up, Some(index)) => index.saturating_sub(1),
+        (Navigation::Up, None) => last,
+        (Navigation::Down, Some(index)) => (index + 1).min(last),
+        (Navigation::Down, None) => 0,
+        (Navigation::First, _) => 0,
+        (Navigation::Last, _) => last,
+        (Navigation::Activate, Some(index)) => index,
+        _ => return Task::none(),
+    };
+
+    // Moving past the first or last option doesn't send a selection
+    if current == Some(index) && navigation != Navigation::Activate {
+        return Task::none();
+    }
+
+    Task::done(picklist.select(options[index].clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::TreeNodeRef as _;
+    use iced::keyboard::{key::Named, Key, Modifiers};
+    use tracing_test::traced_test;
+
+    use super::{focusable, is_focused, key_press, navigate};
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        message::Navigation,
+        Message, SnowcapParser,
+    };
+
+    #[traced_test]
+    #[test]
+    fn navigate_pick_lists() {
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{|[pick-list#first<selected:"b">(["a", "b", "c"]), text("x"), pick-list#second(["d", "e"])]}"#,
+        )
+        .unwrap()
+        .index();
+
+        let targets = focusable(&tree);
+        assert_eq!(targets.len(), 2);
+        let first = targets[0].0.node().data().attrs.clone();
+        let second = targets[1].0.node().data().attrs.clone();
+
+        // Arrows do nothing without a focused pick list
+        let _ = navigate(&tree, Navigation::Down);
+        assert_eq!(
+            first.get(AttributeKind::Selected).unwrap(),
+            Some(AttributeValue::Selected("b".into()))
+        );
+
+        let _ = navigate(&tree, Navigation::Next);
+        assert!(is_focused(&first));
+
+        let _ = navigate(&tree, Navigation::Down);
+        assert_eq!(
+            first.get(AttributeKind::Selected).unwrap(),
+            Some(AttributeValue::Selected("c".into()))
+        );
+
+        let _ = navigate(&tree, Navigation::First);
+        assert_eq!(
+            first.get(AttributeKind::Selected).unwrap(),
+            Some(AttributeValue::Selected("a".into()))
+        );
+
+        // Focus wraps around from the first to the last pick list
+        let _ = navigate(&tree, Navigation::Previous);
+        assert!(!is_focused(&first));
+        assert!(is_focused(&second));
+
+        let _ = navigate(&tree, Navigation::Cancel);
+        assert!(!is_focused(&second));
+    }
+
+    #[traced_test]
+    #[test]
+    fn navigation_keys() {
+        assert!(key_press(Key::Named(Named::Tab), Modifiers::SHIFT).is_some());
+        assert!(key_press(Key::Named(Named::ArrowDown), Modifiers::empty()).is_some());
+        assert!(key_press(Key::Character("a".into()), Modifiers::empty()).is_none());
+    }
+}
@@ -0,0 +1,146 @@
+//! Export of the node hierarchy as graph text
+//!
+//! [`crate::Snowcap::dump_graph()`] renders the live tree as a Graphviz DOT digraph or a Mermaid flowchart. Each node
+//! is labelled with its node id, content kind, [`crate::StableId`] and content hash, the same hash compared when
+//! diffing a reloaded tree, so graphs dumped before and after a reload show which nodes were replaced.
+//!
+//! ```ignore
+//! std::fs::write("tree.dot", snow.dump_graph(GraphFormat::Dot).unwrap())?;
+//! ```
+//!
+//! Render DOT output with `dot -Tsvg tree.dot -o tree.svg`. Mermaid output can be pasted into Markdown documentation
+//! in a `mermaid` code block.
+
+use std::{
+    fmt::Write as _,
+    hash::{Hash as _, Hasher as _},
+};
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use xxhash_rust::xxh64::Xxh64;
+
+use crate::NodeRef;
+
+/// Text format of a graph exported by [`crate::Snowcap::dump_graph()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz DOT digraph
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+}
+
+/// Render the subtree of a node as graph text
+pub(crate) fn dump(root: &NodeRef, format: GraphFormat) -> String {
+    let mut graph = String::new();
+
+    match format {
+        GraphFormat::Dot => {
+            graph.push_str("digraph snowcap {\n");
+            graph.push_str("  node [shape=box, fontname=monospace];\n");
+            write_node(&mut graph, root, format);
+            graph.push_str("}\n");
+        }
+        GraphFormat::Mermaid => {
+            graph.push_str("flowchart TD\n");
+            write_node(&mut graph, root, format);
+        }
+    }
+
+    graph
+}
+
+/// Label of a node, with its id, content kind, stable id and content hash on separate lines
+fn label(noderef: &NodeRef) -> Vec<String> {
+    let node = noderef.node();
+    let data = node.data();
+
+    let mut hasher = Xxh64::new(0);
+    data.hash(&mut hasher);
+
+    let mut lines = vec![format!("{} {}", node.id(), data.content())];
+    if let Some(stable_id) = data.stable_id() {
+        lines.push(stable_id.to_string());
+    }
+    lines.push(format!("{:016x}", hasher.finish()));
+    lines
+}
+
+/// Write the declaration of a node and the edges to its children, followed by its children
+fn write_node(graph: &mut String, noderef: &NodeRef, format: GraphFormat) {
+    let node_id = noderef.node().id();
+    let lines = label(noderef);
+
+    let _ = match format {
+        GraphFormat::Dot => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|line| line.replace('\\', "\\\\").replace('"', "\\\""))
+                .collect();
+            writeln!(graph, "  n{node_id} [label=\"{}\"];", lines.join("\\n"))
+        }
+        GraphFormat::Mermaid => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|line| line.replace('"', "#quot;"))
+                .collect();
+            writeln!(graph, "  n{node_id}[\"{}\"]", lines.join("<br/>"))
+        }
+    };
+
+    let children = noderef
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+
+    for child in &children {
+        let child_id = child.node().id();
+        let _ = match format {
+            GraphFormat::Dot => writeln!(graph, "  n{node_id} -> n{child_id};"),
+            GraphFormat::Mermaid => writeln!(graph, "  n{node_id} --> n{child_id}"),
+        };
+    }
+
+    for child in &children {
+        write_node(graph, child, format);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use tracing_test::traced_test;
+
+    use super::{dump, GraphFormat};
+    use crate::{identity::IdentityIndex, Message, SnowcapParser};
+
+    #[traced_test]
+    #[test]
+    fn dump_formats() {
+        let tree =
+            SnowcapParser::<Message>::parse_memory(r#"{-[text#title("Hello"), text("World")]}"#)
+                .unwrap()
+                .index();
+        IdentityIndex::build(&tree);
+
+        let root_id = tree.root().node().id();
+        let container_id = tree.root().node().children().unwrap()[0].node().id();
+
+        let dot = dump(tree.root(), GraphFormat::Dot);
+        assert!(dot.starts_with("digraph snowcap {"));
+        assert!(dot.contains(&format!("n{root_id} -> n{container_id};")));
+        assert!(dot.contains("#title"));
+
+        let mermaid = dump(tree.root(), GraphFormat::Mermaid);
+        assert!(mermaid.starts_with("flowchart TD"));
+        assert!(mermaid.contains(&format!("n{root_id} --> n{container_id}")));
+
+        // Every node is declared once
+        let declared = dot.lines().filter(|line| line.contains("[label=")).count();
+        assert_eq!(
+            declared,
+            mermaid.lines().filter(|line| line.contains("[\"")).count()
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Headless engine for testing markup and module integration without a window
+//!
+//! [`Headless`] drives a [`Snowcap`] engine the way the iced runtime does, without creating a window or a renderer.
+//! Markup is parsed, and the tasks returned by [`Snowcap::update()`], including the init tasks of module instances,
+//! are run on a local tokio runtime. Each message they produce is passed back to [`Snowcap::update()`].
+//!
+//! The runtime uses a virtual clock, which advances instantly whenever all tasks are waiting on timers, so a
+//! module polling every second can be driven through a minute of updates in a few milliseconds. The virtual clock
+//! is provided by the `test-util` feature of tokio, which is only enabled by the `headless` feature of this crate.
+//!
+//! ```ignore
+//! let tree = Snowcap::run_headless(r#"{|[text#greeting("Hello"), text(timing!{mode:"elapsed"})]}"#)?;
+//! assert!(tree.find("greeting").unwrap().built);
+//! println!("{tree}");
+//! ```
+//!
+//! Widget rebuilds aren't batched, see [`Snowcap::set_update_interval()`], as batching is timed by the real clock.
+
+use std::time::Duration;
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{
+    futures::{
+        future::{select, Either},
+        stream::{BoxStream, SelectAll, StreamExt as _},
+    },
+    Task,
+};
+use iced_runtime::Action;
+use salish::Message;
+use tracing::debug;
+
+use crate::{message::Command, module::data::ModuleDataKind, Error, NodeId, NodeRef, Snowcap};
+
+/// Default number of messages handled by each call to [`Headless::load()`] or [`Headless::run_for()`]
+const DEFAULT_LIMIT: usize = 1024;
+
+/// Default virtual time tasks are driven for after loading markup
+const DEFAULT_DURATION: Duration = Duration::from_secs(1);
+
+/// Description of a node of the tree and the widget built for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WidgetNode {
+    pub node_id: NodeId,
+    pub element_id: Option<String>,
+    /// Kind of content, such as `Widget: text` or `Column`
+    pub kind: String,
+    /// Attributes of the node, empty if there are none
+    pub attrs: String,
+    /// True if a widget was built for the node
+    pub built: bool,
+    /// Module data of the node. Text data is included as text, and other data kinds by their size.
+    pub data: Option<String>,
+    pub children: Vec<WidgetNode>,
+}
+
+impl WidgetNode {
+    fn describe(noderef: &NodeRef, snow: &Snowcap) -> Self {
+        let node = noderef.node();
+        let data = node.data();
+        let node_id = node.id();
+
+        let attrs = if data.attrs.len() > 0 {
+            data.attrs.to_string()
+        } else {
+            String::new()
+        };
+
+        let module_data =
+            data.module_data().map(
+                |module_data| match (module_data.kind(), module_data.bytes()) {
+                    (ModuleDataKind::Text | ModuleDataKind::Error, Ok(bytes)) => {
+                        String::from_utf8_lossy(bytes).to_string()
+                    }
+                    (kind, Ok(bytes)) => format!("{kind:?} ({} bytes)", bytes.len()),
+                    (kind, Err(_)) => format!("{kind:?}"),
+                },
+            );
+
+        let children = node
+            .children()
+            .map(|children| {
+                children
+                    .iter()
+                    .map(|child| WidgetNode::describe(child, snow))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            node_id,
+            element_id: data.element_id.clone(),
+            kind: data.content().to_string(),
+            attrs,
+            built: snow.cache.lock().get(node_id).is_some(),
+            data: module_data,
+            children,
+        }
+    }
+
+    /// Find a node by its element id in this node and its descendants
+    pub fn find(&self, element_id: &str) -> Option<&WidgetNode> {
+        if self.element_id.as_deref() == Some(element_id) {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.find(element_id))
+    }
+
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{:indent$}{}", "", self.kind, indent = depth * 2)?;
+        if let Some(element_id) = &self.element_id {
+            write!(f, " #{element_id}")?;
+        }
+        if !self.attrs.is_empty() {
+            write!(f, " {}", self.attrs)?;
+        }
+        if let Some(data) = &self.data {
+            write!(f, " = {data:?}")?;
+        }
+        if !self.built {
+            write!(f, " (no widget)")?;
+        }
+        writeln!(f)?;
+
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for WidgetNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Drives a [`Snowcap`] engine without a window, on a runtime with a virtual clock
+pub struct Headless {
+    snow: Snowcap,
+    runtime: tokio::runtime::Runtime,
+
+    /// Running tasks, kept between calls so timers continue in [`Headless::run_for()`]
+    streams: SelectAll<BoxStream<'static, Action<Message>>>,
+
+    limit: usize,
+    duration: Duration,
+}
+
+impl std::fmt::Debug for Headless {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Headless")
+            .field("limit", &self.limit)
+            .field("duration", &self.duration)
+            .finish()
+    }
+}
+
+impl Headless {
+    /// Create a headless engine
+    pub fn new() -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()?;
+
+        let mut snow = Snowcap::new()?;
+        snow.set_update_interval(Duration::ZERO);
+
+        Ok(Self {
+            snow,
+            runtime,
+            streams: SelectAll::new(),
+            limit: DEFAULT_LIMIT,
+            duration: DEFAULT_DURATION,
+        })
+    }
+
+    /// Set the maximum number of messages handled by each call to [`Headless::load()`] or [`Headless::run_for()`].
+    /// This bounds modules which emit messages indefinitely, such as periodic timers.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Set the virtual time tasks are driven for after loading markup
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Get the engine
+    pub fn engine(&self) -> &Snowcap {
+        &self.snow
+    }
+
+    /// Get the engine mutably, such as to register modules before loading markup
+    pub fn engine_mut(&mut self) -> &mut Snowcap {
+        &mut self.snow
+    }
+
+    /// Load markup, build its widgets, and drive the init tasks of its modules for the configured duration
+    pub fn load(&mut self, markup: &str) -> Result<&mut Self, Error> {
+        self.snow.load_memory(markup)?;
+
+        // Updates build the widgets of new nodes, and return the init tasks of module instances
+        let task = Task::done(Message::broadcast(Command::Flush));
+        self.drive(task, self.duration);
+        Ok(self)
+    }
+
+    /// Send a message to the engine, and drive the resulting tasks for the configured duration
+    pub fn send(&mut self, message: Message) -> &mut Self {
+        self.drive(Task::done(message), self.duration);
+        self
+    }
+
+    /// Drive running tasks, such as module timers, for a duration of virtual time
+    pub fn run_for(&mut self, duration: Duration) -> &mut Self {
+        self.drive(Task::none(), duration);
+        self
+    }
+
+    /// Describe the tree, and the widgets built for it
+    pub fn describe(&self) -> Option<WidgetNode> {
+        let guard = self.snow.tree.lock();
+        let tree = guard.as_ref()?;
+        Some(WidgetNode::describe(tree.root(), &self.snow))
+    }
+
+    /// Drive a task and every task returned by updating the engine with its messages, until they complete,
+    /// the message limit is reached, or the duration of virtual time elapses
+    fn drive(&mut self, task: Task<Message>, duration: Duration) {
+        let snow = &mut self.snow;
+        let streams = &mut self.streams;
+        let limit = self.limit;
+
+        self.runtime.block_on(async {
+            streams.extend(iced_runtime::task::into_stream(task));
+
+            let mut deadline = Box::pin(tokio::time::sleep(duration));
+
+            let mut handled = 0;
+            while handled < limit {
+                let action = match select(streams.next(), &mut deadline).await {
+                    Either::Left((Some(action), _)) => action,
+                    // All tasks completed, or the duration elapsed
+                    Either::Left((None, _)) | Either::Right(_) => break,
+                };
+
+                // Window and widget operations have no effect without a window
+                if let Action::Output(message) = action {
+                    handled += 1;
+                    streams.extend(iced_runtime::task::into_stream(snow.update(message)));
+                }
+            }
+
+            debug!("Headless engine handled {handled} messages");
+        });
+    }
+}
+
+impl Snowcap {
+    /// Load markup into an engine without a window, drive its module init tasks for a second of virtual time,
+    /// and describe the resulting tree of widgets. See [`Headless`] to drive the engine further.
+    pub fn run_headless(markup: &str) -> Result<WidgetNode, Error> {
+        let mut headless = Headless::new()?;
+        headless.load(markup)?;
+        headless
+            .describe()
+            .ok_or(Error::Unhandled("headless engine has no tree".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_test::traced_test;
+
+    use super::Headless;
+    use crate::{message::Command, Message, Snowcap, Value};
+
+    #[traced_test]
+    #[test]
+    fn headless_widgets() {
+        let tree =
+            Snowcap::run_headless(r#"{|[text#greeting("Hello"), text#rows("World")]}"#).unwrap();
+
+        let greeting = tree.find("greeting").unwrap();
+        assert!(greeting.built);
+        assert_eq!(greeting.kind, "Widget: text");
+        assert!(tree.to_string().contains("#rows"));
+    }
+
+    #[traced_test]
+    #[test]
+    fn headless_virtual_clock() {
+        let mut headless = Headless::new().unwrap();
+        headless
+            .load(r#"{text#elapsed(timing!{mode:"elapsed", periodic:"1s", format:"%s"})}"#)
+            .unwrap();
+
+        // A minute of the timer runs on the virtual clock
+        headless.run_for(Duration::from_secs(60));
+
+        let tree = headless.describe().unwrap();
+        let elapsed = tree.find("elapsed").unwrap();
+        assert!(elapsed.built);
+        let module = &elapsed.children[0];
+        let seconds: u64 = module.data.as_deref().unwrap().parse().unwrap();
+
+        // The timer starts shortly after the virtual clock
+        assert!(seconds >= 59, "{tree}");
+    }
+
+    #[traced_test]
+    #[test]
+    fn headless_undo() {
+        let mut headless = Headless::new().unwrap();
+        headless.load(r#"{text#first("First")}"#).unwrap();
+        headless.load(r#"{text#second("Second")}"#).unwrap();
+
+        headless.send(Message::broadcast(Command::Undo));
+        let tree = headless.describe().unwrap();
+        assert!(tree.find("first").unwrap().built);
+        assert!(tree.find("second").is_none());
+        assert!(headless.engine().can_redo());
+
+        headless.engine_mut().redo().unwrap();
+        assert!(headless.describe().unwrap().find("second").is_some());
+        assert!(!headless.engine().can_redo());
+
+        // Undoing an added child removes it, and rebuilds the widget of its parent without it
+        headless.load(r#"{|[text#first("First")]}"#).unwrap();
+        headless
+            .load(r#"{|[text#first("First"), text#added("Added")]}"#)
+            .unwrap();
+        assert!(headless.describe().unwrap().find("added").unwrap().built);
+
+        headless.send(Message::broadcast(Command::Undo));
+        let tree = headless.describe().unwrap();
+        assert!(tree.find("added").is_none());
+        assert!(tree.find("first").unwrap().built);
+        assert!(headless.engine().timings().build.is_some());
+    }
+
+    #[traced_test]
+    #[test]
+    fn headless_context() {
+        let mut headless = Headless::new().unwrap();
+        headless.engine_mut().set_context("size", 24).unwrap();
+        headless
+            .load(r#"{text#title<size:${ctx.size}>("Hello")}"#)
+            .unwrap();
+        assert!(headless
+            .describe()
+            .unwrap()
+            .find("title")
+            .unwrap()
+            .attrs
+            .contains("24"));
+
+        // Changing a value patches the tree
+        headless.engine_mut().set_context("size", 32).unwrap();
+        let tree = headless.describe().unwrap();
+        assert!(tree.find("title").unwrap().attrs.contains("32"));
+        assert_eq!(headless.engine().context("size"), Some("32"));
+    }
+
+    #[traced_test]
+    #[test]
+    fn headless_state_expression() {
+        let mut headless = Headless::new().unwrap();
+        let state = headless.engine().modules.lock().state().clone();
+        let _ = state.set("title_size", Value::new_integer(20));
+        headless
+            .load(r#"{text#title<size: 10 + title_size>("Hello")}"#)
+            .unwrap();
+        let title = headless.describe().unwrap().find("title").unwrap().node_id;
+
+        // Changing the key rebuilds the widget, which reads the key again
+        let task = state.set("title_size", Value::new_integer(30));
+        headless.drive(task, Duration::from_secs(1));
+        let readers = headless
+            .engine()
+            .cache
+            .lock()
+            .take_state_readers("title_size");
+        assert_eq!(readers, vec![title]);
+        assert!(headless.describe().unwrap().find("title").unwrap().built);
+    }
+
+    #[traced_test]
+    #[test]
+    fn headless_reload_region() {
+        let mut headless = Headless::new().unwrap();
+        headless
+            .load(
+                r#"{-[region#sidebar { text#inbox("Inbox") }, region#content { text#body("Hello") }]}"#,
+            )
+            .unwrap();
+        let body = headless.describe().unwrap().find("body").unwrap().node_id;
+
+        headless
+            .engine_mut()
+            .reload_region("sidebar", r#"|[text#inbox("Inbox"), text#sent("Sent")]"#)
+            .unwrap();
+
+        // The other region is kept
+        let tree = headless.describe().unwrap();
+        assert!(tree.find("sent").unwrap().built);
+        assert_eq!(tree.find("body").unwrap().node_id, body);
+
+        // Spans after the region are moved
+        let span = headless.engine().source_span(body).unwrap();
+        assert_eq!(span.len(), r#"text#body("Hello")"#.len());
+        assert!(span.start > 60);
+
+        assert!(headless
+            .engine_mut()
+            .reload_region("missing", r#"text("None")"#)
+            .is_err());
+        assert!(headless
+            .engine_mut()
+            .reload_region("body", r#"text("None")"#)
+            .is_err());
+
+        headless.engine_mut().undo().unwrap();
+        assert!(headless.describe().unwrap().find("sent").is_none());
+    }
+}
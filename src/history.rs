@@ -0,0 +1,168 @@
+//! Undo and redo of changes to the markup
+//!
+//! Each change patched into the live tree, by [`crate::Snowcap::load_memory()`] or a hot reload of the markup file,
+//! records the markup it replaced. [`crate::Snowcap::undo()`] patches the tree back to the previous markup, and
+//! [`crate::Snowcap::redo()`] reapplies the undone change, so only the nodes which differ are rebuilt and the
+//! state of unchanged widgets and module instances is kept.
+//!
+//! The same can be done by sending [`Command::Undo`](crate::message::Command::Undo) and
+//! [`Command::Redo`](crate::message::Command::Redo), which are applied by the next update.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use parking_lot::Mutex;
+use tracing::debug;
+
+/// Number of changes kept for undo by default
+pub(crate) const DEFAULT_LIMIT: usize = 100;
+
+/// Step through the history requested by a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistoryStep {
+    Undo,
+    Redo,
+}
+
+#[derive(Debug)]
+struct Stacks {
+    /// Markup replaced by each change, the most recent last
+    undo: VecDeque<String>,
+    /// Markup replaced by each undo, the most recent last
+    redo: Vec<String>,
+    /// Maximum number of changes kept for undo
+    limit: usize,
+    /// Steps requested by commands, applied by the next update
+    requested: Vec<HistoryStep>,
+}
+
+/// Markup of the changes which can be undone and redone, shared with the command endpoint
+#[derive(Debug, Clone)]
+pub(crate) struct History(Arc<Mutex<Stacks>>);
+
+impl Default for History {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(Stacks {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit: DEFAULT_LIMIT,
+            requested: Vec::new(),
+        })))
+    }
+}
+
+impl History {
+    /// Record the markup replaced by a change. Changes undone before it can no longer be redone.
+    pub fn record(&self, previous: String) {
+        let mut stacks = self.0.lock();
+        stacks.redo.clear();
+
+        if stacks.limit == 0 {
+            return;
+        }
+
+        while stacks.undo.len() >= stacks.limit {
+            stacks.undo.pop_front();
+        }
+        stacks.undo.push_back(previous);
+
+        debug!("Recorded change, {} can be undone", stacks.undo.len());
+    }
+
+    /// Get the markup before the last change, keeping the current markup to redo
+    pub fn undo(&self, current: String) -> Option<String> {
+        let mut stacks = self.0.lock();
+        let previous = stacks.undo.pop_back()?;
+        stacks.redo.push(current);
+        Some(previous)
+    }
+
+    /// Get the markup of the last undone change, keeping the current markup to undo
+    pub fn redo(&self, current: String) -> Option<String> {
+        let mut stacks = self.0.lock();
+        let next = stacks.redo.pop()?;
+        stacks.undo.push_back(current);
+        Some(next)
+    }
+
+    /// Returns true if there is a change to undo
+    pub fn can_undo(&self) -> bool {
+        !self.0.lock().undo.is_empty()
+    }
+
+    /// Returns true if there is an undone change to redo
+    pub fn can_redo(&self) -> bool {
+        !self.0.lock().redo.is_empty()
+    }
+
+    /// Set the maximum number of changes kept for undo, dropping the oldest changes beyond it
+    pub fn set_limit(&self, limit: usize) {
+        let mut stacks = self.0.lock();
+        stacks.limit = limit;
+        while stacks.undo.len() > limit {
+            stacks.undo.pop_front();
+        }
+    }
+
+    /// Forget all changes, such as when a different file is loaded
+    pub fn clear(&self) {
+        let mut stacks = self.0.lock();
+        stacks.undo.clear();
+        stacks.redo.clear();
+    }
+
+    /// Request a step, applied by the next update
+    pub fn request(&self, step: HistoryStep) {
+        self.0.lock().requested.push(step);
+    }
+
+    /// Take the steps requested since the last update
+    pub fn take_requests(&self) -> Vec<HistoryStep> {
+        std::mem::take(&mut self.0.lock().requested)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::History;
+
+    #[traced_test]
+    #[test]
+    fn undo_redo() {
+        let history = History::default();
+        assert!(history.undo("a".into()).is_none());
+
+        history.record("a".into());
+        history.record("b".into());
+
+        // Current markup is "c"
+        assert_eq!(history.undo("c".into()), Some("b".into()));
+        assert_eq!(history.undo("b".into()), Some("a".into()));
+        assert!(!history.can_undo());
+
+        assert_eq!(history.redo("a".into()), Some("b".into()));
+        assert!(history.can_redo());
+
+        // A new change drops the undone changes
+        history.record("b".into());
+        assert!(!history.can_redo());
+        assert_eq!(history.undo("d".into()), Some("b".into()));
+        assert_eq!(history.undo("b".into()), Some("a".into()));
+    }
+
+    #[traced_test]
+    #[test]
+    fn limit() {
+        let history = History::default();
+        history.set_limit(2);
+
+        for markup in ["a", "b", "c"] {
+            history.record(markup.into());
+        }
+
+        assert_eq!(history.undo("d".into()), Some("c".into()));
+        assert_eq!(history.undo("c".into()), Some("b".into()));
+        assert!(history.undo("b".into()).is_none());
+    }
+}
@@ -0,0 +1,333 @@
+//! Stable node identities which survive tree rebuilds and hot reloads.
+//!
+//! [`NodeId`]s are allocated by the parser, so every reparse of the markup produces a fresh set of ids and
+//! any node replaced during a patch gets a new one. A [`StableId`] is derived from the markup itself, either from the
+//! element id given with `#id`, or from the structural path of child indices relative to the nearest ancestor with an
+//! element id (or the root). Host code should target nodes using [`StableId`] and resolve them to the
+//! current [`NodeId`] with [`crate::Snowcap::resolve()`].
+//!
+//! Element ids should be unique. A node with the same element id as an earlier node in the tree is shadowed by it,
+//! and is reported with the locations of both in the markup as a [`DuplicateElementId`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+
+use crate::{parser::ElementId, ConversionError, IndexedTree, NodeId, NodeRef, SourceLocation};
+
+/// Identity of a node which is stable across reloads of the markup
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StableId {
+    /// Node declared with an element id in the markup (`text#name("...")`)
+    Element(ElementId),
+
+    /// Node identified by the child indices from an anchor. The anchor is the nearest ancestor
+    /// with an element id, or the root of the tree if `anchor` is None.
+    Path {
+        anchor: Option<ElementId>,
+        path: Vec<usize>,
+    },
+}
+
+impl StableId {
+    /// Create a [`StableId`] from an element id
+    pub fn element(id: impl Into<ElementId>) -> Self {
+        StableId::Element(id.into())
+    }
+
+    /// Create a [`StableId`] from a path of child indices starting at the root
+    pub fn path(path: impl Into<Vec<usize>>) -> Self {
+        StableId::Path {
+            anchor: None,
+            path: path.into(),
+        }
+    }
+
+    /// Get the [`StableId`] of the child at `index` of the node with this id
+    pub fn child(&self, index: usize) -> Self {
+        match self {
+            StableId::Element(id) => StableId::Path {
+                anchor: Some(id.clone()),
+                path: vec![index],
+            },
+            StableId::Path { anchor, path } => {
+                let mut path = path.clone();
+                path.push(index);
+                StableId::Path {
+                    anchor: anchor.clone(),
+                    path,
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for StableId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StableId::Element(id) => write!(f, "#{id}"),
+            StableId::Path { anchor, path } => {
+                if let Some(anchor) = anchor {
+                    write!(f, "#{anchor}")?;
+                }
+
+                if path.is_empty() {
+                    return write!(f, "/");
+                }
+
+                for index in path {
+                    write!(f, "/{index}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parse a [`StableId`] from its display form, such as `#name`, `#name/0/1` or `/2/0`
+impl FromStr for StableId {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConversionError::InvalidType(format!("Invalid stable id {s:?}"));
+
+        let (anchor, rest) = if let Some(stripped) = s.strip_prefix('#') {
+            let end = stripped.find('/').unwrap_or(stripped.len());
+            let (anchor, rest) = stripped.split_at(end);
+            if anchor.is_empty() {
+                return Err(invalid());
+            }
+            (Some(anchor.to_string()), rest)
+        } else {
+            (None, s)
+        };
+
+        if rest.is_empty() {
+            return match anchor {
+                Some(anchor) => Ok(StableId::Element(anchor)),
+                None => Err(invalid()),
+            };
+        }
+
+        let rest = rest.strip_prefix('/').ok_or_else(invalid)?;
+
+        let path = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split('/')
+                .map(|index| index.parse::<usize>().map_err(|_| invalid()))
+                .collect::<Result<Vec<usize>, _>>()?
+        };
+
+        Ok(StableId::Path { anchor, path })
+    }
+}
+
+/// Element id given to more than one node in the markup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateElementId {
+    pub id: ElementId,
+    /// Locations of each element with the id, in tree order. The first element is the one the id refers to.
+    pub locations: Vec<SourceLocation>,
+}
+
+impl std::fmt::Display for DuplicateElementId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Duplicate element id #{}", self.id)?;
+
+        let mut locations = self.locations.iter();
+        if let Some(first) = locations.next() {
+            write!(f, " at {first}, shadowing")?;
+            for location in locations {
+                write!(f, " {location}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Bidirectional index between [`StableId`] and the current [`NodeId`] of each node in a tree
+#[derive(Debug, Default)]
+pub(crate) struct IdentityIndex {
+    nodes: HashMap<StableId, NodeId>,
+    ids: HashMap<NodeId, StableId>,
+    /// Node of each element id, looked up without building a [`StableId`]
+    elements: HashMap<ElementId, NodeId>,
+    /// Nodes of each element id given to more than one node, in tree order
+    duplicates: BTreeMap<ElementId, Vec<NodeId>>,
+}
+
+impl IdentityIndex {
+    /// Walk the tree from the root, assigning a [`StableId`] to each node and indexing it.
+    pub fn build(tree: &IndexedTree) -> Self {
+        let mut index = Self::default();
+        index.visit(tree.root().clone(), StableId::path(Vec::new()));
+        index
+    }
+
+    fn visit(&mut self, mut noderef: NodeRef, id: StableId) {
+        let (node_id, id, children) = {
+            let mut node = noderef.node_mut();
+
+            // A node with an element id is anchored to it, otherwise it keeps the structural path
+            let id = match &node.data().element_id {
+                Some(element_id) => StableId::Element(element_id.clone()),
+                None => id,
+            };
+
+            node.data_mut().set_stable_id(id.clone());
+
+            let children: Vec<NodeRef> = node
+                .children()
+                .map(|children| children.iter().cloned().collect())
+                .unwrap_or_default();
+
+            (node.id(), id, children)
+        };
+
+        if let Some(first) = self.nodes.get(&id) {
+            match &id {
+                StableId::Element(element_id) => self
+                    .duplicates
+                    .entry(element_id.clone())
+                    .or_insert_with(|| vec![*first])
+                    .push(node_id),
+                _ => tracing::warn!("Duplicate stable id {id}, node {node_id} will not be indexed"),
+            }
+        } else {
+            if let StableId::Element(element_id) = &id {
+                self.elements.insert(element_id.clone(), node_id);
+            }
+            self.nodes.insert(id.clone(), node_id);
+        }
+        self.ids.insert(node_id, id.clone());
+
+        for (index, child) in children.into_iter().enumerate() {
+            self.visit(child, id.child(index));
+        }
+    }
+
+    /// Get the [`NodeId`] currently assigned to a [`StableId`]
+    pub fn node_id(&self, id: &StableId) -> Option<NodeId> {
+        self.nodes.get(id).copied()
+    }
+
+    /// Get the [`StableId`] of a node
+    pub fn stable_id(&self, node_id: NodeId) -> Option<&StableId> {
+        self.ids.get(&node_id)
+    }
+
+    /// Get the [`NodeId`] of the node with an element id
+    pub fn element(&self, element_id: &str) -> Option<NodeId> {
+        self.elements.get(element_id).copied()
+    }
+
+    /// Report the element ids given to more than one node, with the locations of their elements
+    pub fn validate(
+        &self,
+        mut locate: impl FnMut(NodeId) -> Option<SourceLocation>,
+    ) -> Vec<DuplicateElementId> {
+        self.duplicates
+            .iter()
+            .map(|(id, nodes)| DuplicateElementId {
+                id: id.clone(),
+                locations: nodes
+                    .iter()
+                    .filter_map(|node_id| locate(*node_id))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use tracing_test::traced_test;
+
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+
+    use super::{IdentityIndex, StableId};
+    use crate::{parser::location::SourceMap, Message, SnowcapParser};
+
+    #[traced_test]
+    #[test]
+    fn stable_id_display_roundtrip() {
+        for s in ["#name", "#name/0/1", "/2/0", "/"] {
+            let id = StableId::from_str(s).unwrap();
+            assert_eq!(id.to_string(), s);
+        }
+
+        assert!(StableId::from_str("").is_err());
+        assert!(StableId::from_str("#").is_err());
+        assert!(StableId::from_str("/a").is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn stable_ids_survive_reparse() {
+        let markup = r#"{-[text#first("A"), |[text("B"), text("C")]]}"#;
+
+        let a = SnowcapParser::<Message>::parse_memory(markup)
+            .unwrap()
+            .index();
+        let b = SnowcapParser::<Message>::parse_memory(markup)
+            .unwrap()
+            .index();
+
+        let index_a = IdentityIndex::build(&a);
+        let index_b = IdentityIndex::build(&b);
+
+        assert_eq!(index_a.len(), index_b.len());
+
+        let first = StableId::element("first");
+        let nested = StableId::from_str("/0/0/1/1").unwrap();
+
+        for id in [&first, &nested] {
+            let node_a = index_a.node_id(id).unwrap();
+            let node_b = index_b.node_id(id).unwrap();
+
+            assert_eq!(index_a.stable_id(node_a), Some(id));
+            assert_eq!(index_b.stable_id(node_b), Some(id));
+        }
+    }
+
+    #[traced_test]
+    #[test]
+    fn duplicate_element_ids() {
+        let markup = "{|[\n    text#name(\"A\"),\n    text#other(\"B\"),\n    text#name(\"C\")\n]}";
+        let mut tree = SnowcapParser::<Message>::parse_memory(markup)
+            .unwrap()
+            .index();
+        let index = IdentityIndex::build(&tree);
+
+        let first = index.element("name").unwrap();
+        assert_eq!(index.node_id(&StableId::element("name")), Some(first));
+        assert!(index.element("missing").is_none());
+
+        let nodes = &index.duplicates["name"];
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0], first);
+        assert!(!index.duplicates.contains_key("other"));
+
+        let map = SourceMap::new(None, markup);
+        let duplicates = index.validate(|node_id| {
+            let node = tree.get_node_mut(&node_id)?;
+            let span = node.node().data().span().cloned()?;
+            Some(map.locate_span(&span))
+        });
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(
+            duplicates[0].to_string(),
+            "Duplicate element id #name at 2:5, shadowing 4:5"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Debug inspector overlay
+//!
+//! The inspector is a panel rendered by the engine over the root widget of any snowcap app, listing each node of the
+//! tree with its update state, attributes and the module instances bound to it, followed by the running module
+//! instances and the [`PhaseTimings`] of the last update.
+//!
+//! It is toggled by sending [`Command::ToggleInspector`], by pressing F12 when the application includes
+//! [`crate::Snowcap::subscription()`] in its subscriptions, or with [`crate::Snowcap::set_inspector()`].
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{
+    keyboard::{key::Named, Key, Modifiers},
+    widget::{container, Column, Scrollable, Text},
+    Element, Font, Length,
+};
+use salish::Message;
+
+use crate::{
+    cache::WidgetCache,
+    identity::StableId,
+    message::Command,
+    module::{manager::ModuleInstance, ModuleHandleId},
+    node::State,
+    NodeId, NodeRef, PhaseTimings, SourceLocation,
+};
+
+/// Width of the inspector panel
+const PANEL_WIDTH: f32 = 420.0;
+
+/// A node of the tree as shown by the inspector
+#[derive(Debug, Clone)]
+pub struct InspectedNode {
+    pub depth: usize,
+    pub node_id: NodeId,
+    pub stable_id: Option<StableId>,
+    /// Kind of content, such as `Widget: text` or `Column`
+    pub content: String,
+    /// Location of the element in the markup
+    pub location: Option<SourceLocation>,
+    pub state: State,
+    /// Attributes of the node, empty if there are none
+    pub attrs: String,
+    /// Module instances bound to the node, by its content or attributes
+    pub modules: Vec<ModuleHandleId>,
+    /// True if a widget was built for the node
+    pub built: bool,
+    /// Error of the node if it failed to build
+    pub error: Option<String>,
+}
+
+impl std::fmt::Display for InspectedNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:indent$}{}", "", self.node_id, indent = self.depth * 2)?;
+        if let Some(stable_id) = &self.stable_id {
+            write!(f, " {stable_id}")?;
+        }
+        write!(f, " {}", self.content)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        write!(f, " [{:?}]", self.state)?;
+        if !self.attrs.is_empty() {
+            write!(f, " {}", self.attrs)?;
+        }
+        for handle_id in &self.modules {
+            write!(f, " module:{handle_id}")?;
+        }
+        if !self.built {
+            write!(f, " (no widget)")?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " error: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// State of the engine captured for the inspector, see [`crate::Snowcap::inspect()`]
+#[derive(Debug, Clone, Default)]
+pub struct InspectorReport {
+    /// Nodes of the tree, in depth first order
+    pub nodes: Vec<InspectedNode>,
+    pub modules: Vec<ModuleInstance>,
+    pub timings: PhaseTimings,
+}
+
+impl InspectorReport {
+    /// Capture the nodes of a tree, the widgets built for them, and the running module instances
+    pub(crate) fn capture(
+        root: &NodeRef,
+        cache: &WidgetCache,
+        modules: Vec<ModuleInstance>,
+        timings: PhaseTimings,
+    ) -> Self {
+        let mut report = Self {
+            nodes: Vec::new(),
+            modules,
+            timings,
+        };
+        report.capture_node(root, cache, 0);
+        report
+    }
+
+    fn capture_node(&mut self, noderef: &NodeRef, cache: &WidgetCache, depth: usize) {
+        let node = noderef.node();
+        let data = node.data();
+        let node_id = node.id();
+
+        let modules = self
+            .modules
+            .iter()
+            .filter(|instance| instance.nodes.contains(&node_id))
+            .map(|instance| instance.handle_id)
+            .collect();
+
+        self.nodes.push(InspectedNode {
+            depth,
+            node_id,
+            stable_id: data.stable_id().cloned(),
+            content: data.content().to_string(),
+            location: cache.source_location(data),
+            state: data.get_state(),
+            attrs: if data.attrs.len() > 0 {
+                data.attrs.to_string()
+            } else {
+                String::new()
+            },
+            modules,
+            built: cache.get(node_id).is_some(),
+            error: cache.error(node_id),
+        });
+
+        if let Some(children) = node.children() {
+            for child in children {
+                self.capture_node(child, cache, depth + 1);
+            }
+        }
+    }
+
+    /// Create the inspector panel [`Element`]
+    pub fn view<'a, M: 'a>(&self) -> Element<'a, M> {
+        let heading = |text: &str| Text::new(text.to_string()).size(14);
+        let line = |text: String| Text::new(text).font(Font::MONOSPACE).size(12);
+
+        let nodes = self.nodes.iter().fold(Column::new(), |column, node| {
+            column.push(line(node.to_string()))
+        });
+
+        let modules = self.modules.iter().fold(Column::new(), |column, instance| {
+            column.push(line(format!(
+                "{} {} {} ({})",
+                instance.handle_id, instance.name, instance.args, instance.status
+            )))
+        });
+
+        let timings = if self.timings.is_empty() {
+            "none".to_string()
+        } else {
+            self.timings.to_string()
+        };
+
+        let panel = Column::new()
+            .push(heading(&format!("Tree ({} nodes)", self.nodes.len())))
+            .push(nodes)
+            .push(heading(&format!("Modules ({})", self.modules.len())))
+            .push(modules)
+            .push(heading("Last update"))
+            .push(line(timings))
+            .spacing(4)
+            .padding(8);
+
+        container(Scrollable::new(panel))
+            .width(PANEL_WIDTH)
+            .height(Length::Fill)
+            .style(container::rounded_box)
+            .into()
+    }
+}
+
+impl std::fmt::Display for InspectorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for node in &self.nodes {
+            writeln!(f, "{node}")?;
+        }
+        for instance in &self.modules {
+            writeln!(
+                f,
+                "module {} {} {} ({})",
+                instance.handle_id, instance.name, instance.args, instance.status
+            )?;
+        }
+        writeln!(f, "timings {}", self.timings)
+    }
+}
+
+/// Keyboard shortcut toggling the inspector, subscribed by [`crate::Snowcap::subscription()`]
+pub(crate) fn hotkey(key: Key, _modifiers: Modifiers) -> Option<Message> {
+    match key {
+        Key::Named(Named::F12) => Some(Message::broadcast(Command::ToggleInspector)),
+        _ => None,
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32"), feature = "headless"))]
+mod tests {
+    use iced::keyboard::{key::Named, Key, Modifiers};
+    use tracing_test::traced_test;
+
+    use super::hotkey;
+    use crate::{headless::Headless, node::State};
+
+    #[traced_test]
+    #[test]
+    fn inspect_tree() {
+        let mut headless = Headless::new().unwrap();
+        headless
+            .load(r#"{|[text#greeting("Hello"), text#elapsed(timing!{mode:"elapsed"})]}"#)
+            .unwrap();
+
+        let report = headless.engine().inspect().unwrap();
+
+        let greeting = report
+            .nodes
+            .iter()
+            .find(|node| node.content == "Widget: text" && node.built)
+            .unwrap();
+        assert_eq!(greeting.state, State::Clean);
+
+        // The module instance is listed, and shown on the node bound to it
+        assert_eq!(report.modules.len(), 1);
+        let handle_id = report.modules[0].handle_id;
+        assert!(report
+            .nodes
+            .iter()
+            .any(|node| node.modules.contains(&handle_id)));
+
+        assert!(report.to_string().contains("#greeting"));
+        assert!(report.to_string().contains("timing"));
+
+        assert!(hotkey(Key::Named(Named::F12), Modifiers::empty()).is_some());
+        assert!(hotkey(Key::Named(Named::F1), Modifiers::empty()).is_none());
+    }
+}
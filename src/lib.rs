@@ -14,10 +14,53 @@
 //! Hot reloading is a key goal of [`snowcap`]. Markup files loaded with [`Snowcap::load_file()`] are monitored for changes using [`notify`], and will
 //! automatically be reloaded on change.
 //!
+//! ## Engine Commands
+//! Engine behaviors are triggered by broadcasting a [`message::Command`], so host applications and keybindings control the
+//! engine the same way. Commands reload the markup file, undo and redo changes, set the theme, toggle the inspector,
+//! scroll, clear the widget cache, take a screenshot of the window, dump the tree, and shut down.
+//!
+//! ```ignore
+//! Task::done(Message::broadcast(Command::Screenshot("screenshot.png".into())))
+//! ```
+//!
+//! The path of each screenshot and the graph text of each tree dump are published to the [`message::SCREENSHOT_TOPIC`] and
+//! [`message::TREE_TOPIC`] topics.
+//!
 //! ## Tree Diffing
 //! Tree diffing using Xxh64 hashes is implemented in [`arbutus`] and used to determine changes between the trees, and only affected nodes are
 //! replaced from the new tree into the live tree. Dirty paths are then marked and rebuilt in the [`Snowcap::update()`] phase.
 //!
+//! Nodes store the range of the markup they were parsed from, so a reload only reparses the innermost element enclosing
+//! the changed text, and diffs it against the same element of the live tree. Changes outside any element fall back to
+//! reparsing and diffing the whole file.
+//!
+//! Large apps composed of independent panels can mark them as named regions, and replace the markup of one region
+//! with [`Snowcap::reload_region()`]. Only the nodes inside the region are diffed, and the rest of the tree is kept.
+//!
+//! ```ignore
+//! // {-[region#sidebar { |[text("Inbox"), text("Sent")] }, region#content { text("Select a message") }]}
+//! snow.reload_region("sidebar", r#"|[text("Inbox"), text("Sent"), text("Drafts")]"#)?;
+//! ```
+//!
+//! Replaced nodes get new node ids, so each node is also assigned a [`StableId`] derived from its element id or its
+//! structural path in the markup. Host code can use [`Snowcap::resolve()`] to find the node a [`StableId`] refers to after a reload.
+//!
+//! Element ids are indexed after each load and patch, and [`Snowcap::element()`] looks up the node of an element id.
+//! An element id given to more than one element shadows the later elements, and is logged as a warning with the
+//! locations of each element. [`Snowcap::duplicate_ids()`] returns the [`DuplicateElementId`] reports.
+//!
+//! Each reload produces a [`DiffReport`] of the added, removed and modified nodes, available from [`Snowcap::last_diff()`].
+//! Enabling the diff viewer with [`Snowcap::set_diff_viewer()`] shows the report in a debug panel below the root widget.
+//! [`Snowcap::dump_graph()`] exports the node hierarchy with the content hash of each node as Graphviz DOT or Mermaid text.
+//!
+//! Changes patched into the tree by a reload or [`Snowcap::load_memory()`] can be reverted with [`Snowcap::undo()`] and
+//! reapplied with [`Snowcap::redo()`], or the [`message::Command::Undo`] and [`message::Command::Redo`] commands. Undo
+//! patches the tree back to the previous markup, so only the nodes which differ are rebuilt, which makes the engine
+//! usable as the base of a visual editor.
+//!
+//! Module instances are torn down once all nodes referencing them are removed by a reload. Each module is notified with
+//! [`module::Module::on_shutdown()`], and its endpoints are dropped and running tasks aborted, so timers and sockets aren't leaked.
+//!
 //! ## Widget Caching
 //!
 //! Snowcap caches widgets in-tree, and a root [`iced::Element`] is created from the root widget by reference on each [`Snowcap::view()`] phase.
@@ -32,6 +75,378 @@
 //! along the path. Node references where widgets are dropped are collected into a queue during this iteration pass, and new
 //! widgets are built from the queue (starting with leaves to build children first), and replaced in each [`SnowcapNode`].
 //!
+//! Rebuilds are batched to at most one per update interval, set with [`Snowcap::set_update_interval()`]. Data from
+//! modules sending faster than that updates the tree as it arrives, and the changed nodes are rebuilt together.
+//!
+//! ## Threading
+//!
+//! [`Snowcap`] is [`Send`], so the engine can be created, configured and loaded with markup on a worker thread,
+//! then moved to the thread running the iced application. The module manager and widget cache are shared behind
+//! [`parking_lot::Mutex`] locks rather than `Rc<RefCell>`.
+//!
+//! iced widgets aren't [`Send`], so each widget in the cache is bound to the thread which built it, which is the
+//! thread calling [`Snowcap::init()`] and [`Snowcap::update()`]. The engine must not be moved to another thread
+//! once widgets have been built. Accessing a widget from another thread panics, and widgets dropped on another
+//! thread are leaked rather than destroyed. [`Snowcap`] is not [`Sync`].
+//!
+//! ## Headless Testing
+//!
+//! With the `headless` feature, [`Snowcap::run_headless()`] loads markup into an engine without a window, runs the
+//! init tasks of its modules on a virtual clock, and returns a [`headless::WidgetNode`] description of the tree and the widgets built for it.
+//! [`headless::Headless`] drives the engine further, so markup and module integration can be tested in CI
+//! without a GPU or display.
+//!
+//! Parser and diff regressions can be caught with snapshots of parsed trees, compared by
+//! [`assert_tree_snapshot!`] against files in the `snapshots` directory (see [`testing`]).
+//!
+//! Interaction sequences captured with [`Snowcap::start_recording()`] can be replayed into a fresh headless engine
+//! with [`headless::Headless::replay()`], to reproduce bugs and write regression tests.
+//!
+//! ## Performance Diagnostics
+//!
+//! The time spent parsing, diffing, patching, building widgets and creating the root element is measured, and the
+//! [`PhaseTimings`] of each update which loaded markup or rebuilt widgets are published on the [`perf::topic()`]
+//! diagnostics topic. The last timings are also available from [`Snowcap::timings()`].
+//! Criterion benchmarks of the same phases can be run with `cargo bench`.
+//!
+//! The debug inspector is an overlay listing the nodes of the tree with their state and attributes, the running module
+//! instances, and the timings of the last update. It is toggled with F12, by sending [`message::Command::ToggleInspector`],
+//! or with [`Snowcap::set_inspector()`]. See [`Snowcap::inspect()`] to capture the same information from code.
+//!
+//! Ctrl+clicking a widget while the inspector is shown sends [`message::Command::OpenSource`] with the location of
+//! its element in the markup file, which is published to [`message::OPEN_SOURCE_TOPIC`] as `path:line:column`.
+//! Hosts can open it in an editor, closing the loop with hot reloading:
+//!
+//! ```ignore
+//! let open = snow.on_topic(OPEN_SOURCE_TOPIC, |message| {
+//!     if let Some(location) = message.as_str() {
+//!         let _ = std::process::Command::new("code").args(["--goto", location]).spawn();
+//!     }
+//!     Task::none()
+//! });
+//! ```
+//!
+//! Engine activity is logged with `tracing` events carrying `node_id`, `handle_id` and `element_id` fields. Hosts can
+//! also receive tree loads, module lifecycle changes, widget failures and widget events as [`telemetry::TelemetryEvent`]
+//! values by registering a listener with [`telemetry::subscribe()`].
+//!
+//! Message rates, widget rebuild counts and module latencies are recorded as counters and histograms once
+//! [`metrics::enable()`] is called, and read with [`Snowcap::metrics()`]. The `prometheus` feature adds
+//! [`metrics::MetricsSnapshot::to_prometheus()`] to encode them for a Prometheus scrape endpoint.
+//!
+//! ## Error Boundaries
+//!
+//! An `error-boundary` renders its `fallback` element in place of the guarded element when a widget inside it fails to
+//! convert, or a module inside it fails. The rest of the tree continues to update normally.
+//!
+//! ```text
+//! error-boundary { image(http!{url:"http://example.com/cam.png"}) } fallback { text("camera offline") }
+//! ```
+//!
+//! Outside a boundary, a widget which fails to convert is rendered as a red error placeholder describing the
+//! failure, and its siblings and ancestors are still built.
+//!
+//! Errors and the debug inspector give the location of the element in the markup, such as `button at
+//! sidebar.iced:42:7`. The location of any node is returned by [`Snowcap::source_location()`].
+//!
+//! ## Themes
+//!
+//! A `theme` attribute on the root container sets the theme of every widget, and is applied again on each reload.
+//! The theme can be switched at runtime by sending a [`message::Command::SetTheme`] message, or with [`Snowcap::set_theme()`].
+//! With `theme:"system"` the engine follows the light/dark appearance of the operating system, using the themes
+//! set with [`Snowcap::set_system_themes()`]. The appearance is polled by [`Snowcap::subscription()`], which the
+//! application should include in its subscriptions.
+//!
+//! Colors can reference the palette of the active theme, so they follow theme switches:
+//!
+//! ```text
+//! {<bg:palette(background.weak), text-color:palette(primary)> text("Hello")}
+//! ```
+//!
+//! A custom theme can be defined before the root container, setting the palette and default text size:
+//!
+//! ```text
+//! theme "ocean" {
+//!     palette { base:dark, primary:#336699, background:#0b1d2a },
+//!     text { size:14 }
+//! }
+//! {text("Hello")}
+//! ```
+//!
+//! The engine doesn't apply the `text { size }` of a custom theme, as iced takes the default text size from the
+//! settings of the application. Applications pass [`Snowcap::text_size()`] to the `default_text_size` of their
+//! settings for it to take effect.
+//!
+//! ```text
+//! {<theme:"dark"> text("Hello")}
+//! ```
+//!
+//! ## Cascading Attributes
+//!
+//! The `text-color`, `size`, `font` and `shaping` attributes of an element apply to the text widgets inside it, unless
+//! a text widget or a nearer element sets its own value. Fonts are `monospace`, `default`,
+//! or the name of a font family loaded by the application.
+//!
+//! ```text
+//! {<font:monospace, size:14> |[text("Inherited"), text<size:20>("Larger")]}
+//! ```
+//!
+//! ## Responsive Layouts
+//!
+//! Layout attributes can map breakpoints of the width available to the nearest `responsive` widget to values
+//! (`xs`, `sm`, `md`, `lg` and `xl`). When the width crosses a breakpoint, only the subtrees with responsive
+//! attributes are rebuilt.
+//!
+//! ```text
+//! {responsive(row<spacing:{xs:4, lg:16}>[text<width:{xs:fill, md:200}>("Sidebar"), text("Content")])}
+//! ```
+//!
+//! Widths and heights can also be a percentage of the space available in the parent, or of the window with `vw`
+//! and `vh` units. Relative lengths are resolved during layout, so they follow resizes without a rebuild.
+//! Any element can be bounded with `min-width`, `min-height`, `max-width` and `max-height`.
+//!
+//! ```text
+//! -[col<width:30%, min-width:160>[text("Sidebar")], col<width:70%, height:50vh, max-width:960>[text("Content")]]
+//! ```
+//!
+//! ## Application Context
+//!
+//! Values of the application set with [`Snowcap::set_context()`] are referenced in the markup with `${ctx.key}`.
+//! Inside a string the value is inserted as text, and outside a string it must be a single literal such as a number.
+//! Changing a value reparses the markup and patches the changes into the tree, so only the elements referencing it
+//! are rebuilt.
+//!
+//! ```text
+//! {text("Signed in as ${ctx.username}")}
+//! ```
+//!
+//! ## Expressions
+//!
+//! Numeric and boolean attributes can be computed from an expression of the window size, the size of the parent,
+//! and values in the state store, with the operators and functions of module output expressions. Widgets with
+//! computed attributes are rebuilt when the window is resized, or when a state key they read changes. Comparisons
+//! with `<` and `>` must be in parentheses.
+//!
+//! ```text
+//! {<padding: 2 * pad> text<size: window.width / 40, clip:(window.height < 600)>("Title")}
+//! ```
+//!
+//! ## Stylesheets
+//!
+//! A `styles` block before the root container applies attributes to elements by id with `#id`, or by class with
+//! `.class` and the `class` attribute. Attributes of an element override the rules of the stylesheet, and the
+//! stylesheet can be replaced at runtime with [`Snowcap::set_stylesheet()`].
+//!
+//! The stylesheet can also be a separate file, referenced with `styles!("theme.iss")` before the root container.
+//! The file is watched, and edits only rebuild the widgets of the styled elements.
+//!
+//! ```text
+//! styles { #title { size:40 } .card { padding:12, border:radius(8) } }
+//! {|[text#title("Snowcap"), {<class:"card"> text("Content")}]}
+//! ```
+//!
+//! ## Transitions
+//!
+//! Attributes listed in a `transition` attribute are tweened to their new values when they change, rather than
+//! snapping on rebuild. Transitions run on animation frames requested by [`Snowcap::subscription()`].
+//!
+//! ```text
+//! {<opacity:1, transition:opacity(300ms, ease-out), padding(200ms)> text("Hello")}
+//! ```
+//!
+//! ## Event Handlers
+//!
+//! Buttons, togglers, pick lists, sliders, inputs and drop targets can publish their events directly to a topic with
+//! the `on-press`, `on-toggle`, `on-select`, `on-change`, `on-submit` and `on-drop` attributes, so modules and
+//! application subscriptions can consume them without a Rust handler for each widget. An optional second argument
+//! is published instead of the value of the event.
+//!
+//! ```text
+//! button<on-press:publish("nav", "settings")>(text("Settings"))
+//! ```
+//!
+//! Handlers can also update the key/value store of the [`module::state`] module, which widgets read with `state!{}`,
+//! so small interactive apps can be written in markup alone:
+//!
+//! ```text
+//! col[text(state!{key:"counter", default:0}), button<on-press:state.increment("counter")>(text("+"))]
+//! ```
+//!
+//! Applications handle widget events and topic messages in Rust with [`Snowcap::on_widget_event()`] and
+//! [`Snowcap::on_topic()`], without using the message router directly. Each handler is active until the returned
+//! subscription is dropped, so keep it alongside the engine in the application state:
+//!
+//! ```ignore
+//! let save = snow.on_widget_event("#save", |event| {
+//!     if let WidgetEvent::ButtonPress = event {
+//!         return Task::done(Message::broadcast(AppMessage::Save));
+//!     }
+//!     Task::none()
+//! });
+//! let nav = snow.on_topic("nav", |message| Task::done(Message::broadcast(AppMessage::Navigate(message))));
+//! ```
+//!
+//! ## Inputs
+//!
+//! The `text-input`, `number-input` and `password-input` widgets share the same text input. A `number-input` sends
+//! [`message::widget::WidgetEvent::NumberChanged`] events with its value clamped to the `min` and `max` attributes,
+//! and can be adjusted by `step` with increment and decrement buttons enabled by `spin:true`. A `password-input`
+//! masks its text, and any input can be masked with `secure:true`.
+//!
+//! ```text
+//! |[text-input#name<placeholder:"Name">(), password-input#password(), number-input#age<min:0, max:150>(30)]
+//! ```
+//!
+//! ## Drag and Drop
+//!
+//! Elements with `draggable:true` can be dragged onto elements with `drop-target:true`. The engine tracks the source of
+//! the drag, and the target sends a [`message::widget::WidgetEvent::DroppedOn`] event with the source and target,
+//! so lists can be reordered or cards moved between columns by handling it with [`Snowcap::on_widget_event()`].
+//!
+//! ```text
+//! -[col#todo<drop-target:true>[text#card<draggable:true>("Write docs")], col#done<drop-target:true>[text("Done")]]
+//! ```
+//!
+//! ## File Drops
+//!
+//! A `dropzone` is highlighted while files from the operating system are dragged over the window, and sends a
+//! [`message::widget::WidgetEvent::FileDropped`] event with the path of each file dropped. The path can be published
+//! to a topic with an `on-drop` handler, so an application can open files dragged into it:
+//!
+//! ```text
+//! dropzone<padding:40, on-drop:publish("open")>(text("Drop a file to open it"))
+//! ```
+//!
+//! ## Themer
+//!
+//! A `themer` renders its content with a built-in theme, referenced by name such as `theme:"dracula"` or
+//! `theme:"Tokyo Night"`. Without a `theme` attribute the content keeps the theme of its parent. Markdown and code
+//! widgets inside a themer are rebuilt when its theme changes, including when the theme is set by a module.
+//!
+//! ```text
+//! themer<theme:"nord">(markdown(file!{path:"README.md"}))
+//! ```
+//!
+//! ## Markdown
+//!
+//! Markdown widgets are styled from the palette of the active theme, or the theme of an enclosing `themer`, and are rebuilt when
+//! it changes. Text and code sizes, heading sizes, inline code colors and link colors can be set with attributes, and link clicks
+//! can be published to a topic with an `on-link` handler.
+//!
+//! ```text
+//! markdown<heading-size:36, code-bg:#1a1b26, link-color:#7aa2f7, on-link:publish("links")>(file!{path:"README.md"})
+//! ```
+//!
+//! ## Code
+//!
+//! The `code` widget renders syntax highlighted code with colors matching the active theme, with optional line numbers.
+//!
+//! ```text
+//! code<language:"rust", line-numbers:true, wrap:false>(file!{path:"src/main.rs"})
+//! ```
+//!
+//! ## Bindings
+//!
+//! The value of a slider, toggler, pick list or input can be bound to application data registered with [`Snowcap::bind()`].
+//! Setting the [`Bound`] cell rebuilds only the widgets bound to it, and changes made with the widget are written back to the cell.
+//!
+//! ```text
+//! toggler<value:bind(settings.dark-mode)>("Dark mode")
+//! ```
+//!
+//! ## Localization
+//!
+//! The `i18n` module displays the message of a key from the translation catalog of the active locale. Catalogs are
+//! loaded from Fluent or gettext files with [`Snowcap::load_catalog()`], and the locale is switched at runtime with
+//! [`Snowcap::set_locale()`], which only rebuilds the nodes displaying translated strings.
+//!
+//! ```text
+//! text(i18n!{key:"greeting", name:"Ferris"})
+//! ```
+//!
+//! ## Keyboard Navigation
+//!
+//! Tab and Shift+Tab move the keyboard focus between the pick lists of the markup, which can then be changed with the
+//! arrow keys, Home and End. Escape releases the focus. Selections send the same [`message::widget::WidgetEvent`] as
+//! the mouse, so bindings and handlers respond the same way. Only pick lists with options declared in the markup can
+//! be focused.
+//!
+//! ## Session State
+//!
+//! [`Snowcap::save_state()`] writes the values of sliders, togglers, pick lists and inputs with an element id, the
+//! offsets of scrollables and the size of the window to a file. [`Snowcap::restore_state()`] merges the file into
+//! the tree after the markup is parsed, so the application reopens where the user left off.
+//!
+//! ## Scrolling
+//!
+//! A `scrollable` declared with an element id can be scrolled by sending a [`message::Command::ScrollTo`] message, or with
+//! [`Snowcap::scroll_to()`]. The offsets of scrollables are kept across hot reloads.
+//!
+//! ## Lazy Columns
+//!
+//! A `lazy-col` is a scrollable column of items with the same `item-height`, which only builds widgets for the
+//! items visible in its viewport. Items scrolled out of view keep their module data, but their widgets are dropped
+//! from the cache.
+//!
+//! ```text
+//! {lazy-col<item-height:24, height:400>[text("Item 1"), text("Item 2"), text("Item 3")]}
+//! ```
+//!
+//! ## Inline Assets
+//!
+//! Small assets can be inlined in the markup, so a single file UI can ship its icons without an asset directory.
+//! Binary data is given as a base64 `data:` literal, optionally with a media type, and `embed!()` reads a file when
+//! the markup is parsed. Relative paths are resolved from [`ParserOptions::asset_dir`], or the directory of the
+//! markup file. Files larger than 1 MiB can't be embedded, and should be loaded by a module.
+//!
+//! ```text
+//! {-[image(data:image/png;base64,iVBORw0KGgoAAAANSUhEUg...), svg(embed!("icons/logo.svg"))]}
+//! ```
+//!
+//! ## Strict Markup
+//!
+//! Unknown widgets are rendered as error placeholders, and attributes which don't apply to their element are ignored
+//! when widgets are built. With [`Snowcap::set_parser_options()`] and [`ParserOptions::strict`], these are parse
+//! errors located at the element instead, which is useful in CI to enforce the correctness of markup.
+//!
+//! ```ignore
+//! snow.set_parser_options(ParserOptions::strict());
+//! snow.load_file("app.iced".into())?;
+//! ```
+//!
+//! ## Custom Widgets
+//!
+//! Applications register factories for widget names which aren't built in with [`Snowcap::widgets()`]. A factory
+//! receives the [`NodeId`](arbutus::NodeId), the [`Attributes`] and the [`WidgetContent`] of a node, and returns
+//! its [`DynamicWidget`].
+//!
+//! ```ignore
+//! snow.widgets().register("gauge", |_node_id, _attrs, content| match content {
+//!     WidgetContent::Value(value) => Ok(DynamicWidget::from(Gauge::new(value.float()? as f32))),
+//!     _ => Err(ConversionError::Missing("gauge value".into())),
+//! });
+//! ```
+//!
+//! Custom widgets can take attributes which aren't built in, such as `gauge<needle-color:#f00, sweep:270>`. Parsers
+//! of their values are registered with [`Snowcap::attributes()`], and factories get the parsed values with
+//! [`Attributes::custom()`]. Attributes without a registered parser have a generic [`Value`].
+//!
+//! ```ignore
+//! snow.attributes().register("needle-color", |source| parse_color(source))?;
+//!
+//! snow.widgets().register("gauge", |_node_id, attrs, _content| {
+//!     let color = attrs.custom::<iced::Color>("needle-color").unwrap_or(iced::Color::WHITE);
+//!     let sweep = attrs.custom::<Value>("sweep").and_then(|sweep| sweep.float().ok()).unwrap_or(180.0);
+//!     Ok(DynamicWidget::from(Gauge::new(color, sweep as f32)))
+//! });
+//! ```
+//!
+//! ## Widget Plugins
+//!
+//! A [`WidgetPlugin`] registered with [`Snowcap::register_plugin()`] intercepts the widget built for each node it
+//! matches, and can wrap or replace it, such as to inject debug borders or record analytics of widget events, without
+//! forking the widget conversions. See [`plugin`] for an example.
+//!
 //! ## Dynamic Modules
 //!
 //! There is a module framework in [`module`] which allows for creation of dynamic functionality that can be referenced in the snowcap markup.
@@ -40,6 +455,47 @@
 //!
 //! Arguments can be specified for modules in the grammar using `{key: value (, key:value)+}` and are
 //! passed to [`crate::module::Module::init()`] as [`crate::module::argument::ModuleArguments`].
+//! Arguments are checked by [`crate::module::Module::validate_args()`] of the registered module when the markup is
+//! parsed, so a misspelled argument such as `http!{ulr:"..."}` is a parse error at the location of the module.
+//!
+//! Besides strings, numbers, booleans and arrays, argument values can be durations (`250ms`, `5s`, `1m30s`), data
+//! sizes (`512B`, `2MB`, `4KiB`) and unquoted URLs (`https://example.com/data.json`), which are typed and checked
+//! by the parser, such as `timing!{periodic: 500ms, topic:"tick"}`. Structured configuration is given as a map,
+//! such as `headers: {Accept: "application/json", "X-Token": "..."}`, which modules read with
+//! [`module::argument::ModuleArguments::get_map()`].
+//!
+//! A module given an `id` argument is instantiated once and shared by every node referencing the same `id`.
+//! Each node can select part of the module data with a `field` argument, and only nodes whose selected data changed are rebuilt.
+//!
+//! ```text
+//! text(sysinfo!{id:"sys", field:"cpu"}), text(sysinfo!{id:"sys", field:"memory"})
+//! ```
+//!
+//! Module invocations without an `id` are also shared when their name and arguments are identical (other than
+//! `field` and `expr`), so the same `http!{...}` in several nodes makes a single request.
+//!
+//! Values can be derived from the data of a module instance using `derive!`, which evaluates an expression
+//! (see [`parser::expr`]) each time the source data changes. An `expr` argument can also be given to any module node directly.
+//!
+//! ```text
+//! text(derive!{from:"sys", field:"cpu", expr:"value * 100 |> round"})
+//! ```
+//!
+//! The output of any module can be shaped for display with a `transform` pipeline and a `format` template
+//! (see [`module::output`]).
+//!
+//! ```text
+//! text(http!{url:"http://icanhazip.com", transform:"trim |> truncate(15)", format:"IP {}"})
+//! ```
+//!
+//! The `http` and `file` modules publish the progress of loading large data to the topic of their `progress`
+//! argument, such as `video(http!{url:"...", progress:"downloads/intro"})` (see [`module::progress`]).
+//!
+//! The `file` module lists the paths of the entries of a directory in `list` mode, such as
+//! `file!{path:"./shots", mode:"list", pattern:"*.png"}`, and each path can be selected by its index with `field`.
+//!
+//! With `follow:true`, the `file` module follows a file as it's appended to, and its data is the last lines of the
+//! file, such as `text(file!{path:"app.log", follow:true, lines:200})` for a live log viewer.
 //!
 //! ### Internal Modules
 //!
@@ -47,8 +503,16 @@
 //! |---------------------|-----------------------------------|----------------------|
 //! | [`module::file`]    | Loading files from the filesystem | ```image(file!{path:"pic.png"}) // Get the contents of a PNG file for an image widget ```                    |
 //! | [`module::http`]    | Making HTTP Network Requests      | ```text(http!{method:"get", url:"http://icanhazip.com"}) // Get the contents of a URL into a text widget```  |
-//! | [`module::timing`]  | Timing related functionality      | ```timing!{periodic:"1s"}  // Periodic timer triggering every second```                                      |
+//! | [`module::timing`]  | Timing related functionality      | ```timing!{periodic:"1s", topic:"clock"}  // Periodic timer publishing to the clock topic every second```    |
+//! | [`module::state`]   | Key/value state store             | ```text(state!{key:"counter", default:0}) // Value of a key, updated by state.increment("counter") handlers``` |
+//! | [`module::window`]  | Size and events of the window     | ```text(window!{field:"width"}) // Width of the window, requires Snowcap::subscription()```                  |
+//! | [`module::format`]  | Formatting numbers and dates      | ```text(fmt!{value:1234.5, pattern:"{:,.2} MB"}) // Number with thousands separators of the active locale``` |
+//! | `module::script`    | Logic in Rhai scripts, with the `scripting` feature | ```text(script!{file:"logic.rhai"}) // Subscribes to topics and publishes values computed by the script``` |
+//!
 //!
+//! When loading markup from an untrusted source, set a [`ModulePolicy`] with [`Snowcap::set_module_policy()`] to restrict
+//! which modules can be instantiated, the directories the file module and `embed!()` can read, and the URLs the http module
+//! can request.
 //!
 //! ### Custom Modules
 //! Custom modules can be defined by implementing [`module::Module`] on your own struct, and registering it with the engine using [`Snowcap::modules()`]
@@ -60,6 +524,14 @@
 //! snowcap.modules().register<MyModule>("custom-module");
 //! ```
 //!
+//! Module names can be namespaced with dots to avoid collisions, and referenced in the markup by the same name.
+//! Registering a name already taken by a different module type returns [`module::error::ModuleError::NameCollision`].
+//!
+//! ```ignore
+//! snowcap.modules().register::<Weather>("mycompany.weather")?;
+//! // text(mycompany.weather!{city:"Vancouver"})
+//! ```
+//!
 //! The sealed traits [`module::internal::ModuleInit`] and [`module::internal::ModuleInternal`] get automatically blanket implemented on
 //! any type implementing [`module::Module`] to handle instantation and dynamic message dispatching.
 //!
@@ -68,6 +540,9 @@
 //! * [`module::Module::on_event()`] to handle internal messages defined by [`module::Module::Event`] associated type
 //! * [`module::Module::on_subscription()`] to receive messages published to topics by other modules or the core engine
 //!
+//! The application can publish to the same topics with [`Snowcap::publish()`], and subscribe to them with [`Snowcap::subscribe()`].
+//! Subscription topics may contain wildcards, such as `sensors/*` (see [`Topic::matches()`]).
+//!
 //! In addition, a message type must be defined which implements [`module::event::ModuleEvent`] and set as the associated type [`module::Module::Event`].
 //!
 //! ## Grammar Definitions
@@ -84,6 +559,7 @@
 //! | [`src/parser/gradient.pest`](https://github.com/boondocklabs/snowcap/blob/main/src/parser/gradient.pest)  | Gradient grammar | [`parser::gradient::GradientParser`]
 //! | [`src/parser/module.pest`](https://github.com/boondocklabs/snowcap/blob/main/src/parser/module.pest)  | Dynamic module grammar | [`parser::module::ModuleParser`]
 //! | [`src/parser/value.pest`](https://github.com/boondocklabs/snowcap/blob/main/src/parser/value.pest)  | Value grammar | [`parser::value::ValueParser`]
+//! | [`src/parser/expr.pest`](https://github.com/boondocklabs/snowcap/blob/main/src/parser/expr.pest)  | Derived value expression grammar | [`parser::expr::ExprParser`]
 //!
 //! [`snowcap-viewer`]: https://github.com/boondocklabs/snowcap-viewer
 //! [`snowcap`]: https://github.com/boondocklabs/snowcap
@@ -92,18 +568,41 @@
 //! [`pest`]: https://pest.rs
 //! [`notify`]: https://docs.rs/notify/latest/notify/
 
+mod appearance;
 mod attribute;
+mod batch;
+mod binding;
 //mod connector;
 mod conversion;
 mod data;
+mod diff;
 mod dynamic_widget;
 mod error;
+mod focus;
+mod graph;
 //mod event;
 mod cache;
+mod command;
+mod context;
+#[cfg(all(not(target_arch = "wasm32"), feature = "headless"))]
+pub mod headless;
+mod history;
+mod identity;
+mod inspector;
 pub mod message;
+pub mod metrics;
 pub mod module;
 mod node;
 mod parser;
+pub mod perf;
+mod picker;
+pub mod plugin;
+mod record;
+mod scroll;
+mod session;
+pub mod telemetry;
+pub mod testing;
+mod tween;
 //mod router;
 mod util;
 mod watcher;
@@ -113,313 +612,1584 @@ pub use message::module::*;
 use arbutus::TreeDiff;
 use arbutus::TreeNode as _;
 use arbutus::TreeNodeRef as _;
-use dynamic_widget::DynamicWidget;
+use context::AppContext;
+use diff::DiffRecorder;
+use history::{History, HistoryStep};
+use identity::IdentityIndex;
 
 // Re-export iced
 pub use iced;
 use iced::Task;
 
+use appearance::ThemeState;
+use batch::{Poll, UpdateBatch};
 use cache::WidgetCache;
-use message::widget::WidgetMessage;
+use conversion::drag::DragState;
+use conversion::style::{root_stylesheet, root_stylesheet_file, StyleFile};
+use conversion::theme::root_text_size;
+use message::widget::{WidgetEvent, WidgetMessage};
 use message::Command;
+use metrics::MetricsSnapshot;
 use module::manager::ModuleManager;
 use module::ModuleHandleId;
 use node::SnowcapNode;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
+use parser::incremental::Reparse;
+use parser::location::SourceMap;
+use parser::style::{StyleParser, Stylesheet};
+use record::Recorder;
 use salish::endpoint::Endpoint;
 use salish::router::MessageRouter;
-use watcher::FileWatcher;
+use scroll::ScrollOffsets;
+use session::SessionState;
+use telemetry::TelemetryEvent;
+use watcher::{FileWatcher, WatchEvent, WatchMessage};
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+pub use appearance::Appearance;
+pub use attribute::custom::{AttributeRegistry, CustomAttributes, CustomValue};
+pub use attribute::{AttributeKind, AttributeValue, Attributes};
+pub use binding::{Bindable, Bound};
+pub use cache::WidgetContent;
+pub use conversion::registry::{WidgetFactory, WidgetRegistry};
+pub use conversion::theme::SnowcapTheme;
+pub use conversion::video::{VideoDecoder, VideoStream};
+pub use diff::{DiffChange, DiffEntry, DiffReport};
+pub use dynamic_widget::DynamicWidget;
+pub use error::*;
+pub use graph::GraphFormat;
+pub use identity::{DuplicateElementId, StableId};
+pub use inspector::{InspectedNode, InspectorReport};
+pub use message::widget::WidgetEventSubscription;
+pub use module::http::HttpConfig;
+pub use module::policy::ModulePolicy;
+pub use module::pubsub::TopicSubscription;
+pub use salish::Message;
+
+pub use parser::location::SourceLocation;
+pub use parser::Value;
+pub use parser::{ParserOptions, SnowcapParser};
+pub use perf::PhaseTimings;
+pub use plugin::{PluginNode, WidgetPlugin};
+pub use record::{Recorded, RecordedMessage, Recording};
+
+use tracing::debug;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+//type Node<Data, Id> = arbutus::node::rc::Node<Data, Id>;
+//type NodeRef<M> = arbutus::noderef::rc::NodeRef<Node<SnowcapNode<M>, arbutus::NodeId>>;
+
+type Node<Data, Id> = arbutus::node::arc::Node<Data, Id>;
+type NodeRef = arbutus::noderef::arc::NodeRef<Node<SnowcapNode, arbutus::NodeId>>;
+
+type Tree = arbutus::Tree<NodeRef>;
+type IndexedTree = arbutus::IndexedTree<NodeRef>;
+type NodeId = arbutus::NodeId;
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Source {
+    Module(ModuleHandleId),
+}
+
+/// Maximum time to wait for modules to complete their [`module::Module::on_shutdown()`] tasks
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Top level Snowcap Engine which manages loading and parsing grammar into an [`Arbutus`](https://github.com/boondocklabs/arbutus) tree.
+/// Provides the update() and view()
+pub struct Snowcap {
+    #[cfg(not(target_arch = "wasm32"))]
+    filename: Option<PathBuf>,
+    tree: Arc<Mutex<Option<IndexedTree>>>,
+
+    /// Markup the tree was parsed from, used to reparse only the changed region on reload
+    source: Option<String>,
+
+    identities: IdentityIndex,
+    modules: Arc<Mutex<ModuleManager>>,
+    watcher: Option<FileWatcher>,
+
+    router: MessageRouter<'static, Task<salish::message::Message>, Source>,
+
+    cache: Arc<Mutex<WidgetCache>>,
+
+    /// Show the [`DiffReport`] of the most recent reload in a panel below the root widget
+    diff_viewer: bool,
+    last_diff: Option<DiffReport>,
+
+    /// Show the debug inspector over the root widget, toggled by [`Command::ToggleInspector`]
+    inspector: Arc<AtomicBool>,
+
+    /// Captures widget messages and commands while recording, see [`Snowcap::start_recording()`]
+    recorder: Recorder,
+
+    /// Set by [`Command::Reload`], the file is reloaded by the next update
+    reload: Arc<AtomicBool>,
+
+    /// Markup replaced by each change, to undo and redo changes
+    history: History,
+
+    /// Options of the parser of the markup
+    parser_options: ParserOptions,
+
+    /// Values of the application referenced in the markup with `${ctx.key}`
+    context: AppContext,
+
+    /// Source of the drag in progress between `draggable` and `drop-target` elements
+    drag: DragState,
+
+    /// Tasks queued by a reload, such as shutdown tasks of released module instances, run on the next update
+    teardown_tasks: Vec<Task<Message>>,
+
+    /// Offsets of scrollables, restored after a reload
+    scroll_offsets: ScrollOffsets,
+
+    /// State restored by [`Snowcap::restore_state()`] before markup was loaded, merged once it is parsed
+    restored_state: Option<SessionState>,
+
+    /// False while the window is minimized or hidden
+    window_visible: bool,
+
+    /// Visibility of the window reported by its last resize, applied by the next update
+    window_resized_visible: Arc<Mutex<Option<bool>>>,
+
+    /// Active theme, from the `theme` attribute of the markup root, [`Command::SetTheme`],
+    /// or the system appearance
+    theme: Arc<Mutex<ThemeState>>,
+
+    /// Stylesheet set with [`Snowcap::set_stylesheet()`], replacing the `styles` block of the markup
+    stylesheet: Option<Arc<Stylesheet>>,
+
+    /// External stylesheet referenced by the markup with `styles!()`
+    style_file: Option<StyleFile>,
+
+    /// Files reported modified by the watcher since the last update
+    modified_files: Arc<Mutex<HashSet<PathBuf>>>,
+
+    /// Nodes hidden by the application. Descendants of hidden nodes are also hidden.
+    hidden: HashSet<NodeId>,
+
+    /// Timings of the phases run since the last update, and of the most recent update which did any work
+    timings: PhaseTimings,
+    last_timings: PhaseTimings,
+
+    /// Time taken by the last view()
+    view_time: Cell<Option<Duration>>,
+
+    /// Limits widget rebuilds to once per update interval
+    batch: UpdateBatch,
+
+    _command_endpoint: Endpoint<'static, Command, Task<Message>, Source>,
+    _widget_endpoint: Endpoint<'static, WidgetMessage, Task<Message>, Source>,
+    _watch_endpoint: Endpoint<'static, WatchMessage, Task<Message>, Source>,
+    _state_subscription: TopicSubscription,
+}
+
+// The engine can be moved to the thread running the application, see the Threading section of the crate docs
+const _: () = {
+    fn assert_send<T: Send>() {}
+    #[allow(dead_code)]
+    fn assert_engine() {
+        assert_send::<Snowcap>();
+    }
+};
+
+impl Snowcap {
+    /// Create a new Snowcap Engine instance
+    pub fn new() -> Result<Self, Error> {
+        let router = MessageRouter::<Task<Message>, Source>::new();
+
+        let tree = Arc::new(Mutex::new(None));
+        let modules = Arc::new(Mutex::new(ModuleManager::new(router.clone())));
+
+        // Notify modules of shutdown, and wait for their shutdown tasks before exiting
+        let shutdown = modules.lock().shutdown_hooks();
+
+        // Event handlers of widgets update the state store of the modules
+        let widget_state = modules.lock().state().clone();
+
+        // Window events update the window size of the modules
+        let command_window = modules.lock().window_size().clone();
+
+        let theme = Arc::new(Mutex::new(ThemeState::default()));
+        let command_theme = theme.clone();
+
+        let cache = Arc::new(Mutex::new(WidgetCache::default()));
+        let command_cache = cache.clone();
+        let tweens = cache.lock().tweens();
+        let bindings = cache.lock().bindings();
+        let command_bindings = bindings.clone();
+        let command_tree = tree.clone();
+        let inspector = Arc::new(AtomicBool::new(false));
+        let command_inspector = inspector.clone();
+        let reload = Arc::new(AtomicBool::new(false));
+        let command_reload = reload.clone();
+        let window_resized_visible = Arc::new(Mutex::new(None));
+        let command_resized_visible = window_resized_visible.clone();
+        let history = History::default();
+        let command_history = history.clone();
+        let recorder = Recorder::default();
+        let command_recorder = recorder.clone();
+        let widget_recorder = recorder.clone();
+        let drag = DragState::default();
+        let command_drag = drag.clone();
+        let widget_drag = drag.clone();
+
+        let command_endpoint =
+            router
+                .create_endpoint::<Command>()
+                .message(move |source, command| {
+                    command_recorder.record_command(&command);
+                    metrics::increment(metrics::MESSAGES, Some(("type", "command")));
+                    match command {
+                        Command::Shutdown => {
+                            info!("Shutdown command received from {source:?}");
+                            shutdown.drain(SHUTDOWN_TIMEOUT).chain(iced::exit())
+                        }
+                        Command::Reload => {
+                            // The file is reloaded by the update handling the message, which has the engine
+                            info!("Reload requested by {source:?}");
+                            command_reload.store(true, Ordering::Relaxed);
+                            Task::none()
+                        }
+                        Command::Undo => {
+                            command_history.request(HistoryStep::Undo);
+                            Task::none()
+                        }
+                        Command::Redo => {
+                            command_history.request(HistoryStep::Redo);
+                            Task::none()
+                        }
+                        Command::SetTheme(theme) => {
+                            info!("Theme {theme} set by {source:?}");
+                            command_theme.lock().set(theme);
+                            Task::none()
+                        }
+                        Command::SetAppearance(appearance) => {
+                            command_theme.lock().set_appearance(appearance);
+                            Task::none()
+                        }
+                        Command::Window(event) => {
+                            if let iced::window::Event::Resized(size) = event {
+                                // Minimized windows are resized to zero
+                                *command_resized_visible.lock() =
+                                    Some(size.width > 0.0 && size.height > 0.0);
+
+                                // Widgets with attributes computed from expressions are rebuilt with the new window
+                                // size
+                                let computed = command_cache.lock().take_computed();
+                                if let Some(tree) = &mut *command_tree.lock() {
+                                    for node_id in computed {
+                                        if let Some(node) = tree.get_node_mut(&node_id) {
+                                            node.node_mut().data_mut().set_dirty(true);
+                                        }
+                                    }
+                                }
+                            }
+
+                            // Dropzones are highlighted while files hover, and send the dropped files
+                            let dropped = match &*command_tree.lock() {
+                                Some(tree) => conversion::dropzone::handle_file_event(tree, &event),
+                                None => Task::none(),
+                            };
+                            Task::batch([
+                                dropped,
+                                module::window::handle_event(event, &command_window),
+                            ])
+                        }
+                        Command::AnimationFrame => {
+                            // Mark nodes with running tweens dirty, so they are rebuilt with interpolated values
+                            let mut guard = command_tree.lock();
+                            if let Some(tree) = &mut *guard {
+                                for node_id in tweens.lock().nodes() {
+                                    if let Some(node) = tree.get_node_mut(&node_id) {
+                                        node.node_mut().data_mut().set_dirty(true);
+                                    }
+                                }
+                            }
+                            Task::none()
+                        }
+                        Command::ScrollTo { element_id, offset } => {
+                            scroll::scroll_to(element_id, offset)
+                        }
+                        Command::BindingChanged(path) => {
+                            // Only the nodes bound to the path are rebuilt
+                            let (nodes, value) = {
+                                let bindings = command_bindings.lock();
+                                (bindings.nodes(&path), bindings.value(&path))
+                            };
+
+                            let mut guard = command_tree.lock();
+                            if let Some(tree) = &mut *guard {
+                                for node_id in nodes {
+                                    if let Some(node) = tree.get_node_mut(&node_id) {
+                                        node.node_mut().data_mut().set_dirty(true);
+                                    }
+                                }
+                            }
+
+                            match value {
+                                Some(value) => module::pubsub::publish(
+                                    binding::topic(&path),
+                                    TopicMessage::Value(value),
+                                ),
+                                None => Task::none(),
+                            }
+                        }
+                        // Dirty nodes are rebuilt by the update handling the message
+                        Command::Flush => Task::none(),
+                        Command::ToggleInspector => {
+                            let enabled = !command_inspector.fetch_xor(true, Ordering::Relaxed);
+                            debug!("Inspector enabled={enabled}");
+                            Task::none()
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Command::Screenshot(path) => command::screenshot(path),
+                        #[cfg(target_arch = "wasm32")]
+                        Command::Screenshot(_) => {
+                            tracing::warn!("Screenshots are not supported on wasm");
+                            Task::none()
+                        }
+                        Command::OpenSource { path, line, column } => module::pubsub::publish(
+                            message::OPEN_SOURCE_TOPIC,
+                            TopicMessage::String(format!("{}:{line}:{column}", path.display())),
+                        ),
+                        Command::DumpTree(format) => match &*command_tree.lock() {
+                            Some(tree) => command::dump_tree(tree.root(), format),
+                            None => Task::none(),
+                        },
+                        Command::ClearCache => {
+                            command_cache.lock().clear();
+                            if let Some(tree) = &*command_tree.lock() {
+                                command::mark_all_dirty(tree);
+                            }
+                            Task::none()
+                        }
+                        Command::Drop(target) => command_drag.drop_on(target),
+                        Command::DragCancel => {
+                            command_drag.cancel();
+                            Task::none()
+                        }
+                        Command::Navigate(navigation) => match &*command_tree.lock() {
+                            Some(tree) => focus::navigate(tree, navigation),
+                            None => Task::none(),
+                        },
+                    }
+                });
+
+        // Create an endpoint listening for WidgetMessage messages, which finds the node
+        // in the tree, and marks it as dirty.
+        let _tree = tree.clone();
+        let scroll_offsets = ScrollOffsets::default();
+        let _scroll_offsets = scroll_offsets.clone();
+        let widget_endpoint =
+            router
+                .create_endpoint::<WidgetMessage>()
+                .message(move |_source, message| {
+                    widget_recorder.record(Recorded::Widget(message.clone()));
+                    metrics::increment(metrics::MESSAGES, Some(("type", "widget")));
+
+                    debug!(
+                        node_id = %message.node_id,
+                        element_id = ?message.element_id,
+                        event = ?message.event,
+                        "Widget event"
+                    );
+                    telemetry::emit(TelemetryEvent::WidgetEvent {
+                        node_id: message.node_id,
+                        element_id: message.element_id.clone(),
+                        event: message.event.clone(),
+                    });
+
+                    // Track the source of a drag, until it is dropped onto a target or cancelled
+                    if let WidgetEvent::DragStarted { source } = &message.event {
+                        widget_drag.start(source.clone());
+                    }
+
+                    // Record the offsets of scrollables to restore them after a reload
+                    if let (WidgetEvent::Scrolled(viewport), Some(stable_id)) =
+                        (&message.event, &message.stable_id)
+                    {
+                        _scroll_offsets.record(stable_id.clone(), viewport.absolute_offset());
+                    }
+
+                    let mut guard = _tree.lock();
+                    let tree: &mut IndexedTree = guard.as_mut().unwrap();
+
+                    if let Some(node) = tree.get_node_mut(&message.node_id) {
+                        // Responsive widgets store their breakpoint, and mark descendants with responsive attributes dirty
+                        if let WidgetEvent::Breakpoint(breakpoint) = message.event {
+                            conversion::responsive::set_breakpoint(node, breakpoint);
+                        }
+
+                        // Lazy columns build widgets for the items scrolled into their viewport
+                        if let WidgetEvent::Scrolled(viewport) = &message.event {
+                            conversion::lazy_column::set_viewport(node, viewport);
+                        }
+
+                        // Mark the node as dirty
+                        node.node_mut().data_mut().set_dirty(true);
+
+                        let attrs = &node.node().data().attrs;
+
+                        // Write the value of bound widgets back to their data source
+                        let binding = match (
+                            attrs.get(AttributeKind::Bind),
+                            binding::event_value(&message.event),
+                        ) {
+                            (Ok(Some(AttributeValue::Bind(path))), Some(value))
+                                if bindings.lock().set(&path, &value) =>
+                            {
+                                Task::done(Message::broadcast(Command::BindingChanged(path)))
+                            }
+                            _ => Task::none(),
+                        };
+
+                        // Publish the event with the handler declared by the widget, if any
+                        return Task::batch([
+                            binding,
+                            attribute::handler::dispatch(attrs, &message.event, &widget_state),
+                        ]);
+                    }
+                    Task::none()
+                });
+
+        // Record the files modified on disk, so an external stylesheet is reloaded by the next update
+        let modified_files = Arc::new(Mutex::new(HashSet::new()));
+        let watch_modified = modified_files.clone();
+        let watch_endpoint =
+            router
+                .create_endpoint::<WatchMessage>()
+                .message(move |_source, message| {
+                    if let WatchMessage::Event(WatchEvent::Modified(paths)) = message {
+                        watch_modified.lock().extend(paths);
+                    }
+                    Task::none()
+                });
+
+        // Widgets with attributes computed from expressions are rebuilt when a state key they read changes
+        let state_cache = cache.clone();
+        let state_tree = tree.clone();
+        let state_subscription =
+            TopicSubscription::new(&router, Topic::new("state/**"), move |topic, _message| {
+                let Some(key) = topic.name().strip_prefix("state/") else {
+                    return Task::none();
+                };
+                let readers = state_cache.lock().take_state_readers(key);
+                if let Some(tree) = &mut *state_tree.lock() {
+                    for node_id in readers {
+                        if let Some(node) = tree.get_node_mut(&node_id) {
+                            node.node_mut().data_mut().set_dirty(true);
+                        }
+                    }
+                }
+                Task::none()
+            });
+
+        let snow = Self {
+            tree,
+            source: None,
+            identities: IdentityIndex::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            filename: None,
+            modules,
+            watcher: None,
+            router,
+            _command_endpoint: command_endpoint,
+            _widget_endpoint: widget_endpoint,
+            _watch_endpoint: watch_endpoint,
+            _state_subscription: state_subscription,
+            cache,
+            diff_viewer: false,
+            last_diff: None,
+            inspector,
+            recorder,
+            reload,
+            history,
+            parser_options: ParserOptions::default(),
+            context: AppContext::default(),
+            drag,
+            teardown_tasks: Vec::new(),
+            scroll_offsets,
+            restored_state: None,
+            window_visible: true,
+            window_resized_visible,
+            theme,
+            stylesheet: None,
+            style_file: None,
+            modified_files,
+            hidden: HashSet::new(),
+            timings: PhaseTimings::default(),
+            last_timings: PhaseTimings::default(),
+            view_time: Cell::new(None),
+            batch: UpdateBatch::default(),
+        };
+
+        Ok(snow)
+    }
+
+    /// Engine initialization, called by [`iced::Application`].
+    /// Traverses the tree to build widgets, and gets an init [`iced::Task`]
+    /// from each instantiated [`module`] in the tree.
+    pub fn init(&mut self) -> Task<Message> {
+        let mut tasks = Vec::new();
+
+        let (watcher, watcher_task) = FileWatcher::new();
+
+        self.watcher = Some(watcher);
+
+        tasks.push(watcher_task);
+
+        if let Some(filename) = &self.filename {
+            self.watcher.as_mut().unwrap().watch(filename).unwrap();
+        }
+
+        // Run the initial tree update, and get any tasks (Provider init tasks)
+        // Load the external stylesheet of the markup, or reload it if it was modified
+        #[cfg(not(target_arch = "wasm32"))]
+        self.load_style_file();
+
+        let tree_task = if let Some(tree) = &mut *self.tree.lock() {
+            profiling::scope!("build-widgets");
+            let mut cache = self.cache.lock();
+
+            // Rebuild widgets with styles derived from the active theme when it changes
+            for node_id in cache.set_theme(self.theme()) {
+                if let Some(node) = tree.get_node_mut(&node_id) {
+                    node.node_mut().data_mut().set_dirty(true);
+                }
+            }
+
+            // Rebuild widgets selected by the rules of the stylesheet when it changes
+            for node_id in cache.set_stylesheet(self.stylesheet(tree)) {
+                if let Some(node) = tree.get_node_mut(&node_id) {
+                    node.node_mut().data_mut().set_dirty(true);
+                }
+            }
+
+            match cache.update_tree(tree, &mut self.modules_mut()) {
+                Ok(task) => task,
+                Err(e) => {
+                    error!("Failed to build widgets: {}", e);
+                    Task::none()
+                }
+            }
+        } else {
+            Task::none()
+        };
+
+        tasks.push(tree_task);
+
+        info!("Starting init tasks");
+
+        Task::batch(tasks)
+    }
+
+    /// Lock the [`ModuleManager`] for registering and instantiating modules at runtime
+    pub fn modules(&self) -> MutexGuard<'_, ModuleManager> {
+        self.modules.lock()
+    }
+
+    /// Lock the [`ModuleManager`] for registering and instantiating modules at runtime.
+    /// This is the same lock as [`Snowcap::modules()`], which must not be held at the same time.
+    pub fn modules_mut(&self) -> MutexGuard<'_, ModuleManager> {
+        self.modules.lock()
+    }
+
+    /// Get the [`WidgetRegistry`] of custom widgets, to register factories building widgets with names which
+    /// aren't built in, such as `gauge`
+    pub fn widgets(&self) -> WidgetRegistry {
+        self.cache.lock().registry()
+    }
+
+    /// Get the [`AttributeRegistry`] of custom attributes, to register parsers of the values of attributes which
+    /// aren't built in, such as `needle-color`
+    pub fn attributes(&self) -> AttributeRegistry {
+        AttributeRegistry
+    }
+
+    /// Get a reference to the [`MessageRouter`]
+    pub fn router(&mut self) -> &mut MessageRouter<'static, Task<Message>, Source> {
+        &mut self.router
+    }
+
+    /// Load a markup file and set the active [`arbutus`] tree.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_file(&mut self, filename: String) -> Result<(), Error> {
+        let filename = &PathBuf::from(&filename);
+        let source = std::fs::read_to_string(filename)?;
+        let options = self.parser_options_for(Some(filename.as_path()));
+        let tree = perf::measure(&mut self.timings.parse, || {
+            self.context.parse(&source, &options)
+        })
+        .map_err(Error::Parse)?;
+
+        let tree = IndexedTree::from_tree(tree);
+        debug!(file = %filename.display(), "Markup file loaded into tree\n{}", tree.root());
+
+        self.filename = Some(filename.clone());
+        self.source = Some(source);
+
+        self.set_tree(tree, filename.display().to_string())?;
+
+        Ok(())
+    }
+
+    /// Load markup from memory. If a tree is currently loaded, the new tree is diffed
+    /// and changes are patched into the existing tree.
+    pub fn load_memory(&mut self, data: &str) -> Result<(), Error> {
+        let options = self.parser_options_for(None);
+        let tree = perf::measure(&mut self.timings.parse, || {
+            self.context.parse(data, &options)
+        })?;
+
+        if self.tree.lock().is_some() {
+            // We already have a tree loaded, the markup it replaces can be restored by undo
+            if let Some(previous) = self.source.clone() {
+                if previous != data {
+                    self.history.record(previous);
+                }
+            }
+
+            self.patch_memory(tree, data, "memory");
+            return Ok(());
+        }
+
+        self.source = Some(data.to_string());
+        self.set_tree(IndexedTree::from_tree(tree), "memory".into())?;
+
+        Ok(())
+    }
+
+    /// Replace the element of a named region, such as `region#sidebar { ... }`, with new markup. Only the nodes inside
+    /// the region are diffed and patched into the tree. The markup file isn't written, and the change can be
+    /// reverted with [`Snowcap::undo()`].
+    pub fn reload_region(&mut self, region: &str, markup: &str) -> Result<(), Error> {
+        let not_found = || Error::ElementNotFound(region.to_string());
+
+        let previous = self.source.clone().ok_or_else(not_found)?;
+        let body = parser::incremental::region_body(&previous, region).ok_or_else(not_found)?;
+
+        // Keep the whitespace around the element of the region
+        let old_body = &previous[body.clone()];
+        let leading = &old_body[..old_body.len() - old_body.trim_start().len()];
+        let trailing = &old_body[old_body.trim_end().len()..];
+        let source = format!(
+            "{}{leading}{}{trailing}{}",
+            &previous[..body.start],
+            markup.trim(),
+            &previous[body.end..]
+        );
+
+        if source == previous {
+            return Ok(());
+        }
+
+        let node_id = self.identities.element(region).ok_or_else(not_found)?;
+        let options = self.parser_options_for(self.filename.as_deref());
+
+        let mut guard = self.tree.lock();
+        let tree = guard.as_mut().ok_or_else(not_found)?;
+        let target = tree
+            .get_node_mut(&node_id)
+            .map(|noderef| noderef.clone())
+            .ok_or(Error::NodeNotFound(node_id))?;
+
+        if !matches!(target.node().data().content(), node::Content::Region) {
+            return Err(Error::ElementKind(region.to_string(), "region".into()));
+        }
+
+        // Strict markup is validated by parsing the whole tree
+        let reparse = match options.strict {
+            true => None,
+            false => perf::measure(&mut self.timings.parse, || {
+                parser::value::with_asset_dir(options.asset_dir.as_deref(), &options.policy, || {
+                    self.context.scope(|| {
+                        Reparse::element::<Message>(
+                            target,
+                            &self.context.expand(&previous),
+                            &self.context.expand(&source),
+                        )
+                    })
+                })
+            })?,
+        };
+
+        // The region doesn't enclose the change after context references are replaced, so patch the whole tree
+        let Some(reparse) = reparse else {
+            drop(guard);
+            let tree = self.context.parse(&source, &options)?;
+            self.history.record(previous);
+            self.patch_memory(tree, &source, region);
+            return Ok(());
+        };
+
+        let recorder = DiffRecorder::default();
+        {
+            let event_recorder = recorder.clone();
+            let _listener = tree
+                .on_event(move |event| {
+                    event_recorder.record(event);
+                    invalidate_patched(event);
+                })
+                .unwrap();
+
+            let mut diff = TreeDiff::new(reparse.target().clone(), reparse.replacement());
+            let patch = perf::measure(&mut self.timings.diff, || diff.diff());
+            perf::measure(&mut self.timings.patch, || patch.patch_tree(tree));
+        }
+
+        tree.reindex();
+        reparse.finish(tree);
+
+        // Tear down module instances whose nodes were removed by the patch
+        let teardown = self
+            .modules
+            .lock()
+            .release_nodes(|node_id| tree.get_node_mut(&node_id).is_some());
+        self.teardown_tasks.push(teardown);
+
+        self.identities = IdentityIndex::build(tree);
+        self.teardown_tasks
+            .push(self.scroll_offsets.restore(&self.identities));
+        self.cache.lock().tweens().lock().prune(&self.identities);
+        drop(guard);
+
+        self.set_diff(recorder.finish(format!("region#{region}")));
+        self.history.record(previous);
+        self.source = Some(source);
+        self.update_source_map();
+
+        Ok(())
+    }
+
+    /// Patch the changes of a tree parsed from markup into the live tree
+    fn patch_memory(&mut self, tree: Tree, data: &str, source: &str) {
+        if let Some(current) = &mut *self.tree.lock() {
+            // Diff the trees
+            let recorder = DiffRecorder::default();
+            let _listener = current
+                .on_event({
+                    let recorder = recorder.clone();
+                    move |event| {
+                        recorder.record(event);
+                        invalidate_patched(event);
+                    }
+                })
+                .ok();
+
+            let mut diff = TreeDiff::new(current.root().clone(), tree.root().clone());
+            let patch = perf::measure(&mut self.timings.diff, || diff.diff());
+
+            info!("Patching existing tree {patch:#?}");
+            perf::measure(&mut self.timings.patch, || patch.patch_tree(current));
+
+            current.reindex();
+
+            // Nodes kept by the patch have the spans of the previous markup
+            parser::incremental::copy_spans(tree.root(), current.root(), 0);
+            self.source = Some(data.to_string());
+
+            // Tear down module instances whose nodes were removed by the patch
+            let teardown = self
+                .modules
+                .lock()
+                .release_nodes(|node_id| current.get_node_mut(&node_id).is_some());
+            self.teardown_tasks.push(teardown);
+
+            self.identities = IdentityIndex::build(current);
+            self.theme.lock().apply_markup(current);
+            self.teardown_tasks
+                .push(self.scroll_offsets.restore(&self.identities));
+            self.cache.lock().tweens().lock().prune(&self.identities);
+            self.set_diff(recorder.finish(source));
+        }
+
+        self.update_source_map();
+    }
+
+    /// Map the lines of the markup the tree was parsed from, to locate the elements of widgets and errors
+    fn update_source_map(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let file = self
+            .filename
+            .as_ref()
+            .map(|filename| filename.display().to_string());
+        #[cfg(target_arch = "wasm32")]
+        let file = None;
+
+        let source_map = self
+            .source
+            .as_deref()
+            .map(|source| SourceMap::new(file, &self.context.expand(source)));
+        self.cache.lock().set_source_map(source_map);
+
+        for duplicate in self.duplicate_ids() {
+            warn!("{duplicate}");
+        }
+    }
+
+    /// Validate the element ids of the tree, reporting each id given to more than one element with the locations
+    /// of its elements in the markup. Only the first element with an id can be found by it.
+    pub fn duplicate_ids(&self) -> Vec<DuplicateElementId> {
+        let mut guard = self.tree.lock();
+        let Some(tree) = guard.as_mut() else {
+            return Vec::new();
+        };
+
+        let cache = self.cache.lock();
+        self.identities.validate(|node_id| {
+            let node = tree.get_node_mut(&node_id)?;
+            let location = cache.source_location(node.node().data());
+            location
+        })
+    }
+
+    /// Get the [`NodeId`](arbutus::NodeId) of the element with an element id, such as `text#status("...")`
+    pub fn element(&self, element_id: &str) -> Option<arbutus::NodeId> {
+        self.identities.element(element_id)
+    }
+
+    /// Get the byte range of the element of a node in the markup, after `${ctx.key}` references are replaced
+    pub fn source_span(&self, node_id: NodeId) -> Option<Range<usize>> {
+        let mut guard = self.tree.lock();
+        let node = guard.as_mut()?.get_node_mut(&node_id)?;
+        let span = node.node().data().span().cloned();
+        span
+    }
+
+    /// Get the location of the element of a node in the markup, such as `sidebar.iced:42:7`
+    pub fn source_location(&self, node_id: NodeId) -> Option<SourceLocation> {
+        let mut guard = self.tree.lock();
+        let node = guard.as_mut()?.get_node_mut(&node_id)?;
+        let location = self.cache.lock().source_location(node.node().data());
+        location
+    }
+
+    /// Undo the last change to the markup, patching the tree back to the markup it replaced.
+    /// Returns false if there is no change to undo.
+    ///
+    /// The markup file isn't written, so the change is reapplied if the file is reloaded.
+    pub fn undo(&mut self) -> Result<bool, Error> {
+        self.step(HistoryStep::Undo)
+    }
+
+    /// Redo the last undone change to the markup. Returns false if there is no change to redo.
+    pub fn redo(&mut self) -> Result<bool, Error> {
+        self.step(HistoryStep::Redo)
+    }
+
+    /// Returns true if there is a change to undo
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    /// Returns true if there is an undone change to redo
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Set the maximum number of changes kept for undo, which defaults to 100
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
+
+    /// Set the [`ParserOptions`] of the markup loaded after the call. With [`ParserOptions::strict`], unknown widgets
+    /// and attributes which have no effect on their element are parse errors, rather than warnings when widgets are
+    /// built.
+    pub fn set_parser_options(&mut self, options: ParserOptions) {
+        self.parser_options = options;
+    }
+
+    /// Get the [`ParserOptions`] of markup from a file. Without an [`ParserOptions::asset_dir`], files inlined with
+    /// `embed!()` are resolved from the directory of the markup file, and they must be inside the file roots of the
+    /// [`ModulePolicy`] of the engine. Custom widgets are validated against the [`WidgetRegistry`] of the engine.
+    fn parser_options_for(&self, filename: Option<&Path>) -> ParserOptions {
+        let mut options = self.parser_options.clone();
+        if options.asset_dir.is_none() {
+            options.asset_dir = filename.and_then(Path::parent).map(Path::to_path_buf);
+        }
+        options.widgets = self.widgets();
+        options.policy = self.modules.lock().policy().clone();
+        options
+    }
+
+    /// Patch the tree to the markup of a step through the history
+    fn step(&mut self, step: HistoryStep) -> Result<bool, Error> {
+        let Some(current) = self.source.clone() else {
+            return Ok(false);
+        };
+
+        let markup = match step {
+            HistoryStep::Undo => self.history.undo(current),
+            HistoryStep::Redo => self.history.redo(current),
+        };
+        let Some(markup) = markup else {
+            return Ok(false);
+        };
+
+        let options = self.parser_options_for(self.filename.as_deref());
+        let tree = match perf::measure(&mut self.timings.parse, || {
+            self.context.parse(&markup, &options)
+        }) {
+            Ok(tree) => tree,
+            Err(e) => {
+                // Step back, so the history still matches the live tree
+                let _ = match step {
+                    HistoryStep::Undo => self.history.redo(markup),
+                    HistoryStep::Redo => self.history.undo(markup),
+                };
+                return Err(e.into());
+            }
+        };
+
+        let source = match step {
+            HistoryStep::Undo => "undo",
+            HistoryStep::Redo => "redo",
+        };
+        debug!("Patching the tree to the markup of {source}");
+        self.patch_memory(tree, &markup, source);
+        Ok(true)
+    }
+
+    fn set_tree(&mut self, tree: IndexedTree, source: String) -> Result<(), Error> {
+        // Changes to a previous tree can't be undone
+        self.history.clear();
+        self.identities = IdentityIndex::build(&tree);
+        self.theme.lock().apply_markup(&tree);
+
+        if let Some(state) = self.restored_state.take() {
+            state.apply(&tree);
+            self.teardown_tasks
+                .push(self.scroll_offsets.restore(&self.identities));
+        }
+
+        *self.tree.lock() = Some(tree);
+        self.update_source_map();
+
+        info!(source = %source, "Tree loaded");
+        telemetry::emit(TelemetryEvent::TreeLoaded { source });
+        Ok(())
+    }
+
+    /// Keep the [`DiffReport`] of a reload, and emit it as telemetry
+    fn set_diff(&mut self, report: DiffReport) {
+        let (added, removed, modified) = (
+            report.count(DiffChange::Added),
+            report.count(DiffChange::Removed),
+            report.count(DiffChange::Modified),
+        );
+        info!(source = %report.source, added, removed, modified, "Tree reloaded");
+
+        telemetry::emit(TelemetryEvent::TreeReloaded {
+            source: report.source.clone(),
+            added,
+            removed,
+            modified,
+        });
+        self.last_diff = Some(report);
+    }
+
+    /// Save the state of the user interface to a file, so it can be restored in a later session with
+    /// [`Snowcap::restore_state()`]. The state is the value of each slider, toggler, pick list and text input with
+    /// an element id, the offsets of scrollables, and the size of the window.
+    pub fn save_state(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut state = match &*self.tree.lock() {
+            Some(tree) => SessionState::capture(tree),
+            None => SessionState::default(),
+        };
+        state.window = self.modules.lock().window_size().get();
+        state.scroll = self.scroll_offsets.offsets().into_iter().collect();
+
+        state.save(path.as_ref())?;
+        info!(
+            "Saved the state of {} elements to {}",
+            state.elements.len(),
+            path.as_ref().display()
+        );
+        Ok(())
+    }
+
+    /// Restore the state of the user interface saved with [`Snowcap::save_state()`], and get a [`Task`] scrolling
+    /// the scrollables and resizing the window. If no markup is loaded yet, the state is merged into the tree once
+    /// the markup is parsed.
+    pub fn restore_state(&mut self, path: impl AsRef<Path>) -> Result<Task<Message>, Error> {
+        let state = SessionState::load(path.as_ref())?;
+
+        for (stable_id, offset) in &state.scroll {
+            self.scroll_offsets.record(stable_id.clone(), *offset);
+        }
+
+        let resize = match state.window {
+            Some(size) => {
+                iced::window::get_oldest().and_then(move |id| iced::window::resize(id, size))
+            }
+            None => Task::none(),
+        };
+
+        let scroll = match &*self.tree.lock() {
+            Some(tree) => {
+                state.apply(tree);
+                self.scroll_offsets.restore(&self.identities)
+            }
+            None => {
+                self.restored_state = Some(state);
+                Task::none()
+            }
+        };
+
+        Ok(Task::batch([resize, scroll]))
+    }
+
+    /// Resolve a [`StableId`] to the [`NodeId`](arbutus::NodeId) it currently refers to in the live tree.
+    ///
+    /// Node ids change when nodes are replaced by a reload, so host code should hold on to
+    /// the [`StableId`] and resolve it when needed.
+    pub fn resolve(&self, id: &StableId) -> Option<arbutus::NodeId> {
+        self.identities.node_id(id)
+    }
+
+    /// Get the [`StableId`] of a node in the live tree
+    pub fn stable_id(&self, node_id: arbutus::NodeId) -> Option<&StableId> {
+        self.identities.stable_id(node_id)
+    }
+
+    /// Export the node hierarchy of the tree, with the id, content kind and content hash of each node,
+    /// as Graphviz DOT or Mermaid text. Returns None if no markup is loaded.
+    pub fn dump_graph(&self, format: GraphFormat) -> Option<String> {
+        let guard = self.tree.lock();
+        let tree = guard.as_ref()?;
+        Some(graph::dump(tree.root(), format))
+    }
 
-use std::cell::RefCell;
-use std::path::PathBuf;
-use std::rc::Rc;
-use std::sync::Arc;
+    /// Enable or disable the diff viewer panel, which shows the changes applied by the most recent reload
+    pub fn set_diff_viewer(&mut self, enabled: bool) {
+        self.diff_viewer = enabled;
+    }
 
-pub use conversion::theme::SnowcapTheme;
-pub use error::*;
-pub use salish::Message;
+    /// Register a [`WidgetPlugin`] intercepting the widgets built for the nodes it matches. Plugins are applied in the
+    /// order they are registered, and widgets already built are rebuilt to pass them to the plugin.
+    pub fn register_plugin(&mut self, plugin: impl WidgetPlugin + 'static) {
+        let mut cache = self.cache.lock();
+        cache.register_plugin(Arc::new(plugin));
+        cache.clear();
 
-pub use parser::SnowcapParser;
-pub use parser::Value;
+        if let Some(tree) = &*self.tree.lock() {
+            command::mark_all_dirty(tree);
+        }
+    }
 
-use tracing::error;
-use tracing::info;
+    /// Show or hide the debug inspector over the root widget
+    pub fn set_inspector(&mut self, enabled: bool) {
+        self.inspector.store(enabled, Ordering::Relaxed);
+    }
 
-//type Node<Data, Id> = arbutus::node::rc::Node<Data, Id>;
-//type NodeRef<M> = arbutus::noderef::rc::NodeRef<Node<SnowcapNode<M>, arbutus::NodeId>>;
+    /// Returns true if the debug inspector is shown
+    pub fn inspector_enabled(&self) -> bool {
+        self.inspector.load(Ordering::Relaxed)
+    }
 
-type Node<Data, Id> = arbutus::node::arc::Node<Data, Id>;
-type NodeRef = arbutus::noderef::arc::NodeRef<Node<SnowcapNode, arbutus::NodeId>>;
+    /// Start recording the widget messages and commands routed through the engine, discarding
+    /// any recording in progress. See [`Recording`].
+    pub fn start_recording(&mut self) {
+        self.recorder.start();
+    }
 
-type Tree = arbutus::Tree<NodeRef>;
-type IndexedTree = arbutus::IndexedTree<NodeRef>;
-type NodeId = arbutus::NodeId;
+    /// Stop recording, and get the recorded messages. Returns None if not recording.
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recorder.stop()
+    }
 
-#[derive(Debug, Clone, Copy, Hash)]
-pub enum Source {
-    Module(ModuleHandleId),
-}
+    /// Get the nodes which failed to build into widgets, and their errors. Failing nodes are rendered as an error
+    /// placeholder, or by the fallback of an enclosing error boundary, while the rest of the tree is still built.
+    pub fn build_errors(&self) -> Vec<(NodeId, String)> {
+        self.cache.lock().errors()
+    }
 
-/// Top level Snowcap Engine which manages loading and parsing grammar into an [`Arbutus`](https://github.com/boondocklabs/arbutus) tree.
-/// Provides the update() and view()
-pub struct Snowcap {
-    #[cfg(not(target_arch = "wasm32"))]
-    filename: Option<PathBuf>,
-    tree: Arc<Mutex<Option<IndexedTree>>>,
-    modules: Rc<RefCell<ModuleManager>>,
-    watcher: Option<FileWatcher>,
+    /// Take a snapshot of the engine metrics, recorded while enabled with [`metrics::enable()`].
+    /// Metrics are shared by every engine in the process.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        metrics::snapshot()
+    }
 
-    router: MessageRouter<'static, Task<salish::message::Message>, Source>,
+    /// Capture the nodes of the tree, the running module instances and the last update timings
+    /// shown by the debug inspector. Returns None if no markup is loaded.
+    pub fn inspect(&self) -> Option<InspectorReport> {
+        let modules = self.modules.lock().instances();
+        let guard = self.tree.lock();
+        let tree = guard.as_ref()?;
+        Some(InspectorReport::capture(
+            tree.root(),
+            &self.cache.lock(),
+            modules,
+            self.last_timings,
+        ))
+    }
 
-    cache: Rc<RefCell<WidgetCache>>,
+    /// Get the active theme, declared with a `theme` attribute on the markup root, or set with
+    /// [`Command::SetTheme`]. Return this from the `theme` function of the application, so the
+    /// window background matches the widgets.
+    pub fn theme(&self) -> Option<iced::Theme> {
+        self.theme.lock().active()
+    }
 
-    _command_endpoint: Endpoint<'static, Command, Task<Message>, Source>,
-    _widget_endpoint: Endpoint<'static, WidgetMessage, Task<Message>, Source>,
-}
+    /// Get the default text size declared by a custom theme definition in the markup.
+    /// iced applies the default text size from the application settings, so applications
+    /// should pass this to [`iced::Application::default_text_size()`] when creating the application.
+    pub fn text_size(&self) -> Option<iced::Pixels> {
+        self.tree.lock().as_ref().and_then(root_text_size)
+    }
 
-impl Snowcap {
-    /// Create a new Snowcap Engine instance
-    pub fn new() -> Result<Self, Error> {
-        let router = MessageRouter::<Task<Message>, Source>::new();
+    /// Switch the active theme. Every widget is restyled on the next redraw.
+    pub fn set_theme(&mut self, theme: iced::Theme) {
+        self.theme.lock().set(theme);
+    }
 
-        let tree = Arc::new(Mutex::new(None));
-        let modules = Rc::new(RefCell::new(ModuleManager::new(router.clone())));
-
-        let command_endpoint = router
-            .create_endpoint::<Command>()
-            .message(|source, command| match command {
-                Command::Shutdown => {
-                    println!("Shutdown command received from {source:?}");
-                    iced::exit()
+    /// Replace the `styles` block of the markup with a stylesheet, such as `styles { #title { size:40 } }`.
+    /// Elements with an id or class are rebuilt on the next update.
+    pub fn set_stylesheet(&mut self, source: &str) -> Result<(), Error> {
+        self.stylesheet = Some(Arc::new(StyleParser::parse_source(source)?));
+        Ok(())
+    }
+
+    /// Remove the stylesheet set with [`Snowcap::set_stylesheet()`], restoring the `styles` block of the markup
+    pub fn clear_stylesheet(&mut self) {
+        self.stylesheet = None;
+    }
+
+    /// Get the stylesheet applied to elements, set with [`Snowcap::set_stylesheet()`] or declared in the markup
+    fn stylesheet(&self, tree: &IndexedTree) -> Option<Arc<Stylesheet>> {
+        self.stylesheet
+            .clone()
+            .or_else(|| self.style_file.as_ref()?.stylesheet.clone())
+            .or_else(|| root_stylesheet(tree))
+    }
+
+    /// Load the external stylesheet referenced by the markup with `styles!()` when the markup references a different
+    /// file, or the watcher reported the file modified. The rules of the file are kept until it parses again.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_style_file(&mut self) {
+        let modified = std::mem::take(&mut *self.modified_files.lock());
+
+        let reference = self
+            .tree
+            .lock()
+            .as_ref()
+            .and_then(root_stylesheet_file)
+            .map(|path| {
+                // Relative paths are resolved from the directory of the markup file
+                match self
+                    .filename
+                    .as_ref()
+                    .and_then(|filename| filename.parent())
+                {
+                    Some(dir) if path.is_relative() => dir.join(path),
+                    _ => path,
                 }
-                Command::Reload => todo!(),
             });
 
-        // Create an endpoint listening for WidgetMessage messages, which finds the node
-        // in the tree, and marks it as dirty.
-        let _tree = tree.clone();
-        let widget_endpoint =
-            router
-                .create_endpoint::<WidgetMessage>()
-                .message(move |_source, message| {
-                    let mut guard = _tree.lock();
-                    let tree: &mut IndexedTree = guard.as_mut().unwrap();
+        // The path is only canonicalized when the markup references a different file
+        let path = reference.as_ref().map(|reference| match &self.style_file {
+            Some(file) if file.reference == *reference => file.path.clone(),
+            _ => std::fs::canonicalize(reference).unwrap_or_else(|_| reference.clone()),
+        });
 
-                    if let Some(node) = tree.get_node_mut(&message.node_id) {
-                        // Mark the node as dirty
-                        node.node_mut().data_mut().set_dirty(true);
-                    }
-                    Task::none()
-                });
+        let current = self.style_file.as_ref().map(|file| file.path.clone());
+        if path == current && !path.as_ref().is_some_and(|path| modified.contains(path)) {
+            return;
+        }
 
-        let snow = Self {
-            tree,
-            #[cfg(not(target_arch = "wasm32"))]
-            filename: None,
-            modules,
-            watcher: None,
-            router,
-            _command_endpoint: command_endpoint,
-            _widget_endpoint: widget_endpoint,
-            cache: Rc::new(RefCell::new(WidgetCache::default())),
+        if path != current {
+            if let (Some(watcher), Some(current)) = (self.watcher.as_mut(), &current) {
+                if let Err(e) = watcher.unwatch(current) {
+                    warn!("Failed to unwatch stylesheet {}: {e}", current.display());
+                }
+            }
+            if let (Some(watcher), Some(path)) = (self.watcher.as_mut(), &path) {
+                if let Err(e) = watcher.watch(path) {
+                    warn!("Failed to watch stylesheet {}: {e}", path.display());
+                }
+            }
+        }
+
+        let (Some(reference), Some(path)) = (reference, path) else {
+            self.style_file = None;
+            return;
         };
 
-        Ok(snow)
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(Error::from)
+            .and_then(|source| Ok(StyleParser::parse_source(&source)?));
+
+        let stylesheet = match loaded {
+            Ok(stylesheet) => {
+                info!(file = %path.display(), "Stylesheet loaded");
+                Some(Arc::new(stylesheet))
+            }
+            Err(e) => {
+                error!("Failed to load stylesheet {}: {e}", path.display());
+                self.style_file
+                    .take()
+                    .filter(|file| file.path == path)
+                    .and_then(|file| file.stylesheet)
+            }
+        };
+
+        self.style_file = Some(StyleFile {
+            reference,
+            path,
+            stylesheet,
+        });
     }
 
-    /// Engine initialization, called by [`iced::Application`].
-    /// Traverses the tree to build widgets, and gets an init [`iced::Task`]
-    /// from each instantiated [`module`] in the tree.
-    pub fn init(&mut self) -> Task<Message> {
-        let mut tasks = Vec::new();
+    /// Set the themes used for the light and dark appearances of the operating system, when
+    /// the markup root follows the system with `theme:"system"`. Defaults to light and dark.
+    pub fn set_system_themes(&mut self, light: iced::Theme, dark: iced::Theme) {
+        self.theme.lock().set_system_themes(light, dark);
+    }
 
-        let (watcher, watcher_task) = FileWatcher::new();
+    /// Set the [`ModulePolicy`] restricting the modules, file paths and URLs the markup can use.
+    /// This should be set before loading markup from an untrusted source.
+    pub fn set_module_policy(&mut self, policy: ModulePolicy) {
+        self.modules.lock().set_policy(policy);
+    }
 
-        self.watcher = Some(watcher);
+    /// Set the [`HttpConfig`] of the client of the http module, such as timeouts, redirects and a proxy. Applies to
+    /// http modules of this engine instantiated after the call.
+    pub fn set_http_config(&mut self, config: HttpConfig) {
+        self.modules.lock().set_http_config(config);
+    }
 
-        tasks.push(watcher_task);
+    /// Set the [`VideoDecoder`] used by `video` widgets to play video data from modules
+    pub fn set_video_decoder(&mut self, decoder: Arc<dyn VideoDecoder>) {
+        self.cache.lock().set_video_decoder(decoder);
+    }
 
-        if let Some(filename) = &self.filename {
-            self.watcher.as_mut().unwrap().watch(filename).unwrap();
-        }
+    /// Set the minimum interval between widget rebuilds. Data messages arriving within the interval update the tree
+    /// immediately, and the widgets of the changed nodes are rebuilt together at the end of the interval.
+    /// Defaults to 16ms, one frame at 60Hz. A zero interval rebuilds widgets on every update.
+    pub fn set_update_interval(&mut self, interval: Duration) {
+        self.batch.set_interval(interval);
+    }
 
-        // Run the initial tree update, and get any tasks (Provider init tasks)
-        let tree_task = if let Some(tree) = &*self.tree.lock() {
-            profiling::scope!("build-widgets");
-            info!("{}", tree.root());
-            let mut cache = self.cache.borrow_mut();
-            match cache.update_tree(tree, &mut self.modules_mut()) {
-                Ok(task) => task,
-                Err(e) => {
-                    error!("Failed to build widgets: {}", e);
-                    Task::none()
-                }
-            }
+    /// Get the [`DiffReport`] of the most recent reload
+    pub fn last_diff(&self) -> Option<&DiffReport> {
+        self.last_diff.as_ref()
+    }
+
+    /// Set the visibility of the window. The engine hides the window when it is resized to zero, as it is when
+    /// minimized, and shows it when it is restored. Applications can call this when the window is hidden by other
+    /// means, such as being occluded. Module instances are notified with [`module::Module::on_visibility()`], so timers declared
+    /// with `while-visible:true` pause while the window is hidden.
+    pub fn set_window_visible(&mut self, visible: bool) -> Task<Message> {
+        self.window_visible = visible;
+        self.update_visibility()
+    }
+
+    /// Set the visibility of a node, such as when a collapsible section containing it is collapsed. Module instances
+    /// referenced only by hidden nodes, or their descendants, are notified with [`module::Module::on_visibility()`].
+    /// Nodes are visible unless hidden by the application, as the engine doesn't track which widgets are shown.
+    pub fn set_node_visible(&mut self, node_id: arbutus::NodeId, visible: bool) -> Task<Message> {
+        if visible {
+            self.hidden.remove(&node_id);
         } else {
-            Task::none()
+            self.hidden.insert(node_id);
+        }
+        self.update_visibility()
+    }
+
+    /// Notify module instances whose visibility has changed
+    fn update_visibility(&mut self) -> Task<Message> {
+        let window_visible = self.window_visible;
+        let hidden = &self.hidden;
+
+        let mut guard = self.tree.lock();
+        let Some(tree) = &mut *guard else {
+            return Task::none();
         };
 
-        tasks.push(tree_task);
+        self.modules.lock().update_visibility(|node_id| {
+            if !window_visible {
+                return false;
+            }
 
-        info!("Starting init tasks");
+            // Walk up the tree, checking if the node or any of its ancestors are hidden
+            let mut current = tree.get_node_mut(&node_id).map(|noderef| noderef.clone());
+            while let Some(noderef) = current {
+                if hidden.contains(&noderef.node().id()) {
+                    return false;
+                }
+                current = noderef.node().parent().cloned();
+            }
 
-        Task::batch(tasks)
+            true
+        })
     }
 
-    /// Get a reference to the [`ModuleManager`] for registering and instantiating modules at runtime
-    pub fn modules(&self) -> std::cell::Ref<'_, ModuleManager> {
-        self.modules.borrow()
+    /// Get a [`Task`] scrolling the scrollable declared with `element_id` in the markup to an absolute offset.
+    /// The same can be done by sending a [`Command::ScrollTo`] message.
+    pub fn scroll_to(
+        &self,
+        element_id: impl Into<parser::ElementId>,
+        offset: iced::widget::scrollable::AbsoluteOffset,
+    ) -> Task<Message> {
+        scroll::scroll_to(element_id.into(), offset)
     }
 
-    /// Get a mutable reference to the [`ModuleManager`] for registering and instantiating modules at runtime
-    pub fn modules_mut(&self) -> std::cell::RefMut<'_, ModuleManager> {
-        self.modules.borrow_mut()
+    /// Get a [`iced::Subscription`] forwarding window events to the engine. The application should include
+    /// this in its subscriptions, so the `window!{}` module and subscribers of the `window/*` topics receive them.
+    ///
+    /// While attribute transitions are running, it also requests animation frames to rebuild the tweening nodes,
+    /// and while a drag is in progress it listens for mouse releases outside of drop targets to cancel it.
+    /// Navigation keys move the keyboard focus between pick lists and change their selection. With `theme:"system"`
+    /// it polls the appearance of the operating system.
+    pub fn subscription(&self) -> iced::Subscription<Message> {
+        let frames = if self.cache.lock().tweens().lock().is_active() {
+            iced::window::frames().map(|_| Message::broadcast(Command::AnimationFrame))
+        } else {
+            iced::Subscription::none()
+        };
+
+        iced::Subscription::batch([
+            module::window::subscription(),
+            iced::keyboard::on_key_press(inspector::hotkey),
+            iced::keyboard::on_key_press(focus::key_press),
+            frames,
+            conversion::drag::subscription(&self.drag),
+            appearance::subscription(&self.theme.lock()),
+        ])
     }
 
-    /// Get a reference to the [`MessageRouter`]
-    pub fn router(&mut self) -> &mut MessageRouter<'static, Task<Message>, Source> {
-        &mut self.router
+    /// Get a [`Task`] publishing a message to a [`Topic`]. The message is delivered to modules
+    /// and application subscriptions with a matching topic.
+    pub fn publish(&self, topic: impl Into<Topic>, message: TopicMessage) -> Task<Message> {
+        module::pubsub::publish(topic, message)
     }
 
-    /// Load a markup file and set the active [`arbutus`] tree.
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn load_file(&mut self, filename: String) -> Result<(), Error> {
-        use colored::Colorize;
+    /// Subscribe to messages published to topics matching `pattern`, calling `f` with the topic and message.
+    /// The [`Task`] returned by `f` is run by the engine. The subscription is active until the returned
+    /// [`TopicSubscription`] is dropped.
+    pub fn subscribe<F>(&self, pattern: impl Into<Topic>, f: F) -> TopicSubscription
+    where
+        F: Fn(Topic, TopicMessage) -> Task<Message> + Send + Sync + 'static,
+    {
+        TopicSubscription::new(&self.router, pattern.into(), f)
+    }
 
-        let filename = &PathBuf::from(&filename);
-        let tree = SnowcapParser::<Message>::parse_file(&filename)?;
+    /// Call `f` with each message published to `topic`, which may contain wildcards. Unlike
+    /// [`Snowcap::subscribe()`], `f` only receives the message. The handler is active until the
+    /// returned [`TopicSubscription`] is dropped.
+    pub fn on_topic<F>(&self, topic: impl Into<Topic>, f: F) -> TopicSubscription
+    where
+        F: Fn(TopicMessage) -> Task<Message> + Send + Sync + 'static,
+    {
+        TopicSubscription::new(&self.router, topic.into(), move |_topic, message| {
+            f(message)
+        })
+    }
 
-        let tree = IndexedTree::from_tree(tree);
+    /// Call `f` with each [`WidgetEvent`] sent by the widget with an element id, such as `"#save"` for
+    /// `button#save`. The [`Task`] returned by `f` is run by the engine.
+    /// The handler is active until the returned [`WidgetEventSubscription`] is dropped.
+    pub fn on_widget_event<F>(&self, element_id: &str, f: F) -> WidgetEventSubscription
+    where
+        F: Fn(WidgetEvent) -> Task<Message> + Send + Sync + 'static,
+    {
+        WidgetEventSubscription::new(&self.router, element_id, f)
+    }
 
-        println!(
-            "\n{}\n{}",
-            "Snowcap file loaded into tree:".magenta(),
-            tree.root()
-        );
+    /// Load a translation catalog for a locale, such as `"fr"`, from a Fluent `.ftl` or gettext `.po` file.
+    /// Returns a [`Task`] updating the `i18n!` modules if the locale is active.
+    pub fn load_catalog(
+        &self,
+        locale: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<Task<Message>, Error> {
+        Ok(self
+            .modules
+            .lock()
+            .locales()
+            .load_catalog(locale, path.as_ref())?)
+    }
 
-        self.filename = Some(filename.clone());
+    /// Switch the locale of the `i18n!` modules. Returns a [`Task`] updating their messages, which rebuilds
+    /// only the nodes displaying translated strings.
+    pub fn set_locale(&self, locale: &str) -> Task<Message> {
+        self.modules.lock().locales().set_locale(locale)
+    }
 
-        self.set_tree(tree)?;
+    /// Get the active locale of the `i18n!` modules
+    pub fn locale(&self) -> String {
+        self.modules.lock().locales().locale()
+    }
 
+    /// Set a value of the application, which the markup references with `${ctx.key}`. If the value changed, the
+    /// markup is reparsed and the changes are patched into the tree, rebuilding only the elements referencing it.
+    pub fn set_context(
+        &mut self,
+        key: impl Into<String>,
+        value: impl ToString,
+    ) -> Result<(), Error> {
+        if self.context.set(key, value.to_string()) {
+            self.apply_context()?;
+        }
         Ok(())
     }
 
-    /// Load markup from memory. If a tree is currently loaded, the new tree is diffed
-    /// and changes are patched into the existing tree.
-    pub fn load_memory(&mut self, data: &str) -> Result<(), Error> {
-        let tree = SnowcapParser::<Message>::parse_memory(data)?;
-
-        if let Some(current) = &mut *self.tree.lock() {
-            // We already have a tree loaded. Diff the trees
-            let mut diff = TreeDiff::new(current.root().clone(), tree.root().clone());
-            let patch = diff.diff();
-
-            info!("Patching existing tree {patch:#?}");
-            patch.patch_tree(current);
+    /// Remove a value of the application, so references to it in the markup are empty
+    pub fn remove_context(&mut self, key: &str) -> Result<(), Error> {
+        if self.context.remove(key) {
+            self.apply_context()?;
+        }
+        Ok(())
+    }
 
-            current.reindex();
+    /// Get a value of the application set with [`Snowcap::set_context()`]
+    pub fn context(&self, key: &str) -> Option<&str> {
+        self.context.get(key)
+    }
 
+    /// Patch the tree to the markup with the current values of the application
+    fn apply_context(&mut self) -> Result<(), Error> {
+        let Some(markup) = self.source.clone() else {
+            return Ok(());
+        };
+        if self.tree.lock().is_none() {
             return Ok(());
         }
 
-        self.set_tree(IndexedTree::from_tree(tree))?;
+        let options = self.parser_options_for(self.filename.as_deref());
+        let tree = perf::measure(&mut self.timings.parse, || {
+            self.context.parse(&markup, &options)
+        })?;
 
+        self.patch_memory(tree, &markup, "context");
         Ok(())
     }
 
-    fn set_tree(&mut self, tree: IndexedTree) -> Result<(), Error> {
-        *self.tree.lock() = Some(tree);
-        Ok(())
+    /// Bind a [`Bound`] cell to a path, which widgets reference in the markup with `value:bind(path)`.
+    /// Returns a [`Task`] rebuilding widgets already bound to the path.
+    pub fn bind<T: Bindable>(&self, path: impl Into<String>, cell: Bound<T>) -> Task<Message> {
+        let path = path.into();
+        self.cache
+            .lock()
+            .bindings()
+            .lock()
+            .insert(&path, Arc::new(cell));
+
+        Task::done(Message::broadcast(Command::BindingChanged(path)))
     }
 
     #[cfg(not(target_arch = "wasm32"))]
     pub fn reload_file(&mut self) -> Result<(), Error> {
         use arbutus::TreeDiff;
-        use colored::Colorize;
 
         let filename = self.filename.clone().ok_or(Error::MissingAttribute(
             "No snowcap grammar filename in self".to_string(),
         ))?;
 
-        // Parse the new file into an IndexedTree
-        let mut new_tree = IndexedTree::from_tree(SnowcapParser::<Message>::parse_file(&filename)?);
+        let source = std::fs::read_to_string(&filename)?;
+        let options = self.parser_options_for(Some(filename.as_path()));
 
-        let _listener = new_tree
-            .on_event(|event| {
-                println!("NEW TREE EVENT {event:?}");
-            })
-            .ok();
-
-        println!("{}", "Parsed New Tree".bright_magenta());
-        println!("{}", new_tree.root());
+        // The file was written without changes
+        if self.source.as_deref() == Some(source.as_str()) {
+            return Ok(());
+        }
 
         if let Some(tree) = &mut (*self.tree.lock()) {
+            // Reparse only the element enclosing the changes if possible, otherwise parse the whole file
+            let expanded = self.context.expand(&source);
+            // Strict markup is validated by parsing the whole file
+            let strict = options.strict;
+            let reparse = perf::measure(&mut self.timings.parse, || {
+                parser::value::with_asset_dir(options.asset_dir.as_deref(), &options.policy, || {
+                    self.source.as_deref().filter(|_| !strict).and_then(|old| {
+                        self.context.scope(|| {
+                            Reparse::new::<Message>(tree, &self.context.expand(old), &expanded)
+                        })
+                    })
+                })
+            });
+
             // Register an event handler on the tree. It will automatically be deregistered when it goes out of scope.
             // This handler listens for tree modification events, and marks the nodes as dirty in the snowcap node data,
             // so the affected widgets will be rebuilt on the next update pass.
+            let recorder = DiffRecorder::default();
+            let event_recorder = recorder.clone();
             let _listener = tree
-                .on_event(|event| {
-                    match event {
-                        arbutus::TreeEvent::NodeRemoved { node } => {
-                            if let Some(parent) = node.clone().node_mut().parent_mut() {
-                                parent.node_mut().data_mut().set_state(node::State::Dirty)
-                            }
-                        }
-                        arbutus::TreeEvent::NodeReplaced { node } => node
-                            .clone()
-                            .node_mut()
-                            .data_mut()
-                            .set_state(node::State::New),
-                        arbutus::TreeEvent::SubtreeInserted { node } => {
-                            // Invalidate the whole subtree
-                            for mut n in node {
-                                n.node_mut().data_mut().set_state(node::State::New)
-                            }
-                        }
-                        arbutus::TreeEvent::ChildRemoved { parent, .. } => parent
-                            .clone()
-                            .node_mut()
-                            .data_mut()
-                            .set_state(node::State::Dirty),
-                        arbutus::TreeEvent::ChildrenRemoved { parent, .. } => parent
-                            .clone()
-                            .node_mut()
-                            .data_mut()
-                            .set_state(node::State::Dirty),
-                        arbutus::TreeEvent::ChildrenAdded { parent, children } => {
-                            for child in children {
-                                child
-                                    .clone()
-                                    .node_mut()
-                                    .data_mut()
-                                    .set_state(node::State::New)
-                            }
-                            parent
-                                .clone()
-                                .node_mut()
-                                .data_mut()
-                                .set_state(node::State::Dirty)
-                        }
-                        arbutus::TreeEvent::ChildReplaced { parent, index }
-                        | arbutus::TreeEvent::ChildInserted { parent, index } => {
-                            // Invalidate the child
-                            let mut parent = parent.clone();
-                            let mut node = parent.node_mut();
-                            let child = node.children_mut().unwrap().get_mut(*index).unwrap();
-
-                            child.node_mut().data_mut().set_state(node::State::New);
-                        }
-                    };
+                .on_event(move |event| {
+                    event_recorder.record(event);
+                    invalidate_patched(event);
                 })
                 .unwrap();
 
-            let mut diff = TreeDiff::new(tree.root().clone(), new_tree.root().clone());
-            let patch = diff.diff();
-            patch.patch_tree(tree);
+            let (mut diff, new_tree) = match &reparse {
+                Some(reparse) => (
+                    TreeDiff::new(reparse.target().clone(), reparse.replacement()),
+                    None,
+                ),
+                None => {
+                    let new_tree = IndexedTree::from_tree(
+                        perf::measure(&mut self.timings.parse, || {
+                            self.context.parse(&source, &options)
+                        })
+                        .map_err(Error::Parse)?,
+                    );
+                    debug!("Parsed new tree\n{}", new_tree.root());
+
+                    (
+                        TreeDiff::new(tree.root().clone(), new_tree.root().clone()),
+                        Some(new_tree),
+                    )
+                }
+            };
+            let patch = perf::measure(&mut self.timings.diff, || diff.diff());
+            perf::measure(&mut self.timings.patch, || patch.patch_tree(tree));
 
             tree.reindex();
+
+            // Update the spans of nodes kept by the patch to the new markup
+            if let Some(reparse) = reparse {
+                reparse.finish(tree);
+            } else if let Some(new_tree) = &new_tree {
+                parser::incremental::copy_spans(new_tree.root(), tree.root(), 0);
+            }
+
+            // Tear down module instances whose nodes were removed by the patch
+            let teardown = self
+                .modules
+                .lock()
+                .release_nodes(|node_id| tree.get_node_mut(&node_id).is_some());
+            self.teardown_tasks.push(teardown);
+
+            self.identities = IdentityIndex::build(tree);
+            self.theme.lock().apply_markup(tree);
+            self.teardown_tasks
+                .push(self.scroll_offsets.restore(&self.identities));
+            self.cache.lock().tweens().lock().prune(&self.identities);
+
+            let report = recorder.finish(filename.display().to_string());
+            debug!("{report}");
+            self.set_diff(report);
+
+            // The markup replaced by the reload can be restored by undo
+            if let Some(previous) = self.source.take() {
+                self.history.record(previous);
+            }
         }
 
+        self.source = Some(source);
+        self.update_source_map();
+
         Ok(())
     }
 
@@ -552,6 +2322,8 @@ impl Snowcap {
         };
         */
 
+        metrics::increment(metrics::UPDATES, None);
+
         // Pass the message to the router, and create a batch of returned tasks
         let router_task = if let Some(tasks) = self.router.handle_message(message) {
             Task::batch(tasks)
@@ -559,16 +2331,79 @@ impl Snowcap {
             Task::none()
         };
 
-        let tree_task = if let Some(tree) = &*self.tree.lock() {
+        if self.reload.swap(false, Ordering::Relaxed) {
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Err(e) = self.reload_file() {
+                error!("Failed to reload: {e}");
+            }
+        }
+
+        // Module instances are notified when the window is minimized or restored
+        let visibility_task = match self.window_resized_visible.lock().take() {
+            Some(visible) if visible != self.window_visible => self.set_window_visible(visible),
+            _ => Task::none(),
+        };
+
+        // Undo and redo requested by commands
+        for step in self.history.take_requests() {
+            if let Err(e) = self.step(step) {
+                error!("Failed to {step:?}: {e}");
+            }
+        }
+
+        // Load the external stylesheet of the markup, or reload it if it was modified
+        #[cfg(not(target_arch = "wasm32"))]
+        self.load_style_file();
+
+        let tree_task = if let Some(tree) = &mut *self.tree.lock() {
             profiling::scope!("build-widgets");
-            info!("{}", tree.root());
-            let mut cache = self.cache.borrow_mut();
-            match cache.update_tree(tree, &mut self.modules_mut()) {
-                Ok(task) => task,
-                Err(e) => {
-                    error!("Failed to build widgets: {}", e);
-                    Task::none()
+            let mut cache = self.cache.lock();
+
+            // Rebuild widgets with styles derived from the active theme when it changes
+            for node_id in cache.set_theme(self.theme()) {
+                if let Some(node) = tree.get_node_mut(&node_id) {
+                    node.node_mut().data_mut().set_dirty(true);
+                }
+            }
+
+            // Rebuild widgets selected by the rules of the stylesheet when it changes
+            for node_id in cache.set_stylesheet(self.stylesheet(tree)) {
+                if let Some(node) = tree.get_node_mut(&node_id) {
+                    node.node_mut().data_mut().set_dirty(true);
+                }
+            }
+
+            let needs_update = cache.needs_update();
+            let start = Instant::now();
+
+            // Markup loaded since the last update, and cleared widgets, are built immediately, as their nodes have
+            // no widgets
+            let cleared = cache.take_cleared();
+            let poll = if needs_update && self.timings.parse.is_none() && !cleared {
+                self.batch.poll(start)
+            } else {
+                Poll::Build
+            };
+
+            match poll {
+                Poll::Build => {
+                    let task = match cache.update_tree(tree, &mut self.modules_mut()) {
+                        Ok(task) => task,
+                        Err(e) => {
+                            error!("Failed to build widgets: {}", e);
+                            Task::none()
+                        }
+                    };
+
+                    if needs_update {
+                        self.timings.build = Some(start.elapsed());
+                        self.batch.built(start);
+                    }
+
+                    task
                 }
+                Poll::Schedule(delay) => batch::flush(delay),
+                Poll::Pending => Task::none(),
             }
         } else {
             Task::none()
@@ -576,19 +2411,32 @@ impl Snowcap {
 
         //tree_task.chain(router_task)
 
+        // Tasks queued by reloads since the last update
+        let teardown_task = Task::batch(self.teardown_tasks.drain(..));
+
         // Run the router tasks, followed by tree update tasks
-        router_task.chain(tree_task)
+        Task::batch([
+            teardown_task,
+            visibility_task,
+            router_task.chain(tree_task),
+            self.publish_timings(),
+        ])
     }
 
     #[profiling::function]
     pub fn view<'b>(&'b self) -> iced::Element<'b, Message> {
-        info!("View");
+        let start = Instant::now();
 
         let root = if let Some(tree) = &*self.tree.lock() {
             let root_id = tree.root().node().id();
 
-            if let Some(widget) = self.cache.borrow().get(root_id) {
-                widget.into_element().unwrap()
+            let widget = self.cache.lock().get(root_id);
+            if let Some(widget) = widget {
+                widget.into_element().unwrap_or_else(|e| {
+                    iced::widget::Text::new(format!("Failed to render root widget: {e}"))
+                        .style(iced::widget::text::danger)
+                        .into()
+                })
             } else {
                 iced::widget::Text::new("No root widget in tree").into()
             }
@@ -596,7 +2444,112 @@ impl Snowcap {
             iced::widget::Text::new("No tree").into()
         };
 
+        // Style closures of every widget receive the active theme
+        let root = match self.theme() {
+            Some(theme) => iced::widget::Themer::new(move |_| theme.clone(), root).into(),
+            None => root,
+        };
+
+        let root = match (self.diff_viewer, &self.last_diff) {
+            (true, Some(report)) => iced::widget::Column::new()
+                .push(iced::widget::container(root).height(iced::Length::Fill))
+                .push(iced::widget::horizontal_rule(1))
+                .push(report.view())
+                .into(),
+            _ => root,
+        };
+
+        let root = match self.inspector_enabled().then(|| self.inspect()).flatten() {
+            Some(report) => iced::widget::Stack::new()
+                .push(picker::SourcePicker::new(root))
+                .push(
+                    iced::widget::container(report.view())
+                        .width(iced::Length::Fill)
+                        .align_x(iced::alignment::Horizontal::Right),
+                )
+                .into(),
+            None => root,
+        };
+
+        self.view_time.set(Some(start.elapsed()));
+
         profiling::finish_frame!();
         root
     }
+
+    /// Get the [`PhaseTimings`] of the most recent update which loaded markup or rebuilt widgets
+    pub fn timings(&self) -> PhaseTimings {
+        self.last_timings
+    }
+
+    /// Publish the timings of the phases run since the last update on the [`perf::topic()`] diagnostics topic.
+    /// Nothing is published if no phases ran, so publishing doesn't cause further updates.
+    fn publish_timings(&mut self) -> Task<Message> {
+        if self.timings.is_empty() {
+            return Task::none();
+        }
+
+        let mut timings = std::mem::take(&mut self.timings);
+        timings.view = self.view_time.take();
+
+        debug!("Phase timings {timings}");
+        self.last_timings = timings;
+
+        module::pubsub::publish(perf::topic(), TopicMessage::any(timings))
+    }
+}
+
+/// Mark the nodes changed by a patch of the tree, so the affected widgets are rebuilt on the next update pass
+fn invalidate_patched(event: &arbutus::TreeEvent<NodeRef>) {
+    match event {
+        arbutus::TreeEvent::NodeRemoved { node } => {
+            if let Some(parent) = node.clone().node_mut().parent_mut() {
+                parent.node_mut().data_mut().set_state(node::State::Dirty)
+            }
+        }
+        arbutus::TreeEvent::NodeReplaced { node } => node
+            .clone()
+            .node_mut()
+            .data_mut()
+            .set_state(node::State::New),
+        arbutus::TreeEvent::SubtreeInserted { node } => {
+            // Invalidate the whole subtree
+            for mut n in node {
+                n.node_mut().data_mut().set_state(node::State::New)
+            }
+        }
+        arbutus::TreeEvent::ChildRemoved { parent, .. } => parent
+            .clone()
+            .node_mut()
+            .data_mut()
+            .set_state(node::State::Dirty),
+        arbutus::TreeEvent::ChildrenRemoved { parent, .. } => parent
+            .clone()
+            .node_mut()
+            .data_mut()
+            .set_state(node::State::Dirty),
+        arbutus::TreeEvent::ChildrenAdded { parent, children } => {
+            for child in children {
+                child
+                    .clone()
+                    .node_mut()
+                    .data_mut()
+                    .set_state(node::State::New)
+            }
+            parent
+                .clone()
+                .node_mut()
+                .data_mut()
+                .set_state(node::State::Dirty)
+        }
+        arbutus::TreeEvent::ChildReplaced { parent, index }
+        | arbutus::TreeEvent::ChildInserted { parent, index } => {
+            // Invalidate the child
+            let mut parent = parent.clone();
+            let mut node = parent.node_mut();
+            let child = node.children_mut().unwrap().get_mut(*index).unwrap();
+
+            child.node_mut().data_mut().set_state(node::State::New);
+        }
+    }
 }
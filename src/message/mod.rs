@@ -6,13 +6,18 @@ pub mod widget;
 use std::{
     any::{Any, TypeId},
     hash::Hash,
+    path::PathBuf,
     sync::Arc,
 };
 
+use iced::widget::scrollable::AbsoluteOffset;
 use strum::{EnumDiscriminants, EnumIter};
-use widget::WidgetMessage;
+use widget::{DragNode, WidgetMessage};
 
-use crate::{module::message::ModuleMessage, watcher::WatchMessage};
+use crate::{
+    appearance::Appearance, graph::GraphFormat, module::message::ModuleMessage, parser::ElementId,
+    watcher::WatchMessage,
+};
 
 /// Message Kind
 #[derive(Default, Debug, Clone, EnumDiscriminants)]
@@ -50,6 +55,81 @@ impl Into<TypeId> for &MessageData {
 #[strum_discriminants(derive(EnumIter, Hash))]
 #[strum_discriminants(name(CommandKind))]
 pub enum Command {
+    /// Notify module instances of shutdown, and exit once their shutdown tasks complete
     Shutdown,
+    /// Reload the markup file loaded with [`crate::Snowcap::load_file()`], patching changes into the live tree
     Reload,
+    /// Patch the tree back to the markup before the last change, see [`crate::Snowcap::undo()`]
+    Undo,
+    /// Reapply the last undone change, see [`crate::Snowcap::redo()`]
+    Redo,
+    /// Switch the active theme of the engine, overriding the `theme` attribute of the markup root
+    SetTheme(iced::Theme),
+    /// The light/dark appearance of the operating system changed
+    SetAppearance(Appearance),
+    /// An event of the application window, forwarded by [`crate::Snowcap::subscription()`]
+    Window(iced::window::Event),
+    /// Rebuild nodes with running attribute transitions, sent on each animation frame
+    AnimationFrame,
+    /// Scroll the scrollable declared with `element_id` in the markup to an absolute offset
+    ScrollTo {
+        element_id: ElementId,
+        offset: AbsoluteOffset,
+    },
+    /// The value of a binding changed, rebuild the widgets bound to the path
+    BindingChanged(String),
+    /// Rebuild the widgets of nodes changed since the last rebuild, deferred by update batching
+    Flush,
+    /// Show or hide the debug inspector overlay, see [`crate::InspectorReport`]
+    ToggleInspector,
+    /// Capture the application window into a PNG file. The path is published to [`SCREENSHOT_TOPIC`]
+    /// once the file is written.
+    Screenshot(PathBuf),
+    /// Publish the tree exported as graph text to [`TREE_TOPIC`], see [`crate::Snowcap::dump_graph()`]
+    DumpTree(GraphFormat),
+    /// Drop all cached widgets, and rebuild the whole tree
+    ClearCache,
+    /// The mouse was released over a `drop-target` element, dropping the drag in progress onto it
+    Drop(DragNode),
+    /// The mouse was released outside any drop target, cancelling the drag in progress
+    DragCancel,
+    /// Move the keyboard focus, or change the selection of the focused widget, sent on navigation key presses
+    Navigate(Navigation),
+    /// Open the markup of an element in an editor, sent by Ctrl+clicking a widget while the inspector is shown.
+    /// The location is published to [`OPEN_SOURCE_TOPIC`] as `path:line:column`.
+    OpenSource {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+    },
+}
+
+/// Keyboard navigation of selection widgets, see [`Command::Navigate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Navigation {
+    /// Focus the next widget, with Tab
+    Next,
+    /// Focus the previous widget, with Shift+Tab
+    Previous,
+    /// Select the previous option, with the up or left arrow
+    Up,
+    /// Select the next option, with the down or right arrow
+    Down,
+    /// Select the first option, with Home
+    First,
+    /// Select the last option, with End
+    Last,
+    /// Select the current option again, with Enter
+    Activate,
+    /// Release the focus, with Escape
+    Cancel,
 }
+
+/// Topic the path of each screenshot taken by [`Command::Screenshot`] is published to
+pub const SCREENSHOT_TOPIC: &str = "snowcap/screenshot";
+
+/// Topic the graph text of [`Command::DumpTree`] is published to
+pub const TREE_TOPIC: &str = "snowcap/tree";
+
+/// Topic the location of each [`Command::OpenSource`] is published to, as `path:line:column`
+pub const OPEN_SOURCE_TOPIC: &str = "snowcap/open-source";
@@ -1,11 +1,59 @@
-use std::sync::Arc;
+use std::{any::Any, sync::Arc};
 
 use colored::Colorize as _;
 
-use crate::module::data::ModuleData;
+use crate::{
+    module::data::ModuleData,
+    parser::{value::ValueData, Value},
+};
 
+/// Name of a pub/sub channel
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
-pub struct Topic(pub &'static str);
+pub struct Topic(pub String);
+
+impl Topic {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// Get the name of this topic
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    /// Match a topic name against this topic used as a pattern.
+    ///
+    /// Topic names are split into `/` separated segments. A `*` segment in the pattern matches
+    /// any single segment, and a trailing `**` segment matches any number of remaining segments.
+    /// For example `sensors/*` matches `sensors/temperature` but not `sensors/temperature/min`,
+    /// which is matched by `sensors/**`.
+    pub fn matches(&self, topic: &Topic) -> bool {
+        let mut pattern = self.0.split('/');
+        let mut name = topic.0.split('/');
+
+        loop {
+            match (pattern.next(), name.next()) {
+                (Some("**"), _) => return true,
+                (Some("*"), Some(_)) => continue,
+                (Some(expected), Some(segment)) if expected == segment => continue,
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+impl From<&str> for Topic {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Topic {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
 
 impl std::fmt::Display for Topic {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -13,10 +61,129 @@ impl std::fmt::Display for Topic {
     }
 }
 
+/// Message published to a [`Topic`]
 #[derive(Clone, Debug)]
 pub enum TopicMessage {
+    /// A trigger without a payload
     Trigger,
     String(String),
+    Number(f64),
+    Bytes(Arc<Vec<u8>>),
+    Value(Value),
+    /// Payload of any type, accessed with [`TopicMessage::downcast_ref()`]
+    Any(TopicPayload),
+}
+
+impl TopicMessage {
+    /// Create a message carrying a payload of any type
+    pub fn any<T: Any + Send + Sync>(payload: T) -> Self {
+        Self::Any(TopicPayload {
+            type_name: std::any::type_name::<T>(),
+            payload: Arc::new(payload),
+        })
+    }
+
+    /// Get the payload of a [`TopicMessage::String`], or a string [`TopicMessage::Value`]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            Self::Value(value) => match value.inner() {
+                ValueData::String(string) => Some(string),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Get the payload of a [`TopicMessage::Number`], or a numeric [`TopicMessage::Value`]
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            Self::Value(value) => match value.inner() {
+                ValueData::Float(float) => Some(*float),
+                ValueData::Integer(integer) => Some(*integer as f64),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Get the payload of a [`TopicMessage::Bytes`], or the bytes of a [`TopicMessage::String`]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Bytes(bytes) => Some(bytes),
+            Self::String(string) => Some(string.as_bytes()),
+            _ => None,
+        }
+    }
+
+    /// Get the payload of a [`TopicMessage::Value`]
+    pub fn as_value(&self) -> Option<&Value> {
+        match self {
+            Self::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Downcast the payload of a [`TopicMessage::Any`] to its concrete type
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match self {
+            Self::Any(payload) => payload.payload.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for TopicMessage {
+    fn from(string: String) -> Self {
+        Self::String(string)
+    }
+}
+
+impl From<&str> for TopicMessage {
+    fn from(string: &str) -> Self {
+        Self::String(string.to_string())
+    }
+}
+
+impl From<f64> for TopicMessage {
+    fn from(number: f64) -> Self {
+        Self::Number(number)
+    }
+}
+
+impl From<Vec<u8>> for TopicMessage {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(Arc::new(bytes))
+    }
+}
+
+impl From<Value> for TopicMessage {
+    fn from(value: Value) -> Self {
+        Self::Value(value)
+    }
+}
+
+/// Type erased payload of a [`TopicMessage::Any`]
+#[derive(Clone)]
+pub struct TopicPayload {
+    type_name: &'static str,
+    payload: Arc<dyn Any + Send + Sync>,
+}
+
+impl TopicPayload {
+    /// Get the type name of the payload
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl std::fmt::Debug for TopicPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TopicPayload")
+            .field(&self.type_name)
+            .finish()
+    }
 }
 
 impl std::fmt::Display for TopicMessage {
@@ -51,9 +218,12 @@ pub enum ModuleMessageData {
     Debug(&'static str),
     Error(Arc<Box<dyn std::error::Error + Send + Sync>>),
     //Event(Box<dyn Any + Send + Sync>),
-    /// Module requesting a subscription to a channel
+    /// Module requesting a subscription to a channel. The topic may contain wildcards, see [`Topic::matches()`]
     Subscribe(Topic),
 
+    /// Module cancelling a subscription previously requested with the same topic
+    Unsubscribe(Topic),
+
     /// Publish a message to a channel
     Publish(PublishMessage),
 
@@ -63,3 +233,51 @@ pub enum ModuleMessageData {
     /// Data updated by module
     Data(Arc<Box<dyn ModuleData>>),
 }
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{Topic, TopicMessage};
+    use crate::parser::Value;
+
+    #[traced_test]
+    #[test]
+    fn topic_wildcards() {
+        let topic = Topic::new("sensors/temperature");
+
+        assert!(Topic::new("sensors/temperature").matches(&topic));
+        assert!(Topic::new("sensors/*").matches(&topic));
+        assert!(Topic::new("*/temperature").matches(&topic));
+        assert!(Topic::new("sensors/**").matches(&topic));
+        assert!(Topic::new("**").matches(&topic));
+
+        assert!(!Topic::new("sensors").matches(&topic));
+        assert!(!Topic::new("sensors/humidity").matches(&topic));
+        assert!(!Topic::new("sensors/*").matches(&Topic::new("sensors/temperature/min")));
+        assert!(!Topic::new("sensors/temperature/*").matches(&topic));
+    }
+
+    #[traced_test]
+    #[test]
+    fn typed_payloads() {
+        assert_eq!(TopicMessage::from("hello").as_str(), Some("hello"));
+        assert_eq!(TopicMessage::from(1.5).as_number(), Some(1.5));
+        assert_eq!(
+            TopicMessage::from(vec![1, 2]).as_bytes(),
+            Some(&[1u8, 2][..])
+        );
+        assert_eq!(TopicMessage::Trigger.as_str(), None);
+
+        let value = TopicMessage::from(Value::new_string("snow".into()));
+        assert_eq!(value.as_str(), Some("snow"));
+        assert!(value.as_value().is_some());
+
+        #[derive(Debug, PartialEq)]
+        struct Reading(u32);
+
+        let any = TopicMessage::any(Reading(42));
+        assert_eq!(any.downcast_ref::<Reading>(), Some(&Reading(42)));
+        assert_eq!(any.downcast_ref::<String>(), None);
+    }
+}
@@ -1,13 +1,19 @@
 //! Widget Messages
 
-use crate::{parser::ElementId, NodeId};
-use iced::widget::scrollable::Viewport;
+use crate::{
+    attribute::breakpoint::Breakpoint, identity::StableId, parser::ElementId, NodeId, Source, Value,
+};
+use iced::{widget::scrollable::Viewport, Task};
+use salish::{endpoint::Endpoint, router::MessageRouter, Message};
+use std::path::PathBuf;
 use url::Url;
 
 #[derive(Clone, Debug)]
 pub struct WidgetMessage {
     pub node_id: NodeId,
     pub element_id: Option<ElementId>,
+    /// Identity of the originating node which is stable across reloads
+    pub stable_id: Option<StableId>,
     pub event: WidgetEvent,
 }
 
@@ -16,9 +22,16 @@ impl WidgetMessage {
         Self {
             node_id,
             element_id,
+            stable_id: None,
             event,
         }
     }
+
+    /// Set the [`StableId`] of the originating node
+    pub fn with_stable_id(mut self, stable_id: Option<StableId>) -> Self {
+        self.stable_id = stable_id;
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -32,13 +45,112 @@ pub enum WidgetEvent {
     /// Toggler toggled
     Toggler(bool),
 
-    /// A pick list was selected
-    PickListSelected(String),
+    /// An option of a pick list was selected, with the value of the option
+    PickListSelected(Value),
+
+    /// An option was added to the selection of a multi-select
+    SelectionAdded(String),
+
+    /// An option was removed from the selection of a multi-select
+    SelectionRemoved(String),
 
     /// Slider value changed
     SliderChanged(i32),
     SliderReleased(i32),
     Scrolled(Viewport),
+
+    /// Video playback started
+    VideoPlay,
+
+    /// Video playback paused
+    VideoPause,
+
+    /// Video playback reached the end of the video
+    VideoEnded,
+
+    /// The width available to a responsive widget crossed a breakpoint
+    Breakpoint(Breakpoint),
+
+    /// The text of an input was edited. Sent by a number input while its text isn't a number.
+    InputChanged(String),
+
+    /// Enter was pressed in an input
+    InputSubmitted(String),
+
+    /// The value of a number input changed, clamped to its bounds
+    NumberChanged(f64),
+
+    /// A drag was started from a `draggable` element
+    DragStarted {
+        source: DragNode,
+    },
+
+    /// A drag was dropped onto a `drop-target` element
+    DroppedOn {
+        source: DragNode,
+        target: DragNode,
+    },
+
+    /// A file from the operating system was dropped onto the window, sent by each `dropzone`
+    FileDropped(PathBuf),
+}
+
+/// Source or target of a drag between widgets
+#[derive(Debug, Clone, PartialEq)]
+pub struct DragNode {
+    pub node_id: NodeId,
+    pub element_id: Option<ElementId>,
+}
+
+/// An application handler of the events of a widget, created with [`crate::Snowcap::on_widget_event()`].
+/// The handler is removed when this is dropped.
+pub struct WidgetEventSubscription {
+    element_id: ElementId,
+    _endpoint: Endpoint<'static, WidgetMessage, Task<Message>, Source>,
+}
+
+impl WidgetEventSubscription {
+    /// Call `f` with each event sent by the widget with an element id, given with or without the leading `#`
+    pub(crate) fn new<F>(
+        router: &MessageRouter<'static, Task<Message>, Source>,
+        element_id: &str,
+        f: F,
+    ) -> Self
+    where
+        F: Fn(WidgetEvent) -> Task<Message> + Send + Sync + 'static,
+    {
+        let element_id: ElementId = element_id.trim_start_matches('#').to_string();
+        let endpoint_element_id = element_id.clone();
+
+        let endpoint =
+            router
+                .create_endpoint::<WidgetMessage>()
+                .message(move |_source, message| {
+                    if message.element_id.as_ref() == Some(&endpoint_element_id) {
+                        f(message.event)
+                    } else {
+                        Task::none()
+                    }
+                });
+
+        Self {
+            element_id,
+            _endpoint: endpoint,
+        }
+    }
+
+    /// Get the element id of the widget handled by this subscription
+    pub fn element_id(&self) -> &str {
+        &self.element_id
+    }
+}
+
+impl std::fmt::Debug for WidgetEventSubscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WidgetEventSubscription")
+            .field("element_id", &self.element_id)
+            .finish()
+    }
 }
 
 /*
@@ -83,3 +195,58 @@ impl<'a> AsRef<WidgetMessage> for &'a mut Message {
     }
 }
 */
+
+#[cfg(all(test, not(target_arch = "wasm32"), feature = "headless"))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use iced::Task;
+    use tracing_test::traced_test;
+
+    use super::WidgetEvent;
+    use crate::testing::Harness;
+
+    #[traced_test]
+    #[test]
+    fn widget_event_handlers() {
+        let mut harness = Harness::new(
+            r#"{|[button#save<on-press:publish("nav", "saved")>(text("Save")), button#quit(text("Quit"))]}"#,
+        )
+        .unwrap();
+
+        let presses = Arc::new(AtomicUsize::new(0));
+        let published = Arc::new(AtomicUsize::new(0));
+
+        let handler_presses = presses.clone();
+        let save = harness
+            .headless()
+            .engine()
+            .on_widget_event("#save", move |event| {
+                if let WidgetEvent::ButtonPress = event {
+                    handler_presses.fetch_add(1, Ordering::Relaxed);
+                }
+                Task::none()
+            });
+        assert_eq!(save.element_id(), "save");
+
+        let handler_published = published.clone();
+        let _nav = harness.headless().engine().on_topic("nav", move |message| {
+            assert_eq!(message.as_str(), Some("saved"));
+            handler_published.fetch_add(1, Ordering::Relaxed);
+            Task::none()
+        });
+
+        harness.click("#save").unwrap().click("#quit").unwrap();
+        assert_eq!(presses.load(Ordering::Relaxed), 1);
+        assert_eq!(published.load(Ordering::Relaxed), 1);
+
+        // Dropping the subscription removes the handler
+        drop(save);
+        harness.click("#save").unwrap();
+        assert_eq!(presses.load(Ordering::Relaxed), 1);
+        assert_eq!(published.load(Ordering::Relaxed), 2);
+    }
+}
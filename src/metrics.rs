@@ -0,0 +1,380 @@
+//! Engine metrics
+//!
+//! When enabled with [`enable()`], the engine records counters and histograms of its activity:
+//!
+//! | Metric                             | Kind      | Label    |                                               |
+//! |------------------------------------|-----------|----------|-----------------------------------------------|
+//! | `snowcap_updates_total`            | counter   |          | Messages handled by [`crate::Snowcap::update()`] |
+//! | `snowcap_messages_total`           | counter   | `type`   | Widget, command and module data messages routed |
+//! | `snowcap_widgets_rebuilt`          | histogram |          | Widgets rebuilt by each update which built any |
+//! | `snowcap_module_init_seconds`      | histogram | `module` | Time for a module instance to initialize      |
+//! | `snowcap_module_request_seconds`   | histogram | `module` | Time for a module event task to produce output, such as an HTTP request |
+//!
+//! Metrics are process wide, shared by every engine, and are read with [`snapshot()`] or [`crate::Snowcap::metrics()`].
+//! With the `prometheus` feature, [`MetricsSnapshot::to_prometheus()`] encodes a snapshot in the Prometheus text
+//! exposition format, to be served from a scrape endpoint of the host application.
+
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+
+/// Messages handled by [`crate::Snowcap::update()`]
+pub const UPDATES: &str = "snowcap_updates_total";
+
+/// Messages routed to the engine, labelled by `type`
+pub const MESSAGES: &str = "snowcap_messages_total";
+
+/// Widgets rebuilt by each update
+pub const WIDGETS_REBUILT: &str = "snowcap_widgets_rebuilt";
+
+/// Module instance initialization time, labelled by `module`
+pub const MODULE_INIT: &str = "snowcap_module_init_seconds";
+
+/// Module event task time to first output, labelled by `module`
+pub const MODULE_REQUEST: &str = "snowcap_module_request_seconds";
+
+/// Bucket upper bounds of latency histograms, in seconds
+const LATENCY_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0];
+
+/// Bucket upper bounds of count histograms
+const COUNT_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+
+/// Name of a metric, and its label if it has one
+type MetricKey = (&'static str, Option<(&'static str, String)>);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static REGISTRY: Mutex<Registry> = parking_lot::const_mutex(Registry {
+    counters: BTreeMap::new(),
+    histograms: BTreeMap::new(),
+});
+
+struct Registry {
+    counters: BTreeMap<MetricKey, u64>,
+    histograms: BTreeMap<MetricKey, Histogram>,
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Start or stop recording metrics. Recorded values are kept when stopped.
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if metrics are being recorded
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Clear all recorded metrics
+pub fn reset() {
+    let mut registry = REGISTRY.lock();
+    registry.counters.clear();
+    registry.histograms.clear();
+}
+
+/// Increment a counter, with an optional label
+pub(crate) fn increment(name: &'static str, label: Option<(&'static str, &str)>) {
+    if !is_enabled() {
+        return;
+    }
+
+    let key = (name, label.map(|(key, value)| (key, value.to_string())));
+    *REGISTRY.lock().counters.entry(key).or_default() += 1;
+}
+
+/// Record the number of items in a count histogram
+pub(crate) fn observe_count(name: &'static str, count: usize) {
+    observe(name, None, COUNT_BUCKETS, count as f64);
+}
+
+/// Record a duration in a latency histogram, with an optional label
+pub(crate) fn observe_latency(
+    name: &'static str,
+    label: Option<(&'static str, &str)>,
+    duration: Duration,
+) {
+    observe(name, label, LATENCY_BUCKETS, duration.as_secs_f64());
+}
+
+fn observe(
+    name: &'static str,
+    label: Option<(&'static str, &str)>,
+    bounds: &'static [f64],
+    value: f64,
+) {
+    if !is_enabled() {
+        return;
+    }
+
+    let key = (name, label.map(|(key, value)| (key, value.to_string())));
+    REGISTRY
+        .lock()
+        .histograms
+        .entry(key)
+        .or_insert_with(|| Histogram::new(bounds))
+        .observe(value);
+}
+
+/// Value of a counter
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterSample {
+    pub name: &'static str,
+    pub label: Option<(&'static str, String)>,
+    pub value: u64,
+}
+
+/// Distribution of a histogram. Bucket counts are cumulative, as in Prometheus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSample {
+    pub name: &'static str,
+    pub label: Option<(&'static str, String)>,
+    /// Upper bound of each bucket, and the number of values less than or equal to it
+    pub buckets: Vec<(f64, u64)>,
+    pub sum: f64,
+    pub count: u64,
+}
+
+/// Recorded metrics at a point in time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    pub counters: Vec<CounterSample>,
+    pub histograms: Vec<HistogramSample>,
+}
+
+impl MetricsSnapshot {
+    /// Get the value of a counter, or zero if it hasn't been incremented
+    pub fn counter(&self, name: &str, label: Option<&str>) -> u64 {
+        self.counters
+            .iter()
+            .find(|sample| {
+                sample.name == name
+                    && sample.label.as_ref().map(|(_, value)| value.as_str()) == label
+            })
+            .map(|sample| sample.value)
+            .unwrap_or_default()
+    }
+
+    /// Get a histogram, or None if no values have been recorded
+    pub fn histogram(&self, name: &str, label: Option<&str>) -> Option<&HistogramSample> {
+        self.histograms.iter().find(|sample| {
+            sample.name == name && sample.label.as_ref().map(|(_, value)| value.as_str()) == label
+        })
+    }
+
+    /// Encode the snapshot in the Prometheus text exposition format
+    #[cfg(feature = "prometheus")]
+    pub fn to_prometheus(&self) -> String {
+        use std::fmt::Write as _;
+
+        fn labels(label: &Option<(&'static str, String)>, le: Option<String>) -> String {
+            let mut pairs: Vec<String> = label
+                .iter()
+                .map(|(key, value)| format!("{key}=\"{}\"", value.replace('"', "\\\"")))
+                .collect();
+            pairs.extend(le.map(|le| format!("le=\"{le}\"")));
+
+            if pairs.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", pairs.join(","))
+            }
+        }
+
+        let mut text = String::new();
+        let mut declared = None;
+
+        for sample in &self.counters {
+            if declared != Some(sample.name) {
+                let _ = writeln!(text, "# TYPE {} counter", sample.name);
+                declared = Some(sample.name);
+            }
+            let _ = writeln!(
+                text,
+                "{}{} {}",
+                sample.name,
+                labels(&sample.label, None),
+                sample.value
+            );
+        }
+
+        for sample in &self.histograms {
+            if declared != Some(sample.name) {
+                let _ = writeln!(text, "# TYPE {} histogram", sample.name);
+                declared = Some(sample.name);
+            }
+            for (bound, count) in &sample.buckets {
+                let _ = writeln!(
+                    text,
+                    "{}_bucket{} {count}",
+                    sample.name,
+                    labels(&sample.label, Some(bound.to_string()))
+                );
+            }
+            let _ = writeln!(
+                text,
+                "{}_bucket{} {}",
+                sample.name,
+                labels(&sample.label, Some("+Inf".into())),
+                sample.count
+            );
+            let label = labels(&sample.label, None);
+            let _ = writeln!(text, "{}_sum{label} {}", sample.name, sample.sum);
+            let _ = writeln!(text, "{}_count{label} {}", sample.name, sample.count);
+        }
+
+        text
+    }
+}
+
+/// Take a snapshot of the recorded metrics
+pub fn snapshot() -> MetricsSnapshot {
+    let registry = REGISTRY.lock();
+
+    MetricsSnapshot {
+        counters: registry
+            .counters
+            .iter()
+            .map(|((name, label), value)| CounterSample {
+                name,
+                label: label.clone(),
+                value: *value,
+            })
+            .collect(),
+        histograms: registry
+            .histograms
+            .iter()
+            .map(|((name, label), histogram)| HistogramSample {
+                name,
+                label: label.clone(),
+                buckets: histogram
+                    .bounds
+                    .iter()
+                    .copied()
+                    .zip(histogram.counts.iter().copied())
+                    .collect(),
+                sum: histogram.sum,
+                count: histogram.count,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_test::traced_test;
+
+    use super::{
+        enable, increment, observe_count, observe_latency, snapshot, MESSAGES, MODULE_INIT,
+        WIDGETS_REBUILT,
+    };
+
+    #[traced_test]
+    #[test]
+    fn record_metrics() {
+        enable(true);
+
+        // Metrics are process wide, so only increases are asserted
+        let before = snapshot();
+        increment(MESSAGES, Some(("type", "test")));
+        increment(MESSAGES, Some(("type", "test")));
+        observe_count(WIDGETS_REBUILT, 3);
+        observe_latency(
+            MODULE_INIT,
+            Some(("module", "test")),
+            Duration::from_millis(20),
+        );
+
+        let after = snapshot();
+        assert_eq!(
+            after.counter(MESSAGES, Some("test")) - before.counter(MESSAGES, Some("test")),
+            2
+        );
+
+        let rebuilt = after.histogram(WIDGETS_REBUILT, None).unwrap();
+        assert!(rebuilt.count >= 1);
+        // Buckets are cumulative
+        assert!(rebuilt
+            .buckets
+            .windows(2)
+            .all(|buckets| buckets[0].1 <= buckets[1].1));
+
+        let init = after.histogram(MODULE_INIT, Some("test")).unwrap();
+        assert!(init
+            .buckets
+            .iter()
+            .any(|(bound, count)| *bound == 0.05 && *count >= 1));
+        assert!(init
+            .buckets
+            .iter()
+            .any(|(bound, count)| *bound == 0.01 && *count == 0));
+
+        #[cfg(feature = "prometheus")]
+        {
+            let text = after.to_prometheus();
+            assert!(text.contains("# TYPE snowcap_messages_total counter"));
+            assert!(text.contains("snowcap_messages_total{type=\"test\"}"));
+            assert!(
+                text.contains("snowcap_module_init_seconds_bucket{module=\"test\",le=\"+Inf\"}")
+            );
+        }
+    }
+
+    #[cfg(all(not(target_arch = "wasm32"), feature = "headless"))]
+    #[traced_test]
+    #[test]
+    fn engine_metrics() {
+        use crate::headless::Headless;
+
+        enable(true);
+        let before = snapshot();
+
+        let mut headless = Headless::new().unwrap();
+        headless
+            .load(r#"{|[text("Hello"), text(timing!{mode:"elapsed"})]}"#)
+            .unwrap()
+            .run_for(Duration::from_secs(2));
+
+        let after = headless.engine().metrics();
+        assert!(after.counter(super::UPDATES, None) > before.counter(super::UPDATES, None));
+        assert!(
+            after.counter(MESSAGES, Some("module_data"))
+                > before.counter(MESSAGES, Some("module_data"))
+        );
+        assert!(after.histogram(WIDGETS_REBUILT, None).unwrap().sum >= 3.0);
+        assert!(after.histogram(MODULE_INIT, Some("timing")).is_some());
+    }
+}
@@ -1,14 +1,25 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    time::Duration,
+};
 
 use tracing::warn;
+use url::Url;
 
 use crate::{
-    parser::{value::ValueParser, ParserContext},
+    parser::{
+        value::{ValueData, ValueParser},
+        ParserContext,
+    },
     Value,
 };
 
 use super::error::ModuleError;
 
+/// Arguments accepted by every module, which select and shape the data of the consuming node
+pub const COMMON_ARGUMENTS: &[&str] = &["id", "field", "expr", "transform", "format", "on-error"];
+
 /// A set of arguments for Modules parsed from the grammar
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct ModuleArguments {
@@ -61,10 +72,136 @@ impl ModuleArguments {
             .ok_or(ModuleError::MissingArgument(name.to_string()))
     }
 
+    /// Get a string argument
+    pub fn get_string(&self, name: &str) -> Result<String, ModuleError> {
+        let value = self.get(name)?;
+        match value.inner() {
+            ValueData::String(string) => Ok(string.clone()),
+            _ => Err(invalid(name, "a string", value)),
+        }
+    }
+
+    /// Get an integer argument
+    pub fn get_integer(&self, name: &str) -> Result<u64, ModuleError> {
+        let value = self.get(name)?;
+        value
+            .integer()
+            .map_err(|_| invalid(name, "an integer", value))
+    }
+
+    /// Get a URL argument, given as a URL literal or a string
+    pub fn get_url(&self, name: &str) -> Result<Url, ModuleError> {
+        let value = self.get(name)?;
+        match value.inner() {
+            ValueData::Url(url) => Ok(url.clone()),
+            ValueData::String(url) => Url::parse(url).map_err(|_| invalid(name, "a URL", value)),
+            _ => Err(invalid(name, "a URL", value)),
+        }
+    }
+
+    /// Get a duration argument, given as a duration literal such as `500ms`, a string such as `"1m 30s"`, or as a
+    /// number of seconds
+    pub fn get_duration(&self, name: &str) -> Result<Duration, ModuleError> {
+        let value = self.get(name)?;
+        match value.inner() {
+            ValueData::Duration(duration) => Ok(*duration),
+            ValueData::String(duration) => {
+                duration_str::parse(duration).map_err(|_| invalid(name, "a duration", value))
+            }
+            ValueData::Integer(seconds) => Ok(Duration::from_secs(*seconds)),
+            ValueData::Float(seconds) => Duration::try_from_secs_f64(*seconds)
+                .map_err(|_| invalid(name, "a duration", value)),
+            _ => Err(invalid(name, "a duration", value)),
+        }
+    }
+
+    /// Get a map argument, such as `headers: {Accept: "application/json"}`
+    pub fn get_map(&self, name: &str) -> Result<&BTreeMap<String, Value>, ModuleError> {
+        let value = self.get(name)?;
+        value.map().map_err(|_| invalid(name, "a map", value))
+    }
+
+    /// Get a boolean argument, or the default if it isn't given
+    pub fn get_bool_or(&self, name: &str, default: bool) -> Result<bool, ModuleError> {
+        match self.arguments.get(name) {
+            Some(value) => value
+                .boolean()
+                .map_err(|_| invalid(name, "a boolean", value)),
+            None => Ok(default),
+        }
+    }
+
+    /// Get an argument naming a variant of an enum, such as `mode:"countdown"`
+    pub fn get_enum<T>(&self, name: &str) -> Result<T, ModuleError>
+    where
+        T: FromStr + strum::VariantNames,
+    {
+        let value = self.get(name)?;
+        let expected = format!("one of {}", T::VARIANTS.join(", "));
+        self.get_string(name)?
+            .parse::<T>()
+            .map_err(|_| invalid(name, &expected, value))
+    }
+
     /// Get the number of Arguments
     pub fn len(&self) -> usize {
         self.arguments.len()
     }
+
+    /// Check that each argument is one of the `known` arguments of a module or a [`COMMON_ARGUMENTS`], and
+    /// that the `required` arguments are given. Unknown arguments are reported with the closest known name.
+    pub fn check(&self, known: &[&str], required: &[&str]) -> Result<(), ModuleError> {
+        for arg in self.sort() {
+            let name = arg.name().as_str();
+
+            // Arguments starting with an underscore are generated by the parser
+            if name.starts_with('_') || known.contains(&name) || COMMON_ARGUMENTS.contains(&name) {
+                continue;
+            }
+
+            let suggestion = known
+                .iter()
+                .chain(COMMON_ARGUMENTS)
+                .map(|known| (edit_distance(name, known), known))
+                .filter(|(distance, _)| *distance <= 2)
+                .min_by_key(|(distance, _)| *distance);
+
+            return Err(ModuleError::InvalidArgument(match suggestion {
+                Some((_, known)) => format!("unknown argument '{name}', did you mean '{known}'?"),
+                None => format!("unknown argument '{name}'"),
+            }));
+        }
+
+        for name in required {
+            self.get(name)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Error of an argument whose value doesn't have the expected type
+fn invalid(name: &str, expected: &str, value: &Value) -> ModuleError {
+    ModuleError::InvalidArgument(format!("'{name}' expects {expected}, got '{value}'"))
+}
+
+/// Number of single character insertions, deletions and substitutions to change `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
 }
 
 impl std::fmt::Display for ModuleArguments {
@@ -135,3 +272,76 @@ impl std::fmt::Display for ModuleArgument {
         write!(f, "{}: {}", self.name, self.value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_test::traced_test;
+
+    use super::ModuleArguments;
+    use crate::module::{error::ModuleError, timing::output::TimingMode};
+
+    #[traced_test]
+    #[test]
+    fn typed_getters() {
+        let args = ModuleArguments::new()
+            .arg("url", r#""http://example.com/data""#)
+            .arg("interval", r#""1m 30s""#)
+            .arg("timeout", "5")
+            .arg("once", "true")
+            .arg("mode", r#""countdown""#)
+            .arg("count", "3");
+
+        assert_eq!(args.get_string("url").unwrap(), "http://example.com/data");
+        assert_eq!(args.get_url("url").unwrap().path(), "/data");
+        assert_eq!(
+            args.get_duration("interval").unwrap(),
+            Duration::from_secs(90)
+        );
+        assert_eq!(
+            args.get_duration("timeout").unwrap(),
+            Duration::from_secs(5)
+        );
+        assert!(args.get_bool_or("once", false).unwrap());
+        assert!(args.get_bool_or("missing", true).unwrap());
+        assert_eq!(
+            args.get_enum::<TimingMode>("mode").unwrap(),
+            TimingMode::Countdown
+        );
+
+        // Errors name the argument and the expected type
+        let error = args.get_string("count").unwrap_err().to_string();
+        assert!(error.contains("'count' expects a string"));
+        let error = args.get_url("mode").unwrap_err().to_string();
+        assert!(error.contains("'mode' expects a URL"));
+        let error = args.get_bool_or("url", false).unwrap_err().to_string();
+        assert!(error.contains("'url' expects a boolean"));
+
+        let args = ModuleArguments::new().arg("mode", r#""sideways""#);
+        let error = args.get_enum::<TimingMode>("mode").unwrap_err().to_string();
+        assert!(error.contains("one of clock, elapsed, countdown"));
+
+        assert!(matches!(
+            args.get_duration("missing"),
+            Err(ModuleError::MissingArgument(_))
+        ));
+
+        // Duration and URL literals are typed when they're parsed
+        let args = ModuleArguments::new()
+            .arg("url", "https://example.com/feed")
+            .arg("interval", "1m30s")
+            .arg("headers", r#"{Accept: "application/json"}"#);
+        assert_eq!(args.get_url("url").unwrap().path(), "/feed");
+        assert_eq!(
+            args.get_map("headers").unwrap()["Accept"].to_string(),
+            "application/json"
+        );
+        assert!(args.get_map("interval").is_err());
+        assert_eq!(
+            args.get_duration("interval").unwrap(),
+            Duration::from_secs(90)
+        );
+        assert!(args.get_string("url").is_err());
+    }
+}
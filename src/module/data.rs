@@ -3,6 +3,10 @@
 //! Data objects created by modules are exposed into the core engine using the ModuleData trait.
 //! When a widget wants to get content data from a module, it will call into the [`ModuleData`] impl
 
+use std::sync::Arc;
+
+use file_format::FileFormat;
+
 use super::error::ModuleError;
 
 #[derive(Copy, Clone, Debug)]
@@ -11,9 +15,120 @@ pub enum ModuleDataKind {
     Image,
     Svg,
     Text,
+    /// Encoded video, played by the video widget
+    Video,
+    /// Binary data of no particular format, passed to consumers as is. Widgets identify it from its contents.
+    Bytes,
+    /// A module failed. The bytes are a UTF-8 error message
+    Error,
 }
 
 pub trait ModuleData: std::fmt::Debug + Send + Sync {
     fn kind(&self) -> ModuleDataKind;
     fn bytes(&self) -> Result<&Vec<u8>, ModuleError>;
+
+    /// Select a named field from this data. Modules producing structured data can implement this
+    /// so a single instance can feed several nodes, each selecting a field with the `field` module argument.
+    fn field(&self, _name: &str) -> Option<Box<dyn ModuleData>> {
+        None
+    }
+
+    /// Get the bytes without copying them, for large data such as video which widgets keep while they are shown.
+    /// Data which doesn't keep its bytes in an [`Arc`] returns `None`, and its bytes are copied.
+    fn shared_bytes(&self) -> Option<Arc<Vec<u8>>> {
+        None
+    }
+}
+
+/// Shared module data, used when one module instance feeds multiple nodes
+impl ModuleData for Arc<dyn ModuleData> {
+    fn kind(&self) -> ModuleDataKind {
+        (**self).kind()
+    }
+
+    fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
+        (**self).bytes()
+    }
+
+    fn field(&self, name: &str) -> Option<Box<dyn ModuleData>> {
+        (**self).field(name)
+    }
+
+    fn shared_bytes(&self) -> Option<Arc<Vec<u8>>> {
+        (**self).shared_bytes()
+    }
+}
+
+/// Plain text [`ModuleData`], useful for returning fields selected from structured data
+#[derive(Debug, Clone)]
+pub struct TextData {
+    bytes: Vec<u8>,
+}
+
+impl TextData {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            bytes: text.into().into_bytes(),
+        }
+    }
+}
+
+impl ModuleData for TextData {
+    fn kind(&self) -> ModuleDataKind {
+        ModuleDataKind::Text
+    }
+
+    fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
+        Ok(&self.bytes)
+    }
+}
+
+/// [`ModuleData`] of an asset inlined in the markup, with its kind identified from its contents
+#[derive(Debug, Clone)]
+pub struct EmbeddedData {
+    kind: ModuleDataKind,
+    bytes: Vec<u8>,
+}
+
+impl EmbeddedData {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            kind: FileFormat::from_bytes(&bytes).into(),
+            bytes,
+        }
+    }
+}
+
+impl ModuleData for EmbeddedData {
+    fn kind(&self) -> ModuleDataKind {
+        self.kind
+    }
+
+    fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
+        Ok(&self.bytes)
+    }
+}
+
+/// Error [`ModuleData`] sent when a module fails, rendered as fallback content by consuming nodes
+#[derive(Debug, Clone)]
+pub struct ErrorData {
+    bytes: Vec<u8>,
+}
+
+impl ErrorData {
+    pub fn new(error: impl std::fmt::Display) -> Self {
+        Self {
+            bytes: error.to_string().into_bytes(),
+        }
+    }
+}
+
+impl ModuleData for ErrorData {
+    fn kind(&self) -> ModuleDataKind {
+        ModuleDataKind::Error
+    }
+
+    fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
+        Ok(&self.bytes)
+    }
 }
@@ -1,11 +1,26 @@
-use std::any::Any;
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
-use iced::Task;
+use iced::{task::Handle, Task};
+use parking_lot::Mutex;
 use salish::{filter::SourceFilter, EndpointAddress as _, Message};
+use tracing::debug;
 
-use crate::{module::argument::ModuleArguments, Source};
+use crate::{
+    message::module::ModuleMessageData, metrics, module::argument::ModuleArguments, Source,
+};
 
-use super::{data::ModuleData, event::ModuleEvent, ModuleHandle, ModuleHandleId};
+use super::{
+    data::ModuleData, event::ModuleEvent, pubsub::Subscriptions, ModuleHandle, ModuleHandleId,
+    ModuleInitData,
+};
 
 /// Module event dispatcher which provides type erasure of the concrete [`ModuleEvent`] type.
 ///
@@ -16,16 +31,64 @@ pub struct ModuleDispatch {
     handle_id: ModuleHandleId,
 
     /// Start the module
-    start: Box<dyn for<'b> FnMut(&'b ModuleArguments) -> Task<Message> + Send + Sync>,
+    start:
+        Box<dyn for<'b> FnMut(&'b ModuleArguments, ModuleInitData) -> Task<Message> + Send + Sync>,
+
+    /// Notify the module of shutdown
+    shutdown: ShutdownFn,
+
+    /// Notify the module of a change in visibility
+    visibility: Box<dyn Fn(bool) -> Task<Message> + Send + Sync>,
+
+    /// Tasks returned by the module which are still running
+    tasks: TaskTracker,
 
     /// Vec which holds endpoints created for this module to keep them alive. Once this Vec
     /// is dropped, all of the endpoints will be deregistered from the [`MessageRouter`]
     _endpoints: Vec<Box<dyn Any + Send>>,
 }
 
+/// Type erased closure calling [`super::Module::on_shutdown()`] of a module instance
+pub type ShutdownFn = Arc<dyn Fn() -> Task<Message> + Send + Sync>;
+
+/// Tracks the running tasks of a module instance, so they can be aborted when the instance is torn down
+#[derive(Clone, Default)]
+struct TaskTracker {
+    next: Arc<AtomicU64>,
+    handles: Arc<Mutex<HashMap<u64, Handle>>>,
+}
+
+impl TaskTracker {
+    /// Make a [`Task`] abortable, and track its [`Handle`] until the task completes
+    fn track(&self, task: Task<Message>) -> Task<Message> {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let (task, handle) = task.abortable();
+        self.handles.lock().insert(id, handle);
+
+        // Forget the handle once the task has completed or was aborted
+        let handles = self.handles.clone();
+        task.chain(
+            Task::future(async move {
+                handles.lock().remove(&id);
+            })
+            .discard(),
+        )
+    }
+
+    /// Abort all running tasks
+    fn abort_all(&self) {
+        for (_, handle) in self.handles.lock().drain() {
+            handle.abort();
+        }
+    }
+}
+
 impl Drop for ModuleDispatch {
     fn drop(&mut self) {
-        println!("DISPATCHER DROPPED {}", self.handle_id);
+        debug!("Dispatcher of module {} dropped", self.handle_id);
+
+        // Cancel any timers, streams and requests the module still has running
+        self.tasks.abort_all();
     }
 }
 
@@ -43,8 +106,23 @@ impl ModuleDispatch {
         handle: ModuleHandle<'static, E, D>,
     ) -> Self {
         let start_handle = handle.clone();
+        let published_handle = handle.clone();
+        let visibility_handle = handle.clone();
+        let shutdown_handle = handle.clone();
         let handle_id = handle.id();
 
+        // Set once the module has been notified of shutdown, to stop dispatching new events
+        let stopping = Arc::new(AtomicBool::new(false));
+        let event_stopping = stopping.clone();
+        let published_stopping = stopping.clone();
+        let visibility_stopping = stopping.clone();
+
+        let tasks = TaskTracker::default();
+        let event_tasks = tasks.clone();
+        let published_tasks = tasks.clone();
+        let visibility_tasks = tasks.clone();
+        let start_tasks = tasks.clone();
+
         let router = handle.router().unwrap();
 
         // Create an event endpoint that calls [`Module::on_event()`] for each event received by the endpoint
@@ -53,31 +131,124 @@ impl ModuleDispatch {
             .create_endpoint::<E>()
             .filter(SourceFilter::default().add(Source::Module(handle_id)))
             .message(move |_source, event| {
+                if event_stopping.load(Ordering::Acquire) {
+                    return Task::none();
+                }
+
+                // Record the time until the event task produces its first output, such as an HTTP response
+                let start = Instant::now();
+                let observed = AtomicBool::new(false);
+                let name = handle.name().clone();
+
                 let mut module = handle.try_module_mut().unwrap();
-                module
-                    .on_event(event)
-                    .map(move |m| m.with_source(Source::Module(handle_id)))
+                event_tasks.track(module.on_event(event).map(move |m| {
+                    if !observed.swap(true, Ordering::Relaxed) {
+                        metrics::observe_latency(
+                            metrics::MODULE_REQUEST,
+                            Some(("module", &name)),
+                            start.elapsed(),
+                        );
+                    }
+                    m.with_source(Source::Module(handle_id))
+                }))
             });
 
         // Get the event endpoint address to pass into [`ModuleInit::start()`].
         // This address routes events back into the [`Module::on_event()`] method
         let event_addr = event_endpoint.addr();
 
+        // Topics this module instance has subscribed to
+        let subscriptions = Subscriptions::default();
+        let published_subscriptions = subscriptions.clone();
+
+        // Create an endpoint receiving subscription requests from this module instance
+        let subscribe_endpoint = router
+            .create_endpoint::<ModuleMessageData>()
+            .filter(SourceFilter::default().add(Source::Module(handle_id)))
+            .message(move |_source, message| {
+                match message {
+                    ModuleMessageData::Subscribe(topic) => {
+                        debug!("Module {handle_id} subscribed to {topic}");
+                        subscriptions.subscribe(topic)
+                    }
+                    ModuleMessageData::Unsubscribe(topic) => {
+                        debug!("Module {handle_id} unsubscribed from {topic}");
+                        subscriptions.unsubscribe(&topic)
+                    }
+                    _ => {}
+                }
+                Task::none()
+            });
+
+        // Create an endpoint receiving all published messages, which calls [`Module::on_subscription()`]
+        // for messages published to topics matching the subscriptions of this module instance
+        let published_endpoint =
+            router
+                .create_endpoint::<ModuleMessageData>()
+                .message(move |_source, message| match message {
+                    ModuleMessageData::Publish(publish)
+                        if published_subscriptions.matches(&publish.topic) =>
+                    {
+                        if published_stopping.load(Ordering::Acquire) {
+                            return Task::none();
+                        }
+
+                        let mut module = published_handle.try_module_mut().unwrap();
+                        published_tasks.track(
+                            module
+                                .on_subscription(publish.topic, publish.message)
+                                .map(move |m| m.with_source(Source::Module(handle_id))),
+                        )
+                    }
+                    _ => Task::none(),
+                });
+
         // Keep the endpoints alive in a vec of boxed dyn Any
-        let endpoints: Vec<Box<dyn Any + Send>> = vec![Box::new(event_endpoint)];
+        let endpoints: Vec<Box<dyn Any + Send>> = vec![
+            Box::new(event_endpoint),
+            Box::new(subscribe_endpoint),
+            Box::new(published_endpoint),
+        ];
 
         // Create a `start` closure to proxy to [`ModuleInternal::start()`]
-        let start = Box::new(move |args: &ModuleArguments| {
+        let start = Box::new(move |args: &ModuleArguments, init_data: ModuleInitData| {
             let mut module = start_handle.try_module_mut().unwrap();
-            let task = module.start(start_handle.clone(), args.clone(), event_addr);
+            let task = module.start(start_handle.clone(), args.clone(), init_data, event_addr);
 
             // Return the init Task of this module
-            task
+            start_tasks.track(task)
+        });
+
+        // Create a `visibility` closure to proxy to [`Module::on_visibility()`]
+        let visibility = Box::new(move |visible: bool| {
+            if visibility_stopping.load(Ordering::Acquire) {
+                return Task::none();
+            }
+
+            let mut module = visibility_handle.try_module_mut().unwrap();
+            visibility_tasks.track(
+                module
+                    .on_visibility(visible)
+                    .map(move |m| m.with_source(Source::Module(handle_id))),
+            )
+        });
+
+        // Create a `shutdown` closure to proxy to [`Module::on_shutdown()`]
+        let shutdown: ShutdownFn = Arc::new(move || {
+            stopping.store(true, Ordering::Release);
+
+            let mut module = shutdown_handle.try_module_mut().unwrap();
+            module
+                .on_shutdown()
+                .map(move |m| m.with_source(Source::Module(handle_id)))
         });
 
         Self {
             handle_id,
             start,
+            shutdown,
+            visibility,
+            tasks,
             _endpoints: endpoints,
         }
     }
@@ -89,8 +260,19 @@ impl ModuleDispatch {
 
     /// Starts the module, calling [`crate::module::internal::ModuleInternal::start()`]
     /// which returns an [`iced::Task`] which calls into the async fn [`super::Module::init()`]
-    /// implemented by the module.
-    pub fn start(&mut self, args: &ModuleArguments) -> Task<Message> {
-        (self.start)(args)
+    /// implemented by the module, with the [`ModuleInitData`] of the engine.
+    pub fn start(&mut self, args: &ModuleArguments, init_data: ModuleInitData) -> Task<Message> {
+        (self.start)(args, init_data)
+    }
+
+    /// Notify the module of a change in the visibility of its nodes, calling [`super::Module::on_visibility()`]
+    pub fn set_visible(&self, visible: bool) -> Task<Message> {
+        (self.visibility)(visible)
+    }
+
+    /// Get the shutdown closure of this module instance. Calling it stops dispatching events
+    /// to the module, and returns the [`iced::Task`] from [`super::Module::on_shutdown()`].
+    pub fn shutdown_fn(&self) -> ShutdownFn {
+        self.shutdown.clone()
     }
 }
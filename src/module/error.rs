@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::parser::expr::ExprError;
+
 use super::ModuleHandleId;
 
 #[derive(Error, Debug)]
@@ -16,12 +18,21 @@ pub enum ModuleError {
     #[error("unknown module {0}")]
     Unknown(String),
 
+    #[error("module name '{name}' is already registered to {existing}")]
+    NameCollision { name: String, existing: String },
+
     #[error("missing required argument {0}")]
     MissingArgument(String),
 
     #[error("invalid argument {0}")]
     InvalidArgument(String),
 
+    #[error("denied by module policy: {0}")]
+    Denied(String),
+
+    #[error("expression error {0}")]
+    Expression(#[from] ExprError),
+
     #[error("io error {0}")]
     Io(#[from] std::io::Error),
 
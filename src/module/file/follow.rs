@@ -0,0 +1,219 @@
+//! Following files which are appended to, such as logs
+//!
+//! A file is polled every [`FOLLOW_INTERVAL`] for data appended since the last poll. The last lines of the file are
+//! kept, and the module sends them as text data each time lines are appended. A file which shrinks, such as a log
+//! which was truncated or rotated, is read again from the start.
+
+use std::{
+    collections::VecDeque,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use iced::{futures::SinkExt as _, Task};
+use salish::Message;
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt as _, AsyncSeekExt as _},
+};
+
+use super::FileEvent;
+
+/// Interval between polls of a followed file
+pub const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of lines kept if the `lines` argument isn't given
+pub const DEFAULT_LINES: usize = 100;
+
+/// Maximum number of lines kept, given with the `lines` argument
+pub const MAX_LINES: usize = 10_000;
+
+/// Maximum number of bytes read from the end of a file when following starts, and read from the data appended
+/// between polls. Data appended faster is skipped, up to the last lines.
+const MAX_TAIL_BYTES: u64 = 1024 * 1024;
+
+/// Last lines of a followed file
+#[derive(Debug)]
+pub(super) struct Tail {
+    lines: VecDeque<String>,
+    max_lines: usize,
+
+    /// Data following the last newline, which is completed by the next append
+    partial: Vec<u8>,
+}
+
+impl Tail {
+    pub fn new(max_lines: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            max_lines,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Append data read from the file, returning true if any lines were completed
+    pub fn append(&mut self, data: &[u8]) -> bool {
+        self.partial.extend_from_slice(data);
+
+        let Some(end) = self.partial.iter().rposition(|b| *b == b'\n') else {
+            return false;
+        };
+
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+
+        for line in complete[..end].split(|b| *b == b'\n') {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            self.lines
+                .push_back(String::from_utf8_lossy(line).into_owned());
+        }
+
+        while self.lines.len() > self.max_lines {
+            self.lines.pop_front();
+        }
+
+        true
+    }
+
+    /// Discard the kept lines, when the file was truncated
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.partial.clear();
+    }
+
+    /// Get the kept lines separated by newlines
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Data read from a followed file
+struct ReadData {
+    data: Vec<u8>,
+
+    /// The file shrank, and was read again from the start
+    truncated: bool,
+
+    /// Data before the start of the read data was skipped, so its first line is incomplete
+    skipped: bool,
+}
+
+/// Read the data of a file after an offset, and advance the offset past it. The offset is reset if the file shrank.
+/// At most [`MAX_TAIL_BYTES`] are read from the end of the file.
+async fn read_from(path: &Path, offset: &mut u64) -> std::io::Result<ReadData> {
+    let mut file = File::open(path).await?;
+    let len = file.metadata().await?.len();
+
+    let truncated = len < *offset;
+    if truncated {
+        *offset = 0;
+    }
+
+    let start = (*offset).max(len.saturating_sub(MAX_TAIL_BYTES));
+    let skipped = start > *offset;
+
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut data = Vec::new();
+    file.take(len - start).read_to_end(&mut data).await?;
+    *offset = start + data.len() as u64;
+
+    Ok(ReadData {
+        data,
+        truncated,
+        skipped,
+    })
+}
+
+/// Follow a file, sending [`FileEvent::Appended`] with its last lines each time lines are appended
+pub(super) fn follow(path: PathBuf, max_lines: usize) -> Task<Message> {
+    let stream = iced::stream::channel(1, move |mut output| async move {
+        let mut tail = Tail::new(max_lines);
+
+        let mut offset = 0;
+        let mut skip_line = false;
+
+        // The lines read when following starts are always sent, even if there are none
+        let mut initial = true;
+
+        loop {
+            match read_from(&path, &mut offset).await {
+                Ok(ReadData {
+                    data,
+                    truncated,
+                    skipped,
+                }) => {
+                    // Reads starting near the end of large files skip the line cut by their start
+                    if truncated || skipped {
+                        tail.clear();
+                    }
+                    if skipped {
+                        skip_line = true;
+                    } else if truncated {
+                        skip_line = false;
+                    }
+
+                    let mut data = data.as_slice();
+                    if skip_line {
+                        match data.iter().position(|b| *b == b'\n') {
+                            Some(end) => {
+                                data = &data[end + 1..];
+                                skip_line = false;
+                            }
+                            None => data = &[],
+                        }
+                    }
+
+                    let changed = tail.append(data) || truncated || initial;
+                    initial = false;
+
+                    if changed
+                        && output
+                            .send(Message::unicast(FileEvent::Appended(tail.text())))
+                            .await
+                            .is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let result: Result<FileEvent, crate::Error> = Err(e.into());
+                    let _ = output.send(Message::from(result)).await;
+                    break;
+                }
+            }
+
+            tokio::time::sleep(FOLLOW_INTERVAL).await;
+        }
+    });
+
+    Task::run(stream, |message| message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tail;
+
+    #[test]
+    fn tail() {
+        let mut tail = Tail::new(2);
+
+        // Lines are kept once they're complete
+        assert!(!tail.append(b"one"));
+        assert!(tail.append(b" line\r\ntwo\n"));
+        assert_eq!(tail.text(), "one line\ntwo");
+
+        // Only the last lines are kept
+        assert!(tail.append(b"three\nfour\nfi"));
+        assert_eq!(tail.text(), "three\nfour");
+        assert!(tail.append(b"ve\n"));
+        assert_eq!(tail.text(), "four\nfive");
+
+        tail.clear();
+        assert_eq!(tail.text(), "");
+    }
+}
@@ -15,7 +15,7 @@ impl From<FileFormat> for ModuleDataKind {
                     ModuleDataKind::Image
                 }
             }
-            file_format::Kind::Font => todo!(),
+            file_format::Kind::Video => ModuleDataKind::Video,
             file_format::Kind::Other => {
                 if FileFormat::PlainText == format {
                     ModuleDataKind::Text
@@ -1,24 +1,53 @@
 //! File Module
+//!
+//! With a `progress` argument, the file is read in chunks of [`CHUNK_SIZE`] bytes and the progress of loading it is
+//! published to the given topic, as described in [`super::progress`].
+//!
+//! In `list` mode, the data of the module is the sorted paths of the entries of the directory at `path`, separated by
+//! newlines. A `pattern` with `*` and `?` wildcards selects the entries by name, and hidden entries are only listed if
+//! the pattern starts with a `.`. Nodes can select a path by its index with the `field` argument, or the number of
+//! entries with the `count` field.
+//!
+//! ```text
+//! text(file!{path:"./shots", mode:"list", pattern:"*.png"})
+//! ```
+//!
+//! With `follow:true`, the file is followed as it's appended to, and the data of the module is its last `lines` lines
+//! as text, which is sent again each time lines are appended. Up to [`follow::DEFAULT_LINES`] lines are kept by
+//! default.
+//!
+//! ```text
+//! scrollable(text(file!{path:"app.log", follow:true, lines:200}))
+//! ```
 
 use std::fs::Metadata;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use super::data::{ModuleData, ModuleDataKind};
+use super::data::{ModuleData, ModuleDataKind, TextData};
 use super::internal::ModuleInternal;
+use super::progress::ProgressReporter;
 use super::{error::ModuleError, Module, ModuleEvent, ModuleInitData};
+use crate::message::module::Topic;
 use crate::module::argument::ModuleArguments;
 use async_trait::async_trait;
 use file_format::FileFormat;
-use iced::Task;
+use iced::{futures::SinkExt as _, Task};
+use regex::Regex;
 use salish::Message;
+use strum::{EnumString, VariantNames};
 use tokio::fs::File;
 use tokio::{fs, io::AsyncReadExt as _};
 
+pub mod follow;
 mod format;
 
+/// Size of the chunks a file is read in when its progress is published
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
 pub struct FileContents {
     metadata: Metadata,
-    buf: Vec<u8>,
+    buf: Arc<Vec<u8>>,
     format: FileFormat,
 }
 
@@ -28,7 +57,11 @@ impl ModuleData for FileContents {
     }
 
     fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
-        Ok(&self.buf)
+        Ok(self.buf.as_ref())
+    }
+
+    fn shared_bytes(&self) -> Option<Arc<Vec<u8>>> {
+        Some(self.buf.clone())
     }
 }
 
@@ -41,11 +74,111 @@ impl std::fmt::Debug for FileContents {
     }
 }
 
+/// Paths of the entries of a directory listed in `list` mode
+#[derive(Debug)]
+pub struct FileList {
+    paths: Vec<PathBuf>,
+    bytes: Vec<u8>,
+}
+
+impl FileList {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let bytes = paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+            .into_bytes();
+
+        Self { paths, bytes }
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+impl ModuleData for FileList {
+    fn kind(&self) -> ModuleDataKind {
+        ModuleDataKind::Text
+    }
+
+    fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
+        Ok(&self.bytes)
+    }
+
+    fn field(&self, name: &str) -> Option<Box<dyn ModuleData>> {
+        if name == "count" {
+            return Some(Box::new(TextData::new(self.paths.len().to_string())));
+        }
+
+        let path = self.paths.get(name.parse::<usize>().ok()?)?;
+        Some(Box::new(TextData::new(path.display().to_string())))
+    }
+}
+
+/// Mode of the file module, given with the `mode` argument
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum FileMode {
+    /// Load the contents of the file
+    #[default]
+    Read,
+    /// List the entries of the directory
+    List,
+}
+
+/// Build a regex matching file names against a `pattern` with `*` and `?` wildcards
+fn pattern_regex(pattern: &str) -> Result<Regex, ModuleError> {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+
+    Regex::new(&regex).map_err(|e| ModuleError::InvalidArgument(format!("pattern: {e}")))
+}
+
+/// Check if an entry of a directory is listed, skipping hidden entries unless the pattern starts with a `.`
+fn is_listed(name: &str, pattern: Option<&Regex>) -> bool {
+    match pattern {
+        Some(pattern) => {
+            (!name.starts_with('.') || pattern.as_str().starts_with("^\\."))
+                && pattern.is_match(name)
+        }
+        None => !name.starts_with('.'),
+    }
+}
+
+/// List the entries of a directory matching a pattern, sorted by path
+async fn list(dir: &Path, pattern: Option<&Regex>) -> Result<FileList, crate::Error> {
+    let mut entries = fs::read_dir(dir).await?;
+    let mut paths = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        if is_listed(&entry.file_name().to_string_lossy(), pattern) {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+
+    Ok(FileList::new(paths))
+}
+
 #[derive(Debug)]
 pub(super) enum FileEvent {
     Open(PathBuf),
     Opened(File),
     Loaded(FileContents),
+    List(PathBuf),
+    Listed(FileList),
+    Follow(PathBuf),
+    /// The last lines of a followed file, after lines were appended
+    Appended(String),
 }
 
 impl ModuleEvent for FileEvent {}
@@ -53,6 +186,56 @@ impl ModuleEvent for FileEvent {}
 #[derive(Default, Debug)]
 pub(super) struct FileModule {
     path: Option<PathBuf>,
+    progress: Option<Topic>,
+    pattern: Option<Regex>,
+    lines: usize,
+}
+
+/// Identify the format of the contents of a file on a blocking thread
+async fn identify(metadata: Metadata, buf: Vec<u8>) -> Result<FileEvent, crate::Error> {
+    let contents = tokio::task::spawn_blocking(move || {
+        let format = FileFormat::from_bytes(&buf);
+        FileContents {
+            metadata,
+            buf: Arc::new(buf),
+            format,
+        }
+    })
+    .await
+    .map_err(crate::Error::Tokio)?;
+
+    Ok(FileEvent::Loaded(contents))
+}
+
+/// Read a file in chunks, publishing the progress of loading it to a topic
+fn read_chunks(mut file: File, topic: Topic) -> Task<Message> {
+    let stream = iced::stream::channel(1, |mut output| async move {
+        let result: Result<_, crate::Error> = async {
+            let metadata = file.metadata().await?;
+            let mut reporter = ProgressReporter::new(topic, Some(metadata.len()));
+
+            let mut buf = Vec::with_capacity(metadata.len() as usize);
+            let mut chunk = vec![0; CHUNK_SIZE];
+            loop {
+                let size = file.read(&mut chunk).await?;
+                if size == 0 {
+                    break;
+                }
+                buf.extend_from_slice(&chunk[..size]);
+                if let Some(message) = reporter.advance(size) {
+                    let _ = output.send(message).await;
+                }
+            }
+
+            let _ = output.send(reporter.finish()).await;
+            identify(metadata, buf).await
+        }
+        .await;
+
+        let _ = output.send(Message::from(result)).await;
+    });
+
+    Task::run(stream, |message| message)
 }
 
 /// File module implementation
@@ -61,17 +244,68 @@ impl Module for FileModule {
     type Event = FileEvent;
     type Data = FileContents;
 
+    fn validate_args(args: &ModuleArguments) -> Result<(), ModuleError> {
+        args.check(
+            &["path", "progress", "mode", "pattern", "follow", "lines"],
+            &["path"],
+        )?;
+        ProgressReporter::topic(args)?;
+        let mode = match args.get("mode") {
+            Ok(_) => args.get_enum("mode")?,
+            Err(_) => FileMode::default(),
+        };
+        if args.get_bool_or("follow", false)? && mode == FileMode::List {
+            return Err(ModuleError::InvalidArgument(
+                "'follow' can't be used in list mode".into(),
+            ));
+        }
+        if args.get("lines").is_ok() {
+            let lines = args.get_integer("lines")?;
+            if lines < 1 || lines > follow::MAX_LINES as u64 {
+                return Err(ModuleError::InvalidArgument(format!(
+                    "'lines' must be from 1 to {}",
+                    follow::MAX_LINES
+                )));
+            }
+        }
+        if args.get("pattern").is_ok() {
+            pattern_regex(&args.get_string("pattern")?)?;
+        }
+
+        Ok(())
+    }
+
     async fn init(
         &mut self,
         args: ModuleArguments,
         _init_data: ModuleInitData,
     ) -> Result<Self::Event, ModuleError> {
         self.path = Some(args.get("path")?.to_string().into());
+        self.progress = ProgressReporter::topic(&args)?;
+        self.pattern = match args.get("pattern") {
+            Ok(_) => Some(pattern_regex(&args.get_string("pattern")?)?),
+            Err(_) => None,
+        };
+        let mode = match args.get("mode") {
+            Ok(_) => args.get_enum("mode")?,
+            Err(_) => FileMode::default(),
+        };
+
+        self.lines = match args.get("lines") {
+            Ok(_) => args.get_integer("lines")? as usize,
+            Err(_) => follow::DEFAULT_LINES,
+        };
 
         // Return error if the file doesn't exist
         fs::try_exists(self.path.as_ref().unwrap()).await?;
 
-        Ok(FileEvent::Open(self.path.clone().unwrap()))
+        match mode {
+            FileMode::Read if args.get_bool_or("follow", false)? => {
+                Ok(FileEvent::Follow(self.path.clone().unwrap()))
+            }
+            FileMode::Read => Ok(FileEvent::Open(self.path.clone().unwrap())),
+            FileMode::List => Ok(FileEvent::List(self.path.clone().unwrap())),
+        }
     }
 
     fn on_event(&mut self, event: Self::Event) -> Task<Message> {
@@ -83,6 +317,9 @@ impl Module for FileModule {
                 },
                 |result: Result<FileEvent, crate::Error>| Message::from(result),
             ),
+            FileEvent::Opened(file) if self.progress.is_some() => {
+                read_chunks(file, self.progress.clone().unwrap())
+            }
             FileEvent::Opened(mut file) => Task::perform(
                 async move {
                     let metadata = file.metadata().await?;
@@ -91,22 +328,81 @@ impl Module for FileModule {
                     let size = file.read_to_end(&mut buf).await?;
                     assert_eq!(size, metadata.len() as usize);
 
-                    let contents = tokio::task::spawn_blocking(move || {
-                        let format = FileFormat::from_bytes(&buf);
-                        FileContents {
-                            metadata,
-                            buf,
-                            format,
-                        }
-                    })
-                    .await
-                    .map_err(crate::Error::Tokio)?;
-
-                    Ok(FileEvent::Loaded(contents))
+                    identify(metadata, buf).await
                 },
                 |result: Result<FileEvent, crate::Error>| Message::from(result),
             ),
             FileEvent::Loaded(contents) => self.send_data(contents),
+            FileEvent::List(dir) => {
+                let pattern = self.pattern.clone();
+                Task::perform(
+                    async move { Ok(FileEvent::Listed(list(&dir, pattern.as_ref()).await?)) },
+                    |result: Result<FileEvent, crate::Error>| Message::from(result),
+                )
+            }
+            FileEvent::Listed(list) => {
+                let data: Box<dyn ModuleData> = Box::new(list);
+                Task::done(Message::unicast(data))
+            }
+            FileEvent::Follow(path) => follow::follow(path, self.lines),
+            FileEvent::Appended(text) => {
+                let data: Box<dyn ModuleData> = Box::new(TextData::new(text));
+                Task::done(Message::unicast(data))
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{is_listed, list, pattern_regex, FileModule};
+    use crate::module::{argument::ModuleArguments, data::ModuleData as _, Module as _};
+
+    #[traced_test]
+    #[test]
+    fn list_mode() {
+        let png = pattern_regex("*.png").unwrap();
+        assert!(is_listed("shot-1.png", Some(&png)));
+        assert!(!is_listed("shot-1.png.txt", Some(&png)));
+        assert!(!is_listed(".hidden.png", Some(&png)));
+        assert!(is_listed(
+            ".hidden",
+            Some(&pattern_regex(".h?dden").unwrap())
+        ));
+        assert!(!is_listed(".hidden", None));
+
+        let dir = std::env::temp_dir().join(format!("snowcap-file-list-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b.png", "a.png", "notes.txt", ".hidden.png"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let files = runtime.block_on(list(&dir, Some(&png))).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files.paths(), [dir.join("a.png"), dir.join("b.png")]);
+        assert_eq!(
+            files.field("count").unwrap().bytes().unwrap().as_slice(),
+            b"2"
+        );
+        assert!(files.field("2").is_none());
+
+        let args = ModuleArguments::new().arg("path", r#""shots""#);
+        assert!(FileModule::validate_args(&args.clone().arg("mode", r#""list""#)).is_ok());
+        assert!(FileModule::validate_args(&args.clone().arg("mode", r#""tree""#)).is_err());
+        assert!(FileModule::validate_args(&args.clone().arg("follow", "true")).is_ok());
+        assert!(FileModule::validate_args(&args.clone().arg("lines", "200")).is_ok());
+        assert!(FileModule::validate_args(&args.clone().arg("lines", "0")).is_err());
+        assert!(FileModule::validate_args(&args.clone().arg("lines", "100000000")).is_err());
+        assert!(FileModule::validate_args(
+            &args.clone().arg("mode", r#""list""#).arg("follow", "true")
+        )
+        .is_err());
+        assert!(FileModule::validate_args(&args.arg("pattern", "1")).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Formatting module, displaying a number or date formatted with a pattern.
+//!
+//! ```text
+//! text(fmt!{value:1234567.891, pattern:"{:,.2} MB"})
+//! text(fmt!{value:1700000000, pattern:"Updated {:%Y-%m-%d %H:%M}"})
+//! text(fmt!{value: http!{url:"http://example.com/size"}, pattern:"{:.2} MB"})
+//! ```
+//!
+//! Placeholders of the pattern are `{}` or `{value}` for the value unchanged, or have a format spec after a colon:
+//!
+//! | Spec       | Description                                                        | Example          |
+//! |------------|--------------------------------------------------------------------|------------------|
+//! | `{:.2}`    | Number with a fixed number of decimals                             | `3.14`           |
+//! | `{:,}`     | Number with thousands separators                                   | `1,234,567.891`  |
+//! | `{:,.2}`   | Both                                                               | `1,234,567.89`   |
+//! | `{:%H:%M}` | Date of a unix timestamp or RFC 3339 date, with `strftime` fields   | `13:46`          |
+//!
+//! Separators follow the locale given with the `locale` argument, or the active locale of the [`super::i18n`]
+//! module, so `{:,.2}` is `1.234.567,89` in `de`. The value is displayed unchanged if it can't be formatted.
+//!
+//! The value can also be the output of another module. The module is instantiated in place of `fmt!`, with the
+//! pattern as its `format` argument (see [`super::output`]), and is formatted each time it sends data.
+
+use std::{borrow::Cow, fmt::Write as _};
+
+use async_trait::async_trait;
+use iced::Task;
+use salish::Message;
+use tracing::debug;
+
+use crate::{
+    message::module::{ModuleMessageData, Topic, TopicMessage},
+    module::argument::ModuleArguments,
+};
+
+use super::{
+    data::TextData,
+    error::ModuleError,
+    i18n::{self, Locales},
+    internal::ModuleInternal,
+    Module, ModuleEvent, ModuleInitData,
+};
+
+/// Name of the formatting module, which is replaced by the module given as its `value`
+pub const FORMAT_MODULE: &str = "fmt";
+
+/// Returns true if a pattern has a placeholder for the value
+pub fn has_placeholder(pattern: &str) -> bool {
+    ["{}", "{value}", "{:", "{value:"]
+        .iter()
+        .any(|placeholder| pattern.contains(placeholder))
+}
+
+/// Replace the placeholders of a pattern with the value, formatted with the separators of a locale
+pub fn format_value(pattern: &str, value: &str, locale: &str) -> String {
+    let mut formatted = String::with_capacity(pattern.len() + value.len());
+    let mut rest = pattern;
+
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[..start]);
+
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let placeholder = &after[..end];
+        match placeholder.strip_prefix("value").unwrap_or(placeholder) {
+            "" => formatted.push_str(value),
+            spec if spec.starts_with(':') => {
+                formatted.push_str(&format_spec(&spec[1..], value, locale))
+            }
+            // Not a placeholder, such as a literal brace
+            _ => formatted.push_str(&rest[start..start + end + 2]),
+        }
+
+        rest = &after[end + 1..];
+    }
+
+    formatted.push_str(rest);
+    formatted
+}
+
+/// Format a value with the spec of a placeholder, or get it unchanged if it can't be formatted
+fn format_spec(spec: &str, value: &str, locale: &str) -> String {
+    if spec.starts_with('%') {
+        return format_date(spec, value).unwrap_or_else(|| value.to_string());
+    }
+
+    let Ok(number) = value.trim().parse::<f64>() else {
+        return value.to_string();
+    };
+
+    let (grouped, precision) = match spec.strip_prefix(',') {
+        Some(precision) => (true, precision),
+        None => (false, spec),
+    };
+
+    let text = match precision.strip_prefix('.') {
+        Some(digits) => match digits.parse::<usize>() {
+            Ok(digits) => format!("{number:.digits$}"),
+            Err(_) => return value.to_string(),
+        },
+        None if precision.is_empty() => number.to_string(),
+        None => return value.to_string(),
+    };
+
+    localize(&text, grouped, locale)
+}
+
+/// Replace the decimal point of a formatted number with the separator of a locale, and insert thousands
+/// separators into the integer part if grouped
+fn localize(number: &str, grouped: bool, locale: &str) -> String {
+    let (group, decimal) = separators(locale);
+
+    let (sign, number) = match number.strip_prefix('-') {
+        Some(number) => ("-", number),
+        None => ("", number),
+    };
+
+    let (integer, fraction) = match number.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number, None),
+    };
+
+    let mut localized = String::from(sign);
+    for (i, digit) in integer.chars().enumerate() {
+        if grouped && i > 0 && (integer.len() - i) % 3 == 0 {
+            localized.push_str(group);
+        }
+        localized.push(digit);
+    }
+
+    if let Some(fraction) = fraction {
+        localized.push_str(decimal);
+        localized.push_str(fraction);
+    }
+
+    localized
+}
+
+/// Get the thousands and decimal separators of a locale
+fn separators(locale: &str) -> (&'static str, &'static str) {
+    if matches!(locale, "de-CH" | "de_CH") {
+        return ("’", ".");
+    }
+
+    match i18n::language(locale) {
+        "da" | "de" | "el" | "es" | "id" | "it" | "nl" | "pt" | "tr" => (".", ","),
+        "cs" | "fi" | "fr" | "hu" | "nb" | "pl" | "ru" | "sk" | "sv" | "uk" => ("\u{202f}", ","),
+        _ => (",", "."),
+    }
+}
+
+/// Format a unix timestamp in seconds, or an RFC 3339 date, in the local time zone
+fn format_date(spec: &str, value: &str) -> Option<String> {
+    let value = value.trim();
+
+    let date = match value.parse::<f64>() {
+        Ok(seconds) => {
+            let nanos = ((seconds - seconds.floor()) * 1e9) as u32;
+            chrono::DateTime::from_timestamp(seconds.floor() as i64, nanos)?
+                .with_timezone(&chrono::Local)
+        }
+        Err(_) => chrono::DateTime::parse_from_rfc3339(value)
+            .ok()?
+            .with_timezone(&chrono::Local),
+    };
+
+    // Writing an invalid spec fails rather than panicking
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(spec)).ok()?;
+    Some(formatted)
+}
+
+#[derive(Debug)]
+pub enum FormatEvent {
+    Init,
+}
+impl ModuleEvent for FormatEvent {}
+
+#[derive(Debug, Default)]
+pub struct FormatModule {
+    value: String,
+    pattern: String,
+    locale: Option<String>,
+    locales: Locales,
+}
+
+impl FormatModule {
+    /// Get the data of the formatted value
+    fn text(&self) -> TextData {
+        let locale = self.locale.clone().unwrap_or_else(|| self.locales.locale());
+        TextData::new(format_value(&self.pattern, &self.value, &locale))
+    }
+}
+
+#[async_trait]
+impl Module for FormatModule {
+    type Event = FormatEvent;
+    type Data = TextData;
+
+    async fn init(
+        &mut self,
+        args: ModuleArguments,
+        init_data: ModuleInitData,
+    ) -> Result<Self::Event, ModuleError> {
+        let value: Cow<'_, str> = args.get("value")?.inner().into();
+        self.locales = init_data.locales().clone();
+        self.value = value.into_owned();
+        self.pattern = args
+            .get("pattern")
+            .map(|pattern| pattern.to_string())
+            .unwrap_or_else(|_| "{}".into());
+        self.locale = args.get("locale").ok().map(|locale| locale.to_string());
+
+        Ok(FormatEvent::Init)
+    }
+
+    fn on_event(&mut self, event: Self::Event) -> Task<Message> {
+        match event {
+            // Values are formatted again when the active locale changes, unless the locale is given
+            FormatEvent::Init if self.locale.is_none() => Task::done(Message::broadcast(
+                ModuleMessageData::Subscribe(i18n::topic()),
+            ))
+            .chain(self.send_data(self.text())),
+            FormatEvent::Init => self.send_data(self.text()),
+        }
+    }
+
+    fn on_subscription(&mut self, topic: Topic, _message: TopicMessage) -> Task<Message> {
+        debug!(
+            "Formatting {} for the locale published to {topic}",
+            self.value
+        );
+        self.send_data(self.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{format_value, FormatModule};
+    use crate::module::{argument::ModuleArguments, testing::TestBed};
+
+    #[traced_test]
+    #[test]
+    fn numbers() {
+        assert_eq!(format_value("{:.2} MB", "3.14159", "en"), "3.14 MB");
+        assert_eq!(format_value("{:,}", "1234567", "en"), "1,234,567");
+        assert_eq!(
+            format_value("{value:,.2}", "-1234567.891", "en"),
+            "-1,234,567.89"
+        );
+        assert_eq!(format_value("{:,.2}", "1234567.891", "de"), "1.234.567,89");
+        assert_eq!(
+            format_value("{:,.0}", "1234567", "fr-FR"),
+            "1\u{202f}234\u{202f}567"
+        );
+
+        // Values which aren't numbers, and unknown specs, are left unchanged
+        assert_eq!(format_value("{:.2}", "N/A", "en"), "N/A");
+        assert_eq!(format_value("{:x}", "12", "en"), "12");
+        assert_eq!(format_value("{} and {other}", "1", "en"), "1 and {other}");
+        assert_eq!(format_value("{} {", "1", "en"), "1 {");
+    }
+
+    #[traced_test]
+    #[test]
+    fn dates() {
+        let formatted = format_value("{:%Y}", "2024-06-01T12:00:00Z", "en");
+        assert_eq!(formatted, "2024");
+
+        let formatted = format_value("{:%Y-%m}", "1700000000", "en");
+        assert!(formatted.starts_with("2023-11"));
+
+        assert_eq!(format_value("{:%Y}", "yesterday", "en"), "yesterday");
+    }
+
+    #[traced_test]
+    #[test]
+    fn format_module() {
+        let args = ModuleArguments::new()
+            .arg("value", "1234.5")
+            .arg("pattern", r#""{:,.2} MB""#)
+            .arg("locale", r#""en""#);
+        let mut bed = TestBed::<FormatModule>::new(args).unwrap();
+        bed.run();
+
+        assert_eq!(bed.data()[0], b"1,234.50 MB");
+    }
+}
@@ -0,0 +1,193 @@
+//! Configuration of the HTTP client of the http module
+//!
+//! An [`HttpConfig`] set with [`crate::Snowcap::set_http_config()`] applies to the http modules of that engine
+//! instantiated after it's set. The `timeout` and `max-redirects` arguments of a module override the defaults of the config. Proxies and
+//! certificate validation can only be configured by the application, not by markup.
+//!
+//! ```ignore
+//! snowcap.set_http_config(
+//!     HttpConfig::default()
+//!         .with_timeout(Duration::from_secs(10))
+//!         .with_max_redirects(3)
+//!         .with_proxy(Url::parse("http://localhost:8080")?),
+//! );
+//! ```
+
+use std::time::Duration;
+
+use reqwest::{redirect, Client, Proxy};
+use url::Url;
+
+use crate::module::policy::ModulePolicy;
+
+/// Number of redirects followed by default, matching the default of reqwest
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Options of the HTTP client of the http module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// Timeout of a whole request, or no timeout if `None`
+    timeout: Option<Duration>,
+
+    /// Timeout of connecting to the server, or no timeout if `None`
+    connect_timeout: Option<Duration>,
+
+    /// Number of redirects followed before a request fails. Redirects aren't followed if zero.
+    max_redirects: usize,
+
+    /// Proxy of all requests
+    proxy: Option<Url>,
+
+    /// Accept invalid TLS certificates, such as self signed certificates of development servers
+    accept_invalid_certs: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            connect_timeout: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            proxy: None,
+            accept_invalid_certs: false,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Set the default timeout of a whole request, which modules can override with a `timeout` argument
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout of connecting to the server
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the default number of redirects followed, which modules can override with a `max-redirects` argument.
+    /// Redirects aren't followed if zero, and the redirect response is the data of the module.
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Send all requests through an HTTP or HTTPS proxy, such as `http://localhost:8080`
+    pub fn with_proxy(mut self, proxy: Url) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Accept invalid TLS certificates. This should only be enabled for development servers, as it allows
+    /// responses to be intercepted.
+    pub fn with_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Build a client with the options of the config, and the timeout and redirect limit of a module if given.
+    /// Redirects are only followed to URLs permitted by the [`ModulePolicy`].
+    pub(crate) fn client(
+        &self,
+        timeout: Option<Duration>,
+        max_redirects: Option<usize>,
+        policy: &ModulePolicy,
+    ) -> Result<Client, reqwest::Error> {
+        let redirect = match max_redirects.unwrap_or(self.max_redirects) {
+            0 => redirect::Policy::none(),
+            max => {
+                let policy = policy.clone();
+                redirect::Policy::custom(move |attempt| {
+                    match check_redirect(&policy, attempt.url(), attempt.previous().len(), max) {
+                        Ok(()) => attempt.follow(),
+                        Err(e) => attempt.error(e),
+                    }
+                })
+            }
+        };
+
+        let mut builder = reqwest::ClientBuilder::new()
+            .connection_verbose(true)
+            .user_agent("Snowcap")
+            .redirect(redirect)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+
+        if let Some(timeout) = timeout.or(self.timeout) {
+            builder = builder.timeout(timeout);
+        }
+
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.clone())?);
+        }
+
+        builder.build()
+    }
+}
+
+/// Check a redirect to `url` after `previous` requests, returning the error failing the request if it must not be
+/// followed
+fn check_redirect(
+    policy: &ModulePolicy,
+    url: &Url,
+    previous: usize,
+    max_redirects: usize,
+) -> Result<(), String> {
+    // The previous requests include the first, as counted by `redirect::Policy::limited`
+    if previous > max_redirects {
+        return Err(format!("too many redirects, the limit is {max_redirects}"));
+    }
+
+    policy
+        .check_url(url.as_str())
+        .map_err(|e| format!("redirect {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use url::Url;
+
+    use super::{check_redirect, HttpConfig};
+    use crate::module::policy::ModulePolicy;
+
+    #[test]
+    fn client_options() {
+        let policy = ModulePolicy::default();
+        let config = HttpConfig::default()
+            .with_timeout(Duration::from_secs(5))
+            .with_connect_timeout(Duration::from_secs(1))
+            .with_max_redirects(0)
+            .with_proxy(Url::parse("http://localhost:8080").unwrap())
+            .with_accept_invalid_certs(true);
+        assert!(config.client(None, None, &policy).is_ok());
+        assert!(config
+            .client(Some(Duration::from_secs(1)), Some(3), &policy)
+            .is_ok());
+
+        let config = HttpConfig::default().with_proxy(Url::parse("gopher://localhost").unwrap());
+        assert!(config.client(None, None, &policy).is_err());
+    }
+
+    #[test]
+    fn redirect_policy() {
+        let policy = ModulePolicy::default()
+            .with_url_allow("https://example.com/")
+            .with_url_deny("https://example.com/admin/");
+        let url = |url| Url::parse(url).unwrap();
+
+        assert!(check_redirect(&policy, &url("https://example.com/next"), 1, 3).is_ok());
+        assert!(check_redirect(&policy, &url("https://example.com/next"), 3, 3).is_ok());
+        assert!(check_redirect(&policy, &url("https://example.com/next"), 4, 3).is_err());
+
+        // Each hop is checked against the URL rules of the policy
+        assert!(check_redirect(&policy, &url("https://example.com/admin/users"), 1, 3).is_err());
+        assert!(check_redirect(&policy, &url("http://169.254.169.254/"), 1, 3).is_err());
+    }
+}
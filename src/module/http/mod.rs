@@ -1,18 +1,51 @@
 //! HTTP Request Module
+//!
+//! The kind of the response data is taken from its `Content-Type`. Responses without a content type, or with one
+//! which isn't an image, video or text, are identified from their contents. APIs with imprecise headers can be
+//! given a `kind` argument of `image`, `svg`, `text` or `bytes`, which overrides the content type. `bytes` sends
+//! the response as [`ModuleDataKind::Bytes`] without identifying it, for consumers such as scripts and topic
+//! subscribers.
+//!
+//! ```text
+//! image(http!{url:"https://example.com/avatar", kind:"image"})
+//! ```
+//!
+//! The `timeout` and `max-redirects` arguments override the defaults of the [`HttpConfig`] of the application.
+//! Request headers are given as a map, which replaces the default `Accept: */*`:
+//!
+//! ```text
+//! text(http!{url:"https://example.com/data.json", headers: {Accept: "application/json", "X-Token": "..."}})
+//! ```
+//!
+//! With a `progress` argument, the response is downloaded in chunks and the progress of the download is published
+//! to the given topic, as described in [`super::progress`]. Text downloaded with progress is decoded as UTF-8.
+
+use std::sync::Arc;
 
 use super::data::{ModuleData, ModuleDataKind};
 use super::internal::ModuleInternal;
+use super::progress::ProgressReporter;
 use super::{error::ModuleError, Module, ModuleEvent, ModuleInitData};
-use crate::message::module::ModuleMessageData;
+use crate::message::module::{ModuleMessageData, Topic};
 use crate::module::argument::ModuleArguments;
 use crate::Value;
 use async_trait::async_trait;
-use iced::Task;
+use file_format::FileFormat;
+use iced::{futures::SinkExt as _, Task};
 use reqwest::Url;
-use reqwest::{header, Client, Method};
+use reqwest::{
+    header,
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, Method,
+};
 use salish::Message;
+use strum::{EnumString, VariantNames};
 use thiserror::Error;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
+
+mod config;
+
+pub use config::HttpConfig;
 
 #[derive(Error, Debug)]
 pub enum HttpError {
@@ -26,12 +59,70 @@ pub(super) enum HttpEvent {
     Request(reqwest::Request),
     Response(reqwest::Response),
     Data(HttpData),
+    Failed(HttpError),
+}
+
+/// Route the result of a request step back into [`HttpModule::on_event()`], mapping errors to [`HttpEvent::Failed`]
+fn event_message(result: Result<HttpEvent, HttpError>) -> Message {
+    match result {
+        Ok(event) => Message::unicast(event),
+        Err(e) => Message::unicast(HttpEvent::Failed(e)),
+    }
+}
+
+/// Download the body of a response in chunks, publishing the progress of the download to a topic
+fn download(
+    mut response: reqwest::Response,
+    url: Url,
+    kind: Option<ModuleDataKind>,
+    topic: Topic,
+) -> Task<Message> {
+    let stream = iced::stream::channel(1, |mut output| async move {
+        let total = response.content_length();
+        let mut reporter = ProgressReporter::new(topic, total);
+        let mut data = Vec::with_capacity(total.unwrap_or(0) as usize);
+
+        let result = loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    data.extend_from_slice(&chunk);
+                    if let Some(message) = reporter.advance(chunk.len()) {
+                        let _ = output.send(message).await;
+                    }
+                }
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(HttpError::Reqwest(e)),
+            }
+        };
+
+        let result = result.map(|_| {
+            let data = match kind {
+                Some(ModuleDataKind::Text) => {
+                    String::from_utf8_lossy(&data).into_owned().into_bytes()
+                }
+                _ => data,
+            };
+            let kind = kind.unwrap_or_else(|| FileFormat::from_bytes(&data).into());
+            HttpEvent::Data(HttpData {
+                url,
+                kind,
+                data: Arc::new(data),
+            })
+        });
+
+        if result.is_ok() {
+            let _ = output.send(reporter.finish()).await;
+        }
+        let _ = output.send(event_message(result)).await;
+    });
+
+    Task::run(stream, |message| message)
 }
 
 pub struct HttpData {
     url: Url,
     kind: ModuleDataKind,
-    data: Vec<u8>,
+    data: Arc<Vec<u8>>,
 }
 
 impl std::fmt::Debug for HttpData {
@@ -50,16 +141,86 @@ impl ModuleData for HttpData {
     }
 
     fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
-        Ok(&self.data)
+        Ok(self.data.as_ref())
+    }
+
+    fn shared_bytes(&self) -> Option<Arc<Vec<u8>>> {
+        Some(self.data.clone())
     }
 }
 
 impl ModuleEvent for HttpEvent {}
 
+/// Kind of the response data given with the `kind` argument, overriding the `Content-Type` of the response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum HttpKind {
+    Image,
+    Svg,
+    Text,
+    /// Identify the data from its contents
+    Bytes,
+}
+
+impl HttpKind {
+    /// Get the kind of the module data
+    fn data_kind(self) -> ModuleDataKind {
+        match self {
+            HttpKind::Image => ModuleDataKind::Image,
+            HttpKind::Svg => ModuleDataKind::Svg,
+            HttpKind::Text => ModuleDataKind::Text,
+            HttpKind::Bytes => ModuleDataKind::Bytes,
+        }
+    }
+}
+
+/// Get the kind of the data of a response from its `Content-Type`, or None if it's missing or unknown
+fn content_kind(headers: &HeaderMap) -> Option<ModuleDataKind> {
+    let content_type = headers.get(header::CONTENT_TYPE)?;
+    debug!("Content Type: {content_type:?}");
+
+    let mime: mime::Mime = match content_type.to_str().ok().and_then(|s| s.parse().ok()) {
+        Some(mime) => mime,
+        None => {
+            warn!("Invalid content type {content_type:?}");
+            return None;
+        }
+    };
+
+    match (mime.type_(), mime.subtype()) {
+        (mime::IMAGE, mime::SVG) => Some(ModuleDataKind::Svg),
+        (mime::IMAGE, _) => Some(ModuleDataKind::Image),
+        (mime::VIDEO, _) => Some(ModuleDataKind::Video),
+        (mime::TEXT, _) => Some(ModuleDataKind::Text),
+        _ => None,
+    }
+}
+
+/// Get the headers of the request from the `headers` map argument
+fn request_headers(args: &ModuleArguments) -> Result<HeaderMap, ModuleError> {
+    let mut headers = HeaderMap::new();
+    if args.get("headers").is_err() {
+        return Ok(headers);
+    }
+
+    for (name, value) in args.get_map("headers")? {
+        let header = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| ModuleError::InvalidArgument(format!("header name '{name}': {e}")))?;
+        let value = HeaderValue::from_str(&value.to_string())
+            .map_err(|e| ModuleError::InvalidArgument(format!("value of header '{name}': {e}")))?;
+        headers.insert(header, value);
+    }
+
+    Ok(headers)
+}
+
 #[derive(Default, Debug)]
 pub(super) struct HttpModule {
     method: Option<Method>,
     url: Option<Url>,
+    headers: HeaderMap,
+    kind: Option<HttpKind>,
+    progress: Option<Topic>,
     client: Option<Client>,
 }
 
@@ -67,10 +228,40 @@ pub(super) struct HttpModule {
 impl Module for HttpModule {
     type Event = HttpEvent;
     type Data = HttpData;
+
+    fn validate_args(args: &ModuleArguments) -> Result<(), ModuleError> {
+        args.check(
+            &[
+                "url",
+                "method",
+                "kind",
+                "timeout",
+                "max-redirects",
+                "progress",
+                "headers",
+            ],
+            &["url"],
+        )?;
+        args.get_url("url")?;
+        if args.get("kind").is_ok() {
+            args.get_enum::<HttpKind>("kind")?;
+        }
+        if args.get("timeout").is_ok() {
+            args.get_duration("timeout")?;
+        }
+        if args.get("max-redirects").is_ok() {
+            args.get_integer("max-redirects")?;
+        }
+        ProgressReporter::topic(args)?;
+        request_headers(args)?;
+
+        Ok(())
+    }
+
     async fn init(
         &mut self,
         args: ModuleArguments,
-        _init_data: ModuleInitData,
+        init_data: ModuleInitData,
     ) -> Result<Self::Event, ModuleError> {
         let method = args
             .get("method")
@@ -86,21 +277,27 @@ impl Module for HttpModule {
             }
         }
 
-        let url = args.get("url")?.to_string();
-        match Url::parse(url.as_str()) {
-            Ok(url) => self.url = Some(url),
-            Err(e) => {
-                return Err(ModuleError::InvalidArgument(format!(
-                    "Failed to parse URL: {e:?}"
-                )));
-            }
-        }
+        self.url = Some(args.get_url("url")?);
+        self.headers = request_headers(&args)?;
+        self.kind = match args.get("kind") {
+            Ok(_) => Some(args.get_enum("kind")?),
+            Err(_) => None,
+        };
+        self.progress = ProgressReporter::topic(&args)?;
+
+        let timeout = match args.get("timeout") {
+            Ok(_) => Some(args.get_duration("timeout")?),
+            Err(_) => None,
+        };
+        let max_redirects = match args.get("max-redirects") {
+            Ok(_) => Some(args.get_integer("max-redirects")? as usize),
+            Err(_) => None,
+        };
 
         self.client = Some(
-            reqwest::ClientBuilder::new()
-                .connection_verbose(true)
-                .user_agent("Snowcap")
-                .build()
+            init_data
+                .http_config()
+                .client(timeout, max_redirects, init_data.policy())
                 .map_err(|e| ModuleError::Internal(Box::new(e)))?,
         );
 
@@ -113,16 +310,18 @@ impl Module for HttpModule {
                 let client = self.client.as_ref().unwrap().clone();
                 let method = self.method.as_ref().unwrap().clone();
                 let url = self.url.as_ref().unwrap().clone();
+                let headers = self.headers.clone();
 
                 Task::perform(
                     async move {
                         let req = client
                             .request(method, url)
                             .header(header::ACCEPT, "*/*")
+                            .headers(headers)
                             .build()?;
                         Ok(HttpEvent::Request(req))
                     },
-                    |result: Result<HttpEvent, HttpError>| Message::from(result),
+                    event_message,
                 )
             }
 
@@ -134,65 +333,117 @@ impl Module for HttpModule {
 
                         Ok(HttpEvent::Response(response))
                     },
-                    |result: Result<HttpEvent, HttpError>| Message::from(result),
+                    event_message,
                 )
             }
 
-            HttpEvent::Response(response) => match response.headers().get(header::CONTENT_TYPE) {
-                Some(content_type) => {
-                    let url = self.url.clone().unwrap();
-
-                    debug!("Content Type: {content_type:?}");
-
-                    let mime: mime::Mime = content_type.to_str().unwrap().parse().unwrap();
-
-                    match mime.type_() {
-                        mime::IMAGE => Task::perform(
-                            async move {
-                                let bytes = response.bytes().await.map_err(HttpError::Reqwest)?;
-
-                                let data = HttpData {
-                                    url,
-                                    kind: ModuleDataKind::Image,
-                                    data: bytes.to_vec(),
-                                };
-
-                                Ok(HttpEvent::Data(data))
-                            },
-                            |result: Result<HttpEvent, HttpError>| Message::from(result),
-                        ),
-                        mime::TEXT => Task::perform(
-                            async move {
-                                let text = response.text().await.map_err(HttpError::Reqwest)?;
-
-                                let data = HttpData {
-                                    url,
-                                    kind: ModuleDataKind::Text,
-                                    data: text.as_bytes().to_vec(),
-                                };
-
-                                Ok(HttpEvent::Data(data))
-                            },
-                            |result: Result<HttpEvent, HttpError>| Message::from(result),
-                        ),
-                        _ => {
-                            error!("Unknown content type {content_type:?}");
-                            Task::none()
-                        }
-                    }
-                }
-                None => {
-                    error!("Content-type not provided in response");
-                    Task::none()
+            HttpEvent::Response(response) => {
+                let url = self.url.clone().unwrap();
+                let kind = match self.kind {
+                    Some(kind) => Some(kind.data_kind()),
+                    None => content_kind(response.headers()),
+                };
+
+                if let Some(topic) = self.progress.clone() {
+                    return download(response, url, kind, topic);
                 }
-            },
+
+                Task::perform(
+                    async move {
+                        // Text is decoded with the charset of the content type
+                        let data = match kind {
+                            Some(ModuleDataKind::Text) => response
+                                .text()
+                                .await
+                                .map_err(HttpError::Reqwest)?
+                                .into_bytes(),
+                            _ => response.bytes().await.map_err(HttpError::Reqwest)?.to_vec(),
+                        };
+
+                        // Data without a known content type is identified from its contents
+                        let kind = kind.unwrap_or_else(|| FileFormat::from_bytes(&data).into());
+
+                        Ok(HttpEvent::Data(HttpData {
+                            url,
+                            kind,
+                            data: Arc::new(data),
+                        }))
+                    },
+                    event_message,
+                )
+            }
+
+            HttpEvent::Data(data) if matches!(data.kind, ModuleDataKind::Unknown) => {
+                error!("Unsupported content of {}", data.url);
+                self.send_error("unsupported content type")
+            }
 
             HttpEvent::Data(data) => self.send_data(data),
+
+            HttpEvent::Failed(e) => {
+                error!("HTTP request failed: {e}");
+                self.send_error(e)
+            }
         }
     }
 
     fn on_message(&mut self, message: ModuleMessageData) -> Task<ModuleMessageData> {
-        println!("HTTP on_message {message:#?}");
+        debug!("HTTP on_message {message:?}");
         Task::none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{self, HeaderMap, HeaderValue};
+    use tracing_test::traced_test;
+
+    use super::{content_kind, request_headers, HttpKind, HttpModule};
+    use crate::module::{argument::ModuleArguments, data::ModuleDataKind, Module as _};
+
+    #[traced_test]
+    #[test]
+    fn response_kind() {
+        let kind = |content_type: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+            content_kind(&headers)
+        };
+
+        assert!(matches!(kind("image/png"), Some(ModuleDataKind::Image)));
+        assert!(matches!(kind("image/svg+xml"), Some(ModuleDataKind::Svg)));
+        assert!(matches!(
+            kind("text/plain; charset=utf-8"),
+            Some(ModuleDataKind::Text)
+        ));
+
+        // Unknown, invalid and missing content types are identified from the data
+        assert!(kind("application/octet-stream").is_none());
+        assert!(kind("not a mime type").is_none());
+        assert!(content_kind(&HeaderMap::new()).is_none());
+
+        assert!(matches!(HttpKind::Bytes.data_kind(), ModuleDataKind::Bytes));
+
+        let args = ModuleArguments::new().arg("url", r#""http://example.com""#);
+        assert!(HttpModule::validate_args(&args.clone().arg("kind", r#""svg""#)).is_ok());
+        assert!(HttpModule::validate_args(&args.clone().arg("kind", r#""json""#)).is_err());
+        assert!(HttpModule::validate_args(&args.clone().arg("timeout", "10s")).is_ok());
+        assert!(HttpModule::validate_args(&args.clone().arg("progress", r#""dl""#)).is_ok());
+        assert!(HttpModule::validate_args(&args.clone().arg("progress", "1")).is_err());
+        assert!(
+            HttpModule::validate_args(&args.clone().arg("max-redirects", r#""none""#)).is_err()
+        );
+
+        // Headers replace the default Accept header
+        let headers = args.clone().arg(
+            "headers",
+            r#"{Accept: "application/json", "X-Token": "abc"}"#,
+        );
+        assert!(HttpModule::validate_args(&headers).is_ok());
+        let headers = request_headers(&headers).unwrap();
+        assert_eq!(headers[header::ACCEPT], "application/json");
+        assert_eq!(headers["x-token"], "abc");
+        assert!(HttpModule::validate_args(&args.clone().arg("headers", r#""Accept""#)).is_err());
+        assert!(HttpModule::validate_args(&args.arg("headers", r#"{"Bad Name": "x"}"#)).is_err());
+    }
+}
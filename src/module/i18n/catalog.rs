@@ -0,0 +1,395 @@
+//! Translation catalogs in the Fluent and gettext formats
+//!
+//! Fluent catalogs (`.ftl`) map message ids to patterns. Lines indented below a message continue its pattern.
+//!
+//! ```text
+//! # Shown on the home page
+//! greeting = Hello, { $name }!
+//! farewell =
+//!     Goodbye,
+//!     see you soon
+//! ```
+//!
+//! gettext catalogs (`.po`) map each `msgid` to its `msgstr`, which may continue on following lines.
+//!
+//! ```text
+//! msgid "greeting"
+//! msgstr "Bonjour, { $name } !"
+//! ```
+//!
+//! Only simple messages are supported. Fluent terms, attributes and selectors, and gettext contexts are ignored,
+//! and the first plural form of a gettext message is used.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CatalogError {
+    #[error("unknown catalog format of {0}, expected .ftl or .po")]
+    UnknownFormat(PathBuf),
+
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    #[error("io error {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Format of the source of a [`Catalog`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    /// Fluent `.ftl` files
+    Fluent,
+    /// gettext `.po` files
+    Gettext,
+}
+
+impl CatalogFormat {
+    /// Get the format of a catalog file from its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "ftl" => Some(Self::Fluent),
+            "po" => Some(Self::Gettext),
+            _ => None,
+        }
+    }
+}
+
+/// Translated messages of a locale, by key
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// Load a catalog from a file, with the format of its extension
+    pub fn load(path: &Path) -> Result<Self, CatalogError> {
+        let format = CatalogFormat::from_path(path)
+            .ok_or_else(|| CatalogError::UnknownFormat(path.into()))?;
+        let source = std::fs::read_to_string(path)?;
+        Self::parse(&source, format)
+    }
+
+    /// Parse a catalog from its source
+    pub fn parse(source: &str, format: CatalogFormat) -> Result<Self, CatalogError> {
+        match format {
+            CatalogFormat::Fluent => Self::parse_fluent(source),
+            CatalogFormat::Gettext => Self::parse_gettext(source),
+        }
+    }
+
+    /// Get the message of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    /// Get the number of messages in the catalog
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns true if the catalog has no messages
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Add the messages of another catalog, replacing messages with the same key
+    pub fn extend(&mut self, other: Catalog) {
+        self.messages.extend(other.messages);
+    }
+
+    fn parse_fluent(source: &str) -> Result<Self, CatalogError> {
+        let mut messages = HashMap::new();
+
+        // Message receiving continuation lines, which is None within terms and attributes
+        let mut current: Option<(String, Vec<String>)> = None;
+
+        for (index, line) in source.lines().enumerate() {
+            let trimmed = line.trim();
+
+            if line.starts_with(char::is_whitespace) && !trimmed.is_empty() {
+                if trimmed.starts_with('.') {
+                    // Attributes end the pattern of the message
+                    Self::finish_fluent(&mut messages, current.take());
+                } else if let Some((_, lines)) = &mut current {
+                    lines.push(trimmed.to_string());
+                }
+                continue;
+            }
+
+            Self::finish_fluent(&mut messages, current.take());
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let Some((id, value)) = line.split_once('=') else {
+                return Err(CatalogError::Syntax {
+                    line: index + 1,
+                    message: format!("expected a message, found '{trimmed}'"),
+                });
+            };
+
+            let id = id.trim();
+
+            // Terms are only referenced by other messages
+            if id.starts_with('-') {
+                continue;
+            }
+
+            if id.is_empty()
+                || !id.starts_with(|c: char| c.is_ascii_alphabetic())
+                || !id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(CatalogError::Syntax {
+                    line: index + 1,
+                    message: format!("invalid message id '{id}'"),
+                });
+            }
+
+            let value = value.trim();
+            let lines = if value.is_empty() {
+                Vec::new()
+            } else {
+                vec![value.to_string()]
+            };
+            current = Some((id.to_string(), lines));
+        }
+
+        Self::finish_fluent(&mut messages, current);
+
+        Ok(Self { messages })
+    }
+
+    /// Add a Fluent message with its pattern lines joined, if it has a pattern
+    fn finish_fluent(
+        messages: &mut HashMap<String, String>,
+        message: Option<(String, Vec<String>)>,
+    ) {
+        if let Some((id, lines)) = message {
+            if !lines.is_empty() {
+                messages.insert(id, lines.join("\n"));
+            }
+        }
+    }
+
+    fn parse_gettext(source: &str) -> Result<Self, CatalogError> {
+        #[derive(PartialEq)]
+        enum Field {
+            None,
+            Context,
+            Id,
+            Plural,
+            Str,
+            OtherPlural,
+        }
+
+        let mut messages = HashMap::new();
+        let mut field = Field::None;
+        let mut id = String::new();
+        let mut translation = String::new();
+
+        let mut finish = |id: &mut String, translation: &mut String| {
+            // The header has an empty id, and untranslated messages an empty translation
+            if !id.is_empty() && !translation.is_empty() {
+                messages.insert(std::mem::take(id), std::mem::take(translation));
+            }
+            id.clear();
+            translation.clear();
+        };
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            let syntax = |message: String| CatalogError::Syntax {
+                line: index + 1,
+                message,
+            };
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, rest) = match line.split_once(char::is_whitespace) {
+                Some((keyword, rest)) if !line.starts_with('"') => (keyword, rest.trim()),
+                _ => ("", line),
+            };
+
+            let string = Self::unquote(rest)
+                .ok_or_else(|| syntax(format!("expected a string, found '{rest}'")))?;
+
+            match keyword {
+                "msgctxt" => {
+                    finish(&mut id, &mut translation);
+                    field = Field::Context;
+                }
+                "msgid" => {
+                    if field != Field::Context {
+                        finish(&mut id, &mut translation);
+                    }
+                    field = Field::Id;
+                    id.push_str(&string);
+                }
+                "msgid_plural" => field = Field::Plural,
+                "msgstr" | "msgstr[0]" => {
+                    field = Field::Str;
+                    translation.push_str(&string);
+                }
+                keyword if keyword.starts_with("msgstr[") => field = Field::OtherPlural,
+                "" => match field {
+                    Field::Id => id.push_str(&string),
+                    Field::Str => translation.push_str(&string),
+                    Field::None => return Err(syntax("string without a keyword".into())),
+                    _ => {}
+                },
+                keyword => return Err(syntax(format!("unknown keyword '{keyword}'"))),
+            }
+        }
+
+        finish(&mut id, &mut translation);
+
+        Ok(Self { messages })
+    }
+
+    /// Get the contents of a quoted gettext string, with escapes replaced
+    fn unquote(string: &str) -> Option<String> {
+        let inner = string.strip_prefix('"')?.strip_suffix('"')?;
+
+        let mut unquoted = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                unquoted.push(c);
+                continue;
+            }
+
+            match chars.next()? {
+                'n' => unquoted.push('\n'),
+                't' => unquoted.push('\t'),
+                c => unquoted.push(c),
+            }
+        }
+
+        Some(unquoted)
+    }
+}
+
+/// Replace the `{ $name }` placeables of a message with the values of variables. Placeables without a variable
+/// are kept.
+pub fn format(message: &str, vars: &HashMap<String, String>) -> String {
+    let mut formatted = String::with_capacity(message.len());
+    let mut rest = message;
+
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[..start]);
+
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+
+        let placeable = &rest[start..start + end + 1];
+        let name = placeable[1..placeable.len() - 1].trim();
+
+        match name
+            .strip_prefix('$')
+            .and_then(|name| vars.get(name.trim()))
+        {
+            Some(value) => formatted.push_str(value),
+            None => formatted.push_str(placeable),
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    formatted.push_str(rest);
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tracing_test::traced_test;
+
+    use super::{format, Catalog, CatalogFormat};
+
+    #[traced_test]
+    #[test]
+    fn fluent() {
+        let catalog = Catalog::parse(
+            r#"
+# Home page
+greeting = Hello, { $name }!
+farewell =
+    Goodbye,
+    see you soon
+-brand = Snowcap
+login = Log in
+    .title = Log in to your account
+"#,
+            CatalogFormat::Fluent,
+        )
+        .unwrap();
+
+        assert_eq!(catalog.len(), 3);
+        assert_eq!(catalog.get("greeting"), Some("Hello, { $name }!"));
+        assert_eq!(catalog.get("farewell"), Some("Goodbye,\nsee you soon"));
+        assert_eq!(catalog.get("login"), Some("Log in"));
+        assert_eq!(catalog.get("-brand"), None);
+
+        assert!(Catalog::parse("not a message", CatalogFormat::Fluent).is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn gettext() {
+        let catalog = Catalog::parse(
+            r#"
+msgid ""
+msgstr ""
+"Language: fr\n"
+
+# Home page
+msgid "greeting"
+msgstr "Bonjour, "
+"{ $name } !"
+
+msgid "untranslated"
+msgstr ""
+
+msgid "file"
+msgid_plural "files"
+msgstr[0] "fichier"
+msgstr[1] "fichiers"
+
+msgid "quote"
+msgstr "\"Salut\""
+"#,
+            CatalogFormat::Gettext,
+        )
+        .unwrap();
+
+        assert_eq!(catalog.len(), 3);
+        assert_eq!(catalog.get("greeting"), Some("Bonjour, { $name } !"));
+        assert_eq!(catalog.get("untranslated"), None);
+        assert_eq!(catalog.get("file"), Some("fichier"));
+        assert_eq!(catalog.get("quote"), Some("\"Salut\""));
+
+        assert!(Catalog::parse("msgid greeting", CatalogFormat::Gettext).is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn placeables() {
+        let vars = HashMap::from([("name".to_string(), "Ferris".to_string())]);
+
+        assert_eq!(format("Hello, { $name }!", &vars), "Hello, Ferris!");
+        assert_eq!(format("Hello, {$name}", &vars), "Hello, Ferris");
+        assert_eq!(format("{ $missing } left", &vars), "{ $missing } left");
+    }
+}
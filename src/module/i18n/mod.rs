@@ -0,0 +1,279 @@
+//! Internationalization module, displaying a message of the translation catalog of the active locale.
+//!
+//! ```text
+//! text(i18n!{key:"greeting", name:"Ferris", default:"Hello!"})
+//! ```
+//!
+//! Catalogs are loaded from Fluent or gettext files with [`crate::Snowcap::load_catalog()`] (see [`catalog`]).
+//! Arguments other than `key` and `default` are variables, replacing the `{ $name }` placeables of the message.
+//! A locale such as `fr-CA` falls back to the catalog of its language `fr`. Without a message in either, the
+//! `default` argument is displayed, or the key itself.
+//!
+//! Switching the locale with [`crate::Snowcap::set_locale()`] publishes it to the `i18n/locale` topic. Each
+//! instance sends its translated message, so only the nodes displaying translated strings are rebuilt.
+//!
+//! The active locale and the catalogs are the [`Locales`] of an engine, so engines in the same process can
+//! display different locales.
+
+pub mod catalog;
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use catalog::{Catalog, CatalogError};
+use iced::Task;
+use salish::Message;
+use tracing::{debug, warn};
+
+use crate::{
+    message::module::{ModuleMessageData, Topic, TopicMessage},
+    module::argument::ModuleArguments,
+    Value,
+};
+
+use super::{
+    data::TextData, error::ModuleError, internal::ModuleInternal, pubsub::publish, Module,
+    ModuleEvent, ModuleInitData,
+};
+
+/// Locale active before [`Locales::set_locale()`] is called
+const DEFAULT_LOCALE: &str = "en";
+
+/// Get the topic changes of the active locale and its catalog are published to
+pub fn topic() -> Topic {
+    Topic::new("i18n/locale")
+}
+
+/// Active locale and the loaded catalogs
+#[derive(Debug)]
+struct LocalesInner {
+    active: String,
+    catalogs: HashMap<String, Catalog>,
+}
+
+/// Active locale and the loaded catalogs of an engine. Clones share the same locale and catalogs.
+#[derive(Debug, Clone)]
+pub struct Locales(Arc<Mutex<LocalesInner>>);
+
+impl Default for Locales {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(LocalesInner {
+            active: DEFAULT_LOCALE.to_string(),
+            catalogs: HashMap::new(),
+        })))
+    }
+}
+
+impl Locales {
+    /// Get the active locale
+    pub fn locale(&self) -> String {
+        match self.0.lock() {
+            Ok(locales) => locales.active.clone(),
+            Err(_) => DEFAULT_LOCALE.to_string(),
+        }
+    }
+
+    /// Set the active locale, and get a [`Task`] updating the instances with the messages of its catalog
+    pub fn set_locale(&self, locale: &str) -> Task<Message> {
+        match self.0.lock() {
+            Ok(mut locales) => locales.active = locale.to_string(),
+            Err(e) => {
+                warn!("Locales poisoned: {e}");
+                return Task::none();
+            }
+        }
+
+        debug!("Locale set to {locale}");
+        publish(
+            topic(),
+            TopicMessage::Value(Value::new_string(locale.to_string())),
+        )
+    }
+
+    /// Add the messages of a catalog to a locale, and get a [`Task`] updating the instances if the locale is
+    /// active
+    pub fn add_catalog(&self, locale: &str, catalog: Catalog) -> Task<Message> {
+        let active = match self.0.lock() {
+            Ok(mut locales) => {
+                locales
+                    .catalogs
+                    .entry(locale.to_string())
+                    .or_default()
+                    .extend(catalog);
+                locales.active.clone()
+            }
+            Err(e) => {
+                warn!("Locales poisoned: {e}");
+                return Task::none();
+            }
+        };
+
+        if active == locale || language(&active) == locale {
+            publish(topic(), TopicMessage::Value(Value::new_string(active)))
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Load a Fluent `.ftl` or gettext `.po` catalog file for a locale, see [`Locales::add_catalog()`]
+    pub fn load_catalog(&self, locale: &str, path: &Path) -> Result<Task<Message>, CatalogError> {
+        let catalog = Catalog::load(path)?;
+        debug!(
+            "Loaded {} messages for {locale} from {path:?}",
+            catalog.len()
+        );
+        Ok(self.add_catalog(locale, catalog))
+    }
+
+    /// Get the message of a key in the active locale, or in the catalog of its language, with the variables
+    /// replaced
+    pub fn translate(&self, key: &str, vars: &HashMap<String, String>) -> Option<String> {
+        let locales = self.0.lock().ok()?;
+
+        [locales.active.as_str(), language(&locales.active)]
+            .iter()
+            .find_map(|locale| locales.catalogs.get(*locale)?.get(key))
+            .map(|message| catalog::format(message, vars))
+    }
+}
+
+/// Get the language of a locale, such as `fr` for `fr-CA` or `fr_CA`
+pub(crate) fn language(locale: &str) -> &str {
+    locale.split(['-', '_']).next().unwrap_or(locale)
+}
+
+#[derive(Debug)]
+pub enum I18nEvent {
+    Init,
+}
+impl ModuleEvent for I18nEvent {}
+
+#[derive(Debug, Default)]
+pub struct I18nModule {
+    key: String,
+    default: Option<String>,
+    vars: HashMap<String, String>,
+    locales: Locales,
+}
+
+impl I18nModule {
+    /// Get the data of the message in the active locale
+    fn text(&self) -> TextData {
+        let text = self
+            .locales
+            .translate(&self.key, &self.vars)
+            .or_else(|| self.default.clone())
+            .unwrap_or_else(|| self.key.clone());
+        TextData::new(text)
+    }
+}
+
+#[async_trait]
+impl Module for I18nModule {
+    type Event = I18nEvent;
+    type Data = TextData;
+
+    async fn init(
+        &mut self,
+        args: ModuleArguments,
+        init_data: ModuleInitData,
+    ) -> Result<Self::Event, ModuleError> {
+        self.key = args.get("key")?.to_string();
+        self.locales = init_data.locales().clone();
+
+        for arg in args.sort() {
+            let value: Cow<'_, str> = arg.value().inner().into();
+            match arg.name().as_str() {
+                "key" => {}
+                "default" => self.default = Some(value.into_owned()),
+                name => {
+                    self.vars.insert(name.to_string(), value.into_owned());
+                }
+            }
+        }
+
+        Ok(I18nEvent::Init)
+    }
+
+    fn on_event(&mut self, event: Self::Event) -> Task<Message> {
+        match event {
+            I18nEvent::Init => {
+                Task::done(Message::broadcast(ModuleMessageData::Subscribe(topic())))
+                    .chain(self.send_data(self.text()))
+            }
+        }
+    }
+
+    fn on_subscription(&mut self, topic: Topic, message: TopicMessage) -> Task<Message> {
+        match message {
+            TopicMessage::Value(locale) => {
+                debug!("Translating {} to {locale}", self.key);
+                self.send_data(self.text())
+            }
+            _ => {
+                debug!("Ignoring message on {topic}");
+                Task::none()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tracing_test::traced_test;
+
+    use super::{
+        catalog::{Catalog, CatalogFormat},
+        language, I18nModule, Locales,
+    };
+    use crate::module::{argument::ModuleArguments, testing::TestBed};
+
+    #[traced_test]
+    #[test]
+    fn translate_locales() {
+        let catalog =
+            Catalog::parse("i18n-test = Bonjour, { $name }", CatalogFormat::Fluent).unwrap();
+        let locales = Locales::default();
+        let _ = locales.add_catalog("fr", catalog.clone());
+        let vars = HashMap::from([("name".to_string(), "Ferris".to_string())]);
+
+        let _ = locales.set_locale("fr-CA");
+        assert_eq!(language("fr-CA"), "fr");
+        assert_eq!(
+            locales.translate("i18n-test", &vars),
+            Some("Bonjour, Ferris".into())
+        );
+
+        // Locales of other engines aren't affected
+        assert_eq!(Locales::default().locale(), "en");
+
+        let _ = locales.set_locale("de");
+        assert_eq!(locales.translate("i18n-test", &vars), None);
+
+        let args = ModuleArguments::new()
+            .arg("key", r#""i18n-test""#)
+            .arg("default", r#""Hello""#);
+        let mut bed = TestBed::<I18nModule>::new(args).unwrap();
+        bed.run();
+
+        assert_eq!(bed.data()[0], b"Hello");
+
+        // The instance translates with the locales of its manager
+        let args = ModuleArguments::new()
+            .arg("key", r#""i18n-test""#)
+            .arg("name", r#""Ferris""#);
+        let mut bed = TestBed::<I18nModule>::new(args).unwrap();
+        let _ = bed.manager().locales().add_catalog("fr", catalog);
+        let _ = bed.manager().locales().set_locale("fr");
+        bed.run();
+
+        assert_eq!(bed.data()[0], b"Bonjour, Ferris");
+    }
+}
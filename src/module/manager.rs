@@ -9,25 +9,171 @@
 //! ```ignore
 //! snowcap.modules().register::<MyModule>("custom-module");
 //! ```
-
-use std::{any::Any, collections::HashMap, sync::Arc};
+//!
+//! The live module instances, with their arguments, bound nodes and [`ModuleStatus`], can be listed with [`ModuleManager::instances()`].
+
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    hash::{Hash as _, Hasher as _},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use arbutus::{TreeNode as _, TreeNodeRef as _};
 use iced::Task;
-use salish::{endpoint::Endpoint, filter::SourceFilter, router::MessageRouter, Message};
+use parking_lot::Mutex;
+use salish::{
+    endpoint::Endpoint, filter::SourceFilter, message::Destination, router::MessageRouter,
+    EndpointAddress as _, Message,
+};
 use tracing::{debug, error, warn};
+use xxhash_rust::xxh64::Xxh64;
 
 use crate::{
-    message::module::Topic,
-    module::{argument::ModuleArguments, data::ModuleData},
+    message::module::{ModuleMessageData, PublishMessage, Topic, TopicMessage},
+    metrics,
+    module::{
+        argument::ModuleArguments,
+        data::{ModuleData, ModuleDataKind},
+        http::HttpConfig,
+        i18n::Locales,
+        output::OutputPipeline,
+        policy::ModulePolicy,
+        selector::{DataSelector, SELECTOR_ARGUMENTS},
+        state::StateStore,
+        window::WindowSize,
+        DIAGNOSTICS_TOPIC,
+    },
+    telemetry::{self, TelemetryEvent},
     NodeId, NodeRef, Source,
 };
 
 use super::{
-    dispatch::ModuleDispatch, error::ModuleError, internal::ModuleInit, registry::ModuleRegistry,
-    Module, ModuleHandleId,
+    dispatch::{ModuleDispatch, ShutdownFn},
+    error::ModuleError,
+    internal::ModuleInit,
+    registry::ModuleRegistry,
+    Module, ModuleHandleId, ModuleInitData,
 };
 
+/// Module data forwarded from the data endpoint of a module instance to each consumer endpoint
+#[derive(Debug, Clone)]
+pub(crate) struct ConsumerData(Arc<dyn ModuleData>);
+
+/// Tree nodes consuming the data of a single module instance.
+///
+/// Data sent by the module is received by `data_endpoint` and forwarded to an endpoint
+/// for each consuming node, which selects its field and updates the node.
+struct ModuleConsumers {
+    /// Addresses of the consumer endpoints, shared with the data endpoint closure
+    addrs: Arc<Mutex<Vec<u64>>>,
+
+    /// Most recent data sent by the module instance, set on consumers when they connect
+    last: Arc<Mutex<Option<Arc<dyn ModuleData>>>>,
+
+    /// Endpoints updating each consuming node
+    endpoints: Vec<(
+        NodeId,
+        Endpoint<'static, ConsumerData, Task<crate::Message>, Source>,
+    )>,
+
+    /// Endpoint receiving data from the module instance
+    _data_endpoint: Endpoint<'static, Box<dyn ModuleData>, Task<crate::Message>, Source>,
+}
+
+impl ModuleConsumers {
+    /// Drop the endpoints of consuming nodes for which `exists` returns false
+    fn disconnect(&mut self, exists: &mut impl FnMut(NodeId) -> bool) {
+        let mut addrs = self.addrs.lock();
+
+        self.endpoints.retain(|(node_id, endpoint)| {
+            let keep = exists(*node_id);
+            if !keep {
+                addrs.retain(|addr| *addr != endpoint.addr());
+            }
+            keep
+        });
+    }
+}
+
+/// Status of a module instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleStatus {
+    /// The module is started, and [`Module::init()`] hasn't completed
+    Initializing,
+    /// The module initialized and is running
+    Running,
+    /// The module sent an error, which is the most recent data it sent
+    Error(String),
+}
+
+impl std::fmt::Display for ModuleStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModuleStatus::Initializing => f.write_str("initializing"),
+            ModuleStatus::Running => f.write_str("running"),
+            ModuleStatus::Error(e) => write!(f, "error: {e}"),
+        }
+    }
+}
+
+/// Description of a live module instance, returned by [`ModuleManager::instances()`]
+#[derive(Debug, Clone)]
+pub struct ModuleInstance {
+    pub handle_id: ModuleHandleId,
+    pub name: String,
+    pub args: ModuleArguments,
+    /// Tree nodes bound to this instance
+    pub nodes: Vec<NodeId>,
+    pub status: ModuleStatus,
+}
+
+/// Name, arguments and status of a module instance, kept for inspection
+struct InstanceInfo {
+    name: String,
+    args: ModuleArguments,
+    status: Arc<Mutex<ModuleStatus>>,
+}
+
+/// Shutdown closures of each module instance. This is cloned into the command endpoint of the
+/// [`crate::Snowcap`] engine, to notify modules of shutdown before exiting.
+#[derive(Clone, Default)]
+pub(crate) struct ShutdownHooks(Arc<Mutex<HashMap<ModuleHandleId, ShutdownFn>>>);
+
+impl ShutdownHooks {
+    fn insert(&self, handle_id: ModuleHandleId, shutdown: ShutdownFn) {
+        self.0.lock().insert(handle_id, shutdown);
+    }
+
+    fn remove(&self, handle_id: ModuleHandleId) -> Option<ShutdownFn> {
+        self.0.lock().remove(&handle_id)
+    }
+
+    /// Notify every module instance of shutdown, and get a [`Task`] which completes once the
+    /// shutdown tasks of all modules have completed, or are aborted after `timeout`.
+    pub fn drain(&self, timeout: Duration) -> Task<Message> {
+        let tasks: Vec<Task<Message>> = self.0.lock().values().map(|shutdown| shutdown()).collect();
+
+        debug!("Draining shutdown tasks of {} modules", tasks.len());
+
+        let (drain, drain_handle) = Task::batch(tasks).abortable();
+        let (timer, timer_handle) = Task::future(tokio::time::sleep(timeout)).abortable();
+
+        // Abort the module shutdown tasks if they haven't completed before the timeout
+        let timer = timer.then(move |_| {
+            warn!("Module shutdown timed out after {timeout:?}, aborting");
+            drain_handle.abort();
+            Task::none()
+        });
+
+        // Cancel the timer once the module shutdown tasks have completed
+        let drain = drain.chain(Task::future(async move { timer_handle.abort() }).discard());
+
+        Task::batch([timer, drain])
+    }
+}
+
 /// Manages dynamic dispatch of messages between the [`crate::Snowcap`] engine and module instances.
 /// Allows for registration of modules with the global [`ModuleRegistry`].
 pub struct ModuleManager {
@@ -35,23 +181,54 @@ pub struct ModuleManager {
     /// for dispatching event messages with type erasure
     dispatchers: HashMap<ModuleHandleId, ModuleDispatch>,
 
-    /// Channel subscriptions. Each [`Topic`] key has a [`Vec`] of [`ModuleHandleId`]
-    /// to manage a list of handles to forward each published message to.
-    ///
-    /// TODO: Move pubsub to the [`MessageRouter`]
-    subscriptions: HashMap<Topic, Vec<ModuleHandleId>>,
-
     /// Map of [`ModuleHandleId`] to [`NodeId`], for dispatching module data to nodes
     nodes: HashMap<ModuleHandleId, NodeId>,
 
     /// The [`salish::MessageRouter`] for acquiring new [`Endpoint`] instances
     router: MessageRouter<'static, Task<salish::message::Message>, Source>,
 
-    /// Salish message endpoint to receive ModuleMessage for each instantiated module
-    data_endpoints: HashMap<
-        ModuleHandleId,
-        Endpoint<'static, Box<dyn ModuleData>, Task<crate::Message>, Source>,
-    >,
+    /// Consumers of the data of each instantiated module
+    consumers: HashMap<ModuleHandleId, ModuleConsumers>,
+
+    /// Module instances declared with an `id` argument, which are shared by every node referencing the same id
+    named: HashMap<String, ModuleHandleId>,
+
+    /// Module instances keyed by module name and the hash of their arguments, so identical
+    /// invocations in different nodes share a single instance
+    instances: HashMap<(String, u64), ModuleHandleId>,
+
+    /// Nodes deriving data from a named module instance which hasn't been instantiated yet
+    pending: HashMap<String, Vec<(NodeRef, DataSelector)>>,
+
+    /// Shutdown closures of each module instance
+    shutdown: ShutdownHooks,
+
+    /// Output pipeline of each module instance, from its `transform` and `format` arguments
+    outputs: HashMap<ModuleHandleId, OutputPipeline>,
+
+    /// Tree nodes referencing each module instance. An instance is torn down once all of its nodes are removed.
+    owners: HashMap<ModuleHandleId, HashSet<NodeId>>,
+
+    /// Last visibility each module instance was notified of. Instances are visible until notified otherwise.
+    visibility: HashMap<ModuleHandleId, bool>,
+
+    /// Name, arguments and status of each module instance
+    info: HashMap<ModuleHandleId, InstanceInfo>,
+
+    /// Restrictions checked before instantiating a module
+    policy: ModulePolicy,
+
+    /// State store shared by the module instances and widgets of the engine
+    state: StateStore,
+
+    /// Active locale and translation catalogs of the engine
+    locales: Locales,
+
+    /// Configuration of the client of the http module
+    http_config: Arc<HttpConfig>,
+
+    /// Last known size of the window of the engine
+    window_size: WindowSize,
 
     _ep: Vec<Box<dyn Any>>,
 }
@@ -69,9 +246,21 @@ impl ModuleManager {
 
         let mut manager = Self {
             dispatchers: HashMap::new(),
-            subscriptions: HashMap::new(),
             nodes: HashMap::new(),
-            data_endpoints: HashMap::new(),
+            consumers: HashMap::new(),
+            named: HashMap::new(),
+            instances: HashMap::new(),
+            pending: HashMap::new(),
+            shutdown: ShutdownHooks::default(),
+            outputs: HashMap::new(),
+            owners: HashMap::new(),
+            visibility: HashMap::new(),
+            info: HashMap::new(),
+            policy: ModulePolicy::default(),
+            state: StateStore::default(),
+            locales: Locales::default(),
+            http_config: Arc::default(),
+            window_size: WindowSize::default(),
             router,
             _ep: Vec::new(),
         };
@@ -96,41 +285,134 @@ impl ModuleManager {
         */
     }
 
-    /// Register a module with the global [`ModuleRegistry`]
-    pub fn register<T: ModuleInit + Module>(&self, name: &str) {
-        ModuleRegistry::register::<T>(name);
+    /// Get the [`ShutdownHooks`] of all module instances
+    pub(crate) fn shutdown_hooks(&self) -> ShutdownHooks {
+        self.shutdown.clone()
+    }
+
+    /// Set the [`ModulePolicy`] checked before instantiating modules. Running instances are not affected.
+    pub fn set_policy(&mut self, policy: ModulePolicy) {
+        self.policy = policy;
+    }
+
+    /// Get the [`ModulePolicy`] checked before instantiating modules
+    pub fn policy(&self) -> &ModulePolicy {
+        &self.policy
+    }
+
+    /// Get the [`StateStore`] of the engine
+    pub fn state(&self) -> &StateStore {
+        &self.state
+    }
+
+    /// Set the [`HttpConfig`] of the http module. Running instances are not affected.
+    pub fn set_http_config(&mut self, config: HttpConfig) {
+        self.http_config = Arc::new(config);
+    }
+
+    /// Get the [`Locales`] of the engine
+    pub fn locales(&self) -> &Locales {
+        &self.locales
+    }
+
+    /// Get the [`WindowSize`] of the engine, updated by the window events it receives
+    pub fn window_size(&self) -> &WindowSize {
+        &self.window_size
+    }
+
+    /// Get the [`ModuleInitData`] passed to each module instance when it's started
+    fn init_data(&self) -> ModuleInitData {
+        ModuleInitData {
+            state: self.state.clone(),
+            locales: self.locales.clone(),
+            http_config: self.http_config.clone(),
+            window_size: self.window_size.clone(),
+            policy: self.policy.clone(),
+        }
+    }
+
+    /// Register a module with the global [`ModuleRegistry`]. Names may be namespaced with dots
+    /// (`mycompany.weather`), and registering a name already taken by another module type fails.
+    pub fn register<T: ModuleInit + Module>(&self, name: &str) -> Result<(), ModuleError> {
+        ModuleRegistry::register::<T>(name)
     }
 
     /// Register all internal modules with the registry
     fn register_internal() {
-        ModuleRegistry::register::<super::file::FileModule>("file");
-        ModuleRegistry::register::<super::http::HttpModule>("http");
-        ModuleRegistry::register::<super::timing::TimingModule>("timing");
-        ModuleRegistry::register::<super::sub::SubModule>("sub");
+        let registered = [
+            ModuleRegistry::register::<super::file::FileModule>("file"),
+            ModuleRegistry::register::<super::http::HttpModule>("http"),
+            ModuleRegistry::register::<super::timing::TimingModule>("timing"),
+            ModuleRegistry::register::<super::sub::SubModule>("sub"),
+            ModuleRegistry::register::<super::state::StateModule>("state"),
+            ModuleRegistry::register::<super::window::WindowModule>("window"),
+            ModuleRegistry::register::<super::i18n::I18nModule>("i18n"),
+            ModuleRegistry::register::<super::format::FormatModule>(super::format::FORMAT_MODULE),
+            #[cfg(feature = "scripting")]
+            ModuleRegistry::register::<super::script::ScriptModule>("script"),
+        ];
+
+        for result in registered {
+            if let Err(e) = result {
+                error!("Failed to register internal module: {e}");
+            }
+        }
 
-        println!("{}", ModuleRegistry);
+        debug!("Registered modules\n{}", ModuleRegistry);
     }
 
     /// Create a new module instance, start it, and return a tuple of the [`ModuleHandleId`] and init [`iced::Task`]
+    ///
+    /// If the arguments contain an `id` which matches an existing instance, the existing [`ModuleHandleId`]
+    /// is returned with an empty [`iced::Task`], so multiple nodes can consume data from a single instance.
+    ///
+    /// Unnamed instances are deduplicated by module name and arguments. An invocation identical to a running
+    /// instance, ignoring the `field` and `expr` arguments which only select data for the consuming node,
+    /// shares the existing instance rather than starting another.
+    ///
+    /// Returns [`ModuleError::Denied`] if the module or its arguments aren't permitted by the [`ModulePolicy`].
     pub fn instantiate(
         &mut self,
         name: &String,
         args: ModuleArguments,
     ) -> Result<(ModuleHandleId, Task<Message>), ModuleError> {
+        self.policy.check(name, &args)?;
+
+        let instance_id = args.get("id").ok().map(|id| id.to_string());
+
+        if let Some(handle_id) = instance_id.as_ref().and_then(|id| self.named.get(id)) {
+            debug!(
+                "Module '{name}' id {instance_id:?} already instantiated with handle {handle_id}"
+            );
+            return Ok((*handle_id, Task::none()));
+        }
+
+        let instance_key = (name.clone(), Self::instance_hash(&args));
+
+        if instance_id.is_none() {
+            if let Some(handle_id) = self.instances.get(&instance_key) {
+                debug!("Module '{name}' sharing instance {handle_id} with identical arguments");
+                return Ok((*handle_id, Task::none()));
+            }
+        }
+
+        let output = OutputPipeline::from_args(&args)?;
+
         let name = name.clone();
 
         // Clone the router to move into the closure
         let router = self.router.clone();
+        let init_data = self.init_data();
 
         // Get the descriptor from the [`ModuleRegistry']
-        ModuleRegistry::get(&name, move |descriptor| {
+        let (handle_id, task) = ModuleRegistry::get(&name, |descriptor| {
             // Create a new instance of the module and get a type erased [`ModuleDispatch`] handle
             // to proxy into internal module methods.
             let mut dispatch = (descriptor.new)(router);
 
             // Get the init Task of the module, which calls back to the async [`Module::init()`] method
             // of the [`Module`] implementation for the requested module name.
-            let task = dispatch.start(&args);
+            let task = dispatch.start(&args, init_data);
 
             // Get the handle ID
             let handle_id = dispatch.handle_id();
@@ -152,35 +434,379 @@ impl ModuleManager {
             self.endpoints.insert(handle_id, module_endpoint);
             */
 
+            // Register the shutdown hook of this module instance
+            self.shutdown.insert(handle_id, dispatch.shutdown_fn());
+
             // Register this module instance dispatcher with the manager
             self.dispatchers.insert(dispatch.handle_id(), dispatch);
 
             Ok((handle_id, task))
-        })
+        })?;
+
+        if let Some(instance_id) = instance_id {
+            // Connect any nodes which were waiting on this instance. The new instance hasn't
+            // sent any data yet, so there is nothing to replay to them.
+            for (noderef, selector) in self.pending.remove(&instance_id).unwrap_or_default() {
+                let _ = self.connect_node(handle_id, noderef, selector);
+            }
+
+            self.named.insert(instance_id, handle_id);
+        } else {
+            self.instances.insert(instance_key, handle_id);
+        }
+
+        if !output.is_empty() {
+            self.outputs.insert(handle_id, output);
+        }
+
+        // The instance is running once the init task has completed
+        let status = Arc::new(Mutex::new(ModuleStatus::Initializing));
+        let init_status = status.clone();
+        let init_name = name.clone();
+        let init_start = Instant::now();
+        let task = task.chain(
+            Task::future(async move {
+                // Keep an error sent by the module while it was initializing
+                let mut status = init_status.lock();
+                if *status == ModuleStatus::Initializing {
+                    *status = ModuleStatus::Running;
+                    metrics::observe_latency(
+                        metrics::MODULE_INIT,
+                        Some(("module", &init_name)),
+                        init_start.elapsed(),
+                    );
+                }
+            })
+            .discard(),
+        );
+
+        self.info
+            .insert(handle_id, InstanceInfo { name, args, status });
+
+        Ok((handle_id, task))
     }
 
-    /// Subscribe a module to a [`Topic`]
-    fn subscribe(&mut self, handle_id: ModuleHandleId, channel: &Topic) {
-        debug!("Module HandleId {} subscribed to {:?}", handle_id, channel);
+    /// Hash the arguments configuring a module instance, excluding the [`SELECTOR_ARGUMENTS`]
+    fn instance_hash(args: &ModuleArguments) -> u64 {
+        let mut hasher = Xxh64::new(0);
+        for arg in args
+            .sort()
+            .iter()
+            .filter(|arg| !SELECTOR_ARGUMENTS.contains(&arg.name().as_str()))
+        {
+            arg.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
 
-        self.subscriptions
-            .entry(channel.clone())
-            .or_insert(Vec::new())
-            .push(handle_id);
+    /// Get the [`OutputPipeline`] applied to data sent by a module instance
+    pub fn output_pipeline(&self, handle_id: ModuleHandleId) -> OutputPipeline {
+        self.outputs
+            .get(&handle_id)
+            .cloned()
+            .unwrap_or_default()
+            .with_locales(self.locales.clone())
     }
 
-    pub fn connect_node(&mut self, handle_id: ModuleHandleId, mut noderef: NodeRef) {
-        // Create a data endpoint for this module which updates tree node data
-        let data_endpoint = self
-            .router
-            .create_endpoint::<Box<dyn ModuleData>>()
-            .filter(SourceFilter::default().add(Source::Module(handle_id)))
-            .message(move |source, message| {
-                noderef.node_mut().data_mut().set_module_data(message);
+    /// Connect a tree node as a consumer of data from a module instance.
+    ///
+    /// Each consumer gets its own endpoint, and the [`DataSelector`] selects the part of the module
+    /// data which is set on the node (see [`ModuleData::field()`]). Nodes are only marked
+    /// dirty when the data they consume has changed.
+    ///
+    /// Returns a [`Task`] replaying the most recent data of the instance to the new consumer,
+    /// as instances may be shared with nodes which connected earlier.
+    pub fn connect_node(
+        &mut self,
+        handle_id: ModuleHandleId,
+        mut noderef: NodeRef,
+        selector: DataSelector,
+    ) -> Task<Message> {
+        let node_id = noderef.node().id();
+
+        let consumer_endpoint = self.router.create_endpoint::<ConsumerData>().message(
+            move |_source, ConsumerData(data)| {
+                let data = match selector.select(data) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        warn!("Module {handle_id} data not selected for node: {e}");
+                        return Task::none();
+                    }
+                };
+
+                let mut node = noderef.node_mut();
+
+                // Skip updating the node if the consumed data hasn't changed
+                let unchanged = match (node.data().module_data(), data.bytes()) {
+                    (Some(current), Ok(bytes)) => current.bytes().is_ok_and(|b| b == bytes),
+                    _ => false,
+                };
+
+                if !unchanged {
+                    node.data_mut().set_module_data(data);
+                }
+
                 Task::none()
-            });
+            },
+        );
 
-        self.data_endpoints.insert(handle_id, data_endpoint);
+        let router = self.router.clone();
+        let output = self.output_pipeline(handle_id);
+        let status = self.info.get(&handle_id).map(|info| info.status.clone());
+        let consumers = self.consumers.entry(handle_id).or_insert_with(|| {
+            let addrs: Arc<Mutex<Vec<u64>>> = Arc::default();
+            let data_addrs = addrs.clone();
+
+            let last: Arc<Mutex<Option<Arc<dyn ModuleData>>>> = Arc::default();
+            let data_last = last.clone();
+
+            // Create a data endpoint for this module which forwards data to each consumer
+            let data_endpoint = router
+                .create_endpoint::<Box<dyn ModuleData>>()
+                .filter(SourceFilter::default().add(Source::Module(handle_id)))
+                .message(move |_source, data| {
+                    metrics::increment(metrics::MESSAGES, Some(("type", "module_data")));
+
+                    // Publish module errors to the diagnostics topic
+                    let diagnostics = match data.kind() {
+                        ModuleDataKind::Error => {
+                            let error = data
+                                .bytes()
+                                .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+                                .unwrap_or_default();
+
+                            warn!(handle_id, "Module failed: {error}");
+                            telemetry::emit(TelemetryEvent::ModuleFailed {
+                                handle_id,
+                                error: error.clone(),
+                            });
+
+                            if let Some(status) = &status {
+                                *status.lock() = ModuleStatus::Error(error.clone());
+                            }
+
+                            Task::done(Message::broadcast(ModuleMessageData::Publish(
+                                PublishMessage {
+                                    topic: Topic::new(DIAGNOSTICS_TOPIC),
+                                    message: TopicMessage::String(format!(
+                                        "module {handle_id}: {error}"
+                                    )),
+                                },
+                            )))
+                        }
+                        _ => {
+                            if let Some(status) = &status {
+                                *status.lock() = ModuleStatus::Running;
+                            }
+                            Task::none()
+                        }
+                    };
+
+                    // Shape the module output with the transform and format arguments of the instance
+                    let data = match output.apply(data) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            warn!("Module {handle_id} output transform failed: {e}");
+                            return diagnostics;
+                        }
+                    };
+
+                    let data: Arc<dyn ModuleData> = Arc::from(data);
+
+                    // Keep the data for consumers connected later
+                    *data_last.lock() = Some(data.clone());
+
+                    Task::batch(
+                        data_addrs
+                            .lock()
+                            .iter()
+                            .map(|addr| {
+                                Task::done(
+                                    Message::unicast(ConsumerData(data.clone()))
+                                        .with_dest(Destination::Endpoint(*addr)),
+                                )
+                            })
+                            .chain(std::iter::once(diagnostics)),
+                    )
+                });
+
+            ModuleConsumers {
+                addrs,
+                last,
+                endpoints: Vec::new(),
+                _data_endpoint: data_endpoint,
+            }
+        });
+
+        let addr = consumer_endpoint.addr();
+        consumers.addrs.lock().push(addr);
+        consumers.endpoints.push((node_id, consumer_endpoint));
+
+        // A shared instance may have sent data before this node was connected,
+        // so replay the most recent data to the new consumer
+        let replay = match consumers.last.lock().clone() {
+            Some(data) => Task::done(
+                Message::unicast(ConsumerData(data)).with_dest(Destination::Endpoint(addr)),
+            ),
+            None => Task::none(),
+        };
+
+        self.attach_node(handle_id, node_id);
+
+        replay
+    }
+
+    /// Record a tree node referencing a module instance without consuming its data, such as
+    /// the node of an attribute module. The instance is kept alive while the node is in the tree.
+    pub fn attach_node(&mut self, handle_id: ModuleHandleId, node_id: NodeId) {
+        self.owners.entry(handle_id).or_default().insert(node_id);
+    }
+
+    /// Release module instances referenced by nodes which were removed from the tree.
+    ///
+    /// `exists` is called with each node referencing a module, and returns false if the node has been removed.
+    /// Consumer endpoints of removed nodes are dropped, and instances with no remaining nodes are torn down.
+    /// Returns a [`Task`] running the [`Module::on_shutdown()`] tasks of the released instances.
+    pub fn release_nodes(&mut self, mut exists: impl FnMut(NodeId) -> bool) -> Task<Message> {
+        // Forget derived nodes which were waiting on a named instance
+        for waiting in self.pending.values_mut() {
+            waiting.retain(|(noderef, _)| exists(noderef.node().id()));
+        }
+        self.pending.retain(|_, waiting| !waiting.is_empty());
+
+        for consumers in self.consumers.values_mut() {
+            consumers.disconnect(&mut exists);
+        }
+
+        let released: Vec<ModuleHandleId> = self
+            .owners
+            .iter_mut()
+            .filter_map(|(handle_id, nodes)| {
+                nodes.retain(|node_id| exists(*node_id));
+                nodes.is_empty().then_some(*handle_id)
+            })
+            .collect();
+
+        Task::batch(
+            released
+                .into_iter()
+                .map(|handle_id| self.teardown(handle_id)),
+        )
+    }
+
+    /// Tear down a module instance. The module is notified with [`Module::on_shutdown()`], and its
+    /// dispatcher and endpoints are dropped, which aborts any tasks the module still has running.
+    fn teardown(&mut self, handle_id: ModuleHandleId) -> Task<Message> {
+        debug!(handle_id, "Tearing down module instance");
+        telemetry::emit(TelemetryEvent::ModuleReleased { handle_id });
+
+        // Notify the module first, which also stops dispatching events to it
+        let task = self
+            .shutdown
+            .remove(handle_id)
+            .map(|shutdown| shutdown())
+            .unwrap_or_else(Task::none);
+
+        self.dispatchers.remove(&handle_id);
+        self.consumers.remove(&handle_id);
+        self.outputs.remove(&handle_id);
+        self.owners.remove(&handle_id);
+        self.visibility.remove(&handle_id);
+        self.nodes.remove(&handle_id);
+        self.named.retain(|_, named| *named != handle_id);
+        self.instances.retain(|_, instance| *instance != handle_id);
+        self.info.remove(&handle_id);
+
+        task
+    }
+
+    /// Update the visibility of module instances. `is_visible` is called with each node referencing a module,
+    /// and an instance is visible while any of its nodes is visible. Instances whose visibility changed are
+    /// notified with [`Module::on_visibility()`], and the returned [`Task`] runs their tasks.
+    pub fn update_visibility(
+        &mut self,
+        mut is_visible: impl FnMut(NodeId) -> bool,
+    ) -> Task<Message> {
+        let visibility: Vec<(ModuleHandleId, bool)> = self
+            .owners
+            .iter()
+            .map(|(handle_id, nodes)| {
+                (*handle_id, nodes.iter().any(|node_id| is_visible(*node_id)))
+            })
+            .collect();
+
+        Task::batch(
+            visibility
+                .into_iter()
+                .map(|(handle_id, visible)| self.set_visible(handle_id, visible)),
+        )
+    }
+
+    /// Set the visibility of a module instance, notifying it with [`Module::on_visibility()`] if it changed
+    pub fn set_visible(&mut self, handle_id: ModuleHandleId, visible: bool) -> Task<Message> {
+        let previous = self.visibility.insert(handle_id, visible).unwrap_or(true);
+        if previous == visible {
+            return Task::none();
+        }
+
+        debug!("Module instance {handle_id} visible={visible}");
+
+        self.dispatchers
+            .get(&handle_id)
+            .map(|dispatch| dispatch.set_visible(visible))
+            .unwrap_or_else(Task::none)
+    }
+
+    /// Get the number of running module instances
+    pub fn instance_count(&self) -> usize {
+        self.dispatchers.len()
+    }
+
+    /// Get a description of each live module instance, ordered by [`ModuleHandleId`]
+    pub fn instances(&self) -> Vec<ModuleInstance> {
+        let mut instances: Vec<ModuleInstance> = self
+            .info
+            .iter()
+            .map(|(handle_id, info)| {
+                let mut nodes: Vec<NodeId> = self
+                    .owners
+                    .get(handle_id)
+                    .map(|owners| owners.iter().copied().collect())
+                    .unwrap_or_default();
+                nodes.sort();
+
+                ModuleInstance {
+                    handle_id: *handle_id,
+                    name: info.name.clone(),
+                    args: info.args.clone(),
+                    nodes,
+                    status: info.status.lock().clone(),
+                }
+            })
+            .collect();
+
+        instances.sort_by_key(|instance| instance.handle_id);
+        instances
+    }
+
+    /// Connect a node deriving its data from the module instance declared with the `id` given in `source`.
+    /// If the source hasn't been instantiated yet, the node is connected once it is.
+    pub fn connect_derived(
+        &mut self,
+        source: &str,
+        noderef: NodeRef,
+        selector: DataSelector,
+    ) -> Task<Message> {
+        match self.named.get(source).copied() {
+            Some(handle_id) => self.connect_node(handle_id, noderef, selector),
+            None => {
+                self.pending
+                    .entry(source.to_string())
+                    .or_default()
+                    .push((noderef, selector));
+                Task::none()
+            }
+        }
     }
 
     /// Get the [`NodeId`] associated with a [`ModuleHandleId`]
@@ -4,6 +4,9 @@
 //! * http
 //! * timing
 //! * sub
+//! * state
+//! * script (with the `scripting` feature)
+//! * window
 
 pub mod argument;
 pub mod dispatch;
@@ -12,12 +15,26 @@ pub mod event;
 pub mod handle;
 pub mod manager;
 pub mod message;
+pub mod output;
+pub mod policy;
+pub mod progress;
+pub mod pubsub;
 pub mod registry;
+pub mod selector;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
 
 pub mod file;
+pub mod format;
 pub mod http;
+pub mod i18n;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod state;
 pub mod sub;
 pub mod timing;
+pub mod window;
 
 pub mod data;
 
@@ -26,17 +43,24 @@ pub mod prelude;
 #[cfg(test)]
 mod tests;
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use data::ModuleData;
 use error::ModuleError;
 use event::ModuleEvent;
 use handle::ModuleHandle;
+use http::HttpConfig;
+use i18n::Locales;
 use iced::{
     advanced::graphics::futures::{MaybeSend, MaybeSync},
     Task,
 };
 use internal::ModuleInternal;
+use policy::ModulePolicy;
 use salish::Message;
+use state::StateStore;
+use window::WindowSize;
 
 use crate::{
     message::module::{ModuleMessageData, Topic, TopicMessage},
@@ -47,21 +71,27 @@ use crate::{
 /// Module instance handle ID
 pub(crate) type ModuleHandleId = u64;
 
+/// Topic which module errors are published to
+pub const DIAGNOSTICS_TOPIC: &str = "diagnostics";
+
 /// Dynamic dispatch to an implementation of the sealed [`ModuleInternal`] trait
 pub(crate) type DynModule<E, D> = Box<dyn ModuleInternal<Event = E, Data = D>>;
 
 mod internal {
     //! Sealed Module traits for initializing modules, and dispatching messages
 
-    use crate::{message::module::ModuleMessageData, Error, Source};
+    use crate::{message::module::ModuleMessageData, Source};
 
     use super::{
-        argument::ModuleArguments, data::ModuleData, handle::ModuleHandle, message::ModuleMessage,
+        argument::ModuleArguments,
+        data::{ErrorData, ModuleData},
+        handle::ModuleHandle,
+        message::ModuleMessage,
         Module, ModuleHandleId, ModuleInitData,
     };
     use iced::Task;
     use salish::{message::Destination, Message};
-    use tracing::{debug, debug_span, instrument, trace, Instrument as _};
+    use tracing::{debug, debug_span, error, instrument, trace, Instrument as _};
 
     /// Module startup, and dynamic dispatch of [`ModuleMessage`] from [`crate::module::dispatch::ModuleDispatch`] instances
     /// associated with each instantiation of this [`Module`]
@@ -74,6 +104,7 @@ mod internal {
             &mut self,
             handle: ModuleHandle<'static, Self::Event, Self::Data>,
             args: ModuleArguments,
+            init_data: ModuleInitData,
             event_addr: u64,
         ) -> Task<Message>
         where
@@ -96,8 +127,6 @@ mod internal {
                     // ModuleAsync impl async init() method of the underlying module.
                     match handle.try_module_mut() {
                         Ok(mut module) => {
-                            let init_data = ModuleInitData {};
-
                             debug!("Module async init {}", args);
 
                             let result = module
@@ -107,16 +136,26 @@ mod internal {
 
                             match result {
                                 Ok(event) => {
-                                    println!("RECEIVED EVENT DURING STARTUP");
+                                    debug!(
+                                        handle_id,
+                                        module = %module_name,
+                                        "Module init completed"
+                                    );
                                     Message::unicast(event)
                                         .with_dest(Destination::Endpoint(event_addr))
                                         .with_source(Source::Module(handle_id))
                                 }
-                                Err(e) => Message::broadcast(ModuleMessage::new(
-                                    handle_id,
-                                    Message::unicast(Box::new(Error::from(e))),
-                                ))
-                                .with_source(Source::Module(handle_id)),
+                                Err(e) => {
+                                    error!(
+                                        handle_id,
+                                        module = %module_name,
+                                        "Module init failed: {e}"
+                                    );
+
+                                    // Send the error as module data, so consuming nodes render their fallback
+                                    let data: Box<dyn ModuleData> = Box::new(ErrorData::new(e));
+                                    Message::unicast(data).with_source(Source::Module(handle_id))
+                                }
                             }
                         }
                         Err(e) => Message::broadcast(ModuleMessage::new(
@@ -187,6 +226,14 @@ mod internal {
             Task::done(Message::unicast(data))
         }
 
+        /// Get a Task to send an error from this module to the Snowcap engine. Consuming nodes
+        /// render the `on-error` fallback of the module, and the error is published to the
+        /// [`crate::module::DIAGNOSTICS_TOPIC`] topic.
+        fn send_error(&self, error: impl std::fmt::Display) -> Task<Message> {
+            let data: Box<dyn ModuleData> = Box::new(ErrorData::new(error));
+            Task::done(Message::unicast(data))
+        }
+
         fn event(&self, event: Self::Event) -> Task<Self::Event>
         where
             Self::Event: 'static,
@@ -222,9 +269,42 @@ impl<T> internal::ModuleInit for T where T: Module + Default + 'static {}
 /// Implement [`ModuleInternal`] on anything implementing [`Module`]
 impl<T> internal::ModuleInternal for T where T: Module {}
 
-/// Data passed to module init method
-#[derive(Debug)]
-pub struct ModuleInitData {}
+/// Data passed to module init method, with the services of the engine the instance belongs to
+#[derive(Debug, Clone, Default)]
+pub struct ModuleInitData {
+    state: StateStore,
+    locales: Locales,
+    http_config: Arc<HttpConfig>,
+    window_size: WindowSize,
+    policy: ModulePolicy,
+}
+
+impl ModuleInitData {
+    /// Get the [`StateStore`] of the engine
+    pub fn state(&self) -> &StateStore {
+        &self.state
+    }
+
+    /// Get the [`Locales`] of the engine
+    pub fn locales(&self) -> &Locales {
+        &self.locales
+    }
+
+    /// Get the [`HttpConfig`] set by the application
+    pub fn http_config(&self) -> &HttpConfig {
+        &self.http_config
+    }
+
+    /// Get the [`WindowSize`] of the engine
+    pub fn window_size(&self) -> &WindowSize {
+        &self.window_size
+    }
+
+    /// Get the [`ModulePolicy`] the instance was permitted by, which also restricts what it accesses at runtime
+    pub fn policy(&self) -> &ModulePolicy {
+        &self.policy
+    }
+}
 
 /// Module trait, implemented by each module.
 #[async_trait]
@@ -242,6 +322,16 @@ pub trait Module: MaybeSend + MaybeSync + std::fmt::Debug {
         init_data: ModuleInitData,
     ) -> Result<Self::Event, ModuleError>;
 
+    /// Check the arguments of the module when the markup is parsed, before the module is instantiated, so errors
+    /// such as misspelled arguments are reported at their location in the markup. Arguments accepted by any module
+    /// are listed in [`argument::COMMON_ARGUMENTS`], and [`ModuleArguments::check()`] checks argument names.
+    fn validate_args(_args: &ModuleArguments) -> Result<(), ModuleError>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+
     fn init_tree(&mut self, _tree: Option<&NodeRef>) {}
 
     /// Called when a [`ModuleEvent`] is received for this module.
@@ -258,9 +348,34 @@ pub trait Module: MaybeSend + MaybeSync + std::fmt::Debug {
         Task::none()
     }
 
+    /// Called when the application is shutting down, before [`iced::exit()`].
+    ///
+    /// Modules holding state that must not be lost, such as buffered writes or persistent stores,
+    /// should flush it in the returned [`iced::Task`]. The engine waits for the tasks of all modules to
+    /// complete, up to [`crate::SHUTDOWN_TIMEOUT`], and no further events are dispatched to the module.
+    ///
+    /// It is also called when all nodes referencing the module instance are removed from the tree by a reload.
+    /// Any tasks the module still has running are aborted once the instance is torn down.
+    fn on_shutdown(&mut self) -> Task<Message> {
+        Task::none()
+    }
+
+    /// Called when the visibility of the nodes referencing this module instance changes.
+    ///
+    /// An instance is visible while any of its nodes is visible and the window isn't minimized,
+    /// see [`crate::Snowcap::set_window_visible()`] and [`crate::Snowcap::set_node_visible()`].
+    /// Modules doing periodic work, such as timers, may pause while hidden.
+    fn on_visibility(&mut self, _visible: bool) -> Task<Message> {
+        Task::none()
+    }
+
     /// Called when a subscription message is received on a [`Topic`] that this [`Module`] has subscribed to.
-    /// Subscriptions are created by issuing a [`ModuleMessage::Subscribe`] from an [`iced::Task`] with the [`Topic`] of interest,
-    /// and it will be registered into the [`crate::module::manager::ModuleManager`].
+    /// Subscriptions are created by issuing a [`ModuleMessageData::Subscribe`] from an [`iced::Task`] with the [`Topic`] of interest,
+    /// and it will be registered with the dispatcher of this module instance. The topic may contain wildcards (see [`Topic::matches()`]),
+    /// in which case `topic` is the name of the topic the message was published to.
+    ///
+    /// The payload of `message` can be read with accessors such as [`TopicMessage::as_number()`], and
+    /// payloads of other types published with [`TopicMessage::any()`] with [`TopicMessage::downcast_ref()`].
     ///
     /// An [`iced::Task`] must be returned in response to the message,
     /// which may emit [`ModuleMessage`] messages, which will be dispatched
@@ -0,0 +1,178 @@
+//! Shaping of module output for display
+//!
+//! Any module instance accepts a `transform` argument, which is an expression pipeline
+//! (see [`crate::parser::expr`]) applied to each value the module sends, and a `format`
+//! template with `{}` or `{value}` placeholders. Placeholders can format numbers and dates with a spec such as
+//! `{:,.2}` (see [`super::format`]), with the separators of the active locale.
+//!
+//! ```text
+//! text(http!{url:"http://example.com/temp", transform:"trim |> capture('([0-9.]+)') |> number(1)", format:"{} °C"})
+//! ```
+//!
+//! A `format` without placeholders is left to the module, such as the clock format of the timing module.
+//!
+//! When a module fails, consuming nodes render the text of the `on-error` argument in place of the module data,
+//! or an error widget if it is not given. Image, SVG and video widgets show the text in place of the media.
+//!
+//! ```text
+//! text(http!{url:"http://example.com/status", on-error:"N/A"})
+//! ```
+
+use crate::parser::expr::{Expr, ExprParser, ExprValue};
+
+use super::{
+    argument::ModuleArguments,
+    data::{ModuleData, ModuleDataKind, TextData},
+    error::ModuleError,
+    format::{format_value, has_placeholder},
+    i18n::Locales,
+};
+
+/// Transform and format applied to the data sent by a module instance
+#[derive(Debug, Clone, Default)]
+pub struct OutputPipeline {
+    transform: Option<Expr>,
+    template: Option<String>,
+    fallback: Option<String>,
+
+    /// Locales of the engine, formatting values for the active locale
+    locales: Locales,
+}
+
+impl OutputPipeline {
+    /// Create an [`OutputPipeline`] from the `transform`, `format` and `on-error` module arguments
+    pub fn from_args(args: &ModuleArguments) -> Result<Self, ModuleError> {
+        let transform = args
+            .get("transform")
+            .ok()
+            .map(|transform| {
+                // Each step of the transform receives the module output as its first argument
+                ExprParser::parse_str(&format!("value |> {transform}")).map_err(|e| {
+                    ModuleError::InvalidArgument(format!("transform '{transform}': {e}"))
+                })
+            })
+            .transpose()?;
+
+        let template = args
+            .get("format")
+            .ok()
+            .map(|format| format.to_string())
+            .filter(|format| has_placeholder(format));
+
+        let fallback = args
+            .get("on-error")
+            .ok()
+            .map(|fallback| fallback.to_string());
+
+        Ok(Self {
+            transform,
+            template,
+            fallback,
+            locales: Locales::default(),
+        })
+    }
+
+    /// Format values for the active locale of an engine
+    pub(crate) fn with_locales(mut self, locales: Locales) -> Self {
+        self.locales = locales;
+        self
+    }
+
+    /// Returns true if this pipeline leaves data unchanged
+    pub fn is_empty(&self) -> bool {
+        self.transform.is_none() && self.template.is_none() && self.fallback.is_none()
+    }
+
+    /// Apply the pipeline to data sent by a module
+    pub fn apply(&self, data: Box<dyn ModuleData>) -> Result<Box<dyn ModuleData>, ModuleError> {
+        if self.is_empty() {
+            return Ok(data);
+        }
+
+        // Errors are replaced by the fallback, and never transformed
+        if let ModuleDataKind::Error = data.kind() {
+            return Ok(match &self.fallback {
+                Some(fallback) => Box::new(TextData::new(fallback.clone())),
+                None => data,
+            });
+        }
+
+        if self.transform.is_none() && self.template.is_none() {
+            return Ok(data);
+        }
+
+        let mut text = String::from_utf8_lossy(data.bytes()?).to_string();
+
+        if let Some(transform) = &self.transform {
+            text = transform
+                .eval_value(&ExprValue::from_text(&text))?
+                .to_string();
+        }
+
+        if let Some(template) = &self.template {
+            text = format_value(template, &text, &self.locales.locale());
+        }
+
+        Ok(Box::new(TextData::new(text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::OutputPipeline;
+    use crate::module::{
+        argument::ModuleArguments,
+        data::{ErrorData, ModuleData as _, ModuleDataKind, TextData},
+    };
+
+    #[traced_test]
+    #[test]
+    fn transform_and_format() {
+        let args = ModuleArguments::new()
+            .arg("transform", r#""trim |> truncate(4) |> upper""#)
+            .arg("format", r#""[{value}]""#);
+        let pipeline = OutputPipeline::from_args(&args).unwrap();
+
+        let data = pipeline
+            .apply(Box::new(TextData::new("  snowcap ")))
+            .unwrap();
+        assert_eq!(data.bytes().unwrap(), b"[SNOW]");
+
+        let args = ModuleArguments::new().arg("format", r#""{:.1} MB""#);
+        let pipeline = OutputPipeline::from_args(&args).unwrap();
+
+        let data = pipeline.apply(Box::new(TextData::new("12.345"))).unwrap();
+        assert_eq!(data.bytes().unwrap(), b"12.3 MB");
+    }
+
+    #[traced_test]
+    #[test]
+    fn module_format_ignored() {
+        let args = ModuleArguments::new().arg("format", r#""%H:%M:%S""#);
+        let pipeline = OutputPipeline::from_args(&args).unwrap();
+        assert!(pipeline.is_empty());
+
+        let args = ModuleArguments::new().arg("transform", r#""nope(""#);
+        assert!(OutputPipeline::from_args(&args).is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn error_fallback() {
+        let args = ModuleArguments::new()
+            .arg("on-error", r#""N/A""#)
+            .arg("transform", r#""upper""#);
+        let pipeline = OutputPipeline::from_args(&args).unwrap();
+
+        let data = pipeline.apply(Box::new(ErrorData::new("timeout"))).unwrap();
+        assert!(matches!(data.kind(), ModuleDataKind::Text));
+        assert_eq!(data.bytes().unwrap(), b"N/A");
+
+        // Without a fallback the error is passed through to render an error widget
+        let pipeline = OutputPipeline::default();
+        let data = pipeline.apply(Box::new(ErrorData::new("timeout"))).unwrap();
+        assert!(matches!(data.kind(), ModuleDataKind::Error));
+    }
+}
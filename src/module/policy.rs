@@ -0,0 +1,220 @@
+//! Restrictions on the modules which markup can instantiate
+//!
+//! A [`ModulePolicy`] set with [`crate::Snowcap::set_module_policy()`] is checked each time a module is
+//! instantiated, so markup files from untrusted sources can't read arbitrary files or make arbitrary requests.
+//! A module denied by the policy fails with [`ModuleError::Denied`], which renders like any other module error.
+//! Each redirect followed by the http module is checked against the URL rules as well, so an allowed server can't
+//! redirect requests to a denied URL.
+//!
+//! ```ignore
+//! snowcap.set_module_policy(
+//!     ModulePolicy::default()
+//!         .with_modules(["file", "http", "timing"])
+//!         .with_file_root("./data")
+//!         .with_url_allow("https://api.example.com/")
+//!         .with_url_deny("https://api.example.com/admin/"),
+//! );
+//! ```
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use url::Url;
+
+use super::{argument::ModuleArguments, error::ModuleError};
+
+/// Name of the internal file module, which is restricted to the file roots of the policy
+const FILE_MODULE: &str = "file";
+
+/// Name of the script module, which is restricted to the file roots of the policy
+const SCRIPT_MODULE: &str = "script";
+
+/// Name of the internal http module, which is restricted by the URL rules of the policy
+const HTTP_MODULE: &str = "http";
+
+/// Modules, file paths and URLs which markup is permitted to use. The default policy permits everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModulePolicy {
+    /// Names of the modules which can be instantiated, or any module if `None`
+    modules: Option<HashSet<String>>,
+
+    /// Directories the file module may read from, or any path if `None`
+    file_roots: Option<Vec<PathBuf>>,
+
+    /// URL prefixes the http module may request. Any URL is allowed if empty.
+    url_allow: Vec<String>,
+
+    /// URL prefixes the http module may not request, taking precedence over `url_allow`
+    url_deny: Vec<String>,
+}
+
+impl ModulePolicy {
+    /// Only allow modules with the supplied names to be instantiated
+    pub fn with_modules<I, S>(mut self, modules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.modules
+            .get_or_insert_with(HashSet::new)
+            .extend(modules.into_iter().map(Into::into));
+        self
+    }
+
+    /// Allow the file module to read files inside `root`. Once a root is added, paths
+    /// outside of every root are denied.
+    pub fn with_file_root(mut self, root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let root = root.canonicalize().unwrap_or(root);
+        self.file_roots.get_or_insert_with(Vec::new).push(root);
+        self
+    }
+
+    /// Allow the http module to request URLs starting with `prefix`. Once a prefix is added,
+    /// URLs not matching any allowed prefix are denied.
+    pub fn with_url_allow(mut self, prefix: impl AsRef<str>) -> Self {
+        self.url_allow.push(Self::normalize_url(prefix.as_ref()));
+        self
+    }
+
+    /// Deny the http module from requesting URLs starting with `prefix`
+    pub fn with_url_deny(mut self, prefix: impl AsRef<str>) -> Self {
+        self.url_deny.push(Self::normalize_url(prefix.as_ref()));
+        self
+    }
+
+    /// Check if a module instance with the supplied name and arguments is permitted
+    pub fn check(&self, name: &str, args: &ModuleArguments) -> Result<(), ModuleError> {
+        if let Some(modules) = &self.modules {
+            if !modules.contains(name) {
+                return Err(ModuleError::Denied(format!(
+                    "module '{name}' is not allowed"
+                )));
+            }
+        }
+
+        match name {
+            FILE_MODULE => match args.get("path") {
+                Ok(path) => self.check_path(Path::new(&path.to_string())),
+                Err(_) => Ok(()),
+            },
+            SCRIPT_MODULE => match args.get("file") {
+                Ok(path) => self.check_path(Path::new(&path.to_string())),
+                Err(_) => Ok(()),
+            },
+            HTTP_MODULE => match args.get("url") {
+                Ok(url) => self.check_url(&url.to_string()),
+                Err(_) => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that a path is inside one of the file roots
+    pub(crate) fn check_path(&self, path: &Path) -> Result<(), ModuleError> {
+        let Some(roots) = &self.file_roots else {
+            return Ok(());
+        };
+
+        // Resolve symlinks and `..` components before comparing against the roots. Paths
+        // which can't be resolved are denied, as they can't be shown to be inside a root.
+        let resolved = path
+            .canonicalize()
+            .map_err(|e| ModuleError::Denied(format!("path '{}': {e}", path.display())))?;
+
+        if roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(())
+        } else {
+            Err(ModuleError::Denied(format!(
+                "path '{}' is outside the allowed file roots",
+                path.display()
+            )))
+        }
+    }
+
+    /// Check a URL against the deny and allow prefixes
+    pub(crate) fn check_url(&self, url: &str) -> Result<(), ModuleError> {
+        let url = Url::parse(url)
+            .map_err(|e| ModuleError::Denied(format!("url '{url}': {e}")))?
+            .to_string();
+
+        if self.url_deny.iter().any(|prefix| url.starts_with(prefix)) {
+            return Err(ModuleError::Denied(format!("url '{url}' is denied")));
+        }
+
+        if !self.url_allow.is_empty()
+            && !self.url_allow.iter().any(|prefix| url.starts_with(prefix))
+        {
+            return Err(ModuleError::Denied(format!("url '{url}' is not allowed")));
+        }
+
+        Ok(())
+    }
+
+    /// Normalize a URL prefix the same way requested URLs are, so `https://example.com`
+    /// becomes `https://example.com/` and can't match `https://example.com.evil.net`
+    fn normalize_url(prefix: &str) -> String {
+        Url::parse(prefix)
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| prefix.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::ModulePolicy;
+    use crate::module::{argument::ModuleArguments, error::ModuleError};
+
+    #[traced_test]
+    #[test]
+    fn module_allowlist() {
+        let policy = ModulePolicy::default().with_modules(["timing"]);
+
+        assert!(policy.check("timing", &ModuleArguments::new()).is_ok());
+        assert!(matches!(
+            policy.check("file", &ModuleArguments::new()),
+            Err(ModuleError::Denied(_))
+        ));
+    }
+
+    #[traced_test]
+    #[test]
+    fn url_rules() {
+        let policy = ModulePolicy::default()
+            .with_url_allow("https://example.com")
+            .with_url_deny("https://example.com/admin/");
+
+        let check = |url: &str| {
+            policy.check(
+                "http",
+                &ModuleArguments::new().arg("url", &format!("\"{url}\"")),
+            )
+        };
+
+        assert!(check("https://example.com/status").is_ok());
+        assert!(check("https://example.com/admin/users").is_err());
+        assert!(check("https://example.com.evil.net/").is_err());
+        assert!(check("http://other.net/").is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn file_roots() {
+        let policy = ModulePolicy::default().with_file_root("src/module");
+
+        let check = |path: &str| {
+            policy.check(
+                "file",
+                &ModuleArguments::new().arg("path", &format!("\"{path}\"")),
+            )
+        };
+
+        assert!(check("src/module/mod.rs").is_ok());
+        assert!(check("src/module/../lib.rs").is_err());
+        assert!(check("Cargo.toml").is_err());
+    }
+}
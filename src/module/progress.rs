@@ -0,0 +1,154 @@
+//! Progress of modules loading large data
+//!
+//! The http and file modules publish the progress of loading their data to the topic given by their `progress`
+//! argument, so other nodes can show the state of a download before the data arrives. Each report is a
+//! [`TopicMessage::Value`] map with the number of `bytes` loaded. When the size of the data is known, the map
+//! also has the `total` number of bytes, and the `fraction` loaded from 0 to 1.
+//!
+//! ```text
+//! col[
+//!     text(sub!{topic:"downloads/intro"}),
+//!     video(http!{url:"https://example.com/intro.mp4", progress:"downloads/intro"})
+//! ]
+//! ```
+//!
+//! Reports are published at most every [`REPORT_INTERVAL`], and a final report is published when all the data is
+//! loaded, before the module sends its data.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use salish::Message;
+
+use crate::{
+    message::module::{ModuleMessageData, PublishMessage, Topic, TopicMessage},
+    Value,
+};
+
+use super::{argument::ModuleArguments, error::ModuleError};
+
+/// Minimum interval between reports of the progress of loading data
+pub const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Amount of data loaded by a module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of bytes loaded
+    pub bytes: u64,
+
+    /// Size of the data in bytes, if it's known
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// Get the fraction of the data loaded from 0 to 1, if the size of the data is known
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.bytes as f64 / total as f64).min(1.0)),
+            None => None,
+        }
+    }
+
+    /// Get the progress as a map value with the `bytes`, `total` and `fraction` keys
+    pub fn to_value(&self) -> Value {
+        let mut map = BTreeMap::new();
+        map.insert("bytes".to_string(), Value::new_integer(self.bytes));
+        if let Some(total) = self.total {
+            map.insert("total".to_string(), Value::new_integer(total));
+        }
+        if let Some(fraction) = self.fraction() {
+            map.insert("fraction".to_string(), Value::new_float(fraction));
+        }
+        Value::new_map(map)
+    }
+}
+
+/// Publishes the progress of loading data to a topic, limited to one report per [`REPORT_INTERVAL`]
+#[derive(Debug)]
+pub(crate) struct ProgressReporter {
+    topic: Topic,
+    progress: Progress,
+    reported: Option<Instant>,
+}
+
+impl ProgressReporter {
+    /// Get the topic of the `progress` argument of a module, if it's given
+    pub fn topic(args: &ModuleArguments) -> Result<Option<Topic>, ModuleError> {
+        match args.get("progress") {
+            Ok(_) => Ok(Some(Topic::new(args.get_string("progress")?))),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn new(topic: Topic, total: Option<u64>) -> Self {
+        Self {
+            topic,
+            progress: Progress { bytes: 0, total },
+            reported: None,
+        }
+    }
+
+    /// Add a number of loaded bytes, returning a message publishing the progress if a report is due
+    pub fn advance(&mut self, bytes: usize) -> Option<Message> {
+        self.progress.bytes += bytes as u64;
+
+        let due = match self.reported {
+            Some(reported) => reported.elapsed() >= REPORT_INTERVAL,
+            None => true,
+        };
+
+        due.then(|| self.report())
+    }
+
+    /// Get a message publishing the final progress, when all the data is loaded
+    pub fn finish(&mut self) -> Message {
+        self.progress.total = Some(self.progress.bytes);
+        self.report()
+    }
+
+    fn report(&mut self) -> Message {
+        self.reported = Some(Instant::now());
+        Message::broadcast(ModuleMessageData::Publish(PublishMessage {
+            topic: self.topic.clone(),
+            message: TopicMessage::Value(self.progress.to_value()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Progress, ProgressReporter};
+    use crate::message::module::Topic;
+
+    #[test]
+    fn progress() {
+        let progress = Progress {
+            bytes: 250,
+            total: Some(1000),
+        };
+        assert_eq!(progress.fraction(), Some(0.25));
+
+        let value = progress.to_value();
+        assert_eq!(value.field("bytes").unwrap().integer().unwrap(), 250);
+        assert_eq!(value.field("total").unwrap().integer().unwrap(), 1000);
+
+        // The fraction is unknown without a total
+        let progress = Progress {
+            bytes: 250,
+            total: None,
+        };
+        assert!(progress.fraction().is_none());
+        assert!(progress.to_value().field("fraction").is_none());
+
+        // The first report is published immediately, and later ones once the interval elapses
+        let mut reporter = ProgressReporter::new(Topic::new("downloads/test"), Some(100));
+        assert!(reporter.advance(10).is_some());
+        assert!(reporter.advance(10).is_none());
+        reporter.finish();
+        assert_eq!(reporter.progress.bytes, 20);
+        assert_eq!(reporter.progress.total, Some(20));
+    }
+}
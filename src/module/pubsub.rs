@@ -0,0 +1,105 @@
+//! Topic publish/subscribe on the [`MessageRouter`]
+//!
+//! Modules publish by broadcasting a [`ModuleMessageData::Publish`] message, and subscribe to topics by
+//! broadcasting [`ModuleMessageData::Subscribe`]. Each module instance has an endpoint receiving every
+//! published message, which is forwarded to [`super::Module::on_subscription()`] when the topic matches one
+//! of the subscriptions of the module. Subscription topics may contain wildcards, see [`Topic::matches()`].
+//!
+//! The application can publish and subscribe to the same topics with [`crate::Snowcap::publish()`]
+//! and [`crate::Snowcap::subscribe()`].
+
+use std::sync::Arc;
+
+use iced::Task;
+use parking_lot::Mutex;
+use salish::{endpoint::Endpoint, router::MessageRouter, Message};
+
+use crate::{
+    message::module::{ModuleMessageData, PublishMessage, Topic, TopicMessage},
+    Source,
+};
+
+/// Topics subscribed to by a module instance, shared between the endpoints of its dispatcher
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Subscriptions(Arc<Mutex<Vec<Topic>>>);
+
+impl Subscriptions {
+    /// Add a subscription to a topic
+    pub fn subscribe(&self, topic: Topic) {
+        let mut topics = self.0.lock();
+        if !topics.contains(&topic) {
+            topics.push(topic);
+        }
+    }
+
+    /// Remove a subscription to a topic
+    pub fn unsubscribe(&self, topic: &Topic) {
+        self.0.lock().retain(|subscribed| subscribed != topic);
+    }
+
+    /// Returns true if any subscription matches the topic
+    pub fn matches(&self, topic: &Topic) -> bool {
+        self.0.lock().iter().any(|pattern| pattern.matches(topic))
+    }
+}
+
+/// Create a [`Task`] publishing a message to a topic
+pub fn publish(topic: impl Into<Topic>, message: TopicMessage) -> Task<Message> {
+    Task::done(Message::broadcast(ModuleMessageData::Publish(
+        PublishMessage {
+            topic: topic.into(),
+            message,
+        },
+    )))
+}
+
+/// An application subscription to topics matching a pattern, created with [`crate::Snowcap::subscribe()`].
+/// The subscription is cancelled when this is dropped.
+pub struct TopicSubscription {
+    pattern: Topic,
+    _endpoint: Endpoint<'static, ModuleMessageData, Task<Message>, Source>,
+}
+
+impl TopicSubscription {
+    /// Subscribe to messages published to topics matching `pattern`, calling `f` with each message
+    pub(crate) fn new<F>(
+        router: &MessageRouter<'static, Task<Message>, Source>,
+        pattern: Topic,
+        f: F,
+    ) -> Self
+    where
+        F: Fn(Topic, TopicMessage) -> Task<Message> + Send + Sync + 'static,
+    {
+        let endpoint_pattern = pattern.clone();
+
+        let endpoint =
+            router
+                .create_endpoint::<ModuleMessageData>()
+                .message(move |_source, message| match message {
+                    ModuleMessageData::Publish(publish)
+                        if endpoint_pattern.matches(&publish.topic) =>
+                    {
+                        f(publish.topic, publish.message)
+                    }
+                    _ => Task::none(),
+                });
+
+        Self {
+            pattern,
+            _endpoint: endpoint,
+        }
+    }
+
+    /// Get the topic pattern of this subscription
+    pub fn pattern(&self) -> &Topic {
+        &self.pattern
+    }
+}
+
+impl std::fmt::Debug for TopicSubscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopicSubscription")
+            .field("pattern", &self.pattern)
+            .finish()
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    any::TypeId,
     collections::HashMap,
     sync::{atomic::AtomicU64, Arc, LazyLock, Mutex},
 };
@@ -11,7 +12,10 @@ use colored::Colorize as _;
 
 use crate::Source;
 
-use super::{dispatch::ModuleDispatch, error::ModuleError, internal::ModuleInit, Module};
+use super::{
+    argument::ModuleArguments, dispatch::ModuleDispatch, error::ModuleError, internal::ModuleInit,
+    Module,
+};
 
 /// Module Handle ID generator. Each constructor closure in [`ModuleDescriptor`] keeps a clone of this
 /// [`AtomicU64`] for allocating a new ID on each module instantiation.
@@ -30,6 +34,10 @@ pub type DynModuleNew = Box<
         + 'static,
 >;
 
+/// Type alias for a boxed dyn closure which calls [`Module::validate_args()`] of a registered module
+pub type DynValidateArgs =
+    Box<dyn Fn(&ModuleArguments) -> Result<(), ModuleError> + Send + Sync + 'static>;
+
 /// Dynamic Module registration descriptor.
 /// Each dynamic module which is available for instantiation has
 /// an associated `ModuleDescriptor` that is inserted into the global
@@ -41,8 +49,17 @@ pub struct ModuleDescriptor {
     /// Name of this module
     pub name: String,
 
+    /// Type of the [`Module`] implementation registered under this name
+    pub type_id: TypeId,
+
+    /// Type name of the [`Module`] implementation, for reporting name collisions
+    pub type_name: String,
+
     /// Boxed closure proxying to [`ModuleInit::new()`] of this registered module
     pub new: DynModuleNew,
+
+    /// Boxed closure proxying to [`Module::validate_args()`] of this registered module
+    pub validate: DynValidateArgs,
 }
 
 pub struct ModuleRegistry;
@@ -65,17 +82,33 @@ impl std::fmt::Display for ModuleRegistry {
 }
 
 impl ModuleRegistry {
-    /// Register a [`ModuleDescriptor`] with the global module registry
-    pub fn register_descriptor(descriptor: ModuleDescriptor) {
+    /// Register a [`ModuleDescriptor`] with the global module registry.
+    ///
+    /// Registering the same module type under a name again replaces its descriptor, but a name registered
+    /// to a different module type returns [`ModuleError::NameCollision`].
+    pub fn register_descriptor(descriptor: ModuleDescriptor) -> Result<(), ModuleError> {
         if let Ok(mut registry) = MODULE_REGISTRY.lock() {
+            if let Some(existing) = registry.get(&descriptor.name) {
+                if existing.type_id != descriptor.type_id {
+                    return Err(ModuleError::NameCollision {
+                        name: descriptor.name,
+                        existing: existing.type_name.clone(),
+                    });
+                }
+            }
+
             registry.insert(descriptor.name.clone(), descriptor);
+            Ok(())
         } else {
             panic!("Failed to get module registry");
         }
     }
 
-    /// Register a module with the global registry under the supplied name
-    pub fn register<T: ModuleInit + Module>(name: &str) {
+    /// Register a module with the global registry under the supplied name.
+    ///
+    /// Names may be namespaced with dots, such as `mycompany.weather`, and referenced
+    /// in markup as `mycompany.weather!{...}`.
+    pub fn register<T: ModuleInit + Module>(name: &str) -> Result<(), ModuleError> {
         debug_span!("module-register").in_scope(|| {
             debug!(
                 "Registering module '{}' [{}, {}]",
@@ -113,14 +146,30 @@ impl ModuleRegistry {
             // Create a [`ModuleDescriptor`] for this module registration
             let descriptor = ModuleDescriptor {
                 name,
+                type_id: TypeId::of::<T>(),
+                type_name: T::type_name().to_string(),
                 new: module_new,
+                validate: Box::new(T::validate_args),
             };
 
             // Insert the descriptor into the global module registry
-            ModuleRegistry::register_descriptor(descriptor);
+            ModuleRegistry::register_descriptor(descriptor)
         })
     }
 
+    /// Check the arguments of a module with [`Module::validate_args()`] of the module registered under the name.
+    /// Modules which aren't registered yet are not checked, and fail when they are instantiated.
+    pub fn validate_args(name: &str, args: &ModuleArguments) -> Result<(), ModuleError> {
+        if let Ok(registry) = MODULE_REGISTRY.lock() {
+            match registry.get(name) {
+                Some(descriptor) => (descriptor.validate)(args),
+                None => Ok(()),
+            }
+        } else {
+            panic!("Failed to acquire module registry lock");
+        }
+    }
+
     /// Get a module from the registry by name
     pub fn get<R, F>(name: &str, f: F) -> Result<R, ModuleError>
     where
@@ -0,0 +1,393 @@
+//! Scripting module, running a [Rhai](https://rhai.rs) script to compute values from topics and the state store.
+//! Requires the `scripting` feature.
+//!
+//! ```text
+//! text(script!{file:"logic.rhai", threshold:30})
+//! ```
+//!
+//! The top level of the script runs once when the module starts, and can subscribe to topics. Each message
+//! published to a subscribed topic calls the `on_message(topic, value)` function of the script, if defined.
+//! A value returned by `on_message` other than `()` is displayed as the data of the module. Arguments other
+//! than `file` are constants of the script.
+//!
+//! ```text
+//! subscribe("sensors/temperature");
+//!
+//! fn on_message(topic, value) {
+//!     let hot = value > threshold;
+//!     set_state("hot", hot);
+//!     publish("sensors/fahrenheit", value * 9.0 / 5.0 + 32.0);
+//!     if hot { "Hot" } else { "Normal" }
+//! }
+//! ```
+//!
+//! Functions available to scripts are:
+//!
+//! | Function                  | Description                                                        |
+//! |---------------------------|--------------------------------------------------------------------|
+//! | `subscribe(topic)`        | Subscribe to a topic, which may contain wildcards                  |
+//! | `publish(topic, value)`   | Publish a value to a topic                                         |
+//! | `get_state(key)`          | Get the value of a key of the state store, or `()`                 |
+//! | `set_state(key, value)`   | Set the value of a key of the state store                          |
+//! | `output(value)`           | Display a value as the data of the module                          |
+//!
+//! Scripts are limited to [`MAX_OPERATIONS`] operations each time they are called, so a script which doesn't
+//! terminate can't stall the application.
+
+use std::{path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use iced::Task;
+use parking_lot::Mutex;
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST};
+use salish::Message;
+use tracing::{debug, warn};
+
+use crate::{
+    message::module::{ModuleMessageData, Topic, TopicMessage},
+    module::argument::ModuleArguments,
+    parser::value::ValueData,
+    Value,
+};
+
+use super::{
+    data::TextData, error::ModuleError, internal::ModuleInternal, pubsub::publish,
+    state::StateStore, Module, ModuleEvent, ModuleInitData,
+};
+
+/// Maximum number of operations of each call into a script
+pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Name of the function of the script called with each message of a subscribed topic
+const ON_MESSAGE: &str = "on_message";
+
+/// Effects of a call into a script, applied once the call returns
+#[derive(Debug, Default)]
+struct Effects {
+    subscribe: Vec<Topic>,
+    publish: Vec<(Topic, TopicMessage)>,
+    state: Vec<(String, Value)>,
+    output: Option<String>,
+}
+
+/// Compiled script, and the engine running it
+struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    effects: Arc<Mutex<Effects>>,
+}
+
+impl Script {
+    /// Compile the source of a script, with the arguments of the module as constants
+    fn compile(
+        source: &str,
+        args: &ModuleArguments,
+        state: StateStore,
+    ) -> Result<Self, ModuleError> {
+        let effects = Arc::new(Mutex::new(Effects::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let fx = effects.clone();
+        engine.register_fn("subscribe", move |topic: &str| {
+            fx.lock().subscribe.push(Topic::new(topic))
+        });
+
+        let fx = effects.clone();
+        engine.register_fn("publish", move |topic: &str, value: Dynamic| {
+            fx.lock()
+                .publish
+                .push((Topic::new(topic), topic_message(value)))
+        });
+
+        engine.register_fn("get_state", move |key: &str| {
+            state.get(key).map(to_dynamic).unwrap_or(Dynamic::UNIT)
+        });
+
+        let fx = effects.clone();
+        engine.register_fn("set_state", move |key: &str, value: Dynamic| {
+            fx.lock().state.push((key.to_string(), to_value(value)))
+        });
+
+        let fx = effects.clone();
+        engine.register_fn("output", move |value: Dynamic| {
+            fx.lock().output = Some(value.to_string())
+        });
+
+        let ast = engine
+            .compile(source)
+            .map_err(|e| ModuleError::Internal(Box::new(e)))?;
+
+        let mut scope = Scope::new();
+        for arg in args.sort() {
+            if arg.name() != "file" {
+                scope.push_constant(arg.name().as_str(), to_dynamic(arg.value().clone()));
+            }
+        }
+
+        Ok(Self {
+            engine,
+            ast,
+            scope,
+            effects,
+        })
+    }
+
+    /// Run the top level of the script
+    fn run(&mut self) -> Result<Effects, ModuleError> {
+        self.engine
+            .run_ast_with_scope(&mut self.scope, &self.ast)
+            .map_err(|e| ModuleError::Internal(Box::new(e)))?;
+
+        Ok(std::mem::take(&mut *self.effects.lock()))
+    }
+
+    /// Call the `on_message` function of the script, if it is defined
+    fn on_message(
+        &mut self,
+        topic: &Topic,
+        message: &TopicMessage,
+    ) -> Result<Effects, ModuleError> {
+        if !self.ast.iter_functions().any(|f| f.name == ON_MESSAGE) {
+            debug!("Script has no {ON_MESSAGE} function, ignoring message on {topic}");
+            return Ok(Effects::default());
+        }
+
+        let result: Dynamic = self
+            .engine
+            .call_fn_with_options(
+                CallFnOptions::new().eval_ast(false),
+                &mut self.scope,
+                &self.ast,
+                ON_MESSAGE,
+                (topic.to_string(), message_dynamic(message)),
+            )
+            .map_err(|e| ModuleError::Internal(Box::new(e)))?;
+
+        let mut effects = std::mem::take(&mut *self.effects.lock());
+        if !result.is_unit() {
+            effects.output = Some(result.to_string());
+        }
+
+        Ok(effects)
+    }
+}
+
+/// Convert a [`Value`] to a value of a script
+fn to_dynamic(value: Value) -> Dynamic {
+    match value.inner().clone() {
+        ValueData::None | ValueData::AttributeKind(_) => Dynamic::UNIT,
+        ValueData::String(string) => Dynamic::from(string),
+        ValueData::Float(float) => Dynamic::from_float(float),
+        ValueData::Integer(integer) => Dynamic::from_int(integer as i64),
+        ValueData::Boolean(boolean) => Dynamic::from_bool(boolean),
+        ValueData::Duration(duration) => Dynamic::from_float(duration.as_secs_f64()),
+        ValueData::Size(size) => Dynamic::from_int(size as i64),
+        ValueData::Url(url) => Dynamic::from(url.to_string()),
+        ValueData::Bytes(bytes) => Dynamic::from_blob(bytes.to_vec()),
+        ValueData::Array(values) => {
+            Dynamic::from_array(values.into_iter().map(to_dynamic).collect())
+        }
+        ValueData::Map(map) => Dynamic::from_map(
+            map.into_iter()
+                .map(|(key, value)| (key.into(), to_dynamic(value)))
+                .collect(),
+        ),
+        ValueData::Labelled(_, value) => to_dynamic(*value),
+    }
+}
+
+/// Convert a value of a script to a [`Value`]
+fn to_value(value: Dynamic) -> Value {
+    if let Ok(boolean) = value.as_bool() {
+        Value::new_bool(boolean)
+    } else if let Ok(integer) = value.as_int() {
+        match u64::try_from(integer) {
+            Ok(integer) => Value::new_integer(integer),
+            Err(_) => Value::new_float(integer as f64),
+        }
+    } else if let Ok(float) = value.as_float() {
+        Value::new_float(float)
+    } else if value.is_array() {
+        let values = value.into_array().unwrap_or_default();
+        Value::new_array(values.into_iter().map(to_value).collect())
+    } else if value.is_map() {
+        let map = value.cast::<rhai::Map>();
+        Value::new_map(
+            map.into_iter()
+                .map(|(key, value)| (key.to_string(), to_value(value)))
+                .collect(),
+        )
+    } else {
+        Value::new_string(value.to_string())
+    }
+}
+
+/// Convert a value of a script to a [`TopicMessage`], with `()` as a trigger
+fn topic_message(value: Dynamic) -> TopicMessage {
+    if value.is_unit() {
+        TopicMessage::Trigger
+    } else if value.is_string() {
+        TopicMessage::String(value.to_string())
+    } else {
+        TopicMessage::Value(to_value(value))
+    }
+}
+
+/// Convert the payload of a [`TopicMessage`] to a value of a script
+fn message_dynamic(message: &TopicMessage) -> Dynamic {
+    match message {
+        TopicMessage::Trigger | TopicMessage::Any(_) => Dynamic::UNIT,
+        TopicMessage::String(string) => Dynamic::from(string.clone()),
+        TopicMessage::Number(number) => Dynamic::from_float(*number),
+        TopicMessage::Bytes(bytes) => Dynamic::from_blob(bytes.to_vec()),
+        TopicMessage::Value(value) => to_dynamic(value.clone()),
+    }
+}
+
+#[derive(Debug)]
+pub enum ScriptEvent {
+    Loaded,
+}
+impl ModuleEvent for ScriptEvent {}
+
+#[derive(Default)]
+pub struct ScriptModule {
+    path: PathBuf,
+    script: Option<Script>,
+    state: StateStore,
+}
+
+impl std::fmt::Debug for ScriptModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptModule")
+            .field("path", &self.path)
+            .field("loaded", &self.script.is_some())
+            .finish()
+    }
+}
+
+impl ScriptModule {
+    /// Get a [`Task`] applying the effects of a call into the script
+    fn apply(&self, effects: Effects) -> Task<Message> {
+        let mut tasks: Vec<Task<Message>> = Vec::new();
+
+        for topic in effects.subscribe {
+            debug!("Script {:?} subscribing to {topic}", self.path);
+            tasks.push(Task::done(Message::broadcast(
+                ModuleMessageData::Subscribe(topic),
+            )));
+        }
+
+        for (key, value) in effects.state {
+            tasks.push(self.state.set(&key, value));
+        }
+
+        for (topic, message) in effects.publish {
+            tasks.push(publish(topic, message));
+        }
+
+        if let Some(output) = effects.output {
+            tasks.push(self.send_data(TextData::new(output)));
+        }
+
+        Task::batch(tasks)
+    }
+
+    /// Get a [`Task`] applying the result of a call into the script, or sending its error
+    fn result(&self, result: Result<Effects, ModuleError>) -> Task<Message> {
+        match result {
+            Ok(effects) => self.apply(effects),
+            Err(e) => {
+                warn!("Script {:?} failed: {e}", self.path);
+                self.send_error(e)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Module for ScriptModule {
+    type Event = ScriptEvent;
+    type Data = TextData;
+
+    async fn init(
+        &mut self,
+        args: ModuleArguments,
+        init_data: ModuleInitData,
+    ) -> Result<Self::Event, ModuleError> {
+        self.path = args.get("file")?.to_string().into();
+        self.state = init_data.state().clone();
+
+        let source = tokio::fs::read_to_string(&self.path).await?;
+        self.script = Some(Script::compile(&source, &args, self.state.clone())?);
+
+        debug!("Compiled script {:?}", self.path);
+
+        Ok(ScriptEvent::Loaded)
+    }
+
+    fn on_event(&mut self, event: Self::Event) -> Task<Message> {
+        match event {
+            ScriptEvent::Loaded => match self.script.as_mut().map(Script::run) {
+                Some(result) => self.result(result),
+                None => Task::none(),
+            },
+        }
+    }
+
+    fn on_subscription(&mut self, topic: Topic, message: TopicMessage) -> Task<Message> {
+        match self
+            .script
+            .as_mut()
+            .map(|script| script.on_message(&topic, &message))
+        {
+            Some(result) => self.result(result),
+            None => Task::none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::ScriptModule;
+    use crate::module::{argument::ModuleArguments, testing::TestBed};
+
+    #[traced_test]
+    #[test]
+    fn script_init() {
+        let path = std::env::temp_dir().join("snowcap-script-init.rhai");
+        std::fs::write(
+            &path,
+            r#"
+            subscribe("sensors/temperature");
+            set_state("script_limit", limit * 2);
+            publish("sensors/ready", true);
+            output("Limit " + limit);
+
+            fn on_message(topic, value) {
+                value * 2
+            }
+            "#,
+        )
+        .unwrap();
+
+        let args = ModuleArguments::new()
+            .arg("file", &format!("{:?}", path.display().to_string()))
+            .arg("limit", "21");
+        let mut bed = TestBed::<ScriptModule>::new(args).unwrap();
+        bed.run();
+
+        assert_eq!(bed.data(), vec![b"Limit 21".to_vec()]);
+        assert_eq!(bed.subscriptions()[0].to_string(), "sensors/temperature");
+        assert!(bed
+            .published()
+            .iter()
+            .any(|published| published.topic.to_string() == "sensors/ready"));
+        let state = bed.manager().state();
+        assert_eq!(state.get("script_limit").unwrap().integer().unwrap(), 42);
+    }
+}
@@ -0,0 +1,101 @@
+//! Selection of the module data consumed by a tree node
+//!
+//! Nodes consuming data from a module instance can select a `field` of the data, and
+//! transform it with an `expr` expression (see [`crate::parser::expr`]).
+
+use std::sync::Arc;
+
+use crate::parser::expr::{Expr, ExprParser, ExprValue};
+
+use super::{
+    argument::ModuleArguments,
+    data::{ModuleData, ModuleDataKind, TextData},
+    error::ModuleError,
+};
+
+/// Name of the pseudo module which derives values from the data of another module instance
+pub const DERIVE_MODULE: &str = "derive";
+
+/// Module arguments used by a consuming node to select its data, rather than configuring the module instance
+pub(crate) const SELECTOR_ARGUMENTS: [&str; 2] = ["field", "expr"];
+
+/// Selects and transforms the data of a module instance for a consuming node
+#[derive(Debug, Clone, Default)]
+pub struct DataSelector {
+    field: Option<String>,
+    expr: Option<Expr>,
+}
+
+impl DataSelector {
+    /// Create a [`DataSelector`] from the `field` and `expr` module arguments
+    pub fn from_args(args: &ModuleArguments) -> Result<Self, ModuleError> {
+        let field = args.get("field").ok().map(|field| field.to_string());
+
+        let expr = args
+            .get("expr")
+            .ok()
+            .map(|expr| {
+                ExprParser::parse_str(&expr.to_string())
+                    .map_err(|e| ModuleError::InvalidArgument(format!("expr '{expr}': {e}")))
+            })
+            .transpose()?;
+
+        Ok(Self { field, expr })
+    }
+
+    /// Select the data consumed by a node from the data of a module instance
+    pub fn select(&self, data: Arc<dyn ModuleData>) -> Result<Box<dyn ModuleData>, ModuleError> {
+        // Errors are passed to the node unchanged, to render fallback content
+        if let ModuleDataKind::Error = data.kind() {
+            return Ok(Box::new(data));
+        }
+
+        let data: Box<dyn ModuleData> = match &self.field {
+            Some(field) => data
+                .field(field)
+                .ok_or_else(|| ModuleError::InvalidArgument(format!("no field '{field}'")))?,
+            None => Box::new(data),
+        };
+
+        match &self.expr {
+            Some(expr) => {
+                let value = ExprValue::from_text(&String::from_utf8_lossy(data.bytes()?));
+                let result = expr.eval_value(&value)?;
+                Ok(Box::new(TextData::new(result.to_string())))
+            }
+            None => Ok(data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tracing_test::traced_test;
+
+    use super::DataSelector;
+    use crate::module::{argument::ModuleArguments, data::ModuleData, data::TextData};
+
+    #[traced_test]
+    #[test]
+    fn select_expr() {
+        let args = ModuleArguments::new().arg("expr", r#""value * 100 |> round""#);
+        let selector = DataSelector::from_args(&args).unwrap();
+
+        let data: Arc<dyn ModuleData> = Arc::new(TextData::new("0.256"));
+        let selected = selector.select(data).unwrap();
+
+        assert_eq!(selected.bytes().unwrap(), b"26");
+    }
+
+    #[traced_test]
+    #[test]
+    fn missing_field() {
+        let args = ModuleArguments::new().arg("field", r#""cpu""#);
+        let selector = DataSelector::from_args(&args).unwrap();
+
+        let data: Arc<dyn ModuleData> = Arc::new(TextData::new("text"));
+        assert!(selector.select(data).is_err());
+    }
+}
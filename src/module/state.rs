@@ -0,0 +1,230 @@
+//! State module, exposing a value of the key/value state store as data.
+//!
+//! ```text
+//! text(state!{key:"counter", default:0})
+//! button<on-press:state.increment("counter")>(text("+"))
+//! toggler<on-toggle:state.toggle("dark-mode")>("Dark mode")
+//! button<on-press:state.set("page", "settings")>(text("Settings"))
+//! ```
+//!
+//! The [`StateStore`] is owned by the engine and shared by all of its instances, so any number of widgets can read
+//! the same key. The `default` argument initializes the key if it has no value yet. Event handlers update the store
+//! with [`StateAction`], and each change is published to the `state/{key}` topic, which updates the instances
+//! reading the key.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use iced::Task;
+use salish::Message;
+use tracing::{debug, warn};
+
+use crate::{
+    message::module::{ModuleMessageData, Topic, TopicMessage},
+    module::argument::ModuleArguments,
+    parser::error::ParseError,
+    Value,
+};
+
+use super::{
+    data::TextData, error::ModuleError, internal::ModuleInternal, pubsub::publish, Module,
+    ModuleEvent, ModuleInitData,
+};
+
+/// Get the topic changes of a key are published to
+pub fn topic(key: &str) -> Topic {
+    Topic::new(format!("state/{key}"))
+}
+
+/// Key/value state store of an engine. Clones share the same values.
+#[derive(Debug, Clone, Default)]
+pub struct StateStore(Arc<Mutex<HashMap<String, Value>>>);
+
+impl StateStore {
+    /// Get the value of a key
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.0.lock().ok()?.get(key).cloned()
+    }
+
+    /// Set the value of a key, and get a [`Task`] publishing the change
+    pub fn set(&self, key: &str, value: Value) -> Task<Message> {
+        match self.0.lock() {
+            Ok(mut state) => {
+                state.insert(key.to_string(), value.clone());
+            }
+            Err(e) => {
+                warn!("State store poisoned: {e}");
+                return Task::none();
+            }
+        }
+
+        debug!("State {key} set to {value}");
+        publish(topic(key), TopicMessage::Value(value))
+    }
+
+    /// Set the value of a key if it has no value
+    fn init(&self, key: &str, default: Value) -> Value {
+        match self.0.lock() {
+            Ok(mut state) => state.entry(key.to_string()).or_insert(default).clone(),
+            Err(_) => default,
+        }
+    }
+}
+
+/// Get the data of a value, as text
+fn text(value: &Value) -> TextData {
+    let text: Cow<'_, str> = value.inner().into();
+    TextData::new(text)
+}
+
+/// Update of the state store performed by an event handler
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StateAction {
+    /// Set a key to a string
+    Set(String, String),
+    /// Add one to a number
+    Increment(String),
+    /// Subtract one from a number
+    Decrement(String),
+    /// Invert a boolean
+    Toggle(String),
+}
+
+impl StateAction {
+    /// Parse an action from its name, such as `increment`, and its arguments
+    pub fn parse(name: &str, key: String, value: Option<String>) -> Result<Self, ParseError> {
+        match (name.to_lowercase().as_str(), value) {
+            ("set", Some(value)) => Ok(Self::Set(key, value)),
+            ("increment", None) => Ok(Self::Increment(key)),
+            ("decrement", None) => Ok(Self::Decrement(key)),
+            ("toggle", None) => Ok(Self::Toggle(key)),
+            (name, _) => Err(ParseError::Unhandled(format!("state action '{name}'"))),
+        }
+    }
+
+    /// Apply the action to a store, and get a [`Task`] publishing the change
+    pub fn apply(&self, store: &StateStore) -> Task<Message> {
+        match self {
+            Self::Set(key, value) => store.set(key, Value::new_string(value.clone())),
+            Self::Increment(key) => {
+                store.set(key, Value::new_float(Self::number(store, key) + 1.0))
+            }
+            Self::Decrement(key) => {
+                store.set(key, Value::new_float(Self::number(store, key) - 1.0))
+            }
+            Self::Toggle(key) => {
+                let toggled = store.get(key).and_then(|value| value.boolean().ok());
+                store.set(key, Value::new_bool(!toggled.unwrap_or(false)))
+            }
+        }
+    }
+
+    /// Current value of a key as a number, or zero
+    fn number(store: &StateStore, key: &str) -> f64 {
+        store
+            .get(key)
+            .and_then(|value| value.float().ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+pub enum StateEvent {
+    Init(String, Value),
+}
+impl ModuleEvent for StateEvent {}
+
+#[derive(Debug, Default)]
+pub struct StateModule;
+
+#[async_trait]
+impl Module for StateModule {
+    type Event = StateEvent;
+    type Data = TextData;
+
+    async fn init(
+        &mut self,
+        args: ModuleArguments,
+        init_data: ModuleInitData,
+    ) -> Result<Self::Event, ModuleError> {
+        let key = args.get("key")?.to_string();
+        let default = args.get("default").cloned().unwrap_or_default();
+
+        let value = init_data.state().init(&key, default);
+        Ok(StateEvent::Init(key, value))
+    }
+
+    fn on_event(&mut self, event: Self::Event) -> Task<Message> {
+        match event {
+            StateEvent::Init(key, value) => Task::done(Message::broadcast(
+                ModuleMessageData::Subscribe(topic(&key)),
+            ))
+            .chain(self.send_data(text(&value))),
+        }
+    }
+
+    fn on_subscription(&mut self, topic: Topic, message: TopicMessage) -> Task<Message> {
+        match message {
+            TopicMessage::Value(value) => {
+                debug!("State {topic} changed to {value}");
+                self.send_data(text(&value))
+            }
+            _ => {
+                debug!("Ignoring message on {topic}");
+                Task::none()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{StateAction, StateModule, StateStore};
+    use crate::module::{argument::ModuleArguments, testing::TestBed};
+
+    #[traced_test]
+    #[test]
+    fn state_default() {
+        let args = ModuleArguments::new()
+            .arg("key", r#""clicks""#)
+            .arg("default", "5");
+        let mut bed = TestBed::<StateModule>::new(args).unwrap();
+        bed.run();
+
+        assert_eq!(bed.data()[0], b"5");
+        assert_eq!(
+            bed.manager()
+                .state()
+                .get("clicks")
+                .unwrap()
+                .integer()
+                .unwrap(),
+            5
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn state_actions() {
+        let store = StateStore::default();
+
+        let _ = StateAction::Increment("counter".into()).apply(&store);
+        let _ = StateAction::Increment("counter".into()).apply(&store);
+        let _ = StateAction::Decrement("counter".into()).apply(&store);
+        assert_eq!(store.get("counter").unwrap().float().unwrap(), 1.0);
+
+        let _ = StateAction::Toggle("enabled".into()).apply(&store);
+        assert!(store.get("enabled").unwrap().boolean().unwrap());
+
+        // Stores of other engines don't share values
+        assert!(StateStore::default().get("counter").is_none());
+
+        assert!(StateAction::parse("set", "page".into(), None).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! Test bed for unit testing [`Module`] implementations
+//!
+//! A [`TestBed`] instantiates a single module with a set of arguments, and drives the tasks returned
+//! by the module on a local tokio runtime, without the [`crate::Snowcap`] engine or an iced application.
+//! Data sent by the module, and topic messages it publishes, are collected for inspection.
+//!
+//! ```ignore
+//! let args = ModuleArguments::new().arg("mode", r#""elapsed""#).arg("periodic", r#""10ms""#);
+//!
+//! let mut bed = TestBed::<TimingModule>::new(args)?.with_limit(8);
+//! bed.run();
+//!
+//! assert_eq!(bed.data()[0], b"00:00:00");
+//! ```
+
+use std::sync::Arc;
+
+use iced::{
+    futures::stream::{SelectAll, StreamExt as _},
+    Task,
+};
+use iced_runtime::Action;
+use parking_lot::Mutex;
+use salish::{endpoint::Endpoint, filter::SourceFilter, router::MessageRouter, Message};
+use tracing::{debug, warn};
+
+use crate::{
+    message::module::{ModuleMessageData, PublishMessage, Topic},
+    Source,
+};
+
+use super::{
+    argument::ModuleArguments, data::ModuleData, error::ModuleError, manager::ModuleManager,
+    Module, ModuleHandleId,
+};
+
+/// Default number of messages handled by each call to [`TestBed::run()`] or [`TestBed::event()`]
+const DEFAULT_LIMIT: usize = 64;
+
+/// Messages emitted by the module under test
+#[derive(Debug, Default)]
+struct Collected {
+    data: Vec<Box<dyn ModuleData>>,
+    published: Vec<PublishMessage>,
+    subscriptions: Vec<Topic>,
+}
+
+/// Instantiates a single [`Module`] and collects the [`ModuleData`] and topic messages it emits
+pub struct TestBed<M: Module + Default + 'static> {
+    router: MessageRouter<'static, Task<Message>, Source>,
+    runtime: tokio::runtime::Runtime,
+    handle_id: ModuleHandleId,
+    init: Option<Task<Message>>,
+    limit: usize,
+    collected: Arc<Mutex<Collected>>,
+
+    manager: ModuleManager,
+    _data_endpoint: Endpoint<'static, Box<dyn ModuleData>, Task<Message>, Source>,
+    _module_endpoint: Endpoint<'static, ModuleMessageData, Task<Message>, Source>,
+    _module: std::marker::PhantomData<M>,
+}
+
+impl<M: Module + Default + 'static> std::fmt::Debug for TestBed<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TestBed")
+            .field("module", &std::any::type_name::<M>())
+            .field("handle_id", &self.handle_id)
+            .field("collected", &*self.collected.lock())
+            .finish()
+    }
+}
+
+impl<M: Module + Default + 'static> TestBed<M> {
+    /// Instantiate the module `M` with the supplied arguments.
+    ///
+    /// The module is not started until [`TestBed::run()`] is called.
+    pub fn new(args: ModuleArguments) -> Result<Self, ModuleError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        let router = MessageRouter::new();
+        let mut manager = ModuleManager::new(router.clone());
+
+        // Register the module under its type name, so it can't collide with registered modules
+        let name = std::any::type_name::<M>().to_string();
+        manager.register::<M>(&name)?;
+
+        let (handle_id, init) = manager.instantiate(&name, args)?;
+
+        let collected = Arc::new(Mutex::new(Collected::default()));
+
+        // Apply the transform and format arguments as the engine does
+        let output = manager.output_pipeline(handle_id);
+
+        let data_collected = collected.clone();
+        let data_endpoint = router
+            .create_endpoint::<Box<dyn ModuleData>>()
+            .filter(SourceFilter::default().add(Source::Module(handle_id)))
+            .message(move |_source, data| {
+                debug!("TestBed received data {data:?}");
+                match output.apply(data) {
+                    Ok(data) => data_collected.lock().data.push(data),
+                    Err(e) => warn!("TestBed output transform failed: {e}"),
+                }
+                Task::none()
+            });
+
+        let module_collected = collected.clone();
+        let module_endpoint = router
+            .create_endpoint::<ModuleMessageData>()
+            .filter(SourceFilter::default().add(Source::Module(handle_id)))
+            .message(move |_source, message| {
+                debug!("TestBed received module message {message:?}");
+                let mut collected = module_collected.lock();
+                match message {
+                    ModuleMessageData::Publish(publish) => collected.published.push(publish),
+                    ModuleMessageData::Subscribe(topic) => collected.subscriptions.push(topic),
+                    _ => {}
+                }
+                Task::none()
+            });
+
+        Ok(Self {
+            router,
+            runtime,
+            handle_id,
+            init: Some(init),
+            limit: DEFAULT_LIMIT,
+            collected,
+            manager,
+            _data_endpoint: data_endpoint,
+            _module_endpoint: module_endpoint,
+            _module: std::marker::PhantomData,
+        })
+    }
+
+    /// Set the maximum number of messages handled by each call to [`TestBed::run()`] or [`TestBed::event()`].
+    /// This bounds modules which emit messages indefinitely, such as periodic timers.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Get the [`ModuleHandleId`] of the module under test
+    pub fn handle_id(&self) -> ModuleHandleId {
+        self.handle_id
+    }
+
+    /// Get the [`ModuleManager`] of the module under test, whose services such as the
+    /// [`crate::module::state::StateStore`] can be prepared before calling [`TestBed::run()`]
+    pub fn manager(&self) -> &ModuleManager {
+        &self.manager
+    }
+
+    /// Start the module, and drive its tasks until they complete or the message limit is reached.
+    /// Calling this again after the module has started drives nothing.
+    pub fn run(&mut self) -> &mut Self {
+        if let Some(init) = self.init.take() {
+            self.drive(init);
+        }
+        self
+    }
+
+    /// Send an event to the module, and drive the resulting tasks
+    pub fn event(&mut self, event: M::Event) -> &mut Self {
+        self.run();
+
+        let message = Message::unicast(event).with_source(Source::Module(self.handle_id));
+        self.drive(Task::done(message));
+        self
+    }
+
+    /// Notify the module of shutdown, and drive its [`Module::on_shutdown()`] tasks
+    pub fn shutdown(&mut self) -> &mut Self {
+        let task = self.manager.shutdown_hooks().drain(crate::SHUTDOWN_TIMEOUT);
+        self.drive(task);
+        self
+    }
+
+    /// Set the visibility of the module, and drive its [`Module::on_visibility()`] tasks
+    pub fn visibility(&mut self, visible: bool) -> &mut Self {
+        self.run();
+
+        let task = self.manager.set_visible(self.handle_id, visible);
+        self.drive(task);
+        self
+    }
+
+    /// Take the [`ModuleData`] sent by the module since the last call
+    pub fn take_data(&mut self) -> Vec<Box<dyn ModuleData>> {
+        std::mem::take(&mut self.collected.lock().data)
+    }
+
+    /// Get the bytes of each [`ModuleData`] sent by the module
+    pub fn data(&self) -> Vec<Vec<u8>> {
+        self.collected
+            .lock()
+            .data
+            .iter()
+            .filter_map(|data| data.bytes().ok().cloned())
+            .collect()
+    }
+
+    /// Get the messages published to topics by the module
+    pub fn published(&self) -> Vec<PublishMessage> {
+        self.collected.lock().published.clone()
+    }
+
+    /// Get the topics the module has subscribed to
+    pub fn subscriptions(&self) -> Vec<Topic> {
+        self.collected.lock().subscriptions.clone()
+    }
+
+    /// Drive a task and every task returned from routing its messages
+    fn drive(&mut self, task: Task<Message>) {
+        let router = &mut self.router;
+        let limit = self.limit;
+
+        self.runtime.block_on(async {
+            let mut streams = SelectAll::new();
+            streams.extend(iced_runtime::task::into_stream(task));
+
+            let mut handled = 0;
+            while handled < limit {
+                let Some(action) = streams.next().await else {
+                    break;
+                };
+
+                if let Action::Output(message) = action {
+                    handled += 1;
+
+                    for task in router.handle_message(message).into_iter().flatten() {
+                        streams.extend(iced_runtime::task::into_stream(task));
+                    }
+                }
+            }
+
+            debug!("TestBed handled {handled} messages");
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use tokio::time::Instant;
+
+    use super::TestBed;
+    use crate::module::{
+        argument::ModuleArguments,
+        manager::ModuleStatus,
+        timing::{TimingEvent, TimingModule},
+    };
+
+    #[traced_test]
+    #[test]
+    fn timing_testbed() {
+        let args = ModuleArguments::new()
+            .arg("mode", r#""elapsed""#)
+            .arg("format", r#""%s""#)
+            .arg("periodic", r#""10ms""#)
+            .arg("topic", r#""testbed""#);
+
+        let mut bed = TestBed::<TimingModule>::new(args).unwrap().with_limit(16);
+        bed.run();
+
+        assert_eq!(bed.subscriptions(), vec!["testbed".into()]);
+        assert!(!bed.data().is_empty());
+        assert_eq!(bed.data()[0], b"0");
+        assert!(!bed.published().is_empty());
+    }
+
+    #[traced_test]
+    #[test]
+    fn running_after_init() {
+        let args = ModuleArguments::new().arg("mode", r#""clock""#);
+
+        let mut bed = TestBed::<TimingModule>::new(args).unwrap().with_limit(4);
+        let status = |bed: &TestBed<TimingModule>| bed.manager().instances()[0].status.clone();
+        assert_eq!(status(&bed), ModuleStatus::Initializing);
+
+        // The instance is running once its init task has completed
+        bed.run();
+        assert_eq!(status(&bed), ModuleStatus::Running);
+    }
+
+    #[traced_test]
+    #[test]
+    fn shutdown_stops_events() {
+        let args = ModuleArguments::new().arg("mode", r#""clock""#);
+
+        let mut bed = TestBed::<TimingModule>::new(args).unwrap().with_limit(4);
+        bed.run();
+        bed.event(TimingEvent::Tick(Instant::now()));
+        assert!(!bed.take_data().is_empty());
+
+        bed.shutdown();
+        bed.event(TimingEvent::Tick(Instant::now()));
+        assert!(bed.take_data().is_empty());
+    }
+
+    #[traced_test]
+    #[test]
+    fn timer_while_visible() {
+        let args = ModuleArguments::new()
+            .arg("mode", r#""clock""#)
+            .arg("periodic", r#""10ms""#)
+            .arg("while-visible", "true");
+
+        let mut bed = TestBed::<TimingModule>::new(args).unwrap().with_limit(4);
+        bed.run();
+        assert!(!bed.take_data().is_empty());
+
+        // The timer is paused while hidden
+        bed.visibility(false);
+        assert!(bed.take_data().is_empty());
+
+        // and restarted once visible again
+        bed.visibility(true);
+        assert!(!bed.take_data().is_empty());
+    }
+}
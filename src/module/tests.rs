@@ -4,8 +4,13 @@ use tracing::debug;
 use tracing_test::traced_test;
 
 use crate::{
-    module::{argument::ModuleArguments, manager::ModuleManager},
-    Source,
+    module::{
+        argument::ModuleArguments,
+        error::ModuleError,
+        manager::{ModuleManager, ModuleStatus},
+        timing::TimingModule,
+    },
+    SnowcapParser, Source,
 };
 
 #[traced_test]
@@ -22,3 +27,68 @@ fn message() {
 
     debug!("{manager:#?}");
 }
+
+#[traced_test]
+#[test]
+fn inspect_instances() {
+    let router = MessageRouter::<Task<Message>, Source>::new();
+    let mut manager = ModuleManager::new(router);
+
+    let args = ModuleArguments::new().arg("periodic", r#""1s""#);
+    let (handle_id, _task) = manager.instantiate(&"timing".into(), args).unwrap();
+    manager.attach_node(handle_id, 7);
+
+    let instances = manager.instances();
+    assert_eq!(instances.len(), 1);
+    assert_eq!(instances[0].handle_id, handle_id);
+    assert_eq!(instances[0].name, "timing");
+    assert_eq!(instances[0].nodes, vec![7]);
+    assert_eq!(instances[0].status, ModuleStatus::Initializing);
+    assert!(instances[0].args.get("periodic").is_ok());
+}
+
+#[traced_test]
+#[test]
+fn namespaced_registration() {
+    let router = MessageRouter::<Task<Message>, Source>::new();
+    let mut manager = ModuleManager::new(router);
+
+    // Registering the same type again is allowed
+    manager.register::<TimingModule>("test.timing").unwrap();
+    manager.register::<TimingModule>("test.timing").unwrap();
+
+    let args = ModuleArguments::new().arg("periodic", r#""1s""#);
+    assert!(manager.instantiate(&"test.timing".into(), args).is_ok());
+
+    // A name registered to another module type collides
+    assert!(matches!(
+        manager.register::<TimingModule>("http"),
+        Err(ModuleError::NameCollision { .. })
+    ));
+}
+
+#[traced_test]
+#[test]
+fn validate_args_at_parse() {
+    // Registers the internal modules
+    let _manager = ModuleManager::new(MessageRouter::<Task<Message>, Source>::new());
+
+    assert!(SnowcapParser::<Message>::parse_memory(
+        r#"{text(http!{url:"http://example.com", on-error:"N/A"})}"#
+    )
+    .is_ok());
+
+    let error = SnowcapParser::<Message>::parse_memory(
+        "{|[\n    text(\"A\"),\n    text(http!{ulr:\"http://example.com\"})\n]}",
+    )
+    .unwrap_err();
+    assert_eq!(error.location().line, 3);
+    assert!(error.to_string().contains("did you mean 'url'?"));
+
+    // Required arguments and argument values are checked
+    assert!(SnowcapParser::<Message>::parse_memory(r#"{image(file!{})}"#).is_err());
+    assert!(SnowcapParser::<Message>::parse_memory(r#"{text(http!{url:"not a url"})}"#).is_err());
+
+    // Modules which aren't registered are checked when they are instantiated
+    assert!(SnowcapParser::<Message>::parse_memory(r#"{text(unregistered!{any:1})}"#).is_ok());
+}
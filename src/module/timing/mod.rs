@@ -1,9 +1,30 @@
+//! Timing module, publishing a trigger message to a topic on a schedule.
+//!
+//! ```text
+//! timing!{periodic:"1s"}
+//! timing!{cron:"0 */5 * * * *", topic:"refresh"}
+//! timing!{delay:"10s", topic:"startup"}
+//! text(timing!{mode:"clock", format:"%H:%M:%S"})
+//! text(timing!{mode:"countdown", duration:"5m", format:"%M:%S"})
+//! text(timing!{mode:"clock", periodic:"1s", while-visible:true})
+//! ```
+//!
+//! Each instance publishes to the topic given by the `topic` argument, or `tick` if unspecified.
+//! See [`TimerSpec`] for the scheduling arguments.
+//!
+//! With a `mode` argument, the module also sends a formatted string as its data on each trigger,
+//! defaulting to a one second period if no schedule is given. See [`TimingOutput`] for the modes.
+//!
+//! With `while-visible:true` the timer is paused while the nodes referencing the instance are hidden,
+//! or the window is minimized, and restarted when they are visible again.
+
 use async_trait::async_trait;
-use iced::Task;
+use iced::{task::Handle, Task};
 use salish::Message;
 use tokio::time::Instant;
-use tokio_stream::wrappers::IntervalStream;
-use tracing::{debug, error};
+use tracing::{debug, warn};
+
+use std::time::Duration;
 
 use crate::{
     message::module::{ModuleMessageData, PublishMessage, Topic, TopicMessage},
@@ -11,30 +32,122 @@ use crate::{
     NodeRef,
 };
 
-use super::{data::ModuleData, error::ModuleError, Module, ModuleEvent, ModuleInitData};
+use super::internal::ModuleInternal;
+use super::{
+    data::{ModuleData, ModuleDataKind},
+    error::ModuleError,
+    Module, ModuleEvent, ModuleInitData,
+};
+
+pub mod output;
+pub mod schedule;
+
+pub use output::{TimingMode, TimingOutput};
+pub use schedule::TimerSpec;
+
+/// Topic published to when no `topic` argument is given
+const DEFAULT_TOPIC: &str = "tick";
+
+/// Period of the timer when a `mode` is given without a schedule
+const DEFAULT_OUTPUT_PERIOD: Duration = Duration::from_secs(1);
 
+/// Formatted output of the timing module, rendered by [`TimingOutput`]
 #[derive(Debug)]
-pub struct TimingData;
+pub struct TimingData {
+    bytes: Vec<u8>,
+}
+
+impl TimingData {
+    pub fn new(text: String) -> Self {
+        Self {
+            bytes: text.into_bytes(),
+        }
+    }
+}
+
 impl ModuleData for TimingData {
-    fn kind(&self) -> super::data::ModuleDataKind {
-        todo!()
+    fn kind(&self) -> ModuleDataKind {
+        ModuleDataKind::Text
     }
 
     fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
-        todo!()
+        Ok(&self.bytes)
     }
 }
 
 #[derive(Debug)]
 pub enum TimingEvent {
-    Init(IntervalStream),
+    Init(TimerSpec),
     Tick(Instant),
     Failed,
 }
 impl ModuleEvent for TimingEvent {}
 
-#[derive(Default, Debug)]
-pub struct TimingModule {}
+#[derive(Debug)]
+pub struct TimingModule {
+    topic: Topic,
+    output: Option<TimingOutput>,
+
+    /// Pause the timer while hidden
+    while_visible: bool,
+
+    /// Schedule of the timer, kept to restart it when visible again
+    spec: Option<TimerSpec>,
+
+    /// Abort handle of the running timer
+    timer: Option<Handle>,
+}
+
+impl Default for TimingModule {
+    fn default() -> Self {
+        Self {
+            topic: Topic::new(DEFAULT_TOPIC),
+            output: None,
+            while_visible: false,
+            spec: None,
+            timer: None,
+        }
+    }
+}
+
+impl TimingModule {
+    /// Get a Task publishing a trigger to the topic of this instance
+    fn publish(&self) -> Task<Message> {
+        Task::done(Message::broadcast(ModuleMessageData::Publish(
+            PublishMessage {
+                topic: self.topic.clone(),
+                message: TopicMessage::Trigger,
+            },
+        )))
+    }
+
+    /// Start the timer stream of the schedule, keeping an abort handle to pause it
+    fn start_timer(&mut self) -> Task<Message> {
+        let Some(spec) = self.spec.clone() else {
+            return Task::none();
+        };
+
+        let topic = self.topic.clone();
+
+        let timer = if self.output.is_some() {
+            // Route each trigger back into on_event() to render the output
+            Task::run(spec.stream(), |instant| {
+                Message::unicast(TimingEvent::Tick(instant))
+            })
+        } else {
+            Task::run(spec.stream(), move |_instant| {
+                Message::broadcast(ModuleMessageData::Publish(PublishMessage {
+                    topic: topic.clone(),
+                    message: TopicMessage::Trigger,
+                }))
+            })
+        };
+
+        let (timer, handle) = timer.abortable();
+        self.timer = Some(handle);
+        timer
+    }
+}
 
 #[async_trait]
 impl Module for TimingModule {
@@ -48,28 +161,23 @@ impl Module for TimingModule {
     ) -> Result<Self::Event, ModuleError> {
         debug!("Timing module init");
 
-        /*
-        let interval = args
-            .get("interval")
-            .unwrap_or(&Value::new_string("1s".into()));
-        */
-        let interval = args.get("interval")?;
+        if let Ok(topic) = args.get("topic") {
+            self.topic = Topic::new(topic.to_string());
+        }
 
-        let interval: String = interval.to_string();
-        match duration_str::parse(interval) {
-            Ok(duration) => {
-                let interval = tokio::time::interval(duration);
-                let stream = IntervalStream::new(interval);
+        self.output = TimingOutput::from_args(&args)?;
 
-                Ok(TimingEvent::Init(stream))
-            }
-            Err(e) => {
-                error!("Failed to convert interval argument");
-                Err(ModuleError::InvalidArgument(format!(
-                    "Cannot parse interval: '{e}'"
-                )))
+        self.while_visible = args.get_bool_or("while-visible", false)?;
+
+        let spec = match TimerSpec::from_args(&args) {
+            // Outputs without a schedule update every second
+            Err(ModuleError::MissingArgument(_)) if self.output.is_some() => {
+                TimerSpec::periodic(DEFAULT_OUTPUT_PERIOD)
             }
-        }
+            spec => spec?,
+        };
+
+        Ok(TimingEvent::Init(spec))
     }
 
     fn init_tree(&mut self, tree: Option<&NodeRef>) {
@@ -78,21 +186,26 @@ impl Module for TimingModule {
 
     fn on_event(&mut self, event: Self::Event) -> Task<Message> {
         match event {
-            TimingEvent::Init(stream) => Task::done(Message::broadcast(
-                ModuleMessageData::Subscribe(Topic("tick")),
-            ))
-            .chain(Task::run(stream, |_instant| {
-                Message::broadcast(ModuleMessageData::Publish(PublishMessage {
-                    topic: Topic("tick"),
-                    message: TopicMessage::Trigger,
-                }))
-            })),
-            TimingEvent::Tick(instant) => {
-                println!("Timing Module: TICK {instant:?}");
-                Task::none()
+            TimingEvent::Init(spec) => {
+                let topic = self.topic.clone();
+                debug!("Starting timer {spec:?} publishing to {topic}");
+
+                let subscribe = Task::done(Message::broadcast(ModuleMessageData::Subscribe(
+                    topic.clone(),
+                )));
+
+                self.spec = Some(spec);
+                subscribe.chain(self.start_timer())
             }
+            TimingEvent::Tick(instant) => match &self.output {
+                Some(output) => {
+                    let data = TimingData::new(output.render(instant));
+                    Task::batch([self.send_data(data), self.publish()])
+                }
+                None => self.publish(),
+            },
             TimingEvent::Failed => {
-                println!("Timing module failed event");
+                warn!("Timing module failed event");
                 Task::none()
             }
         }
@@ -102,6 +215,24 @@ impl Module for TimingModule {
         Task::none()
     }
 
+    fn on_visibility(&mut self, visible: bool) -> Task<Message> {
+        if !self.while_visible {
+            return Task::none();
+        }
+
+        if visible {
+            if self.timer.is_none() {
+                debug!("Resuming timer publishing to {}", self.topic);
+                return self.start_timer();
+            }
+        } else if let Some(timer) = self.timer.take() {
+            debug!("Pausing timer publishing to {} while hidden", self.topic);
+            timer.abort();
+        }
+
+        Task::none()
+    }
+
     fn on_subscription(&mut self, _topic: Topic, _message: TopicMessage) -> Task<Message> {
         Task::none()
     }
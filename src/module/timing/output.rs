@@ -0,0 +1,156 @@
+//! Formatted data output of the timing module, for rendering clocks and countdowns
+
+use std::time::Duration;
+
+use chrono::format::{Item, StrftimeItems};
+use strum::{EnumString, VariantNames};
+use tokio::time::Instant;
+
+use crate::module::{argument::ModuleArguments, error::ModuleError};
+
+/// What the timing module renders as its data on each trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum TimingMode {
+    /// Local wall clock time, formatted with chrono format specifiers
+    Clock,
+    /// Time elapsed since the module started
+    Elapsed,
+    /// Time remaining of the `duration` argument since the module started
+    Countdown,
+}
+
+/// Renders the data of the timing module from the `mode`, `format` and `duration` arguments.
+///
+/// In `clock` mode the format uses chrono specifiers such as `%H:%M:%S`. The `elapsed` and
+/// `countdown` modes accept `%H` hours, `%M` minutes, `%S` seconds, `%s` total seconds and `%%`.
+#[derive(Debug, Clone)]
+pub struct TimingOutput {
+    mode: TimingMode,
+    format: String,
+    duration: Duration,
+    start: Instant,
+}
+
+impl TimingOutput {
+    /// Create a [`TimingOutput`] from module arguments. Returns None if no `mode` argument was given.
+    pub fn from_args(args: &ModuleArguments) -> Result<Option<Self>, ModuleError> {
+        if args.get("mode").is_err() {
+            return Ok(None);
+        }
+
+        let mode = args.get_enum::<TimingMode>("mode")?;
+
+        let format = args
+            .get("format")
+            .map(|format| format.to_string())
+            .unwrap_or_else(|_| "%H:%M:%S".into());
+
+        // Formatting the clock with an invalid specifier panics, so reject it up front
+        if mode == TimingMode::Clock
+            && StrftimeItems::new(&format).any(|item| matches!(item, Item::Error))
+        {
+            return Err(ModuleError::InvalidArgument(format!(
+                "format '{format}' has an invalid specifier"
+            )));
+        }
+
+        let duration = match mode {
+            TimingMode::Countdown => args.get_duration("duration")?,
+            _ => Duration::ZERO,
+        };
+
+        Ok(Some(Self {
+            mode,
+            format,
+            duration,
+            start: Instant::now(),
+        }))
+    }
+
+    pub fn mode(&self) -> TimingMode {
+        self.mode
+    }
+
+    /// Render the output at the supplied [`Instant`]
+    pub fn render(&self, now: Instant) -> String {
+        match self.mode {
+            TimingMode::Clock => chrono::Local::now().format(&self.format).to_string(),
+            TimingMode::Elapsed => format_duration(&self.format, now - self.start),
+            TimingMode::Countdown => {
+                format_duration(&self.format, self.duration.saturating_sub(now - self.start))
+            }
+        }
+    }
+}
+
+/// Format a [`Duration`] using `%H`, `%M`, `%S`, `%s` and `%%` specifiers
+pub fn format_duration(format: &str, duration: Duration) -> String {
+    let total = duration.as_secs();
+    let mut out = String::with_capacity(format.len());
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('H') => out.push_str(&format!("{:02}", total / 3600)),
+            Some('M') => out.push_str(&format!("{:02}", (total / 60) % 60)),
+            Some('S') => out.push_str(&format!("{:02}", total % 60)),
+            Some('s') => out.push_str(&total.to_string()),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_test::traced_test;
+
+    use super::{format_duration, TimingMode, TimingOutput};
+    use crate::module::argument::ModuleArguments;
+
+    #[traced_test]
+    #[test]
+    fn duration_format() {
+        let duration = Duration::from_secs(3 * 3600 + 25 * 60 + 7);
+        assert_eq!(format_duration("%H:%M:%S", duration), "03:25:07");
+        assert_eq!(format_duration("%ss left %%", duration), "12307s left %");
+    }
+
+    #[traced_test]
+    #[test]
+    fn output_args() {
+        let args = ModuleArguments::new();
+        assert!(TimingOutput::from_args(&args).unwrap().is_none());
+
+        let args = ModuleArguments::new().arg("mode", r#""clock""#);
+        let output = TimingOutput::from_args(&args).unwrap().unwrap();
+        assert_eq!(output.mode(), TimingMode::Clock);
+
+        // Countdown requires a duration
+        let args = ModuleArguments::new().arg("mode", r#""countdown""#);
+        assert!(TimingOutput::from_args(&args).is_err());
+
+        let args = ModuleArguments::new().arg("mode", r#""sundial""#);
+        assert!(TimingOutput::from_args(&args).is_err());
+
+        // Invalid chrono specifiers are rejected rather than panicking when the clock renders
+        let args = ModuleArguments::new()
+            .arg("mode", r#""clock""#)
+            .arg("format", r#""%H:%Q""#);
+        assert!(TimingOutput::from_args(&args).is_err());
+    }
+}
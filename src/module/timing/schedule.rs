@@ -0,0 +1,201 @@
+//! Timer schedules parsed from the timing module arguments
+
+use std::str::FromStr as _;
+use std::time::Duration;
+
+use iced::futures::stream::{self, BoxStream, StreamExt as _};
+use tokio::time::Instant;
+use tokio_stream::wrappers::IntervalStream;
+
+use crate::module::{argument::ModuleArguments, error::ModuleError};
+
+/// When a timer should trigger
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    /// Trigger repeatedly at a fixed interval
+    Periodic(Duration),
+
+    /// Trigger at each time matching a cron expression, evaluated in local time
+    Cron(Box<cron::Schedule>),
+
+    /// Trigger a single time, after the configured delay
+    Once,
+}
+
+/// Timer specification built from [`ModuleArguments`]
+///
+/// | Argument   | Description |
+/// |------------|-------------|
+/// | `periodic` | Trigger at a fixed interval, such as `"1s"` or `"500ms"`. `interval` is accepted as an alias |
+/// | `cron`     | Trigger on a cron schedule with a seconds field, such as `"0 */5 * * * *"` |
+/// | `delay`    | Wait before the schedule starts. With no `periodic` or `cron`, triggers once after the delay |
+/// | `once`     | Stop after the first trigger |
+#[derive(Debug, Clone)]
+pub struct TimerSpec {
+    schedule: Schedule,
+    delay: Option<Duration>,
+    once: bool,
+}
+
+impl TimerSpec {
+    /// Build a [`TimerSpec`] from module arguments
+    pub fn from_args(args: &ModuleArguments) -> Result<Self, ModuleError> {
+        let periodic = ["periodic", "interval"]
+            .into_iter()
+            .find(|name| args.get(name).is_ok())
+            .map(|name| args.get_duration(name))
+            .transpose()?;
+
+        let cron = args
+            .get("cron")
+            .ok()
+            .map(|value| {
+                cron::Schedule::from_str(&value.to_string()).map_err(|e| {
+                    ModuleError::InvalidArgument(format!("Cannot parse cron '{value}': {e}"))
+                })
+            })
+            .transpose()?;
+
+        let delay = args
+            .get("delay")
+            .ok()
+            .map(|_| args.get_duration("delay"))
+            .transpose()?;
+
+        let once = args.get_bool_or("once", false)?;
+
+        let schedule = match (periodic, cron) {
+            (Some(_), Some(_)) => {
+                return Err(ModuleError::InvalidArgument(
+                    "periodic and cron cannot both be specified".into(),
+                ))
+            }
+            (Some(period), None) => Schedule::Periodic(period),
+            (None, Some(cron)) => Schedule::Cron(Box::new(cron)),
+            (None, None) if delay.is_some() || once => Schedule::Once,
+            (None, None) => {
+                return Err(ModuleError::MissingArgument(
+                    "one of periodic, cron, delay or once".into(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            schedule,
+            delay,
+            once,
+        })
+    }
+
+    /// Create a [`TimerSpec`] triggering at a fixed interval
+    pub fn periodic(period: Duration) -> Self {
+        Self {
+            schedule: Schedule::Periodic(period),
+            delay: None,
+            once: false,
+        }
+    }
+
+    /// Get the [`Schedule`] of this timer
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// Get the initial delay of this timer
+    pub fn delay(&self) -> Option<Duration> {
+        self.delay
+    }
+
+    /// Returns true if this timer triggers at most once
+    pub fn is_once(&self) -> bool {
+        self.once || matches!(self.schedule, Schedule::Once)
+    }
+
+    /// Create a stream which yields an [`Instant`] each time the timer triggers
+    pub fn stream(self) -> BoxStream<'static, Instant> {
+        let once = self.is_once();
+        let delay = self.delay.unwrap_or_default();
+
+        let ticks = match self.schedule {
+            Schedule::Periodic(period) => {
+                IntervalStream::new(tokio::time::interval(period)).boxed()
+            }
+            Schedule::Cron(schedule) => stream::unfold(schedule, |schedule| async move {
+                let next = schedule.upcoming(chrono::Local).next()?;
+                let wait = (next - chrono::Local::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+                Some((Instant::now(), schedule))
+            })
+            .boxed(),
+            Schedule::Once => stream::once(async { Instant::now() }).boxed(),
+        };
+
+        let ticks = stream::once(async move {
+            tokio::time::sleep(delay).await;
+            ticks
+        })
+        .flatten();
+
+        if once {
+            ticks.take(1).boxed()
+        } else {
+            ticks.boxed()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_test::traced_test;
+
+    use super::{Schedule, TimerSpec};
+    use crate::module::argument::ModuleArguments;
+
+    #[traced_test]
+    #[test]
+    fn periodic() {
+        let args = ModuleArguments::new().arg("periodic", r#""500ms""#);
+        let spec = TimerSpec::from_args(&args).unwrap();
+        assert!(
+            matches!(spec.schedule(), Schedule::Periodic(d) if *d == Duration::from_millis(500))
+        );
+        assert!(!spec.is_once());
+    }
+
+    #[traced_test]
+    #[test]
+    fn cron() {
+        let args = ModuleArguments::new()
+            .arg("cron", r#""0 */5 * * * *""#)
+            .arg("once", "true");
+        let spec = TimerSpec::from_args(&args).unwrap();
+        assert!(matches!(spec.schedule(), Schedule::Cron(_)));
+        assert!(spec.is_once());
+    }
+
+    #[traced_test]
+    #[test]
+    fn delay() {
+        let args = ModuleArguments::new().arg("delay", r#""2s""#);
+        let spec = TimerSpec::from_args(&args).unwrap();
+        assert!(matches!(spec.schedule(), Schedule::Once));
+        assert_eq!(spec.delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[traced_test]
+    #[test]
+    fn invalid() {
+        assert!(TimerSpec::from_args(&ModuleArguments::new()).is_err());
+        assert!(
+            TimerSpec::from_args(&ModuleArguments::new().arg("cron", r#""not a cron""#)).is_err()
+        );
+        assert!(TimerSpec::from_args(
+            &ModuleArguments::new()
+                .arg("periodic", r#""1s""#)
+                .arg("cron", r#""* * * * * *""#)
+        )
+        .is_err());
+    }
+}
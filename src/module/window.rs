@@ -0,0 +1,241 @@
+//! Window module, exposing the size of the application window as data.
+//!
+//! ```text
+//! text(window!{})
+//! text(window!{field:"width"})
+//! ```
+//!
+//! The data of the module is the size of the window formatted as `{width}x{height}`, and the `width` and
+//! `height` fields can be selected with the `field` argument. It is updated each time the window is resized.
+//!
+//! Window events are published by the engine to topics, which modules can subscribe to:
+//!
+//! | Topic                    | Message                                                |
+//! |--------------------------|--------------------------------------------------------|
+//! | `window/resized`         | [`iced::Size`] of the window, as [`TopicMessage::Any`] |
+//! | `window/focused`         | [`TopicMessage::Trigger`]                              |
+//! | `window/unfocused`       | [`TopicMessage::Trigger`]                              |
+//! | `window/close-requested` | [`TopicMessage::Trigger`]                              |
+//! | `window/file-hovered`    | Path of a file dragged over the window, as a string    |
+//! | `window/file-dropped`    | Path of a file dropped onto the window, as a string    |
+//! | `window/files-left`      | [`TopicMessage::Trigger`] when dragged files leave     |
+//!
+//! Events are received by the engine from [`crate::Snowcap::subscription()`], which the application must include
+//! in its subscriptions. Close requests are only delivered if the application disables `exit_on_close_request`.
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use iced::{window, Size, Subscription, Task};
+use salish::Message;
+use tracing::debug;
+
+use crate::{
+    message::{
+        module::{ModuleMessageData, Topic, TopicMessage},
+        Command,
+    },
+    module::argument::ModuleArguments,
+};
+
+use super::{
+    data::{ModuleData, ModuleDataKind, TextData},
+    error::ModuleError,
+    internal::ModuleInternal,
+    pubsub::publish,
+    Module, ModuleEvent, ModuleInitData,
+};
+
+/// Topic the size of the window is published to when resized
+pub const RESIZED_TOPIC: &str = "window/resized";
+
+/// Topic published to when the window gains focus
+pub const FOCUSED_TOPIC: &str = "window/focused";
+
+/// Topic published to when the window loses focus
+pub const UNFOCUSED_TOPIC: &str = "window/unfocused";
+
+/// Topic published to when closing the window is requested
+pub const CLOSE_REQUESTED_TOPIC: &str = "window/close-requested";
+
+/// Topic the path of a file dragged over the window is published to
+pub const FILE_HOVERED_TOPIC: &str = "window/file-hovered";
+
+/// Topic the path of a file dropped onto the window is published to
+pub const FILE_DROPPED_TOPIC: &str = "window/file-dropped";
+
+/// Topic published to when files dragged over the window leave it without being dropped
+pub const FILES_LEFT_TOPIC: &str = "window/files-left";
+
+/// Last known size of the window of an engine, for module instances created after the window opened and for
+/// layouts relative to the window. Clones share the same size.
+#[derive(Debug, Clone, Default)]
+pub struct WindowSize(Arc<Mutex<Option<Size>>>);
+
+impl WindowSize {
+    /// Get the last known size of the window, if it has been opened
+    pub fn get(&self) -> Option<Size> {
+        self.0.lock().ok().and_then(|size| *size)
+    }
+
+    /// Record the size of the window
+    fn set(&self, size: Size) {
+        if let Ok(mut current) = self.0.lock() {
+            *current = Some(size);
+        }
+    }
+}
+
+/// Get a [`Subscription`] forwarding window events to the engine as [`Command::Window`] messages
+pub(crate) fn subscription() -> Subscription<Message> {
+    iced::event::listen_with(|event, _status, _id| match event {
+        iced::Event::Window(
+            event @ (window::Event::Opened { .. }
+            | window::Event::Resized(_)
+            | window::Event::Focused
+            | window::Event::Unfocused
+            | window::Event::CloseRequested
+            | window::Event::FileHovered(_)
+            | window::Event::FileDropped(_)
+            | window::Event::FilesHoveredLeft),
+        ) => Some(Message::broadcast(Command::Window(event))),
+        _ => None,
+    })
+}
+
+/// Record a window event in the [`WindowSize`] of the engine, and get a [`Task`] publishing it to its topic
+pub(crate) fn handle_event(event: window::Event, window: &WindowSize) -> Task<Message> {
+    match event {
+        window::Event::Opened { size, .. } | window::Event::Resized(size) => {
+            window.set(size);
+            publish(RESIZED_TOPIC, TopicMessage::any(size))
+        }
+        window::Event::Focused => publish(FOCUSED_TOPIC, TopicMessage::Trigger),
+        window::Event::Unfocused => publish(UNFOCUSED_TOPIC, TopicMessage::Trigger),
+        window::Event::CloseRequested => publish(CLOSE_REQUESTED_TOPIC, TopicMessage::Trigger),
+        window::Event::FileHovered(path) => publish(
+            FILE_HOVERED_TOPIC,
+            TopicMessage::String(path.display().to_string()),
+        ),
+        window::Event::FileDropped(path) => publish(
+            FILE_DROPPED_TOPIC,
+            TopicMessage::String(path.display().to_string()),
+        ),
+        window::Event::FilesHoveredLeft => publish(FILES_LEFT_TOPIC, TopicMessage::Trigger),
+        _ => Task::none(),
+    }
+}
+
+/// Size of the window, with `width` and `height` fields
+#[derive(Debug)]
+pub struct WindowData {
+    bytes: Vec<u8>,
+    size: Size,
+}
+
+impl WindowData {
+    pub fn new(size: Size) -> Self {
+        Self {
+            bytes: format!("{}x{}", size.width, size.height).into_bytes(),
+            size,
+        }
+    }
+}
+
+impl ModuleData for WindowData {
+    fn kind(&self) -> ModuleDataKind {
+        ModuleDataKind::Text
+    }
+
+    fn bytes(&self) -> Result<&Vec<u8>, ModuleError> {
+        Ok(&self.bytes)
+    }
+
+    fn field(&self, name: &str) -> Option<Box<dyn ModuleData>> {
+        match name {
+            "width" => Some(Box::new(TextData::new(self.size.width.to_string()))),
+            "height" => Some(Box::new(TextData::new(self.size.height.to_string()))),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WindowEvent {
+    Init,
+}
+impl ModuleEvent for WindowEvent {}
+
+#[derive(Debug, Default)]
+pub struct WindowModule {
+    window: WindowSize,
+}
+
+#[async_trait]
+impl Module for WindowModule {
+    type Event = WindowEvent;
+    type Data = WindowData;
+
+    async fn init(
+        &mut self,
+        _args: ModuleArguments,
+        init_data: ModuleInitData,
+    ) -> Result<Self::Event, ModuleError> {
+        self.window = init_data.window_size().clone();
+        Ok(WindowEvent::Init)
+    }
+
+    fn on_event(&mut self, event: Self::Event) -> Task<Message> {
+        match event {
+            WindowEvent::Init => {
+                let subscribe = Task::done(Message::broadcast(ModuleMessageData::Subscribe(
+                    Topic::new(RESIZED_TOPIC),
+                )));
+
+                match self.window.get() {
+                    Some(size) => subscribe.chain(self.send_data(WindowData::new(size))),
+                    None => subscribe,
+                }
+            }
+        }
+    }
+
+    fn on_subscription(&mut self, topic: Topic, message: TopicMessage) -> Task<Message> {
+        match message.downcast_ref::<Size>() {
+            Some(size) => {
+                debug!("Window resized to {size:?}");
+                self.send_data(WindowData::new(*size))
+            }
+            None => {
+                debug!("Ignoring message on {topic}");
+                Task::none()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::{window, Size};
+    use tracing_test::traced_test;
+
+    use super::{handle_event, WindowModule};
+    use crate::module::{argument::ModuleArguments, data::ModuleData as _, testing::TestBed};
+
+    #[traced_test]
+    #[test]
+    fn window_size() {
+        let mut bed = TestBed::<WindowModule>::new(ModuleArguments::new()).unwrap();
+        let _ = handle_event(
+            window::Event::Resized(Size::new(800.0, 600.0)),
+            bed.manager().window_size(),
+        );
+        bed.run();
+
+        assert_eq!(bed.data()[0], b"800x600");
+
+        let data = bed.take_data();
+        let width = data[0].field("width").unwrap();
+        assert_eq!(width.bytes().unwrap(), b"800");
+    }
+}
@@ -2,9 +2,13 @@
 
 use colored::Colorize;
 use parking_lot::Mutex;
+use std::ops::Range;
 use std::string::ToString;
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 use std::{
     hash::{Hash, Hasher},
     ops::Deref,
@@ -13,6 +17,7 @@ use std::{
 use strum::{EnumDiscriminants, EnumIter};
 use xxhash_rust::xxh64::Xxh64;
 
+use crate::identity::StableId;
 use crate::module::data::ModuleData;
 use crate::parser::module::Module;
 use crate::{attribute::Attributes, Value};
@@ -29,6 +34,13 @@ pub enum Content {
     Row,
     Column,
     Stack,
+    /// Column which only builds widgets for the children visible in its viewport
+    LazyColumn,
+    /// Error boundary. The first child is the guarded element, and the second child is the fallback
+    Boundary,
+    /// Named region of markup, which can be reloaded without diffing the rest of the tree. The only child is the
+    /// element of the region.
+    Region,
     #[strum(to_string = "Value: {0}")]
     Value(Value),
     #[strum(to_string = "Module {0}")]
@@ -49,6 +61,18 @@ impl Default for Content {
     }
 }
 
+/// Incremented whenever a node is created, or its state changes to [`State::New`] or [`State::Dirty`]
+static DIRTY_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// Get the dirty epoch of nodes. If it hasn't changed since a tree was updated, none of its nodes need updates.
+pub(crate) fn dirty_epoch() -> u64 {
+    DIRTY_EPOCH.load(Ordering::Acquire)
+}
+
+fn advance_epoch() {
+    DIRTY_EPOCH.fetch_add(1, Ordering::AcqRel);
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum State {
     /// Node newly introduced to tree
@@ -70,10 +94,17 @@ pub struct SnowcapNode {
     //pub widget: Option<DynamicWidget<M>>,
     state: State,
     module_data: Option<Box<dyn ModuleData>>,
+
+    /// Identity of this node which is stable across reloads. Assigned when the tree is indexed.
+    stable_id: Option<StableId>,
+
+    /// Byte range of the element in the markup this node was parsed from
+    span: Option<Range<usize>>,
 }
 
 impl Clone for SnowcapNode {
     fn clone(&self) -> Self {
+        advance_epoch();
         SnowcapNode {
             element_id: self.element_id.clone(),
             attrs: self.attrs.clone(),
@@ -81,6 +112,8 @@ impl Clone for SnowcapNode {
             //widget: None,
             state: State::New,
             module_data: None,
+            stable_id: self.stable_id.clone(),
+            span: self.span.clone(),
         }
     }
 }
@@ -113,6 +146,7 @@ impl std::fmt::Display for SnowcapNode {
 
 impl Default for SnowcapNode {
     fn default() -> Self {
+        advance_epoch();
         Self {
             content: Content::default(),
             element_id: None,
@@ -120,6 +154,8 @@ impl Default for SnowcapNode {
             //widget: None,
             state: State::New,
             module_data: None,
+            stable_id: None,
+            span: None,
         }
     }
 }
@@ -180,6 +216,9 @@ impl SnowcapNode {
 
     pub fn set_state(&mut self, state: State) {
         //debug!("{} {:?} -> {:?}", self, self.state, state);
+        if state != State::Clean {
+            advance_epoch();
+        }
         self.state = state
     }
 
@@ -205,6 +244,32 @@ impl SnowcapNode {
     pub fn module_data(&self) -> Option<&Box<dyn ModuleData>> {
         self.module_data.as_ref()
     }
+
+    /// Get the [`StableId`] of this node
+    pub fn stable_id(&self) -> Option<&StableId> {
+        self.stable_id.as_ref()
+    }
+
+    /// Set the [`StableId`] of this node
+    pub fn set_stable_id(&mut self, id: StableId) {
+        self.stable_id = Some(id);
+    }
+
+    /// Add the byte range of the element in the markup to this node
+    pub fn with_span(mut self, span: Range<usize>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Get the byte range of the element in the markup this node was parsed from
+    pub fn span(&self) -> Option<&Range<usize>> {
+        self.span.as_ref()
+    }
+
+    /// Set the byte range of the element in the markup
+    pub fn set_span(&mut self, span: Option<Range<usize>>) {
+        self.span = span;
+    }
 }
 
 /// Deref into the inner [`Content`]
@@ -2,7 +2,9 @@
 
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
 
 use arbutus::{NodeBuilder, TreeBuilder, TreeNodeRef as _};
 use attribute::AttributeParser;
@@ -10,10 +12,12 @@ use module::ModuleParser;
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 use pest_derive::Parser;
+use style::StyleParser;
+use theme::ThemeParser;
 use tracing::{debug, debug_span};
 use value::{ValueData, ValueParser};
 
-use crate::attribute::Attributes;
+use crate::attribute::{Attribute, AttributeValue, Attributes};
 
 use crate::node::{Content, SnowcapNode};
 use crate::Tree;
@@ -21,11 +25,18 @@ use crate::Tree;
 pub(crate) mod attribute;
 pub(crate) mod color;
 pub(crate) mod error;
+pub(crate) mod expr;
 pub(crate) mod gradient;
 mod hash;
+pub(crate) mod incremental;
+pub(crate) mod location;
 pub(crate) mod module;
+pub(crate) mod style;
+pub(crate) mod theme;
+pub(crate) mod validate;
 pub(crate) mod value;
 
+pub use validate::ParserOptions;
 pub use value::Value;
 
 #[cfg(test)]
@@ -75,7 +86,12 @@ where
     pub fn parse_file(filename: &Path) -> Result<Tree, crate::Error> {
         tracing::info!("Parsing file {filename:?}");
         let data = std::fs::read_to_string(filename).expect("cannot read file");
-        SnowcapParser::<M>::parse_memory(data.as_str()).map_err(|e| crate::Error::Parse(e))
+        let options = ParserOptions {
+            asset_dir: filename.parent().map(Path::to_path_buf),
+            ..Default::default()
+        };
+        SnowcapParser::<M>::parse_memory_with(data.as_str(), &options)
+            .map_err(|e| crate::Error::Parse(e))
     }
 
     /// Parse a Snowcap string from memory into an [`arbutus::Tree`].
@@ -88,19 +104,61 @@ where
     ///
     /// A `Result` containing the parsed [`arbutus::Tree`], or a [`crate::Error`] if parsing fails.
     pub fn parse_memory(data: &str) -> Result<Tree, ParseErrorContext> {
+        Self::parse_memory_with(data, &ParserOptions::default())
+    }
+
+    /// Parse a Snowcap string from memory into an [`arbutus::Tree`] with [`ParserOptions`]. A strict parser
+    /// rejects unknown widgets and attributes which have no effect on their element.
+    pub fn parse_memory_with(
+        data: &str,
+        options: &ParserOptions,
+    ) -> Result<Tree, ParseErrorContext> {
+        let tree = value::with_asset_dir(options.asset_dir.as_deref(), &options.policy, || {
+            Self::parse_tree(data)
+        })?;
+        if options.strict {
+            validate::validate(tree.root(), data, &options.widgets)?;
+        }
+        Ok(tree)
+    }
+
+    fn parse_tree(data: &str) -> Result<Tree, ParseErrorContext> {
         debug_span!("parser").in_scope(|| {
-            let markup = SnowcapParser::<M>::parse(Rule::markup, data)
-                .map_err(|e| {
-                    let mut context = ParserContext::default();
-                    match e.line_col {
-                        pest::error::LineColLocation::Pos(pos) => context.location = pos,
-                        pest::error::LineColLocation::Span(_, _) => todo!(),
-                    }
-                    context.input = data.into();
-                    ParseErrorContext::new(context, ParseError::from(e))
-                })?
-                .next()
-                .unwrap();
+            let mut pairs = SnowcapParser::<M>::parse(Rule::markup, data)
+                .map_err(|e| Self::error_context(data, e))?;
+
+            let mut markup = pairs.next().unwrap();
+
+            // A custom theme definition is stored in the attributes of the root node
+            let mut root_attrs = Attributes::new();
+            if markup.as_rule() == Rule::theme_definition {
+                Self::parse_theme(&markup, &mut root_attrs)
+                    .map_err(|e| ParseErrorContext::new((&markup).into(), e))?;
+
+                markup = pairs.next().unwrap();
+            }
+
+            // A stylesheet is also stored in the attributes of the root node
+            if markup.as_rule() == Rule::stylesheet {
+                Self::parse_stylesheet(&markup, &mut root_attrs)
+                    .map_err(|e| ParseErrorContext::new((&markup).into(), e))?;
+
+                markup = pairs.next().unwrap();
+            } else if markup.as_rule() == Rule::stylesheet_file {
+                // The file is loaded by the engine, which reloads it when it changes
+                let path = markup
+                    .clone()
+                    .into_inner()
+                    .next()
+                    .unwrap()
+                    .into_inner()
+                    .as_str();
+                root_attrs
+                    .push(Attribute::from(AttributeValue::StylesheetFile(path.into())))
+                    .map_err(|e| ParseErrorContext::new((&markup).into(), e.into()))?;
+
+                markup = pairs.next().unwrap();
+            }
 
             // Initialize parser context
             let mut parser = Self::default().context((&markup).into());
@@ -113,7 +171,7 @@ where
                 crate::NodeRef,
             >::new();
 
-            let root = SnowcapNode::new(Content::Root);
+            let root = SnowcapNode::new(Content::Root).with_attrs(root_attrs);
 
             builder = builder
                 .root(root, |root| parser.parse_pair(markup, root))
@@ -129,6 +187,56 @@ where
         })
     }
 
+    /// Parse a single element from a region of markup into an [`arbutus::Tree`]. The element is the only child
+    /// of the root node, and the spans of the nodes are relative to the start of the region.
+    pub(crate) fn parse_fragment(data: &str) -> Result<Tree, ParseErrorContext> {
+        debug_span!("parser").in_scope(|| {
+            let mut pairs = SnowcapParser::<M>::parse(Rule::fragment, data)
+                .map_err(|e| Self::error_context(data, e))?;
+
+            let element = pairs.next().ok_or_else(|| {
+                ParseErrorContext::new(ParserContext::default(), ParseError::Missing("element"))
+            })?;
+
+            let mut parser = Self::default().context((&element).into());
+
+            let builder = TreeBuilder::<
+                SnowcapNode,
+                ParseError,
+                arbutus::IdGenerator,
+                crate::Node<SnowcapNode, crate::NodeId>,
+                crate::NodeRef,
+            >::new()
+            .root(SnowcapNode::new(Content::Root), |root| {
+                parser.parse_pair(element, root)
+            })
+            .map_err(|e| ParseErrorContext::new(parser.context.clone(), e))?;
+
+            let tree = builder
+                .done()
+                .map_err(|e| ParseErrorContext::new(parser.context.clone(), e))?;
+
+            Ok(tree.unwrap())
+        })
+    }
+
+    /// Get the [`ParseErrorContext`] of a pest error
+    fn error_context(data: &str, e: pest::error::Error<Rule>) -> ParseErrorContext {
+        let mut context = ParserContext::default();
+        // Errors spanning a range of the markup are located at the start of the span
+        match e.line_col {
+            pest::error::LineColLocation::Pos(pos) => context.location = pos,
+            pest::error::LineColLocation::Span(start, _) => context.location = start,
+        }
+        context.input = data.into();
+        ParseErrorContext::new(context, ParseError::from(e))
+    }
+
+    /// Get the byte range of a pair in the markup
+    fn span(pair: &Pair<Rule>) -> Range<usize> {
+        pair.as_span().start()..pair.as_span().end()
+    }
+
     pub fn context(mut self, context: ParserContext) -> Self {
         self.context = context;
         self
@@ -143,6 +251,27 @@ where
         AttributeParser::parse_attributes(pair.as_str())
     }
 
+    /// Parse a custom theme definition, adding the theme and default text size to the root [`Attributes`]
+    fn parse_theme(pair: &Pair<Rule>, attrs: &mut Attributes) -> Result<(), ParseError> {
+        let theme = ThemeParser::parse_str(pair.as_str())?;
+
+        attrs.push(Attribute::from(AttributeValue::Theme(theme.theme())))?;
+        if let Some(size) = theme.text_size() {
+            attrs.push(Attribute::from(AttributeValue::Size(size)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Parse a stylesheet, adding it to the root [`Attributes`]
+    fn parse_stylesheet(pair: &Pair<Rule>, attrs: &mut Attributes) -> Result<(), ParseError> {
+        let stylesheet = StyleParser::parse_str(pair.as_str())?;
+        attrs.push(Attribute::from(AttributeValue::Stylesheet(Arc::new(
+            stylesheet,
+        ))))?;
+        Ok(())
+    }
+
     /// Parse [`Value`] from the pairs
     fn parse_value(&self, pair: Pair<Rule>) -> Result<Value, ParseError> {
         let context = ParserContext::from(&pair);
@@ -164,6 +293,7 @@ where
         pair: Pair<Rule>,
         builder: &mut SnowNodeBuilder<'b>,
     ) -> Result<(), ParseError> {
+        let span = Self::span(&pair);
         let inner = pair.into_inner();
 
         let mut id = None;
@@ -177,8 +307,16 @@ where
                     tracing::info!("Element List ID {container_id}");
                     id = Some(container_id.to_string());
                 }
-                Rule::row | Rule::column | Rule::widget | Rule::stack => {
-                    let mut node = SnowcapNode::new(Content::Container).with_element_id(id);
+                Rule::row
+                | Rule::column
+                | Rule::lazy_column
+                | Rule::widget
+                | Rule::stack
+                | Rule::boundary
+                | Rule::region => {
+                    let mut node = SnowcapNode::new(Content::Container)
+                        .with_element_id(id)
+                        .with_span(span);
 
                     if let Some(attrs) = attrs {
                         node = node.with_attrs(attrs);
@@ -258,7 +396,7 @@ where
         pair: Pair<Rule>,
         builder: &mut SnowNodeBuilder<'b>,
     ) -> Result<(), ParseError> {
-        let node = SnowcapNode::new(Content::Row);
+        let node = SnowcapNode::new(Content::Row).with_span(Self::span(&pair));
 
         builder.child(node, |row| {
             debug!("Parsing row contents");
@@ -288,7 +426,7 @@ where
         pair: Pair<Rule>,
         builder: &mut SnowNodeBuilder<'b>,
     ) -> Result<(), ParseError> {
-        let node = SnowcapNode::new(Content::Column);
+        let node = SnowcapNode::new(Content::Column).with_span(Self::span(&pair));
 
         builder.child(node, |col| {
             debug!("Parsing column contents");
@@ -306,6 +444,32 @@ where
         })
     }
 
+    /// Parse a lazy Column, which only builds widgets for the items visible in its viewport.
+    ///
+    /// The items are the element list, parsed in the same way as a [`Content::Column`].
+    fn parse_lazy_column<'b>(
+        &mut self,
+        pair: Pair<Rule>,
+        builder: &mut SnowNodeBuilder<'b>,
+    ) -> Result<(), ParseError> {
+        let node = SnowcapNode::new(Content::LazyColumn).with_span(Self::span(&pair));
+
+        builder.child(node, |col| {
+            debug!("Parsing lazy column contents");
+            let (id, attrs) = self.parse_element_list(pair.into_inner(), col)?;
+            col.node_mut()
+                .with_data_mut(|data| {
+                    data.element_id = id;
+                    if let Some(attrs) = attrs {
+                        data.attrs = attrs;
+                    }
+                    Ok::<(), ()>(())
+                })
+                .ok();
+            Ok(())
+        })
+    }
+
     /// Parse a Stack widget.
     ///
     /// Parses the ID and [`Attributes`] for this stack, and an element list as the contents.
@@ -318,7 +482,7 @@ where
         pair: Pair<Rule>,
         builder: &'b mut SnowNodeBuilder<'_>,
     ) -> Result<(), ParseError> {
-        let node = SnowcapNode::new(Content::Stack);
+        let node = SnowcapNode::new(Content::Stack).with_span(Self::span(&pair));
 
         builder.child(node, |stack| {
             debug!("Parsing column contents");
@@ -337,6 +501,65 @@ where
         })
     }
 
+    /// Parse an error boundary.
+    ///
+    /// Parses the ID and [`Attributes`] for this boundary, and adds the guarded element followed
+    /// by the fallback element as children of a [`Content::Boundary`] node.
+    ///
+    /// The supplied NodeBuilder provides the context of the parent node.
+    ///
+    fn parse_boundary<'b>(
+        &mut self,
+        pair: Pair<Rule>,
+        builder: &mut SnowNodeBuilder<'b>,
+    ) -> Result<(), ParseError> {
+        let node = SnowcapNode::new(Content::Boundary).with_span(Self::span(&pair));
+
+        builder.child(node, |boundary| {
+            debug!("Parsing error boundary contents");
+            let (id, attrs) = self.parse_element_list(pair.into_inner(), boundary)?;
+            boundary
+                .node_mut()
+                .with_data_mut(|data| {
+                    data.element_id = id;
+                    if let Some(attrs) = attrs {
+                        data.attrs = attrs;
+                    }
+                    Ok::<(), ()>(())
+                })
+                .ok();
+            Ok(())
+        })
+    }
+
+    /// Parse a named region.
+    ///
+    /// Adds the element of the region as the only child of a [`Content::Region`] node, with the name of the region
+    /// as its element id.
+    ///
+    /// The supplied NodeBuilder provides the context of the parent node.
+    ///
+    fn parse_region<'b>(
+        &mut self,
+        pair: Pair<Rule>,
+        builder: &mut SnowNodeBuilder<'b>,
+    ) -> Result<(), ParseError> {
+        let node = SnowcapNode::new(Content::Region).with_span(Self::span(&pair));
+
+        builder.child(node, |region| {
+            debug!("Parsing region contents");
+            let (id, _) = self.parse_element_list(pair.into_inner(), region)?;
+            region
+                .node_mut()
+                .with_data_mut(|data| {
+                    data.element_id = id;
+                    Ok::<(), ()>(())
+                })
+                .ok();
+            Ok(())
+        })
+    }
+
     /// Parse a generic widget.
     ///
     /// Parses the ID and [`Attributes`] for this widget, and recursively parses its content.
@@ -349,10 +572,11 @@ where
         pair: Pair<Rule>,
         builder: &mut SnowNodeBuilder<'b>,
     ) -> Result<(), ParseError> {
+        let span = Self::span(&pair);
         let mut inner = pair.into_inner();
         let label = inner.next().unwrap().as_str().to_string();
 
-        let node = SnowcapNode::new(Content::Widget(label));
+        let node = SnowcapNode::new(Content::Widget(label)).with_span(span);
 
         builder.child(node, |widget| {
             for pair in inner {
@@ -390,7 +614,13 @@ where
                     Rule::module => {
                         self.parse_module(pair, widget)?;
                     }
-                    Rule::widget | Rule::row | Rule::column | Rule::stack => {
+                    Rule::widget
+                    | Rule::row
+                    | Rule::column
+                    | Rule::lazy_column
+                    | Rule::stack
+                    | Rule::boundary
+                    | Rule::region => {
                         self.parse_pair(pair, widget)?;
                     }
                     _ => {
@@ -425,7 +655,7 @@ where
         let module = ModuleParser::parse_str(pair.as_str(), self.context.clone())?;
 
         // Add the module to the tree
-        let node = SnowcapNode::new(Content::Module(module));
+        let node = SnowcapNode::new(Content::Module(module)).with_span(Self::span(&pair));
         builder.child(node, |_| Ok(()))?;
 
         Ok(())
@@ -444,7 +674,10 @@ where
             Rule::container => self.parse_container(pair, builder),
             Rule::row => self.parse_row(pair, builder),
             Rule::column => self.parse_column(pair, builder),
+            Rule::lazy_column => self.parse_lazy_column(pair, builder),
             Rule::stack => self.parse_stack(pair, builder),
+            Rule::boundary => self.parse_boundary(pair, builder),
+            Rule::region => self.parse_region(pair, builder),
             Rule::widget => self.parse_widget(pair, builder),
             Rule::module => self.parse_module(pair, builder),
             Rule::element_value => self.parse_pair(pair.into_inner().last().unwrap(), builder),
@@ -1,4 +1,10 @@
-use iced::widget::scrollable::Scrollbar;
+use std::{collections::BTreeSet, time::Duration};
+
+use iced::widget::{
+    rule::FillMode,
+    scrollable::{Anchor, Direction, Scrollbar},
+};
+use parking_lot::Mutex;
 use pest::{
     iterators::{Pair, Pairs},
     Parser,
@@ -7,19 +13,57 @@ use pest_derive::Parser;
 use tracing::{debug, debug_span, warn};
 
 use crate::{
-    attribute::{Attribute, AttributeKind, AttributeValue, Attributes},
-    module::argument::ModuleArgument,
-    parser::{color::ColorParser, gradient::GradientParser, module::ModuleParser, ParserContext},
+    attribute::{
+        breakpoint::Breakpoint,
+        custom::{self, AttributeRegistry, CustomAttributes, CustomValue},
+        expression::ValueExpression,
+        handler::Handler,
+        palette::PaletteColor,
+        relative::RelativeLength,
+        transition::{Easing, Transition, TransitionProperty},
+        Attribute, AttributeKind, AttributeValue, Attributes,
+    },
+    context,
+    conversion::theme::{SnowcapTheme, SYSTEM_THEME},
+    module::{argument::ModuleArgument, state::StateAction},
+    parser::{
+        color::ColorParser, expr::ExprParser, gradient::GradientParser, module::ModuleParser,
+        ParserContext,
+    },
 };
 
 use super::{ParseError, Value};
 
+/// Font family names parsed from markup. iced fonts refer to families by `&'static str`, so each
+/// name is leaked once and shared by every reparse of the markup.
+static FONT_FAMILIES: Mutex<BTreeSet<&'static str>> = parking_lot::const_mutex(BTreeSet::new());
+
+/// Get the static name of a font family
+fn font_family(name: String) -> &'static str {
+    let mut families = FONT_FAMILIES.lock();
+    match families.get(name.as_str()) {
+        Some(family) => *family,
+        None => {
+            let family: &'static str = Box::leak(name.into_boxed_str());
+            families.insert(family);
+            family
+        }
+    }
+}
+
 #[derive(Debug)]
 enum AttributeOption {
     Color(iced::Color),
     Gradient(iced::Gradient),
     WidthPixels(iced::Pixels),
     Radius(iced::border::Radius),
+    Palette(PaletteColor),
+    Offset(iced::Vector),
+    Blur(f32),
+    Margin(f32),
+    ScrollerWidth(f32),
+    Anchor(Anchor),
+    Spacing(f32),
 }
 
 #[derive(Parser)]
@@ -40,6 +84,9 @@ impl AttributeParser {
                         gradient,
                     )))
                 }
+                AttributeOption::Palette(color) => {
+                    return Ok(AttributeValue::BackgroundPalette(color))
+                }
                 _ => warn!("Unsupported background option {:?}", option),
             }
         }
@@ -85,7 +132,7 @@ impl AttributeParser {
     fn parse_string(pair: Pair<'_, Rule>) -> Result<String, ParseError> {
         match pair.as_rule() {
             Rule::string => {
-                let str = pair.into_inner().last().unwrap().as_str().to_string();
+                let str = context::expand_string(pair.into_inner().last().unwrap().as_str());
                 debug!("parse_string() inner '{str}'");
                 Ok(str)
             }
@@ -122,6 +169,18 @@ impl AttributeParser {
         }
     }
 
+    fn parse_f64(pair: Pair<'_, Rule>) -> Result<f64, ParseError> {
+        match pair.as_rule() {
+            Rule::float => Ok(pair.as_str().parse().map_err(|e| ParseError::Float(e))?),
+            _ => {
+                return Err(ParseError::UnsupportedRule(format!(
+                    "parse_f64 expecting float, got {:?}",
+                    pair.as_rule()
+                )))
+            }
+        }
+    }
+
     fn parse_u16(pair: Pair<'_, Rule>) -> Result<u16, ParseError> {
         match pair.as_rule() {
             Rule::integer => Ok(pair.as_str().parse().map_err(|e| ParseError::Integer(e))?),
@@ -217,27 +276,113 @@ impl AttributeParser {
         }
     }
 
-    fn parse_direction(
-        pair: Pair<'_, Rule>,
-    ) -> Result<iced::widget::scrollable::Direction, ParseError> {
+    /// Parse a font, which is `monospace`, `default`, or the name of a font family loaded by the application
+    fn parse_font(pair: Pair<'_, Rule>) -> Result<iced::Font, ParseError> {
         match pair.as_rule() {
-            Rule::direction_horizontal => Ok(iced::widget::scrollable::Direction::Horizontal(
-                Scrollbar::default(),
-            )),
-            Rule::direction_vertical => Ok(iced::widget::scrollable::Direction::Vertical(
-                Scrollbar::default(),
-            )),
-            Rule::both => Ok(iced::widget::scrollable::Direction::Both {
-                vertical: Scrollbar::default(),
-                horizontal: Scrollbar::default(),
+            Rule::font_monospace => Ok(iced::Font::MONOSPACE),
+            Rule::font_default => Ok(iced::Font::DEFAULT),
+            Rule::string => Ok(iced::Font::with_name(font_family(Self::parse_string(
+                pair,
+            )?))),
+            _ => Err(ParseError::UnsupportedRule(format!(
+                "parse_font() expecting monospace | default | string. Got {:#?}",
+                pair.as_rule()
+            ))),
+        }
+    }
+
+    fn parse_direction(mut pairs: Pairs<'_, Rule>) -> Result<Direction, ParseError> {
+        let pair = pairs.next().unwrap();
+
+        // Scrollbar options, such as vertical(width(8), margin(2)), apply to each scrollbar
+        let mut scrollbar = Scrollbar::default();
+        if let Some(options) = pairs.next() {
+            for option in Self::parse_options(options.into_inner())? {
+                scrollbar = match option {
+                    AttributeOption::WidthPixels(width) => scrollbar.width(width),
+                    AttributeOption::Margin(margin) => scrollbar.margin(margin),
+                    AttributeOption::ScrollerWidth(width) => scrollbar.scroller_width(width),
+                    AttributeOption::Anchor(anchor) => scrollbar.anchor(anchor),
+                    AttributeOption::Spacing(spacing) => scrollbar.spacing(spacing),
+                    _ => {
+                        warn!("Unsupported Scrollbar option {:?}", option);
+                        scrollbar
+                    }
+                };
+            }
+        }
+
+        match pair.as_rule() {
+            Rule::direction_horizontal => Ok(Direction::Horizontal(scrollbar)),
+            Rule::direction_vertical => Ok(Direction::Vertical(scrollbar)),
+            Rule::both => Ok(Direction::Both {
+                vertical: scrollbar,
+                horizontal: scrollbar,
             }),
             _ => Err(ParseError::UnsupportedRule(format!(
-                "parse_shaping() expecting basic | advanced. Got {:#?}",
+                "parse_direction() expecting horizontal | vertical | both. Got {:#?}",
                 pair.as_rule()
             ))),
         }
     }
 
+    /// Parse a transition such as `opacity(300ms, ease-out)`
+    fn parse_transition(pair: Pair<'_, Rule>) -> Result<Transition, ParseError> {
+        let mut inner = pair.into_inner();
+
+        let property = TransitionProperty::parse(inner.next().unwrap().as_str())?;
+
+        let mut duration = inner.next().unwrap().into_inner();
+        let value: f64 = duration
+            .next()
+            .unwrap()
+            .as_str()
+            .parse()
+            .map_err(|e| ParseError::Unhandled(format!("transition duration: {e}")))?;
+        let nanos = match duration.next().unwrap().as_str().to_lowercase().as_str() {
+            "ms" => value * 1e6,
+            _ => value * 1e9,
+        };
+        let duration = Duration::from_nanos(nanos.round() as u64);
+
+        let easing = match inner.next() {
+            Some(easing) => Easing::parse(easing.as_str())?,
+            None => Easing::default(),
+        };
+
+        Ok(Transition {
+            property,
+            duration,
+            easing,
+        })
+    }
+
+    /// Parse an event handler such as `publish("nav", "settings")` or `state.increment("counter")`
+    fn parse_handler(pair: Pair<'_, Rule>) -> Result<Handler, ParseError> {
+        let handler = pair.into_inner().last().unwrap();
+        let rule = handler.as_rule();
+        let mut inner = handler.into_inner();
+
+        match rule {
+            Rule::handler_publish => {
+                let topic = Self::parse_string(inner.next().unwrap())?;
+                let payload = inner.next().map(Self::parse_string).transpose()?;
+
+                Ok(Handler::publish(topic, payload))
+            }
+            Rule::handler_state => {
+                let action = inner.next().unwrap().as_str();
+                let key = Self::parse_string(inner.next().unwrap())?;
+                let value = inner.next().map(Self::parse_string).transpose()?;
+
+                Ok(Handler::State(StateAction::parse(action, key, value)?))
+            }
+            _ => Err(ParseError::UnsupportedRule(format!(
+                "parse_handler() expecting publish | state. Got {rule:?}"
+            ))),
+        }
+    }
+
     fn parse_radius(pair: Pair<'_, Rule>) -> Result<iced::border::Radius, ParseError> {
         match pair.as_rule() {
             Rule::uniform => {
@@ -274,6 +419,60 @@ impl AttributeParser {
         }
     }
 
+    /// Parse a color such as `#ff0000` or `color(#ff0000)`
+    fn parse_color(pair: Pair<'_, Rule>) -> Result<iced::Color, ParseError> {
+        match pair.as_rule() {
+            Rule::option_color => Ok(ColorParser::parse_str(pair.into_inner().as_str())?),
+            Rule::color_hex => Ok(ColorParser::parse_str(pair.as_str())?),
+            _ => Err(ParseError::UnsupportedRule(format!(
+                "parse_color expecting color, got {:?}",
+                pair.as_rule()
+            ))),
+        }
+    }
+
+    /// Parse how an image fits its bounds, such as `cover` or `scale-down`
+    fn parse_content_fit(pair: Pair<'_, Rule>) -> Result<iced::ContentFit, ParseError> {
+        match pair.as_str().to_lowercase().as_str() {
+            "contain" => Ok(iced::ContentFit::Contain),
+            "cover" => Ok(iced::ContentFit::Cover),
+            "fill" => Ok(iced::ContentFit::Fill),
+            "none" => Ok(iced::ContentFit::None),
+            "scale-down" => Ok(iced::ContentFit::ScaleDown),
+            fit => Err(ParseError::UnsupportedRule(format!(
+                "parse_content_fit unknown fit {fit}"
+            ))),
+        }
+    }
+
+    /// Parse the fill mode of a rule, such as `full`, `80%` or `padded(4, 8)`
+    fn parse_fill_mode(pair: Pair<'_, Rule>) -> Result<FillMode, ParseError> {
+        match pair.as_rule() {
+            Rule::fill_full => Ok(FillMode::Full),
+            Rule::fill_percent => Ok(FillMode::Percent(Self::parse_float(
+                pair.into_inner().last().unwrap(),
+            )?)),
+            Rule::fill_padded => {
+                let padding = pair
+                    .into_inner()
+                    .map(Self::parse_u16)
+                    .collect::<Result<Vec<_>, _>>()?;
+                match padding[..] {
+                    [padding] => Ok(FillMode::Padded(padding)),
+                    [start, end] => Ok(FillMode::AsymmetricPadding(start, end)),
+                    _ => Err(ParseError::UnsupportedRule(format!(
+                        "parse_fill_mode expecting one or two paddings, got {}",
+                        padding.len()
+                    ))),
+                }
+            }
+            _ => Err(ParseError::UnsupportedRule(format!(
+                "parse_fill_mode expecting full | percent | padded, got {:?}",
+                pair.as_rule()
+            ))),
+        }
+    }
+
     fn parse_length(pair: Pair<'_, Rule>) -> Result<iced::Length, ParseError> {
         match pair.as_rule() {
             Rule::fill => Ok(iced::Length::Fill),
@@ -293,6 +492,21 @@ impl AttributeParser {
         }
     }
 
+    /// Parse a length relative to the parent or the window, such as `50%`, `10vw` or `20vh`
+    fn parse_relative(pair: Pair<'_, Rule>) -> Result<RelativeLength, ParseError> {
+        let rule = pair.as_rule();
+        let percent = Self::parse_float(pair.into_inner().last().unwrap())?;
+
+        match rule {
+            Rule::percent_length => Ok(RelativeLength::Percent(percent)),
+            Rule::vw_length => Ok(RelativeLength::ViewportWidth(percent)),
+            Rule::vh_length => Ok(RelativeLength::ViewportHeight(percent)),
+            _ => Err(ParseError::UnsupportedRule(format!(
+                "parse_relative expecting percent | vw | vh, got {rule:?}"
+            ))),
+        }
+    }
+
     /// Get the [`AttributeKind`] for a pair
     fn pair_kind(pair: &Pair<'_, Rule>) -> Result<AttributeKind, ParseError> {
         match pair.as_rule() {
@@ -310,14 +524,58 @@ impl AttributeParser {
             Rule::attr_label => Ok(AttributeKind::Label),
             Rule::attr_max_width => Ok(AttributeKind::MaxWidth),
             Rule::attr_max_height => Ok(AttributeKind::MaxHeight),
+            Rule::attr_min_width => Ok(AttributeKind::MinWidth),
+            Rule::attr_min_height => Ok(AttributeKind::MinHeight),
             Rule::attr_align => Ok(AttributeKind::HorizontalAlignment),
             Rule::attr_clip => Ok(AttributeKind::Clip),
             Rule::attr_toggled => Ok(AttributeKind::Toggled),
             Rule::attr_wrapping => Ok(AttributeKind::Wrapping),
             Rule::attr_shaping => Ok(AttributeKind::Shaping),
+            Rule::attr_font => Ok(AttributeKind::Font),
             Rule::attr_border => Ok(AttributeKind::Border),
             Rule::attr_shadow => Ok(AttributeKind::Shadow),
             Rule::attr_direction => Ok(AttributeKind::ScrollDirection),
+            Rule::attr_wheel => Ok(AttributeKind::Wheel),
+            Rule::attr_spin => Ok(AttributeKind::Spin),
+            Rule::attr_step => Ok(AttributeKind::Step),
+            Rule::attr_animated => Ok(AttributeKind::Animated),
+            Rule::attr_theme => Ok(AttributeKind::Theme),
+            Rule::attr_opacity => Ok(AttributeKind::Opacity),
+            Rule::attr_transition => Ok(AttributeKind::Transition),
+            Rule::attr_on_press => Ok(AttributeKind::OnPress),
+            Rule::attr_on_toggle => Ok(AttributeKind::OnToggle),
+            Rule::attr_on_select => Ok(AttributeKind::OnSelect),
+            Rule::attr_on_change => Ok(AttributeKind::OnChange),
+            Rule::attr_value => Ok(AttributeKind::Bind),
+            Rule::attr_on_link => Ok(AttributeKind::OnLink),
+            Rule::attr_heading_size => Ok(AttributeKind::HeadingSize),
+            Rule::attr_code_size => Ok(AttributeKind::CodeSize),
+            Rule::attr_code_color => Ok(AttributeKind::CodeColor),
+            Rule::attr_code_bg => Ok(AttributeKind::CodeBackground),
+            Rule::attr_link_color => Ok(AttributeKind::LinkColor),
+            Rule::attr_language => Ok(AttributeKind::Language),
+            Rule::attr_wrap => Ok(AttributeKind::Wrap),
+            Rule::attr_line_numbers => Ok(AttributeKind::LineNumbers),
+            Rule::attr_item_height => Ok(AttributeKind::ItemHeight),
+            Rule::attr_placeholder => Ok(AttributeKind::Placeholder),
+            Rule::attr_min => Ok(AttributeKind::Min),
+            Rule::attr_max => Ok(AttributeKind::Max),
+            Rule::attr_secure => Ok(AttributeKind::Secure),
+            Rule::attr_on_submit => Ok(AttributeKind::OnSubmit),
+            Rule::attr_draggable => Ok(AttributeKind::Draggable),
+            Rule::attr_drop_target => Ok(AttributeKind::DropTarget),
+            Rule::attr_on_drop => Ok(AttributeKind::OnDrop),
+            Rule::attr_active_color => Ok(AttributeKind::ActiveColor),
+            Rule::attr_handle_color => Ok(AttributeKind::HandleColor),
+            Rule::attr_text_size => Ok(AttributeKind::TextSize),
+            Rule::attr_thickness => Ok(AttributeKind::Thickness),
+            Rule::attr_color => Ok(AttributeKind::RuleColor),
+            Rule::attr_fill_mode => Ok(AttributeKind::FillMode),
+            Rule::attr_bg_image => Ok(AttributeKind::BackgroundImage),
+            Rule::attr_fit => Ok(AttributeKind::ContentFit),
+            Rule::attr_dim => Ok(AttributeKind::Dim),
+            Rule::attr_z_index => Ok(AttributeKind::ZIndex),
+            Rule::attr_class => Ok(AttributeKind::Class),
             _ => Err(ParseError::UnsupportedRule(format!(
                 "In pair_kind() rule={:?} {}:{}",
                 pair.as_rule(),
@@ -331,8 +589,18 @@ impl AttributeParser {
         match pair.as_rule() {
             Rule::attr_background => Ok(Some(Self::parse_background(pair.into_inner())?)),
             Rule::attr_text_color => {
-                let color = ColorParser::parse_str(pair.into_inner().as_str())?;
-                Ok(Some(AttributeValue::TextColor(color)))
+                let inner = pair.into_inner().next().unwrap();
+                match inner.as_rule() {
+                    Rule::option_palette => Ok(Some(AttributeValue::TextPalette(
+                        PaletteColor::parse(inner.into_inner().as_str())?,
+                    ))),
+                    Rule::option_color => Ok(Some(AttributeValue::TextColor(
+                        ColorParser::parse_str(inner.into_inner().as_str())?,
+                    ))),
+                    _ => Ok(Some(AttributeValue::TextColor(ColorParser::parse_str(
+                        inner.as_str(),
+                    )?))),
+                }
             }
             Rule::attr_align_x | Rule::attr_align_y => {
                 if let Some(pair) = pair.into_inner().last() {
@@ -342,6 +610,38 @@ impl AttributeParser {
                 }
             }
             Rule::attr_padding => Ok(Some(AttributeValue::Padding(Self::parse_padding(pair)?))),
+            Rule::attr_max_width => Ok(Some(AttributeValue::MaxWidth(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
+            Rule::attr_max_height => Ok(Some(AttributeValue::MaxHeight(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
+            Rule::attr_min_width => Ok(Some(AttributeValue::MinWidth(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
+            Rule::attr_min_height => Ok(Some(AttributeValue::MinHeight(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
             Rule::attr_height => {
                 let pair = pair.into_inner().last().unwrap();
 
@@ -354,8 +654,12 @@ impl AttributeParser {
                         pair.into_inner().last().unwrap(),
                     )?))),
 
+                    Rule::relative => Ok(Some(AttributeValue::HeightRelative(
+                        Self::parse_relative(pair.into_inner().last().unwrap())?,
+                    ))),
+
                     _ => Err(ParseError::UnsupportedRule(format!(
-                        "attr_height expecting pixels | length, got {:?}",
+                        "attr_height expecting pixels | length | relative, got {:?}",
                         pair.as_rule()
                     ))),
                 }
@@ -372,8 +676,12 @@ impl AttributeParser {
                         pair.into_inner().last().unwrap(),
                     )?))),
 
+                    Rule::relative => Ok(Some(AttributeValue::WidthRelative(
+                        Self::parse_relative(pair.into_inner().last().unwrap())?,
+                    ))),
+
                     _ => Err(ParseError::UnsupportedRule(format!(
-                        "attr_height expecting pixels | length, got {:?}",
+                        "attr_height expecting pixels | length | relative, got {:?}",
                         pair.as_rule()
                     ))),
                 }
@@ -409,9 +717,38 @@ impl AttributeParser {
                     .last()
                     .unwrap(),
             )?))),
-            Rule::attr_selected => Ok(Some(AttributeValue::Selected(Self::parse_string(
-                pair.into_inner().last().unwrap(),
-            )?))),
+            Rule::attr_selected => {
+                let pair = pair.into_inner().last().unwrap();
+
+                match pair.as_rule() {
+                    Rule::string_list => Ok(Some(AttributeValue::SelectedList(
+                        pair.into_inner()
+                            .map(Self::parse_string)
+                            .collect::<Result<Vec<String>, ParseError>>()?,
+                    ))),
+                    _ => Ok(Some(AttributeValue::Selected(Self::parse_string(pair)?))),
+                }
+            }
+            Rule::attr_class => {
+                let pair = pair.into_inner().last().unwrap();
+
+                // Each string can hold several classes separated by whitespace, such as "card raised"
+                let strings = match pair.as_rule() {
+                    Rule::string_list => pair
+                        .into_inner()
+                        .map(Self::parse_string)
+                        .collect::<Result<Vec<String>, ParseError>>()?,
+                    _ => vec![Self::parse_string(pair)?],
+                };
+
+                Ok(Some(AttributeValue::Class(
+                    strings
+                        .iter()
+                        .flat_map(|classes| classes.split_whitespace())
+                        .map(str::to_string)
+                        .collect(),
+                )))
+            }
             Rule::attr_label => Ok(Some(AttributeValue::Label(Self::parse_string(
                 pair.into_inner().last().unwrap(),
             )?))),
@@ -438,15 +775,181 @@ impl AttributeParser {
 
                 Ok(Some(AttributeValue::Border(border)))
             }
+            Rule::attr_shadow => {
+                let mut shadow = iced::Shadow::default();
+                for option in Self::parse_options(pair.into_inner())? {
+                    match option {
+                        AttributeOption::Color(color) => shadow.color = color,
+                        AttributeOption::Offset(offset) => shadow.offset = offset,
+                        AttributeOption::Blur(blur) => shadow.blur_radius = blur,
+                        _ => warn!("Unsupported Shadow option {:?}", option),
+                    }
+                }
+
+                Ok(Some(AttributeValue::Shadow(shadow)))
+            }
             Rule::attr_wrapping => Ok(Some(AttributeValue::Wrapping(Self::parse_wrapping(
                 pair.into_inner().last().unwrap(),
             )?))),
             Rule::attr_shaping => Ok(Some(AttributeValue::Shaping(Self::parse_shaping(
                 pair.into_inner().last().unwrap(),
             )?))),
+            Rule::attr_font => Ok(Some(AttributeValue::Font(Self::parse_font(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_opacity => Ok(Some(AttributeValue::Opacity(Self::parse_float(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_transition => Ok(Some(AttributeValue::Transition(
+                pair.into_inner()
+                    .map(Self::parse_transition)
+                    .collect::<Result<_, _>>()?,
+            ))),
+            Rule::attr_on_press => Ok(Some(AttributeValue::OnPress(Self::parse_handler(pair)?))),
+            Rule::attr_on_toggle => Ok(Some(AttributeValue::OnToggle(Self::parse_handler(pair)?))),
+            Rule::attr_on_select => Ok(Some(AttributeValue::OnSelect(Self::parse_handler(pair)?))),
+            Rule::attr_on_change => Ok(Some(AttributeValue::OnChange(Self::parse_handler(pair)?))),
+            Rule::attr_on_link => Ok(Some(AttributeValue::OnLink(Self::parse_handler(pair)?))),
+            Rule::attr_on_submit => Ok(Some(AttributeValue::OnSubmit(Self::parse_handler(pair)?))),
+            Rule::attr_on_drop => Ok(Some(AttributeValue::OnDrop(Self::parse_handler(pair)?))),
+            Rule::attr_active_color => Ok(Some(AttributeValue::ActiveColor(Self::parse_color(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_handle_color => Ok(Some(AttributeValue::HandleColor(Self::parse_color(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_text_size => Ok(Some(AttributeValue::TextSize(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
+            Rule::attr_thickness => Ok(Some(AttributeValue::Thickness(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
+            Rule::attr_color => {
+                let inner = pair.into_inner().next().unwrap();
+                match inner.as_rule() {
+                    Rule::option_palette => Ok(Some(AttributeValue::RulePalette(
+                        PaletteColor::parse(inner.into_inner().as_str())?,
+                    ))),
+                    _ => Ok(Some(AttributeValue::RuleColor(Self::parse_color(inner)?))),
+                }
+            }
+            Rule::attr_fill_mode => Ok(Some(AttributeValue::FillMode(Self::parse_fill_mode(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_fit => Ok(Some(AttributeValue::ContentFit(Self::parse_content_fit(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_dim => Ok(Some(AttributeValue::Dim(Self::parse_float(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_z_index => {
+                let pair = pair.into_inner().last().unwrap();
+                Ok(Some(AttributeValue::ZIndex(
+                    pair.as_str().parse().map_err(|e| ParseError::Integer(e))?,
+                )))
+            }
+            Rule::attr_placeholder => Ok(Some(AttributeValue::Placeholder(Self::parse_string(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_min => Ok(Some(AttributeValue::Min(Self::parse_f64(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_max => Ok(Some(AttributeValue::Max(Self::parse_f64(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_secure => Ok(Some(AttributeValue::Secure(Self::parse_boolean(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_draggable => Ok(Some(AttributeValue::Draggable(Self::parse_boolean(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_drop_target => Ok(Some(AttributeValue::DropTarget(Self::parse_boolean(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_heading_size => Ok(Some(AttributeValue::HeadingSize(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
+            Rule::attr_code_size => Ok(Some(AttributeValue::CodeSize(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
+            Rule::attr_code_color => Ok(Some(AttributeValue::CodeColor(Self::parse_color(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_code_bg => Ok(Some(AttributeValue::CodeBackground(Self::parse_color(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_link_color => Ok(Some(AttributeValue::LinkColor(Self::parse_color(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_language => Ok(Some(AttributeValue::Language(Self::parse_string(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_wrap => Ok(Some(AttributeValue::Wrap(Self::parse_boolean(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_line_numbers => Ok(Some(AttributeValue::LineNumbers(Self::parse_boolean(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_item_height => Ok(Some(AttributeValue::ItemHeight(Self::parse_pixels(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .last()
+                    .unwrap(),
+            )?))),
+            Rule::attr_value => Ok(Some(AttributeValue::Bind(
+                pair.into_inner()
+                    .last()
+                    .unwrap()
+                    .into_inner()
+                    .as_str()
+                    .to_string(),
+            ))),
             Rule::attr_direction => Ok(Some(AttributeValue::ScrollDirection(
-                Self::parse_direction(pair.into_inner().last().unwrap())?,
+                Self::parse_direction(pair.into_inner())?,
             ))),
+            Rule::attr_wheel => Ok(Some(AttributeValue::Wheel(Self::parse_boolean(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_spin => Ok(Some(AttributeValue::Spin(Self::parse_boolean(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_step => Ok(Some(AttributeValue::Step(Self::parse_f64(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_animated => Ok(Some(AttributeValue::Animated(Self::parse_boolean(
+                pair.into_inner().last().unwrap(),
+            )?))),
+            Rule::attr_theme => {
+                let name = Self::parse_string(pair.into_inner().last().unwrap())?;
+                if name.eq_ignore_ascii_case(SYSTEM_THEME) {
+                    return Ok(Some(AttributeValue::SystemTheme));
+                }
+                let theme = SnowcapTheme::try_from(name.as_str())
+                    .map_err(|e| ParseError::Unhandled(e.to_string()))?;
+                Ok(Some(AttributeValue::Theme(theme.0)))
+            }
             Rule::EOI => Ok(None),
             _ => Err(ParseError::UnsupportedRule(format!(
                 "In parse_attribute rule={:?}",
@@ -469,6 +972,10 @@ impl AttributeParser {
                     let gradient = GradientParser::parse_str(pair.into_inner().as_str())?;
                     options.push(AttributeOption::Gradient(gradient));
                 }
+                Rule::option_palette => {
+                    let color = PaletteColor::parse(pair.into_inner().as_str())?;
+                    options.push(AttributeOption::Palette(color));
+                }
                 Rule::option_width => {
                     let width = Self::parse_pixels(pair.into_inner().last().unwrap())?;
                     options.push(AttributeOption::WidthPixels(width.into()));
@@ -477,6 +984,49 @@ impl AttributeParser {
                     let radius = Self::parse_radius(pair.into_inner().last().unwrap())?;
                     options.push(AttributeOption::Radius(radius))
                 }
+                Rule::option_offset => {
+                    let mut inner = pair.into_inner();
+                    let x: f32 = inner
+                        .next()
+                        .unwrap()
+                        .as_str()
+                        .parse()
+                        .map_err(ParseError::Float)?;
+                    let y: f32 = inner
+                        .next()
+                        .unwrap()
+                        .as_str()
+                        .parse()
+                        .map_err(ParseError::Float)?;
+                    options.push(AttributeOption::Offset(iced::Vector::new(x, y)))
+                }
+                Rule::option_blur => {
+                    let blur: f32 = pair
+                        .into_inner()
+                        .as_str()
+                        .parse()
+                        .map_err(ParseError::Float)?;
+                    options.push(AttributeOption::Blur(blur))
+                }
+                Rule::option_margin => {
+                    let margin = Self::parse_float(pair.into_inner().next().unwrap())?;
+                    options.push(AttributeOption::Margin(margin))
+                }
+                Rule::option_scroller_width => {
+                    let width = Self::parse_float(pair.into_inner().next().unwrap())?;
+                    options.push(AttributeOption::ScrollerWidth(width))
+                }
+                Rule::option_spacing => {
+                    let spacing = Self::parse_float(pair.into_inner().next().unwrap())?;
+                    options.push(AttributeOption::Spacing(spacing))
+                }
+                Rule::option_anchor => {
+                    let anchor = match pair.into_inner().next().unwrap().as_rule() {
+                        Rule::anchor_end => Anchor::End,
+                        _ => Anchor::Start,
+                    };
+                    options.push(AttributeOption::Anchor(anchor))
+                }
                 _ => {}
             };
         }
@@ -503,11 +1053,74 @@ impl AttributeParser {
         Ok(Attribute::new(kind).with_module(module))
     }
 
+    /// Parse an attribute with values for each [`Breakpoint`], such as `width:{sm:fill, lg:400}`
+    fn parse_responsive(attr: Pair<Rule>, responsive: Pair<Rule>) -> Result<Attribute, ParseError> {
+        let kind = Self::pair_kind(&attr)?;
+
+        // Name of the attribute, used to parse each value as an attribute of the same name
+        let name = attr.as_str().split(':').next().unwrap_or_default().trim();
+
+        let mut values = Vec::new();
+        for entry in responsive.into_inner() {
+            let mut inner = entry.into_inner();
+            let breakpoint = Breakpoint::parse(inner.next().unwrap().as_str())?;
+            let value = inner.next().unwrap().as_str().trim();
+
+            let attrs = Self::parse_attributes(&format!("{name}:{value}"))?;
+            if let Some(value) = attrs.into_iter().find_map(|attr| attr.value().cloned()) {
+                values.push((breakpoint, value));
+            }
+        }
+
+        Ok(Attribute::new(kind).with_value(AttributeValue::Responsive(values)))
+    }
+
+    /// Parse an attribute computed from an expression when the widget is built, such as `width:200 + 2 * pad`
+    fn parse_expression(attr: Pair<Rule>, expression: Pair<Rule>) -> Result<Attribute, ParseError> {
+        let kind = Self::pair_kind(&attr)?;
+
+        // Name of the attribute, used to parse the result as an attribute of the same name
+        let name = attr.as_str().split(':').next().unwrap_or_default().trim();
+
+        let source = expression.as_str().trim();
+        let expr = ExprParser::parse_str(source)?;
+
+        Ok(
+            Attribute::new(kind).with_value(AttributeValue::Expression(ValueExpression::new(
+                name, source, expr,
+            ))),
+        )
+    }
+
+    /// Parse an attribute which isn't built in, returning its lowercase name and value. Built in attributes with
+    /// a value they don't accept are also matched by the custom attribute rule, and are errors.
+    fn parse_custom(pair: Pair<Rule>) -> Result<(String, CustomValue), ParseError> {
+        let mut inner = pair.into_inner();
+        let name = inner
+            .next()
+            .ok_or(ParseError::Missing("custom attribute name"))?
+            .as_str()
+            .to_ascii_lowercase();
+        let source = inner
+            .next()
+            .ok_or(ParseError::Missing("custom attribute value"))?
+            .as_str()
+            .trim();
+
+        if custom::is_builtin(&name) {
+            return Err(ParseError::InvalidAttribute(name, source.to_string()));
+        }
+
+        let value = AttributeRegistry::parse(&name, source)?;
+        Ok((name, value))
+    }
+
     pub fn parse_attributes(data: &str) -> Result<Attributes, ParseError> {
         let attributes: Result<Attributes, ParseError> =
             debug_span!("AttributeParser").in_scope(|| {
                 debug!("Parsing attributes '{data}'");
                 let mut attributes = Attributes::default();
+                let mut custom = CustomAttributes::new();
 
                 let pairs = AttributeParser::parse(Rule::attribute_list, data)?;
 
@@ -516,6 +1129,12 @@ impl AttributeParser {
                     match pair.as_rule() {
                         Rule::attribute_list => {
                             for pair in pair.into_inner() {
+                                if pair.as_rule() == Rule::attr_custom {
+                                    let (name, value) = Self::parse_custom(pair)?;
+                                    custom.insert(name, value);
+                                    continue;
+                                }
+
                                 // Check if this pair contains a module
                                 let mut inner = pair.clone().into_inner();
                                 let module = inner.find(|pair| {
@@ -526,9 +1145,25 @@ impl AttributeParser {
                                     }
                                 });
 
+                                let responsive = pair
+                                    .clone()
+                                    .into_inner()
+                                    .find(|pair| pair.as_rule() == Rule::responsive);
+
+                                let expression = pair
+                                    .clone()
+                                    .into_inner()
+                                    .find(|pair| pair.as_rule() == Rule::expression);
+
                                 if let Some(module) = module {
                                     let attribute = Self::parse_module(pair, module)?;
                                     attributes.push(attribute)?;
+                                } else if let Some(expression) = expression {
+                                    let attribute = Self::parse_expression(pair, expression)?;
+                                    attributes.push(attribute)?;
+                                } else if let Some(responsive) = responsive {
+                                    let attribute = Self::parse_responsive(pair, responsive)?;
+                                    attributes.push(attribute)?;
                                 } else {
                                     if let Some(value) = Self::parse_attribute(pair)? {
                                         attributes.push(Attribute::from(value))?;
@@ -542,6 +1177,10 @@ impl AttributeParser {
                     }
                 }
 
+                if !custom.is_empty() {
+                    attributes.push(Attribute::from(AttributeValue::Custom(custom)))?;
+                }
+
                 debug!("Parsed Attributes {:#?}", attributes);
 
                 Ok(attributes)
@@ -558,7 +1197,7 @@ mod tests {
             scrollable::{Direction, Scrollbar},
             text::{Shaping, Wrapping},
         },
-        Padding,
+        Color, Length, Padding, Pixels,
     };
     use tracing::info;
     use tracing_test::traced_test;
@@ -709,6 +1348,135 @@ mod tests {
         check_radius(&attr, 1.0, 2.0, 3.0, 4.0);
     }
 
+    #[traced_test]
+    #[test]
+    fn test_responsive() {
+        let attrs =
+            AttributeParser::parse_attributes("width:{sm:fill, lg:400}, spacing:4").unwrap();
+
+        assert_eq!(
+            attrs.get(AttributeKind::WidthPixels).unwrap().unwrap(),
+            AttributeValue::Responsive(vec![
+                (Breakpoint::Sm, AttributeValue::WidthLength(Length::Fill)),
+                (Breakpoint::Lg, AttributeValue::WidthPixels(400.into())),
+            ])
+        );
+
+        let resolved = attrs.resolve(Breakpoint::Md);
+        assert_eq!(
+            resolved.get(AttributeKind::WidthLength).unwrap(),
+            Some(AttributeValue::WidthLength(Length::Fill))
+        );
+        assert!(resolved.get(AttributeKind::Spacing).unwrap().is_some());
+
+        // Unset below the smallest breakpoint
+        let resolved = attrs.resolve(Breakpoint::Xs);
+        assert!(resolved.get(AttributeKind::WidthLength).unwrap().is_none());
+        assert!(resolved.get(AttributeKind::WidthPixels).unwrap().is_none());
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_relative() {
+        let attrs = AttributeParser::parse_attributes("width:50%, height:20vh").unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::WidthRelative).unwrap(),
+            Some(AttributeValue::WidthRelative(RelativeLength::Percent(50.0)))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::HeightRelative).unwrap(),
+            Some(AttributeValue::HeightRelative(
+                RelativeLength::ViewportHeight(20.0)
+            ))
+        );
+
+        let attrs = AttributeParser::parse_attributes("width:{xs:100%, md:10vw}").unwrap();
+        assert_eq!(
+            attrs
+                .resolve(Breakpoint::Lg)
+                .get(AttributeKind::WidthRelative)
+                .unwrap(),
+            Some(AttributeValue::WidthRelative(
+                RelativeLength::ViewportWidth(10.0)
+            ))
+        );
+
+        // Plain pixels are unchanged
+        let attrs = AttributeParser::parse_attributes("width:50").unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::WidthPixels).unwrap(),
+            Some(AttributeValue::WidthPixels(50.into()))
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_min_max() {
+        let attrs = AttributeParser::parse_attributes(
+            "min-width:120, max-width:960, min-height:40, max-height:{xs:200, lg:400}",
+        )
+        .unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::MinWidth).unwrap(),
+            Some(AttributeValue::MinWidth(120.into()))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::MaxWidth).unwrap(),
+            Some(AttributeValue::MaxWidth(960.into()))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::MinHeight).unwrap(),
+            Some(AttributeValue::MinHeight(40.into()))
+        );
+        assert_eq!(
+            attrs
+                .resolve(Breakpoint::Lg)
+                .get(AttributeKind::MaxHeight)
+                .unwrap(),
+            Some(AttributeValue::MaxHeight(400.into()))
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_palette() {
+        let attrs = AttributeParser::parse_attributes(
+            "text-color:palette(primary), bg:palette(background.weak)",
+        )
+        .unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::TextPalette).unwrap().unwrap(),
+            AttributeValue::TextPalette(PaletteColor::parse("primary").unwrap())
+        );
+        assert_eq!(
+            attrs
+                .get(AttributeKind::BackgroundPalette)
+                .unwrap()
+                .unwrap(),
+            AttributeValue::BackgroundPalette(PaletteColor::parse("background.weak").unwrap())
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_shadow() {
+        let attrs =
+            AttributeParser::parse_attributes("shadow:color(#00000080),offset(2.0,-4.0),blur(8)")
+                .unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::Shadow).unwrap().unwrap(),
+            AttributeValue::Shadow(iced::Shadow {
+                color: iced::Color::from_rgba8(0, 0, 0, 128.0 / 255.0),
+                offset: iced::Vector::new(2.0, -4.0),
+                blur_radius: 8.0,
+            })
+        );
+
+        // Shadow options are followed by other attributes
+        let attrs = AttributeParser::parse_attributes("shadow:blur(4), width:fill").unwrap();
+        assert!(attrs.get(AttributeKind::WidthLength).unwrap().is_some());
+    }
+
     #[traced_test]
     #[test]
     fn test_shaping() {
@@ -725,6 +1493,40 @@ mod tests {
         );
     }
 
+    #[traced_test]
+    #[test]
+    fn test_font() {
+        let attrs = AttributeParser::parse_attributes("font:monospace").unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::Font).unwrap().unwrap(),
+            AttributeValue::Font(iced::Font::MONOSPACE)
+        );
+
+        // Family names are shared by each parse
+        let attrs = AttributeParser::parse_attributes(r#"font:"Fira Sans""#).unwrap();
+        let again = AttributeParser::parse_attributes(r#"font:"Fira Sans""#).unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::Font).unwrap().unwrap(),
+            AttributeValue::Font(iced::Font::with_name("Fira Sans"))
+        );
+        match (
+            attrs.get(AttributeKind::Font).unwrap(),
+            again.get(AttributeKind::Font).unwrap(),
+        ) {
+            (
+                Some(AttributeValue::Font(iced::Font {
+                    family: iced::font::Family::Name(first),
+                    ..
+                })),
+                Some(AttributeValue::Font(iced::Font {
+                    family: iced::font::Family::Name(second),
+                    ..
+                })),
+            ) => assert!(std::ptr::eq(first, second)),
+            other => panic!("Expected named fonts, got {other:?}"),
+        }
+    }
+
     #[traced_test]
     #[test]
     fn test_wrapping() {
@@ -813,6 +1615,111 @@ mod tests {
         }
     }
 
+    #[traced_test]
+    #[test]
+    fn test_transition() {
+        let attrs = AttributeParser::parse_attributes(
+            "transition:opacity(300ms, ease-out), padding(0.5s), opacity:0.25",
+        )
+        .unwrap();
+
+        assert_eq!(
+            attrs.get(AttributeKind::Transition).unwrap(),
+            Some(AttributeValue::Transition(vec![
+                Transition {
+                    property: TransitionProperty::Opacity,
+                    duration: Duration::from_millis(300),
+                    easing: Easing::EaseOut,
+                },
+                Transition {
+                    property: TransitionProperty::Padding,
+                    duration: Duration::from_millis(500),
+                    easing: Easing::Linear,
+                },
+            ]))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::Opacity).unwrap(),
+            Some(AttributeValue::Opacity(0.25))
+        );
+
+        assert!(AttributeParser::parse_attributes("transition:margin(1s)").is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_handler() {
+        let attrs = AttributeParser::parse_attributes(
+            r#"on-press:publish("nav", "settings"), on-toggle:publish("dark-mode")"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            attrs.get(AttributeKind::OnPress).unwrap(),
+            Some(AttributeValue::OnPress(Handler::publish(
+                "nav",
+                Some("settings".into())
+            )))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::OnToggle).unwrap(),
+            Some(AttributeValue::OnToggle(Handler::publish(
+                "dark-mode",
+                None
+            )))
+        );
+
+        let attrs =
+            AttributeParser::parse_attributes(r#"on-press:state.increment("counter")"#).unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::OnPress).unwrap(),
+            Some(AttributeValue::OnPress(Handler::State(
+                StateAction::Increment("counter".into())
+            )))
+        );
+
+        assert!(AttributeParser::parse_attributes(r#"on-press:state.explode("counter")"#).is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_binding() {
+        let attrs = AttributeParser::parse_attributes("value:bind(user.name)").unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::Bind).unwrap(),
+            Some(AttributeValue::Bind("user.name".into()))
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_scrollbar() {
+        let attrs = AttributeParser::parse_attributes(
+            "direction: vertical(width(8), margin(2), scroller-width(6), anchor(end))",
+        )
+        .unwrap();
+
+        assert_eq!(
+            attrs.get(AttributeKind::ScrollDirection).unwrap(),
+            Some(AttributeValue::ScrollDirection(Direction::Vertical(
+                Scrollbar::new()
+                    .width(8.0)
+                    .margin(2.0)
+                    .scroller_width(6.0)
+                    .anchor(Anchor::End)
+            )))
+        );
+
+        let attrs = AttributeParser::parse_attributes("direction: both(spacing(4))").unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::ScrollDirection).unwrap(),
+            Some(AttributeValue::ScrollDirection(Direction::Both {
+                vertical: Scrollbar::new().spacing(4.0),
+                horizontal: Scrollbar::new().spacing(4.0),
+            }))
+        );
+    }
+
     #[traced_test]
     #[test]
     fn test_clip() {
@@ -836,4 +1743,59 @@ mod tests {
             _ => panic!("Clip AttributeValue not found"),
         }
     }
+
+    #[traced_test]
+    #[test]
+    fn test_toggler_style() {
+        let attrs = AttributeParser::parse_attributes(
+            "active-color: #ff0000, handle-colour: color(#00ff00), text-size: 18",
+        )
+        .unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::ActiveColor).unwrap(),
+            Some(AttributeValue::ActiveColor(Color::from_rgb8(255, 0, 0)))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::HandleColor).unwrap(),
+            Some(AttributeValue::HandleColor(Color::from_rgb8(0, 255, 0)))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::TextSize).unwrap(),
+            Some(AttributeValue::TextSize(Pixels(18.0)))
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_rule_style() {
+        let attrs = AttributeParser::parse_attributes(
+            "thickness: 2, color: palette(primary.weak), fill-mode: 80%",
+        )
+        .unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::Thickness).unwrap(),
+            Some(AttributeValue::Thickness(Pixels(2.0)))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::RulePalette).unwrap(),
+            Some(AttributeValue::RulePalette(
+                PaletteColor::parse("primary.weak").unwrap()
+            ))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::FillMode).unwrap(),
+            Some(AttributeValue::FillMode(FillMode::Percent(80.0)))
+        );
+
+        let attrs =
+            AttributeParser::parse_attributes("color: #ff0000, fill-mode: padded(4, 8)").unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::RuleColor).unwrap(),
+            Some(AttributeValue::RuleColor(Color::from_rgb8(255, 0, 0)))
+        );
+        assert_eq!(
+            attrs.get(AttributeKind::FillMode).unwrap(),
+            Some(AttributeValue::FillMode(FillMode::AsymmetricPadding(4, 8)))
+        );
+    }
 }
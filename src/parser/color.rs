@@ -1,10 +1,12 @@
 use iced::Color;
-use pest::Parser;
+use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
 use tracing::debug;
 
 use super::ParseError;
 
+mod named;
+
 #[derive(Parser)]
 #[grammar = "parser/color.pest"]
 pub struct ColorParser;
@@ -124,12 +126,59 @@ impl ColorParser {
 
                     return Ok(Color::from_rgba8(red, green, blue, alpha));
                 }
+                Rule::color_hsl | Rule::color_hsla => {
+                    let mut inner = pair.into_inner();
+                    let hue = Self::parse_number(inner.next().unwrap())?;
+                    let saturation = Self::parse_number(inner.next().unwrap())?;
+                    let lightness = Self::parse_number(inner.next().unwrap())?;
+                    let alpha = match inner.next() {
+                        Some(alpha) => Self::parse_number(alpha)?,
+                        None => 1.0,
+                    };
+
+                    return Ok(hsla(hue, saturation, lightness, alpha));
+                }
+                Rule::color_named => {
+                    return named::named_color(pair.as_str())
+                        .ok_or(ParseError::InvalidColor(pair.as_str().to_string()));
+                }
                 _ => continue,
             }
         }
 
         Ok(iced::Color::BLACK)
     }
+
+    /// Parse a hue, percentage or alpha. Percentages are returned as a fraction.
+    fn parse_number(pair: Pair<'_, Rule>) -> Result<f32, ParseError> {
+        match pair.as_rule() {
+            Rule::number => pair.as_str().parse().map_err(ParseError::Float),
+            Rule::percentage => Ok(Self::parse_number(pair.into_inner().next().unwrap())? * 0.01),
+            _ => Self::parse_number(pair.into_inner().next().unwrap()),
+        }
+    }
+}
+
+/// Convert a hue in degrees, and saturation, lightness and alpha in the range 0.0-1.0 to a [`Color`]
+fn hsla(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Color {
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let saturation = saturation.clamp(0.0, 1.0);
+    let lightness = lightness.clamp(0.0, 1.0);
+
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let m = lightness - chroma / 2.0;
+    Color::from_rgba(r + m, g + m, b + m, alpha.clamp(0.0, 1.0))
 }
 
 #[cfg(test)]
@@ -246,6 +295,45 @@ mod tests {
         }
     }
 
+    #[traced_test]
+    #[test]
+    fn test_parse_named_color() {
+        color_eq(
+            ColorParser::parse_str("slategray").unwrap(),
+            Color::from_rgb8(0x70, 0x80, 0x90),
+        );
+        color_eq(
+            ColorParser::parse_str("Red").unwrap(),
+            Color::from_rgb(1.0, 0.0, 0.0),
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_parse_hsl_color() {
+        color_eq(
+            ColorParser::parse_str("hsl(210, 50%, 40%)").unwrap(),
+            Color::from_rgb8(0x33, 0x66, 0x99),
+        );
+        color_eq(
+            ColorParser::parse_str("hsla(120deg, 100%, 25%, 0.5)").unwrap(),
+            Color::from_rgba(0.0, 0.5, 0.0, 0.5),
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_parse_rgb_function() {
+        color_eq(
+            ColorParser::parse_str("rgb(255, 127, 255)").unwrap(),
+            Color::from_rgb(1.0, 0.5, 1.0),
+        );
+        color_eq(
+            ColorParser::parse_str("rgba(1.0, 0.34, 0.2, 0.5)").unwrap(),
+            Color::from_rgba(1.0, 0.34, 0.2, 0.5),
+        );
+    }
+
     #[test]
     fn test_invalid_color_format() {
         let result = ColorParser::parse_str("invalid-color");
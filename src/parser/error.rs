@@ -10,7 +10,7 @@ use thiserror::Error;
 
 use crate::SyncError;
 
-use super::{ParserContext, Rule};
+use super::{location::SourceLocation, ParserContext, Rule};
 
 #[derive(Error, Debug)]
 pub struct ParseErrorContext {
@@ -22,6 +22,11 @@ impl ParseErrorContext {
     pub fn new(context: ParserContext, error: ParseError) -> Self {
         Self { context, error }
     }
+
+    /// Get the location in the markup of the element being parsed when the error occurred
+    pub fn location(&self) -> SourceLocation {
+        SourceLocation::from(&self.context)
+    }
 }
 
 impl std::fmt::Display for ParseErrorContext {
@@ -96,6 +101,21 @@ pub enum ParseError {
     #[error("Missing {0}")]
     Missing(&'static str),
 
+    #[error("Invalid value {1} of attribute {0}")]
+    InvalidAttribute(String, String),
+
+    #[error("Custom attribute {0}: {1}")]
+    CustomAttribute(String, String),
+
+    #[error("Invalid arguments of module {0}: {1}")]
+    ModuleArguments(String, crate::module::error::ModuleError),
+
+    #[error("Unknown widget {0}")]
+    UnknownWidget(String),
+
+    #[error("Attribute {attribute} is not supported by {element}")]
+    UnsupportedAttribute { element: String, attribute: String },
+
     #[error(transparent)]
     Color(#[from] pest::error::Error<super::color::Rule>),
 
@@ -111,6 +131,15 @@ pub enum ParseError {
     #[error(transparent)]
     Value(#[from] pest::error::Error<super::value::Rule>),
 
+    #[error(transparent)]
+    Expr(#[from] pest::error::Error<super::expr::Rule>),
+
+    #[error(transparent)]
+    Theme(#[from] pest::error::Error<super::theme::Rule>),
+
+    #[error(transparent)]
+    Style(#[from] pest::error::Error<super::style::Rule>),
+
     #[error("Invalid Color {0}")]
     InvalidColor(String),
 
@@ -126,6 +155,12 @@ pub enum ParseError {
     #[error(transparent)]
     Url(#[from] url::ParseError),
 
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("Cannot embed {0}: {1}")]
+    Embed(String, String),
+
     #[error(transparent)]
     QrCode(#[from] iced::widget::qr_code::Error),
 
@@ -0,0 +1,676 @@
+//! Expression parser and evaluator for computing derived values.
+//!
+//! Expressions support arithmetic on numbers, single quoted strings, function calls,
+//! and a pipe operator which passes the value on the left as the first argument of a function.
+//!
+//! ```text
+//! value * 100 |> round
+//! (value / 1024) |> fixed(2)
+//! 'CPU ' + value
+//! value |> capture('temp=([0-9]+)') |> template('{} °C')
+//! if(value >= 100, 'Full', 'Filling')
+//! ```
+//!
+//! Comparisons evaluate to `true` or `false`. Numbers are compared by value, and other values by their text.
+
+use pest::iterators::Pair;
+use pest::Parser;
+use pest_derive::Parser;
+use thiserror::Error;
+use tracing::debug;
+
+use super::ParseError;
+
+/// Largest number of fractional digits of the `fixed` and `number` functions
+pub const MAX_DIGITS: usize = 17;
+
+/// Value produced by evaluating an [`Expr`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprValue {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+}
+
+impl ExprValue {
+    /// Create a value from text, which is a [`ExprValue::Number`] if the text parses as a number
+    pub fn from_text(text: &str) -> Self {
+        match text.trim().parse::<f64>() {
+            Ok(number) => ExprValue::Number(number),
+            Err(_) => ExprValue::Text(text.to_string()),
+        }
+    }
+
+    pub fn number(&self) -> Result<f64, ExprError> {
+        match self {
+            ExprValue::Number(number) => Ok(*number),
+            ExprValue::Text(text) => Err(ExprError::Type(format!("expected number, got '{text}'"))),
+            ExprValue::Bool(flag) => Err(ExprError::Type(format!("expected number, got {flag}"))),
+        }
+    }
+
+    /// Returns true if the value is true, a non-zero number, or non-empty text
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            ExprValue::Number(number) => *number != 0.0,
+            ExprValue::Text(text) => !text.is_empty(),
+            ExprValue::Bool(flag) => *flag,
+        }
+    }
+
+    /// Get a number of fractional digits, which must be between 0 and [`MAX_DIGITS`]
+    fn digits(&self) -> Result<usize, ExprError> {
+        let digits = self.number()?;
+        if (0.0..=MAX_DIGITS as f64).contains(&digits) {
+            Ok(digits as usize)
+        } else {
+            Err(ExprError::Type(format!(
+                "expected between 0 and {MAX_DIGITS} digits, got {digits}"
+            )))
+        }
+    }
+}
+
+impl std::fmt::Display for ExprValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExprValue::Number(number) => write!(f, "{number}"),
+            ExprValue::Text(text) => f.write_str(text),
+            ExprValue::Bool(flag) => write!(f, "{flag}"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ExprError {
+    #[error("unknown variable '{0}'")]
+    UnknownVariable(String),
+
+    #[error("unknown function '{0}'")]
+    UnknownFunction(String),
+
+    #[error("function '{function}' expects {expected} arguments")]
+    Arity {
+        function: String,
+        expected: &'static str,
+    },
+
+    #[error("type error: {0}")]
+    Type(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    /// Returns true if the operator compares its operands
+    fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+        )
+    }
+}
+
+/// Parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(ExprValue),
+    Var(String),
+    Neg(Box<Expr>),
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+impl Expr {
+    /// Evaluate the expression, resolving variables with the supplied closure
+    pub fn eval(
+        &self,
+        resolve: &dyn Fn(&str) -> Option<ExprValue>,
+    ) -> Result<ExprValue, ExprError> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Var(name) => {
+                resolve(name).ok_or_else(|| ExprError::UnknownVariable(name.clone()))
+            }
+            Expr::Neg(expr) => Ok(ExprValue::Number(-expr.eval(resolve)?.number()?)),
+            Expr::Binary { op, lhs, rhs } => {
+                let lhs = lhs.eval(resolve)?;
+                let rhs = rhs.eval(resolve)?;
+
+                match (op, lhs, rhs) {
+                    (op, lhs, rhs) if op.is_comparison() => {
+                        Ok(ExprValue::Bool(compare(*op, &lhs, &rhs)))
+                    }
+                    // Adding text concatenates
+                    (BinaryOp::Add, ExprValue::Text(lhs), rhs) => {
+                        Ok(ExprValue::Text(format!("{lhs}{rhs}")))
+                    }
+                    (BinaryOp::Add, lhs, ExprValue::Text(rhs)) => {
+                        Ok(ExprValue::Text(format!("{lhs}{rhs}")))
+                    }
+                    (op, lhs, rhs) => {
+                        let (lhs, rhs) = (lhs.number()?, rhs.number()?);
+                        Ok(ExprValue::Number(match op {
+                            BinaryOp::Add => lhs + rhs,
+                            BinaryOp::Sub => lhs - rhs,
+                            BinaryOp::Mul => lhs * rhs,
+                            BinaryOp::Div => lhs / rhs,
+                            _ => lhs % rhs,
+                        }))
+                    }
+                }
+            }
+            Expr::Call { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(resolve))
+                    .collect::<Result<Vec<ExprValue>, _>>()?;
+                Self::call(name, args)
+            }
+        }
+    }
+
+    /// Get the names of the variables read by the expression
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Literal(_) => {}
+            Expr::Var(name) => names.push(name),
+            Expr::Neg(expr) => expr.collect_variables(names),
+            Expr::Binary { lhs, rhs, .. } => {
+                lhs.collect_variables(names);
+                rhs.collect_variables(names);
+            }
+            Expr::Call { args, .. } => args.iter().for_each(|arg| arg.collect_variables(names)),
+        }
+    }
+
+    /// Evaluate the expression with `value` bound to the supplied [`ExprValue`]
+    pub fn eval_value(&self, value: &ExprValue) -> Result<ExprValue, ExprError> {
+        self.eval(&|name| (name == "value").then(|| value.clone()))
+    }
+
+    fn call(name: &str, args: Vec<ExprValue>) -> Result<ExprValue, ExprError> {
+        let arity = |expected: &'static str| ExprError::Arity {
+            function: name.to_string(),
+            expected,
+        };
+
+        let number = |f: fn(f64) -> f64| match args.as_slice() {
+            [value] => Ok(ExprValue::Number(f(value.number()?))),
+            _ => Err(arity("1")),
+        };
+
+        let text = |f: fn(&str) -> String| match args.as_slice() {
+            [value] => Ok(ExprValue::Text(f(&value.to_string()))),
+            _ => Err(arity("1")),
+        };
+
+        match name {
+            "round" => number(f64::round),
+            "floor" => number(f64::floor),
+            "ceil" => number(f64::ceil),
+            "abs" => number(f64::abs),
+            "sqrt" => number(f64::sqrt),
+            "upper" => text(str::to_uppercase),
+            "lower" => text(str::to_lowercase),
+            "trim" => text(|s| s.trim().to_string()),
+            "len" => match args.as_slice() {
+                [value] => Ok(ExprValue::Number(value.to_string().chars().count() as f64)),
+                _ => Err(arity("1")),
+            },
+            "fixed" => match args.as_slice() {
+                [value, digits] => Ok(ExprValue::Text(format!(
+                    "{:.*}",
+                    digits.digits()?,
+                    value.number()?
+                ))),
+                _ => Err(arity("2")),
+            },
+            "truncate" => match args.as_slice() {
+                [value, length] => Ok(ExprValue::Text(
+                    value
+                        .to_string()
+                        .chars()
+                        .take(length.number()? as usize)
+                        .collect(),
+                )),
+                _ => Err(arity("2")),
+            },
+            "capture" => match args.as_slice() {
+                [value, pattern] => Self::capture(value, pattern, 1),
+                [value, pattern, group] => Self::capture(value, pattern, group.number()? as usize),
+                _ => Err(arity("2 or 3")),
+            },
+            "template" => match args.as_slice() {
+                [value, template] => Ok(ExprValue::Text(interpolate(
+                    &template.to_string(),
+                    &value.to_string(),
+                ))),
+                _ => Err(arity("2")),
+            },
+            "number" => match args.as_slice() {
+                [value, digits] => Ok(ExprValue::Text(group_thousands(&format!(
+                    "{:.*}",
+                    digits.digits()?,
+                    value.number()?
+                )))),
+                _ => Err(arity("2")),
+            },
+            "min" => match args.as_slice() {
+                [a, b] => Ok(ExprValue::Number(a.number()?.min(b.number()?))),
+                _ => Err(arity("2")),
+            },
+            "max" => match args.as_slice() {
+                [a, b] => Ok(ExprValue::Number(a.number()?.max(b.number()?))),
+                _ => Err(arity("2")),
+            },
+            "if" => match args.as_slice() {
+                [condition, then, otherwise] => Ok(if condition.is_truthy() {
+                    then.clone()
+                } else {
+                    otherwise.clone()
+                }),
+                _ => Err(arity("3")),
+            },
+            "clamp" => match args.as_slice() {
+                [value, min, max] => {
+                    let (min, max) = (min.number()?, max.number()?);
+                    // f64::clamp panics if the bounds are NaN or reversed
+                    if min.is_nan() || max.is_nan() || min > max {
+                        return Err(ExprError::Type(format!(
+                            "clamp bounds {min} and {max} aren't in order"
+                        )));
+                    }
+                    Ok(ExprValue::Number(value.number()?.clamp(min, max)))
+                }
+                _ => Err(arity("3")),
+            },
+            _ => Err(ExprError::UnknownFunction(name.to_string())),
+        }
+    }
+
+    /// Get a capture group of the first match of a regex pattern, or the whole match if the
+    /// pattern has no such group. Evaluates to an empty string if the pattern doesn't match.
+    fn capture(
+        value: &ExprValue,
+        pattern: &ExprValue,
+        group: usize,
+    ) -> Result<ExprValue, ExprError> {
+        let regex = regex::Regex::new(&pattern.to_string())
+            .map_err(|e| ExprError::Type(format!("invalid pattern: {e}")))?;
+
+        let text = value.to_string();
+        let captured = regex
+            .captures(&text)
+            .and_then(|captures| captures.get(group).or_else(|| captures.get(0)))
+            .map(|m| m.as_str().to_string())
+            .unwrap_or_default();
+
+        Ok(ExprValue::from_text(&captured))
+    }
+}
+
+/// Compare two values, by number if both are numbers, otherwise by their text
+fn compare(op: BinaryOp, lhs: &ExprValue, rhs: &ExprValue) -> bool {
+    use std::cmp::Ordering;
+
+    let ordering = match (lhs, rhs) {
+        (ExprValue::Number(lhs), ExprValue::Number(rhs)) => lhs.partial_cmp(rhs),
+        (lhs, rhs) => Some(lhs.to_string().cmp(&rhs.to_string())),
+    };
+
+    match (op, ordering) {
+        (BinaryOp::Eq, ordering) => ordering == Some(Ordering::Equal),
+        (BinaryOp::Ne, ordering) => ordering != Some(Ordering::Equal),
+        // Comparisons with NaN are false
+        (_, None) => false,
+        (BinaryOp::Lt, Some(ordering)) => ordering.is_lt(),
+        (BinaryOp::Le, Some(ordering)) => ordering.is_le(),
+        (BinaryOp::Gt, Some(ordering)) => ordering.is_gt(),
+        (_, Some(ordering)) => ordering.is_ge(),
+    }
+}
+
+/// Replace `{}` and `{value}` placeholders in a template with a value
+pub fn interpolate(template: &str, value: &str) -> String {
+    template.replace("{value}", value).replace("{}", value)
+}
+
+/// Insert thousands separators into the integer part of a formatted number
+fn group_thousands(number: &str) -> String {
+    let (sign, number) = match number.strip_prefix('-') {
+        Some(number) => ("-", number),
+        None => ("", number),
+    };
+
+    let (integer, fraction) = match number.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number, None),
+    };
+
+    let mut grouped = String::with_capacity(number.len() + integer.len() / 3);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    match fraction {
+        Some(fraction) => format!("{sign}{grouped}.{fraction}"),
+        None => format!("{sign}{grouped}"),
+    }
+}
+
+#[derive(Parser)]
+#[grammar = "parser/expr.pest"]
+pub struct ExprParser;
+
+impl ExprParser {
+    pub fn parse_str(data: &str) -> Result<Expr, ParseError> {
+        debug!("Parsing expression {data}");
+        let pipeline = ExprParser::parse(Rule::expression, data)?
+            .next()
+            .ok_or(ParseError::Missing("expression"))?;
+
+        Self::parse_pipeline(pipeline)
+    }
+
+    fn parse_pipeline(pair: Pair<Rule>) -> Result<Expr, ParseError> {
+        let mut inner = pair.into_inner();
+        let mut expr =
+            Self::parse_comparison(inner.next().ok_or(ParseError::Missing("expression"))?)?;
+
+        // Each filter receives the expression on the left as its first argument
+        for filter in inner {
+            let mut filter = filter.into_inner();
+            let name = filter
+                .next()
+                .ok_or(ParseError::Missing("filter name"))?
+                .as_str()
+                .to_string();
+
+            let mut args = vec![expr];
+            for arg in filter {
+                args.push(Self::parse_comparison(arg)?);
+            }
+
+            expr = Expr::Call { name, args };
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_comparison(pair: Pair<Rule>) -> Result<Expr, ParseError> {
+        let mut inner = pair.into_inner();
+        let lhs = Self::parse_sum(inner.next().ok_or(ParseError::Missing("operand"))?)?;
+
+        let (Some(op), Some(rhs)) = (inner.next(), inner.next()) else {
+            return Ok(lhs);
+        };
+
+        let op = match op.as_str() {
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">" => BinaryOp::Gt,
+            _ => BinaryOp::Ge,
+        };
+
+        Ok(Expr::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(Self::parse_sum(rhs)?),
+        })
+    }
+
+    fn parse_sum(pair: Pair<Rule>) -> Result<Expr, ParseError> {
+        let mut inner = pair.into_inner();
+        let mut expr = Self::parse_product(inner.next().ok_or(ParseError::Missing("operand"))?)?;
+
+        while let (Some(op), Some(rhs)) = (inner.next(), inner.next()) {
+            let op = match op.as_str() {
+                "+" => BinaryOp::Add,
+                _ => BinaryOp::Sub,
+            };
+            expr = Expr::Binary {
+                op,
+                lhs: Box::new(expr),
+                rhs: Box::new(Self::parse_product(rhs)?),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_product(pair: Pair<Rule>) -> Result<Expr, ParseError> {
+        let mut inner = pair.into_inner();
+        let mut expr = Self::parse_unary(inner.next().ok_or(ParseError::Missing("operand"))?)?;
+
+        while let (Some(op), Some(rhs)) = (inner.next(), inner.next()) {
+            let op = match op.as_str() {
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                _ => BinaryOp::Rem,
+            };
+            expr = Expr::Binary {
+                op,
+                lhs: Box::new(expr),
+                rhs: Box::new(Self::parse_unary(rhs)?),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_unary(pair: Pair<Rule>) -> Result<Expr, ParseError> {
+        let mut negate = false;
+
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::neg => negate = true,
+                _ => {
+                    let expr = Self::parse_primary(pair)?;
+                    return Ok(if negate {
+                        Expr::Neg(Box::new(expr))
+                    } else {
+                        expr
+                    });
+                }
+            }
+        }
+
+        Err(ParseError::Missing("operand"))
+    }
+
+    fn parse_primary(pair: Pair<Rule>) -> Result<Expr, ParseError> {
+        match pair.as_rule() {
+            Rule::number => Ok(Expr::Literal(ExprValue::Number(
+                pair.as_str().parse().map_err(ParseError::Float)?,
+            ))),
+            Rule::string => Ok(Expr::Literal(ExprValue::Text(
+                pair.into_inner().as_str().to_string(),
+            ))),
+            Rule::ident => Ok(match pair.as_str() {
+                "true" => Expr::Literal(ExprValue::Bool(true)),
+                "false" => Expr::Literal(ExprValue::Bool(false)),
+                name => Expr::Var(name.to_string()),
+            }),
+            Rule::call => {
+                let mut inner = pair.into_inner();
+                let name = inner
+                    .next()
+                    .ok_or(ParseError::Missing("function name"))?
+                    .as_str()
+                    .to_string();
+                let args = inner
+                    .map(Self::parse_comparison)
+                    .collect::<Result<Vec<Expr>, _>>()?;
+                Ok(Expr::Call { name, args })
+            }
+            Rule::pipeline => Self::parse_pipeline(pair),
+            _ => Err(ParseError::UnsupportedRule(format!(
+                "{}: {} {:?}",
+                file!(),
+                line!(),
+                pair.as_rule()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{ExprParser, ExprValue};
+
+    fn eval(expr: &str, value: ExprValue) -> ExprValue {
+        ExprParser::parse_str(expr)
+            .unwrap()
+            .eval_value(&value)
+            .unwrap()
+    }
+
+    #[traced_test]
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            eval("1 + 2 * 3 - -1", ExprValue::Number(0.0)),
+            ExprValue::Number(8.0)
+        );
+        assert_eq!(
+            eval("(1 + 2) * value", ExprValue::Number(2.0)),
+            ExprValue::Number(6.0)
+        );
+        assert_eq!(
+            eval("7 % 4", ExprValue::Number(0.0)),
+            ExprValue::Number(3.0)
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn pipeline() {
+        assert_eq!(
+            eval("value * 100 |> round", ExprValue::Number(0.4256)),
+            ExprValue::Number(43.0)
+        );
+        assert_eq!(
+            eval("value |> fixed(2)", ExprValue::Number(3.14159)),
+            ExprValue::Text("3.14".into())
+        );
+        assert_eq!(
+            eval("value |> clamp(0, 10) |> max(5)", ExprValue::Number(42.0)),
+            ExprValue::Number(10.0)
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn text() {
+        assert_eq!(
+            eval("'CPU ' + value + '%'", ExprValue::Number(12.0)),
+            ExprValue::Text("CPU 12%".into())
+        );
+        assert_eq!(
+            eval("value |> trim |> upper", ExprValue::Text(" ok ".into())),
+            ExprValue::Text("OK".into())
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn output_functions() {
+        assert_eq!(
+            eval("value |> truncate(5)", ExprValue::Text("snowcap".into())),
+            ExprValue::Text("snowc".into())
+        );
+        assert_eq!(
+            eval(
+                "value |> capture('temp=([0-9]+)') |> template('{} C')",
+                ExprValue::Text("sensor temp=21 ok".into())
+            ),
+            ExprValue::Text("21 C".into())
+        );
+        assert_eq!(
+            eval("value |> number(1)", ExprValue::Number(-1234567.89)),
+            ExprValue::Text("-1,234,567.9".into())
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn comparisons() {
+        assert_eq!(
+            eval("value * 2 >= 10", ExprValue::Number(5.0)),
+            ExprValue::Bool(true)
+        );
+        assert_eq!(
+            eval("value != 'ok'", ExprValue::Text("ok".into())),
+            ExprValue::Bool(false)
+        );
+        assert_eq!(
+            eval("if(value < 600, 12, 16)", ExprValue::Number(480.0)),
+            ExprValue::Number(12.0)
+        );
+        assert_eq!(
+            eval("if(false, 'a', 'b') |> upper", ExprValue::Number(0.0)),
+            ExprValue::Text("B".into())
+        );
+    }
+
+    #[traced_test]
+    #[test]
+    fn errors() {
+        assert!(ExprParser::parse_str("value *").is_err());
+
+        let expr = ExprParser::parse_str("other + 1").unwrap();
+        assert!(expr.eval_value(&ExprValue::Number(1.0)).is_err());
+
+        let expr = ExprParser::parse_str("value |> nope").unwrap();
+        assert!(expr.eval_value(&ExprValue::Number(1.0)).is_err());
+
+        let expr = ExprParser::parse_str("value * 2").unwrap();
+        assert!(expr.eval_value(&ExprValue::Text("abc".into())).is_err());
+
+        // Reversed or NaN bounds of clamp are errors rather than panics
+        let expr = ExprParser::parse_str("value |> clamp(10, 0)").unwrap();
+        assert!(expr.eval_value(&ExprValue::Number(1.0)).is_err());
+        let expr = ExprParser::parse_str("clamp(1, 0, value)").unwrap();
+        assert!(expr.eval_value(&ExprValue::Number(f64::NAN)).is_err());
+
+        // The number of digits is bounded
+        let expr = ExprParser::parse_str("value |> fixed(100000000)").unwrap();
+        assert!(expr.eval_value(&ExprValue::Number(1.0)).is_err());
+        let expr = ExprParser::parse_str("value |> number(-1)").unwrap();
+        assert!(expr.eval_value(&ExprValue::Number(1.0)).is_err());
+    }
+}
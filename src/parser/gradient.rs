@@ -1,5 +1,13 @@
-use iced::{gradient::Linear, Gradient};
-use pest::Parser;
+//! Gradient parser
+//!
+//! Gradients are an angle followed by up to 8 color stops, such as `to-right, [#fff@0.0, #000@1.0]`.
+//! The angle is a direction keyword (`to-top`, `to-right`, `to-bottom-left`, ...), degrees (`45deg`),
+//! or radians (`1.57`). As in CSS, `0deg` points to the top and angles increase clockwise.
+
+use std::f32::consts::PI;
+
+use iced::{gradient::Linear, Gradient, Radians};
+use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
 use tracing::debug;
 
@@ -20,16 +28,12 @@ impl GradientParser {
             match pair.as_rule() {
                 Rule::gradient => {
                     let mut inner = pair.into_inner();
-                    let rad: f32 = inner
-                        .next()
-                        .unwrap()
-                        .as_str()
-                        .parse()
-                        .map_err(ParseError::Float)?;
 
-                    debug!("Gradient rad {rad}");
+                    let angle = Self::parse_angle(inner.next().unwrap())?;
+
+                    debug!("Gradient angle {angle:?}");
 
-                    let mut linear = Linear::new(rad);
+                    let mut linear = Linear::new(angle);
 
                     let stops = inner.next().unwrap().into_inner();
                     for stop in stops {
@@ -57,11 +61,51 @@ impl GradientParser {
 
         Ok(Gradient::Linear(Linear::new(1.0)))
     }
+
+    /// Parse the angle of a linear gradient from a direction keyword, degrees, or radians
+    fn parse_angle(pair: Pair<'_, Rule>) -> Result<Radians, ParseError> {
+        let angle = pair.into_inner().next().unwrap();
+
+        match angle.as_rule() {
+            Rule::direction => {
+                let turns = match angle.into_inner().next().unwrap().as_rule() {
+                    Rule::to_top => 0.0,
+                    Rule::to_top_right => 0.125,
+                    Rule::to_right => 0.25,
+                    Rule::to_bottom_right => 0.375,
+                    Rule::to_bottom => 0.5,
+                    Rule::to_bottom_left => 0.625,
+                    Rule::to_left => 0.75,
+                    Rule::to_top_left => 0.875,
+                    rule => return Err(ParseError::UnsupportedRule(format!("{rule:?}"))),
+                };
+                Ok(Radians(turns * 2.0 * PI))
+            }
+            Rule::degrees => {
+                let degrees: f32 = angle
+                    .into_inner()
+                    .as_str()
+                    .parse()
+                    .map_err(ParseError::Float)?;
+                Ok(Radians(degrees.to_radians()))
+            }
+            Rule::radians => {
+                let radians: f32 = angle
+                    .into_inner()
+                    .as_str()
+                    .parse()
+                    .map_err(ParseError::Float)?;
+                Ok(Radians(radians))
+            }
+            rule => Err(ParseError::UnsupportedRule(format!("{rule:?}"))),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use approx::assert_relative_eq;
     use tracing_test::traced_test;
 
     #[traced_test]
@@ -74,4 +118,23 @@ mod tests {
             tracing::info!("Got gradient {gradient:#?}");
         }
     }
+
+    #[traced_test]
+    #[test]
+    fn test_angle_keywords() {
+        let angle = |data: &str| match GradientParser::parse_str(data).unwrap() {
+            Gradient::Linear(linear) => linear.angle.0,
+        };
+
+        assert_relative_eq!(angle("to-right, [#fff@0.0, #000@1.0]"), PI / 2.0);
+        assert_relative_eq!(angle("to-top-left, [#fff@0.0, #000@1.0]"), PI * 1.75);
+        assert_relative_eq!(angle("45deg, [#fff@0.0, #000@1.0]"), PI / 4.0);
+        assert_relative_eq!(angle("1.5rad, [#fff@0.0, #000@1.0]"), 1.5);
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_unknown_angle() {
+        assert!(GradientParser::parse_str("radial, [#fff@0.0, #000@1.0]").is_err());
+    }
 }
@@ -35,7 +35,16 @@ impl std::hash::Hash for ValueData {
             ValueData::Float(num) => state.write(&num.to_ne_bytes()),
             ValueData::Integer(num) => state.write(&num.to_ne_bytes()),
             ValueData::Boolean(b) => b.hash(state),
+            ValueData::Duration(duration) => duration.hash(state),
+            ValueData::Size(size) => state.write(&size.to_ne_bytes()),
+            ValueData::Url(url) => url.hash(state),
+            ValueData::Bytes(bytes) => bytes.hash(state),
             ValueData::Array(vec) => vec.hash(state),
+            ValueData::Map(map) => map.hash(state),
+            ValueData::Labelled(label, value) => {
+                label.hash(state);
+                value.hash(state);
+            }
             ValueData::AttributeKind(kind) => kind.hash(state),
             ValueData::None => {}
         }
@@ -0,0 +1,441 @@
+//! Incremental reparsing of changed regions of markup
+//!
+//! Each element node stores the byte range it was parsed from. When markup is reloaded, the [`Edit`] between the
+//! previous and new text is found by comparing their common prefix and suffix, and only the deepest element enclosing
+//! the edit is reparsed. The resulting subtree is diffed against that element rather than diffing complete trees.
+//!
+//! If the reparsed region doesn't parse as a single element, such as when the edit adds a sibling, the next enclosing
+//! element is tried. Edits outside any element, such as in a theme definition, require a full reparse.
+
+use std::ops::Range;
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use tracing::debug;
+
+use crate::{node::Content, IndexedTree, NodeRef, Tree};
+
+use super::{error::ParseErrorContext, SnowcapParser};
+
+/// Change between two versions of markup. Bytes before `start` are unchanged, and bytes after `old_end` in the
+/// previous text are unchanged at `new_end` in the new text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Edit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl Edit {
+    /// Find the edit between two versions of markup, or None if they are identical
+    pub fn between(old: &str, new: &str) -> Option<Self> {
+        if old == new {
+            return None;
+        }
+
+        let mut start = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(start) || !new.is_char_boundary(start) {
+            start -= 1;
+        }
+
+        // The suffix can't overlap the prefix
+        let mut suffix = old
+            .bytes()
+            .rev()
+            .zip(new.bytes().rev())
+            .take(old.len().min(new.len()) - start)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix)
+        {
+            suffix -= 1;
+        }
+
+        Some(Self {
+            start,
+            old_end: old.len() - suffix,
+            new_end: new.len() - suffix,
+        })
+    }
+
+    /// Returns true if the edit is inside a span of the previous text
+    fn inside(&self, span: &Range<usize>) -> bool {
+        span.start <= self.start && self.old_end <= span.end
+    }
+
+    /// Get the position in the new text of a position in the previous text at or after the end of the edit
+    fn shift(&self, position: usize) -> usize {
+        position - self.old_end + self.new_end
+    }
+}
+
+/// Collect the element nodes enclosing an edit, from the outermost to the innermost
+fn enclosing(noderef: &NodeRef, edit: &Edit, chain: &mut Vec<NodeRef>) {
+    let children = noderef
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+
+    for child in children {
+        let inside = match (child.node().data().span(), child.node().data().content()) {
+            (_, Content::Root | Content::Value(_) | Content::None) => false,
+            (Some(span), _) => edit.inside(span),
+            (None, _) => false,
+        };
+
+        if inside {
+            chain.push(child.clone());
+            enclosing(&child, edit, chain);
+            return;
+        }
+    }
+}
+
+/// Move the spans of nodes at or after the end of an edit, and extend the spans enclosing it
+fn shift_spans(noderef: &NodeRef, edit: &Edit) {
+    let span = noderef.node().data().span().cloned();
+
+    if let Some(span) = span {
+        let shifted = if span.start >= edit.old_end {
+            edit.shift(span.start)..edit.shift(span.end)
+        } else if span.end >= edit.old_end {
+            span.start..edit.shift(span.end)
+        } else {
+            span
+        };
+        noderef.node_mut().data_mut().set_span(Some(shifted));
+    }
+
+    let children = noderef
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+
+    for child in children {
+        shift_spans(&child, edit);
+    }
+}
+
+/// Clear the spans of a node and its descendants, so they are never reparsed incrementally
+fn clear_spans(noderef: &NodeRef) {
+    noderef.node_mut().data_mut().set_span(None);
+
+    let children = noderef
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+
+    for child in children {
+        clear_spans(&child);
+    }
+}
+
+/// Copy the spans of a parsed subtree, offset by the start of the region it was parsed from, into the subtree of
+/// the live tree it was patched into. Nodes kept by a patch still have the spans of the previous text.
+pub(crate) fn copy_spans(from: &NodeRef, to: &NodeRef, offset: usize) {
+    let span = from
+        .node()
+        .data()
+        .span()
+        .map(|span| span.start + offset..span.end + offset);
+    to.node_mut().data_mut().set_span(span);
+
+    let from_children = from
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+    let to_children = to
+        .node()
+        .children()
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+
+    if from_children.len() != to_children.len() {
+        to_children.iter().for_each(clear_spans);
+        return;
+    }
+
+    for (from, to) in from_children.iter().zip(to_children.iter()) {
+        copy_spans(from, to, offset);
+    }
+}
+
+/// Find the body of a named region, such as `region#sidebar { ... }`, in markup. Returns the byte range between the
+/// braces of the region. Strings and comments are skipped, so they may contain unbalanced braces.
+pub(crate) fn region_body(markup: &str, name: &str) -> Option<Range<usize>> {
+    let bytes = markup.as_bytes();
+    let mut open = None;
+    let mut depth = 0;
+    let mut position = 0;
+
+    while position < bytes.len() {
+        match bytes[position] {
+            b'"' => {
+                position += 1;
+                while position < bytes.len() && bytes[position] != b'"' {
+                    position += if bytes[position] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'/' if bytes.get(position + 1) == Some(&b'/') => {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+            }
+            b'{' if open.is_some() => depth += 1,
+            b'}' if open.is_some() => {
+                if depth == 0 {
+                    return open.map(|start| start..position);
+                }
+                depth -= 1;
+            }
+            _ if open.is_none() => {
+                if let Some(start) = region_start(markup, position, name) {
+                    open = Some(start);
+                    position = start;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+
+    None
+}
+
+/// Get the position after the opening brace of the region `name`, if its declaration starts at `position`
+fn region_start(markup: &str, position: usize, name: &str) -> Option<usize> {
+    if !markup.is_char_boundary(position) {
+        return None;
+    }
+
+    let is_label = |c: char| c.is_ascii_alphanumeric() || c == '-';
+
+    // The keyword can't be the end of a longer label
+    if markup[..position].chars().next_back().is_some_and(is_label) {
+        return None;
+    }
+
+    let rest = markup.get(position..)?;
+    let keyword = rest.get(..6)?;
+    if !keyword.eq_ignore_ascii_case("region") {
+        return None;
+    }
+
+    let rest = rest[6..].trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix(name)?;
+    if rest.chars().next().is_some_and(is_label) {
+        return None;
+    }
+
+    let rest = rest.trim_start().strip_prefix('{')?;
+    Some(markup.len() - rest.len())
+}
+
+/// An element of the live tree reparsed from the changed region of new markup
+pub(crate) struct Reparse {
+    /// Node of the element in the live tree
+    target: NodeRef,
+
+    /// Parent and index of the element, to find the node it is replaced with after patching
+    parent: NodeRef,
+    index: usize,
+
+    /// Tree with the reparsed element as the only child of the root
+    fragment: Tree,
+
+    /// Start of the element in the new markup
+    start: usize,
+
+    edit: Edit,
+}
+
+impl Reparse {
+    /// Reparse the innermost element of a tree parsed from `old` enclosing the changes in `new`.
+    /// Returns None if the markup is unchanged, or no enclosing element could be reparsed.
+    pub fn new<M: std::fmt::Debug>(tree: &IndexedTree, old: &str, new: &str) -> Option<Self> {
+        let edit = Edit::between(old, new)?;
+
+        let mut chain = Vec::new();
+        enclosing(tree.root(), &edit, &mut chain);
+
+        for target in chain.into_iter().rev() {
+            let span = target.node().data().span().cloned()?;
+            let region = span.start..edit.shift(span.end);
+
+            match SnowcapParser::<M>::parse_fragment(&new[region.clone()]) {
+                Ok(fragment) => return Self::replace(target, fragment, region, edit),
+                Err(e) => debug!("Region {region:?} is not a single element: {e}"),
+            }
+        }
+
+        None
+    }
+
+    /// Reparse a given element of a tree parsed from `old`, such as a named region, which must enclose the changes
+    /// in `new`. Returns None if the markup is unchanged or the element doesn't enclose the changes, and an error if
+    /// the changed element doesn't parse.
+    pub fn element<M: std::fmt::Debug>(
+        target: NodeRef,
+        old: &str,
+        new: &str,
+    ) -> Result<Option<Self>, ParseErrorContext> {
+        let Some(edit) = Edit::between(old, new) else {
+            return Ok(None);
+        };
+
+        let span = match target.node().data().span().cloned() {
+            Some(span) if edit.inside(&span) => span,
+            span => {
+                debug!("Element span {span:?} doesn't enclose the edit {edit:?}");
+                return Ok(None);
+            }
+        };
+
+        let region = span.start..edit.shift(span.end);
+        let fragment = SnowcapParser::<M>::parse_fragment(&new[region.clone()])?;
+
+        Ok(Self::replace(target, fragment, region, edit))
+    }
+
+    /// Replace an element of the live tree with a fragment parsed from a region of the new markup
+    fn replace(target: NodeRef, fragment: Tree, region: Range<usize>, edit: Edit) -> Option<Self> {
+        let parent = target.node().parent().cloned()?;
+        let target_id = target.node().id();
+        let index = parent
+            .node()
+            .children()?
+            .iter()
+            .position(|child| child.node().id() == target_id)?;
+
+        debug!(
+            "Reparsed node {target_id} from bytes {}..{}",
+            region.start, region.end
+        );
+
+        Some(Self {
+            target,
+            parent,
+            index,
+            fragment,
+            start: region.start,
+            edit,
+        })
+    }
+
+    /// Node of the reparsed element in the live tree, which is diffed against [`Self::replacement()`]
+    pub fn target(&self) -> &NodeRef {
+        &self.target
+    }
+
+    /// Root of the reparsed element
+    pub fn replacement(&self) -> NodeRef {
+        self.fragment.root().node().children().unwrap()[0].clone()
+    }
+
+    /// Update the spans of the live tree after the replacement has been patched in
+    pub fn finish(self, tree: &IndexedTree) {
+        shift_spans(tree.root(), &self.edit);
+
+        let patched = self
+            .parent
+            .node()
+            .children()
+            .and_then(|children| children.get(self.index).cloned());
+
+        match patched {
+            Some(patched) => copy_spans(&self.replacement(), &patched, self.start),
+            None => clear_spans(tree.root()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::{TreeDiff, TreeNode as _, TreeNodeRef as _};
+    use tracing_test::traced_test;
+
+    use super::{region_body, Edit, Reparse};
+    use crate::{node::Content, Message, SnowcapParser};
+
+    #[traced_test]
+    #[test]
+    fn edit_between() {
+        let edit = Edit::between(r#"text("B")"#, r#"text("Bee")"#).unwrap();
+        assert_eq!(
+            edit,
+            Edit {
+                start: 7,
+                old_end: 7,
+                new_end: 9
+            }
+        );
+
+        assert!(Edit::between("same", "same").is_none());
+    }
+
+    #[traced_test]
+    #[test]
+    fn reparse_changed_element() {
+        let old = r#"{-[text("A"), text("B")]}"#;
+        let new = r#"{-[text("A"), text("Bee")]}"#;
+
+        let mut tree = SnowcapParser::<Message>::parse_memory(old).unwrap().index();
+
+        let reparse = Reparse::new::<Message>(&tree, old, new).unwrap();
+        assert!(matches!(
+            reparse.target().node().data().content(),
+            Content::Widget(name) if name == "text"
+        ));
+        assert_eq!(reparse.target().node().data().span(), Some(&(14..23)));
+
+        let mut diff = TreeDiff::new(reparse.target().clone(), reparse.replacement());
+        diff.diff().patch_tree(&mut tree);
+        tree.reindex();
+        reparse.finish(&tree);
+
+        // The enclosing elements are extended, and the reparsed element has its span in the new markup
+        let container = tree.root().node().children().unwrap()[0].clone();
+        assert_eq!(container.node().data().span(), Some(&(0..new.len())));
+
+        let row = container.node().children().unwrap()[0].clone();
+        let text = row.node().children().unwrap()[1].clone();
+        let span = text.node().data().span().cloned().unwrap();
+        assert_eq!(&new[span], r#"text("Bee")"#);
+
+        // Adding a sibling reparses the row
+        let newer = r#"{-[text("A"), text("Bee"), text("C")]}"#;
+        let reparse = Reparse::new::<Message>(&tree, new, newer).unwrap();
+        assert!(matches!(
+            reparse.target().node().data().content(),
+            Content::Row
+        ));
+    }
+
+    #[traced_test]
+    #[test]
+    fn find_region_body() {
+        let markup = r#"{-[region#nav { text("}") }, region#sidebar-list { text("A") }, region#sidebar {
+            // closing } in a comment
+            |[text("B"), button(text("C"))]
+        }]}"#;
+
+        let body = region_body(markup, "sidebar").unwrap();
+        assert_eq!(
+            markup[body].trim(),
+            "// closing } in a comment\n            |[text(\"B\"), button(text(\"C\"))]"
+        );
+
+        let body = region_body(markup, "nav").unwrap();
+        assert_eq!(&markup[body], r#" text("}") "#);
+
+        assert!(region_body(markup, "side").is_none());
+    }
+}
@@ -0,0 +1,117 @@
+//! Mapping of the spans of nodes to locations in the markup
+
+use std::ops::Range;
+
+use super::ParserContext;
+
+/// Location of an element in the markup, displayed as `file:line:column`, or `line:column` for markup loaded from
+/// memory. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    /// File the markup was loaded from
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}:")?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl From<&ParserContext> for SourceLocation {
+    fn from(context: &ParserContext) -> Self {
+        let (line, column) = context.location;
+        Self {
+            file: None,
+            line,
+            column,
+        }
+    }
+}
+
+/// Offsets of the lines of the markup a tree was parsed from, to locate the spans of its nodes
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceMap {
+    file: Option<String>,
+    text: String,
+    /// Byte offset of the start of each line
+    lines: Vec<usize>,
+}
+
+impl SourceMap {
+    /// Map the markup a tree was parsed from, loaded from a file or from memory
+    pub fn new(file: Option<String>, text: &str) -> Self {
+        let lines = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Self {
+            file,
+            text: text.to_string(),
+            lines,
+        }
+    }
+
+    /// Get the location of a byte offset in the markup
+    pub fn locate(&self, offset: usize) -> SourceLocation {
+        let offset = offset.min(self.text.len());
+        let line = self.lines.partition_point(|start| *start <= offset) - 1;
+        let start = self.lines[line];
+
+        // Columns count characters rather than bytes
+        let column = self
+            .text
+            .get(start..offset)
+            .map(|text| text.chars().count())
+            .unwrap_or(offset - start);
+
+        SourceLocation {
+            file: self.file.clone(),
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    /// Get the location of the start of a span
+    pub fn locate_span(&self, span: &Range<usize>) -> SourceLocation {
+        self.locate(span.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use tracing_test::traced_test;
+
+    use super::SourceMap;
+    use crate::{Message, SnowcapParser};
+
+    #[traced_test]
+    #[test]
+    fn locate_nodes() {
+        let markup = "{|[\n    text(\"A\"),\n    button#ok(text(\"Ok\"))\n]}";
+        let tree = SnowcapParser::<Message>::parse_memory(markup).unwrap();
+        let map = SourceMap::new(Some("sidebar.iced".into()), markup);
+
+        let container = tree.root().node().children().unwrap()[0].clone();
+        let column = container.node().children().unwrap()[0].clone();
+        let button = column.node().children().unwrap()[1].clone();
+        let span = button.node().data().span().cloned().unwrap();
+
+        let location = map.locate_span(&span);
+        assert_eq!(location.to_string(), "sidebar.iced:3:5");
+
+        assert_eq!(map.locate(0).to_string(), "sidebar.iced:1:1");
+        assert_eq!(
+            SourceMap::new(None, markup)
+                .locate(markup.len())
+                .to_string(),
+            "4:3"
+        );
+    }
+}
@@ -6,6 +6,8 @@ use tracing::debug;
 use crate::{
     module::{
         argument::{ModuleArgument, ModuleArguments},
+        format::FORMAT_MODULE,
+        registry::ModuleRegistry,
         ModuleHandleId,
     },
     parser::value::ValueParser,
@@ -77,6 +79,12 @@ impl std::hash::Hash for Module {
     }
 }
 
+/// Argument of a module, with a value or a nested module
+enum ParsedArgument {
+    Value(ModuleArgument),
+    Module(String, Module),
+}
+
 #[derive(Parser)]
 #[grammar = "parser/module.pest"]
 pub struct ModuleParser;
@@ -89,6 +97,9 @@ impl ModuleParser {
         let mut module = Module::default();
         module.context = Some(context);
 
+        // Modules given as argument values
+        let mut nested = Vec::new();
+
         if let Some(root) = pairs.into_iter().last() {
             for pair in root.into_inner() {
                 match pair.as_rule() {
@@ -99,12 +110,21 @@ impl ModuleParser {
                         Self::parse_arguments(
                             pair,
                             &mut module.args,
+                            &mut nested,
                             module.context.as_ref().unwrap(),
                         )?;
                     }
 
                     // Return the module when the EOI rule is emitted
-                    Rule::EOI => return Ok(module),
+                    Rule::EOI => {
+                        let module = Self::flatten(module, nested)?;
+
+                        // Report invalid arguments with the location of the module in the markup
+                        ModuleRegistry::validate_args(&module.name, &module.args)
+                            .map_err(|e| ParseError::ModuleArguments(module.name.clone(), e))?;
+
+                        return Ok(module);
+                    }
 
                     // Handle unsupported rules
                     _ => {
@@ -124,10 +144,36 @@ impl ModuleParser {
         Err(ParseError::Missing("EOI not emitted"))
     }
 
+    /// Replace a `fmt!` module with the module given as its `value`, which formats its own output with the
+    /// pattern as its `format` argument. Other modules can't have modules as arguments.
+    fn flatten(module: Module, nested: Vec<(String, Module)>) -> Result<Module, ParseError> {
+        let mut nested = nested.into_iter();
+        let Some((name, mut inner)) = nested.next() else {
+            return Ok(module);
+        };
+
+        if module.name != FORMAT_MODULE || name != "value" || nested.next().is_some() {
+            return Err(ParseError::Unhandled(format!(
+                "module as argument '{name}' of module '{}'",
+                module.name
+            )));
+        }
+
+        if let Ok(pattern) = module.args.get("pattern") {
+            inner
+                .args
+                .insert(ModuleArgument::new("format".into(), pattern.clone()));
+        }
+        inner.context = module.context;
+
+        Ok(inner)
+    }
+
+    /// Parse an argument, which is returned as a module if its value is a module
     fn parse_argument(
         pair: Pair<Rule>,
         context: &ParserContext,
-    ) -> Result<ModuleArgument, ParseError> {
+    ) -> Result<ParsedArgument, ParseError> {
         let mut arg = ModuleArgument::default();
 
         for pair in pair.into_inner() {
@@ -138,6 +184,10 @@ impl ModuleParser {
                 Rule::value => {
                     arg.set_value(ValueParser::parse_str(pair.as_str(), context)?);
                 }
+                Rule::nested_module => {
+                    let module = Self::parse_str(pair.as_str(), context.clone())?;
+                    return Ok(ParsedArgument::Module(arg.name().clone(), module));
+                }
                 // Handle unsupported rules
                 _ => {
                     return Err(ParseError::UnsupportedRule(format!(
@@ -150,21 +200,22 @@ impl ModuleParser {
             }
         }
 
-        Ok(arg)
+        Ok(ParsedArgument::Value(arg))
     }
 
     fn parse_arguments(
         pair: Pair<Rule>,
         dest: &mut ModuleArguments,
+        nested: &mut Vec<(String, Module)>,
         context: &ParserContext,
     ) -> Result<(), ParseError> {
         for pair in pair.into_inner() {
             //println!("ARGUMENT PAIR {pair:?}")
             match pair.as_rule() {
-                Rule::argument => {
-                    let argument = Self::parse_argument(pair, context)?;
-                    dest.insert(argument);
-                }
+                Rule::argument => match Self::parse_argument(pair, context)? {
+                    ParsedArgument::Value(argument) => dest.insert(argument),
+                    ParsedArgument::Module(name, module) => nested.push((name, module)),
+                },
                 // Handle unsupported rules
                 _ => {
                     return Err(ParseError::UnsupportedRule(format!(
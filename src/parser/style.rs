@@ -0,0 +1,187 @@
+//! Parser for stylesheets declared in markup before the root container
+//!
+//! ```text
+//! styles {
+//!     #title { size:40, text-color:#ffffff }
+//!     .card, .panel { padding:12, border:radius(8) }
+//! }
+//! ```
+//!
+//! Each rule applies attributes to the element with an id, or to elements with a class in their `class` attribute.
+//! See [`crate::conversion::style`] for how rules are applied.
+
+use pest::Parser;
+use pest_derive::Parser;
+use tracing::debug;
+
+use crate::attribute::Attributes;
+
+use super::{
+    attribute::AttributeParser,
+    error::{ParseError, ParseErrorContext},
+    ParserContext,
+};
+
+#[derive(Parser)]
+#[grammar = "parser/style.pest"]
+pub struct StyleParser;
+
+/// Selects the elements a [`StyleRule`] applies to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum StyleSelector {
+    /// Element with an id, such as `#title`
+    Id(String),
+    /// Elements with a class, such as `.card`
+    Class(String),
+}
+
+/// Attributes applied to the elements matching any of the selectors of the rule
+#[derive(Debug, Clone)]
+pub struct StyleRule {
+    selectors: Vec<StyleSelector>,
+    attrs: Attributes,
+}
+
+impl StyleRule {
+    /// Get the selectors of the rule
+    pub fn selectors(&self) -> &[StyleSelector] {
+        &self.selectors
+    }
+
+    /// Get the attributes applied by the rule
+    pub fn attrs(&self) -> &Attributes {
+        &self.attrs
+    }
+}
+
+/// Rules of a `styles` block, in the order they are declared
+#[derive(Debug, Clone)]
+pub struct Stylesheet {
+    source: String,
+    rules: Vec<StyleRule>,
+}
+
+impl Stylesheet {
+    /// Get the rules of the stylesheet
+    pub fn rules(&self) -> &[StyleRule] {
+        &self.rules
+    }
+
+    /// Get the source of the stylesheet
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+/// Stylesheets are equal if they were parsed from the same source
+impl PartialEq for Stylesheet {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl std::hash::Hash for Stylesheet {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.source.hash(state);
+    }
+}
+
+impl StyleParser {
+    /// Parse a stylesheet from its source, with the source in the context of any error
+    pub fn parse_source(data: &str) -> Result<Stylesheet, ParseErrorContext> {
+        Self::parse_str(data).map_err(|e| {
+            let context = ParserContext {
+                input: data.into(),
+                location: (1, 1),
+            };
+            ParseErrorContext::new(context, e)
+        })
+    }
+
+    pub fn parse_str(data: &str) -> Result<Stylesheet, ParseError> {
+        debug!("Parsing stylesheet {data}");
+        let pairs = StyleParser::parse(Rule::stylesheet, data)?;
+
+        let mut rules = Vec::new();
+
+        for pair in pairs {
+            if pair.as_rule() != Rule::style_rule {
+                continue;
+            }
+
+            let mut selectors = Vec::new();
+            let mut attrs = Attributes::new();
+
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::id_selector => {
+                        selectors.push(StyleSelector::Id(pair.into_inner().as_str().to_string()))
+                    }
+                    Rule::class_selector => {
+                        selectors.push(StyleSelector::Class(pair.into_inner().as_str().to_string()))
+                    }
+                    Rule::attributes => {
+                        let data = pair.as_str().trim();
+                        if !data.is_empty() {
+                            attrs = AttributeParser::parse_attributes(data)?;
+                        }
+                    }
+                    _ => continue,
+                }
+            }
+
+            rules.push(StyleRule { selectors, attrs });
+        }
+
+        Ok(Stylesheet {
+            source: data.to_string(),
+            rules,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{StyleParser, StyleSelector};
+    use crate::attribute::{AttributeKind, AttributeValue};
+
+    #[traced_test]
+    #[test]
+    fn parse_stylesheet() {
+        let stylesheet = StyleParser::parse_str(
+            r#"styles {
+                // Heading
+                #title { size:40, text-color:#ffffff }
+                .card, .panel { padding:12, width:{xs:fill, lg:400} }
+                .empty {}
+            }"#,
+        )
+        .unwrap();
+
+        let rules = stylesheet.rules();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].selectors(), &[StyleSelector::Id("title".into())]);
+        assert_eq!(
+            rules[0].attrs().get(AttributeKind::Size).unwrap(),
+            Some(AttributeValue::Size(40.into()))
+        );
+        assert_eq!(
+            rules[1].selectors(),
+            &[
+                StyleSelector::Class("card".into()),
+                StyleSelector::Class("panel".into())
+            ]
+        );
+        assert!(rules[1].attrs().is_responsive());
+        assert_eq!(rules[2].attrs().len(), 0);
+    }
+
+    #[traced_test]
+    #[test]
+    fn invalid_stylesheet() {
+        assert!(StyleParser::parse_str("styles { title { size:40 } }").is_err());
+        assert!(StyleParser::parse_str("styles { #title { size: } }").is_err());
+    }
+}
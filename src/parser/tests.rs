@@ -33,3 +33,89 @@ fn row() {
 fn col() {
     parse(r#"{col[text("a"), text("b")]}"#);
 }
+
+#[test]
+fn error_boundary() {
+    parse(r#"{error-boundary #guard { col[text("a"), text("b")] } fallback { text("failed") }}"#);
+}
+
+#[test]
+fn stylesheet() {
+    parse(
+        r#"styles { #title { size:40 } .card, .panel { padding:12 } } {|[text#title("a"), text<class:"card">("b")]}"#,
+    );
+}
+
+#[test]
+fn stylesheet_file() {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+
+    use crate::attribute::{AttributeKind, AttributeValue};
+
+    let tree = parse(r#"styles!("theme.iss") {text#title("a")}"#);
+    assert_eq!(
+        tree.root()
+            .node()
+            .data()
+            .attrs
+            .get(AttributeKind::StylesheetFile)
+            .unwrap(),
+        Some(AttributeValue::StylesheetFile("theme.iss".into()))
+    );
+}
+
+#[test]
+fn inline_assets() {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+
+    use crate::{node::Content, IndexedTree, ParserOptions};
+
+    let dir = std::env::temp_dir().join("snowcap-inline-assets");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("icon.svg"), "<svg/>").unwrap();
+
+    let options = ParserOptions {
+        asset_dir: Some(dir),
+        ..Default::default()
+    };
+    let tree = IndexedTree::from_tree(
+        SnowcapParser::<M>::parse_memory_with(
+            r#"{|[image(data:image/png;base64,aGVsbG8=), svg(embed!("icon.svg"))]}"#,
+            &options,
+        )
+        .unwrap(),
+    );
+
+    let mut bytes: Vec<Vec<u8>> = tree
+        .leaf_iter()
+        .filter_map(|leaf| match leaf.node().data().content() {
+            Content::Value(value) => Some(value.bytes().unwrap().to_vec()),
+            _ => None,
+        })
+        .collect();
+    bytes.sort();
+    assert_eq!(bytes, vec![b"<svg/>".to_vec(), b"hello".to_vec()]);
+
+    // Files which can't be read are parse errors
+    assert!(SnowcapParser::<M>::parse_memory(r#"{image(embed!("missing-icon.png"))}"#).is_err());
+}
+
+#[test]
+fn span_error_location() {
+    use pest::error::{Error, ErrorVariant};
+
+    use super::Rule;
+
+    let data = "{text(\"a\")}\n{oops}";
+    let span = pest::Span::new(data, 13, 17).unwrap();
+    let error = Error::new_from_span(
+        ErrorVariant::<Rule>::CustomError {
+            message: "unexpected element".into(),
+        },
+        span,
+    );
+
+    let context = SnowcapParser::<M>::error_context(data, error);
+    assert_eq!(context.location().line, 2);
+    assert_eq!(context.location().column, 2);
+}
@@ -42,3 +42,79 @@ fn module_value() {
 fn module_reserved_argument() {
     parse(r#"{text<size:sub!{_topic:"test"}>("hello")}"#);
 }
+
+/// Test parsing modules with namespaced names, as content and as attribute values
+#[test]
+fn module_namespaced() {
+    parse(r#"{text<size:mycompany.size!{}>(mycompany.weather!{city:"Vancouver"})}"#);
+}
+
+/// Test that a module given as the value of fmt! replaces it, formatting its output with the pattern
+#[test]
+fn module_nested_format() {
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+
+    use crate::{node::Content, IndexedTree};
+
+    let tree = IndexedTree::from_tree(parse(
+        r#"{text(fmt!{value: http!{url:"http://example.com/size"}, pattern:"{:.2} MB"})}"#,
+    ));
+    let module = tree
+        .leaf_iter()
+        .find_map(|node| match node.node().data().content() {
+            Content::Module(module) => Some(module.clone()),
+            _ => None,
+        })
+        .unwrap();
+
+    assert_eq!(module.name(), "http");
+    assert_eq!(module.args().get("format").unwrap().to_string(), "{:.2} MB");
+}
+
+/// Test that only fmt! accepts a module as an argument
+#[test]
+#[should_panic]
+fn module_nested_rejected() {
+    parse(r#"{text(x!{value: http!{url:"http://example.com"}})}"#);
+}
+
+/// Test that duration, size and URL literals in module arguments are typed by the parser
+#[test]
+fn module_literal_arguments() {
+    use crate::parser::{module::ModuleParser, ParserContext};
+
+    let module = ModuleParser::parse_str(
+        r#"x!{url: https://example.com/feed, interval: 1m30s, limit: 2MB}"#,
+        ParserContext::default(),
+    )
+    .unwrap();
+
+    let args = module.args();
+    assert_eq!(
+        args.get("url").unwrap().url().unwrap().host_str(),
+        Some("example.com")
+    );
+    assert_eq!(
+        args.get("interval").unwrap().duration().unwrap(),
+        std::time::Duration::from_secs(90)
+    );
+    assert_eq!(args.get("limit").unwrap().size().unwrap(), 2_000_000);
+}
+
+/// Test that map literals in module arguments, including nested maps, are parsed as maps
+#[test]
+fn module_map_argument() {
+    use crate::parser::{module::ModuleParser, ParserContext};
+
+    let module = ModuleParser::parse_str(
+        r#"x!{headers: {Accept: "text/plain", "X-Retry": {count: 2}}, url: "http://example.com"}"#,
+        ParserContext::default(),
+    )
+    .unwrap();
+
+    let headers = module.args().get("headers").unwrap();
+    assert_eq!(headers.map().unwrap().len(), 2);
+    assert_eq!(headers.field("Accept").unwrap().to_string(), "text/plain");
+    let retry = headers.field("X-Retry").unwrap();
+    assert_eq!(retry.field("count").unwrap().integer().unwrap(), 2);
+}
@@ -0,0 +1,191 @@
+//! Parser for custom theme definitions declared in markup before the root container
+//!
+//! ```text
+//! theme "ocean" {
+//!     palette { base:dark, background:#0b1d2a, primary:#336699, text:#e0e6eb },
+//!     text { size:14 }
+//! }
+//! ```
+//!
+//! The `palette` section starts from the palette of the `base` theme (light if not specified),
+//! and overrides each of the `background`, `text`, `primary`, `success` and `danger` colors.
+//!
+//! The `text` section sets the default text size, which has no effect unless the application passes
+//! [`crate::Snowcap::text_size()`] to the `default_text_size` of its settings.
+
+use iced::{theme::Palette, Pixels, Theme};
+use pest::Parser;
+use pest_derive::Parser;
+use tracing::debug;
+
+use crate::conversion::theme::SnowcapTheme;
+
+use super::{color::ColorParser, ParseError};
+
+/// Name of custom themes declared without a name
+const DEFAULT_NAME: &str = "Custom";
+
+#[derive(Parser)]
+#[grammar = "parser/theme.pest"]
+pub struct ThemeParser;
+
+/// A custom theme declared in markup
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeDefinition {
+    name: String,
+    palette: Palette,
+    text_size: Option<Pixels>,
+}
+
+impl ThemeDefinition {
+    /// Get the name of the theme
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the palette of the theme
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// Get the default text size declared in the `text` section
+    pub fn text_size(&self) -> Option<Pixels> {
+        self.text_size
+    }
+
+    /// Create an [`iced::theme::Custom`] theme from the definition
+    pub fn theme(&self) -> Theme {
+        Theme::custom(self.name.clone(), self.palette)
+    }
+}
+
+impl ThemeParser {
+    pub fn parse_str(data: &str) -> Result<ThemeDefinition, ParseError> {
+        debug!("Parsing theme definition {data}");
+        let pairs = ThemeParser::parse(Rule::theme, data)?;
+
+        let mut name = DEFAULT_NAME.to_string();
+        let mut palette = Palette::LIGHT;
+        let mut text_size = None;
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::string => name = pair.into_inner().as_str().to_string(),
+                Rule::section => {
+                    let mut inner = pair.into_inner();
+                    let section = inner.next().unwrap().as_str();
+
+                    let properties: Vec<(&str, &str)> = inner
+                        .map(|property| {
+                            let mut inner = property.into_inner();
+                            (
+                                inner.next().unwrap().as_str(),
+                                inner.next().unwrap().as_str(),
+                            )
+                        })
+                        .collect();
+
+                    match section {
+                        "palette" => {
+                            // Apply the base palette first, so the order of the properties doesn't matter
+                            if let Some((_, base)) = properties.iter().find(|(k, _)| *k == "base") {
+                                palette = SnowcapTheme::try_from(*base)
+                                    .map_err(|e| ParseError::Unhandled(e.to_string()))?
+                                    .theme()
+                                    .palette();
+                            }
+
+                            for (key, value) in properties {
+                                let color = || ColorParser::parse_str(value);
+                                match key {
+                                    "base" => {}
+                                    "background" => palette.background = color()?,
+                                    "text" => palette.text = color()?,
+                                    "primary" => palette.primary = color()?,
+                                    "success" => palette.success = color()?,
+                                    "danger" => palette.danger = color()?,
+                                    _ => {
+                                        return Err(ParseError::Unhandled(format!(
+                                            "theme palette property {key}"
+                                        )))
+                                    }
+                                }
+                            }
+                        }
+                        "text" => {
+                            for (key, value) in properties {
+                                match key {
+                                    "size" => {
+                                        text_size =
+                                            Some(Pixels(value.parse().map_err(ParseError::Float)?))
+                                    }
+                                    _ => {
+                                        return Err(ParseError::Unhandled(format!(
+                                            "theme text property {key}"
+                                        )))
+                                    }
+                                }
+                            }
+                        }
+                        _ => return Err(ParseError::Unhandled(format!("theme section {section}"))),
+                    }
+                }
+                _ => continue,
+            }
+        }
+
+        Ok(ThemeDefinition {
+            name,
+            palette,
+            text_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::{theme::Palette, Color, Pixels};
+    use tracing_test::traced_test;
+
+    use super::ThemeParser;
+    use crate::parser::color::ColorParser;
+
+    #[traced_test]
+    #[test]
+    fn parse_theme() {
+        let theme = ThemeParser::parse_str(
+            r#"theme "ocean" {
+                palette { base:dark, primary:#336699 },
+                text { size:14 }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(theme.name(), "ocean");
+        assert_eq!(theme.palette().primary, Color::from_rgb8(0x33, 0x66, 0x99));
+        assert_eq!(theme.palette().background, Palette::DARK.background);
+        assert_eq!(theme.text_size(), Some(Pixels(14.0)));
+    }
+
+    #[traced_test]
+    #[test]
+    fn color_functions() {
+        let theme = ThemeParser::parse_str(
+            "theme { palette { primary: hsl(210, 50%, 40%), success:rgb(0, 128, 0), danger:#cc0000 } }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            theme.palette().primary,
+            ColorParser::parse_str("hsl(210, 50%, 40%)").unwrap()
+        );
+        assert_eq!(theme.palette().success, Color::from_rgb8(0, 128, 0));
+        assert_eq!(theme.palette().danger, Color::from_rgb8(0xcc, 0, 0));
+    }
+
+    #[traced_test]
+    #[test]
+    fn unknown_property() {
+        assert!(ThemeParser::parse_str("theme { palette { accent:#fff } }").is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! Strict validation of parsed markup
+//!
+//! With [`ParserOptions::strict`], the tree is validated after parsing, and markup which would only produce
+//! warnings or error placeholders when widgets are built is a parse error, located at the offending element:
+//!
+//! * Widget names which aren't built in, and have no factory in [`ParserOptions::widgets`], which the engine sets
+//!   to the registry of [`crate::Snowcap::widgets()`]
+//! * Attributes which only apply to other widgets, such as `on-press` on a `text`
+//! * Custom attributes on built in widgets, which no widget would read
+
+use std::path::PathBuf;
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use tracing::warn;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    conversion::{registry::WidgetRegistry, widget::BUILTIN_WIDGETS},
+    module::policy::ModulePolicy,
+    node::Content,
+    NodeRef,
+};
+
+use super::{
+    error::{ParseError, ParseErrorContext},
+    location::SourceMap,
+    ParserContext,
+};
+
+/// Options of the markup parser
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Reject unknown widgets and attributes which have no effect, rather than warning when widgets are built.
+    /// Useful in CI to enforce the correctness of markup.
+    pub strict: bool,
+
+    /// Directory which relative paths of files inlined with `embed!()` are resolved from. Defaults to the working
+    /// directory, or the directory of the markup file when it's loaded from a file.
+    pub asset_dir: Option<PathBuf>,
+
+    /// Policy whose file roots restrict the files inlined with `embed!()`. The engine parses markup with its own
+    /// [`ModulePolicy`].
+    pub policy: ModulePolicy,
+
+    /// Custom widgets accepted by a strict parser. The engine parses markup with its own registry.
+    pub widgets: WidgetRegistry,
+}
+
+impl ParserOptions {
+    /// Options of a strict parser
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Elements accepting attributes which only apply to some elements. Attributes not listed apply to any element.
+const WIDGET_ATTRIBUTES: &[(AttributeKind, &[&str])] = &[
+    (AttributeKind::OnPress, &["button"]),
+    (AttributeKind::OnToggle, &["toggler"]),
+    (AttributeKind::Toggled, &["toggler"]),
+    (AttributeKind::ActiveColor, &["toggler"]),
+    (AttributeKind::HandleColor, &["toggler"]),
+    (AttributeKind::OnSelect, &["pick-list", "multi-select"]),
+    (AttributeKind::Selected, &["pick-list", "multi-select"]),
+    (AttributeKind::SelectedList, &["pick-list", "multi-select"]),
+    (
+        AttributeKind::OnChange,
+        &[
+            "slider",
+            "vertical-slider",
+            "text-input",
+            "number-input",
+            "password-input",
+        ],
+    ),
+    (
+        AttributeKind::OnSubmit,
+        &["text-input", "number-input", "password-input"],
+    ),
+    (
+        AttributeKind::Placeholder,
+        &["text-input", "number-input", "password-input"],
+    ),
+    (
+        AttributeKind::Secure,
+        &["text-input", "number-input", "password-input"],
+    ),
+    (
+        AttributeKind::Min,
+        &["text-input", "number-input", "password-input"],
+    ),
+    (
+        AttributeKind::Max,
+        &["text-input", "number-input", "password-input"],
+    ),
+    (AttributeKind::Wheel, &["slider", "vertical-slider"]),
+    (
+        AttributeKind::Spin,
+        &["slider", "vertical-slider", "number-input"],
+    ),
+    (
+        AttributeKind::Step,
+        &["slider", "vertical-slider", "number-input"],
+    ),
+    (AttributeKind::CellSize, &["qr-code"]),
+    (AttributeKind::HeadingSize, &["markdown"]),
+    (AttributeKind::CodeSize, &["markdown"]),
+    (AttributeKind::CodeColor, &["markdown"]),
+    (AttributeKind::CodeBackground, &["markdown"]),
+    (AttributeKind::LinkColor, &["markdown"]),
+    (AttributeKind::OnLink, &["markdown"]),
+    (AttributeKind::Language, &["code"]),
+    (AttributeKind::Wrap, &["code"]),
+    (AttributeKind::LineNumbers, &["code"]),
+    (
+        AttributeKind::Thickness,
+        &["rule-horizontal", "rule-vertical"],
+    ),
+    (
+        AttributeKind::FillMode,
+        &["rule-horizontal", "rule-vertical"],
+    ),
+    (
+        AttributeKind::RuleColor,
+        &["rule-horizontal", "rule-vertical"],
+    ),
+    (
+        AttributeKind::RulePalette,
+        &["rule-horizontal", "rule-vertical"],
+    ),
+    (AttributeKind::Animated, &["image"]),
+    (AttributeKind::ScrollDirection, &["scrollable"]),
+    (AttributeKind::ItemHeight, &["lazy-column"]),
+    (AttributeKind::BackgroundImage, &["container"]),
+    (AttributeKind::ContentFit, &["container"]),
+    (AttributeKind::Dim, &["container"]),
+];
+
+/// Name of the element of a node, as it is matched by [`WIDGET_ATTRIBUTES`]
+fn element_name(content: &Content) -> Option<&str> {
+    match content {
+        Content::Widget(name) => Some(name.as_str()),
+        Content::Container => Some("container"),
+        Content::Row => Some("row"),
+        Content::Column => Some("column"),
+        Content::Stack => Some("stack"),
+        Content::LazyColumn => Some("lazy-column"),
+        _ => None,
+    }
+}
+
+/// Check a node for unknown widgets and attributes which have no effect
+fn check(
+    content: &Content,
+    attrs: &crate::Attributes,
+    widgets: &WidgetRegistry,
+) -> Option<ParseError> {
+    let name = element_name(content)?;
+    let builtin = !matches!(content, Content::Widget(_)) || BUILTIN_WIDGETS.contains(&name);
+
+    if !builtin && !widgets.contains(name) {
+        return Some(ParseError::UnknownWidget(name.to_string()));
+    }
+
+    for attr in attrs {
+        let kind = attr.kind();
+
+        // Attributes of custom widgets are read by their factories
+        if kind == AttributeKind::Custom && builtin {
+            if let Some(AttributeValue::Custom(custom)) = attr.value() {
+                if let Some(attribute) = custom.keys().next() {
+                    return Some(ParseError::UnsupportedAttribute {
+                        element: name.to_string(),
+                        attribute: attribute.clone(),
+                    });
+                }
+            }
+        }
+
+        let supported = WIDGET_ATTRIBUTES
+            .iter()
+            .find(|(widget_kind, _)| *widget_kind == kind)
+            .map(|(_, elements)| elements.contains(&name) || !builtin)
+            .unwrap_or(true);
+
+        if !supported {
+            return Some(ParseError::UnsupportedAttribute {
+                element: name.to_string(),
+                attribute: format!("{kind:?}"),
+            });
+        }
+    }
+
+    None
+}
+
+/// Validate the nodes of a tree parsed from markup, returning an error located at the first invalid element.
+/// Every invalid element is logged. Widgets which aren't built in must have a factory in `widgets`.
+pub(crate) fn validate(
+    root: &NodeRef,
+    data: &str,
+    widgets: &WidgetRegistry,
+) -> Result<(), ParseErrorContext> {
+    let map = SourceMap::new(None, data);
+    let mut first = None;
+    let mut stack = vec![root.clone()];
+
+    while let Some(noderef) = stack.pop() {
+        let node = noderef.node();
+        let error = check(node.data().content(), &node.data().attrs, widgets);
+
+        if let Some(error) = error {
+            let location = node
+                .data()
+                .span()
+                .map(|span| map.locate_span(span))
+                .map(|location| (location.line, location.column))
+                .unwrap_or((1, 1));
+
+            warn!(
+                "Strict markup error at {}:{}: {error}",
+                location.0, location.1
+            );
+
+            if first.is_none() {
+                first = Some((location, error));
+            }
+        }
+
+        // Visit the children in markup order
+        if let Some(children) = node.children() {
+            stack.extend(children.iter().rev().cloned());
+        }
+    }
+
+    match first {
+        Some((location, error)) => Err(ParseErrorContext::new(
+            ParserContext {
+                input: data.to_string(),
+                location,
+            },
+            error,
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use iced::widget::Text;
+
+    use super::ParserOptions;
+    use crate::{dynamic_widget::DynamicWidget, Message, SnowcapParser};
+
+    #[traced_test]
+    #[test]
+    fn strict_markup() {
+        let parse =
+            |markup| SnowcapParser::<Message>::parse_memory_with(markup, &ParserOptions::strict());
+
+        assert!(parse(r#"{|[text("A"), button<on-press:publish("go")>(text("Go"))]}"#).is_ok());
+
+        // Unknown widgets and misplaced attributes are only rejected in strict mode
+        for markup in [
+            r#"{|[text("A"), gadget("B")]}"#,
+            r#"{|[text<on-press:publish("go")>("A")]}"#,
+            r#"{|[text<strict-test-sweep:270>("A")]}"#,
+        ] {
+            assert!(SnowcapParser::<Message>::parse_memory(markup).is_ok());
+
+            let error = parse(markup).unwrap_err();
+            assert_eq!(error.location().line, 1);
+        }
+
+        // Custom widgets are accepted once a factory is registered in the options
+        let options = ParserOptions::strict();
+        options
+            .widgets
+            .register("gadget", |_node_id, _attrs, _content| {
+                Ok(DynamicWidget::from(Text::new("gadget")))
+            });
+        assert!(
+            SnowcapParser::<Message>::parse_memory_with(r#"{|[gadget("B")]}"#, &options).is_ok()
+        );
+    }
+}
@@ -1,12 +1,23 @@
-use crate::{attribute::AttributeKind, ConversionError};
+use crate::{attribute::AttributeKind, context, module::policy::ModulePolicy, ConversionError};
 
 use super::{error::ParseError, ParserContext};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use iced::widget::text::IntoFragment;
 use pest::{iterators::Pair, Parser as _};
 use pest_derive::Parser;
-use std::{borrow::Borrow, fmt::Write, ops::Deref};
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::Write,
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 use strum::EnumDiscriminants;
 use tracing::debug;
+use url::Url;
 
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub struct Value {
@@ -57,6 +68,52 @@ impl Value {
         }
     }
 
+    /// Create a map of keys to values
+    pub fn new_map(val: BTreeMap<String, Self>) -> Self {
+        Self {
+            inner: ValueData::Map(val),
+            context: None,
+        }
+    }
+
+    /// Create a value shown with a label
+    pub fn new_labelled(label: String, val: Self) -> Self {
+        Self {
+            inner: ValueData::Labelled(label, Box::new(val)),
+            context: None,
+        }
+    }
+
+    pub fn new_duration(val: Duration) -> Self {
+        Self {
+            inner: ValueData::Duration(val),
+            context: None,
+        }
+    }
+
+    /// Create a data size value, in bytes
+    pub fn new_size(val: u64) -> Self {
+        Self {
+            inner: ValueData::Size(val),
+            context: None,
+        }
+    }
+
+    pub fn new_url(val: Url) -> Self {
+        Self {
+            inner: ValueData::Url(val),
+            context: None,
+        }
+    }
+
+    /// Create a binary value, such as an asset inlined in the markup
+    pub fn new_bytes(val: Vec<u8>) -> Self {
+        Self {
+            inner: ValueData::Bytes(Arc::new(val)),
+            context: None,
+        }
+    }
+
     pub fn new_attribute_kind(val: AttributeKind) -> Self {
         Self {
             inner: ValueData::AttributeKind(val),
@@ -126,6 +183,80 @@ impl Value {
             ))
         }
     }
+
+    /// Get the entries of a map value
+    pub fn map(&self) -> Result<&BTreeMap<String, Value>, ConversionError> {
+        if let ValueData::Map(map) = self.inner() {
+            Ok(map)
+        } else {
+            Err(ConversionError::InvalidType(
+                "expecting ValueKind::Map".into(),
+            ))
+        }
+    }
+
+    /// Get the value of a key of a map value, such as a field of a record
+    pub fn field(&self, key: &str) -> Option<&Value> {
+        match self.inner() {
+            ValueData::Map(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// Get a [`Duration`] from this Value. Inner ValueData kind must be Duration
+    pub fn duration(&self) -> Result<Duration, ConversionError> {
+        match self.inner() {
+            ValueData::Duration(duration) => Ok(*duration),
+            _ => Err(ConversionError::InvalidType(format!(
+                "expecting ValueKind::Duration. Got {:?}",
+                self.inner()
+            ))),
+        }
+    }
+
+    /// Get a data size in bytes from this Value. Inner ValueData kind must be Size
+    pub fn size(&self) -> Result<u64, ConversionError> {
+        match self.inner() {
+            ValueData::Size(size) => Ok(*size),
+            _ => Err(ConversionError::InvalidType(format!(
+                "expecting ValueKind::Size. Got {:?}",
+                self.inner()
+            ))),
+        }
+    }
+
+    /// Get a [`Url`] from this Value. Inner ValueData kind must be Url
+    pub fn url(&self) -> Result<&Url, ConversionError> {
+        if let ValueData::Url(url) = self.inner() {
+            Ok(url)
+        } else {
+            Err(ConversionError::InvalidType(
+                "expecting ValueKind::Url".into(),
+            ))
+        }
+    }
+
+    /// Get the bytes of a binary value
+    pub fn bytes(&self) -> Result<&Arc<Vec<u8>>, ConversionError> {
+        if let ValueData::Bytes(bytes) = self.inner() {
+            Ok(bytes)
+        } else {
+            Err(ConversionError::InvalidType(
+                "expecting ValueKind::Bytes".into(),
+            ))
+        }
+    }
+
+    /// Get the label and the value of a labelled value
+    pub fn labelled(&self) -> Result<(&str, &Value), ConversionError> {
+        if let ValueData::Labelled(label, value) = self.inner() {
+            Ok((label, value))
+        } else {
+            Err(ConversionError::InvalidType(
+                "expecting ValueKind::Labelled".into(),
+            ))
+        }
+    }
 }
 
 impl Deref for Value {
@@ -152,7 +283,17 @@ pub enum ValueData {
     Float(f64),
     Integer(u64),
     Boolean(bool),
+    Duration(Duration),
+    /// A data size in bytes
+    Size(u64),
+    Url(Url),
+    /// Binary data, given as base64 or embedded from a file
+    Bytes(Arc<Vec<u8>>),
     Array(Vec<Value>),
+    /// A map of keys to values, ordered by key
+    Map(BTreeMap<String, Value>),
+    /// A value shown with a label
+    Labelled(String, Box<Value>),
     AttributeKind(AttributeKind),
 }
 
@@ -172,7 +313,15 @@ impl PartialEq for ValueData {
             }
             (Self::Integer(a), Self::Integer(b)) => a == b,
             (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::Duration(a), Self::Duration(b)) => a == b,
+            (Self::Size(a), Self::Size(b)) => a == b,
+            (Self::Url(a), Self::Url(b)) => a == b,
+            (Self::Bytes(a), Self::Bytes(b)) => a == b,
             (Self::Array(a), Self::Array(b)) => a == b,
+            (Self::Map(a), Self::Map(b)) => a == b,
+            (Self::Labelled(a, a_value), Self::Labelled(b, b_value)) => {
+                a == b && a_value == b_value
+            }
             _ => false,
         }
     }
@@ -185,6 +334,10 @@ impl std::fmt::Display for ValueData {
             ValueData::Float(num) => f.write_fmt(format_args!("{}f64", num)),
             ValueData::Integer(num) => f.write_fmt(format_args!("{}u64", num)),
             ValueData::Boolean(b) => f.write_fmt(format_args!("{}", b)),
+            ValueData::Duration(duration) => f.write_fmt(format_args!("{:?}", duration)),
+            ValueData::Size(size) => f.write_fmt(format_args!("{}B", size)),
+            ValueData::Url(url) => f.write_str(url.as_str()),
+            ValueData::Bytes(bytes) => f.write_fmt(format_args!("<{} bytes>", bytes.len())),
             ValueData::Array(vec) => {
                 f.write_char('[')?;
                 let mut iter = vec.iter().peekable();
@@ -196,6 +349,18 @@ impl std::fmt::Display for ValueData {
                 }
                 f.write_char(']')
             }
+            ValueData::Map(map) => {
+                f.write_char('{')?;
+                let mut iter = map.iter().peekable();
+                while let Some((key, val)) = iter.next() {
+                    write!(f, "{}: {}", key, val)?;
+                    if iter.peek().is_some() {
+                        write!(f, ", ")?;
+                    }
+                }
+                f.write_char('}')
+            }
+            ValueData::Labelled(label, _value) => f.write_str(label),
             ValueData::AttributeKind(kind) => f.write_fmt(format_args!("{:?}", kind)),
             ValueData::None => write!(f, "None"),
         }
@@ -255,13 +420,71 @@ impl<'a> Into<std::borrow::Cow<'a, str>> for &ValueData {
             ValueData::Float(n) => format!("{n}").into(),
             ValueData::Integer(n) => format!("{n}").into(),
             ValueData::Boolean(b) => format!("{b}").into(),
+            ValueData::Duration(duration) => format!("{duration:?}").into(),
+            ValueData::Size(size) => format!("{size}B").into(),
+            ValueData::Url(url) => url.to_string().into(),
+            ValueData::Bytes(bytes) => format!("<{} bytes>", bytes.len()).into(),
             ValueData::Array(_value) => todo!(),
+            ValueData::Map(_map) => self.to_string().into(),
+            ValueData::Labelled(label, _value) => label.clone().into(),
             ValueData::AttributeKind(_kind) => todo!(),
             ValueData::None => format!("None").into(),
         }
     }
 }
 
+/// Largest file which can be embedded in the markup with `embed!()`. Larger assets should be loaded by a module.
+pub const MAX_EMBED_SIZE: u64 = 1 << 20;
+
+thread_local! {
+    /// Directory which relative paths of embedded files are resolved from, while markup is parsed
+    static ASSET_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+
+    /// Policy whose file roots embedded files must be inside, while markup is parsed
+    static ASSET_POLICY: RefCell<Option<ModulePolicy>> = const { RefCell::new(None) };
+}
+
+/// Run a parser with relative paths of embedded files resolved from a directory, rather than the working directory,
+/// and embedded files restricted to the file roots of a policy
+pub(crate) fn with_asset_dir<R>(
+    dir: Option<&Path>,
+    policy: &ModulePolicy,
+    parse: impl FnOnce() -> R,
+) -> R {
+    let previous_dir = ASSET_DIR.replace(dir.map(Path::to_path_buf));
+    let previous_policy = ASSET_POLICY.replace(Some(policy.clone()));
+    let result = parse();
+    ASSET_DIR.set(previous_dir);
+    ASSET_POLICY.set(previous_policy);
+    result
+}
+
+/// Read a file to embed in the markup
+fn embed(path: &Path) -> Result<Vec<u8>, ParseError> {
+    let path = match ASSET_DIR.with_borrow(|dir| dir.clone()) {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    };
+    let error = |reason: String| ParseError::Embed(path.display().to_string(), reason);
+
+    ASSET_POLICY.with_borrow(|policy| match policy {
+        Some(policy) => policy.check_path(&path).map_err(|e| error(e.to_string())),
+        None => Ok(()),
+    })?;
+
+    let size = std::fs::metadata(&path)
+        .map_err(|e| error(e.to_string()))?
+        .len();
+    if size > MAX_EMBED_SIZE {
+        return Err(error(format!(
+            "{size} bytes is larger than the limit of {MAX_EMBED_SIZE} bytes"
+        )));
+    }
+
+    debug!("Embedding {size} bytes from {}", path.display());
+    std::fs::read(&path).map_err(|e| error(e.to_string()))
+}
+
 #[derive(Parser)]
 #[grammar = "parser/value.pest"]
 pub struct ValueParser;
@@ -272,7 +495,9 @@ impl ValueParser {
 
         for pair in pair.into_inner() {
             value = match pair.as_rule() {
-                Rule::string => Value::new_string(pair.into_inner().as_str().into()),
+                Rule::string => {
+                    Value::new_string(context::expand_string(pair.into_inner().as_str()))
+                }
                 Rule::float => Value::new_float(pair.as_str().parse().map_err(ParseError::Float)?),
                 Rule::integer => {
                     Value::new_integer(pair.as_str().parse().map_err(ParseError::Integer)?)
@@ -281,6 +506,20 @@ impl ValueParser {
                     Value::new_bool(pair.as_str().parse().map_err(ParseError::Boolean)?)
                 }
                 Rule::none => Value::default(),
+                Rule::duration => Value::new_duration(Self::parse_duration(pair.as_str())?),
+                Rule::size => Value::new_size(Self::parse_size(pair.as_str())?),
+                Rule::url => Value::new_url(Url::parse(pair.as_str())?),
+                Rule::bytes => Value::new_bytes(Self::parse_bytes(pair.as_str())?),
+                Rule::embed => {
+                    let path = pair
+                        .into_inner()
+                        .next()
+                        .ok_or(ParseError::Missing("embed path"))?
+                        .into_inner()
+                        .as_str()
+                        .to_string();
+                    Value::new_bytes(embed(Path::new(&path))?)
+                }
 
                 Rule::array => {
                     let mut values = Vec::new();
@@ -290,6 +529,36 @@ impl ValueParser {
                     Value::new_array(values)
                 }
 
+                Rule::map => {
+                    let mut map = BTreeMap::new();
+                    for entry in pair.into_inner() {
+                        let mut inner = entry.into_inner();
+                        let key = inner.next().ok_or(ParseError::Missing("map key"))?;
+                        let key = match key.as_rule() {
+                            Rule::string => key.into_inner().as_str().to_string(),
+                            _ => key.as_str().to_string(),
+                        };
+                        let value = Self::parse_value(
+                            inner.next().ok_or(ParseError::Missing("map value"))?,
+                        )?;
+                        map.insert(key, value);
+                    }
+                    Value::new_map(map)
+                }
+
+                Rule::labelled => {
+                    let mut inner = pair.into_inner();
+                    let label = inner
+                        .next()
+                        .ok_or(ParseError::Missing("label"))?
+                        .into_inner()
+                        .as_str()
+                        .to_string();
+                    let value =
+                        Self::parse_value(inner.next().ok_or(ParseError::Missing("value"))?)?;
+                    Value::new_labelled(label, value)
+                }
+
                 // Return the module when the EOI rule is emitted
 
                 // Handle unsupported rules
@@ -307,6 +576,70 @@ impl ValueParser {
         Ok(value)
     }
 
+    /// Parse a duration literal, which is a sequence of numbers with units such as `1m30s`
+    fn parse_duration(text: &str) -> Result<Duration, ParseError> {
+        let invalid = || ParseError::Unhandled(format!("duration {text}"));
+        let mut duration = Duration::ZERO;
+        let mut rest = text;
+
+        while !rest.is_empty() {
+            let number_end = rest
+                .find(|c: char| c.is_ascii_alphabetic())
+                .ok_or_else(invalid)?;
+            let unit_end = rest[number_end..]
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .map_or(rest.len(), |end| number_end + end);
+
+            let number: f64 = rest[..number_end].parse().map_err(ParseError::Float)?;
+            let seconds = match &rest[number_end..unit_end] {
+                "ns" => 1e-9,
+                "us" => 1e-6,
+                "ms" => 1e-3,
+                "s" => 1.0,
+                "m" => 60.0,
+                "h" => 3600.0,
+                "d" => 86400.0,
+                _ => return Err(invalid()),
+            };
+
+            let part = Duration::try_from_secs_f64(number * seconds).map_err(|_| invalid())?;
+            duration = duration.checked_add(part).ok_or_else(invalid)?;
+            rest = &rest[unit_end..];
+        }
+
+        Ok(duration)
+    }
+
+    /// Decode the base64 of a `data:` literal, after the media type if one is given
+    fn parse_bytes(text: &str) -> Result<Vec<u8>, ParseError> {
+        let data = text.trim_start_matches("data:");
+        let data = data.split_once(";base64,").map_or(data, |(_, data)| data);
+        Ok(BASE64.decode(data)?)
+    }
+
+    /// Parse a data size literal to a number of bytes. `KB` is 1000 bytes, and `KiB` is 1024 bytes.
+    fn parse_size(text: &str) -> Result<u64, ParseError> {
+        let unit_start = text
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(text.len());
+
+        let number: f64 = text[..unit_start].parse().map_err(ParseError::Float)?;
+        let multiple = match text[unit_start..].to_ascii_lowercase().as_str() {
+            "b" => 1u64,
+            "kb" => 1000,
+            "mb" => 1000u64.pow(2),
+            "gb" => 1000u64.pow(3),
+            "tb" => 1000u64.pow(4),
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            "tib" => 1 << 40,
+            _ => return Err(ParseError::Unhandled(format!("size {text}"))),
+        };
+
+        Ok((number * multiple as f64).round() as u64)
+    }
+
     pub fn parse_str(data: &str, context: &ParserContext) -> Result<Value, ParseError> {
         debug!("Parsing value {data}");
 
@@ -339,7 +672,10 @@ impl ValueParser {
 
 #[cfg(test)]
 mod tests {
-    use super::ValueParser;
+    use std::time::Duration;
+
+    use super::{with_asset_dir, ValueParser};
+    use crate::module::policy::ModulePolicy;
     use crate::parser::{value::ValueDataKind, ParserContext};
     use approx::abs_diff_eq;
 
@@ -453,4 +789,160 @@ mod tests {
         assert_eq!(array[1].integer().unwrap(), 2);
         assert_eq!(array[2].integer().unwrap(), 3);
     }
+
+    #[test]
+    fn labelled() {
+        let value = ValueParser::parse_str(
+            r#"[{label:"One", value:1}, {label: "Half", value: 0.5}, "three"]"#,
+            &ParserContext::default(),
+        )
+        .unwrap();
+        let array = value.array().unwrap();
+        assert!(array[0].is_kind(ValueDataKind::Labelled));
+
+        let (label, one) = array[0].labelled().unwrap();
+        assert_eq!(label, "One");
+        assert_eq!(one.integer().unwrap(), 1);
+        assert_eq!(array[1].to_string(), "Half");
+        assert!(array[2].labelled().is_err());
+
+        // Only scalars can be labelled, other values make a map
+        let value =
+            ValueParser::parse_str(r#"{label:"List", value:[1]}"#, &ParserContext::default())
+                .unwrap();
+        assert!(value.is_kind(ValueDataKind::Map));
+        assert!(value.labelled().is_err());
+    }
+
+    #[test]
+    fn duration() {
+        let value = ValueParser::parse_str("250ms", &ParserContext::default()).unwrap();
+        assert!(value.is_kind(ValueDataKind::Duration));
+        assert_eq!(value.duration().unwrap(), Duration::from_millis(250));
+
+        let value = ValueParser::parse_str("1m30s", &ParserContext::default()).unwrap();
+        assert_eq!(value.duration().unwrap(), Duration::from_secs(90));
+
+        let value = ValueParser::parse_str("1.5h", &ParserContext::default()).unwrap();
+        assert_eq!(value.duration().unwrap(), Duration::from_secs(5400));
+
+        assert!(ValueParser::parse_str("5 parsecs", &ParserContext::default()).is_err());
+    }
+
+    #[test]
+    fn size() {
+        let value = ValueParser::parse_str("2MB", &ParserContext::default()).unwrap();
+        assert!(value.is_kind(ValueDataKind::Size));
+        assert_eq!(value.size().unwrap(), 2_000_000);
+
+        let value = ValueParser::parse_str("4KiB", &ParserContext::default()).unwrap();
+        assert_eq!(value.size().unwrap(), 4096);
+
+        // A size isn't a duration in minutes
+        let value = ValueParser::parse_str("2mb", &ParserContext::default()).unwrap();
+        assert!(value.is_kind(ValueDataKind::Size));
+    }
+
+    #[test]
+    fn url() {
+        let value = ValueParser::parse_str(
+            "[https://example.com/data.json?page=2, 5s]",
+            &ParserContext::default(),
+        )
+        .unwrap();
+        let array = value.array().unwrap();
+        assert!(array[0].is_kind(ValueDataKind::Url));
+        assert_eq!(
+            array[0].url().unwrap().as_str(),
+            "https://example.com/data.json?page=2"
+        );
+        assert_eq!(array[1].duration().unwrap(), Duration::from_secs(5));
+
+        // Quoted URLs remain strings
+        let value =
+            ValueParser::parse_str(r#""https://example.com""#, &ParserContext::default()).unwrap();
+        assert!(value.is_kind(ValueDataKind::String));
+    }
+
+    #[test]
+    fn map() {
+        let value = ValueParser::parse_str(
+            r#"{Accept: "text/plain", "max-age": 30s, retry: {count: 3}}"#,
+            &ParserContext::default(),
+        )
+        .unwrap();
+        assert!(value.is_kind(ValueDataKind::Map));
+
+        let map = value.map().unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["Accept"].to_string(), "text/plain");
+        assert_eq!(
+            value.field("max-age").unwrap().duration().unwrap(),
+            Duration::from_secs(30)
+        );
+        let retry = value.field("retry").unwrap();
+        assert_eq!(retry.field("count").unwrap().integer().unwrap(), 3);
+        assert!(value.field("missing").is_none());
+
+        // Records in an array, and empty maps
+        let value = ValueParser::parse_str(
+            r#"[{name: "a"}, {name: "b"}, {}]"#,
+            &ParserContext::default(),
+        )
+        .unwrap();
+        let array = value.array().unwrap();
+        assert_eq!(array[1].field("name").unwrap().to_string(), "b");
+        assert!(array[2].map().unwrap().is_empty());
+    }
+
+    #[test]
+    fn bytes() {
+        let value = ValueParser::parse_str("data:aGVsbG8=", &ParserContext::default()).unwrap();
+        assert!(value.is_kind(ValueDataKind::Bytes));
+        assert_eq!(value.bytes().unwrap().as_slice(), b"hello");
+
+        let value =
+            ValueParser::parse_str("data:text/plain;base64,aGk=", &ParserContext::default())
+                .unwrap();
+        assert_eq!(value.bytes().unwrap().as_slice(), b"hi");
+
+        assert!(ValueParser::parse_str("data:a", &ParserContext::default()).is_err());
+    }
+
+    #[test]
+    fn embed() {
+        let dir = std::env::temp_dir().join("snowcap-embed-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("icon.svg"), "<svg/>").unwrap();
+
+        let policy = ModulePolicy::default();
+
+        // Relative paths are resolved from the asset directory
+        let value = with_asset_dir(Some(&dir), &policy, || {
+            ValueParser::parse_str(r#"embed!("icon.svg")"#, &ParserContext::default())
+        })
+        .unwrap();
+        assert_eq!(value.bytes().unwrap().as_slice(), b"<svg/>");
+
+        let missing = with_asset_dir(Some(&dir), &policy, || {
+            ValueParser::parse_str(r#"embed!("missing.png")"#, &ParserContext::default())
+        });
+        assert!(missing.unwrap_err().to_string().contains("missing.png"));
+
+        // Files outside the file roots of the policy can't be embedded
+        let root = dir.join("assets");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("logo.svg"), "<svg/>").unwrap();
+        let policy = ModulePolicy::default().with_file_root(&root);
+
+        let inside = with_asset_dir(Some(&dir), &policy, || {
+            ValueParser::parse_str(r#"embed!("assets/logo.svg")"#, &ParserContext::default())
+        });
+        assert!(inside.is_ok());
+
+        let outside = with_asset_dir(Some(&dir), &policy, || {
+            ValueParser::parse_str(r#"embed!("icon.svg")"#, &ParserContext::default())
+        });
+        assert!(outside.is_err());
+    }
 }
@@ -0,0 +1,118 @@
+//! Performance diagnostics
+//!
+//! The engine measures the time spent in each phase of loading markup and rebuilding widgets, and publishes the
+//! [`PhaseTimings`] of each update which did any work on the [`topic()`] diagnostics topic:
+//!
+//! ```ignore
+//! let subscription = snow.subscribe(snowcap::perf::topic(), |_topic, message| {
+//!     if let Some(timings) = message.downcast_ref::<PhaseTimings>() {
+//!         println!("{timings}");
+//!     }
+//!     Task::none()
+//! });
+//! ```
+//!
+//! The timings of the most recent update are also available from [`crate::Snowcap::timings()`]. Criterion benchmarks
+//! of the phases are in `benches/phases.rs`, run with `cargo bench`.
+//!
+//! Timings shouldn't be displayed by widgets of the same engine, as rebuilding them would publish new timings.
+
+use std::{fmt::Display, time::Duration, time::Instant};
+
+use crate::message::module::Topic;
+
+/// Topic [`PhaseTimings`] are published to
+pub const DIAGNOSTICS_TOPIC: &str = "snowcap/diagnostics";
+
+/// Get the topic [`PhaseTimings`] are published to
+pub fn topic() -> Topic {
+    Topic::new(DIAGNOSTICS_TOPIC)
+}
+
+/// Time spent in each phase of an update. Phases which didn't run are None.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// Parsing markup into a tree
+    pub parse: Option<Duration>,
+    /// Diffing a reloaded tree against the live tree
+    pub diff: Option<Duration>,
+    /// Patching the differences into the live tree
+    pub patch: Option<Duration>,
+    /// Rebuilding the widgets of new and dirty nodes
+    pub build: Option<Duration>,
+    /// Creating the root element in the last [`crate::Snowcap::view()`]
+    pub view: Option<Duration>,
+}
+
+impl PhaseTimings {
+    /// Total time of the phases which ran
+    pub fn total(&self) -> Duration {
+        self.phases().filter_map(|(_, duration)| duration).sum()
+    }
+
+    /// Returns true if no phases ran
+    pub fn is_empty(&self) -> bool {
+        self.phases().all(|(_, duration)| duration.is_none())
+    }
+
+    /// Name and duration of each phase
+    fn phases(&self) -> impl Iterator<Item = (&'static str, Option<Duration>)> {
+        [
+            ("parse", self.parse),
+            ("diff", self.diff),
+            ("patch", self.patch),
+            ("build", self.build),
+            ("view", self.view),
+        ]
+        .into_iter()
+    }
+}
+
+impl Display for PhaseTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+        for (name, duration) in self.phases() {
+            if let Some(duration) = duration {
+                if !first {
+                    write!(f, " ")?;
+                }
+                write!(f, "{name}={duration:?}")?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run a phase, adding the time it took to a timing
+pub(crate) fn measure<T>(timing: &mut Option<Duration>, phase: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = phase();
+    *timing = Some(timing.unwrap_or_default() + start.elapsed());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_test::traced_test;
+
+    use super::{measure, PhaseTimings};
+
+    #[traced_test]
+    #[test]
+    fn phase_timings() {
+        let mut timings = PhaseTimings::default();
+        assert!(timings.is_empty());
+
+        let value = measure(&mut timings.parse, || 42);
+        assert_eq!(value, 42);
+        assert!(timings.parse.is_some());
+
+        timings.build = Some(Duration::from_millis(2));
+        assert!(timings.total() >= Duration::from_millis(2));
+        assert!(timings.to_string().contains("build=2ms"));
+        assert!(!timings.to_string().contains("diff"));
+    }
+}
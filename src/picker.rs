@@ -0,0 +1,294 @@
+//! Click-to-source picking of widgets while the debug inspector is shown
+//!
+//! Ctrl+clicking a widget while the inspector is shown sends [`Command::OpenSource`] with the location of its
+//! element in the markup file, rather than clicking the widget. The engine publishes the location to
+//! [`OPEN_SOURCE_TOPIC`](crate::message::OPEN_SOURCE_TOPIC) as `path:line:column`, which hosts can open in an editor.
+//!
+//! The [`SourcePicker`] wrapping the root widget forwards the click to the tree without a cursor position, so no
+//! widget reacts to it, and each [`crate::dynamic_widget::WidgetRef`] containing the click offers its location
+//! with [`offer()`]. Widgets are visited from the root down, so the location of the innermost widget is kept.
+
+use std::cell::RefCell;
+
+use iced::{
+    advanced::{
+        layout, mouse, overlay, renderer,
+        widget::{tree, Operation, Tree},
+        Clipboard, Layout, Shell, Widget,
+    },
+    event, keyboard, Element, Event, Length, Point, Rectangle, Size, Vector,
+};
+use salish::Message;
+use tracing::debug;
+
+use crate::{message::Command, SourceLocation};
+
+/// Click being picked, and the location of the innermost widget containing it
+struct Pick {
+    position: Point,
+    location: Option<SourceLocation>,
+}
+
+thread_local! {
+    /// Pick in progress, set while the [`SourcePicker`] forwards a click to the tree
+    static PICK: RefCell<Option<Pick>> = const { RefCell::new(None) };
+}
+
+/// Offer the location of the element of a widget to the pick in progress, if the widget contains the click
+pub(crate) fn offer(bounds: Rectangle, location: Option<&SourceLocation>) {
+    let Some(location) = location else {
+        return;
+    };
+
+    PICK.with_borrow_mut(|pick| {
+        if let Some(pick) = pick {
+            if bounds.contains(pick.position) {
+                pick.location = Some(location.clone());
+            }
+        }
+    });
+}
+
+/// State of the [`SourcePicker`] widget
+#[derive(Debug, Default)]
+struct State {
+    modifiers: keyboard::Modifiers,
+}
+
+/// Widget wrapping the root widget, which picks the widget under a Ctrl+click
+pub(crate) struct SourcePicker<'a> {
+    content: Element<'a, Message>,
+}
+
+impl<'a> SourcePicker<'a> {
+    pub fn new(content: impl Into<Element<'a, Message>>) -> Self {
+        Self {
+            content: content.into(),
+        }
+    }
+}
+
+impl<'a> Widget<Message, iced::Theme, iced::Renderer> for SourcePicker<'a> {
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.content));
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &iced::Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        operation: &mut dyn Operation,
+    ) {
+        self.content
+            .as_widget()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+
+        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = &event {
+            state.modifiers = *modifiers;
+        }
+
+        let position = match (&event, cursor.position()) {
+            (Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)), Some(position))
+                if state.modifiers.control() =>
+            {
+                position
+            }
+            _ => {
+                return self.content.as_widget_mut().on_event(
+                    &mut tree.children[0],
+                    event,
+                    layout,
+                    cursor,
+                    renderer,
+                    clipboard,
+                    shell,
+                    viewport,
+                )
+            }
+        };
+
+        // Forward the click without a cursor, so widgets offer their locations without reacting to it
+        PICK.set(Some(Pick {
+            position,
+            location: None,
+        }));
+        let _ = self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            mouse::Cursor::Unavailable,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        );
+        let location = PICK.take().and_then(|pick| pick.location);
+
+        match location {
+            Some(SourceLocation {
+                file: Some(file),
+                line,
+                column,
+            }) => {
+                debug!("Picked {file}:{line}:{column}");
+                shell.publish(Message::broadcast(Command::OpenSource {
+                    path: file.into(),
+                    line,
+                    column,
+                }));
+            }
+            location => debug!("No markup file to open for the picked widget {location:?}"),
+        }
+
+        event::Status::Captured
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        let state = tree.state.downcast_ref::<State>();
+        if state.modifiers.control() && cursor.is_over(layout.bounds()) {
+            return mouse::Interaction::Crosshair;
+        }
+
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &iced::Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, iced::Theme, iced::Renderer>> {
+        self.content
+            .as_widget_mut()
+            .overlay(&mut tree.children[0], layout, renderer, translation)
+    }
+}
+
+impl<'a> From<SourcePicker<'a>> for Element<'a, Message> {
+    fn from(picker: SourcePicker<'a>) -> Self {
+        Element::new(picker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::{Point, Rectangle, Size};
+    use tracing_test::traced_test;
+
+    use super::{offer, Pick, PICK};
+    use crate::SourceLocation;
+
+    #[traced_test]
+    #[test]
+    fn innermost_offer() {
+        let location = |line| SourceLocation {
+            file: Some("app.iced".into()),
+            line,
+            column: 1,
+        };
+
+        // Offers outside a pick are ignored
+        offer(
+            Rectangle::new(Point::ORIGIN, Size::new(100.0, 100.0)),
+            Some(&location(1)),
+        );
+        assert!(PICK.take().is_none());
+
+        PICK.set(Some(Pick {
+            position: Point::new(20.0, 20.0),
+            location: None,
+        }));
+        offer(
+            Rectangle::new(Point::ORIGIN, Size::new(100.0, 100.0)),
+            Some(&location(1)),
+        );
+        offer(
+            Rectangle::new(Point::new(10.0, 10.0), Size::new(20.0, 20.0)),
+            Some(&location(2)),
+        );
+        offer(
+            Rectangle::new(Point::new(50.0, 50.0), Size::new(20.0, 20.0)),
+            Some(&location(3)),
+        );
+
+        let pick = PICK.take().unwrap();
+        assert_eq!(pick.location, Some(location(2)));
+    }
+}
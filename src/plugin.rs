@@ -0,0 +1,199 @@
+//! Widget build plugins
+//!
+//! A [`WidgetPlugin`] registered with [`crate::Snowcap::register_plugin()`] intercepts the widget built for each node
+//! it matches, and can wrap or replace it without changing the widget conversions, such as to draw debug borders
+//! around widgets or to record analytics of their events.
+//!
+//! ```ignore
+//! struct DebugBorders;
+//!
+//! impl WidgetPlugin for DebugBorders {
+//!     fn matches(&self, node: &PluginNode) -> bool {
+//!         node.kind == "button"
+//!     }
+//!
+//!     fn wrap(&self, _node: &PluginNode, widget: Element<'static, Message>) -> Element<'static, Message> {
+//!         container(widget).style(|_| container::bordered_box(&Theme::Dark)).into()
+//!     }
+//! }
+//!
+//! snow.register_plugin(DebugBorders);
+//! ```
+//!
+//! Plugins are applied in the order they were registered, after drag and drop and size constraints, so a plugin
+//! receives the complete widget of the node. Widgets which failed to build are rendered as error placeholders
+//! without being passed to plugins.
+
+use std::sync::Arc;
+
+use iced::{widget::Container, Element};
+use salish::Message;
+use tracing::debug;
+
+use crate::{
+    dynamic_widget::DynamicWidget,
+    node::{Content, SnowcapNode},
+    ConversionError, NodeId, SourceLocation,
+};
+
+/// Node whose widget was built, passed to a [`WidgetPlugin`]
+#[derive(Debug, Clone)]
+pub struct PluginNode<'a> {
+    pub node_id: NodeId,
+    /// Name of the widget, such as `button`, or of the layout, such as `row` or `container`
+    pub kind: &'a str,
+    /// Element id given with `#id` in the markup
+    pub element_id: Option<&'a str>,
+    /// Location of the element in the markup
+    pub location: Option<&'a SourceLocation>,
+}
+
+impl<'a> PluginNode<'a> {
+    fn new(node_id: NodeId, data: &'a SnowcapNode, location: Option<&'a SourceLocation>) -> Self {
+        let kind = match data.content() {
+            Content::Widget(name) => name.as_str(),
+            Content::Container => "container",
+            Content::Row => "row",
+            Content::Column => "column",
+            Content::Stack => "stack",
+            Content::LazyColumn => "lazy-column",
+            Content::Boundary => "error-boundary",
+            Content::Region => "region",
+            Content::Root => "root",
+            Content::Value(_) | Content::Module(_) | Content::None => "",
+        };
+
+        Self {
+            node_id,
+            kind,
+            element_id: data.element_id.as_deref(),
+            location,
+        }
+    }
+}
+
+/// Plugin intercepting the widgets built for the nodes it matches
+pub trait WidgetPlugin: Send + Sync {
+    /// Name of the plugin, used in logs
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Returns true if the plugin intercepts the widget of a node
+    fn matches(&self, node: &PluginNode<'_>) -> bool;
+
+    /// Wrap or replace the widget built for a matching node
+    fn wrap(
+        &self,
+        node: &PluginNode<'_>,
+        widget: Element<'static, Message>,
+    ) -> Element<'static, Message>;
+}
+
+/// Plugins registered with the engine, in the order they are applied
+#[derive(Default, Clone)]
+pub(crate) struct Plugins {
+    plugins: Vec<Arc<dyn WidgetPlugin>>,
+}
+
+impl std::fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.plugins.iter().map(|plugin| plugin.name()))
+            .finish()
+    }
+}
+
+impl Plugins {
+    pub fn register(&mut self, plugin: Arc<dyn WidgetPlugin>) {
+        debug!("Registered widget plugin {}", plugin.name());
+        self.plugins.push(plugin);
+    }
+
+    /// Pass the widget built for a node through each plugin matching it
+    pub fn apply(
+        &self,
+        node_id: NodeId,
+        data: &SnowcapNode,
+        location: Option<&SourceLocation>,
+        mut widget: DynamicWidget<Message>,
+    ) -> Result<DynamicWidget<Message>, ConversionError> {
+        if self.plugins.is_empty() {
+            return Ok(widget);
+        }
+
+        let node = PluginNode::new(node_id, data, location);
+
+        for plugin in self.plugins.iter().filter(|plugin| plugin.matches(&node)) {
+            debug!(
+                "Widget plugin {} intercepting node {node_id}",
+                plugin.name()
+            );
+            let element = plugin.wrap(&node, widget.into_element()?);
+            widget = DynamicWidget::default()
+                .with_widget(Container::new(element))
+                .with_node_id(node_id);
+        }
+
+        Ok(widget)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use iced::{widget::container, Element};
+    use salish::Message;
+    use tracing_test::traced_test;
+
+    use super::{PluginNode, WidgetPlugin};
+    use crate::{cache::WidgetCache, module::manager::ModuleManager, SnowcapParser, Source};
+
+    #[derive(Default)]
+    struct CountButtons {
+        wrapped: AtomicUsize,
+    }
+
+    impl WidgetPlugin for CountButtons {
+        fn matches(&self, node: &PluginNode<'_>) -> bool {
+            node.kind == "button"
+        }
+
+        fn wrap(
+            &self,
+            node: &PluginNode<'_>,
+            widget: Element<'static, Message>,
+        ) -> Element<'static, Message> {
+            assert!(node.element_id.is_some());
+            self.wrapped.fetch_add(1, Ordering::Relaxed);
+            container(widget).padding(1).into()
+        }
+    }
+
+    #[traced_test]
+    #[test]
+    fn plugin_wraps_matching() {
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{|[button#ok(text("Ok")), text("Label"), button#cancel(text("Cancel"))]}"#,
+        )
+        .unwrap()
+        .index();
+
+        let router =
+            salish::router::MessageRouter::<iced::Task<salish::message::Message>, Source>::new();
+        let mut modules = ModuleManager::new(router);
+
+        let plugin = Arc::new(CountButtons::default());
+        let mut cache = WidgetCache::default();
+        cache.register_plugin(plugin.clone());
+
+        let _task = cache.update_tree(&tree, &mut modules).unwrap();
+        assert_eq!(plugin.wrapped.load(Ordering::Relaxed), 2);
+        assert!(cache.get(tree.root().node().id()).is_some());
+    }
+}
@@ -0,0 +1,232 @@
+//! Recording and replay of engine input
+//!
+//! While recording is enabled with [`crate::Snowcap::start_recording()`], every [`WidgetMessage`] and [`Command`]
+//! routed through the engine is captured with the time elapsed since the first recorded message. The [`Recording`] returned by
+//! [`crate::Snowcap::stop_recording()`] can be replayed into a fresh `Headless` engine of the `headless` feature,
+//! loaded with the same markup, reproducing the interaction sequence on the virtual clock:
+//!
+//! ```ignore
+//! snow.start_recording();
+//! // ... interact with the application
+//! let recording = snow.stop_recording().unwrap();
+//!
+//! let mut headless = Headless::new()?;
+//! headless.load(markup)?.replay(&recording);
+//! ```
+//!
+//! Messages from module instances aren't replayed, as the module instances of the replaying engine produce them
+//! again on the virtual clock. Flushes and animation frames are produced by the engine itself, and aren't recorded.
+//! Widget messages are retargeted to the node with the same [`crate::StableId`] in the replaying engine, and the
+//! state a widget stores in its attributes before sending its message, such as a slider value, is restored.
+
+use std::{sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use tokio::time::Instant;
+
+use crate::message::{widget::WidgetMessage, Command};
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "headless"))]
+use crate::{
+    attribute::AttributeValue, conversion::pick_list, headless::Headless,
+    message::widget::WidgetEvent,
+};
+
+/// Message captured by a recording
+#[derive(Debug, Clone)]
+pub enum Recorded {
+    Widget(WidgetMessage),
+    Command(Command),
+}
+
+/// A captured message, and the time it was routed relative to the first message of the recording
+#[derive(Debug, Clone)]
+pub struct RecordedMessage {
+    pub at: Duration,
+    pub message: Recorded,
+}
+
+/// Sequence of messages captured by [`crate::Snowcap::start_recording()`]
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub messages: Vec<RecordedMessage>,
+}
+
+impl Recording {
+    /// Get the number of recorded messages
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns true if no messages were recorded
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Time of the last recorded message
+    pub fn duration(&self) -> Duration {
+        self.messages
+            .last()
+            .map(|message| message.at)
+            .unwrap_or_default()
+    }
+}
+
+/// Captures messages into a [`Recording`] while enabled. Cloned into the endpoints of the engine.
+///
+/// Times are measured from the first message rather than from [`Recorder::start()`], as messages are recorded
+/// inside the runtime driving the engine, which may be on a virtual clock.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Recorder(Arc<Mutex<Option<(Option<Instant>, Recording)>>>);
+
+impl Recorder {
+    /// Start a new recording, discarding any recording in progress
+    pub fn start(&self) {
+        *self.0.lock() = Some((None, Recording::default()));
+    }
+
+    /// Stop recording, and get the recorded messages
+    pub fn stop(&self) -> Option<Recording> {
+        self.0.lock().take().map(|(_, recording)| recording)
+    }
+
+    /// Record a message if recording
+    pub fn record(&self, message: Recorded) {
+        if let Some((start, recording)) = &mut *self.0.lock() {
+            let start = start.get_or_insert_with(Instant::now);
+            recording.messages.push(RecordedMessage {
+                at: start.elapsed(),
+                message,
+            });
+        }
+    }
+
+    /// Record a command, unless it is produced by the engine itself
+    pub fn record_command(&self, command: &Command) {
+        match command {
+            Command::Flush | Command::AnimationFrame => {}
+            // Selections made with the keyboard are recorded as widget messages
+            Command::Navigate(_) => {}
+            // Replaying would open the editor again
+            Command::OpenSource { .. } => {}
+            command => self.record(Recorded::Command(command.clone())),
+        }
+    }
+}
+
+/// Attribute a widget stores before sending a message with an event
+#[cfg(all(not(target_arch = "wasm32"), feature = "headless"))]
+fn event_state(event: &WidgetEvent) -> Option<AttributeValue> {
+    match event {
+        WidgetEvent::SliderChanged(value) | WidgetEvent::SliderReleased(value) => {
+            Some(AttributeValue::SliderValue(*value))
+        }
+        WidgetEvent::Toggler(toggled) => Some(AttributeValue::Toggled(*toggled)),
+        WidgetEvent::PickListSelected(selected) => {
+            Some(AttributeValue::Selected(pick_list::value_text(selected)))
+        }
+        WidgetEvent::InputChanged(text) => Some(AttributeValue::InputValue(text.clone())),
+        WidgetEvent::NumberChanged(value) => Some(AttributeValue::InputValue(value.to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "headless"))]
+impl Headless {
+    /// Replay a [`Recording`] into the engine, driving tasks on the virtual clock between messages
+    /// as they were timed when recorded
+    pub fn replay(&mut self, recording: &Recording) -> &mut Self {
+        let mut last = Duration::ZERO;
+
+        for recorded in &recording.messages {
+            self.run_for(recorded.at.saturating_sub(last));
+            last = recorded.at;
+
+            let message = match &recorded.message {
+                Recorded::Widget(message) => match self.retarget(message.clone()) {
+                    Some(message) => salish::Message::broadcast(message),
+                    None => continue,
+                },
+                Recorded::Command(command) => salish::Message::broadcast(command.clone()),
+            };
+
+            self.send(message);
+        }
+
+        self
+    }
+
+    /// Find the node of a recorded widget message in this engine, and restore the state of its widget
+    fn retarget(&mut self, mut message: WidgetMessage) -> Option<WidgetMessage> {
+        if let Some(node_id) = message
+            .stable_id
+            .as_ref()
+            .and_then(|stable_id| self.engine().resolve(stable_id))
+        {
+            message.node_id = node_id;
+        }
+
+        let mut guard = self.engine().tree.lock();
+        let Some(node) = guard
+            .as_mut()
+            .and_then(|tree| tree.get_node_mut(&message.node_id))
+        else {
+            tracing::warn!("Replayed message target {} not found", message.node_id);
+            return None;
+        };
+
+        if let Some(value) = event_state(&message.event) {
+            let _ = node.node().data().attrs.set(value);
+        }
+        drop(guard);
+
+        Some(message)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32"), feature = "headless"))]
+mod tests {
+    use std::time::Duration;
+
+    use tracing_test::traced_test;
+
+    use crate::{headless::Headless, testing::Harness};
+
+    #[traced_test]
+    #[test]
+    fn record_replay() {
+        let markup = r#"{|[button#save(text("Save")), slider#vol(), toggler#mute()]}"#;
+
+        let mut harness = Harness::new(markup).unwrap();
+        harness.headless().engine_mut().start_recording();
+        harness
+            .click("#save")
+            .unwrap()
+            .set_slider("#vol", 30)
+            .unwrap();
+        harness.headless().run_for(Duration::from_secs(5));
+        harness.toggle("#mute", true).unwrap();
+        let recording = harness.headless().engine_mut().stop_recording().unwrap();
+
+        // Press, slider change and release, and toggle
+        assert_eq!(recording.len(), 4);
+        assert!(recording.duration() >= Duration::from_secs(5));
+
+        let mut headless = Headless::new().unwrap();
+        headless.load(markup).unwrap().replay(&recording);
+
+        let original = harness.describe().unwrap();
+        let replayed = headless.describe().unwrap();
+        for id in ["vol", "mute"] {
+            assert_eq!(
+                original.find(id).unwrap().attrs,
+                replayed.find(id).unwrap().attrs
+            );
+        }
+        assert!(replayed
+            .find("vol")
+            .unwrap()
+            .attrs
+            .contains("SliderValue(30)"));
+    }
+}
@@ -0,0 +1,99 @@
+//! Scroll position control of scrollables declared in markup
+//!
+//! Each `scrollable` is given an [`iced::widget::scrollable::Id`] derived from its [`StableId`], so scrollables
+//! declared with an element id can be scrolled with [`Command::ScrollTo`](crate::message::Command::ScrollTo) or
+//! [`crate::Snowcap::scroll_to()`].
+//!
+//! The offsets of scrollables are recorded as they are scrolled, and restored after a hot reload,
+//! as scrollables replaced by the reload start again from the top.
+
+use std::{collections::HashMap, sync::Arc};
+
+use iced::{
+    widget::scrollable::{self, AbsoluteOffset},
+    Task,
+};
+use parking_lot::Mutex;
+use salish::Message;
+use tracing::debug;
+
+use crate::{identity::IdentityIndex, parser::ElementId, StableId};
+
+/// Get the [`scrollable::Id`] of the scrollable with a [`StableId`]
+pub(crate) fn scrollable_id(stable_id: &StableId) -> scrollable::Id {
+    scrollable::Id::new(stable_id.to_string())
+}
+
+/// Get a [`Task`] scrolling the scrollable with an element id to an absolute offset
+pub(crate) fn scroll_to(element_id: ElementId, offset: AbsoluteOffset) -> Task<Message> {
+    debug!("Scroll #{element_id} to {offset:?}");
+    scrollable::scroll_to(scrollable_id(&StableId::element(element_id)), offset)
+}
+
+/// Last offsets of scrollables, shared with the widget message endpoint
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ScrollOffsets(Arc<Mutex<HashMap<StableId, AbsoluteOffset>>>);
+
+impl ScrollOffsets {
+    /// Record the offset of a scrollable
+    pub fn record(&self, stable_id: StableId, offset: AbsoluteOffset) {
+        self.0.lock().insert(stable_id, offset);
+    }
+
+    /// Get the recorded offset of a scrollable
+    pub fn get(&self, stable_id: &StableId) -> Option<AbsoluteOffset> {
+        self.0.lock().get(stable_id).copied()
+    }
+
+    /// Get the recorded offsets of all scrollables
+    pub fn offsets(&self) -> Vec<(StableId, AbsoluteOffset)> {
+        self.0
+            .lock()
+            .iter()
+            .map(|(stable_id, offset)| (stable_id.clone(), *offset))
+            .collect()
+    }
+
+    /// Get a [`Task`] restoring the recorded offsets of scrollables after a reload.
+    /// Offsets of scrollables no longer in the tree are discarded.
+    pub fn restore(&self, identities: &IdentityIndex) -> Task<Message> {
+        let mut offsets = self.0.lock();
+        offsets.retain(|stable_id, _| identities.node_id(stable_id).is_some());
+
+        Task::batch(
+            offsets.iter().map(|(stable_id, offset)| {
+                scrollable::scroll_to(scrollable_id(stable_id), *offset)
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::widget::scrollable::AbsoluteOffset;
+    use tracing_test::traced_test;
+
+    use super::ScrollOffsets;
+    use crate::{identity::IdentityIndex, Message, SnowcapParser, StableId};
+
+    #[traced_test]
+    #[test]
+    fn discard_removed() {
+        let tree = SnowcapParser::<Message>::parse_memory(
+            r#"{scrollable#list(col[text("a"), text("b")])}"#,
+        )
+        .unwrap()
+        .index();
+        let identities = IdentityIndex::build(&tree);
+
+        let offsets = ScrollOffsets::default();
+        let offset = AbsoluteOffset { x: 0.0, y: 120.0 };
+        offsets.record(StableId::element("list"), offset);
+        offsets.record(StableId::element("removed"), offset);
+
+        let _ = offsets.restore(&identities);
+
+        assert_eq!(offsets.get(&StableId::element("list")), Some(offset));
+        assert_eq!(offsets.get(&StableId::element("removed")), None);
+    }
+}
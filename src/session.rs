@@ -0,0 +1,324 @@
+//! Persisting the state of the user interface across sessions
+//!
+//! [`crate::Snowcap::save_state()`] writes the state the user changed by interacting with the interface to a file,
+//! and [`crate::Snowcap::restore_state()`] merges it into the tree parsed from the markup, so an application reopens
+//! where the user left off. The state consists of:
+//!
+//! * The value of each slider, toggler, pick list and text input with an element id
+//! * The offset of each scrollable, by its [`StableId`]
+//! * The size of the window
+//!
+//! Widgets without an element id aren't saved, as they can't be matched after the markup changes. Saved elements
+//! missing from the markup are ignored. State restored before markup is loaded is merged after it is parsed.
+//!
+//! The file has one entry per line, with tab separated fields:
+//!
+//! ```text
+//! window	1024	768
+//! slider	volume	42
+//! toggled	dark-mode	true
+//! selected	language	fr
+//! input	search	snow\tcap
+//! scroll	#log	0	512.5
+//! ```
+
+use std::{collections::BTreeMap, path::Path, str::FromStr as _};
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use iced::{widget::scrollable::AbsoluteOffset, Size};
+use tracing::debug;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    error::SessionError,
+    IndexedTree, StableId,
+};
+
+/// Attributes storing the state of interactive widgets
+const INTERACTIVE: [AttributeKind; 4] = [
+    AttributeKind::SliderValue,
+    AttributeKind::Toggled,
+    AttributeKind::Selected,
+    AttributeKind::InputValue,
+];
+
+/// State of the user interface, saved by [`crate::Snowcap::save_state()`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SessionState {
+    /// Size of the window
+    pub window: Option<Size>,
+    /// Interactive attributes of elements, by element id
+    pub elements: BTreeMap<String, Vec<AttributeValue>>,
+    /// Offsets of scrollables
+    pub scroll: BTreeMap<StableId, AbsoluteOffset>,
+}
+
+impl SessionState {
+    /// Capture the interactive attributes of the elements of a tree
+    pub fn capture(tree: &IndexedTree) -> Self {
+        let mut state = Self::default();
+
+        tree.leaf_iter().for_each(|noderef| {
+            let node = noderef.node();
+            let data = node.data();
+            let Some(element_id) = &data.element_id else {
+                return;
+            };
+
+            let values: Vec<AttributeValue> = INTERACTIVE
+                .iter()
+                .filter_map(|kind| data.attrs.get(*kind).ok().flatten())
+                .collect();
+
+            if !values.is_empty() {
+                state.elements.insert(element_id.clone(), values);
+            }
+        });
+
+        state
+    }
+
+    /// Set the saved attributes of the elements of a tree, and mark them dirty. Returns the number of elements
+    /// restored.
+    pub fn apply(&self, tree: &IndexedTree) -> usize {
+        let mut restored = 0;
+
+        tree.leaf_iter().for_each(|noderef| {
+            let values = {
+                let node = noderef.node();
+                let Some(values) = node
+                    .data()
+                    .element_id
+                    .as_ref()
+                    .and_then(|element_id| self.elements.get(element_id))
+                else {
+                    return;
+                };
+
+                let attrs = &node.data().attrs;
+                values
+                    .iter()
+                    .filter(|value| attrs.set((*value).clone()).is_ok())
+                    .count()
+            };
+
+            if values > 0 {
+                noderef.node_mut().data_mut().set_dirty(true);
+                restored += 1;
+            }
+        });
+
+        debug!("Restored the state of {restored} elements");
+        restored
+    }
+
+    /// Load the state from a file
+    pub fn load(path: &Path) -> Result<Self, SessionError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Save the state to a file
+    pub fn save(&self, path: &Path) -> Result<(), SessionError> {
+        Ok(std::fs::write(path, self.to_text())?)
+    }
+
+    /// Parse the state from the text of a state file
+    pub fn parse(text: &str) -> Result<Self, SessionError> {
+        let mut state = Self::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let syntax = |message: String| SessionError::Syntax {
+                line: index + 1,
+                message,
+            };
+            let number = |field: &str| {
+                field
+                    .parse::<f32>()
+                    .map_err(|_| syntax(format!("expected a number, found '{field}'")))
+            };
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["window", width, height] => {
+                    state.window = Some(Size::new(number(width)?, number(height)?))
+                }
+                ["scroll", id, x, y] => {
+                    let id = StableId::from_str(id)
+                        .map_err(|_| syntax(format!("invalid stable id '{id}'")))?;
+                    state.scroll.insert(
+                        id,
+                        AbsoluteOffset {
+                            x: number(x)?,
+                            y: number(y)?,
+                        },
+                    );
+                }
+                [kind, element_id, value] => {
+                    let value = match *kind {
+                        "slider" => AttributeValue::SliderValue(
+                            value
+                                .parse()
+                                .map_err(|_| syntax(format!("invalid slider value '{value}'")))?,
+                        ),
+                        "toggled" => AttributeValue::Toggled(
+                            value
+                                .parse()
+                                .map_err(|_| syntax(format!("invalid toggled flag '{value}'")))?,
+                        ),
+                        "selected" => AttributeValue::Selected(unescape(value)),
+                        "input" => AttributeValue::InputValue(unescape(value)),
+                        kind => return Err(syntax(format!("unknown entry '{kind}'"))),
+                    };
+
+                    state
+                        .elements
+                        .entry(element_id.to_string())
+                        .or_default()
+                        .push(value);
+                }
+                _ => return Err(syntax(format!("unexpected entry '{line}'"))),
+            }
+        }
+
+        Ok(state)
+    }
+
+    /// Get the text of a state file
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+
+        if let Some(size) = self.window {
+            lines.push(format!("window\t{}\t{}", size.width, size.height));
+        }
+
+        for (element_id, values) in &self.elements {
+            for value in values {
+                let (kind, text) = match value {
+                    AttributeValue::SliderValue(value) => ("slider", value.to_string()),
+                    AttributeValue::Toggled(toggled) => ("toggled", toggled.to_string()),
+                    AttributeValue::Selected(selected) => ("selected", escape(selected)),
+                    AttributeValue::InputValue(input) => ("input", escape(input)),
+                    _ => continue,
+                };
+                lines.push(format!("{kind}\t{element_id}\t{text}"));
+            }
+        }
+
+        for (id, offset) in &self.scroll {
+            lines.push(format!("scroll\t{id}\t{}\t{}", offset.x, offset.y));
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+/// Escape the separators of a text value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Replace the escapes of a text value
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use arbutus::TreeNodeRef as _;
+    use iced::Size;
+    use tracing_test::traced_test;
+
+    use super::SessionState;
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        Message, SnowcapParser, StableId,
+    };
+
+    #[traced_test]
+    #[test]
+    fn roundtrip() {
+        let mut state = SessionState {
+            window: Some(Size::new(1024.0, 768.0)),
+            ..Default::default()
+        };
+        state.elements.insert(
+            "search".into(),
+            vec![AttributeValue::InputValue("snow\tcap\n\\".into())],
+        );
+        state
+            .elements
+            .insert("dark".into(), vec![AttributeValue::Toggled(true)]);
+        state.scroll.insert(
+            StableId::element("log"),
+            iced::widget::scrollable::AbsoluteOffset { x: 0.0, y: 512.5 },
+        );
+
+        let text = state.to_text();
+        assert!(text.contains("toggled\tdark\ttrue"));
+        assert_eq!(SessionState::parse(&text).unwrap(), state);
+
+        assert!(SessionState::parse("slider\tvolume\tloud").is_err());
+        assert!(SessionState::parse("unknown").is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn capture_and_apply() {
+        let markup = r#"{|[toggler#dark<toggled:true>("Dark"), slider#volume(), text("Unnamed")]}"#;
+        let tree = SnowcapParser::<Message>::parse_memory(markup)
+            .unwrap()
+            .index();
+
+        let state = SessionState::capture(&tree);
+        assert_eq!(
+            state.elements.get("dark"),
+            Some(&vec![AttributeValue::Toggled(true)])
+        );
+
+        // Restored into a tree parsed from the same markup, without the saved state
+        let markup = r#"{|[toggler#dark("Dark"), slider#volume(), text("Unnamed")]}"#;
+        let restored = SnowcapParser::<Message>::parse_memory(markup)
+            .unwrap()
+            .index();
+        state.apply(&restored);
+
+        let toggler = SessionState::capture(&restored);
+        assert_eq!(toggler.elements.get("dark"), state.elements.get("dark"));
+
+        let attrs = restored
+            .leaf_iter()
+            .find(|noderef| noderef.node().data().element_id.as_deref() == Some("dark"))
+            .map(|noderef| noderef.node().data().attrs.clone())
+            .unwrap();
+        assert_eq!(
+            attrs.get(AttributeKind::Toggled).unwrap(),
+            Some(AttributeValue::Toggled(true))
+        );
+    }
+}
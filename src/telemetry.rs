@@ -0,0 +1,137 @@
+//! Engine telemetry
+//!
+//! The engine emits `tracing` events with `node_id`, `handle_id` and `element_id` fields as it loads markup, builds
+//! widgets and manages module instances. The same events are delivered as [`TelemetryEvent`] values to listeners
+//! registered with [`subscribe()`], so hosts can collect them programmatically without installing a tracing
+//! subscriber:
+//!
+//! ```ignore
+//! let _telemetry = snowcap::telemetry::subscribe(|event| {
+//!     if let TelemetryEvent::WidgetFailed { node_id, error, .. } = event {
+//!         metrics.widget_failed(*node_id, error);
+//!     }
+//! });
+//! ```
+//!
+//! Listeners are called synchronously on the thread running the engine, and are registered until the returned
+//! [`TelemetrySubscription`] is dropped. Listeners should be quick, and must not subscribe or unsubscribe.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::RwLock;
+
+use crate::{message::widget::WidgetEvent, module::ModuleHandleId, NodeId};
+
+/// Event emitted by the engine
+#[derive(Debug, Clone)]
+pub enum TelemetryEvent {
+    /// Markup was loaded into a new tree
+    TreeLoaded { source: String },
+    /// Markup was reloaded, and the changes patched into the live tree
+    TreeReloaded {
+        source: String,
+        added: usize,
+        removed: usize,
+        modified: usize,
+    },
+    /// A module instance was started for a node
+    ModuleStarted {
+        handle_id: ModuleHandleId,
+        name: String,
+        node_id: NodeId,
+    },
+    /// A module instance sent an error
+    ModuleFailed {
+        handle_id: ModuleHandleId,
+        error: String,
+    },
+    /// A module instance with no remaining nodes was torn down
+    ModuleReleased { handle_id: ModuleHandleId },
+    /// The widget of a node failed to build
+    WidgetFailed {
+        node_id: NodeId,
+        element_id: Option<String>,
+        error: String,
+    },
+    /// A widget sent an event
+    WidgetEvent {
+        node_id: NodeId,
+        element_id: Option<String>,
+        event: WidgetEvent,
+    },
+}
+
+type Listener = Arc<dyn Fn(&TelemetryEvent) + Send + Sync>;
+
+/// Registered listeners, keyed by subscription id
+static LISTENERS: RwLock<BTreeMap<u64, Listener>> = parking_lot::const_rwlock(BTreeMap::new());
+
+/// Id of the next subscription
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Registration of a telemetry listener, which is removed when dropped
+#[derive(Debug)]
+pub struct TelemetrySubscription {
+    id: u64,
+}
+
+impl Drop for TelemetrySubscription {
+    fn drop(&mut self) {
+        LISTENERS.write().remove(&self.id);
+    }
+}
+
+/// Call `f` with each [`TelemetryEvent`] emitted by any engine, until the returned [`TelemetrySubscription`] is dropped
+pub fn subscribe<F>(f: F) -> TelemetrySubscription
+where
+    F: Fn(&TelemetryEvent) + Send + Sync + 'static,
+{
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    LISTENERS.write().insert(id, Arc::new(f));
+    TelemetrySubscription { id }
+}
+
+/// Deliver an event to the registered listeners
+pub(crate) fn emit(event: TelemetryEvent) {
+    let listeners = LISTENERS.read();
+    for listener in listeners.values() {
+        listener(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use tracing_test::traced_test;
+
+    use super::{emit, subscribe, TelemetryEvent};
+
+    #[traced_test]
+    #[test]
+    fn telemetry_listeners() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let listener_events = events.clone();
+
+        let subscription = subscribe(move |event| {
+            if let TelemetryEvent::ModuleReleased { handle_id } = event {
+                listener_events.lock().push(*handle_id);
+            }
+        });
+
+        emit(TelemetryEvent::ModuleReleased { handle_id: 42 });
+        drop(subscription);
+        emit(TelemetryEvent::ModuleReleased { handle_id: 43 });
+
+        // Other tests may emit events concurrently, but not after the subscription was dropped
+        assert!(events.lock().contains(&42));
+        assert!(!events.lock().contains(&43));
+    }
+}
@@ -0,0 +1,301 @@
+//! Snapshot testing of parsed markup trees
+//!
+//! [`tree_snapshot()`] parses and indexes markup, and serializes the tree into a stable text format with a line
+//! for each node, indented by depth, with its [`crate::StableId`], content, attributes and content hash:
+//!
+//! ```text
+//! / Root 5f3a0c9e1d2b4a67
+//!   /0 Container 1c2d3e4f5a6b7c8d
+//!     /0/0 Column <Some(Spacing(Pixels(10.0)))> 9d8e7f6a5b4c3d2e
+//!       #title Widget: text 0123456789abcdef
+//!         #title/0 Value: Hello fedcba9876543210
+//! ```
+//!
+//! [`assert_tree_snapshot!`](crate::assert_tree_snapshot) compares the snapshot of markup against a `.snap` file in the
+//! `snapshots` directory of the crate under test, named after the test module and function:
+//!
+//! ```ignore
+//! #[test]
+//! fn greeting() {
+//!     snowcap::assert_tree_snapshot!(r#"{|[text#title("Hello")]}"#);
+//! }
+//! ```
+//!
+//! The snapshot file is written on the first run, and should be committed. Once it exists, a different tree fails
+//! the assertion with a diff of the lines which changed. Set `SNOWCAP_UPDATE_SNAPSHOTS=1` to overwrite snapshots
+//! after an intended change.
+//!
+//! With the `headless` feature, a `Harness` simulates interactions with the widgets of markup loaded into a headless engine, such as pressing
+//! buttons and dragging sliders, so end-to-end behavior can be asserted in unit tests.
+
+use std::{
+    fmt::Write as _,
+    hash::{Hash as _, Hasher as _},
+    path::{Path, PathBuf},
+};
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use xxhash_rust::xxh64::Xxh64;
+
+use crate::{
+    cache::{self, DataContent, WidgetCache},
+    identity::IdentityIndex,
+    module::data::ModuleData,
+    node::Content,
+    Error, IndexedTree, Message, NodeRef, SnowcapParser,
+};
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "headless"))]
+mod harness;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "headless"))]
+pub use harness::Harness;
+
+/// Environment variable which overwrites snapshots which don't match when set to `1`
+pub const UPDATE_SNAPSHOTS_VAR: &str = "SNOWCAP_UPDATE_SNAPSHOTS";
+
+/// Directory of snapshot files, relative to the manifest directory of the crate under test
+const SNAPSHOT_DIR: &str = "snapshots";
+
+/// Parse and index markup, and serialize the tree into the snapshot text format
+pub fn tree_snapshot(markup: &str) -> Result<String, Error> {
+    let tree = IndexedTree::from_tree(SnowcapParser::<Message>::parse_memory(markup)?);
+    IdentityIndex::build(&tree);
+
+    let mut snapshot = String::new();
+    write_node(&mut snapshot, tree.root(), 0);
+    Ok(snapshot)
+}
+
+/// Write the line of a node, followed by its children
+fn write_node(snapshot: &mut String, noderef: &NodeRef, depth: usize) {
+    let node = noderef.node();
+    let data = node.data();
+
+    let mut hasher = Xxh64::new(0);
+    data.hash(&mut hasher);
+
+    let _ = write!(snapshot, "{:indent$}", "", indent = depth * 2);
+    if let Some(stable_id) = data.stable_id() {
+        let _ = write!(snapshot, "{stable_id} ");
+    }
+    let _ = write!(snapshot, "{}", data.content());
+    if data.attrs.len() > 0 {
+        let _ = write!(snapshot, " {}", data.attrs);
+    }
+    let _ = writeln!(snapshot, " {:016x}", hasher.finish());
+
+    if let Some(children) = node.children() {
+        for child in children {
+            write_node(snapshot, child, depth + 1);
+        }
+    }
+}
+
+/// Get the name of the test function from the type name of a function declared inside it
+#[doc(hidden)]
+pub fn function_name(type_name: &str) -> &str {
+    type_name
+        .trim_end_matches("::f")
+        .split("::")
+        .rfind(|segment| *segment != "{{closure}}")
+        .unwrap_or("snapshot")
+}
+
+/// Get the path of the snapshot file of a test
+fn snapshot_path(manifest_dir: &str, module_path: &str, name: &str) -> PathBuf {
+    Path::new(manifest_dir)
+        .join(SNAPSHOT_DIR)
+        .join(format!("{}__{name}.snap", module_path.replace("::", "__")))
+}
+
+/// Describe the lines which differ between an expected and actual snapshot
+fn diff_lines(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    let mut diff = String::new();
+    for line in 0..expected.len().max(actual.len()) {
+        match (expected.get(line), actual.get(line)) {
+            (Some(expected), Some(actual)) if expected == actual => {}
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    let _ = writeln!(diff, "{:>4} - {expected}", line + 1);
+                }
+                if let Some(actual) = actual {
+                    let _ = writeln!(diff, "{:>4} + {actual}", line + 1);
+                }
+            }
+        }
+    }
+    diff
+}
+
+/// Compare the snapshot of markup against the snapshot file of a test, writing it if it doesn't exist.
+/// Called by [`assert_tree_snapshot!`](crate::assert_tree_snapshot), and panics if the snapshots differ.
+#[doc(hidden)]
+pub fn assert_snapshot(manifest_dir: &str, module_path: &str, name: &str, markup: &str) {
+    let actual = match tree_snapshot(markup) {
+        Ok(snapshot) => snapshot,
+        Err(e) => panic!("Failed to parse markup of snapshot {name}: {e}"),
+    };
+
+    let path = snapshot_path(manifest_dir, module_path, name);
+    let update = std::env::var(UPDATE_SNAPSHOTS_VAR).is_ok_and(|value| value == "1");
+
+    match std::fs::read_to_string(&path) {
+        Ok(expected) if expected == actual => {}
+        Ok(expected) if !update => panic!(
+            "Tree snapshot {} doesn't match, set {UPDATE_SNAPSHOTS_VAR}=1 to update\n{}",
+            path.display(),
+            diff_lines(&expected, &actual)
+        ),
+        _ => {
+            tracing::warn!("Writing tree snapshot {}", path.display());
+            if let Err(e) = path
+                .parent()
+                .map(std::fs::create_dir_all)
+                .transpose()
+                .and_then(|_| std::fs::write(&path, &actual))
+            {
+                panic!("Failed to write snapshot {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Assert the tree parsed from markup matches a snapshot file, see [`crate::testing`].
+///
+/// The snapshot is named after the enclosing test function, or can be named explicitly with
+/// `assert_tree_snapshot!("name", markup)`.
+#[macro_export]
+macro_rules! assert_tree_snapshot {
+    ($markup:expr) => {{
+        fn f() {}
+        let name = $crate::testing::function_name(::std::any::type_name_of_val(&f));
+        $crate::testing::assert_snapshot(env!("CARGO_MANIFEST_DIR"), module_path!(), name, $markup)
+    }};
+    ($name:expr, $markup:expr) => {{
+        $crate::testing::assert_snapshot(env!("CARGO_MANIFEST_DIR"), module_path!(), $name, $markup)
+    }};
+}
+
+/// Module data of animated image widgets, decoded by the `decode` group of the `phases` benchmark to compare
+/// decoding on worker threads with decoding as the widgets are built
+#[doc(hidden)]
+pub struct DecodeQueue {
+    _tree: IndexedTree,
+    modules: Vec<NodeRef>,
+}
+
+impl DecodeQueue {
+    /// Create `count` animated image widgets, with the module data created by `data`
+    pub fn new(count: usize, data: impl Fn() -> Box<dyn ModuleData>) -> Result<Self, Error> {
+        let items: Vec<String> = (0..count)
+            .map(|i| format!(r#"image<animated:true>(file!{{path:"{i}.gif"}})"#))
+            .collect();
+        let tree = IndexedTree::from_tree(SnowcapParser::<Message>::parse_memory(&format!(
+            "{{-[{}]}}",
+            items.join(",")
+        ))?);
+
+        let mut modules = Vec::new();
+        let mut pending = vec![tree.root().clone()];
+        while let Some(noderef) = pending.pop() {
+            if let Content::Module(_) = noderef.node().data().content() {
+                noderef.node_mut().data_mut().set_module_data(data());
+                modules.push(noderef.clone());
+            }
+            if let Some(children) = noderef.node().children() {
+                pending.extend(children.iter().cloned());
+            }
+        }
+
+        Ok(Self {
+            _tree: tree,
+            modules,
+        })
+    }
+
+    /// Decode the data on worker threads, as updates with enough module data do. Returns the number decoded.
+    pub fn decode_concurrently(&self) -> usize {
+        let nodes: Vec<&NodeRef> = self.modules.iter().collect();
+        WidgetCache::decode_concurrently(&nodes).len()
+    }
+
+    /// Decode the data serially, as the widgets are built. Returns the number decoded.
+    pub fn decode_serially(&self) -> usize {
+        self.modules
+            .iter()
+            .filter_map(|noderef| {
+                let node = noderef.node();
+                let data = node.data().module_data()?;
+                let animated = node.parent().is_some_and(cache::is_animated);
+                Some(DataContent::decode(data.as_ref(), animated))
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_test::traced_test;
+
+    use super::{assert_snapshot, diff_lines, function_name, snapshot_path, tree_snapshot};
+
+    #[traced_test]
+    #[test]
+    fn snapshot_format() {
+        let snapshot =
+            tree_snapshot(r#"{|<spacing:10>[text#title("Hello"), text("World")]}"#).unwrap();
+        let lines: Vec<&str> = snapshot.lines().collect();
+
+        assert!(lines[0].starts_with("/ Root "));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("      #title Widget: text ")));
+        assert!(lines.iter().any(|line| line.contains("Value: ")));
+
+        // Serialization is stable, and changes with the markup
+        assert_eq!(
+            snapshot,
+            tree_snapshot(r#"{|<spacing:10>[text#title("Hello"), text("World")]}"#).unwrap()
+        );
+        let changed =
+            tree_snapshot(r#"{|<spacing:10>[text#title("Hello"), text("Earth")]}"#).unwrap();
+        assert_ne!(snapshot, changed);
+        assert!(!diff_lines(&snapshot, &changed).is_empty());
+    }
+
+    #[traced_test]
+    #[test]
+    fn snapshot_file() {
+        let dir = std::env::temp_dir().join(format!("snowcap-snapshots-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+
+        // Written on the first run, and matched on the next
+        assert_snapshot(dir, "tests", "greeting", r#"{text("Hello")}"#);
+        assert!(snapshot_path(dir, "tests", "greeting").exists());
+        assert_snapshot(dir, "tests", "greeting", r#"{text("Hello")}"#);
+
+        let changed = std::panic::catch_unwind(|| {
+            assert_snapshot(dir, "tests", "greeting", r#"{text("Goodbye")}"#)
+        });
+        assert!(changed.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[traced_test]
+    #[test]
+    fn snapshot_name() {
+        assert_eq!(
+            function_name("snowcap::testing::tests::snapshot_name::f"),
+            "snapshot_name"
+        );
+        assert_eq!(
+            function_name("app::tests::greeting::{{closure}}::f"),
+            "greeting"
+        );
+    }
+}
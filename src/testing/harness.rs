@@ -0,0 +1,266 @@
+//! Simulation of widget interactions on a [`Headless`] engine
+//!
+//! A [`Harness`] synthesizes the [`WidgetMessage`] a widget sends when the user interacts with it, and routes it
+//! through [`crate::Snowcap::update()`] as the iced runtime would. Widgets are selected by their element id:
+//!
+//! ```ignore
+//! let mut harness = Harness::new(r#"{|[button#save(text("Save")), slider#vol<value:10>()]}"#)?;
+//! harness.click("#save")?.set_slider("#vol", 50)?;
+//!
+//! assert!(harness.describe().unwrap().find("vol").unwrap().attrs.contains("SliderValue(50)"));
+//! ```
+//!
+//! Stateful widgets such as sliders store their new value in the node attributes before the message is sent,
+//! matching the widgets built by the engine.
+
+use arbutus::{TreeNode as _, TreeNodeRef as _};
+use salish::Message;
+
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    conversion::pick_list,
+    headless::{Headless, WidgetNode},
+    message::widget::{WidgetEvent, WidgetMessage},
+    node::Content,
+    Error, Snowcap, StableId, Value,
+};
+
+/// Drives a [`Headless`] engine with synthesized widget events
+#[derive(Debug)]
+pub struct Harness {
+    headless: Headless,
+}
+
+impl Harness {
+    /// Load markup into a new headless engine
+    pub fn new(markup: &str) -> Result<Self, Error> {
+        let mut headless = Headless::new()?;
+        headless.load(markup)?;
+        Ok(Self { headless })
+    }
+
+    /// Create a harness from a [`Headless`] engine, such as one with modules registered
+    pub fn with_headless(headless: Headless) -> Self {
+        Self { headless }
+    }
+
+    /// Get the headless engine, to drive tasks or send other messages
+    pub fn headless(&mut self) -> &mut Headless {
+        &mut self.headless
+    }
+
+    /// Get the engine
+    pub fn engine(&self) -> &Snowcap {
+        self.headless.engine()
+    }
+
+    /// Describe the tree, and the widgets built for it
+    pub fn describe(&self) -> Option<WidgetNode> {
+        self.headless.describe()
+    }
+
+    /// Press a `button`
+    pub fn click(&mut self, selector: &str) -> Result<&mut Self, Error> {
+        self.event(selector, "button", WidgetEvent::ButtonPress)
+    }
+
+    /// Drag a `slider` to a value, and release it
+    pub fn set_slider(&mut self, selector: &str, value: i32) -> Result<&mut Self, Error> {
+        self.set(selector, "slider", AttributeValue::SliderValue(value))?;
+        self.event(selector, "slider", WidgetEvent::SliderChanged(value))?;
+        self.event(selector, "slider", WidgetEvent::SliderReleased(value))
+    }
+
+    /// Switch a `toggler` on or off
+    pub fn toggle(&mut self, selector: &str, toggled: bool) -> Result<&mut Self, Error> {
+        self.set(selector, "toggler", AttributeValue::Toggled(toggled))?;
+        self.event(selector, "toggler", WidgetEvent::Toggler(toggled))
+    }
+
+    /// Select a string option of a `pick-list`
+    pub fn select(&mut self, selector: &str, option: &str) -> Result<&mut Self, Error> {
+        self.select_value(selector, Value::new_string(option.to_string()))
+    }
+
+    /// Select the option of a `pick-list` with a value, such as a number or the value of a labelled option
+    pub fn select_value(&mut self, selector: &str, value: Value) -> Result<&mut Self, Error> {
+        self.set(
+            selector,
+            "pick-list",
+            AttributeValue::Selected(pick_list::value_text(&value)),
+        )?;
+        self.event(selector, "pick-list", WidgetEvent::PickListSelected(value))
+    }
+
+    /// Replace the text of a `text-input` or `password-input`
+    pub fn input(&mut self, selector: &str, text: &str) -> Result<&mut Self, Error> {
+        self.set(selector, "", AttributeValue::InputValue(text.to_string()))?;
+        self.event(selector, "", WidgetEvent::InputChanged(text.to_string()))
+    }
+
+    /// Press enter in a `text-input` or `password-input`, submitting its text
+    pub fn submit(&mut self, selector: &str) -> Result<&mut Self, Error> {
+        let text = match self.get(selector, "", AttributeKind::InputValue)? {
+            Some(AttributeValue::InputValue(text)) => text,
+            _ => String::new(),
+        };
+        self.event(selector, "", WidgetEvent::InputSubmitted(text))
+    }
+
+    /// Enter a value into a `number-input`
+    pub fn set_number(&mut self, selector: &str, value: f64) -> Result<&mut Self, Error> {
+        self.set(
+            selector,
+            "number-input",
+            AttributeValue::InputValue(value.to_string()),
+        )?;
+        self.event(selector, "number-input", WidgetEvent::NumberChanged(value))
+    }
+
+    /// Send an event from the widget with an element id, and drive the resulting tasks.
+    /// The widget must be of the named kind, or any kind if `widget` is empty.
+    pub fn event(
+        &mut self,
+        selector: &str,
+        widget: &str,
+        event: WidgetEvent,
+    ) -> Result<&mut Self, Error> {
+        let (node_id, element_id, stable_id) = self.find(selector, widget)?;
+
+        let message =
+            WidgetMessage::new(node_id, Some(element_id), event).with_stable_id(Some(stable_id));
+        self.headless.send(Message::broadcast(message));
+
+        Ok(self)
+    }
+
+    /// Get an attribute of the widget with an element id
+    fn get(
+        &self,
+        selector: &str,
+        widget: &str,
+        kind: AttributeKind,
+    ) -> Result<Option<AttributeValue>, Error> {
+        let (node_id, _, _) = self.find(selector, widget)?;
+
+        let mut guard = self.engine().tree.lock();
+        let node = guard
+            .as_mut()
+            .and_then(|tree| tree.get_node_mut(&node_id))
+            .ok_or(Error::NodeNotFound(node_id))?;
+        Ok(node.node().data().attrs.get(kind)?)
+    }
+
+    /// Set an attribute of the widget with an element id
+    fn set(&mut self, selector: &str, widget: &str, value: AttributeValue) -> Result<(), Error> {
+        let (node_id, _, _) = self.find(selector, widget)?;
+
+        let mut guard = self.engine().tree.lock();
+        let node = guard
+            .as_mut()
+            .and_then(|tree| tree.get_node_mut(&node_id))
+            .ok_or(Error::NodeNotFound(node_id))?;
+        node.node().data().attrs.set(value)?;
+        Ok(())
+    }
+
+    /// Find the node of a widget by its element id, with or without a leading `#`
+    fn find(
+        &self,
+        selector: &str,
+        widget: &str,
+    ) -> Result<(crate::NodeId, String, StableId), Error> {
+        let element_id = selector.trim_start_matches('#').to_string();
+        let stable_id = StableId::element(element_id.clone());
+
+        let node_id = self
+            .engine()
+            .resolve(&stable_id)
+            .ok_or_else(|| Error::ElementNotFound(element_id.clone()))?;
+
+        let content = self
+            .engine()
+            .tree
+            .lock()
+            .as_mut()
+            .and_then(|tree| tree.get_node_mut(&node_id))
+            .map(|node| node.node().data().content().clone());
+
+        match content {
+            Some(Content::Widget(name)) if widget.is_empty() || name == widget => {
+                Ok((node_id, element_id, stable_id))
+            }
+            Some(_) if widget.is_empty() => Ok((node_id, element_id, stable_id)),
+            Some(_) => Err(Error::ElementKind(element_id, widget.to_string())),
+            None => Err(Error::NodeNotFound(node_id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use iced::Task;
+    use parking_lot::Mutex;
+    use tracing_test::traced_test;
+
+    use super::Harness;
+    use crate::Error;
+
+    #[traced_test]
+    #[test]
+    fn harness_events() {
+        let mut harness = Harness::new(
+            r#"{|[button#save(text("Save")), slider#vol(), toggler#mute<label:"Mute">()]}"#,
+        )
+        .unwrap();
+
+        harness
+            .click("#save")
+            .unwrap()
+            .set_slider("#vol", 50)
+            .unwrap()
+            .toggle("mute", true)
+            .unwrap();
+
+        let tree = harness.describe().unwrap();
+        assert!(tree.find("vol").unwrap().attrs.contains("SliderValue(50)"));
+        assert!(tree.find("mute").unwrap().attrs.contains("Toggled(true)"));
+
+        // Unknown elements, and widgets of the wrong kind are errors
+        assert!(matches!(
+            harness.click("#missing"),
+            Err(Error::ElementNotFound(_))
+        ));
+        assert!(matches!(
+            harness.click("#vol"),
+            Err(Error::ElementKind(_, _))
+        ));
+    }
+
+    #[traced_test]
+    #[test]
+    fn submit_publishes() {
+        let mut harness =
+            Harness::new(r#"{text-input#name<on-submit:publish("name")>()}"#).unwrap();
+
+        let submitted = Arc::new(Mutex::new(Vec::new()));
+        let handler_submitted = submitted.clone();
+        let _name = harness
+            .headless()
+            .engine()
+            .on_topic("name", move |message| {
+                handler_submitted
+                    .lock()
+                    .push(message.as_str().map(String::from));
+                Task::none()
+            });
+
+        // Typing doesn't publish, pressing enter publishes the text
+        harness.input("#name", "ferris").unwrap();
+        assert!(submitted.lock().is_empty());
+        harness.submit("#name").unwrap();
+        assert_eq!(*submitted.lock(), vec![Some("ferris".to_string())]);
+    }
+}
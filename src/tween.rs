@@ -0,0 +1,225 @@
+//! Tweening of attribute values with transitions
+//!
+//! When a node with a `transition` attribute is rebuilt with a new value of an animated attribute, the value
+//! is tweened from its current value to the new one over the duration of the transition. While tweens are
+//! running, [`crate::Snowcap::subscription()`] requests animation frames, and each frame marks the tweening
+//! nodes dirty so they are rebuilt with the interpolated values.
+//!
+//! Target values are tracked by [`StableId`], so values changed by a hot reload are also tweened.
+
+use std::{collections::HashMap, time::Instant};
+
+use tracing::debug;
+
+use crate::{
+    attribute::{
+        transition::{interpolate, Transition},
+        AttributeKind, AttributeValue, Attributes,
+    },
+    identity::IdentityIndex,
+    NodeId, StableId, SyncError,
+};
+
+/// A running tween of an attribute value
+#[derive(Debug)]
+struct Tween {
+    /// Node currently built with the tweened value
+    node_id: NodeId,
+    from: AttributeValue,
+    to: AttributeValue,
+    start: Instant,
+    transition: Transition,
+}
+
+impl Tween {
+    /// Linear progress of the tween, in the range 0..=1
+    fn progress(&self, now: Instant) -> f32 {
+        let duration = self.transition.duration.as_secs_f32();
+        if duration <= 0.0 {
+            return 1.0;
+        }
+        (now.saturating_duration_since(self.start).as_secs_f32() / duration).min(1.0)
+    }
+
+    fn finished(&self, now: Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// Interpolated value at an instant
+    fn value(&self, now: Instant) -> AttributeValue {
+        let t = self.transition.easing.apply(self.progress(now));
+        interpolate(&self.from, &self.to, t).unwrap_or_else(|| self.to.clone())
+    }
+}
+
+/// Target values and running tweens of nodes with transitions
+#[derive(Debug, Default)]
+pub(crate) struct Tweens {
+    /// Values of animated attributes from the last build of each node
+    targets: HashMap<StableId, HashMap<AttributeKind, AttributeValue>>,
+
+    /// Running tweens of each node and attribute
+    active: HashMap<(StableId, AttributeKind), Tween>,
+}
+
+impl Tweens {
+    /// Get the attributes to build a node with. Animated attributes whose value changed since the last build
+    /// start a tween, and running tweens replace the value with the interpolated value at `now`.
+    ///
+    /// The `transition` attribute is removed, so widgets don't need to handle it.
+    pub fn apply(
+        &mut self,
+        node_id: NodeId,
+        stable_id: Option<&StableId>,
+        attrs: &Attributes,
+        now: Instant,
+    ) -> Result<Attributes, SyncError> {
+        let Some(AttributeValue::Transition(transitions)) = attrs.get(AttributeKind::Transition)?
+        else {
+            return Ok(attrs.clone());
+        };
+
+        let mut resolved = Attributes::new();
+        let mut targets = stable_id.map(|id| self.targets.entry(id.clone()).or_default());
+
+        for mut attr in attrs {
+            let kind = attr.kind();
+            if kind == AttributeKind::Transition {
+                continue;
+            }
+
+            let transition = transitions
+                .iter()
+                .find(|transition| transition.animates(kind));
+
+            if let (Some(transition), Some(stable_id), Some(targets), Some(value)) =
+                (transition, stable_id, targets.as_mut(), attr.value_mut())
+            {
+                let key = (stable_id.clone(), kind);
+                let previous = targets.insert(kind, value.clone());
+
+                if let Some(previous) = previous.filter(|previous| previous != &*value) {
+                    // Start from the current value of a running tween, so interrupted transitions don't jump
+                    let from = match self.active.get(&key) {
+                        Some(tween) => tween.value(now),
+                        None => previous,
+                    };
+
+                    debug!("Node {node_id} tweening {kind:?} from {from:?} to {value:?}");
+                    self.active.insert(
+                        key.clone(),
+                        Tween {
+                            node_id,
+                            from,
+                            to: value.clone(),
+                            start: now,
+                            transition: *transition,
+                        },
+                    );
+                }
+
+                match self.active.get_mut(&key) {
+                    Some(tween) if tween.finished(now) => {
+                        self.active.remove(&key);
+                    }
+                    Some(tween) => {
+                        tween.node_id = node_id;
+                        *value = tween.value(now);
+                    }
+                    None => {}
+                }
+            }
+
+            resolved.push(attr)?;
+        }
+
+        Ok(resolved)
+    }
+
+    /// Returns true if any tweens are running
+    pub fn is_active(&self) -> bool {
+        !self.active.is_empty()
+    }
+
+    /// Nodes with running tweens, which are rebuilt on each animation frame
+    pub fn nodes(&self) -> Vec<NodeId> {
+        self.active.values().map(|tween| tween.node_id).collect()
+    }
+
+    /// Discard the values of nodes no longer in the tree after a reload
+    pub fn prune(&mut self, identities: &IdentityIndex) {
+        self.targets
+            .retain(|stable_id, _| identities.node_id(stable_id).is_some());
+        self.active
+            .retain(|(stable_id, _), _| identities.node_id(stable_id).is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use arbutus::{TreeNode as _, TreeNodeRef as _};
+    use tracing_test::traced_test;
+
+    use super::Tweens;
+    use crate::{
+        attribute::{AttributeKind, AttributeValue},
+        parser::attribute::AttributeParser,
+        Message, SnowcapParser, StableId,
+    };
+
+    #[traced_test]
+    #[test]
+    fn tween_opacity() {
+        let mut tweens = Tweens::default();
+        let tree = SnowcapParser::<Message>::parse_memory(r#"{text#fade("Hello")}"#)
+            .unwrap()
+            .index();
+        let node_id = tree.root().node().id();
+        let stable_id = StableId::element("fade");
+        let start = Instant::now();
+
+        let attrs =
+            AttributeParser::parse_attributes("opacity:0, transition:opacity(100ms)").unwrap();
+        let built = tweens
+            .apply(node_id, Some(&stable_id), &attrs, start)
+            .unwrap();
+        assert!(built.get(AttributeKind::Transition).unwrap().is_none());
+        assert!(!tweens.is_active());
+
+        let attrs =
+            AttributeParser::parse_attributes("opacity:1, transition:opacity(100ms)").unwrap();
+        let _ = tweens
+            .apply(node_id, Some(&stable_id), &attrs, start)
+            .unwrap();
+        assert_eq!(tweens.nodes(), vec![node_id]);
+
+        let built = tweens
+            .apply(
+                node_id,
+                Some(&stable_id),
+                &attrs,
+                start + Duration::from_millis(50),
+            )
+            .unwrap();
+        assert_eq!(
+            built.get(AttributeKind::Opacity).unwrap(),
+            Some(AttributeValue::Opacity(0.5))
+        );
+
+        let built = tweens
+            .apply(
+                node_id,
+                Some(&stable_id),
+                &attrs,
+                start + Duration::from_millis(100),
+            )
+            .unwrap();
+        assert_eq!(
+            built.get(AttributeKind::Opacity).unwrap(),
+            Some(AttributeValue::Opacity(1.0))
+        );
+        assert!(!tweens.is_active());
+    }
+}
@@ -1,4 +1,10 @@
+use std::{
+    mem::ManuallyDrop,
+    thread::{self, ThreadId},
+};
+
 use iced::{advanced::Widget, Element};
+use tracing::warn;
 
 /// Wrap an Element with a Widget impl, so it can be used as a dyn Widget.
 /// This is used by the Markdown widget, as the iced API only exposes an Element
@@ -117,3 +123,99 @@ impl<M> Widget<M, iced::Theme, iced::Renderer> for ElementWrapper<M> {
             .overlay(tree, layout, renderer, translation)
     }
 }
+
+/// Holds a value which isn't [`Send`], such as an iced widget, so it can be stored in a [`Send`] container.
+/// The value can only be accessed from the thread which created it, and accessing it from another thread panics.
+/// If dropped on another thread, the value is leaked instead of running its destructor on the wrong thread.
+pub struct ThreadBound<T> {
+    thread: ThreadId,
+    value: ManuallyDrop<T>,
+}
+
+// SAFETY: the value is only accessed, and dropped, on the thread which created it
+unsafe impl<T> Send for ThreadBound<T> {}
+
+impl<T> ThreadBound<T> {
+    /// Bind a value to the current thread
+    pub fn new(value: T) -> Self {
+        Self {
+            thread: thread::current().id(),
+            value: ManuallyDrop::new(value),
+        }
+    }
+
+    /// Returns true if called on the thread which created the value
+    pub fn is_owner(&self) -> bool {
+        self.thread == thread::current().id()
+    }
+
+    /// Get a reference to the value. Panics if called from another thread.
+    pub fn get(&self) -> &T {
+        if !self.is_owner() {
+            panic!(
+                "Value bound to thread {:?} accessed from thread {:?}",
+                self.thread,
+                thread::current().id()
+            );
+        }
+        &self.value
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for ThreadBound<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_owner() {
+            self.value.fmt(f)
+        } else {
+            write!(f, "ThreadBound({:?})", self.thread)
+        }
+    }
+}
+
+impl<T> Drop for ThreadBound<T> {
+    fn drop(&mut self) {
+        if self.is_owner() {
+            // SAFETY: the value is dropped once, and never accessed again
+            unsafe { ManuallyDrop::drop(&mut self.value) }
+        } else {
+            warn!(
+                "Leaking value bound to thread {:?} dropped on thread {:?}",
+                self.thread,
+                thread::current().id()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, thread};
+
+    use tracing_test::traced_test;
+
+    use super::ThreadBound;
+
+    #[traced_test]
+    #[test]
+    fn thread_bound() {
+        let rc = Rc::new(1);
+        let bound = ThreadBound::new(rc.clone());
+        assert_eq!(**bound.get(), 1);
+        assert_eq!(Rc::strong_count(&rc), 2);
+
+        // Accessing from another thread panics, and dropping on another thread leaks the value
+        let result = thread::spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| **bound.get()));
+            drop(bound);
+            result.is_err()
+        })
+        .join()
+        .unwrap();
+        assert!(result);
+        assert_eq!(Rc::strong_count(&rc), 2);
+
+        // Dropping on the owning thread drops the value
+        drop(ThreadBound::new(rc.clone()));
+        assert_eq!(Rc::strong_count(&rc), 2);
+    }
+}
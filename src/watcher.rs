@@ -35,7 +35,8 @@ pub enum WatchCommand {}
 
 #[derive(Debug, Clone)]
 pub enum WatchEvent {
-    Test,
+    /// Data of the watched files was modified
+    Modified(Vec<PathBuf>),
     Error(Arc<Box<dyn std::error::Error + Send + Sync>>),
 }
 
@@ -80,7 +81,7 @@ impl FileWatcher {
                     InternalMessage::Event(event) => match event.kind {
                         notify::EventKind::Modify(notify::event::ModifyKind::Data(change)) => {
                             info!("Data Modified: '{:?}' paths: {:?}", change, event.paths);
-                            WatchMessage::Event(WatchEvent::Test)
+                            WatchMessage::Event(WatchEvent::Modified(event.paths))
                         }
 
                         _ => {